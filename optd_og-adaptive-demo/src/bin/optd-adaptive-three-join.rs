@@ -32,10 +32,9 @@ async fn main() -> Result<()> {
             .with_config(session_config.clone())
             .with_runtime_env(Arc::new(rn_config));
         let catalog = Arc::new(MemoryCatalogProviderList::new());
-        let mut optimizer: DatafusionOptimizer = DatafusionOptimizer::new_physical(
-            Arc::new(DatafusionCatalog::new(catalog.clone())),
-            true,
-        );
+        let optd_og_catalog = Arc::new(DatafusionCatalog::new(catalog.clone()));
+        let mut optimizer: DatafusionOptimizer =
+            DatafusionOptimizer::new_physical(optd_og_catalog.clone(), true);
         state = state.with_catalog_list(catalog);
         // clean up optimizer rules so that we can plug in our own optimizer
         state = state.with_optimizer_rules(vec![]);
@@ -44,7 +43,8 @@ async fn main() -> Result<()> {
         optimizer.optd_og_optimizer_mut().prop.partial_explore_iter = None;
         optimizer.optd_og_optimizer_mut().prop.partial_explore_space = None;
         // use optd_og-bridge query planner
-        state = state.with_query_planner(Arc::new(OptdQueryPlanner::new(optimizer)));
+        state =
+            state.with_query_planner(Arc::new(OptdQueryPlanner::new(optimizer, optd_og_catalog)));
         SessionContext::new_with_state(state.build())
     };
     ctx.refresh_catalogs().await?;
@@ -57,10 +57,9 @@ async fn main() -> Result<()> {
             .with_config(session_config.clone())
             .with_runtime_env(Arc::new(rn_config));
         let catalog = Arc::new(MemoryCatalogProviderList::new());
-        let mut optimizer: DatafusionOptimizer = DatafusionOptimizer::new_physical(
-            Arc::new(DatafusionCatalog::new(catalog.clone())),
-            true,
-        );
+        let optd_og_catalog = Arc::new(DatafusionCatalog::new(catalog.clone()));
+        let mut optimizer: DatafusionOptimizer =
+            DatafusionOptimizer::new_physical(optd_og_catalog.clone(), true);
         state = state.with_catalog_list(catalog);
         // clean up optimizer rules so that we can plug in our own optimizer
         state = state.with_optimizer_rules(vec![]);
@@ -68,7 +67,7 @@ async fn main() -> Result<()> {
         // Disable limit
         optimizer.optd_og_optimizer_mut().prop.partial_explore_iter = None;
         optimizer.optd_og_optimizer_mut().prop.partial_explore_space = None;
-        perfect_optimizer = Arc::new(OptdQueryPlanner::new(optimizer));
+        perfect_optimizer = Arc::new(OptdQueryPlanner::new(optimizer, optd_og_catalog));
         // use optd_og-bridge query planner
         state = state.with_query_planner(perfect_optimizer.clone());
         SessionContext::new_with_state(state.build())
@@ -35,16 +35,16 @@ async fn main() -> Result<()> {
             .with_config(session_config.clone())
             .with_runtime_env(Arc::new(runtime_env));
         let catalog = Arc::new(MemoryCatalogProviderList::new());
-        let optimizer: DatafusionOptimizer = DatafusionOptimizer::new_physical(
-            Arc::new(DatafusionCatalog::new(catalog.clone())),
-            true,
-        );
+        let optd_og_catalog = Arc::new(DatafusionCatalog::new(catalog.clone()));
+        let optimizer: DatafusionOptimizer =
+            DatafusionOptimizer::new_physical(optd_og_catalog.clone(), true);
         state = state.with_catalog_list(catalog);
         // clean up optimizer rules so that we can plug in our own optimizer
         state = state.with_optimizer_rules(vec![]);
         state = state.with_physical_optimizer_rules(vec![]);
         // use optd_og-bridge query planner
-        state = state.with_query_planner(Arc::new(OptdQueryPlanner::new(optimizer)));
+        state =
+            state.with_query_planner(Arc::new(OptdQueryPlanner::new(optimizer, optd_og_catalog)));
         SessionContext::new_with_state(state.build())
     };
     ctx.refresh_catalogs().await?;
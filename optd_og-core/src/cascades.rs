@@ -5,10 +5,34 @@
 
 //! The core cascades optimizer implementation.
 
+mod checkpoint;
+mod cost_breakdown;
+mod cost_cache;
+mod eviction;
+mod exploration;
+mod handle;
+mod incremental;
 mod memo;
 mod optimizer;
+mod progress;
+mod replay;
 pub mod rule_match;
+mod snapshot;
 mod tasks2;
 
+pub use checkpoint::{CheckpointExpr, CheckpointGroup, CheckpointHook, MemoCheckpoint};
+pub use cost_breakdown::CostBreakdown;
+pub use eviction::{
+    CostBasedRetention, EvictionCandidate, EvictionPolicy, KeepWinnersOnly, LruEviction,
+};
+pub use exploration::ExplorationStrategy;
+pub use handle::GroupHandle;
+pub use incremental::{IncrementalOptimization, PlanImprovement};
 pub use memo::{Memo, NaiveMemo};
-pub use optimizer::{CascadesOptimizer, ExprId, GroupId, OptimizerProperties, RelNodeContext};
+pub use optimizer::{
+    CascadesOptimizer, CascadesStats, ExprId, GroupId, MemoDumpOptions, OptimizerProperties,
+    OptimizerTrace, RelNodeContext,
+};
+pub use progress::{CancellationToken, OptimizationProgress, ProgressHook};
+pub use replay::{ReplayState, TraceReplay};
+pub use snapshot::{MemoDiff, MemoSnapshot, SnapshotGroup, SnapshotWinner, WinnerChange};
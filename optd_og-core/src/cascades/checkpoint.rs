@@ -0,0 +1,184 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Checkpoints of the memo table, so that a long exploration can be resumed after a crash
+//! instead of starting over.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{GroupId, Memo};
+use crate::nodes::{ArcPredNode, NodeType, PlanNode, PlanNodeOrGroup};
+
+/// All expressions of the memo table. Winners, explored groups and fired rules are not part of
+/// the checkpoint: the optimizer re-derives them when the search is resumed, which is cheap
+/// compared to exploring the plan space again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T: Serialize, T::PredType: Serialize",
+    deserialize = "T: Deserialize<'de>, T::PredType: Deserialize<'de>"
+))]
+pub struct MemoCheckpoint<T: NodeType> {
+    /// The root group of the query being optimized.
+    pub root: GroupId,
+    pub groups: Vec<CheckpointGroup<T>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T: Serialize, T::PredType: Serialize",
+    deserialize = "T: Deserialize<'de>, T::PredType: Deserialize<'de>"
+))]
+pub struct CheckpointGroup<T: NodeType> {
+    pub group_id: GroupId,
+    pub exprs: Vec<CheckpointExpr<T>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T: Serialize, T::PredType: Serialize",
+    deserialize = "T: Deserialize<'de>, T::PredType: Deserialize<'de>"
+))]
+pub struct CheckpointExpr<T: NodeType> {
+    pub typ: T,
+    pub children: Vec<GroupId>,
+    pub predicates: Vec<ArcPredNode<T>>,
+}
+
+/// Calls `callback` with a checkpoint of the memo table every `interval` optimizer tasks.
+pub struct CheckpointHook<T: NodeType> {
+    pub interval: usize,
+    pub callback: Box<dyn FnMut(MemoCheckpoint<T>) + Send + Sync>,
+}
+
+impl<T: NodeType> MemoCheckpoint<T> {
+    pub fn from_memo(memo: &impl Memo<T>, root: GroupId) -> Self {
+        let groups = memo
+            .get_all_group_ids()
+            .into_iter()
+            .map(|group_id| CheckpointGroup {
+                group_id,
+                exprs: memo
+                    .get_all_exprs_in_group(group_id)
+                    .into_iter()
+                    .map(|expr_id| {
+                        let expr = memo.get_expr_memoed(expr_id);
+                        CheckpointExpr {
+                            typ: expr.typ.clone(),
+                            children: expr.children.clone(),
+                            predicates: expr
+                                .predicates
+                                .iter()
+                                .map(|pred_id| memo.get_pred(*pred_id))
+                                .collect(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self {
+            root: memo.reduce_group(root),
+            groups,
+        }
+    }
+
+    /// Add the expressions of the checkpoint to an empty memo table and return the new id of
+    /// the root group. The group ids are not preserved.
+    pub fn restore(self, memo: &mut impl Memo<T>) -> Result<GroupId> {
+        let mut pending = self
+            .groups
+            .into_iter()
+            .flat_map(|group| {
+                let group_id = group.group_id;
+                group.exprs.into_iter().map(move |expr| (group_id, expr))
+            })
+            .collect::<Vec<_>>();
+        let mut group_mapping: HashMap<GroupId, GroupId> = HashMap::new();
+        // A group can only be created once all of its children are, so restore the groups
+        // bottom-up.
+        loop {
+            let (ready, rest): (Vec<_>, Vec<_>) = pending.into_iter().partition(|(_, expr)| {
+                expr.children
+                    .iter()
+                    .all(|child| group_mapping.contains_key(child))
+            });
+            pending = rest;
+            if ready.is_empty() {
+                break;
+            }
+            for (group_id, expr) in ready {
+                let node = Arc::new(PlanNode {
+                    typ: expr.typ,
+                    children: expr
+                        .children
+                        .iter()
+                        .map(|child| {
+                            PlanNodeOrGroup::Group(memo.reduce_group(group_mapping[child]))
+                        })
+                        .collect(),
+                    predicates: expr.predicates,
+                });
+                match group_mapping.get(&group_id) {
+                    Some(&new_group_id) => {
+                        memo.add_expr_to_group(node.into(), memo.reduce_group(new_group_id));
+                    }
+                    None => {
+                        let (new_group_id, _) = memo.add_new_expr(node);
+                        group_mapping.insert(group_id, new_group_id);
+                    }
+                }
+            }
+        }
+        if !pending.is_empty() {
+            tracing::warn!(
+                "{} expressions in the checkpoint cannot be restored",
+                pending.len()
+            );
+        }
+        let Some(&root) = group_mapping.get(&self.root) else {
+            bail!("root group {} is not in the checkpoint", self.root);
+        };
+        Ok(memo.reduce_group(root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cascades::NaiveMemo;
+    use crate::nodes::Value;
+    use crate::tests::common::{expr, join, list, project, scan};
+
+    #[test]
+    fn restore_checkpoint() {
+        let mut memo = NaiveMemo::new(Arc::new([]));
+        let (root, _) = memo.add_new_expr(project(
+            join(scan("t1"), scan("t2"), expr(Value::Bool(true))),
+            list(vec![expr(Value::Int64(1))]),
+        ));
+        let (join_group, _) =
+            memo.add_new_expr(join(scan("t1"), scan("t2"), expr(Value::Bool(true))));
+        memo.add_expr_to_group(
+            join(scan("t2"), scan("t1"), expr(Value::Bool(true))).into(),
+            join_group,
+        );
+        let checkpoint = MemoCheckpoint::from_memo(&memo, root);
+
+        let mut restored = NaiveMemo::new(Arc::new([]));
+        let restored_root = checkpoint.restore(&mut restored).unwrap();
+        assert_eq!(
+            restored.get_all_group_ids().len(),
+            memo.get_all_group_ids().len()
+        );
+        assert_eq!(restored.get_all_exprs_in_group(restored_root).len(), 1);
+        let restored_join = restored
+            .get_expr_memoed(restored.get_all_exprs_in_group(restored_root)[0])
+            .children[0];
+        assert_eq!(restored.get_all_exprs_in_group(restored_join).len(), 2);
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! The costs of the winning plan of a group, operator by operator, to find out why the plan was
+//! chosen, e.g., which operator dominates its cost.
+
+use std::fmt::Display;
+
+use anyhow::{bail, Context, Result};
+
+use super::memo::Winner;
+use super::{ExprId, GroupId, Memo};
+use crate::cost::{Cost, CostModel};
+use crate::nodes::NodeType;
+
+/// An operator of the winning plan of a group, with the operators of its inputs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CostBreakdown {
+    pub group_id: GroupId,
+    pub expr_id: ExprId,
+    /// The type of the operator, e.g., `PhysicalHashJoin(Inner)`.
+    pub operator: String,
+    /// The cost of the operator alone.
+    pub operation_cost: Cost,
+    pub operation_weighted_cost: f64,
+    /// The cost of the operator and its inputs.
+    pub total_cost: Cost,
+    pub total_weighted_cost: f64,
+    /// `operation_cost` as explained by the cost model, e.g., `{compute=10,io=0}`.
+    pub operation_cost_display: String,
+    /// `total_cost` as explained by the cost model.
+    pub total_cost_display: String,
+    /// The number of rows of the operator, if the cost model estimates it.
+    pub row_cnt: Option<f64>,
+    /// The statistics of the operator as explained by the cost model, e.g., `{row_cnt=1000}`.
+    pub stat_display: String,
+    pub children: Vec<CostBreakdown>,
+}
+
+impl CostBreakdown {
+    /// The breakdown of the winner of `group_id`. Fails if the group or one of the groups below
+    /// it has no winner.
+    pub fn from_winner<T: NodeType, M: Memo<T>>(
+        memo: &M,
+        cost_model: &(impl CostModel<T, M> + ?Sized),
+        group_id: GroupId,
+    ) -> Result<Self> {
+        let Winner::Full(winner) = memo.get_group_winner(group_id) else {
+            bail!("no winner for group {}", group_id);
+        };
+        let expr = memo.get_expr_memoed(winner.expr_id);
+        let children = expr
+            .children
+            .iter()
+            .map(|child| {
+                Self::from_winner(memo, cost_model, *child)
+                    .with_context(|| format!("when processing expr {}", winner.expr_id))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            group_id,
+            expr_id: winner.expr_id,
+            operator: expr.typ.to_string(),
+            operation_cost: winner.operation_cost.clone(),
+            operation_weighted_cost: winner.operation_weighted_cost,
+            total_cost: winner.total_cost.clone(),
+            total_weighted_cost: winner.total_weighted_cost,
+            operation_cost_display: cost_model.explain_cost(&winner.operation_cost),
+            total_cost_display: cost_model.explain_cost(&winner.total_cost),
+            row_cnt: cost_model.estimated_row_cnt(&winner.statistics),
+            stat_display: cost_model.explain_statistics(&winner.statistics),
+            children,
+        })
+    }
+
+    /// The operators of the plan, depth first, with their depth.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &CostBreakdown)> {
+        let mut stack = vec![(0, self)];
+        std::iter::from_fn(move || {
+            let (depth, node) = stack.pop()?;
+            stack.extend(node.children.iter().rev().map(|child| (depth + 1, child)));
+            Some((depth, node))
+        })
+    }
+}
+
+impl Display for CostBreakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (depth, node) in self.iter() {
+            writeln!(
+                f,
+                "{}{} group_id={} operation_cost={} total_cost={} stat={}",
+                "  ".repeat(depth),
+                node.operator,
+                node.group_id,
+                node.operation_cost_display,
+                node.total_cost_display,
+                node.stat_display
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::ExprId;
+use crate::cost::{Cost, Statistics};
+
+/// The operation cost and the statistics computed for a physical expression, which only depend
+/// on the expression and on the statistics of its inputs.
+struct CostCacheEntry {
+    /// The statistics of the winners of the children the entry was computed with. They are
+    /// compared by pointer, an entry is stale once a child gets a new winner; holding them keeps
+    /// the pointers from being reused.
+    input_stats: Vec<Option<Arc<Statistics>>>,
+    operation_cost: Option<Cost>,
+    statistics: Option<Arc<Statistics>>,
+}
+
+impl CostCacheEntry {
+    fn new(input_stats: &[Option<Arc<Statistics>>]) -> Self {
+        Self {
+            input_stats: input_stats.to_vec(),
+            operation_cost: None,
+            statistics: None,
+        }
+    }
+
+    fn matches(&self, input_stats: &[Option<Arc<Statistics>>]) -> bool {
+        self.input_stats.len() == input_stats.len()
+            && self
+                .input_stats
+                .iter()
+                .zip(input_stats)
+                .all(|(cached, stats)| match (cached, stats) {
+                    (Some(cached), Some(stats)) => Arc::ptr_eq(cached, stats),
+                    (None, None) => true,
+                    _ => false,
+                })
+    }
+}
+
+/// Caches the operation costs and statistics of the physical expressions, so that they are not
+/// computed again each time an expression is optimized, e.g., when its group is optimized with
+/// another upper bound or in a later stage. The cascades tasks do not require physical
+/// properties yet, an expression is costed the same in all contexts.
+#[derive(Default)]
+pub(super) struct CostCache {
+    entries: HashMap<ExprId, CostCacheEntry>,
+}
+
+impl CostCache {
+    pub(super) fn get_operation_cost(
+        &self,
+        expr_id: ExprId,
+        input_stats: &[Option<Arc<Statistics>>],
+    ) -> Option<Cost> {
+        self.entries
+            .get(&expr_id)
+            .filter(|entry| entry.matches(input_stats))
+            .and_then(|entry| entry.operation_cost.clone())
+    }
+
+    pub(super) fn get_statistics(
+        &self,
+        expr_id: ExprId,
+        input_stats: &[Option<Arc<Statistics>>],
+    ) -> Option<Arc<Statistics>> {
+        self.entries
+            .get(&expr_id)
+            .filter(|entry| entry.matches(input_stats))
+            .and_then(|entry| entry.statistics.clone())
+    }
+
+    fn entry_mut(
+        &mut self,
+        expr_id: ExprId,
+        input_stats: &[Option<Arc<Statistics>>],
+    ) -> &mut CostCacheEntry {
+        let entry = self
+            .entries
+            .entry(expr_id)
+            .or_insert_with(|| CostCacheEntry::new(input_stats));
+        if !entry.matches(input_stats) {
+            *entry = CostCacheEntry::new(input_stats);
+        }
+        entry
+    }
+
+    pub(super) fn insert_operation_cost(
+        &mut self,
+        expr_id: ExprId,
+        input_stats: &[Option<Arc<Statistics>>],
+        operation_cost: Cost,
+    ) {
+        self.entry_mut(expr_id, input_stats).operation_cost = Some(operation_cost);
+    }
+
+    pub(super) fn insert_statistics(
+        &mut self,
+        expr_id: ExprId,
+        input_stats: &[Option<Arc<Statistics>>],
+        statistics: Arc<Statistics>,
+    ) {
+        self.entry_mut(expr_id, input_stats).statistics = Some(statistics);
+    }
+
+    pub(super) fn remove(&mut self, expr_id: ExprId) {
+        self.entries.remove(&expr_id);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> Arc<Statistics> {
+        Arc::new(Statistics(Box::new(())))
+    }
+
+    #[test]
+    fn invalidate_on_new_input_stats() {
+        let mut cache = CostCache::default();
+        let input_stats = vec![Some(stats()), None];
+        cache.insert_operation_cost(ExprId(1), &input_stats, Cost(vec![1.0]));
+        cache.insert_statistics(ExprId(1), &input_stats, stats());
+        assert_eq!(
+            cache.get_operation_cost(ExprId(1), &input_stats),
+            Some(Cost(vec![1.0]))
+        );
+        assert!(cache.get_statistics(ExprId(1), &input_stats).is_some());
+
+        // equal statistics of a new winner are not the same
+        let new_input_stats = vec![Some(stats()), None];
+        assert_eq!(cache.get_operation_cost(ExprId(1), &new_input_stats), None);
+        cache.insert_operation_cost(ExprId(1), &new_input_stats, Cost(vec![2.0]));
+        assert!(cache.get_statistics(ExprId(1), &new_input_stats).is_none());
+        assert_eq!(cache.get_operation_cost(ExprId(1), &input_stats), None);
+    }
+}
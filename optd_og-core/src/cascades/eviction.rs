@@ -0,0 +1,132 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Eviction policies, which bound the memory of a memo table kept across optimizations (e.g., in
+//! adaptive mode) by evicting the expressions least worth keeping, see
+//! [`super::CascadesOptimizer::set_eviction_policy`].
+
+use itertools::Itertools;
+
+use super::{ExprId, GroupId};
+use crate::cost::CostComparator;
+
+/// An expression of the memo table which can be evicted. The winners of the groups, and the last
+/// expression of a group, are never candidates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvictionCandidate {
+    pub expr_id: ExprId,
+    pub group_id: GroupId,
+    /// When the group was last accessed, i.e., got a new expression or winner, as a logical
+    /// clock of the memo table.
+    pub last_access: u64,
+    /// The weighted cost of the winner of the group, if it has one.
+    pub winner_cost: Option<f64>,
+}
+
+pub trait EvictionPolicy: 'static + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Chooses the candidates to evict. `target` is how many expressions the memo table holds
+    /// above its capacity; a policy may choose fewer or more of them.
+    fn select(&self, candidates: &[EvictionCandidate], target: usize) -> Vec<ExprId>;
+}
+
+/// Evicts the expressions of the groups accessed least recently first.
+pub struct LruEviction;
+
+impl EvictionPolicy for LruEviction {
+    fn name(&self) -> &'static str {
+        "lru"
+    }
+
+    fn select(&self, candidates: &[EvictionCandidate], target: usize) -> Vec<ExprId> {
+        candidates
+            .iter()
+            .sorted_by_key(|candidate| (candidate.last_access, candidate.expr_id))
+            .take(target)
+            .map(|candidate| candidate.expr_id)
+            .collect()
+    }
+}
+
+/// Evicts all the expressions but the winners once the memo table is above its capacity, which
+/// keeps the plans chosen so far and drops the rest of the exploration.
+pub struct KeepWinnersOnly;
+
+impl EvictionPolicy for KeepWinnersOnly {
+    fn name(&self) -> &'static str {
+        "keep_winners_only"
+    }
+
+    fn select(&self, candidates: &[EvictionCandidate], _target: usize) -> Vec<ExprId> {
+        candidates
+            .iter()
+            .map(|candidate| candidate.expr_id)
+            .collect()
+    }
+}
+
+/// Retains the expressions of the most expensive groups, whose exploration is the most costly
+/// to redo, and evicts the ones of the groups without a winner and of the cheapest groups first.
+#[derive(Default)]
+pub struct CostBasedRetention {
+    pub cost_comparator: CostComparator,
+}
+
+impl EvictionPolicy for CostBasedRetention {
+    fn name(&self) -> &'static str {
+        "cost_based_retention"
+    }
+
+    fn select(&self, candidates: &[EvictionCandidate], target: usize) -> Vec<ExprId> {
+        candidates
+            .iter()
+            .sorted_by(|a, b| match (a.winner_cost, b.winner_cost) {
+                (Some(a_cost), Some(b_cost)) => self
+                    .cost_comparator
+                    .compare(a_cost, b_cost)
+                    .then(a.expr_id.cmp(&b.expr_id)),
+                (a_cost, b_cost) => a_cost
+                    .is_some()
+                    .cmp(&b_cost.is_some())
+                    .then(a.expr_id.cmp(&b.expr_id)),
+            })
+            .take(target)
+            .map(|candidate| candidate.expr_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(expr_id: usize, last_access: u64, winner_cost: Option<f64>) -> EvictionCandidate {
+        EvictionCandidate {
+            expr_id: ExprId(expr_id),
+            group_id: GroupId(expr_id),
+            last_access,
+            winner_cost,
+        }
+    }
+
+    #[test]
+    fn select_evictions() {
+        let candidates = vec![
+            candidate(1, 5, Some(10.0)),
+            candidate(2, 1, None),
+            candidate(3, 3, Some(1.0)),
+        ];
+        assert_eq!(
+            LruEviction.select(&candidates, 2),
+            vec![ExprId(2), ExprId(3)]
+        );
+        assert_eq!(KeepWinnersOnly.select(&candidates, 1).len(), 3);
+        assert_eq!(
+            CostBasedRetention::default().select(&candidates, 2),
+            vec![ExprId(2), ExprId(3)]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Exploration strategies, which decide the transformation rules applied to the logical
+//! expressions of the memo table, see [`super::OptimizerProperties::exploration_strategy`].
+
+/// How the transformation rules are applied. The implementation rules are always applied, so
+/// that each group gets a physical plan.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ExplorationStrategy {
+    /// Apply all the transformation rules, until a budget is used.
+    #[default]
+    Exhaustive,
+    /// Apply each transformation rule to an expression with a probability proportional to its
+    /// promise (see [`crate::rules::Rule::promise`]), the most promising rules being applied
+    /// with probability `sample_rate`. For join graphs too large to be explored even with the
+    /// budgets, this samples the plan space and returns a good-enough plan quickly. The same
+    /// `seed` explores the same plans.
+    Randomized { seed: u64, sample_rate: f64 },
+}
+
+/// Draws the transformation rules applied with [`ExplorationStrategy::Randomized`].
+pub(super) struct RuleSampler {
+    state: u64,
+    sample_rate: f64,
+    max_promise: f64,
+}
+
+impl RuleSampler {
+    pub(super) fn new(seed: u64, sample_rate: f64, max_promise: f64) -> Self {
+        Self {
+            state: seed,
+            sample_rate,
+            max_promise,
+        }
+    }
+
+    /// splitmix64, which is enough to sample rules and keeps the optimizer free of a random
+    /// number generator dependency.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Whether to apply a rule of the given promise.
+    pub(super) fn sample(&mut self, promise: f64) -> bool {
+        if self.max_promise <= 0.0 {
+            return false;
+        }
+        let probability = self.sample_rate * promise / self.max_promise;
+        // the 53 high bits make a uniform float in [0, 1)
+        let draw = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        draw < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_proportionally_to_promise() {
+        let mut sampler = RuleSampler::new(42, 0.5, 4.0);
+        let draws = 10000;
+        let promising = (0..draws).filter(|_| sampler.sample(4.0)).count();
+        let unpromising = (0..draws).filter(|_| sampler.sample(1.0)).count();
+        assert!((4500..5500).contains(&promising), "{promising}");
+        assert!((1000..1500).contains(&unpromising), "{unpromising}");
+
+        // the same seed draws the same rules
+        let mut first = RuleSampler::new(7, 0.5, 1.0);
+        let mut second = RuleSampler::new(7, 0.5, 1.0);
+        assert!((0..100).all(|_| first.sample(1.0) == second.sample(1.0)));
+    }
+}
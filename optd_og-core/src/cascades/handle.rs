@@ -0,0 +1,64 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use serde::{Deserialize, Serialize};
+
+use super::{GroupId, Memo};
+use crate::nodes::NodeType;
+
+/// A reference to a group of the memo table, handed out by the public API of the optimizer.
+/// Two groups found to be equivalent are merged during the optimization, and the id of the group
+/// merged into the other one is no longer valid. A handle follows the merges: it resolves to the
+/// group its group was merged into, so that it can be kept across the optimization stages.
+///
+/// The methods of [`super::CascadesOptimizer`] taking a group accept a handle, and resolve it.
+/// Raw [`GroupId`]s are still accepted, and resolved the same way, but are deprecated in the
+/// public API as they are stale once resolved.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub struct GroupHandle(GroupId);
+
+impl GroupHandle {
+    pub fn new(group_id: GroupId) -> Self {
+        Self(group_id)
+    }
+
+    /// The current id of the group in the memo table.
+    pub fn resolve<T: NodeType>(&self, memo: &(impl Memo<T> + ?Sized)) -> GroupId {
+        memo.reduce_group(self.0)
+    }
+}
+
+impl From<GroupId> for GroupHandle {
+    fn from(group_id: GroupId) -> Self {
+        Self::new(group_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cascades::NaiveMemo;
+    use crate::nodes::Value;
+    use crate::tests::common::{expr, list, project, scan};
+
+    #[test]
+    fn follow_group_merges() {
+        let mut memo = NaiveMemo::new(Arc::new([]));
+        let expr1 = project(scan("t1"), list(vec![expr(Value::Int64(1))]));
+        let expr2 = project(scan("t1-alias"), list(vec![expr(Value::Int64(1))]));
+        let handle1 = GroupHandle::new(memo.add_new_expr(expr1).0);
+        let handle2 = GroupHandle::new(memo.add_new_expr(expr2).0);
+        assert_ne!(handle1.resolve(&memo), handle2.resolve(&memo));
+
+        // merging the scans merges the projections
+        let (scan_group_id, _) = memo.get_expr_info(scan("t1"));
+        memo.add_expr_to_group(scan("t1-alias").into(), scan_group_id);
+        let group_id = handle1.resolve(&memo);
+        assert_eq!(group_id, handle2.resolve(&memo));
+        assert!(memo.get_all_group_ids().contains(&group_id));
+    }
+}
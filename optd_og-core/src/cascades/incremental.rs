@@ -0,0 +1,82 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Optimization as an iterator of progressively better plans, so that a caller can run the first
+//! plan found while the search goes on, or stop once a plan is cheap enough, see
+//! [`super::CascadesOptimizer::optimize_incremental`].
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use crate::nodes::{ArcPlanNode, NodeType};
+
+/// A new best plan of the root group.
+#[derive(Clone, Debug)]
+pub struct PlanImprovement<T: NodeType> {
+    pub plan: ArcPlanNode<T>,
+    /// The weighted cost of the winner of the root group.
+    pub cost: f64,
+    /// The number of optimizer tasks run when the plan was found.
+    pub steps: usize,
+}
+
+/// Where the optimizer tasks hand the new best plans over to the iterator.
+pub(super) type ImprovementSlot<T> = Rc<RefCell<Option<PlanImprovement<T>>>>;
+
+/// Pauses the optimizer tasks once, so that the iterator can return the plan they handed over.
+#[derive(Default)]
+pub(super) struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            Poll::Pending
+        }
+    }
+}
+
+/// Runs the optimizer tasks until they find a new best plan of the root group. Dropping the
+/// iterator stops the search, and leaves the best plan found so far in the memo table.
+pub struct IncrementalOptimization<'a, T: NodeType> {
+    task: Option<Pin<Box<dyn Future<Output = ()> + 'a>>>,
+    slot: ImprovementSlot<T>,
+}
+
+impl<'a, T: NodeType> IncrementalOptimization<'a, T> {
+    pub(super) fn new(
+        task: Pin<Box<dyn Future<Output = ()> + 'a>>,
+        slot: ImprovementSlot<T>,
+    ) -> Self {
+        Self {
+            task: Some(task),
+            slot,
+        }
+    }
+}
+
+impl<T: NodeType> Iterator for IncrementalOptimization<'_, T> {
+    type Item = PlanImprovement<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let task = self.task.as_mut()?;
+        let mut cx = Context::from_waker(Waker::noop());
+        // 32MB stack for the optimization process, as in `fire_optimize_tasks`
+        let poll = stacker::grow(32 * 1024 * 1024, || task.as_mut().poll(&mut cx));
+        if poll.is_ready() {
+            self.task = None;
+        }
+        self.slot.borrow_mut().take()
+    }
+}
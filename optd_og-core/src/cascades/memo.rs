@@ -3,18 +3,22 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+mod union_find;
+
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use itertools::Itertools;
 use tracing::trace;
 
+use super::eviction::EvictionCandidate;
 use super::optimizer::{ExprId, GroupId, PredId};
-use crate::cost::{Cost, Statistics};
+use crate::cost::{Cost, CostComparator, Statistics};
 use crate::logical_property::{LogicalProperty, LogicalPropertyBuilderAny};
 use crate::nodes::{ArcPlanNode, ArcPredNode, NodeType, PlanNode, PlanNodeOrGroup};
+use union_find::GroupUnionFind;
 
 pub type ArcMemoPlanNode<T> = Arc<MemoPlanNode<T>>;
 
@@ -109,6 +113,10 @@ pub trait Memo<T: NodeType>: 'static + Send + Sync {
     /// Add a new predicate into the memo table.
     fn add_new_pred(&mut self, pred_node: ArcPredNode<T>) -> PredId;
 
+    /// Look up an expression in the memo table without adding it. Returns `None` if the
+    /// expression or any of its children is not in the memo table.
+    fn find_expr(&self, rel_node: ArcPlanNode<T>) -> Option<(GroupId, ExprId)>;
+
     /// Get the group id of an expression.
     /// The group id is volatile, depending on whether the groups are merged.
     fn get_group_id(&self, expr_id: ExprId) -> GroupId;
@@ -135,6 +143,23 @@ pub trait Memo<T: NodeType>: 'static + Send + Sync {
     /// enabled. Returns number of expressions in the memo table.
     fn estimated_plan_space(&self) -> usize;
 
+    /// The expressions which can be evicted, ordered by id, see [`EvictionCandidate`].
+    fn eviction_candidates(&self) -> Vec<EvictionCandidate>;
+
+    /// Evict the expressions from the memo table, skipping the ones which are not candidates.
+    /// Returns the evicted expressions.
+    fn evict_exprs(&mut self, exprs: &[ExprId]) -> Vec<ExprId>;
+
+    /// Drop the predicates no longer referenced by any expression of the memo table, e.g., the
+    /// predicates of the evicted expressions, which are interned until then. Returns the number
+    /// of predicates dropped.
+    fn compact_predicates(&mut self) -> usize;
+
+    /// Check the consistency of the memo table: the indexes of the expressions, the merged
+    /// groups, and the winners being expressions of their groups. This is a debugging aid, see
+    /// [`super::OptimizerProperties::verify_memo_integrity`].
+    fn verify_integrity(&self) -> Result<()>;
+
     // The below functions can be overwritten by the memo table implementation if there
     // are more efficient way to retrieve the information.
 
@@ -214,9 +239,18 @@ pub struct NaiveMemo<T: NodeType> {
 
     // We update all group IDs in the memo table upon group merging, but
     // there might be edge cases that some tasks still hold the old group ID.
-    // In this case, we need this mapping to redirect to the merged group ID.
-    merged_group_mapping: HashMap<GroupId, GroupId>,
+    // In this case, we need the merged groups to redirect to the merged group ID.
+    merged_groups: GroupUnionFind,
+    // The expressions with each group as a child, which are rewritten when the group is merged.
+    // The expressions evicted or deduplicated since are skipped when the group is merged.
+    group_parents: HashMap<GroupId, HashSet<ExprId>>,
     dup_expr_mapping: HashMap<ExprId, ExprId>,
+
+    cost_comparator: CostComparator,
+
+    // When each group was last accessed, for the eviction policies.
+    access_clock: u64,
+    group_last_access: HashMap<GroupId, u64>,
 }
 
 impl<T: NodeType> Memo<T> for NaiveMemo<T> {
@@ -224,7 +258,6 @@ impl<T: NodeType> Memo<T> for NaiveMemo<T> {
         let (group_id, expr_id) = self
             .add_new_group_expr_inner(rel_node, None)
             .expect("should not trigger merge group");
-        self.verify_integrity();
         (group_id, expr_id)
     }
 
@@ -235,18 +268,17 @@ impl<T: NodeType> Memo<T> for NaiveMemo<T> {
     ) -> Option<ExprId> {
         match rel_node {
             PlanNodeOrGroup::Group(input_group) => {
-                let input_group = self.reduce_group(input_group);
-                let group_id = self.reduce_group(group_id);
+                let input_group = self.reduce_group_mut(input_group);
+                let group_id = self.reduce_group_mut(group_id);
                 self.merge_group_inner(input_group, group_id);
                 None
             }
             PlanNodeOrGroup::PlanNode(rel_node) => {
-                let reduced_group_id = self.reduce_group(group_id);
+                let reduced_group_id = self.reduce_group_mut(group_id);
                 let (returned_group_id, expr_id) = self
                     .add_new_group_expr_inner(rel_node, Some(reduced_group_id))
                     .unwrap();
                 assert_eq!(returned_group_id, reduced_group_id);
-                self.verify_integrity();
                 Some(expr_id)
             }
         }
@@ -262,6 +294,27 @@ impl<T: NodeType> Memo<T> for NaiveMemo<T> {
         pred_id
     }
 
+    fn find_expr(&self, rel_node: ArcPlanNode<T>) -> Option<(GroupId, ExprId)> {
+        let mut children_group_ids = Vec::with_capacity(rel_node.children.len());
+        for child in &rel_node.children {
+            children_group_ids.push(match child {
+                PlanNodeOrGroup::Group(group) => self.reduce_group(*group),
+                PlanNodeOrGroup::PlanNode(child) => self.find_expr(child.clone())?.0,
+            });
+        }
+        let mut predicates = Vec::with_capacity(rel_node.predicates.len());
+        for pred in &rel_node.predicates {
+            predicates.push(*self.pred_node_to_pred_id.get(pred)?);
+        }
+        let memo_node = MemoPlanNode {
+            typ: rel_node.typ.clone(),
+            children: children_group_ids,
+            predicates,
+        };
+        let expr_id = *self.expr_node_to_expr_id.get(&memo_node)?;
+        Some((self.get_group_id(expr_id), expr_id))
+    }
+
     fn get_pred(&self, pred_id: PredId) -> ArcPredNode<T> {
         self.pred_id_to_pred_node[&pred_id].clone()
     }
@@ -313,14 +366,167 @@ impl<T: NodeType> Memo<T> for NaiveMemo<T> {
         let group_id = self.reduce_group(group_id);
         let grp = self.groups.get_mut(&group_id);
         grp.unwrap().info = group_info;
+        self.touch_group(group_id);
     }
 
     fn estimated_plan_space(&self) -> usize {
         self.expr_id_to_expr_node.len()
     }
 
+    fn eviction_candidates(&self) -> Vec<EvictionCandidate> {
+        let mut candidates = Vec::new();
+        for (group_id, group) in &self.groups {
+            if group.group_exprs.len() <= 1 {
+                continue;
+            }
+            let winner = group.info.winner.as_full_winner();
+            let winner_expr_id = winner.map(|winner| self.reduce_expr(winner.expr_id));
+            for expr_id in &group.group_exprs {
+                if Some(*expr_id) != winner_expr_id {
+                    candidates.push(EvictionCandidate {
+                        expr_id: *expr_id,
+                        group_id: *group_id,
+                        last_access: self.group_last_access[group_id],
+                        winner_cost: winner.map(|winner| winner.total_weighted_cost),
+                    });
+                }
+            }
+        }
+        candidates.sort_by_key(|candidate| candidate.expr_id);
+        candidates
+    }
+
+    fn evict_exprs(&mut self, exprs: &[ExprId]) -> Vec<ExprId> {
+        let mut evicted = Vec::new();
+        for expr_id in exprs {
+            let Some(&group_id) = self.expr_id_to_group_id.get(expr_id) else {
+                continue;
+            };
+            let winner_expr_id = self.groups[&group_id]
+                .info
+                .winner
+                .as_full_winner()
+                .map(|winner| self.reduce_expr(winner.expr_id));
+            let group = self.groups.get_mut(&group_id).unwrap();
+            // the last expression of a group is kept, the group is still referenced
+            if winner_expr_id == Some(*expr_id) || group.group_exprs.len() <= 1 {
+                continue;
+            }
+            group.group_exprs.remove(expr_id);
+            let expr = self.expr_id_to_expr_node.remove(expr_id).unwrap();
+            self.expr_node_to_expr_id.remove(expr.as_ref());
+            self.expr_id_to_group_id.remove(expr_id);
+            evicted.push(*expr_id);
+        }
+        self.dup_expr_mapping
+            .retain(|_, expr_id| !evicted.contains(expr_id));
+        evicted
+    }
+
+    fn compact_predicates(&mut self) -> usize {
+        let referenced: HashSet<PredId> = self
+            .expr_id_to_expr_node
+            .values()
+            .flat_map(|expr| expr.predicates.iter().copied())
+            .collect();
+        let before = self.pred_id_to_pred_node.len();
+        self.pred_id_to_pred_node
+            .retain(|pred_id, _| referenced.contains(pred_id));
+        self.pred_node_to_pred_id
+            .retain(|_, pred_id| referenced.contains(pred_id));
+        let dropped = before - self.pred_id_to_pred_node.len();
+        trace!(event = "compact_predicates", dropped = dropped);
+        dropped
+    }
+
     fn reduce_group(&self, group_id: GroupId) -> GroupId {
-		self.merged_group_mapping[&group_id]
+        self.merged_groups.find(group_id)
+    }
+
+    fn verify_integrity(&self) -> Result<()> {
+        let num_of_exprs = self.expr_id_to_expr_node.len();
+        ensure!(
+            num_of_exprs == self.expr_node_to_expr_id.len()
+                && num_of_exprs == self.expr_id_to_group_id.len(),
+            "expression indexes out of sync: {} expressions, {} nodes, {} group mappings",
+            num_of_exprs,
+            self.expr_node_to_expr_id.len(),
+            self.expr_id_to_group_id.len()
+        );
+
+        for group_id in self.groups.keys() {
+            let reduced = self.reduce_group(*group_id);
+            ensure!(
+                reduced == *group_id,
+                "group {} is merged into {} but still exists",
+                group_id,
+                reduced
+            );
+        }
+
+        for (id, node) in self.expr_id_to_expr_node.iter() {
+            ensure!(
+                self.expr_node_to_expr_id.get(node.as_ref()) == Some(id),
+                "expression {} is not found by its node {}",
+                id,
+                node
+            );
+            for pred in &node.predicates {
+                ensure!(
+                    self.pred_id_to_pred_node.contains_key(pred),
+                    "expression {} references the dropped predicate {}",
+                    id,
+                    pred
+                );
+            }
+            for child in &node.children {
+                ensure!(
+                    self.groups.contains_key(child),
+                    "invalid group used in expression {}, where {} does not exist any more",
+                    node,
+                    child
+                );
+                ensure!(
+                    self.group_parents
+                        .get(child)
+                        .is_some_and(|parents| parents.contains(id)),
+                    "expression {} is not a parent of its child group {}",
+                    id,
+                    child
+                );
+            }
+        }
+
+        let mut cnt = 0;
+        for (group_id, group) in &self.groups {
+            cnt += group.group_exprs.len();
+            ensure!(!group.group_exprs.is_empty(), "group {} is empty", group_id);
+            for expr in &group.group_exprs {
+                ensure!(
+                    self.expr_id_to_group_id.get(expr) == Some(group_id),
+                    "expression {} of group {} is mapped to group {:?}",
+                    expr,
+                    group_id,
+                    self.expr_id_to_group_id.get(expr)
+                );
+            }
+            if let Some(winner) = group.info.winner.as_full_winner() {
+                let expr_id = self.reduce_expr(winner.expr_id);
+                ensure!(
+                    group.group_exprs.contains(&expr_id),
+                    "the winner {} of group {} is not an expression of the group",
+                    expr_id,
+                    group_id
+                );
+            }
+        }
+        ensure!(
+            cnt == num_of_exprs,
+            "{} expressions in the groups, {} in the memo table",
+            cnt,
+            num_of_exprs
+        );
+        Ok(())
     }
 }
 
@@ -334,12 +540,22 @@ impl<T: NodeType> NaiveMemo<T> {
             pred_node_to_pred_id: HashMap::new(),
             groups: HashMap::new(),
             group_expr_counter: 0,
-            merged_group_mapping: HashMap::new(),
+            merged_groups: GroupUnionFind::default(),
+            group_parents: HashMap::new(),
             property_builders,
             dup_expr_mapping: HashMap::new(),
+            cost_comparator: CostComparator::default(),
+            access_clock: 0,
+            group_last_access: HashMap::new(),
         }
     }
 
+    /// Use the given comparator when merging the winners of two groups.
+    pub fn with_cost_comparator(mut self, cost_comparator: CostComparator) -> Self {
+        self.cost_comparator = cost_comparator;
+        self
+    }
+
     /// Get the next group id. Group id and expr id shares the same counter, so as to make it easier
     /// to debug...
     fn next_group_id(&mut self) -> GroupId {
@@ -364,43 +580,21 @@ impl<T: NodeType> NaiveMemo<T> {
         PredId(id)
     }
 
-    fn verify_integrity(&self) {
-        const ENABLE_INTEGRITY_CHECK: bool = false;
-        if ENABLE_INTEGRITY_CHECK {
-            let num_of_exprs = self.expr_id_to_expr_node.len();
-            assert_eq!(num_of_exprs, self.expr_node_to_expr_id.len());
-            assert_eq!(num_of_exprs, self.expr_id_to_group_id.len());
+    fn reduce_expr(&self, mut expr_id: ExprId) -> ExprId {
+        while let Some(new_expr_id) = self.dup_expr_mapping.get(&expr_id) {
+            expr_id = *new_expr_id;
+        }
+        expr_id
+    }
 
-            let mut valid_groups = HashSet::new();
-            for to in self.merged_group_mapping.values() {
-                assert_eq!(self.merged_group_mapping[to], *to);
-                valid_groups.insert(*to);
-            }
-            assert_eq!(valid_groups.len(), self.groups.len());
-
-            for (id, node) in self.expr_id_to_expr_node.iter() {
-                assert_eq!(self.expr_node_to_expr_id[node], *id);
-                for child in &node.children {
-                    assert!(
-                        valid_groups.contains(child),
-                        "invalid group used in expression {}, where {} does not exist any more",
-                        node,
-                        child
-                    );
-                }
-            }
+    /// Like [`Memo::reduce_group`], and compresses the path to the merged group.
+    fn reduce_group_mut(&mut self, group_id: GroupId) -> GroupId {
+        self.merged_groups.find_mut(group_id)
+    }
 
-            let mut cnt = 0;
-            for (group_id, group) in &self.groups {
-                assert!(valid_groups.contains(group_id));
-                cnt += group.group_exprs.len();
-                assert!(!group.group_exprs.is_empty());
-                for expr in &group.group_exprs {
-                    assert_eq!(self.expr_id_to_group_id[expr], *group_id);
-                }
-            }
-            assert_eq!(cnt, num_of_exprs);
-        }
+    fn touch_group(&mut self, group_id: GroupId) {
+        self.access_clock += 1;
+        self.group_last_access.insert(group_id, self.access_clock);
     }
 
     fn merge_group_inner(&mut self, merge_into: GroupId, merge_from: GroupId) {
@@ -417,7 +611,7 @@ impl<T: NodeType> NaiveMemo<T> {
             assert!(ret.is_some());
             group_merge_into.group_exprs.insert(from_expr);
         }
-        self.merged_group_mapping.insert(merge_from, merge_into);
+        self.merged_groups.union(merge_into, merge_from);
 
         // Merge winner
         if let Some(winner) = group_merge_from.info.winner.as_full_winner() {
@@ -426,64 +620,67 @@ impl<T: NodeType> NaiveMemo<T> {
                     group_merge_into.info.winner = Winner::Full(winner.clone());
                 }
                 Winner::Full(winner_into) => {
-                    if winner.total_weighted_cost < winner_into.total_weighted_cost {
+                    if self
+                        .cost_comparator
+                        .is_better(winner.total_weighted_cost, winner_into.total_weighted_cost)
+                    {
                         group_merge_into.info.winner = Winner::Full(winner.clone());
                     }
                 }
             }
         }
 
-        // Update all indexes and other data structures
-        // 1. update merged group mapping -- could be optimized with union find
-        for (_, mapped_to) in self.merged_group_mapping.iter_mut() {
-            if *mapped_to == merge_from {
-                *mapped_to = merge_into;
-            }
+        if let Some(last_access) = self.group_last_access.remove(&merge_from) {
+            let into_access = self.group_last_access.entry(merge_into).or_default();
+            *into_access = (*into_access).max(last_access);
         }
 
+        // Update the expressions with the merged group as a child, and their indexes
         let mut pending_recursive_merge = Vec::new();
-        // 2. update all group expressions and indexes
-        for (group_id, group) in self.groups.iter_mut() {
-            let mut new_expr_list = HashSet::new();
-            for expr_id in group.group_exprs.iter() {
-                let expr = self.expr_id_to_expr_node[expr_id].clone();
-                if expr.children.contains(&merge_from) {
-                    // Create the new expr node
-                    let old_expr = expr.as_ref().clone();
-                    let mut new_expr = expr.as_ref().clone();
-                    new_expr.children.iter_mut().for_each(|x| {
-                        if *x == merge_from {
-                            *x = merge_into;
-                        }
-                    });
-                    // Update all existing entries and indexes
-                    self.expr_id_to_expr_node
-                        .insert(*expr_id, Arc::new(new_expr.clone()));
-                    self.expr_node_to_expr_id.remove(&old_expr);
-                    if let Some(dup_expr) = self.expr_node_to_expr_id.get(&new_expr) {
-                        // If new_expr == some_other_old_expr in the memo table, unless they belong
-                        // to the same group, we should merge the two
-                        // groups. This should not happen. We should simply drop this expression.
-                        let dup_group_id = self.expr_id_to_group_id[dup_expr];
-                        if dup_group_id != *group_id {
-                            pending_recursive_merge.push((dup_group_id, *group_id));
-                        }
-                        self.expr_id_to_expr_node.remove(expr_id);
-                        self.expr_id_to_group_id.remove(expr_id);
-                        self.dup_expr_mapping.insert(*expr_id, *dup_expr);
-                        new_expr_list.insert(*dup_expr); // adding this temporarily -- should be
-                                                         // removed once recursive merge finishes
-                    } else {
-                        self.expr_node_to_expr_id.insert(new_expr, *expr_id);
-                        new_expr_list.insert(*expr_id);
-                    }
-                } else {
-                    new_expr_list.insert(*expr_id);
+        let parents = self.group_parents.remove(&merge_from).unwrap_or_default();
+        for expr_id in &parents {
+            let Some(expr) = self.expr_id_to_expr_node.get(expr_id).cloned() else {
+                continue;
+            };
+            if !expr.children.contains(&merge_from) {
+                continue;
+            }
+            let group_id = self.expr_id_to_group_id[expr_id];
+            // Create the new expr node
+            let old_expr = expr.as_ref().clone();
+            let mut new_expr = expr.as_ref().clone();
+            new_expr.children.iter_mut().for_each(|x| {
+                if *x == merge_from {
+                    *x = merge_into;
                 }
+            });
+            // Update all existing entries and indexes
+            self.expr_node_to_expr_id.remove(&old_expr);
+            if let Some(&dup_expr) = self.expr_node_to_expr_id.get(&new_expr) {
+                // If new_expr == some_other_old_expr in the memo table, unless they belong
+                // to the same group, we should merge the two
+                // groups. This should not happen. We should simply drop this expression.
+                let dup_group_id = self.expr_id_to_group_id[&dup_expr];
+                if dup_group_id != group_id {
+                    pending_recursive_merge.push((dup_group_id, group_id));
+                }
+                self.expr_id_to_expr_node.remove(expr_id);
+                self.expr_id_to_group_id.remove(expr_id);
+                self.dup_expr_mapping.insert(*expr_id, dup_expr);
+                let group = self.groups.get_mut(&group_id).unwrap();
+                group.group_exprs.remove(expr_id);
+                // adding this temporarily -- should be removed once recursive merge finishes
+                group.group_exprs.insert(dup_expr);
+            } else {
+                self.expr_id_to_expr_node
+                    .insert(*expr_id, Arc::new(new_expr.clone()));
+                self.expr_node_to_expr_id.insert(new_expr, *expr_id);
             }
-            assert!(!new_expr_list.is_empty());
-            group.group_exprs = new_expr_list;
         }
+        self.group_parents
+            .entry(merge_into)
+            .or_default()
+            .extend(parents);
         for (merge_from, merge_into) in pending_recursive_merge {
             // We need to reduce because each merge would probably invalidate some groups in the
             // last loop iteration.
@@ -504,14 +701,14 @@ impl<T: NodeType> NaiveMemo<T> {
             .map(|child| {
                 match child {
                     // TODO: can I remove reduce?
-                    PlanNodeOrGroup::Group(group) => self.reduce_group(*group),
+                    PlanNodeOrGroup::Group(group) => self.reduce_group_mut(*group),
                     PlanNodeOrGroup::PlanNode(child) => {
                         // No merge / modification to the memo should occur for the following
                         // operation
                         let (group, _) = self
                             .add_new_group_expr_inner(child.clone(), None)
                             .expect("should not trigger merge group");
-                        self.reduce_group(group) // TODO: can I remove?
+                        self.reduce_group_mut(group) // TODO: can I remove?
                     }
                 }
             })
@@ -528,7 +725,7 @@ impl<T: NodeType> NaiveMemo<T> {
         if let Some(&expr_id) = self.expr_node_to_expr_id.get(&memo_node) {
             let group_id = self.expr_id_to_group_id[&expr_id];
             if let Some(add_to_group_id) = add_to_group_id {
-                let add_to_group_id = self.reduce_group(add_to_group_id);
+                let add_to_group_id = self.reduce_group_mut(add_to_group_id);
                 self.merge_group_inner(add_to_group_id, group_id);
                 return Ok((add_to_group_id, expr_id));
             }
@@ -544,6 +741,12 @@ impl<T: NodeType> NaiveMemo<T> {
             .insert(expr_id, memo_node.clone().into());
         self.expr_id_to_group_id.insert(expr_id, group_id);
         self.expr_node_to_expr_id.insert(memo_node.clone(), expr_id);
+        for child in &memo_node.children {
+            self.group_parents
+                .entry(*child)
+                .or_default()
+                .insert(expr_id);
+        }
         self.append_expr_to_group(expr_id, group_id, memo_node);
         Ok((group_id, expr_id))
     }
@@ -552,28 +755,10 @@ impl<T: NodeType> NaiveMemo<T> {
     /// rel node. Should be only used for debugging purpose.
     #[cfg(test)]
     pub(crate) fn get_expr_info(&self, rel_node: ArcPlanNode<T>) -> (GroupId, ExprId) {
-        let children_group_ids = rel_node
-            .children
-            .iter()
-            .map(|child| match child {
-                PlanNodeOrGroup::Group(group) => *group,
-                PlanNodeOrGroup::PlanNode(child) => self.get_expr_info(child.clone()).0,
-            })
-            .collect::<Vec<_>>();
-        let memo_node = MemoPlanNode {
-            typ: rel_node.typ.clone(),
-            children: children_group_ids,
-            predicates: rel_node
-                .predicates
-                .iter()
-                .map(|x| self.pred_node_to_pred_id[x])
-                .collect(),
-        };
-        let Some(&expr_id) = self.expr_node_to_expr_id.get(&memo_node) else {
-            unreachable!("not found {}", memo_node)
+        let Some(info) = self.find_expr(rel_node.clone()) else {
+            unreachable!("not found {}", rel_node)
         };
-        let group_id = self.expr_id_to_group_id[&expr_id];
-        (group_id, expr_id)
+        info
     }
 
     fn infer_properties(&self, memo_node: MemoPlanNode<T>) -> Vec<Box<dyn LogicalProperty>> {
@@ -612,6 +797,7 @@ impl<T: NodeType> NaiveMemo<T> {
         memo_node: MemoPlanNode<T>,
     ) {
         trace!(event = "add_expr_to_group", group_id = %group_id, expr_id = %expr_id, memo_node = %memo_node);
+        self.touch_group(group_id);
         if let Entry::Occupied(mut entry) = self.groups.entry(group_id) {
             let group = entry.get_mut();
             group.group_exprs.insert(expr_id);
@@ -625,7 +811,7 @@ impl<T: NodeType> NaiveMemo<T> {
         };
         group.group_exprs.insert(expr_id);
         self.groups.insert(group_id, group);
-        self.merged_group_mapping.insert(group_id, group_id);
+        self.merged_groups.insert(group_id);
     }
 
     pub fn clear_winner(&mut self) {
@@ -746,6 +932,45 @@ pub(crate) mod tests {
             memo.get_expr_memoed(expr2_id)
         ); // these two expressions are merged
         assert_eq!(memo.get_expr_info(expr1), memo.get_expr_info(expr2));
+        memo.verify_integrity().unwrap();
+    }
+
+    #[test]
+    fn compact_unreferenced_predicates() {
+        let mut memo = NaiveMemo::new(Arc::new([]));
+        let (group_id, _) =
+            memo.add_new_expr(project(scan("t1"), list(vec![expr(Value::Int64(1))])));
+        let evicted = memo
+            .add_expr_to_group(
+                project(scan("t1"), list(vec![expr(Value::Int64(2))])).into(),
+                group_id,
+            )
+            .unwrap();
+        memo.add_new_pred(expr(Value::Int64(3)));
+        // the unreferenced predicate
+        assert_eq!(memo.compact_predicates(), 1);
+
+        assert_eq!(memo.evict_exprs(&[evicted]), vec![evicted]);
+        // the list of the evicted expression
+        assert_eq!(memo.compact_predicates(), 1);
+        assert_eq!(memo.compact_predicates(), 0);
+        memo.verify_integrity().unwrap();
+        assert!(memo
+            .find_expr(project(scan("t1"), list(vec![expr(Value::Int64(1))])))
+            .is_some());
+    }
+
+    #[test]
+    fn verify_corrupted_memo() {
+        let mut memo = NaiveMemo::new(Arc::new([]));
+        let (_, expr_id) = memo.add_new_expr(project(scan("t1"), list(vec![])));
+        memo.verify_integrity().unwrap();
+
+        let node = memo.get_expr_memoed(expr_id);
+        memo.expr_node_to_expr_id.remove(node.as_ref());
+        memo.expr_node_to_expr_id
+            .insert(node.as_ref().clone(), ExprId(expr_id.0 + 100));
+        assert!(memo.verify_integrity().is_err());
     }
 
     #[test]
@@ -0,0 +1,112 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+
+use crate::cascades::GroupId;
+
+struct UnionFindNode {
+    parent: GroupId,
+    rank: u32,
+    /// For the roots, the group the other groups of the set were merged into. The root is chosen
+    /// by rank to keep the trees shallow, and is not always that group.
+    merged_into: GroupId,
+}
+
+/// The sets of merged groups, with union by rank and path compression. The paths are only
+/// compressed when the memo table is mutably borrowed, the lookups through `&self` walk them.
+#[derive(Default)]
+pub(super) struct GroupUnionFind {
+    nodes: HashMap<GroupId, UnionFindNode>,
+}
+
+impl GroupUnionFind {
+    pub(super) fn insert(&mut self, group_id: GroupId) {
+        self.nodes.insert(
+            group_id,
+            UnionFindNode {
+                parent: group_id,
+                rank: 0,
+                merged_into: group_id,
+            },
+        );
+    }
+
+    fn root(&self, mut group_id: GroupId) -> GroupId {
+        loop {
+            let parent = self.nodes[&group_id].parent;
+            if parent == group_id {
+                return group_id;
+            }
+            group_id = parent;
+        }
+    }
+
+    /// The group the given group was merged into.
+    pub(super) fn find(&self, group_id: GroupId) -> GroupId {
+        self.nodes[&self.root(group_id)].merged_into
+    }
+
+    /// Like [`Self::find`], and points the groups on the path directly to the root.
+    pub(super) fn find_mut(&mut self, mut group_id: GroupId) -> GroupId {
+        let root = self.root(group_id);
+        while group_id != root {
+            let node = self.nodes.get_mut(&group_id).unwrap();
+            group_id = std::mem::replace(&mut node.parent, root);
+        }
+        self.nodes[&root].merged_into
+    }
+
+    /// Merges the set of `merge_from` into the set of `merge_into`, after which both groups are
+    /// found as `merge_into`.
+    pub(super) fn union(&mut self, merge_into: GroupId, merge_from: GroupId) {
+        let root_into = self.root(merge_into);
+        let root_from = self.root(merge_from);
+        if root_into == root_from {
+            return;
+        }
+        let rank_into = self.nodes[&root_into].rank;
+        let rank_from = self.nodes[&root_from].rank;
+        let (root, child) = if rank_into >= rank_from {
+            (root_into, root_from)
+        } else {
+            (root_from, root_into)
+        };
+        self.nodes.get_mut(&child).unwrap().parent = root;
+        let root = self.nodes.get_mut(&root).unwrap();
+        if rank_into == rank_from {
+            root.rank += 1;
+        }
+        root.merged_into = merge_into;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_merged_groups() {
+        let mut union_find = GroupUnionFind::default();
+        for group_id in 0..5 {
+            union_find.insert(GroupId(group_id));
+        }
+        union_find.union(GroupId(0), GroupId(1));
+        union_find.union(GroupId(2), GroupId(3));
+        // the root of the set of 0 has a higher rank, the set is still found as 4
+        union_find.union(GroupId(4), GroupId(0));
+        assert_eq!(union_find.find(GroupId(1)), GroupId(4));
+        assert_eq!(union_find.find(GroupId(4)), GroupId(4));
+        assert_eq!(union_find.find(GroupId(3)), GroupId(2));
+
+        union_find.union(GroupId(2), GroupId(4));
+        for group_id in 0..5 {
+            assert_eq!(union_find.find(GroupId(group_id)), GroupId(2));
+        }
+        assert_eq!(union_find.find_mut(GroupId(1)), GroupId(2));
+        let root = union_find.root(GroupId(1));
+        assert_eq!(union_find.nodes[&GroupId(1)].parent, root);
+    }
+}
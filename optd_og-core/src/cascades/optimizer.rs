@@ -3,24 +3,38 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt::Display;
 use std::future::Future;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use tracing::trace;
 
+use super::checkpoint::{CheckpointHook, MemoCheckpoint};
+use super::cost_breakdown::CostBreakdown;
+use super::cost_cache::CostCache;
+use super::eviction::EvictionPolicy;
+use super::exploration::ExplorationStrategy;
+use super::handle::GroupHandle;
+use super::incremental::IncrementalOptimization;
 use super::memo::{ArcMemoPlanNode, GroupInfo, Memo, WinnerInfo};
+use super::progress::{CancellationToken, OptimizationProgress, ProgressHook};
+use super::replay::TraceReplay;
+use super::snapshot::MemoSnapshot;
 use super::NaiveMemo;
 use crate::cascades::memo::Winner;
 use crate::cascades::tasks2::{TaskContext, TaskDesc};
-use crate::cost::CostModel;
+use crate::cost::{Cost, CostComparator, CostModel, Statistics};
 use crate::logical_property::{LogicalPropertyBuilder, LogicalPropertyBuilderAny};
 use crate::nodes::{
-    ArcPlanNode, ArcPredNode, NodeType, PlanNodeMeta, PlanNodeMetaMap, PlanNodeOrGroup,
+    ArcPlanNode, ArcPredNode, NodeType, PlanAnnotation, PlanAnnotations, PlanNode, PlanNodeOrGroup,
 };
 use crate::optimizer::Optimizer;
 use crate::physical_property::PhysicalProperty;
@@ -34,6 +48,10 @@ pub struct OptimizerContext {
     pub logical_budget_used: bool,
     /// Not apply all rules any more; get a physical plan ASAP
     pub all_budget_used: bool,
+    /// The optimization was cancelled through the cancellation token, see `all_budget_used`
+    pub cancelled: bool,
+    /// The optimization ran out of time, see `OptimizerProperties::timeout` and `all_budget_used`
+    pub timed_out: bool,
     pub rules_applied: usize,
 }
 
@@ -45,10 +63,44 @@ pub struct OptimizerProperties {
     pub partial_explore_iter: Option<usize>,
     /// Plan space can be expanded by this number of times before we stop applying logical rules.
     pub partial_explore_space: Option<usize>,
+    /// If a call of [`CascadesOptimizer::fire_optimize_tasks`] runs longer than this, we stop
+    /// applying rules and finish with the best plan found so far.
+    pub timeout: Option<Duration>,
     /// Disable pruning during optimization.
     pub disable_pruning: bool,
     /// Enable tracing during optimization.
     pub enable_tracing: bool,
+    /// How costs are compared when deciding winners.
+    pub cost_comparator: CostComparator,
+    /// Retain the given number of the cheapest physical expressions of each group, the winner
+    /// included, see [`CascadesOptimizer::get_group_alternatives`].
+    pub retain_alternatives: Option<usize>,
+    /// Clamp the negative and non-finite costs produced by the cost model, see [`Cost::clamp`],
+    /// and log a warning, instead of failing a debug assertion (debug builds) or comparing them
+    /// as they are (release builds). The floor should be positive, as the memo table rejects
+    /// winners of zero cost.
+    pub cost_floor: Option<f64>,
+    /// How the transformation rules are applied, e.g., sampled for large join graphs.
+    pub exploration_strategy: ExplorationStrategy,
+    /// Check the integrity of the memo table after every rule application, and panic if it is
+    /// corrupted, see [`Memo::verify_integrity`]. This is slow, and meant for tests.
+    pub verify_memo_integrity: bool,
+}
+
+/// The parts of the memo table printed by [`CascadesOptimizer::dump_with_options`]. The default
+/// prints the whole memo table, like [`CascadesOptimizer::dump`].
+#[derive(Default, Clone, Debug)]
+pub struct MemoDumpOptions {
+    /// Only print this group and the groups its expressions take as children, transitively.
+    pub root: Option<GroupHandle>,
+    /// Only print the groups at most this many children away from `root`, if set.
+    pub max_depth: Option<usize>,
+    /// Only print the winner of each group, and only follow the children of the winners from
+    /// `root`.
+    pub winners_only: bool,
+    /// Only print the groups explored since the last [`CascadesOptimizer::step_next_stage`],
+    /// i.e., in the last optimization stage.
+    pub last_stage_only: bool,
 }
 
 #[derive(Clone)]
@@ -79,6 +131,31 @@ pub enum OptimizerTrace {
         /// The rule ID
         rule_id: usize,
     },
+    /// Applying a rule produced an expression of another group, and merged the two groups
+    MergeGroup {
+        stage: usize,
+        step: usize,
+        group_id: GroupId,
+        applied_expr_id: ExprId,
+        rule_id: usize,
+    },
+    /// The winner of a group is updated
+    UpdateWinner {
+        stage: usize,
+        step: usize,
+        group_id: GroupId,
+        expr_id: ExprId,
+        total_weighted_cost: f64,
+    },
+    /// A physical expression is pruned, its cost so far exceeding the upper bound of the search
+    Prune {
+        stage: usize,
+        step: usize,
+        group_id: GroupId,
+        expr_id: ExprId,
+        cost_so_far: f64,
+        upper_bound: f64,
+    },
 }
 
 impl OptimizerTrace {
@@ -86,6 +163,9 @@ impl OptimizerTrace {
         match self {
             OptimizerTrace::DecideWinner { stage, step, .. } => (*stage, *step),
             OptimizerTrace::ApplyRule { stage, step, .. } => (*stage, *step),
+            OptimizerTrace::MergeGroup { stage, step, .. } => (*stage, *step),
+            OptimizerTrace::UpdateWinner { stage, step, .. } => (*stage, *step),
+            OptimizerTrace::Prune { stage, step, .. } => (*stage, *step),
         }
     }
 }
@@ -120,6 +200,46 @@ impl std::fmt::Display for OptimizerTrace {
                     stage, step, group_id, applied_expr_id, produced_expr_id, rule_id
                 )
             }
+            OptimizerTrace::MergeGroup {
+                stage,
+                step,
+                group_id,
+                applied_expr_id,
+                rule_id,
+            } => {
+                write!(
+                    f,
+                    "step={}/{} merge_group group_id={} applied_expr_id={} rule_id={}",
+                    stage, step, group_id, applied_expr_id, rule_id
+                )
+            }
+            OptimizerTrace::UpdateWinner {
+                stage,
+                step,
+                group_id,
+                expr_id,
+                total_weighted_cost,
+            } => {
+                write!(
+                    f,
+                    "step={}/{} update_winner group_id={} expr_id={} total_weighted_cost={}",
+                    stage, step, group_id, expr_id, total_weighted_cost
+                )
+            }
+            OptimizerTrace::Prune {
+                stage,
+                step,
+                group_id,
+                expr_id,
+                cost_so_far,
+                upper_bound,
+            } => {
+                write!(
+                    f,
+                    "step={}/{} prune group_id={} expr_id={} cost_so_far={} upper_bound={}",
+                    stage, step, group_id, expr_id, cost_so_far, upper_bound
+                )
+            }
         }
     }
 }
@@ -133,6 +253,18 @@ pub struct CascadesStats {
     pub optimize_expr_count: usize,
     pub apply_rule_count: usize,
     pub optimize_input_count: usize,
+    /// The invalid costs clamped, see [`OptimizerProperties::cost_floor`].
+    pub invalid_cost_count: usize,
+    /// The operation costs and statistics of the physical expressions reused instead of being
+    /// computed again.
+    pub cost_cache_hit_count: usize,
+    /// The times the eviction policy ran, see [`CascadesOptimizer::step_evict`].
+    pub eviction_count: usize,
+    pub evicted_expr_count: usize,
+    /// The predicates dropped, see [`CascadesOptimizer::step_compact_predicates`].
+    pub compacted_pred_count: usize,
+    /// The transformation rules not applied by [`ExplorationStrategy::Randomized`].
+    pub sampled_out_rule_count: usize,
     pub trace: HashMap<GroupId, Vec<OptimizerTrace>>,
 }
 
@@ -141,6 +273,11 @@ pub struct CascadesOptimizer<T: NodeType, M: Memo<T> = NaiveMemo<T>> {
     explored_group: HashSet<GroupId>,
     explored_expr: HashSet<TaskDesc>,
     fired_rules: HashMap<ExprId, HashSet<RuleId>>,
+    /// The rule which produced each expression, and the expression it was applied to.
+    expr_provenance: HashMap<ExprId, (RuleId, ExprId)>,
+    /// The cheapest alternatives of each group, ordered by cost, if they are retained.
+    group_alternatives: HashMap<GroupId, Vec<WinnerInfo>>,
+    pub(super) cost_cache: CostCache,
     pub rules: Arc<[Arc<dyn Rule<T, Self>>]>,
     pub stats: CascadesStats,
     disabled_rules: HashSet<usize>,
@@ -149,6 +286,12 @@ pub struct CascadesOptimizer<T: NodeType, M: Memo<T> = NaiveMemo<T>> {
     pub ctx: OptimizerContext,
     pub prop: OptimizerProperties,
     stage: usize,
+    pub(super) checkpoint_hook: Option<CheckpointHook<T>>,
+    pub(super) progress_hook: Option<ProgressHook>,
+    pub(super) cancellation_token: Option<CancellationToken>,
+    /// The capacity of the memo table, in expressions, and the policy evicting the expressions
+    /// above it.
+    eviction: Option<(usize, Box<dyn EvictionPolicy>)>,
 }
 
 /// `RelNode` only contains the representation of the plan nodes. Sometimes, we need more context,
@@ -161,7 +304,9 @@ pub struct RelNodeContext {
     pub children_group_ids: Vec<GroupId>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Hash)]
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Hash, Serialize, Deserialize,
+)]
 pub struct GroupId(pub usize);
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Hash)]
@@ -203,36 +348,75 @@ impl<T: NodeType> CascadesOptimizer<T, NaiveMemo<T>> {
         logical_property_builders: Arc<[Box<dyn LogicalPropertyBuilderAny<T>>]>,
         prop: OptimizerProperties,
     ) -> Self {
-        let memo = NaiveMemo::new(logical_property_builders.clone());
+        Self::new_with_shared_cost(rules, cost.into(), logical_property_builders, prop)
+    }
+
+    /// Like [`Self::new_with_options`], with a cost model shared with another optimizer.
+    pub fn new_with_shared_cost(
+        rules: Vec<Arc<dyn Rule<T, Self>>>,
+        cost: Arc<dyn CostModel<T, NaiveMemo<T>>>,
+        logical_property_builders: Arc<[Box<dyn LogicalPropertyBuilderAny<T>>]>,
+        prop: OptimizerProperties,
+    ) -> Self {
+        let memo = NaiveMemo::new(logical_property_builders.clone())
+            .with_cost_comparator(prop.cost_comparator);
         Self {
             memo,
             explored_group: HashSet::new(),
             explored_expr: HashSet::new(),
             fired_rules: HashMap::new(),
+            expr_provenance: HashMap::new(),
+            group_alternatives: HashMap::new(),
+            cost_cache: CostCache::default(),
             rules: rules.into(),
-            cost: cost.into(),
+            cost,
             ctx: OptimizerContext::default(),
             logical_property_builders,
             prop,
             stats: CascadesStats::default(),
             disabled_rules: HashSet::new(),
             stage: 0,
+            checkpoint_hook: None,
+            progress_hook: None,
+            cancellation_token: None,
+            eviction: None,
         }
     }
 
     /// Clear the memo table and all optimizer states.
     pub fn step_clear(&mut self) {
-        self.memo = NaiveMemo::new(self.logical_property_builders.clone());
+        self.memo = NaiveMemo::new(self.logical_property_builders.clone())
+            .with_cost_comparator(self.prop.cost_comparator);
         self.fired_rules.clear();
+        self.expr_provenance.clear();
+        self.group_alternatives.clear();
+        self.cost_cache.clear();
         self.explored_group.clear();
         self.explored_expr.clear();
+        self.cost.reset_caches();
+        self.ctx = OptimizerContext::default();
     }
 
     /// Clear the winner so that the optimizer can continue to explore the group.
     pub fn step_clear_winner(&mut self) {
         self.memo.clear_winner();
+        self.group_alternatives.clear();
+        self.cost_cache.clear();
         self.explored_group.clear();
         self.explored_expr.clear();
+        self.cost.reset_caches();
+        // a new search, with its own budgets
+        self.ctx = OptimizerContext::default();
+    }
+
+    /// Clear the memo table and restore the expressions of the checkpoint. Returns the root group
+    /// of the checkpoint.
+    pub fn step_restore_checkpoint(
+        &mut self,
+        checkpoint: MemoCheckpoint<T>,
+    ) -> Result<GroupHandle> {
+        self.step_clear();
+        checkpoint.restore(&mut self.memo).map(GroupHandle::new)
     }
 
     /// Clear the explored groups so that the optimizer can continue to apply the rules.
@@ -251,6 +435,10 @@ impl<T: NodeType, M: Memo<T>> CascadesOptimizer<T, M> {
         self.rules.clone()
     }
 
+    pub fn logical_property_builders(&self) -> Arc<[Box<dyn LogicalPropertyBuilderAny<T>>]> {
+        self.logical_property_builders.clone()
+    }
+
     pub fn disable_rule(&mut self, rule_id: usize) {
         self.disabled_rules.insert(rule_id);
     }
@@ -285,12 +473,233 @@ impl<T: NodeType, M: Memo<T>> CascadesOptimizer<T, M> {
         }
     }
 
+    /// Call `callback` with a checkpoint of the memo table every `interval` optimizer tasks.
+    pub fn set_checkpoint_hook(
+        &mut self,
+        interval: usize,
+        callback: impl FnMut(MemoCheckpoint<T>) + Send + Sync + 'static,
+    ) {
+        assert!(interval > 0, "checkpoint interval must be positive");
+        self.checkpoint_hook = Some(CheckpointHook {
+            interval,
+            callback: Box::new(callback),
+        });
+    }
+
+    pub fn clear_checkpoint_hook(&mut self) {
+        self.checkpoint_hook = None;
+    }
+
+    /// Call `callback` with the progress of the optimization every `interval` optimizer tasks.
+    pub fn set_progress_hook(
+        &mut self,
+        interval: usize,
+        callback: impl FnMut(&OptimizationProgress) + Send + Sync + 'static,
+    ) {
+        assert!(interval > 0, "progress interval must be positive");
+        self.progress_hook = Some(ProgressHook {
+            interval,
+            callback: Box::new(callback),
+        });
+    }
+
+    pub fn clear_progress_hook(&mut self) {
+        self.progress_hook = None;
+    }
+
+    /// Stop the optimization with the best plan found so far once `token` is cancelled.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation_token = Some(token);
+    }
+
+    pub fn clear_cancellation_token(&mut self) {
+        self.cancellation_token = None;
+    }
+
+    /// Bound the memo table to `capacity` expressions with the eviction policy, which runs in
+    /// [`Self::step_evict`]. Unlike [`OptimizerProperties::partial_explore_space`], which stops
+    /// the exploration of a query, the eviction keeps a memo table reused across queries small.
+    pub fn set_eviction_policy(&mut self, capacity: usize, policy: impl EvictionPolicy) {
+        self.eviction = Some((capacity, Box::new(policy)));
+    }
+
+    pub fn clear_eviction_policy(&mut self) {
+        self.eviction = None;
+    }
+
+    /// Evict expressions with the eviction policy if the memo table holds more expressions than
+    /// its capacity. The groups keep their winners, and the rules are fired again on the groups
+    /// which lost expressions the next time they are explored. Must be called between
+    /// optimizations. Returns the number of evicted expressions.
+    pub fn step_evict(&mut self) -> usize {
+        let Some((capacity, policy)) = &self.eviction else {
+            return 0;
+        };
+        let plan_space = self.memo.estimated_plan_space();
+        if plan_space <= *capacity {
+            return 0;
+        }
+        let candidates = self.memo.eviction_candidates();
+        let selected = policy.select(&candidates, plan_space - capacity);
+        let evicted: HashSet<ExprId> = self.memo.evict_exprs(&selected).into_iter().collect();
+        trace!(
+            event = "evict",
+            policy = policy.name(),
+            evicted = evicted.len()
+        );
+        self.step_compact_predicates();
+
+        let groups: HashSet<GroupId> = candidates
+            .iter()
+            .filter(|candidate| evicted.contains(&candidate.expr_id))
+            .map(|candidate| candidate.group_id)
+            .collect();
+        for group_id in groups {
+            for expr_id in self.memo.get_all_exprs_in_group(group_id) {
+                self.fired_rules.remove(&expr_id);
+            }
+            self.explored_group.remove(&group_id);
+        }
+        for expr_id in &evicted {
+            self.fired_rules.remove(expr_id);
+            self.expr_provenance.remove(expr_id);
+            self.cost_cache.remove(*expr_id);
+        }
+        for alternatives in self.group_alternatives.values_mut() {
+            alternatives.retain(|alternative| !evicted.contains(&alternative.expr_id));
+        }
+        self.explored_expr.retain(|task| match task {
+            TaskDesc::OptimizeExpr(expr_id, _) | TaskDesc::OptimizeInput(expr_id, _) => {
+                !evicted.contains(expr_id)
+            }
+        });
+        self.stats.eviction_count += 1;
+        self.stats.evicted_expr_count += evicted.len();
+        evicted.len()
+    }
+
+    /// Drop the predicates no longer referenced by the memo table, see
+    /// [`Memo::compact_predicates`]. Called by [`Self::step_evict`]. Returns the number of
+    /// predicates dropped.
+    pub fn step_compact_predicates(&mut self) -> usize {
+        let dropped = self.memo.compact_predicates();
+        self.stats.compacted_pred_count += dropped;
+        dropped
+    }
+
+    /// The progress of the optimization of `root`.
+    pub fn step_progress(
+        &self,
+        root: impl Into<GroupHandle>,
+        steps: usize,
+    ) -> OptimizationProgress {
+        let root = self.resolve_group(root);
+        let best_cost = match self.memo.get_group_winner(root) {
+            Winner::Full(winner) => Some(winner.total_weighted_cost),
+            _ => None,
+        };
+        OptimizationProgress {
+            steps,
+            groups_explored: self.explored_group.len(),
+            group_count: self.memo.get_all_group_ids().len(),
+            best_cost,
+            budget_consumed: self
+                .prop
+                .partial_explore_iter
+                .map(|budget| steps as f64 / budget.max(1) as f64),
+            budget_used: self.ctx.all_budget_used,
+        }
+    }
+
+    /// The rules whose rewrites the best plan of the group is made of, including the rules
+    /// which produced the expressions the rewrites were applied to.
+    pub fn step_winner_rules(&self, group_id: impl Into<GroupHandle>) -> BTreeSet<RuleId> {
+        let group_id = self.resolve_group(group_id);
+        let mut rules = BTreeSet::new();
+        let mut visited = HashSet::new();
+        let mut groups = vec![group_id];
+        while let Some(group_id) = groups.pop() {
+            if !visited.insert(group_id) {
+                continue;
+            }
+            let Winner::Full(winner) = self.memo.get_group_winner(group_id) else {
+                continue;
+            };
+            let mut expr_id = winner.expr_id;
+            let mut chain = HashSet::new();
+            while let Some(&(rule_id, applied_expr_id)) = self.expr_provenance.get(&expr_id) {
+                if !chain.insert(expr_id) {
+                    break;
+                }
+                rules.insert(rule_id);
+                expr_id = applied_expr_id;
+            }
+            groups.extend(self.memo.get_expr_memoed(winner.expr_id).children.iter());
+        }
+        rules
+    }
+
+    pub fn step_checkpoint(&self, root: impl Into<GroupHandle>) -> MemoCheckpoint<T> {
+        MemoCheckpoint::from_memo(&self.memo, self.resolve_group(root))
+    }
+
+    /// Replay the events recorded with [`OptimizerProperties::enable_tracing`], e.g., to bisect
+    /// the event where a group got a bad winner.
+    pub fn step_trace_replay(&self) -> TraceReplay {
+        TraceReplay::new(&self.stats, &self.memo)
+    }
+
+    /// Take a snapshot of the winners of the memo table, e.g., to compare the winners of two
+    /// optimization stages with [`MemoSnapshot::diff`].
+    pub fn step_snapshot_memo(&self, name: impl Into<String>) -> MemoSnapshot {
+        MemoSnapshot::from_memo(name, &self.memo)
+    }
+
+    /// The costs of the winning plan of a group, operator by operator, e.g., to audit why the
+    /// plan was chosen.
+    pub fn step_cost_breakdown(&self, group_id: impl Into<GroupHandle>) -> Result<CostBreakdown> {
+        CostBreakdown::from_winner(&self.memo, self.cost.as_ref(), self.resolve_group(group_id))
+    }
+
+    pub(super) fn record_provenance(
+        &mut self,
+        produced_expr_id: ExprId,
+        rule_id: RuleId,
+        applied_expr_id: ExprId,
+    ) {
+        self.expr_provenance
+            .entry(produced_expr_id)
+            .or_insert((rule_id, applied_expr_id));
+    }
+
     pub fn is_rule_disabled(&self, rule_id: usize) -> bool {
         self.disabled_rules.contains(&rule_id)
     }
 
-    pub fn dump(&self, mut f: impl std::fmt::Write) -> std::fmt::Result {
-        for group_id in self.memo.get_all_group_ids() {
+    pub fn dump(&self, f: impl std::fmt::Write) -> std::fmt::Result {
+        self.dump_with_options(f, &MemoDumpOptions::default())
+    }
+
+    /// Print the groups of the memo table selected by `options`, with their winners, logical
+    /// properties, expressions and traces.
+    pub fn dump_with_options(
+        &self,
+        mut f: impl std::fmt::Write,
+        options: &MemoDumpOptions,
+    ) -> std::fmt::Result {
+        let group_ids = match options.root {
+            Some(root) => self.dump_reachable_groups(self.resolve_group(root), options),
+            None => self.memo.get_all_group_ids(),
+        };
+        let explored_groups: HashSet<_> = self
+            .explored_group
+            .iter()
+            .map(|group_id| self.memo.reduce_group(*group_id))
+            .collect();
+        for group_id in group_ids {
+            if options.last_stage_only && !explored_groups.contains(&group_id) {
+                continue;
+            }
             let winner_str = match &self.memo.get_group_info(group_id).winner {
                 Winner::Impossible => "winner=<impossible>".to_string(),
                 Winner::Unknown => "winner=<unknown>".to_string(),
@@ -317,7 +726,7 @@ impl<T: NodeType, M: Memo<T>> CascadesOptimizer<T, M> {
                 )?;
             }
             let mut all_predicates = BTreeSet::new();
-            for expr_id in self.memo.get_all_exprs_in_group(group_id) {
+            for expr_id in self.dump_group_exprs(group_id, options) {
                 let memo_node = self.memo.get_expr_memoed(expr_id);
                 for pred in &memo_node.predicates {
                     all_predicates.insert(*pred);
@@ -341,34 +750,81 @@ impl<T: NodeType, M: Memo<T>> CascadesOptimizer<T, M> {
         Ok(())
     }
 
-    /// Optimize a `RelNode`.
-    pub fn step_optimize_rel(&mut self, root_rel: ArcPlanNode<T>) -> Result<GroupId> {
-        trace!(event = "step_optimize_rel", rel = %root_rel);
+    fn dump_group_exprs(&self, group_id: GroupId, options: &MemoDumpOptions) -> Vec<ExprId> {
+        if options.winners_only {
+            match self.memo.get_group_winner(group_id) {
+                Winner::Full(winner) => vec![winner.expr_id],
+                _ => vec![],
+            }
+        } else {
+            self.memo.get_all_exprs_in_group(group_id)
+        }
+    }
+
+    /// The groups reachable from `root` within `options.max_depth` children, ordered by id.
+    fn dump_reachable_groups(&self, root: GroupId, options: &MemoDumpOptions) -> Vec<GroupId> {
+        let mut visited = BTreeSet::new();
+        // breadth first, so that a group is visited at its smallest depth
+        let mut queue = VecDeque::from([(root, 0)]);
+        while let Some((group_id, depth)) = queue.pop_front() {
+            if !visited.insert(group_id) {
+                continue;
+            }
+            if options
+                .max_depth
+                .is_some_and(|max_depth| depth >= max_depth)
+            {
+                continue;
+            }
+            for expr_id in self.dump_group_exprs(group_id, options) {
+                for child in &self.memo.get_expr_memoed(expr_id).children {
+                    queue.push_back((self.memo.reduce_group(*child), depth + 1));
+                }
+            }
+        }
+        visited.into_iter().collect()
+    }
+
+    /// Optimize a `RelNode`. Returns the group of the plan, which follows the merges of the
+    /// later optimization stages.
+    pub fn step_optimize(&mut self, root_rel: ArcPlanNode<T>) -> Result<GroupHandle> {
+        trace!(event = "step_optimize", rel = %root_rel);
         let (group_id, _) = self.add_new_expr(root_rel);
         self.fire_optimize_tasks(group_id)?;
-        Ok(group_id)
+        Ok(GroupHandle::new(group_id))
+    }
+
+    /// Optimize a `RelNode`.
+    #[deprecated(note = "the group id is stale once the group is merged, use `step_optimize`")]
+    pub fn step_optimize_rel(&mut self, root_rel: ArcPlanNode<T>) -> Result<GroupId> {
+        let group = self.step_optimize(root_rel)?;
+        Ok(self.resolve_group(group))
+    }
+
+    /// The current id of a group, see [`GroupHandle`].
+    pub fn resolve_group(&self, group: impl Into<GroupHandle>) -> GroupId {
+        group.into().resolve(&self.memo)
     }
 
     /// Gets the group binding.
     pub fn step_get_optimize_rel(
         &self,
-        group_id: GroupId,
-        meta: &mut Option<PlanNodeMetaMap>,
+        group_id: impl Into<GroupHandle>,
+        meta: &mut Option<PlanAnnotations>,
     ) -> Result<ArcPlanNode<T>> {
+        let group_id = self.resolve_group(group_id);
         let res = self
             .memo
             .get_best_group_binding(group_id, |node, group_id, info| {
                 if let Some(meta) = meta {
-                    let node = node.as_ref() as *const _ as usize;
-                    let node_meta = PlanNodeMeta::new(
+                    let mut annotation = PlanAnnotation::new(
                         group_id,
-                        info.total_weighted_cost,
                         info.total_cost.clone(),
                         info.statistics.clone(),
-                        self.cost.explain_cost(&info.total_cost),
-                        self.cost.explain_statistics(&info.statistics),
+                        self.cost.as_ref(),
                     );
-                    meta.insert(node, node_meta);
+                    annotation.weighted_cost = info.total_weighted_cost;
+                    meta.insert(&node, annotation);
                 }
             });
         if res.is_err() && cfg!(debug_assertions) {
@@ -379,11 +835,95 @@ impl<T: NodeType, M: Memo<T>> CascadesOptimizer<T, M> {
         res
     }
 
-    pub fn fire_optimize_tasks(&mut self, group_id: GroupId) -> Result<()> {
+    /// Computes the cost and the statistics of each node of a physical plan with the current
+    /// cost model, like [`Self::step_get_optimize_rel`] does for the winner, e.g., to explain a
+    /// plan which was not chosen. The plan can combine any expressions of the memo table, so that
+    /// the cost model can look up the properties of their groups. Children which are groups take
+    /// the cost and the statistics of the group winner.
+    pub fn step_compute_plan_meta(
+        &self,
+        plan: ArcPlanNode<T>,
+        meta: &mut PlanAnnotations,
+    ) -> Result<GroupId> {
+        let (group_id, _, _) = self.compute_plan_meta_inner(plan, meta)?;
+        Ok(group_id)
+    }
+
+    fn compute_plan_meta_inner(
+        &self,
+        plan: ArcPlanNode<T>,
+        meta: &mut PlanAnnotations,
+    ) -> Result<(GroupId, Cost, Arc<Statistics>)> {
+        let mut children_group_ids = Vec::with_capacity(plan.children.len());
+        let mut input_cost = Vec::with_capacity(plan.children.len());
+        let mut input_stats = Vec::with_capacity(plan.children.len());
+        for child in &plan.children {
+            let (group_id, cost, stat) = match child {
+                PlanNodeOrGroup::PlanNode(child) => {
+                    self.compute_plan_meta_inner(child.clone(), meta)?
+                }
+                PlanNodeOrGroup::Group(group_id) => match self.get_group_winner(*group_id) {
+                    Winner::Full(info) => {
+                        (*group_id, info.total_cost.clone(), info.statistics.clone())
+                    }
+                    _ => bail!("group {} has no winner", group_id),
+                },
+            };
+            children_group_ids.push(group_id);
+            input_cost.push(cost);
+            input_stats.push(stat);
+        }
+
+        let Some((group_id, expr_id)) = self.memo.find_expr(Arc::new(PlanNode {
+            typ: plan.typ.clone(),
+            children: children_group_ids
+                .iter()
+                .map(|group_id| PlanNodeOrGroup::Group(*group_id))
+                .collect(),
+            predicates: plan.predicates.clone(),
+        })) else {
+            bail!("{} is not in the memo table", plan.typ);
+        };
+        let context = RelNodeContext {
+            expr_id,
+            group_id,
+            children_group_ids,
+        };
+        let cost = self.cost();
+        let input_stats_ref = input_stats.iter().map(|x| x.as_ref()).collect_vec();
+        let operation_cost = cost.compute_operation_cost(
+            &plan.typ,
+            &plan.predicates,
+            &input_stats_ref.iter().map(|x| Some(*x)).collect_vec(),
+            context.clone(),
+            self,
+        );
+        let statistics = Arc::new(cost.derive_statistics(
+            &plan.typ,
+            &plan.predicates,
+            &input_stats_ref,
+            context,
+            self,
+        ));
+        let total_cost = cost.sum(&operation_cost, &input_cost);
+        meta.insert(
+            &plan,
+            PlanAnnotation::new(
+                group_id,
+                total_cost.clone(),
+                statistics.clone(),
+                cost.as_ref(),
+            ),
+        );
+        Ok((group_id, total_cost, statistics))
+    }
+
+    pub fn fire_optimize_tasks(&mut self, group_id: impl Into<GroupHandle>) -> Result<()> {
         use pollster::FutureExt as _;
+        let group_id = self.resolve_group(group_id);
         trace!(event = "fire_optimize_tasks", root_group_id = %group_id);
         self.stage += 1;
-        let mut task = TaskContext::new(self, self.stage);
+        let mut task = TaskContext::new(self, self.stage, group_id);
         // 32MB stack for the optimization process, TODO: reduce memory footprint
         stacker::grow(32 * 1024 * 1024, || {
             let fut: Pin<Box<dyn Future<Output = ()>>> = Box::pin(task.fire_optimize(group_id));
@@ -392,6 +932,30 @@ impl<T: NodeType, M: Memo<T>> CascadesOptimizer<T, M> {
         Ok(())
     }
 
+    /// Optimizes the group like [`Self::fire_optimize_tasks`], as an iterator of the plans which
+    /// improve on the best plan of the group found so far. The last plan is the winner of the
+    /// group. Dropping the iterator early keeps the best plan found so far as the winner, and
+    /// [`Self::step_next_stage`] has to be called before optimizing the group again.
+    pub fn optimize_incremental(
+        &mut self,
+        group_id: impl Into<GroupHandle>,
+    ) -> IncrementalOptimization<'_, T> {
+        let group_id = self.resolve_group(group_id);
+        trace!(event = "optimize_incremental", root_group_id = %group_id);
+        self.stage += 1;
+        let stage = self.stage;
+        let improvements = Rc::new(RefCell::new(None));
+        let task_improvements = improvements.clone();
+        IncrementalOptimization::new(
+            Box::pin(async move {
+                let mut task = TaskContext::new(self, stage, group_id);
+                task.fire_optimize_incremental(group_id, task_improvements)
+                    .await;
+            }),
+            improvements,
+        )
+    }
+
     fn optimize_inner(&mut self, root_rel: ArcPlanNode<T>) -> Result<ArcPlanNode<T>> {
         let (group_id, _) = self.add_new_expr(root_rel);
 
@@ -427,16 +991,123 @@ impl<T: NodeType, M: Memo<T>> CascadesOptimizer<T, M> {
         self.memo.update_group_info(group_id, GroupInfo { winner });
     }
 
+    /// Validates the cost of a single operation computed by the cost model, see
+    /// [`OptimizerProperties::cost_floor`].
+    pub(super) fn validate_operation_cost(&mut self, expr_id: ExprId, operation_cost: &mut Cost) {
+        let Err(err) = operation_cost.validate() else {
+            return;
+        };
+        if let Some(floor) = self.prop.cost_floor {
+            tracing::warn!(
+                "clamping the cost of expr_id={} {}: {}",
+                expr_id,
+                self.cost.explain_cost(operation_cost),
+                err
+            );
+            operation_cost.clamp(floor);
+            self.stats.invalid_cost_count += 1;
+        } else if cfg!(debug_assertions) {
+            panic!("cost model produced an {} for expr_id={}", err, expr_id);
+        }
+    }
+
+    /// Validates that accumulating the costs of the inputs into the cost of an operation does
+    /// not make it cheaper than any of them.
+    pub(super) fn validate_total_cost(
+        &self,
+        expr_id: ExprId,
+        operation_cost: &Cost,
+        input_costs: &[Cost],
+        total_cost: &Cost,
+    ) {
+        let total_weighted_cost = self.cost.weighted_cost(total_cost);
+        let Some(part) = std::iter::once(operation_cost)
+            .chain(input_costs)
+            .find(|part| {
+                self.prop
+                    .cost_comparator
+                    .is_better(total_weighted_cost, self.cost.weighted_cost(part))
+            })
+        else {
+            return;
+        };
+        let message = format!(
+            "the total cost {} of expr_id={} is lower than the cost {} it accumulates",
+            self.cost.explain_cost(total_cost),
+            expr_id,
+            self.cost.explain_cost(part)
+        );
+        if self.prop.cost_floor.is_some() {
+            tracing::warn!("{}", message);
+        } else if cfg!(debug_assertions) {
+            panic!("{}", message);
+        }
+    }
+
+    /// Retain a costed physical expression of the group if it is among the cheapest ones, see
+    /// [`OptimizerProperties::retain_alternatives`].
+    pub(super) fn record_alternative(&mut self, group_id: GroupId, alternative: &WinnerInfo) {
+        let Some(k) = self.prop.retain_alternatives else {
+            return;
+        };
+        let cost_comparator = self.prop.cost_comparator;
+        let alternatives = self.group_alternatives.entry(group_id).or_default();
+        // An expression is costed again when its children get cheaper winners.
+        alternatives.retain(|other| other.expr_id != alternative.expr_id);
+        alternatives.push(alternative.clone());
+        alternatives.sort_by(|a, b| {
+            cost_comparator
+                .compare(a.total_weighted_cost, b.total_weighted_cost)
+                .then(a.expr_id.cmp(&b.expr_id))
+        });
+        alternatives.truncate(k);
+    }
+
+    /// The `k` cheapest physical expressions of the group, including the winner, ordered by cost.
+    /// Only available if [`OptimizerProperties::retain_alternatives`] is set; the expressions
+    /// pruned by their cost bound are never costed fully and are not retained.
+    pub fn get_group_alternatives(
+        &self,
+        group_id: impl Into<GroupHandle>,
+        k: usize,
+    ) -> Vec<WinnerInfo> {
+        let group_id = self.resolve_group(group_id);
+        let mut alternatives: Vec<WinnerInfo> = Vec::new();
+        // The alternatives of merged groups are recorded under the ids before the merge.
+        for (_, group_alternatives) in self
+            .group_alternatives
+            .iter()
+            .filter(|(other, _)| self.memo.reduce_group(**other) == group_id)
+        {
+            for alternative in group_alternatives {
+                if !alternatives
+                    .iter()
+                    .any(|other| other.expr_id == alternative.expr_id)
+                {
+                    alternatives.push(alternative.clone());
+                }
+            }
+        }
+        alternatives.sort_by(|a, b| {
+            self.prop
+                .cost_comparator
+                .compare(a.total_weighted_cost, b.total_weighted_cost)
+                .then(a.expr_id.cmp(&b.expr_id))
+        });
+        alternatives.truncate(k);
+        alternatives
+    }
+
     /// Get the properties of a Cascades group
     /// P is the type of the property you expect
     /// idx is the idx of the property you want. The order of properties is defined
     ///   by the property_builders parameter in CascadesOptimizer::new()
     pub fn get_property_by_group<P: LogicalPropertyBuilder<T>>(
         &self,
-        group_id: GroupId,
+        group_id: impl Into<GroupHandle>,
         idx: usize,
     ) -> P::Prop {
-        self.memo.get_group(group_id).properties[idx]
+        self.memo.get_group(self.resolve_group(group_id)).properties[idx]
             .as_any()
             .downcast_ref::<P::Prop>()
             .unwrap()
@@ -0,0 +1,59 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Progress reports and cancellation of long-running optimizations, so that a UI can show a
+//! progress indicator and stop the search to use the best plan found so far.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The progress of the optimization of a query, reported to the [`ProgressHook`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptimizationProgress {
+    /// The number of optimizer tasks run so far.
+    pub steps: usize,
+    /// The number of groups explored so far.
+    pub groups_explored: usize,
+    /// The number of groups in the memo table.
+    pub group_count: usize,
+    /// The weighted cost of the best plan of the root group so far, if one has been found.
+    pub best_cost: Option<f64>,
+    /// The fraction of the iteration budget consumed, if there is one. It may exceed 1 as the
+    /// optimizer still implements the remaining groups after the budget is used.
+    pub budget_consumed: Option<f64>,
+    /// Whether the optimizer has stopped exploring and is finishing with the best plan.
+    pub budget_used: bool,
+}
+
+/// Calls `callback` with the progress of the optimization every `interval` optimizer tasks.
+pub struct ProgressHook {
+    pub interval: usize,
+    pub callback: Box<dyn FnMut(&OptimizationProgress) + Send + Sync>,
+}
+
+/// Stops a running optimization: once cancelled, the optimizer applies no more transformation
+/// rules and returns the best plan found so far. The clones of a token share its state, so it
+/// can be cancelled from another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Un-cancel the token, so that it can be reused for the next optimization.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
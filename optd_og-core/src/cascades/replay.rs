@@ -0,0 +1,199 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Replays the decisions recorded with [`super::OptimizerProperties::enable_tracing`], to find
+//! out where a bad plan choice originated, see [`super::CascadesOptimizer::step_trace_replay`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::optimizer::{OptimizerTrace, RuleId};
+use super::{CascadesStats, ExprId, GroupId, Memo};
+use crate::nodes::NodeType;
+
+/// The state of the memo table reconstructed from the first events of a trace.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayState {
+    /// The events replayed.
+    pub steps: usize,
+    /// The expressions produced by the rules in each group.
+    pub produced_exprs: BTreeMap<GroupId, BTreeSet<ExprId>>,
+    /// The rule which produced each expression, and the expression it was applied to.
+    pub provenance: BTreeMap<ExprId, (RuleId, ExprId)>,
+    /// The winner of each group, and its weighted cost.
+    pub winners: BTreeMap<GroupId, (ExprId, f64)>,
+    /// The physical expressions pruned in each group.
+    pub pruned_exprs: BTreeMap<GroupId, BTreeSet<ExprId>>,
+    /// The groups merged by applying a rule to an expression, with the rule.
+    pub merges: Vec<(GroupId, ExprId, RuleId)>,
+}
+
+impl ReplayState {
+    fn apply(&mut self, group_id: GroupId, event: &OptimizerTrace) {
+        self.steps += 1;
+        match event {
+            OptimizerTrace::DecideWinner { .. } => {}
+            OptimizerTrace::ApplyRule {
+                applied_expr_id,
+                produced_expr_id,
+                rule_id,
+                ..
+            } => {
+                self.produced_exprs
+                    .entry(group_id)
+                    .or_default()
+                    .insert(*produced_expr_id);
+                self.provenance
+                    .insert(*produced_expr_id, (*rule_id, *applied_expr_id));
+            }
+            OptimizerTrace::MergeGroup {
+                applied_expr_id,
+                rule_id,
+                ..
+            } => {
+                self.merges.push((group_id, *applied_expr_id, *rule_id));
+            }
+            OptimizerTrace::UpdateWinner {
+                expr_id,
+                total_weighted_cost,
+                ..
+            } => {
+                self.winners
+                    .insert(group_id, (*expr_id, *total_weighted_cost));
+            }
+            OptimizerTrace::Prune { expr_id, .. } => {
+                self.pruned_exprs
+                    .entry(group_id)
+                    .or_default()
+                    .insert(*expr_id);
+            }
+        }
+    }
+
+    /// The winner of the group after the replayed events.
+    pub fn winner(&self, group_id: GroupId) -> Option<ExprId> {
+        self.winners.get(&group_id).map(|(expr_id, _)| *expr_id)
+    }
+}
+
+/// The events of a trace in the order they were recorded. The groups of the events are resolved
+/// in the memo table the trace is replayed against, so that the events of merged groups are
+/// replayed on the group they were merged into.
+pub struct TraceReplay {
+    events: Vec<(GroupId, OptimizerTrace)>,
+}
+
+impl TraceReplay {
+    pub fn new<T: NodeType>(stats: &CascadesStats, memo: &(impl Memo<T> + ?Sized)) -> Self {
+        let mut events = stats
+            .trace
+            .iter()
+            .flat_map(|(group_id, trace)| {
+                let group_id = memo.reduce_group(*group_id);
+                trace.iter().map(move |event| (group_id, event.clone()))
+            })
+            .collect::<Vec<_>>();
+        events.sort_by_key(|(_, event)| event.stage_step());
+        Self { events }
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &OptimizerTrace> {
+        self.events.iter().map(|(_, event)| event)
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The state after replaying the first `steps` events.
+    pub fn state_at(&self, steps: usize) -> ReplayState {
+        let mut state = ReplayState::default();
+        for (group_id, event) in self.events.iter().take(steps) {
+            state.apply(*group_id, event);
+        }
+        state
+    }
+
+    /// Finds the first number of replayed events after which `is_bad` holds, assuming that it
+    /// keeps holding once it does, e.g., the event where a group got a bad winner. Returns
+    /// `None` if it does not hold for the whole trace.
+    pub fn bisect(&self, mut is_bad: impl FnMut(&ReplayState) -> bool) -> Option<usize> {
+        if !is_bad(&self.state_at(self.len())) {
+            return None;
+        }
+        if is_bad(&self.state_at(0)) {
+            return Some(0);
+        }
+        let (mut good, mut bad) = (0, self.len());
+        while bad - good > 1 {
+            let mid = good + (bad - good) / 2;
+            if is_bad(&self.state_at(mid)) {
+                bad = mid;
+            } else {
+                good = mid;
+            }
+        }
+        Some(bad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cascades::NaiveMemo;
+    use crate::tests::common::scan;
+
+    #[test]
+    fn bisect_winner_update() {
+        let mut memo = NaiveMemo::new(Arc::new([]));
+        let (group_id, _) = memo.add_new_expr(scan("t1"));
+        let (other_group_id, _) = memo.add_new_expr(scan("t2"));
+        let mut stats = CascadesStats::default();
+        let update_winner = |step, expr_id, total_weighted_cost| OptimizerTrace::UpdateWinner {
+            stage: 1,
+            step,
+            group_id,
+            expr_id: ExprId(expr_id),
+            total_weighted_cost,
+        };
+        stats.trace.insert(
+            group_id,
+            vec![
+                update_winner(1, 1, 10.0),
+                update_winner(3, 3, 5.0),
+                update_winner(4, 4, 1.0),
+            ],
+        );
+        stats.trace.insert(
+            other_group_id,
+            vec![OptimizerTrace::Prune {
+                stage: 1,
+                step: 2,
+                group_id: other_group_id,
+                expr_id: ExprId(2),
+                cost_so_far: 3.0,
+                upper_bound: 2.0,
+            }],
+        );
+        let replay = TraceReplay::new(&stats, &memo);
+        assert_eq!(replay.len(), 4);
+        let state = replay.state_at(2);
+        assert_eq!(state.winner(group_id), Some(ExprId(1)));
+        assert!(state.pruned_exprs[&other_group_id].contains(&ExprId(2)));
+        assert_eq!(
+            replay.bisect(|state| state.winner(group_id) == Some(ExprId(4))),
+            Some(4)
+        );
+        assert_eq!(
+            replay.bisect(|state| state.winner(group_id) == Some(ExprId(2))),
+            None
+        );
+    }
+}
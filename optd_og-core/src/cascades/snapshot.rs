@@ -0,0 +1,259 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Named snapshots of the winners of the memo table, e.g., after each optimization stage, and
+//! the differences between them, to find out why a later stage chose a worse plan.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use super::memo::Winner;
+use super::{ExprId, GroupId, Memo};
+use crate::cost::CostComparator;
+use crate::nodes::NodeType;
+
+/// The winner of a group at the time of a snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnapshotWinner {
+    pub expr_id: ExprId,
+    /// The memoized expression, e.g., `(PhysicalHashJoin !1 !2 P3)`.
+    pub expr: String,
+    pub weighted_cost: f64,
+}
+
+impl Display for SnapshotWinner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expr_id={} weighted_cost={} | {}",
+            self.expr_id, self.weighted_cost, self.expr
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnapshotGroup {
+    /// The memoized expressions of the group.
+    pub exprs: BTreeMap<ExprId, String>,
+    /// `None` if the group has no winner yet or no physical plan.
+    pub winner: Option<SnapshotWinner>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoSnapshot {
+    pub name: String,
+    pub groups: BTreeMap<GroupId, SnapshotGroup>,
+}
+
+impl MemoSnapshot {
+    pub fn from_memo<T: NodeType>(name: impl Into<String>, memo: &impl Memo<T>) -> Self {
+        let groups = memo
+            .get_all_group_ids()
+            .into_iter()
+            .map(|group_id| {
+                let exprs = memo
+                    .get_all_exprs_in_group(group_id)
+                    .into_iter()
+                    .map(|expr_id| (expr_id, memo.get_expr_memoed(expr_id).to_string()))
+                    .collect();
+                let winner = match memo.get_group_winner(group_id) {
+                    Winner::Full(winner) => Some(SnapshotWinner {
+                        expr_id: winner.expr_id,
+                        expr: memo.get_expr_memoed(winner.expr_id).to_string(),
+                        weighted_cost: winner.total_weighted_cost,
+                    }),
+                    Winner::Unknown | Winner::Impossible => None,
+                };
+                (group_id, SnapshotGroup { exprs, winner })
+            })
+            .collect();
+        Self {
+            name: name.into(),
+            groups,
+        }
+    }
+
+    /// What changed in the memo table from this snapshot to `later`. Groups merged in between
+    /// are compared under the id they are merged into.
+    pub fn diff(&self, later: &MemoSnapshot) -> MemoDiff {
+        let cost_comparator = CostComparator::default();
+        let known_exprs = self
+            .groups
+            .values()
+            .flat_map(|group| group.exprs.keys())
+            .collect::<BTreeSet<_>>();
+        let mut changed_winners = Vec::new();
+        let mut new_exprs = Vec::new();
+        for (group_id, group) in &later.groups {
+            for (expr_id, expr) in &group.exprs {
+                if !known_exprs.contains(expr_id) {
+                    new_exprs.push((*group_id, *expr_id, expr.clone()));
+                }
+            }
+            let Some(before) = self.groups.get(group_id) else {
+                continue;
+            };
+            let changed = match (&before.winner, &group.winner) {
+                (Some(before), Some(after)) => {
+                    before.expr_id != after.expr_id
+                        || cost_comparator
+                            .compare(before.weighted_cost, after.weighted_cost)
+                            .is_ne()
+                }
+                (None, None) => false,
+                _ => true,
+            };
+            if changed {
+                changed_winners.push(WinnerChange {
+                    group_id: *group_id,
+                    before: before.winner.clone(),
+                    after: group.winner.clone(),
+                });
+            }
+        }
+        MemoDiff {
+            from: self.name.clone(),
+            to: later.name.clone(),
+            changed_winners,
+            new_exprs,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WinnerChange {
+    pub group_id: GroupId,
+    pub before: Option<SnapshotWinner>,
+    pub after: Option<SnapshotWinner>,
+}
+
+impl WinnerChange {
+    /// How much the cost of the group changed, if it has a winner in both snapshots.
+    pub fn cost_delta(&self) -> Option<f64> {
+        match (&self.before, &self.after) {
+            (Some(before), Some(after)) => Some(after.weighted_cost - before.weighted_cost),
+            _ => None,
+        }
+    }
+
+    /// Whether the winner expression changed, rather than only its cost.
+    pub fn winner_changed(&self) -> bool {
+        self.before.as_ref().map(|winner| winner.expr_id)
+            != self.after.as_ref().map(|winner| winner.expr_id)
+    }
+}
+
+/// The differences between two snapshots of the memo table, see [`MemoSnapshot::diff`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoDiff {
+    pub from: String,
+    pub to: String,
+    /// The groups whose winner or its cost changed, ordered by group id.
+    pub changed_winners: Vec<WinnerChange>,
+    /// The expressions added to the memo table, with the groups they belong to.
+    pub new_exprs: Vec<(GroupId, ExprId, String)>,
+}
+
+impl MemoDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changed_winners.is_empty() && self.new_exprs.is_empty()
+    }
+}
+
+impl Display for MemoDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "memo diff {} -> {}: {} winners changed, {} new exprs",
+            self.from,
+            self.to,
+            self.changed_winners.len(),
+            self.new_exprs.len()
+        )?;
+        let winner_str = |winner: &Option<SnapshotWinner>| match winner {
+            Some(winner) => winner.to_string(),
+            None => "<none>".to_string(),
+        };
+        for change in &self.changed_winners {
+            write!(f, "  group_id={}", change.group_id)?;
+            if let Some(delta) = change.cost_delta() {
+                write!(f, " cost_delta={}", delta)?;
+            }
+            writeln!(f)?;
+            writeln!(f, "    before: {}", winner_str(&change.before))?;
+            writeln!(f, "    after: {}", winner_str(&change.after))?;
+        }
+        for (group_id, expr_id, expr) in &self.new_exprs {
+            writeln!(
+                f,
+                "  new expr_id={} in group_id={} | {}",
+                expr_id, group_id, expr
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cascades::memo::{GroupInfo, WinnerInfo};
+    use crate::cascades::NaiveMemo;
+    use crate::cost::{Cost, Statistics};
+    use crate::nodes::Value;
+    use crate::tests::common::{expr, join, scan, MemoTestRelTyp};
+
+    fn set_winner(
+        memo: &mut NaiveMemo<MemoTestRelTyp>,
+        group_id: GroupId,
+        expr_id: ExprId,
+        cost: f64,
+    ) {
+        memo.update_group_info(
+            group_id,
+            GroupInfo {
+                winner: Winner::Full(WinnerInfo {
+                    expr_id,
+                    total_weighted_cost: cost,
+                    operation_weighted_cost: cost,
+                    total_cost: Cost(vec![cost]),
+                    operation_cost: Cost(vec![cost]),
+                    statistics: Arc::new(Statistics(Box::new(()))),
+                }),
+            },
+        );
+    }
+
+    #[test]
+    fn diff_snapshots() {
+        let mut memo = NaiveMemo::new(Arc::new([]));
+        let (join_group, join_expr) =
+            memo.add_new_expr(join(scan("t1"), scan("t2"), expr(Value::Bool(true))));
+        set_winner(&mut memo, join_group, join_expr, 10.0);
+        let first = MemoSnapshot::from_memo("stage1", &memo);
+        assert!(first.diff(&first).is_empty());
+
+        let commuted_expr = memo
+            .add_expr_to_group(
+                join(scan("t2"), scan("t1"), expr(Value::Bool(true))).into(),
+                join_group,
+            )
+            .unwrap();
+        set_winner(&mut memo, join_group, commuted_expr, 8.0);
+        let second = MemoSnapshot::from_memo("stage2", &memo);
+
+        let diff = first.diff(&second);
+        assert_eq!(diff.new_exprs.len(), 1);
+        assert_eq!(diff.new_exprs[0].1, commuted_expr);
+        assert_eq!(diff.changed_winners.len(), 1);
+        let change = &diff.changed_winners[0];
+        assert_eq!(change.group_id, join_group);
+        assert!(change.winner_changed());
+        assert_eq!(change.cost_delta(), Some(-2.0));
+        assert!(diff.to_string().contains("cost_delta=-2"));
+    }
+}
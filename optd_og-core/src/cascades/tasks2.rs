@@ -1,8 +1,12 @@
 use std::sync::Arc;
+use std::time::Instant;
 
 use itertools::Itertools;
 use tracing::trace;
 
+use super::checkpoint::MemoCheckpoint;
+use super::exploration::{ExplorationStrategy, RuleSampler};
+use super::incremental::{ImprovementSlot, PlanImprovement, YieldNow};
 use super::memo::MemoPlanNode;
 use super::rule_match::match_and_pick_expr;
 use super::{optimizer::RuleId, CascadesOptimizer, ExprId, GroupId, Memo};
@@ -12,7 +16,7 @@ use crate::cascades::{
     RelNodeContext,
 };
 use crate::cost::{Cost, Statistics};
-use crate::nodes::ArcPredNode;
+use crate::nodes::{ArcPlanNode, ArcPredNode};
 use crate::{nodes::NodeType, rules::RuleMatcher};
 
 struct SearchContext {
@@ -28,6 +32,18 @@ pub struct TaskContext<'a, T: NodeType, M: Memo<T>> {
     steps: usize,
     /// Counter of trace produced, used in the traces
     trace_steps: usize,
+    /// The group being optimized, recorded in the memo checkpoints
+    root_group_id: GroupId,
+    /// Draws the transformation rules applied, with the randomized exploration strategy
+    sampler: Option<RuleSampler>,
+    /// Receives the new best plans of the root group, with the incremental optimization
+    improvements: Option<ImprovementSlot<T>>,
+    /// The last plan handed over to the incremental optimization
+    last_improvement: Option<ArcPlanNode<T>>,
+    /// Whether the winner of the root group changed since the last plan was handed over
+    root_improved: bool,
+    /// When the tasks started, to enforce the timeout
+    started_at: Instant,
 }
 
 /// Ensures we don't run into cycles / dead loops.
@@ -38,12 +54,39 @@ pub enum TaskDesc {
 }
 
 impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
-    pub fn new(optimizer: &'a mut CascadesOptimizer<T, M>, stage: usize) -> Self {
+    pub fn new(
+        optimizer: &'a mut CascadesOptimizer<T, M>,
+        stage: usize,
+        root_group_id: GroupId,
+    ) -> Self {
+        let sampler = match optimizer.prop.exploration_strategy {
+            ExplorationStrategy::Exhaustive => None,
+            ExplorationStrategy::Randomized { seed, sample_rate } => {
+                let max_promise = optimizer
+                    .rules()
+                    .iter()
+                    .filter(|rule| !rule.is_impl_rule())
+                    .map(|rule| rule.promise())
+                    .fold(0.0, f64::max);
+                // each task of the optimizer draws different rules
+                Some(RuleSampler::new(
+                    seed ^ stage as u64,
+                    sample_rate,
+                    max_promise,
+                ))
+            }
+        };
         Self {
             stage,
             optimizer,
             steps: 0,
             trace_steps: 0,
+            root_group_id,
+            sampler,
+            improvements: None,
+            last_improvement: None,
+            root_improved: false,
+            started_at: Instant::now(),
         }
     }
 
@@ -56,6 +99,51 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
         .await;
     }
 
+    /// Optimizes the group like [`Self::fire_optimize`], and hands each new best plan of the
+    /// group over to `improvements`, pausing until the plan is consumed.
+    pub async fn fire_optimize_incremental(
+        &mut self,
+        group_id: GroupId,
+        improvements: ImprovementSlot<T>,
+    ) {
+        self.improvements = Some(improvements);
+        self.fire_optimize(group_id).await;
+        // The winners of the children may have improved after the root group was last costed.
+        self.root_improved = true;
+        self.report_improvement().await;
+    }
+
+    async fn report_improvement(&mut self) {
+        if !std::mem::take(&mut self.root_improved) {
+            return;
+        }
+        let Some(improvements) = self.improvements.clone() else {
+            return;
+        };
+        let memo = self.optimizer.memo();
+        let root_group_id = memo.reduce_group(self.root_group_id);
+        let Some(cost) = memo
+            .get_group_winner(root_group_id)
+            .as_full_winner()
+            .map(|winner| winner.total_weighted_cost)
+        else {
+            return;
+        };
+        let Ok(plan) = memo.get_best_group_binding(root_group_id, |_, _, _| {}) else {
+            return;
+        };
+        if self.last_improvement.as_ref() == Some(&plan) {
+            return;
+        }
+        *improvements.borrow_mut() = Some(PlanImprovement {
+            plan: plan.clone(),
+            cost,
+            steps: self.steps,
+        });
+        self.last_improvement = Some(plan);
+        YieldNow::default().await;
+    }
+
     async fn optimize_group(&mut self, ctx: SearchContext) {
         Box::pin(self.optimize_group_inner(ctx)).await;
     }
@@ -179,6 +267,15 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
             {
                 break;
             }
+            if !rule.is_impl_rule()
+                && self
+                    .sampler
+                    .as_mut()
+                    .is_some_and(|sampler| !sampler.sample(rule.promise()))
+            {
+                self.optimizer.stats.sampled_out_rule_count += 1;
+                continue;
+            }
             if top_matches(rule.matcher(), expr.typ.clone()) {
                 for &input_group_id in &expr.children {
                     self.explore_group(SearchContext {
@@ -328,30 +425,38 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
                 break;
             }
 
-            trace!(event = "before_apply_rule", task = "apply_rule", input_binding=%binding);			
-			let applied = rule.apply(self.optimizer, binding);
+            trace!(event = "before_apply_rule", task = "apply_rule", input_binding=%binding);
+            let applied = if self.optimizer.prop.enable_tracing {
+                // Per-rule spans are only emitted with tracing enabled, as they are too
+                // fine-grained for the default planning spans.
+                let _span = tracing::info_span!(
+                    "optd_og.rule",
+                    rule = rule.name(),
+                    group_id = %group_id,
+                    expr_id = %expr_id,
+                    stage = self.stage
+                )
+                .entered();
+                rule.apply(self.optimizer, binding)
+            } else {
+                rule.apply(self.optimizer, binding)
+            };
             for expr in applied {
                 trace!(event = "after_apply_rule", task = "apply_rule", output_binding=%expr);
                 // TODO: remove clone in the below line
-                if let Some(produced_expr_id) =
-                    self.optimizer.add_expr_to_group(expr.clone(), group_id)
-                {
-                    if self.optimizer.prop.enable_tracing {
-                        self.trace_steps += 1;
-                        self.optimizer
-                            .stats
-                            .trace
-                            .entry(group_id)
-                            .or_default()
-                            .push(OptimizerTrace::ApplyRule {
-                                stage: self.stage,
-                                step: self.trace_steps,
-                                group_id,
-                                applied_expr_id: expr_id,
-                                produced_expr_id,
-                                rule_id,
-                            });
-                    }
+                let produced_expr_id = self.optimizer.add_expr_to_group(expr.clone(), group_id);
+                self.verify_memo_integrity(rule.name(), expr_id);
+                if let Some(produced_expr_id) = produced_expr_id {
+                    self.optimizer
+                        .record_provenance(produced_expr_id, rule_id, expr_id);
+                    self.record_trace(group_id, |stage, step| OptimizerTrace::ApplyRule {
+                        stage,
+                        step,
+                        group_id,
+                        applied_expr_id: expr_id,
+                        produced_expr_id,
+                        rule_id,
+                    });
                     let typ = expr.unwrap_typ();
                     if typ.is_logical() {
                         self.optimize_expr(
@@ -375,6 +480,13 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
                     }
                     trace!(event = "apply_rule", expr_id = %expr_id, rule_id = %rule_id, new_expr_id = %expr_id);
                 } else {
+                    self.record_trace(group_id, |stage, step| OptimizerTrace::MergeGroup {
+                        stage,
+                        step,
+                        group_id,
+                        applied_expr_id: expr_id,
+                        rule_id,
+                    });
                     trace!(event = "apply_rule", expr_id = %expr_id, rule_id = %rule_id, "triggered group merge");
                 }
             }
@@ -382,16 +494,48 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
         trace!(event = "task_end", task = "apply_rule", expr_id = %expr_id, rule_id = %rule_id);
     }
 
+    /// Record an event in the trace of the group, if tracing is enabled.
+    fn record_trace(
+        &mut self,
+        group_id: GroupId,
+        event: impl FnOnce(usize, usize) -> OptimizerTrace,
+    ) {
+        if self.optimizer.prop.enable_tracing {
+            self.trace_steps += 1;
+            let event = event(self.stage, self.trace_steps);
+            self.optimizer
+                .stats
+                .trace
+                .entry(group_id)
+                .or_default()
+                .push(event);
+        }
+    }
+
+    fn verify_memo_integrity(&self, rule_name: &str, expr_id: ExprId) {
+        if !self.optimizer.prop.verify_memo_integrity {
+            return;
+        }
+        if let Err(err) = self.optimizer.memo().verify_integrity() {
+            panic!(
+                "memo table corrupted after applying {} to {}: {:#}",
+                rule_name, expr_id, err
+            );
+        }
+    }
+
     fn update_winner_if_better(&mut self, group_id: GroupId, proposed_winner: WinnerInfo) {
-        let mut update_cost = false;
+        self.optimizer.record_alternative(group_id, &proposed_winner);
         let current_winner = self.optimizer.get_group_winner(group_id);
-        if let Some(winner) = current_winner.as_full_winner() {
-            if winner.total_weighted_cost > proposed_winner.total_weighted_cost {
-                update_cost = true;
-            }
+        let update_cost = if let Some(winner) = current_winner.as_full_winner() {
+            // Costs within the tolerance keep the current winner, as with exactly equal costs.
+            self.optimizer.prop.cost_comparator.is_better(
+                proposed_winner.total_weighted_cost,
+                winner.total_weighted_cost,
+            )
         } else {
-            update_cost = true;
-        }
+            true
+        };
         if update_cost {
             tracing::trace!(
                 event = "update_winner",
@@ -400,8 +544,20 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
                 total_weighted_cost = %proposed_winner.total_weighted_cost,
                 operation_weighted_cost = %proposed_winner.operation_weighted_cost,
             );
+            self.record_trace(group_id, |stage, step| OptimizerTrace::UpdateWinner {
+                stage,
+                step,
+                group_id,
+                expr_id: proposed_winner.expr_id,
+                total_weighted_cost: proposed_winner.total_weighted_cost,
+            });
             self.optimizer
                 .update_group_winner(group_id, Winner::Full(proposed_winner));
+            if self.improvements.is_some() {
+                let memo = self.optimizer.memo();
+                self.root_improved |=
+                    memo.reduce_group(group_id) == memo.reduce_group(self.root_group_id);
+            }
         }
     }
 
@@ -443,18 +599,37 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
             );
             children_winners.push(winner.map(|x| x.expr_id));
         }
-        let input_stats_ref = input_stats
-            .iter()
-            .map(|x| x.as_ref().map(|y| y.as_ref()))
-            .collect_vec();
-        let operation_cost = cost.compute_operation_cost(
-            &expr.typ,
-            predicates,
-            &input_stats_ref,
-            context.clone(),
-            self.optimizer,
-        );
+        let operation_cost = if let Some(operation_cost) = self
+            .optimizer
+            .cost_cache
+            .get_operation_cost(expr_id, &input_stats)
+        {
+            self.optimizer.stats.cost_cache_hit_count += 1;
+            operation_cost
+        } else {
+            let input_stats_ref = input_stats
+                .iter()
+                .map(|x| x.as_ref().map(|y| y.as_ref()))
+                .collect_vec();
+            let mut operation_cost = cost.compute_operation_cost(
+                &expr.typ,
+                predicates,
+                &input_stats_ref,
+                context.clone(),
+                self.optimizer,
+            );
+            self.optimizer
+                .validate_operation_cost(expr_id, &mut operation_cost);
+            self.optimizer.cost_cache.insert_operation_cost(
+                expr_id,
+                &input_stats,
+                operation_cost.clone(),
+            );
+            operation_cost
+        };
         let total_cost = cost.sum(&operation_cost, &input_cost);
+        self.optimizer
+            .validate_total_cost(expr_id, &operation_cost, &input_cost, &total_cost);
         (
             input_stats,
             input_cost,
@@ -523,8 +698,21 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
                     current_processing = %input_group_idx,
                     total_child_groups = %expr.children.len());
                 if let Some(upper_bound) = upper_bound {
-                    if upper_bound < cost_so_far {
+                    if self
+                        .optimizer
+                        .prop
+                        .cost_comparator
+                        .is_better(upper_bound, cost_so_far)
+                    {
                         // allow strictly == because we want to replan one of the child
+                        self.record_trace(group_id, |stage, step| OptimizerTrace::Prune {
+                            stage,
+                            step,
+                            group_id,
+                            expr_id,
+                            cost_so_far,
+                            upper_bound,
+                        });
                         trace!(event = "task_finish", task = "optimize_inputs", expr_id = %expr_id, result = "pruned");
                         self.optimizer.mark_task_end(&desc);
                         return;
@@ -557,25 +745,38 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
         // Compute everything again
         let (input_stats, _, total_cost, operation_cost, children_winner) =
             self.gather_statistics_and_costs(group_id, expr_id, &expr, &predicates);
-        let input_stats_ref = input_stats
-            .iter()
-            .map(|x| {
-                x.as_ref()
-                    .expect("stats should be available for full winners")
-                    .as_ref()
-            })
-            .collect_vec();
-        let statistics = Arc::new(cost.derive_statistics(
-            &expr.typ,
-            &predicates,
-            &input_stats_ref,
-            RelNodeContext {
-                expr_id,
-                group_id,
-                children_group_ids: expr.children.clone(),
-            },
-            self.optimizer,
-        ));
+        let statistics = if let Some(statistics) = self
+            .optimizer
+            .cost_cache
+            .get_statistics(expr_id, &input_stats)
+        {
+            self.optimizer.stats.cost_cache_hit_count += 1;
+            statistics
+        } else {
+            let input_stats_ref = input_stats
+                .iter()
+                .map(|x| {
+                    x.as_ref()
+                        .expect("stats should be available for full winners")
+                        .as_ref()
+                })
+                .collect_vec();
+            let statistics = Arc::new(cost.derive_statistics(
+                &expr.typ,
+                &predicates,
+                &input_stats_ref,
+                RelNodeContext {
+                    expr_id,
+                    group_id,
+                    children_group_ids: expr.children.clone(),
+                },
+                self.optimizer,
+            ));
+            self.optimizer
+                .cost_cache
+                .insert_statistics(expr_id, &input_stats, statistics.clone());
+            statistics
+        };
         let proposed_winner = WinnerInfo {
             expr_id,
             total_cost: total_cost.clone(),
@@ -584,27 +785,20 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
             operation_weighted_cost: cost.weighted_cost(&operation_cost),
             statistics,
         };
-        if self.optimizer.prop.enable_tracing {
-            self.trace_steps += 1;
-            self.optimizer
-                .stats
-                .trace
-                .entry(group_id)
-                .or_default()
-                .push(OptimizerTrace::DecideWinner {
-                    stage: self.stage,
-                    step: self.trace_steps,
-                    group_id,
-                    proposed_winner_info: proposed_winner.clone(),
-                    children_winner: children_winner.into_iter().map(|x| x.unwrap()).collect(),
-                });
-        }
+        self.record_trace(group_id, |stage, step| OptimizerTrace::DecideWinner {
+            stage,
+            step,
+            group_id,
+            proposed_winner_info: proposed_winner.clone(),
+            children_winner: children_winner.into_iter().map(|x| x.unwrap()).collect(),
+        });
         self.update_winner_if_better(group_id, proposed_winner);
         trace!(event = "task_finish", task = "optimize_inputs", expr_id = %expr_id, result = "resolved");
         self.optimizer.mark_task_end(&desc);
+        self.report_improvement().await;
     }
 
-    fn on_task_start(&self) {
+    fn on_task_start(&mut self) {
         if (self.optimizer.ctx.all_budget_used || self.optimizer.ctx.logical_budget_used)
             && self.steps % 100000 == 0
         {
@@ -612,5 +806,53 @@ impl<'a, T: NodeType, M: Memo<T>> TaskContext<'a, T, M> {
             println!("step={}", self.steps);
             self.optimizer.dump_stats();
         }
+        let steps = self.steps;
+        if let Some(hook) = self
+            .optimizer
+            .checkpoint_hook
+            .as_mut()
+            .filter(|hook| steps.is_multiple_of(hook.interval))
+        {
+            let checkpoint = MemoCheckpoint::from_memo(&self.optimizer.memo, self.root_group_id);
+            (hook.callback)(checkpoint);
+        }
+        if !self.optimizer.ctx.all_budget_used
+            && self
+                .optimizer
+                .cancellation_token
+                .as_ref()
+                .is_some_and(|token| token.is_cancelled())
+        {
+            tracing::warn!(
+                "optimization cancelled, not applying any rules any more. current iter: {}",
+                steps
+            );
+            self.optimizer.ctx.all_budget_used = true;
+            self.optimizer.ctx.cancelled = true;
+        }
+        if !self.optimizer.ctx.all_budget_used
+            && self
+                .optimizer
+                .prop
+                .timeout
+                .is_some_and(|timeout| self.started_at.elapsed() > timeout)
+        {
+            tracing::warn!(
+                "optimization timed out, not applying any rules any more. current iter: {}",
+                steps
+            );
+            self.optimizer.ctx.all_budget_used = true;
+            self.optimizer.ctx.timed_out = true;
+        }
+        if self
+            .optimizer
+            .progress_hook
+            .as_ref()
+            .is_some_and(|hook| steps.is_multiple_of(hook.interval))
+        {
+            let progress = self.optimizer.step_progress(self.root_group_id, steps);
+            let hook = self.optimizer.progress_hook.as_mut().unwrap();
+            (hook.callback)(&progress);
+        }
     }
 }
@@ -15,6 +15,170 @@ pub struct Statistics(pub Box<dyn std::any::Any + Send + Sync + 'static>);
 #[derive(Default, Clone, Debug, PartialOrd, PartialEq)]
 pub struct Cost(pub Vec<f64>);
 
+/// A component of a cost which is negative or not finite, which a cost model should never
+/// produce, e.g., from statistics with a NaN row count.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidCost {
+    pub component: usize,
+    pub value: f64,
+}
+
+impl std::fmt::Display for InvalidCost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid cost component {}: {}",
+            self.component, self.value
+        )
+    }
+}
+
+impl Cost {
+    /// Checks that all components of the cost are finite and non-negative.
+    pub fn validate(&self) -> Result<(), InvalidCost> {
+        match self
+            .0
+            .iter()
+            .enumerate()
+            .find(|(_, value)| !value.is_finite() || **value < 0.0)
+        {
+            Some((component, value)) => Err(InvalidCost {
+                component,
+                value: *value,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Clamps the invalid components of the cost: NaN and negative components to `floor`, the
+    /// infinite ones to `f64::MAX`.
+    pub fn clamp(&mut self, floor: f64) {
+        for value in &mut self.0 {
+            if value.is_nan() || *value < 0.0 {
+                *value = floor;
+            } else if value.is_infinite() {
+                *value = f64::MAX;
+            }
+        }
+    }
+}
+
+/// Compares weighted costs with a tolerance, so that tiny floating-point differences (e.g., from
+/// a different order of accumulation, a different platform, or adaptive adjustments) do not
+/// change the chosen plan across runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CostComparator {
+    /// Two costs are considered equal if they differ by at most `epsilon` relative to the larger
+    /// one (or absolutely, for costs smaller than 1).
+    pub epsilon: f64,
+    /// Compare costs as integers scaled by `1 / epsilon` instead, which makes the comparison
+    /// transitive at the price of treating `epsilon` as an absolute tolerance.
+    pub quantize: bool,
+}
+
+impl Default for CostComparator {
+    fn default() -> Self {
+        Self {
+            epsilon: Self::DEFAULT_EPSILON,
+            quantize: false,
+        }
+    }
+}
+
+impl CostComparator {
+    pub const DEFAULT_EPSILON: f64 = 1e-9;
+
+    pub fn new(epsilon: f64) -> Self {
+        Self {
+            epsilon,
+            quantize: false,
+        }
+    }
+
+    pub fn new_quantized(epsilon: f64) -> Self {
+        Self {
+            epsilon,
+            quantize: true,
+        }
+    }
+
+    /// The cost rounded to a multiple of `epsilon`, in units of `epsilon`, used when `quantize` is
+    /// set. Kept as a float, as huge costs would saturate an integer and compare equal.
+    pub fn quantized(&self, cost: f64) -> f64 {
+        (cost / self.epsilon).round()
+    }
+
+    pub fn compare(&self, a: f64, b: f64) -> std::cmp::Ordering {
+        if self.quantize {
+            return self.quantized(a).total_cmp(&self.quantized(b));
+        }
+        let tolerance = self.epsilon * a.abs().max(b.abs()).max(1.0);
+        if (a - b).abs() <= tolerance {
+            std::cmp::Ordering::Equal
+        } else {
+            a.total_cmp(&b)
+        }
+    }
+
+    /// Whether the cost `a` is better than `b` by more than the tolerance.
+    pub fn is_better(&self, a: f64, b: f64) -> bool {
+        self.compare(a, b).is_lt()
+    }
+}
+
+/// How a cost model costs a single operator, so that users can audit what the optimizer
+/// believes. Generated by the cost model itself, see [`CostModel::describe`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CostFormula {
+    /// The operator, e.g., `PhysicalHashJoin`.
+    pub operator: String,
+    /// The cost of the operator, e.g., `compute = 2 * left_rows + right_rows`.
+    pub cost: String,
+    /// The estimated number of output rows of the operator.
+    pub row_cnt: String,
+    /// The names and current values of the constants used by the formulas.
+    pub constants: Vec<(String, f64)>,
+}
+
+impl CostFormula {
+    pub fn new(
+        operator: impl Into<String>,
+        cost: impl Into<String>,
+        row_cnt: impl Into<String>,
+    ) -> Self {
+        Self {
+            operator: operator.into(),
+            cost: cost.into(),
+            row_cnt: row_cnt.into(),
+            constants: vec![],
+        }
+    }
+
+    pub fn with_constant(mut self, name: impl Into<String>, value: f64) -> Self {
+        self.constants.push((name.into(), value));
+        self
+    }
+}
+
+/// Renders the formulas as a markdown table.
+pub fn render_cost_formulas(formulas: &[CostFormula]) -> String {
+    let mut report =
+        String::from("| operator | cost | row count | constants |\n|---|---|---|---|\n");
+    for formula in formulas {
+        let constants = formula
+            .constants
+            .iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        report += &format!(
+            "| {} | {} | {} | {} |\n",
+            formula.operator, formula.cost, formula.row_cnt, constants
+        );
+    }
+    report
+}
+
 pub trait CostModel<T: NodeType, M: Memo<T>>: 'static + Send + Sync {
     /// Compute the cost of a single operation. `RelNodeContext` might be
     /// optional in the future when we implement physical property enforcers.
@@ -55,9 +219,94 @@ pub trait CostModel<T: NodeType, M: Memo<T>>: 'static + Send + Sync {
         total_cost
     }
 
+    /// The number of rows of the statistics, if the cost model estimates it.
+    fn estimated_row_cnt(&self, _stat: &Statistics) -> Option<f64> {
+        None
+    }
+
+    /// The components of a compound cost, named after what they measure.
+    fn cost_components(&self, cost: &Cost) -> Vec<(String, f64)> {
+        cost.0
+            .iter()
+            .enumerate()
+            .map(|(idx, component)| (format!("cost{}", idx), *component))
+            .collect()
+    }
+
     /// The zero cost.
     fn zero(&self) -> Cost;
 
     /// The weighted cost of a compound cost.
     fn weighted_cost(&self, cost: &Cost) -> f64;
+
+    /// Describes the cost formula of each operator with the current values of its constants.
+    fn describe(&self) -> Vec<CostFormula> {
+        vec![]
+    }
+
+    /// Called when the optimizer drops the explored plans of the previous query. Cost models
+    /// which cache estimates across the expressions of a query should clear them here.
+    fn reset_caches(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::*;
+
+    #[test]
+    fn compare_with_tolerance() {
+        let cmp = CostComparator::new(1e-6);
+        assert_eq!(cmp.compare(1000.0, 1000.0 + 1e-4), Ordering::Equal);
+        assert_eq!(cmp.compare(0.1 + 0.2, 0.3), Ordering::Equal);
+        assert_eq!(cmp.compare(1000.0, 1000.1), Ordering::Less);
+        assert!(cmp.is_better(1.0, 2.0));
+        assert!(!cmp.is_better(2.0, 1.0));
+        assert!(!cmp.is_better(1.0, 1.0 + 1e-9));
+
+        let cmp = CostComparator::new_quantized(0.01);
+        assert_eq!(cmp.quantized(12.3449), 1234.0);
+        assert_eq!(cmp.compare(12.3449, 12.341), Ordering::Equal);
+        assert_eq!(cmp.compare(12.34, 12.36), Ordering::Less);
+        assert_eq!(cmp.compare(1e30, 2e30), Ordering::Less);
+        assert_eq!(cmp.compare(f64::MAX, 1e30), Ordering::Greater);
+    }
+
+    #[test]
+    fn validate_costs() {
+        assert_eq!(Cost(vec![0.0, 1.5]).validate(), Ok(()));
+        assert_eq!(
+            Cost(vec![1.0, -2.0]).validate(),
+            Err(InvalidCost {
+                component: 1,
+                value: -2.0
+            })
+        );
+        let err = Cost(vec![f64::NAN]).validate().unwrap_err();
+        assert_eq!(err.component, 0);
+        assert!(err.value.is_nan());
+        assert!(Cost(vec![f64::INFINITY]).validate().is_err());
+
+        let mut cost = Cost(vec![f64::NAN, -1.0, f64::INFINITY, 3.0]);
+        cost.clamp(1.0);
+        assert_eq!(cost, Cost(vec![1.0, 1.0, f64::MAX, 3.0]));
+        assert_eq!(cost.validate(), Ok(()));
+    }
+
+    #[test]
+    fn render_formulas() {
+        let formulas = vec![
+            CostFormula::new("Scan", "io = rows", "table_rows"),
+            CostFormula::new("Filter", "compute = rows", "rows * selectivity")
+                .with_constant("selectivity", 0.01),
+        ];
+        assert_eq!(
+            render_cost_formulas(&formulas),
+            "| operator | cost | row count | constants |\n\
+             |---|---|---|---|\n\
+             | Scan | io = rows | table_rows |  |\n\
+             | Filter | compute = rows | rows * selectivity | selectivity = 0.01 |\n"
+        );
+    }
 }
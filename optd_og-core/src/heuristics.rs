@@ -3,6 +3,8 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+mod cost_check;
 mod optimizer;
 
+pub use cost_check::{CascadesCostEstimator, PlanCostEstimator};
 pub use optimizer::{ApplyOrder, HeuristicsOptimizer, HeuristicsOptimizerOptions};
@@ -0,0 +1,71 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::Arc;
+
+use crate::cascades::{CascadesOptimizer, Memo, NaiveMemo, OptimizerProperties};
+use crate::cost::CostModel;
+use crate::logical_property::LogicalPropertyBuilderAny;
+use crate::nodes::{ArcPlanNode, NodeType};
+use crate::rules::Rule;
+
+/// Estimates the cost of the plans rewritten by the heuristic rules, so that the rewrites which
+/// increase it are rejected, see [`super::HeuristicsOptimizer::set_cost_check`].
+pub trait PlanCostEstimator<T: NodeType>: 'static + Send + Sync {
+    /// The weighted cost of the plan, or `None` if it cannot be estimated, in which case the
+    /// rewrite is kept.
+    fn estimate_cost(&self, plan: ArcPlanNode<T>) -> Option<f64>;
+}
+
+impl<T: NodeType, F> PlanCostEstimator<T> for F
+where
+    F: Fn(ArcPlanNode<T>) -> Option<f64> + 'static + Send + Sync,
+{
+    fn estimate_cost(&self, plan: ArcPlanNode<T>) -> Option<f64> {
+        self(plan)
+    }
+}
+
+/// Estimates the cost of a logical plan as the cost of its best physical plan, found by a
+/// cascades optimizer with the given rules, usually the implementation rules only, and cost
+/// model. A new memo table is used for each plan, so that the estimates do not touch the caches
+/// of the cost model.
+pub struct CascadesCostEstimator<T: NodeType> {
+    rules: Vec<Arc<dyn Rule<T, CascadesOptimizer<T>>>>,
+    cost: Arc<dyn CostModel<T, NaiveMemo<T>>>,
+    logical_property_builders: Arc<[Box<dyn LogicalPropertyBuilderAny<T>>]>,
+}
+
+impl<T: NodeType> CascadesCostEstimator<T> {
+    pub fn new(
+        rules: Vec<Arc<dyn Rule<T, CascadesOptimizer<T>>>>,
+        cost: Arc<dyn CostModel<T, NaiveMemo<T>>>,
+        logical_property_builders: Arc<[Box<dyn LogicalPropertyBuilderAny<T>>]>,
+    ) -> Self {
+        Self {
+            rules,
+            cost,
+            logical_property_builders,
+        }
+    }
+}
+
+impl<T: NodeType> PlanCostEstimator<T> for CascadesCostEstimator<T> {
+    fn estimate_cost(&self, plan: ArcPlanNode<T>) -> Option<f64> {
+        let mut optimizer = CascadesOptimizer::new_with_shared_cost(
+            self.rules.clone(),
+            self.cost.clone(),
+            self.logical_property_builders.clone(),
+            OptimizerProperties::default(),
+        );
+        let group_id = optimizer.step_optimize(plan).ok()?;
+        let group_id = optimizer.resolve_group(group_id);
+        optimizer
+            .memo()
+            .get_group_winner(group_id)
+            .as_full_winner()
+            .map(|winner| winner.total_weighted_cost)
+    }
+}
@@ -10,6 +10,8 @@ use std::sync::Arc;
 use anyhow::{Context, Result};
 use itertools::Itertools;
 
+use super::PlanCostEstimator;
+use crate::cost::CostComparator;
 use crate::logical_property::{LogicalProperty, LogicalPropertyBuilderAny};
 use crate::nodes::{ArcPlanNode, NodeType, PlanNode, PlanNodeOrGroup};
 use crate::optimizer::Optimizer;
@@ -34,6 +36,9 @@ pub struct HeuristicsOptimizer<T: NodeType> {
     logical_property_builders: Arc<[Box<dyn LogicalPropertyBuilderAny<T>>]>,
     physical_property_builders: PhysicalPropertyBuilders<T>,
     logical_properties_cache: HashMap<ArcPlanNode<T>, Arc<[Box<dyn LogicalProperty>]>>,
+    /// Estimates the cost of the rewrites, which are only kept if they do not increase it.
+    cost_check: Option<Box<dyn PlanCostEstimator<T>>>,
+    rejected_rewrite_count: usize,
 }
 
 fn match_node<T: NodeType>(
@@ -106,9 +111,28 @@ impl<T: NodeType> HeuristicsOptimizer<T> {
             logical_property_builders,
             logical_properties_cache: HashMap::new(),
             physical_property_builders: PhysicalPropertyBuilders(physical_property_builders),
+            cost_check: None,
+            rejected_rewrite_count: 0,
         }
     }
 
+    /// The heuristic rules are applied blindly, and a rewrite can make the plan more expensive,
+    /// e.g., a filter pushed below a selective projection. With a cost check, the cost of the
+    /// plan is estimated before and after each rewrite, and the cheaper plan is kept.
+    pub fn set_cost_check(&mut self, estimator: Option<Box<dyn PlanCostEstimator<T>>>) {
+        self.cost_check = estimator;
+    }
+
+    /// The rewrites rejected by the cost check, see [`Self::set_cost_check`].
+    pub fn rejected_rewrite_count(&self) -> usize {
+        self.rejected_rewrite_count
+    }
+
+    /// Append rules which are applied after the existing ones.
+    pub fn add_rules(&mut self, rules: impl IntoIterator<Item = Arc<dyn Rule<T, Self>>>) {
+        self.rules = self.rules.iter().cloned().chain(rules).collect();
+    }
+
     fn optimize_inputs(
         &mut self,
         inputs: &[PlanNodeOrGroup<T>],
@@ -123,6 +147,8 @@ impl<T: NodeType> HeuristicsOptimizer<T> {
     }
 
     fn apply_rules(&mut self, mut root_rel: ArcPlanNode<T>) -> Result<ArcPlanNode<T>> {
+        // the estimated cost of `root_rel`, computed once a rule rewrites it
+        let mut root_cost = None;
         for rule in self.rules.clone().as_ref() {
             // Properties only matter for applying rules, therefore applying it before each rule
             // invoke.
@@ -132,7 +158,29 @@ impl<T: NodeType> HeuristicsOptimizer<T> {
                 let mut results = rule.apply(self, binding);
                 assert!(results.len() <= 1);
                 if !results.is_empty() {
-                    root_rel = results.remove(0).unwrap_plan_node();
+                    let rewritten = results.remove(0).unwrap_plan_node();
+                    let Some(estimator) = &self.cost_check else {
+                        root_rel = rewritten;
+                        continue;
+                    };
+                    let cost =
+                        *root_cost.get_or_insert_with(|| estimator.estimate_cost(root_rel.clone()));
+                    let rewritten_cost = estimator.estimate_cost(rewritten.clone());
+                    let regression = cost.zip(rewritten_cost).filter(|(cost, rewritten_cost)| {
+                        CostComparator::default().is_better(*cost, *rewritten_cost)
+                    });
+                    if let Some((cost, rewritten_cost)) = regression {
+                        tracing::debug!(
+                            rule = rule.name(),
+                            cost,
+                            rewritten_cost,
+                            "heuristic rewrite rejected by the cost check"
+                        );
+                        self.rejected_rewrite_count += 1;
+                        continue;
+                    }
+                    root_rel = rewritten;
+                    root_cost = Some(rewritten_cost);
                 }
             }
         }
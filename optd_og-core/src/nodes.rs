@@ -12,12 +12,14 @@ use std::hash::Hash;
 use std::sync::Arc;
 
 use arrow_schema::DataType;
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use itertools::Itertools;
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::cascades::GroupId;
-use crate::cost::{Cost, Statistics};
+use crate::cascades::{GroupId, Memo};
+use crate::cost::{Cost, CostModel, Statistics};
+use crate::physical_property::PhysicalProperty;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct SerializableOrderedF64(pub OrderedFloat<f64>);
@@ -43,6 +45,114 @@ impl<'de> Deserialize<'de> for SerializableOrderedF64 {
     }
 }
 
+/// A decimal of `value * 10^-scale`. Decimals compare by their numeric value, so that
+/// decimals of different scales, e.g., a column and a literal of different types, can be
+/// compared.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Decimal128 {
+    pub value: i128,
+    pub scale: i8,
+}
+
+impl Decimal128 {
+    pub fn new(value: i128, scale: i8) -> Self {
+        Self { value, scale }
+    }
+
+    /// The decimal nearest to `value` with the given scale, or `None` if it is out of range.
+    pub fn from_f64(value: f64, scale: i8) -> Option<Self> {
+        let scaled = (value * 10f64.powi(scale as i32)).round();
+        if !scaled.is_finite() || scaled.abs() >= i128::MAX as f64 {
+            return None;
+        }
+        Some(Self::new(scaled as i128, scale))
+    }
+
+    /// The same number with `scale`, rounding towards zero if the scale is reduced, or `None`
+    /// if it is out of range.
+    pub fn rescale(&self, scale: i8) -> Option<Self> {
+        let diff = scale as i32 - self.scale as i32;
+        let factor = 10i128.checked_pow(diff.unsigned_abs())?;
+        let value = if diff >= 0 {
+            self.value.checked_mul(factor)?
+        } else {
+            self.value / factor
+        };
+        Some(Self::new(value, scale))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.value as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// The same number without trailing zeros, so that equal numbers have equal representations.
+    fn normalize(&self) -> Self {
+        if self.value == 0 {
+            return Self::new(0, 0);
+        }
+        let mut normalized = *self;
+        while normalized.scale > i8::MIN && normalized.value.unsigned_abs().is_multiple_of(10) {
+            normalized.value /= 10;
+            normalized.scale -= 1;
+        }
+        normalized
+    }
+}
+
+impl PartialEq for Decimal128 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Decimal128 {}
+
+impl Hash for Decimal128 {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let normalized = self.normalize();
+        normalized.value.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+impl PartialOrd for Decimal128 {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal128 {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if self.scale < other.scale {
+            return other.cmp(self).reverse();
+        }
+        // A number that overflows when rescaled to the larger scale is larger in magnitude than
+        // any number of that scale.
+        match other.rescale(self.scale) {
+            Some(other) => self.value.cmp(&other.value),
+            None => 0.cmp(&other.value),
+        }
+    }
+}
+
+impl Display for Decimal128 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.scale <= 0 {
+            return write!(
+                f,
+                "{}{}",
+                self.value,
+                "0".repeat(self.scale.unsigned_abs() as usize)
+            );
+        }
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", self.value.unsigned_abs(), width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        let sign = if self.value < 0 { "-" } else { "" };
+        write!(f, "{sign}{int}.{frac}")
+    }
+}
+
 // TODO: why not use arrow types here? Do we really need to define our own Value type?
 // Shouldn't we at least remove this from the core/engine?
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
@@ -60,8 +170,19 @@ pub enum Value {
     String(Arc<str>),
     Bool(bool),
     Date32(i32),
-    Decimal128(i128),
+    Decimal128(Decimal128),
+    /// Nanoseconds since the UNIX epoch in UTC. The time zone only changes how a timestamp is
+    /// displayed, so it is not part of the value.
+    Timestamp(i64),
+    /// Nanoseconds since midnight.
+    Time(i64),
     Serialized(Arc<[u8]>),
+    /// The elements of an array, none of which is null.
+    List(Arc<[Value]>),
+    /// The fields of a struct by name, in the order of the struct type.
+    Struct(Arc<[(Arc<str>, Value)]>),
+    /// The entries of a map by key, in the order they were written.
+    Map(Arc<[(Value, Value)]>),
 }
 
 impl std::fmt::Display for Value {
@@ -81,7 +202,24 @@ impl std::fmt::Display for Value {
             Self::Bool(x) => write!(f, "{x}"),
             Self::Date32(x) => write!(f, "{x}(date32)"),
             Self::Decimal128(x) => write!(f, "{x}(decimal128)"),
+            Self::Timestamp(x) => write!(f, "{x}(timestamp)"),
+            Self::Time(x) => write!(f, "{x}(time)"),
             Self::Serialized(x) => write!(f, "<len:{}>", x.len()),
+            Self::List(x) => write!(f, "[{}]", x.iter().join(", ")),
+            Self::Struct(x) => write!(
+                f,
+                "{{{}}}",
+                x.iter()
+                    .map(|(name, value)| format!("{name}: {value}"))
+                    .join(", ")
+            ),
+            Self::Map(x) => write!(
+                f,
+                "map{{{}}}",
+                x.iter()
+                    .map(|(key, value)| format!("{key}: {value}"))
+                    .join(", ")
+            ),
         }
     }
 }
@@ -161,6 +299,13 @@ impl Value {
         }
     }
 
+    pub fn as_decimal128(&self) -> Decimal128 {
+        match self {
+            Value::Decimal128(i) => *i,
+            _ => panic!("Value is not a decimal128"),
+        }
+    }
+
     pub fn as_bool(&self) -> bool {
         match self {
             Value::Bool(i) => *i,
@@ -175,6 +320,22 @@ impl Value {
         }
     }
 
+    /// Returns the nanoseconds since the UNIX epoch.
+    pub fn as_timestamp(&self) -> i64 {
+        match self {
+            Value::Timestamp(i) => *i,
+            _ => panic!("Value is not a timestamp"),
+        }
+    }
+
+    /// Returns the nanoseconds since midnight.
+    pub fn as_time(&self) -> i64 {
+        match self {
+            Value::Time(i) => *i,
+            _ => panic!("Value is not a time"),
+        }
+    }
+
     pub fn as_slice(&self) -> Arc<[u8]> {
         match self {
             Value::Serialized(i) => i.clone(),
@@ -182,6 +343,27 @@ impl Value {
         }
     }
 
+    pub fn as_list(&self) -> Arc<[Value]> {
+        match self {
+            Value::List(i) => i.clone(),
+            _ => panic!("Value is not a list"),
+        }
+    }
+
+    pub fn as_struct(&self) -> Arc<[(Arc<str>, Value)]> {
+        match self {
+            Value::Struct(i) => i.clone(),
+            _ => panic!("Value is not a struct"),
+        }
+    }
+
+    pub fn as_map(&self) -> Arc<[(Value, Value)]> {
+        match self {
+            Value::Map(i) => i.clone(),
+            _ => panic!("Value is not a map"),
+        }
+    }
+
     pub fn convert_to_type(&self, typ: DataType) -> Value {
         match typ {
             DataType::Int32 => Value::Int32(match self {
@@ -211,12 +393,36 @@ impl Value {
                 }
                 _ => panic!("{self} could not be converted into an Date32"),
             }),
-            DataType::Decimal128(_, _) => Value::Decimal128(match self {
-                // TODO: Should we be ignoring the scale and precision here?
-                Value::Int128(i128) => *i128,
-                Value::Int64(i64) => (*i64).into(),
-                Value::Int32(i32) => (*i32).into(),
-                _ => panic!("{self} could not be converted into an Decimal128"),
+            DataType::Decimal128(_, scale) => Value::Decimal128(
+                match self {
+                    Value::Decimal128(decimal) => decimal.rescale(scale),
+                    Value::Int128(i128) => Decimal128::new(*i128, 0).rescale(scale),
+                    Value::Int64(i64) => Decimal128::new((*i64).into(), 0).rescale(scale),
+                    Value::Int32(i32) => Decimal128::new((*i32).into(), 0).rescale(scale),
+                    Value::Float(f64) => Decimal128::from_f64(*f64.0, scale),
+                    Value::String(str) => str
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|f64| Decimal128::from_f64(f64, scale)),
+                    _ => None,
+                }
+                .unwrap_or_else(|| panic!("{self} could not be converted into an Decimal128")),
+            ),
+            DataType::Timestamp(_, _) => Value::Timestamp(match self {
+                Value::Timestamp(nanos) => *nanos,
+                Value::Date32(days) => *days as i64 * NANOS_PER_DAY,
+                Value::String(str) => parse_timestamp(str)
+                    .unwrap_or_else(|| panic!("{self} could not be converted into a Timestamp")),
+                _ => panic!("{self} could not be converted into a Timestamp"),
+            }),
+            DataType::Time32(_) | DataType::Time64(_) => Value::Time(match self {
+                Value::Time(nanos) => *nanos,
+                Value::String(str) => {
+                    let time = NaiveTime::parse_from_str(str, "%H:%M:%S%.f").unwrap();
+                    time.num_seconds_from_midnight() as i64 * NANOS_PER_SECOND
+                        + time.nanosecond() as i64
+                }
+                _ => panic!("{self} could not be converted into a Time"),
             }),
             _ => unimplemented!(
                 "Have not implemented convert_to_type from {self} for DataType {typ}"
@@ -225,6 +431,23 @@ impl Value {
     }
 }
 
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const NANOS_PER_DAY: i64 = 86_400 * NANOS_PER_SECOND;
+
+/// Parses a timestamp literal, e.g., `2024-01-31 12:00:00.5` or `2024-01-31`, as nanoseconds
+/// since the UNIX epoch.
+fn parse_timestamp(str: &str) -> Option<i64> {
+    let datetime = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(str, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(str, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })?;
+    datetime.and_utc().timestamp_nanos_opt()
+}
+
 pub trait NodeType:
     PartialEq + Eq + Hash + Clone + 'static + Display + Debug + Send + Sync
 {
@@ -239,7 +462,11 @@ pub type ArcPlanNode<T> = Arc<PlanNode<T>>;
 /// A pointer to a predicate node
 pub type ArcPredNode<T> = Arc<PredNode<T>>;
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T: Serialize, T::PredType: Serialize",
+    deserialize = "T: Deserialize<'de>, T::PredType: Deserialize<'de>"
+))]
 pub enum PlanNodeOrGroup<T: NodeType> {
     PlanNode(ArcPlanNode<T>),
     Group(GroupId),
@@ -270,6 +497,14 @@ impl<T: NodeType> PlanNodeOrGroup<T> {
             PlanNodeOrGroup::Group(group_id) => *group_id,
         }
     }
+
+    /// The plan node, or `None` if this is a placeholder group.
+    pub fn as_plan_node(&self) -> Option<ArcPlanNode<T>> {
+        match self {
+            PlanNodeOrGroup::PlanNode(node) => Some(node.clone()),
+            PlanNodeOrGroup::Group(_) => None,
+        }
+    }
 }
 
 impl<T: NodeType> std::fmt::Display for PlanNodeOrGroup<T> {
@@ -281,7 +516,11 @@ impl<T: NodeType> std::fmt::Display for PlanNodeOrGroup<T> {
     }
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T: Serialize, T::PredType: Serialize",
+    deserialize = "T: Deserialize<'de>, T::PredType: Deserialize<'de>"
+))]
 pub struct PlanNode<T: NodeType> {
     /// A generic plan node type
     pub typ: T,
@@ -337,7 +576,11 @@ impl<T: NodeType> From<GroupId> for PlanNodeOrGroup<T> {
     }
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T::PredType: Serialize",
+    deserialize = "T::PredType: Deserialize<'de>"
+))]
 pub struct PredNode<T: NodeType> {
     /// A generic predicate node type
     pub typ: T::PredType,
@@ -369,45 +612,174 @@ impl<T: NodeType> PredNode<T> {
     }
 }
 
-/// Metadata for a rel node.
+/// What the optimizer chose and estimated for a node of an optimized plan.
 #[derive(Clone)]
-pub struct PlanNodeMeta {
+pub struct PlanAnnotation {
     /// The group (id) of the `RelNode`
     pub group_id: GroupId,
-    /// Weighted cost of the `RelNode`
+    /// The number of rows the `RelNode` is estimated to produce, if the cost model estimates it
+    pub row_cnt: Option<f64>,
+    /// Weighted cost of the `RelNode` and its inputs
     pub weighted_cost: f64,
-    /// Cost of the `RelNode`
+    /// Cost of the `RelNode` and its inputs
     pub cost: Cost,
+    /// The components of `cost` named by the cost model, e.g., `("compute", 10.0)`
+    pub cost_components: Vec<(String, f64)>,
     /// Statistics
     pub stat: Arc<Statistics>,
     /// Cost in display string
-    /// TODO: this should be lazily processed and generated
     pub cost_display: String,
     /// Statistics in display string
-    /// TODO: this should be lazily processed and generated
     pub stat_display: String,
+    /// The physical properties of the output of the `RelNode` chosen by the optimizer, e.g., the
+    /// order of its rows, at most one of each type
+    pub physical_props: Vec<Arc<dyn PhysicalProperty>>,
 }
 
-impl PlanNodeMeta {
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
+impl PlanAnnotation {
+    /// Annotates a `RelNode` with its cost and statistics, as explained by the cost model.
+    pub fn new<T: NodeType, M: Memo<T>, C: CostModel<T, M> + ?Sized>(
         group_id: GroupId,
-        weighted_cost: f64,
         cost: Cost,
         stat: Arc<Statistics>,
-        cost_display: String,
-        stat_display: String,
+        cost_model: &C,
     ) -> Self {
         Self {
             group_id,
-            weighted_cost,
+            row_cnt: cost_model.estimated_row_cnt(&stat),
+            weighted_cost: cost_model.weighted_cost(&cost),
+            cost_components: cost_model.cost_components(&cost),
+            cost_display: cost_model.explain_cost(&cost),
+            stat_display: cost_model.explain_statistics(&stat),
             cost,
             stat,
-            cost_display,
-            stat_display,
+            physical_props: Vec::new(),
         }
     }
+
+    /// The chosen physical property of the given type, if any.
+    pub fn physical_prop<P: PhysicalProperty>(&self) -> Option<&P> {
+        self.physical_props
+            .iter()
+            .find_map(|prop| prop.as_any().downcast_ref::<P>())
+    }
+
+    /// Records a chosen physical property, replacing the one of the same type if any.
+    pub fn set_physical_prop<P: PhysicalProperty>(&mut self, prop: P) {
+        self.physical_props
+            .retain(|prop| prop.as_any().downcast_ref::<P>().is_none());
+        self.physical_props.push(Arc::new(prop));
+    }
+}
+
+/// The annotations of the nodes of a plan. The nodes are told apart by their addresses, so the
+/// annotations only hold as long as the plan is kept alive, and a node rebuilt by a rewrite has
+/// to be annotated again.
+#[derive(Clone, Default)]
+pub struct PlanAnnotations(HashMap<usize, PlanAnnotation>);
+
+impl PlanAnnotations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key<T: NodeType>(node: &PlanNode<T>) -> usize {
+        node as *const _ as usize
+    }
+
+    pub fn get<T: NodeType>(&self, node: &PlanNode<T>) -> Option<&PlanAnnotation> {
+        self.0.get(&Self::key(node))
+    }
+
+    pub fn get_mut<T: NodeType>(&mut self, node: &PlanNode<T>) -> Option<&mut PlanAnnotation> {
+        self.0.get_mut(&Self::key(node))
+    }
+
+    /// Annotates the node, returning its previous annotation if any.
+    pub fn insert<T: NodeType>(
+        &mut self,
+        node: &PlanNode<T>,
+        annotation: PlanAnnotation,
+    ) -> Option<PlanAnnotation> {
+        self.0.insert(Self::key(node), annotation)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
-/// A hash table storing `RelNode` (memory address, metadata) pairs.
-pub type PlanNodeMetaMap = HashMap<usize, PlanNodeMeta>;
+#[cfg(test)]
+mod tests {
+    use arrow_schema::TimeUnit;
+
+    use super::*;
+
+    #[test]
+    fn display_nested_values() {
+        let value = Value::Struct(
+            vec![
+                (
+                    "a".into(),
+                    Value::List(vec![Value::Int32(1), Value::Int32(2)].into()),
+                ),
+                (
+                    "b".into(),
+                    Value::Map(vec![(Value::String("k".into()), Value::Bool(true))].into()),
+                ),
+            ]
+            .into(),
+        );
+        assert_eq!(
+            value.to_string(),
+            "{a: [1(i32), 2(i32)], b: map{\"k\": true}}"
+        );
+    }
+
+    #[test]
+    fn compare_decimals_of_different_scales() {
+        let a = Decimal128::new(150, 2);
+        let b = Decimal128::new(15, 1);
+        assert_eq!(a, b);
+        let hash = |decimal: &Decimal128| {
+            let mut hasher = std::hash::DefaultHasher::new();
+            decimal.hash(&mut hasher);
+            std::hash::Hasher::finish(&hasher)
+        };
+        assert_eq!(hash(&a), hash(&b));
+        assert!(Decimal128::new(151, 2) > b);
+        assert!(Decimal128::new(-2, 0) < Decimal128::new(-199, 2));
+        assert!(Decimal128::new(i128::MAX, 0) > Decimal128::new(1, 30));
+        assert_eq!(a.to_string(), "1.50");
+        assert_eq!(Decimal128::new(-5, 3).to_string(), "-0.005");
+        assert_eq!(
+            Value::Float(SerializableOrderedF64(0.05.into()))
+                .convert_to_type(DataType::Decimal128(15, 2)),
+            Value::Decimal128(Decimal128::new(5, 2))
+        );
+    }
+
+    #[test]
+    fn convert_to_timestamp_and_time() {
+        assert_eq!(
+            Value::String("1970-01-02 00:00:01.5".into()).convert_to_type(DataType::Timestamp(
+                TimeUnit::Microsecond,
+                Some("+08:00".into())
+            )),
+            Value::Timestamp(NANOS_PER_DAY + 1_500_000_000)
+        );
+        assert_eq!(
+            Value::Date32(1).convert_to_type(DataType::Timestamp(TimeUnit::Second, None)),
+            Value::Timestamp(NANOS_PER_DAY)
+        );
+        assert_eq!(
+            Value::String("00:01:00".into())
+                .convert_to_type(DataType::Time64(TimeUnit::Nanosecond)),
+            Value::Time(60 * NANOS_PER_SECOND)
+        );
+    }
+}
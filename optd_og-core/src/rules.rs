@@ -19,4 +19,9 @@ pub trait Rule<T: NodeType, O: Optimizer<T>>: 'static + Send + Sync {
     fn is_impl_rule(&self) -> bool {
         false
     }
+    /// How likely the rule is to lead to a better plan, relative to the other transformation
+    /// rules, see [`crate::cascades::ExplorationStrategy::Randomized`].
+    fn promise(&self) -> f64 {
+        1.0
+    }
 }
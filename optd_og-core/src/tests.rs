@@ -4,4 +4,5 @@
 // https://opensource.org/licenses/MIT.
 
 pub(crate) mod common;
+mod dataflow;
 pub(crate) mod heuristics_physical_property;
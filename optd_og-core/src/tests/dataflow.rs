@@ -0,0 +1,931 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! A toy dataflow IR which is not a query plan, optimized with the heuristics and the cascades
+//! optimizers. It shows what a `NodeType` has to provide to embed optd_og (a logical property, the
+//! rules and a cost model), and keeps the core from depending on anything SQL-specific.
+//!
+//! A dataflow reads records from named streams, transforms them with user-defined functions and
+//! merges streams. A map outputs the listed fields, where a field with the same name as an input
+//! field is copied from the input.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use itertools::Itertools;
+
+use crate::cascades::{
+    CancellationToken, CascadesOptimizer, ExplorationStrategy, KeepWinnersOnly, LruEviction, Memo,
+    MemoDumpOptions, NaiveMemo, RelNodeContext,
+};
+use crate::cost::{Cost, CostModel, Statistics};
+use crate::heuristics::{
+    ApplyOrder, CascadesCostEstimator, HeuristicsOptimizer, HeuristicsOptimizerOptions,
+};
+use crate::logical_property::{LogicalProperty, LogicalPropertyBuilder, LogicalPropertyBuilderAny};
+use crate::nodes::{
+    ArcPlanNode, ArcPredNode, NodeType, PlanAnnotations, PlanNode, PlanNodeOrGroup, PredNode, Value,
+};
+use crate::optimizer::Optimizer;
+use crate::rules::{Rule, RuleMatcher};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum DataflowTyp {
+    /// Predicates: the stream name, the fields of the records.
+    Source,
+    /// Predicates: the function, the output fields.
+    Map,
+    /// Predicates: the function, the fields read by the function.
+    Filter,
+    Union,
+    PhysicalSource,
+    PhysicalMap,
+    PhysicalFilter,
+    PhysicalUnion,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum DataflowPredTyp {
+    Stream,
+    Udf,
+    Field,
+    Fields,
+}
+
+impl std::fmt::Display for DataflowTyp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::fmt::Display for DataflowPredTyp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl NodeType for DataflowTyp {
+    type PredType = DataflowPredTyp;
+
+    fn is_logical(&self) -> bool {
+        matches!(self, Self::Source | Self::Map | Self::Filter | Self::Union)
+    }
+}
+
+impl DataflowTyp {
+    fn to_physical(&self) -> Self {
+        match self {
+            Self::Source => Self::PhysicalSource,
+            Self::Map => Self::PhysicalMap,
+            Self::Filter => Self::PhysicalFilter,
+            Self::Union => Self::PhysicalUnion,
+            _ => unreachable!("{} is already physical", self),
+        }
+    }
+}
+
+fn pred(typ: DataflowPredTyp, name: &str) -> ArcPredNode<DataflowTyp> {
+    Arc::new(PredNode {
+        typ,
+        children: vec![],
+        data: Some(Value::String(name.into())),
+    })
+}
+
+fn fields(names: &[&str]) -> ArcPredNode<DataflowTyp> {
+    Arc::new(PredNode {
+        typ: DataflowPredTyp::Fields,
+        children: names
+            .iter()
+            .map(|name| pred(DataflowPredTyp::Field, name))
+            .collect(),
+        data: None,
+    })
+}
+
+fn field_names(fields: &ArcPredNode<DataflowTyp>) -> Vec<String> {
+    fields
+        .children
+        .iter()
+        .map(|field| field.unwrap_data().as_str().to_string())
+        .collect()
+}
+
+fn source(stream: &str, field_names: &[&str]) -> ArcPlanNode<DataflowTyp> {
+    Arc::new(PlanNode {
+        typ: DataflowTyp::Source,
+        children: vec![],
+        predicates: vec![pred(DataflowPredTyp::Stream, stream), fields(field_names)],
+    })
+}
+
+fn map(
+    input: impl Into<PlanNodeOrGroup<DataflowTyp>>,
+    udf: &str,
+    output: &[&str],
+) -> ArcPlanNode<DataflowTyp> {
+    Arc::new(PlanNode {
+        typ: DataflowTyp::Map,
+        children: vec![input.into()],
+        predicates: vec![pred(DataflowPredTyp::Udf, udf), fields(output)],
+    })
+}
+
+fn filter(
+    input: impl Into<PlanNodeOrGroup<DataflowTyp>>,
+    udf: &str,
+    reads: &[&str],
+) -> ArcPlanNode<DataflowTyp> {
+    Arc::new(PlanNode {
+        typ: DataflowTyp::Filter,
+        children: vec![input.into()],
+        predicates: vec![pred(DataflowPredTyp::Udf, udf), fields(reads)],
+    })
+}
+
+fn union(
+    left: impl Into<PlanNodeOrGroup<DataflowTyp>>,
+    right: impl Into<PlanNodeOrGroup<DataflowTyp>>,
+) -> ArcPlanNode<DataflowTyp> {
+    Arc::new(PlanNode {
+        typ: DataflowTyp::Union,
+        children: vec![left.into(), right.into()],
+        predicates: vec![],
+    })
+}
+
+/// Converts all the logical nodes of a plan into their physical counterparts, to build the
+/// expected plans.
+fn to_physical(node: ArcPlanNode<DataflowTyp>) -> ArcPlanNode<DataflowTyp> {
+    Arc::new(PlanNode {
+        typ: node.typ.to_physical(),
+        children: node
+            .children
+            .iter()
+            .map(|child| to_physical(child.unwrap_plan_node()).into())
+            .collect(),
+        predicates: node.predicates.clone(),
+    })
+}
+
+/// The fields of the records produced by a node.
+struct FieldsPropertyBuilder;
+
+#[derive(Clone, Debug)]
+struct FieldsProp(Vec<String>);
+
+impl std::fmt::Display for FieldsProp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl LogicalProperty for FieldsProp {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl LogicalPropertyBuilder<DataflowTyp> for FieldsPropertyBuilder {
+    type Prop = FieldsProp;
+
+    fn derive(
+        &self,
+        typ: DataflowTyp,
+        predicates: &[ArcPredNode<DataflowTyp>],
+        children: &[&Self::Prop],
+    ) -> Self::Prop {
+        match typ {
+            DataflowTyp::Source | DataflowTyp::Map => FieldsProp(field_names(&predicates[1])),
+            DataflowTyp::Filter => children[0].clone(),
+            // Both inputs of a union have the same fields.
+            DataflowTyp::Union => children[0].clone(),
+            _ => unreachable!("properties are only derived for logical nodes"),
+        }
+    }
+
+    fn property_name(&self) -> &'static str {
+        "fields"
+    }
+}
+
+/// Filter the records before transforming them, if the filter only reads fields copied from the
+/// input of the map.
+struct FilterPastMapRule {
+    matcher: RuleMatcher<DataflowTyp>,
+}
+
+impl FilterPastMapRule {
+    fn new() -> Self {
+        Self {
+            matcher: RuleMatcher::MatchNode {
+                typ: DataflowTyp::Filter,
+                children: vec![RuleMatcher::MatchNode {
+                    typ: DataflowTyp::Map,
+                    children: vec![RuleMatcher::Any],
+                }],
+            },
+        }
+    }
+}
+
+impl<O: Optimizer<DataflowTyp>> Rule<DataflowTyp, O> for FilterPastMapRule {
+    fn matcher(&self) -> &RuleMatcher<DataflowTyp> {
+        &self.matcher
+    }
+
+    fn apply(
+        &self,
+        optimizer: &O,
+        binding: ArcPlanNode<DataflowTyp>,
+    ) -> Vec<PlanNodeOrGroup<DataflowTyp>> {
+        let map = binding.child_rel(0);
+        let map_input = map.child(0);
+        let input_fields = optimizer
+            .get_logical_property::<FieldsPropertyBuilder>(map_input.clone(), 0)
+            .0;
+        let output_fields = field_names(&map.predicates[1]);
+        let copied = field_names(&binding.predicates[1])
+            .iter()
+            .all(|field| input_fields.contains(field) && output_fields.contains(field));
+        if !copied {
+            return vec![];
+        }
+        let filter = PlanNode {
+            typ: DataflowTyp::Filter,
+            children: vec![map_input],
+            predicates: binding.predicates.clone(),
+        };
+        let map = PlanNode {
+            typ: DataflowTyp::Map,
+            children: vec![PlanNodeOrGroup::PlanNode(filter.into())],
+            predicates: map.predicates.clone(),
+        };
+        vec![map.into()]
+    }
+
+    fn name(&self) -> &'static str {
+        "filter_past_map"
+    }
+}
+
+struct ImplementationRule {
+    matcher: RuleMatcher<DataflowTyp>,
+}
+
+impl ImplementationRule {
+    fn all<O: Optimizer<DataflowTyp>>() -> Vec<Arc<dyn Rule<DataflowTyp, O>>> {
+        [
+            DataflowTyp::Source,
+            DataflowTyp::Map,
+            DataflowTyp::Filter,
+            DataflowTyp::Union,
+        ]
+        .into_iter()
+        .map(|typ| {
+            Arc::new(Self {
+                matcher: RuleMatcher::MatchNode {
+                    typ,
+                    children: vec![RuleMatcher::AnyMany],
+                },
+            }) as Arc<dyn Rule<DataflowTyp, O>>
+        })
+        .collect()
+    }
+}
+
+impl<O: Optimizer<DataflowTyp>> Rule<DataflowTyp, O> for ImplementationRule {
+    fn matcher(&self) -> &RuleMatcher<DataflowTyp> {
+        &self.matcher
+    }
+
+    fn apply(&self, _: &O, binding: ArcPlanNode<DataflowTyp>) -> Vec<PlanNodeOrGroup<DataflowTyp>> {
+        let PlanNode {
+            typ,
+            children,
+            predicates,
+        } = Arc::unwrap_or_clone(binding);
+        let node = PlanNode {
+            typ: typ.to_physical(),
+            children,
+            predicates,
+        };
+        vec![node.into()]
+    }
+
+    fn name(&self) -> &'static str {
+        "implementation"
+    }
+
+    fn is_impl_rule(&self) -> bool {
+        true
+    }
+}
+
+/// The cost is the number of records read and processed, where transforming a record is more expensive
+/// than filtering it.
+struct DataflowCostModel {
+    stream_rows: HashMap<String, f64>,
+}
+
+const MAP_COST_PER_ROW: f64 = 10.0;
+const FILTER_SELECTIVITY: f64 = 0.1;
+
+impl DataflowCostModel {
+    fn row_cnt(Statistics(stat): &Statistics) -> f64 {
+        *stat.downcast_ref::<f64>().unwrap()
+    }
+
+    fn stream_row_cnt(&self, predicates: &[ArcPredNode<DataflowTyp>]) -> f64 {
+        self.stream_rows[predicates[0].unwrap_data().as_str().as_ref()]
+    }
+}
+
+impl CostModel<DataflowTyp, NaiveMemo<DataflowTyp>> for DataflowCostModel {
+    fn compute_operation_cost(
+        &self,
+        node: &DataflowTyp,
+        predicates: &[ArcPredNode<DataflowTyp>],
+        children_stats: &[Option<&Statistics>],
+        _: RelNodeContext,
+        _: &CascadesOptimizer<DataflowTyp>,
+    ) -> Cost {
+        let row_cnts = children_stats
+            .iter()
+            .map(|child| child.map(Self::row_cnt).unwrap_or(0.0))
+            .collect_vec();
+        let cost = match node {
+            DataflowTyp::PhysicalSource => self.stream_row_cnt(predicates),
+            DataflowTyp::PhysicalMap => row_cnts[0] * MAP_COST_PER_ROW,
+            DataflowTyp::PhysicalFilter => row_cnts[0],
+            DataflowTyp::PhysicalUnion => 0.0,
+            _ => unreachable!("logical nodes are not costed"),
+        };
+        Cost(vec![cost])
+    }
+
+    fn derive_statistics(
+        &self,
+        node: &DataflowTyp,
+        predicates: &[ArcPredNode<DataflowTyp>],
+        children_stats: &[&Statistics],
+        _: RelNodeContext,
+        _: &CascadesOptimizer<DataflowTyp>,
+    ) -> Statistics {
+        let row_cnts = children_stats
+            .iter()
+            .map(|child| Self::row_cnt(child))
+            .collect_vec();
+        let row_cnt = match node {
+            DataflowTyp::PhysicalSource => self.stream_row_cnt(predicates),
+            DataflowTyp::PhysicalMap => row_cnts[0],
+            DataflowTyp::PhysicalFilter => row_cnts[0] * FILTER_SELECTIVITY,
+            DataflowTyp::PhysicalUnion => row_cnts.iter().sum(),
+            _ => unreachable!("logical nodes are not costed"),
+        };
+        Statistics(Box::new(row_cnt))
+    }
+
+    fn explain_cost(&self, cost: &Cost) -> String {
+        format!("{{cost={}}}", cost.0[0])
+    }
+
+    fn explain_statistics(&self, stat: &Statistics) -> String {
+        format!("{{row_cnt={}}}", Self::row_cnt(stat))
+    }
+
+    fn estimated_row_cnt(&self, stat: &Statistics) -> Option<f64> {
+        Some(Self::row_cnt(stat))
+    }
+
+    fn accumulate(&self, total_cost: &mut Cost, cost: &Cost) {
+        total_cost.0[0] += cost.0[0];
+    }
+
+    fn zero(&self) -> Cost {
+        Cost(vec![0.0])
+    }
+
+    fn weighted_cost(&self, cost: &Cost) -> f64 {
+        cost.0[0]
+    }
+}
+
+fn fields_property_builder() -> Arc<[Box<dyn LogicalPropertyBuilderAny<DataflowTyp>>]> {
+    vec![Box::new(FieldsPropertyBuilder) as Box<dyn LogicalPropertyBuilderAny<DataflowTyp>>].into()
+}
+
+fn dataflow() -> ArcPlanNode<DataflowTyp> {
+    filter(
+        map(
+            union(
+                source("clicks", &["user", "url"]),
+                source("views", &["user", "url"]),
+            ),
+            "parse_url",
+            &["user", "domain"],
+        ),
+        "is_bot",
+        &["user"],
+    )
+}
+
+/// A cascades optimizer pushing the filters past the maps, over streams of 1000 clicks and 500
+/// views.
+fn dataflow_optimizer() -> CascadesOptimizer<DataflowTyp> {
+    let mut rules: Vec<Arc<dyn Rule<DataflowTyp, CascadesOptimizer<DataflowTyp>>>> =
+        vec![Arc::new(FilterPastMapRule::new())];
+    rules.extend(ImplementationRule::all());
+    CascadesOptimizer::new(
+        rules,
+        Box::new(DataflowCostModel {
+            stream_rows: [("clicks".to_string(), 1000.0), ("views".to_string(), 500.0)].into(),
+        }),
+        fields_property_builder(),
+    )
+}
+
+#[test]
+fn heuristics_filter_past_map() {
+    let mut optimizer = HeuristicsOptimizer::new_with_rules(
+        vec![Arc::new(FilterPastMapRule::new())],
+        HeuristicsOptimizerOptions {
+            apply_order: ApplyOrder::TopDown,
+            enable_physical_prop_passthrough: false,
+        },
+        fields_property_builder(),
+        vec![].into(),
+    );
+    let input = union(
+        source("clicks", &["user", "url"]),
+        source("views", &["user", "url"]),
+    );
+    let optimized = optimizer.optimize(dataflow()).unwrap();
+    assert_eq!(
+        optimized,
+        map(
+            filter(input.clone(), "is_bot", &["user"]),
+            "parse_url",
+            &["user", "domain"]
+        )
+    );
+
+    // `domain` is computed by the map, so the filter stays on top of it.
+    let plan = filter(
+        map(input, "parse_url", &["user", "domain"]),
+        "is_tracker",
+        &["domain"],
+    );
+    assert_eq!(optimizer.optimize(plan.clone()).unwrap(), plan);
+}
+
+#[test]
+fn heuristics_cost_check_dataflow() {
+    let mut optimizer = HeuristicsOptimizer::new_with_rules(
+        vec![Arc::new(FilterPastMapRule::new())],
+        HeuristicsOptimizerOptions {
+            apply_order: ApplyOrder::TopDown,
+            enable_physical_prop_passthrough: false,
+        },
+        fields_property_builder(),
+        vec![].into(),
+    );
+    // filtering before the map is cheaper
+    optimizer.set_cost_check(Some(Box::new(CascadesCostEstimator::new(
+        ImplementationRule::all(),
+        Arc::new(DataflowCostModel {
+            stream_rows: [("clicks".to_string(), 1000.0), ("views".to_string(), 500.0)].into(),
+        }),
+        fields_property_builder(),
+    ))));
+    let optimized = optimizer.optimize(dataflow()).unwrap();
+    assert_ne!(optimized, dataflow());
+    assert_eq!(optimizer.rejected_rewrite_count(), 0);
+
+    // an estimator preferring the filter on top rejects the rewrite
+    optimizer.set_cost_check(Some(Box::new(|plan: ArcPlanNode<DataflowTyp>| {
+        let cost = if plan.typ == DataflowTyp::Filter {
+            0.0
+        } else {
+            1.0
+        };
+        Some(cost)
+    })));
+    assert_eq!(optimizer.optimize(dataflow()).unwrap(), dataflow());
+    assert_eq!(optimizer.rejected_rewrite_count(), 1);
+}
+
+#[test]
+fn cascades_cheapest_dataflow() {
+    let mut optimizer = dataflow_optimizer();
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+    let optimized = optimizer
+        .step_get_optimize_rel(group_id, &mut None)
+        .unwrap();
+    let input = union(
+        source("clicks", &["user", "url"]),
+        source("views", &["user", "url"]),
+    );
+    assert_eq!(
+        optimized,
+        to_physical(map(
+            filter(input, "is_bot", &["user"]),
+            "parse_url",
+            &["user", "domain"]
+        ))
+    );
+    let fields = optimizer.get_property_by_group::<FieldsPropertyBuilder>(group_id, 0);
+    assert_eq!(fields.0, vec!["user", "domain"]);
+
+    let winner_rules = optimizer
+        .step_winner_rules(group_id)
+        .into_iter()
+        .map(|rule_id| optimizer.rules()[rule_id].name())
+        .collect_vec();
+    assert!(winner_rules.contains(&"filter_past_map"));
+    assert!(winner_rules.contains(&"implementation"));
+}
+
+#[test]
+fn cascades_plan_meta_of_unoptimized_dataflow() {
+    let mut optimizer = dataflow_optimizer();
+    optimizer.step_optimize(dataflow()).unwrap();
+
+    // The plan which was not chosen: the map transforms all the rows before the filter.
+    let plan = to_physical(dataflow());
+    let mut meta = PlanAnnotations::new();
+    let group_id = optimizer
+        .step_compute_plan_meta(plan.clone(), &mut meta)
+        .unwrap();
+    let root_meta = meta.get(&plan).unwrap();
+    assert_eq!(root_meta.group_id, group_id);
+    assert_eq!(
+        root_meta.weighted_cost,
+        1500.0 + 1500.0 * MAP_COST_PER_ROW + 1500.0
+    );
+    assert_eq!(root_meta.row_cnt, Some(150.0));
+    assert_eq!(
+        root_meta.cost_components,
+        vec![("cost0".to_string(), root_meta.weighted_cost)]
+    );
+    assert_eq!(meta.len(), 5);
+
+    let unknown = to_physical(filter(source("clicks", &["user"]), "is_bot", &["user"]));
+    assert!(optimizer
+        .step_compute_plan_meta(unknown, &mut PlanAnnotations::new())
+        .is_err());
+}
+
+#[test]
+fn cascades_cancel_dataflow_from_progress() {
+    let mut optimizer = dataflow_optimizer();
+    let token = CancellationToken::new();
+    optimizer.set_cancellation_token(token.clone());
+    let reports = Arc::new(Mutex::new(vec![]));
+    let reports_hook = reports.clone();
+    optimizer.set_progress_hook(1, move |progress| {
+        // Stop as soon as the first task ran, as a UI would on "use the best plan now".
+        token.cancel();
+        reports_hook.lock().unwrap().push(progress.clone());
+    });
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+    let optimized = optimizer
+        .step_get_optimize_rel(group_id, &mut None)
+        .unwrap();
+
+    // The filter was not pushed past the map, but there is still a physical plan.
+    assert_eq!(optimized, to_physical(dataflow()));
+    assert!(optimizer.ctx.cancelled);
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports[0].steps, 1);
+    assert_eq!(reports[0].best_cost, None);
+    assert!(reports.last().unwrap().budget_used);
+    assert!(reports.windows(2).all(|w| w[0].steps < w[1].steps));
+}
+
+#[test]
+fn cascades_time_out_dataflow() {
+    let mut optimizer = dataflow_optimizer();
+    optimizer.prop.timeout = Some(Duration::ZERO);
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+    let optimized = optimizer
+        .step_get_optimize_rel(group_id, &mut None)
+        .unwrap();
+
+    // The filter was not pushed past the map, but there is still a physical plan.
+    assert_eq!(optimized, to_physical(dataflow()));
+    assert!(optimizer.ctx.timed_out);
+    assert!(!optimizer.ctx.cancelled);
+
+    // The next search gets its own budget.
+    optimizer.prop.timeout = None;
+    optimizer.step_clear_winner();
+    optimizer.fire_optimize_tasks(group_id).unwrap();
+    assert!(!optimizer.ctx.timed_out);
+    assert_ne!(
+        optimizer
+            .step_get_optimize_rel(group_id, &mut None)
+            .unwrap(),
+        optimized
+    );
+}
+
+#[test]
+fn cascades_optimize_dataflow_incrementally() {
+    let mut optimizer = dataflow_optimizer();
+    let (group_id, _) = optimizer.add_new_expr(dataflow());
+    let improvements = optimizer.optimize_incremental(group_id).collect_vec();
+
+    assert!(!improvements.is_empty());
+    assert!(improvements.windows(2).all(|w| w[0].cost >= w[1].cost));
+    assert!(improvements.windows(2).all(|w| w[0].plan != w[1].plan));
+    assert!(improvements.windows(2).all(|w| w[0].steps <= w[1].steps));
+    let last = improvements.last().unwrap();
+    assert_eq!(
+        last.plan,
+        optimizer
+            .step_get_optimize_rel(group_id, &mut None)
+            .unwrap()
+    );
+    let winner = optimizer
+        .memo()
+        .get_group_winner(optimizer.resolve_group(group_id));
+    assert_eq!(
+        last.cost,
+        winner.as_full_winner().unwrap().total_weighted_cost
+    );
+
+    // Stopping at the first plan still leaves a winner.
+    optimizer.step_clear_winner();
+    let first = optimizer.optimize_incremental(group_id).next().unwrap();
+    assert_eq!(first.plan, improvements[0].plan);
+    assert!(optimizer.step_get_optimize_rel(group_id, &mut None).is_ok());
+}
+
+#[test]
+fn cascades_retain_dataflow_alternatives() {
+    let mut optimizer = dataflow_optimizer();
+    optimizer.prop.retain_alternatives = Some(4);
+    optimizer.prop.disable_pruning = true;
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+
+    let alternatives = optimizer.get_group_alternatives(group_id, 4);
+    let winner = optimizer
+        .memo()
+        .get_group_winner(optimizer.resolve_group(group_id));
+    assert_eq!(
+        alternatives[0].expr_id,
+        winner.as_full_winner().unwrap().expr_id
+    );
+    // The rejected plan where the map transforms all the rows before the filter.
+    assert_eq!(alternatives.len(), 2);
+    assert_eq!(
+        alternatives[1].total_weighted_cost,
+        1500.0 + 1500.0 * MAP_COST_PER_ROW + 1500.0
+    );
+    assert_eq!(optimizer.get_group_alternatives(group_id, 1).len(), 1);
+
+    optimizer.step_clear_winner();
+    assert!(optimizer.get_group_alternatives(group_id, 4).is_empty());
+}
+
+fn nan_stats_optimizer(cost_floor: Option<f64>) -> CascadesOptimizer<DataflowTyp> {
+    let mut optimizer = CascadesOptimizer::new(
+        ImplementationRule::all(),
+        Box::new(DataflowCostModel {
+            // e.g., an average over no samples
+            stream_rows: [
+                ("clicks".to_string(), 1000.0),
+                ("views".to_string(), f64::NAN),
+            ]
+            .into(),
+        }),
+        fields_property_builder(),
+    );
+    optimizer.prop.cost_floor = cost_floor;
+    optimizer
+}
+
+#[test]
+fn cascades_clamp_nan_dataflow_costs() {
+    let mut optimizer = nan_stats_optimizer(Some(1.0));
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+
+    let winner = optimizer
+        .memo()
+        .get_group_winner(optimizer.resolve_group(group_id));
+    let winner = winner.as_full_winner().unwrap();
+    assert!(winner.total_cost.validate().is_ok());
+    assert!(winner.total_weighted_cost.is_finite());
+    assert!(optimizer.stats.invalid_cost_count > 0);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "invalid cost component 0: NaN")]
+fn cascades_assert_valid_dataflow_costs() {
+    let mut optimizer = nan_stats_optimizer(None);
+    let _ = optimizer.step_optimize(dataflow());
+}
+
+#[test]
+fn cascades_evict_dataflow_exprs() {
+    let mut optimizer = dataflow_optimizer();
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+    let optimized = optimizer
+        .step_get_optimize_rel(group_id, &mut None)
+        .unwrap();
+    let plan_space = optimizer.memo().estimated_plan_space();
+
+    // under the capacity
+    optimizer.set_eviction_policy(plan_space, LruEviction);
+    assert_eq!(optimizer.step_evict(), 0);
+
+    optimizer.set_eviction_policy(plan_space - 2, LruEviction);
+    assert_eq!(optimizer.step_evict(), 2);
+    assert_eq!(optimizer.memo().estimated_plan_space(), plan_space - 2);
+
+    optimizer.set_eviction_policy(0, KeepWinnersOnly);
+    optimizer.step_evict();
+    let group_ids = optimizer.memo().get_all_group_ids();
+    assert_eq!(optimizer.memo().estimated_plan_space(), group_ids.len());
+    assert_eq!(optimizer.stats.eviction_count, 2);
+    assert_eq!(
+        optimizer.stats.evicted_expr_count,
+        plan_space - group_ids.len()
+    );
+    assert_eq!(
+        optimizer
+            .step_get_optimize_rel(group_id, &mut None)
+            .unwrap(),
+        optimized
+    );
+
+    // the winners are costed again
+    optimizer.step_clear_winner();
+    optimizer.fire_optimize_tasks(group_id).unwrap();
+    assert_eq!(
+        optimizer
+            .step_get_optimize_rel(group_id, &mut None)
+            .unwrap(),
+        optimized
+    );
+}
+
+#[test]
+fn cascades_sample_dataflow_rules() {
+    let optimize = |sample_rate: f64| {
+        let mut optimizer = dataflow_optimizer();
+        optimizer.prop.exploration_strategy = ExplorationStrategy::Randomized {
+            seed: 42,
+            sample_rate,
+        };
+        let group_id = optimizer.step_optimize(dataflow()).unwrap();
+        let optimized = optimizer
+            .step_get_optimize_rel(group_id, &mut None)
+            .unwrap();
+        (optimized, optimizer.stats.sampled_out_rule_count)
+    };
+
+    // no transformation is sampled, the plan is only implemented
+    let (optimized, sampled_out) = optimize(0.0);
+    assert_eq!(optimized, to_physical(dataflow()));
+    assert!(sampled_out > 0);
+
+    // all the transformations are sampled, as in the exhaustive exploration
+    let (optimized, sampled_out) = optimize(1.0);
+    assert_ne!(optimized, to_physical(dataflow()));
+    assert_eq!(sampled_out, 0);
+}
+
+#[test]
+fn cascades_verify_dataflow_memo_integrity() {
+    let mut optimizer = dataflow_optimizer();
+    optimizer.prop.verify_memo_integrity = true;
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+    optimizer.memo().verify_integrity().unwrap();
+    assert!(optimizer.step_get_optimize_rel(group_id, &mut None).is_ok());
+}
+
+#[test]
+fn cascades_cache_dataflow_costs() {
+    let mut optimizer = dataflow_optimizer();
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+    let optimized = optimizer
+        .step_get_optimize_rel(group_id, &mut None)
+        .unwrap();
+
+    // the next stage costs the same expressions with the same inputs
+    let hits = optimizer.stats.cost_cache_hit_count;
+    optimizer.step_next_stage();
+    optimizer.fire_optimize_tasks(group_id).unwrap();
+    assert!(optimizer.stats.cost_cache_hit_count > hits);
+    assert_eq!(
+        optimizer
+            .step_get_optimize_rel(group_id, &mut None)
+            .unwrap(),
+        optimized
+    );
+}
+
+#[test]
+fn cascades_replay_dataflow_trace() {
+    let mut optimizer = dataflow_optimizer();
+    optimizer.prop.enable_tracing = true;
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+    let group_id = optimizer.resolve_group(group_id);
+    let winner = optimizer
+        .memo()
+        .get_group_winner(group_id)
+        .as_full_winner()
+        .unwrap()
+        .expr_id;
+
+    let replay = optimizer.step_trace_replay();
+    let state = replay.state_at(replay.len());
+    assert_eq!(state.winner(group_id), Some(winner));
+    // find the event where the root group got its final winner
+    let step = replay
+        .bisect(|state| state.winner(group_id) == Some(winner))
+        .unwrap();
+    assert!(step > 0);
+    assert_ne!(replay.state_at(step - 1).winner(group_id), Some(winner));
+    assert!(!replay.state_at(step).produced_exprs.is_empty());
+}
+
+#[test]
+fn cascades_dump_dataflow_memo() {
+    let mut optimizer = dataflow_optimizer();
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+    let dump = |optimizer: &CascadesOptimizer<DataflowTyp>, options: &MemoDumpOptions| {
+        let mut buf = String::new();
+        optimizer.dump_with_options(&mut buf, options).unwrap();
+        buf
+    };
+    let count =
+        |dump: &str, prefix: &str| dump.lines().filter(|line| line.starts_with(prefix)).count();
+
+    let full = dump(&optimizer, &MemoDumpOptions::default());
+    assert_eq!(
+        count(&full, "group_id="),
+        optimizer.memo().get_all_group_ids().len()
+    );
+    let root = dump(
+        &optimizer,
+        &MemoDumpOptions {
+            root: Some(group_id),
+            max_depth: Some(0),
+            ..Default::default()
+        },
+    );
+    assert_eq!(count(&root, "group_id="), 1);
+    assert!(root.starts_with(&format!("group_id={} ", optimizer.resolve_group(group_id))));
+    let winners = dump(
+        &optimizer,
+        &MemoDumpOptions {
+            root: Some(group_id),
+            winners_only: true,
+            ..Default::default()
+        },
+    );
+    assert!(count(&winners, "group_id=") > 1);
+    assert_eq!(count(&winners, "  expr_id="), count(&winners, "group_id="));
+    assert!(count(&winners, "  expr_id=") < count(&full, "  expr_id="));
+
+    let last_stage = MemoDumpOptions {
+        last_stage_only: true,
+        ..Default::default()
+    };
+    assert!(!dump(&optimizer, &last_stage).is_empty());
+    optimizer.step_next_stage();
+    assert!(dump(&optimizer, &last_stage).is_empty());
+}
+
+#[test]
+fn cascades_break_down_dataflow_cost() {
+    let mut optimizer = dataflow_optimizer();
+    let group_id = optimizer.step_optimize(dataflow()).unwrap();
+    let optimized = optimizer
+        .step_get_optimize_rel(group_id, &mut None)
+        .unwrap();
+
+    let breakdown = optimizer.step_cost_breakdown(group_id).unwrap();
+    assert_eq!(breakdown.operator, optimized.typ.to_string());
+    assert_eq!(breakdown.children.len(), optimized.children.len());
+    // the total cost of an operator is its own cost plus the total costs of its inputs
+    for (_, node) in breakdown.iter() {
+        let inputs = node
+            .children
+            .iter()
+            .map(|child| child.total_weighted_cost)
+            .sum::<f64>();
+        assert!((node.operation_weighted_cost + inputs - node.total_weighted_cost).abs() < 1e-6);
+    }
+    assert_eq!(
+        breakdown.to_string().lines().count(),
+        breakdown.iter().count()
+    );
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! A what-if index advisor: the queries of a workload are optimized again with each candidate
+//! index added to the catalog hypothetically, and the indexes are ranked by how much cheaper the
+//! chosen plans get. The cascades optimizer and its cost model are the costing engine, the
+//! indexes are never built.
+
+use anyhow::{Context, Result};
+use datafusion::logical_expr::LogicalPlan;
+use optd_og_datafusion_repr::properties::schema::IndexDef;
+use optd_og_datafusion_repr::OptimizationResult;
+
+use crate::OptdDfContext;
+
+/// A hypothetical index of a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexCandidate {
+    pub table: String,
+    pub index: IndexDef,
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexAdvice {
+    pub candidate: IndexCandidate,
+    /// How the cost of each query of the workload changes with the index, in the order of the
+    /// workload. Negative if the query gets cheaper.
+    pub query_cost_deltas: Vec<f64>,
+}
+
+impl IndexAdvice {
+    pub fn total_cost_delta(&self) -> f64 {
+        self.query_cost_deltas.iter().sum()
+    }
+}
+
+fn root_cost(result: &OptimizationResult) -> f64 {
+    result
+        .meta
+        .get(&result.plan)
+        .map_or(0.0, |meta| meta.weighted_cost)
+}
+
+impl OptdDfContext {
+    /// Evaluates each candidate index on its own against the workload. Returns the advice
+    /// ordered by the total cost delta, the most beneficial index first.
+    pub async fn advise_indexes(
+        &self,
+        workload: &[&str],
+        candidates: Vec<IndexCandidate>,
+    ) -> Result<Vec<IndexAdvice>> {
+        let mut logical_plans = Vec::with_capacity(workload.len());
+        for sql in workload {
+            logical_plans.push(self.ctx.sql(sql).await?.into_optimized_plan()?);
+        }
+        // The memo table of adaptive mode is kept across queries, where the index lookups
+        // would not be explored once the rule fired without the candidate index.
+        let enable_adaptive = {
+            let mut guard = self.optimizer.optimizer.lock().unwrap();
+            let optimizer = guard.as_mut().context("the optimizer is already in use")?;
+            let enable_adaptive = optimizer.adaptive_enabled();
+            optimizer.enable_adaptive(false);
+            enable_adaptive
+        };
+        let advice = self.advise_indexes_inner(&logical_plans, candidates).await;
+        self.optimizer
+            .optimizer
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .enable_adaptive(enable_adaptive);
+        self.optimizer.catalog.clear_hypothetical_indexes();
+        advice
+    }
+
+    async fn advise_indexes_inner(
+        &self,
+        logical_plans: &[LogicalPlan],
+        candidates: Vec<IndexCandidate>,
+    ) -> Result<Vec<IndexAdvice>> {
+        let baseline = self.workload_costs(logical_plans).await?;
+        let mut advice = Vec::with_capacity(candidates.len());
+        for candidate in candidates {
+            self.optimizer
+                .catalog
+                .set_hypothetical_indexes(&candidate.table, vec![candidate.index.clone()]);
+            let costs = self.workload_costs(logical_plans).await?;
+            self.optimizer.catalog.clear_hypothetical_indexes();
+            advice.push(IndexAdvice {
+                candidate,
+                query_cost_deltas: costs
+                    .iter()
+                    .zip(&baseline)
+                    .map(|(cost, baseline)| cost - baseline)
+                    .collect(),
+            });
+        }
+        advice.sort_by(|a, b| a.total_cost_delta().total_cmp(&b.total_cost_delta()));
+        Ok(advice)
+    }
+
+    /// The costs of the plans optd_og chooses for the queries.
+    async fn workload_costs(&self, logical_plans: &[LogicalPlan]) -> Result<Vec<f64>> {
+        let state = self.ctx.state();
+        let mut costs = Vec::with_capacity(logical_plans.len());
+        for logical_plan in logical_plans {
+            let (_, result) = self
+                .optimizer
+                .optimize_logical_plan_with_result(logical_plan, &state)
+                .await?;
+            costs.push(root_cost(&result));
+        }
+        Ok(costs)
+    }
+}
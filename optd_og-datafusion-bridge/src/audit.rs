@@ -0,0 +1,224 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Planning audit records, which hold everything the plan of a query was chosen with (the
+//! settings of the optimizer, the statistics and the cardinality hints), so that the plan can be
+//! reproduced later in a sandboxed optimizer, e.g., to investigate why the plan of a query
+//! changed overnight.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use datafusion::catalog::CatalogProviderList;
+use optd_og_datafusion_repr::cost::CardinalityHints;
+use optd_og_datafusion_repr::plan_nodes::dispatch_plan_explain_to_string;
+use optd_og_datafusion_repr::{DatafusionOptimizer, StageConfig};
+use optd_og_datafusion_repr_adv_cost::adv_stats::stats::DataFusionBaseTableStats;
+use serde::{Deserialize, Serialize};
+
+use crate::{create_df_context, OptdDfContext};
+
+/// The settings of the optimizer a plan was chosen with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuditConfig {
+    pub use_df_logical: bool,
+    pub enable_adaptive: bool,
+    pub enable_heuristic: bool,
+    pub stages: Vec<StageConfig>,
+    /// The cascades rules disabled in all stages.
+    pub disabled_rules: Vec<String>,
+    pub nlj_row_threshold: Option<usize>,
+    pub partial_explore_iter: Option<usize>,
+    pub partial_explore_space: Option<usize>,
+}
+
+impl AuditConfig {
+    pub fn capture(optimizer: &DatafusionOptimizer, use_df_logical: bool) -> Self {
+        let cascades = optimizer.optd_og_cascades_optimizer();
+        let disabled_rules = cascades
+            .rules()
+            .iter()
+            .enumerate()
+            .filter(|(rule_id, _)| cascades.is_rule_disabled(*rule_id))
+            .map(|(_, rule)| rule.name().to_string())
+            .collect();
+        Self {
+            use_df_logical,
+            enable_adaptive: optimizer.adaptive_enabled(),
+            enable_heuristic: optimizer.is_heuristic_enabled(),
+            stages: optimizer.optimization_stages().to_vec(),
+            disabled_rules,
+            nlj_row_threshold: optimizer.nlj_row_threshold(),
+            partial_explore_iter: cascades.prop.partial_explore_iter,
+            partial_explore_space: cascades.prop.partial_explore_space,
+        }
+    }
+
+    /// Applies the settings to an optimizer created with the same `enable_adaptive`.
+    fn apply(&self, optimizer: &mut DatafusionOptimizer) -> Result<()> {
+        optimizer.enable_heuristic(self.enable_heuristic);
+        optimizer.set_optimization_stages(self.stages.clone())?;
+        optimizer.set_nlj_row_threshold(self.nlj_row_threshold);
+        let cascades = optimizer.optd_og_optimizer_mut();
+        for rule_name in &self.disabled_rules {
+            cascades.disable_rule_by_name(rule_name);
+        }
+        cascades.prop.partial_explore_iter = self.partial_explore_iter;
+        cascades.prop.partial_explore_space = self.partial_explore_space;
+        Ok(())
+    }
+}
+
+/// Everything the plan of a query was chosen with, see the module documentation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlanningAuditRecord {
+    pub sql: String,
+    pub config: AuditConfig,
+    /// The statistics of the advanced cost model, `None` if the plan was costed with the
+    /// default cost model.
+    pub stats: Option<DataFusionBaseTableStats>,
+    pub cardinality_hints: CardinalityHints,
+    /// The chosen physical plan, as explained by optd_og.
+    pub plan: String,
+}
+
+/// The plan chosen in the sandbox for the query of an audit record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reproduction {
+    pub plan: String,
+    /// Whether the plan is the one of the audit record.
+    pub reproduced: bool,
+}
+
+impl PlanningAuditRecord {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(std::fs::write(path, serde_json::to_vec_pretty(self)?)?)
+    }
+
+    /// Plans the query again in a new optimizer created with the settings, statistics and hints
+    /// of the record, resolving the tables in `catalog`. The runtime statistics of adaptive mode
+    /// are not part of the record, the sandbox optimizes the query as a first run would.
+    pub async fn reproduce(&self, catalog: Arc<dyn CatalogProviderList>) -> Result<Reproduction> {
+        let sandbox = create_df_context(
+            None,
+            None,
+            Some(catalog),
+            self.config.enable_adaptive,
+            self.config.use_df_logical,
+            self.stats.is_some(),
+            self.stats.clone(),
+        )
+        .await?;
+        {
+            let mut guard = sandbox.optimizer.optimizer.lock().unwrap();
+            let optimizer = guard.as_mut().unwrap();
+            self.config.apply(optimizer)?;
+            *optimizer.cardinality_hints.lock().unwrap() = self.cardinality_hints.clone();
+        }
+        let plan = plan_sql(&sandbox, &self.sql).await?;
+        Ok(Reproduction {
+            reproduced: plan == self.plan,
+            plan,
+        })
+    }
+}
+
+impl OptdDfContext {
+    /// Plans the query and records everything its plan was chosen with. The statistics of the
+    /// advanced cost model cannot be read back from the optimizer, pass the ones it was created
+    /// with.
+    pub async fn capture_audit_record(
+        &self,
+        sql: &str,
+        stats: Option<DataFusionBaseTableStats>,
+    ) -> Result<PlanningAuditRecord> {
+        let plan = plan_sql(self, sql).await?;
+        let use_df_logical = self.ctx.state().config().options().optimizer.max_passes > 0;
+        let guard = self.optimizer.optimizer.lock().unwrap();
+        let optimizer = guard.as_ref().context("the optimizer is already in use")?;
+        Ok(PlanningAuditRecord {
+            sql: sql.to_string(),
+            config: AuditConfig::capture(optimizer, use_df_logical),
+            stats,
+            cardinality_hints: optimizer.cardinality_hints.lock().unwrap().clone(),
+            plan,
+        })
+    }
+}
+
+async fn plan_sql(ctx: &OptdDfContext, sql: &str) -> Result<String> {
+    let logical_plan = ctx.ctx.sql(sql).await?.into_optimized_plan()?;
+    let (_, result) = ctx
+        .optimizer
+        .optimize_logical_plan_with_result(&logical_plan, &ctx.ctx.state())
+        .await?;
+    Ok(dispatch_plan_explain_to_string(result.plan, None))
+}
+
+type RecordChange = fn(&mut PlanningAuditRecord, &PlanningAuditRecord);
+
+/// The settings and inputs of an audit record, in the order [`bisect_plan_change`] applies them.
+const RECORD_CHANGES: [(&str, RecordChange); 9] = [
+    ("stats", |record, to| record.stats = to.stats.clone()),
+    ("cardinality_hints", |record, to| {
+        record.cardinality_hints = to.cardinality_hints.clone()
+    }),
+    ("use_df_logical", |record, to| {
+        record.config.use_df_logical = to.config.use_df_logical
+    }),
+    ("enable_adaptive", |record, to| {
+        record.config.enable_adaptive = to.config.enable_adaptive
+    }),
+    ("enable_heuristic", |record, to| {
+        record.config.enable_heuristic = to.config.enable_heuristic
+    }),
+    ("stages", |record, to| {
+        record.config.stages = to.config.stages.clone()
+    }),
+    ("disabled_rules", |record, to| {
+        record.config.disabled_rules = to.config.disabled_rules.clone()
+    }),
+    ("nlj_row_threshold", |record, to| {
+        record.config.nlj_row_threshold = to.config.nlj_row_threshold
+    }),
+    ("budgets", |record, to| {
+        record.config.partial_explore_iter = to.config.partial_explore_iter;
+        record.config.partial_explore_space = to.config.partial_explore_space;
+    }),
+];
+
+/// Finds the change between two audit records of the same query, e.g., of yesterday and of
+/// today, which changed its plan: starting from the settings of `from`, the changes to the
+/// settings and inputs of `to` are applied one at a time, and the query is reproduced after
+/// each one. Returns the name of the first change after which the plan differs, or `None` if
+/// the plan only changes with something the records do not hold, e.g., the tables.
+pub async fn bisect_plan_change(
+    from: &PlanningAuditRecord,
+    to: &PlanningAuditRecord,
+    catalog: Arc<dyn CatalogProviderList>,
+) -> Result<Option<&'static str>> {
+    let baseline = from.reproduce(catalog.clone()).await?.plan;
+    let mut record = from.clone();
+    for (name, apply) in RECORD_CHANGES {
+        let before = serde_json::to_value(&record)?;
+        apply(&mut record, to);
+        if serde_json::to_value(&record)? == before {
+            continue;
+        }
+        let plan = record.reproduce(catalog.clone()).await?.plan;
+        if plan != baseline {
+            return Ok(Some(name));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,176 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! The `optd.*` options of a datafusion session, which can be set in SQL, e.g.,
+//! `SET optd.table_row_hint = 'lineitem=6000000'`.
+
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use datafusion::common::config::ConfigExtension;
+use datafusion::common::extensions_options;
+use itertools::Itertools;
+use optd_og_core::cascades::OptimizerProperties;
+use optd_og_datafusion_repr::cost::DefaultSelectivityOverrides;
+use optd_og_datafusion_repr::DatafusionOptimizer;
+
+extensions_options! {
+    /// The options of optd_og in the datafusion session config.
+    pub struct OptdDFConfig {
+        /// The row counts of the tables without statistics, as `table=rows` separated by
+        /// commas, e.g., `lineitem=6000000,orders=1500000`.
+        pub table_row_hint: String, default = String::new()
+        /// Overrides the selectivity the advanced cost model estimates for the equalities on the
+        /// columns without statistics, e.g., `a = 1`.
+        pub default_eq_sel: Option<f64>, default = None
+        /// Overrides the selectivity the advanced cost model estimates for the range comparisons
+        /// on the columns without statistics, e.g., `a < 1`.
+        pub default_ineq_sel: Option<f64>, default = None
+        /// Overrides the selectivity the advanced cost model estimates for the predicates it
+        /// knows nothing about, e.g., a column compared to a function call.
+        pub default_unk_sel: Option<f64>, default = None
+        /// Whether the selectivity of the filters over the tables without statistics is
+        /// estimated by running them over a sample of the table before optimizing the query.
+        pub sample_selectivity: bool, default = false
+        /// The number of rows read from a table to estimate the selectivity of a filter.
+        pub sample_rows: usize, default = 1000
+        /// The number of rows read from the tables to estimate the selectivities of the filters
+        /// of a query, after which the remaining filters are not sampled.
+        pub sample_budget: usize, default = 10000
+        /// In adaptive mode, how many times more or fewer rows than estimated an operator has
+        /// to produce for the query to be re-planned with the observed row count, see
+        /// `DatafusionOptimizer::set_misestimate_threshold`.
+        pub misestimate_threshold: Option<f64>, default = None
+        /// The hash joins estimated to produce at most this fraction of the rows of their probe
+        /// side filter the probe side scan with the range of their build side keys, see
+        /// `DatafusionOptimizer::set_runtime_filter_selectivity`.
+        pub runtime_filter_selectivity: Option<f64>, default = None
+        /// Overrides the number of optimizer tasks after which no more rules are applied, see
+        /// `OptimizerProperties::partial_explore_iter`.
+        pub explore_iter_budget: Option<usize>, default = None
+        /// Overrides the plan space size after which no more logical rules are applied, see
+        /// `OptimizerProperties::partial_explore_space`.
+        pub explore_space_budget: Option<usize>, default = None
+        /// The time in milliseconds after which each optimization stage stops applying rules
+        /// and finishes with the best plan found so far, see `OptimizerProperties::timeout`.
+        pub timeout_ms: Option<u64>, default = None
+        /// The only cascades transformation rules applied, separated by commas, or all of them
+        /// if empty. The implementation rules are always applied.
+        pub rules: String, default = String::new()
+        /// The cascades rules not applied, separated by commas.
+        pub disabled_rules: String, default = String::new()
+        /// Whether `EXPLAIN` shows the optd_og logical plans, before and after the heuristic
+        /// rewrites.
+        pub explain_logical: bool, default = true
+        /// Whether `EXPLAIN` shows the logical join orders in the memo table and the join order
+        /// of the chosen plan.
+        pub explain_join_orders: bool, default = true
+        /// Whether `EXPLAIN` shows the optd_og physical plan and the rules it was produced by.
+        pub explain_physical: bool, default = true
+    }
+}
+
+impl ConfigExtension for OptdDFConfig {
+    const PREFIX: &'static str = "optd";
+}
+
+impl OptdDFConfig {
+    /// The row counts of `optd.table_row_hint`.
+    pub fn table_row_hints(&self) -> Result<Vec<(String, usize)>> {
+        parse_table_row_hints(&self.table_row_hint)
+    }
+
+    /// The default selectivities set in the session. Fails if one is not between 0 and 1.
+    pub fn default_selectivities(&self) -> Result<DefaultSelectivityOverrides> {
+        for (name, sel) in [
+            ("default_eq_sel", self.default_eq_sel),
+            ("default_ineq_sel", self.default_ineq_sel),
+            ("default_unk_sel", self.default_unk_sel),
+        ] {
+            if let Some(sel) = sel {
+                if !(0.0..=1.0).contains(&sel) {
+                    bail!("optd.{} should be between 0 and 1, got {}", name, sel);
+                }
+            }
+        }
+        Ok(DefaultSelectivityOverrides {
+            eq: self.default_eq_sel,
+            ineq: self.default_ineq_sel,
+            unk: self.default_unk_sel,
+        })
+    }
+
+    /// Applies the exploration budgets set in the session, keeping the budgets of the optimizer
+    /// which are not set.
+    pub fn apply_exploration_budgets(&self, prop: &mut OptimizerProperties) {
+        if let Some(iter) = self.explore_iter_budget {
+            prop.partial_explore_iter = Some(iter);
+        }
+        if let Some(space) = self.explore_space_budget {
+            prop.partial_explore_space = Some(space);
+        }
+        if let Some(timeout_ms) = self.timeout_ms {
+            prop.timeout = Some(Duration::from_millis(timeout_ms));
+        }
+    }
+
+    /// The cascades rules `optd.rules` and `optd.disabled_rules` disable. Fails if a rule is
+    /// unknown.
+    pub fn rules_to_disable(&self, optimizer: &DatafusionOptimizer) -> Result<Vec<String>> {
+        let rules = optimizer.optd_og_cascades_optimizer().rules();
+        let known = |name: &str| rules.iter().any(|rule| rule.name() == name);
+        let enabled = split_rule_names(&self.rules).collect_vec();
+        let mut disabled = split_rule_names(&self.disabled_rules)
+            .map(str::to_string)
+            .collect_vec();
+        for rule_name in enabled
+            .iter()
+            .copied()
+            .chain(disabled.iter().map(String::as_str))
+        {
+            if !known(rule_name) {
+                bail!(
+                    "unknown rule {} in optd.rules or optd.disabled_rules",
+                    rule_name
+                );
+            }
+        }
+        if !enabled.is_empty() {
+            disabled.extend(
+                rules
+                    .iter()
+                    .filter(|rule| !rule.is_impl_rule() && !enabled.contains(&rule.name()))
+                    .map(|rule| rule.name().to_string()),
+            );
+        }
+        Ok(disabled)
+    }
+}
+
+fn split_rule_names(rules: &str) -> impl Iterator<Item = &str> {
+    rules
+        .split(',')
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+}
+
+fn parse_table_row_hints(hints: &str) -> Result<Vec<(String, usize)>> {
+    let mut table_rows = Vec::new();
+    for hint in hints
+        .split(',')
+        .map(str::trim)
+        .filter(|hint| !hint.is_empty())
+    {
+        let Some((table, rows)) = hint.split_once('=') else {
+            bail!("expected table=rows in optd.table_row_hint, got {}", hint);
+        };
+        let rows = rows
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid row count in optd.table_row_hint: {}", hint))?;
+        table_rows.push((table.trim().to_string(), rows));
+    }
+    Ok(table_rows)
+}
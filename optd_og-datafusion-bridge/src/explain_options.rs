@@ -0,0 +1,149 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! The optd_og options of an `EXPLAIN`, e.g.,
+//! `EXPLAIN (optd_budget = 1000, optd_rules = 'join_commute_rule') SELECT ...`, which change the
+//! optimizer for one statement without touching the session. An `optd_<name>` option sets
+//! `optd.<name>` of [`OptdDFConfig`](crate::OptdDFConfig) for the statement, and `optd_budget`
+//! is short for `optd.explore_iter_budget`. The `analyze` and `verbose` options are the flags of
+//! the datafusion `EXPLAIN`.
+
+use anyhow::{bail, Result};
+use datafusion::dataframe::DataFrame;
+use datafusion::prelude::SessionContext;
+
+/// An `EXPLAIN` statement with its options split off.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplainWithOptions {
+    /// The statement datafusion can parse, with the `analyze` and `verbose` flags.
+    pub sql: String,
+    /// The session options set for the statement, e.g., `optd.explore_iter_budget`.
+    pub options: Vec<(String, String)>,
+}
+
+/// Splits the options off an `EXPLAIN (...)` statement. Returns `None` if the statement is not
+/// an `EXPLAIN` or has no option list.
+pub fn parse_explain_options(sql: &str) -> Result<Option<ExplainWithOptions>> {
+    let sql = sql.trim_start();
+    let Some(keyword) = sql.get(..7) else {
+        return Ok(None);
+    };
+    if !keyword.eq_ignore_ascii_case("explain") {
+        return Ok(None);
+    }
+    let Some(rest) = sql[7..].trim_start().strip_prefix('(') else {
+        return Ok(None);
+    };
+    let (list, statement) = split_option_list(rest)?;
+
+    let (mut analyze, mut verbose) = (false, false);
+    let mut options = Vec::new();
+    for option in split_options(list) {
+        let (name, value) = match option.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(unquote(value.trim()))),
+            None => match option.split_once(char::is_whitespace) {
+                Some((name, value)) => (name.trim(), Some(unquote(value.trim()))),
+                None => (option, None),
+            },
+        };
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "analyze" | "verbose" => {
+                let enabled = match value.as_deref().map(str::to_ascii_lowercase).as_deref() {
+                    None | Some("true") | Some("on") => true,
+                    Some("false") | Some("off") => false,
+                    Some(value) => bail!("invalid value of EXPLAIN option {}: {}", name, value),
+                };
+                if name == "analyze" {
+                    analyze = enabled;
+                } else {
+                    verbose = enabled;
+                }
+            }
+            _ => {
+                let Some(key) = name.strip_prefix("optd_") else {
+                    bail!("unsupported EXPLAIN option {}", name);
+                };
+                let Some(value) = value else {
+                    bail!("EXPLAIN option {} needs a value", name);
+                };
+                let key = match key {
+                    "budget" => "explore_iter_budget",
+                    key => key,
+                };
+                options.push((format!("optd.{}", key), value));
+            }
+        }
+    }
+
+    let mut sql = "EXPLAIN ".to_string();
+    if analyze {
+        sql.push_str("ANALYZE ");
+    }
+    if verbose {
+        sql.push_str("VERBOSE ");
+    }
+    sql.push_str(statement.trim_start());
+    Ok(Some(ExplainWithOptions { sql, options }))
+}
+
+/// Splits `opt, ...) statement` at the parenthesis closing the option list.
+fn split_option_list(sql: &str) -> Result<(&str, &str)> {
+    let mut quoted = false;
+    for (idx, c) in sql.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            ')' if !quoted => return Ok((&sql[..idx], &sql[idx + 1..])),
+            _ => {}
+        }
+    }
+    bail!("unterminated EXPLAIN option list")
+}
+
+fn split_options(list: &str) -> Vec<&str> {
+    let mut options = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (idx, c) in list.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            ',' if !quoted => {
+                options.push(&list[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    options.push(&list[start..]);
+    options
+        .into_iter()
+        .map(str::trim)
+        .filter(|option| !option.is_empty())
+        .collect()
+}
+
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+    {
+        Some(value) => value.replace("''", "'"),
+        None => value.to_string(),
+    }
+}
+
+/// Plans a statement like [`SessionContext::sql`], applying the options of an `EXPLAIN (...)`
+/// to a copy of the session state, so that they only apply to the statement.
+pub async fn sql_with_explain_options(ctx: &SessionContext, sql: &str) -> Result<DataFrame> {
+    let Some(explain) = parse_explain_options(sql)? else {
+        return Ok(ctx.sql(sql).await?);
+    };
+    let mut state = ctx.state();
+    for (key, value) in &explain.options {
+        state.config_mut().options_mut().set(key, value)?;
+    }
+    let plan = state.create_logical_plan(&explain.sql).await?;
+    Ok(DataFrame::new(state, plan))
+}
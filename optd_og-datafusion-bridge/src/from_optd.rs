@@ -8,55 +8,313 @@ use std::sync::Arc;
 
 use anyhow::{bail, Context, Result};
 use async_recursion::async_recursion;
-use datafusion::arrow::datatypes::{Field, IntervalMonthDayNano, Schema, SchemaRef};
+use datafusion::arrow::array::{new_empty_array, Array, ArrayRef, MapArray, StructArray};
+use datafusion::arrow::buffer::OffsetBuffer;
+use datafusion::arrow::datatypes::{
+    DataType, Field, IntervalMonthDayNano, Schema, SchemaRef, TimeUnit,
+};
+use datafusion::common::UnnestOptions;
 use datafusion::datasource::source_as_provider;
-use datafusion::logical_expr::Operator;
+use datafusion::logical_expr::{Operator, ReturnTypeArgs, TableSource};
 use datafusion::physical_expr::aggregate::AggregateExprBuilder;
 use datafusion::physical_expr::{self, LexOrdering, PhysicalExprRef, ScalarFunctionExpr};
-use datafusion::physical_plan::aggregates::AggregateMode;
+use datafusion::physical_plan::aggregates::{AggregateExec, AggregateMode};
+use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
 use datafusion::physical_plan::joins::utils::{ColumnIndex, JoinFilter};
 use datafusion::physical_plan::joins::{CrossJoinExec, PartitionMode};
 use datafusion::physical_plan::projection::ProjectionExec;
-use datafusion::physical_plan::{self, ExecutionPlan, PhysicalExpr};
-use datafusion::scalar::ScalarValue;
-use optd_og_core::nodes::{PlanNodeMetaMap, PlanNodeOrGroup};
+use datafusion::physical_plan::repartition::RepartitionExec;
+use datafusion::physical_plan::sorts::sort_preserving_merge::SortPreservingMergeExec;
+use datafusion::physical_plan::unnest::{ListUnnest, UnnestExec};
+use datafusion::physical_plan::{self, ExecutionPlan, InputOrderMode, Partitioning, PhysicalExpr};
+use datafusion::scalar::{ScalarStructBuilder, ScalarValue};
+use optd_og_core::nodes::{PlanAnnotation, PlanAnnotations, PlanNodeOrGroup, Value};
 use optd_og_datafusion_repr::plan_nodes::{
-    ArcDfPlanNode, ArcDfPredNode, BetweenPred, BinOpPred, BinOpType, CastPred, ColumnRefPred,
-    ConstantPred, ConstantType, DfNodeType, DfPredType, DfReprPlanNode, DfReprPredNode, FuncPred,
-    FuncType, InListPred, JoinType, LikePred, LogOpPred, LogOpType, PhysicalAgg,
-    PhysicalEmptyRelation, PhysicalFilter, PhysicalHashJoin, PhysicalLimit, PhysicalNestedLoopJoin,
-    PhysicalProjection, PhysicalScan, PhysicalSort, SortOrderPred, SortOrderType,
+    ArcDfPlanNode, ArcDfPredNode, BetweenPred, BinOpPred, BinOpType, BuildSide, CastPred,
+    ColumnRefPred, ConstantPred, ConstantType, DfNodeType, DfPredType, DfReprPlanNode,
+    DfReprPredNode, FuncPred, FuncType, InListPred, JoinType, LikePred, ListPred, LogOpPred,
+    LogOpType, PhysicalAgg, PhysicalEmptyRelation, PhysicalFilter, PhysicalFinalAgg,
+    PhysicalHashJoin, PhysicalLimit, PhysicalNestedLoopJoin, PhysicalPartialAgg,
+    PhysicalProjection, PhysicalRuntimeFilter, PhysicalScan, PhysicalSingleton, PhysicalSort,
+    PhysicalStreamAgg, PhysicalTableFunction, SortOrderPred, SortOrderType, UNNEST_FUNCTION,
 };
+use optd_og_datafusion_repr::properties::ordering::OrderingProp;
 use optd_og_datafusion_repr::properties::schema::Schema as OptdSchema;
 
 use crate::physical_collector::CollectorExec;
+use crate::runtime_filter::{RuntimeFilter, RuntimeFilterBuildExec, RuntimeFilterExec};
+use crate::singleton::SingletonExec;
 use crate::OptdPlanContext;
 
-fn from_optd_og_schema(optd_og_schema: OptdSchema) -> Schema {
+/// The columns are looked up by position, but datafusion may look them up by name, so the
+/// names are made unique.
+pub(crate) fn from_optd_og_schema(optd_og_schema: OptdSchema) -> Schema {
     let match_type = |typ: &ConstantType| typ.into_data_type();
     let mut fields = Vec::with_capacity(optd_og_schema.len());
-    for field in optd_og_schema.fields {
-        fields.push(Field::new(
-            field.name,
-            match_type(&field.typ),
-            field.nullable,
-        ));
+    for (field, name) in optd_og_schema
+        .fields
+        .iter()
+        .zip(optd_og_schema.unique_names())
+    {
+        fields.push(Field::new(name, match_type(&field.typ), field.nullable));
     }
     Schema::new(fields)
 }
 
+/// The value of a constant. optd_og has no null constants.
+pub(crate) fn from_optd_og_constant(expr: &ConstantPred) -> Result<ScalarValue> {
+    let value = expr.value();
+    let value = match expr.constant_type() {
+        ConstantType::Bool => ScalarValue::Boolean(Some(value.as_bool())),
+        ConstantType::UInt8 => ScalarValue::UInt8(Some(value.as_u8())),
+        ConstantType::UInt16 => ScalarValue::UInt16(Some(value.as_u16())),
+        ConstantType::UInt32 => ScalarValue::UInt32(Some(value.as_u32())),
+        ConstantType::UInt64 => ScalarValue::UInt64(Some(value.as_u64())),
+        ConstantType::Int8 => ScalarValue::Int8(Some(value.as_i8())),
+        ConstantType::Int16 => ScalarValue::Int16(Some(value.as_i16())),
+        ConstantType::Int32 => ScalarValue::Int32(Some(value.as_i32())),
+        ConstantType::Int64 => ScalarValue::Int64(Some(value.as_i64())),
+        ConstantType::Float32 => ScalarValue::Float32(Some(value.as_f64() as f32)),
+        ConstantType::Float64 => ScalarValue::Float64(Some(value.as_f64())),
+        ConstantType::Decimal128(precision, scale) => {
+            let decimal = value
+                .as_decimal128()
+                .rescale(scale)
+                .with_context(|| format!("{value} does not fit in the scale {scale}"))?;
+            ScalarValue::Decimal128(Some(decimal.value), precision, scale)
+        }
+        ConstantType::Date => ScalarValue::Date32(Some(value.as_i64() as i32)),
+        ConstantType::Date64 => ScalarValue::Date64(Some(value.as_i64())),
+        ConstantType::Timestamp(unit) => {
+            let value = Some(expr.value_in_unit());
+            match unit {
+                TimeUnit::Second => ScalarValue::TimestampSecond(value, None),
+                TimeUnit::Millisecond => ScalarValue::TimestampMillisecond(value, None),
+                TimeUnit::Microsecond => ScalarValue::TimestampMicrosecond(value, None),
+                TimeUnit::Nanosecond => ScalarValue::TimestampNanosecond(value, None),
+            }
+        }
+        ConstantType::Time(unit) => {
+            let value = expr.value_in_unit();
+            match unit {
+                TimeUnit::Second => ScalarValue::Time32Second(Some(value as i32)),
+                TimeUnit::Millisecond => ScalarValue::Time32Millisecond(Some(value as i32)),
+                TimeUnit::Microsecond => ScalarValue::Time64Microsecond(Some(value)),
+                TimeUnit::Nanosecond => ScalarValue::Time64Nanosecond(Some(value)),
+            }
+        }
+        ConstantType::IntervalMonthDateNano => {
+            let value = value.as_i128();
+            ScalarValue::IntervalMonthDayNano(Some(IntervalMonthDayNano::new(
+                (value >> 96) as i32,
+                ((value >> 64) & ((1 << 32) - 1)) as i32,
+                (value & ((1 << 64) - 1)) as i64,
+            )))
+        }
+        ConstantType::Utf8String => ScalarValue::Utf8(Some(value.as_str().to_string())),
+        ConstantType::Binary => bail!("binary constants are not supported"),
+        ConstantType::List | ConstantType::Struct | ConstantType::Map => {
+            from_optd_og_nested_value(&value, &expr.data_type())?
+        }
+    };
+    Ok(value)
+}
+
+/// The value of a nested constant, or of one of its elements, of `data_type`.
+fn from_optd_og_nested_value(value: &Value, data_type: &DataType) -> Result<ScalarValue> {
+    let scalars = |values: &[Value], data_type: &DataType| -> Result<Vec<ScalarValue>> {
+        values
+            .iter()
+            .map(|value| from_optd_og_nested_value(value, data_type))
+            .collect()
+    };
+    let array = |values: Vec<ScalarValue>, data_type: &DataType| -> Result<ArrayRef> {
+        if values.is_empty() {
+            Ok(new_empty_array(data_type))
+        } else {
+            Ok(ScalarValue::iter_to_array(values)?)
+        }
+    };
+    let value = match data_type {
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            let values = scalars(&value.as_list(), field.data_type())?;
+            let list = ScalarValue::List(ScalarValue::new_list(
+                &values,
+                field.data_type(),
+                field.is_nullable(),
+            ));
+            list.cast_to(data_type)?
+        }
+        DataType::Struct(fields) => {
+            let mut builder = ScalarStructBuilder::new();
+            for (field, (_, value)) in fields.iter().zip(value.as_struct().iter()) {
+                builder = builder.with_scalar(
+                    field.clone(),
+                    from_optd_og_nested_value(value, field.data_type())?,
+                );
+            }
+            builder.build()?
+        }
+        DataType::Map(entries_field, ordered) => {
+            let DataType::Struct(fields) = entries_field.data_type() else {
+                bail!("the entries of a map are not a struct");
+            };
+            let (keys, items): (Vec<_>, Vec<_>) = value.as_map().iter().cloned().unzip();
+            let keys = scalars(&keys, fields[0].data_type())?;
+            let items = scalars(&items, fields[1].data_type())?;
+            let entries = StructArray::try_new(
+                fields.clone(),
+                vec![
+                    array(keys, fields[0].data_type())?,
+                    array(items, fields[1].data_type())?,
+                ],
+                None,
+            )?;
+            let offsets = OffsetBuffer::from_lengths([entries.len()]);
+            ScalarValue::Map(Arc::new(MapArray::try_new(
+                entries_field.clone(),
+                offsets,
+                entries,
+                None,
+                *ordered,
+            )?))
+        }
+        _ => from_optd_og_constant(&ConstantPred::new_with_type(
+            value.clone(),
+            ConstantType::from_data_type(data_type.clone()),
+        ))?,
+    };
+    Ok(value)
+}
+
+pub(crate) fn from_optd_og_bin_op(op: BinOpType) -> Result<Operator> {
+    Ok(match op {
+        BinOpType::Eq => Operator::Eq,
+        BinOpType::Neq => Operator::NotEq,
+        BinOpType::Leq => Operator::LtEq,
+        BinOpType::Lt => Operator::Lt,
+        BinOpType::Geq => Operator::GtEq,
+        BinOpType::Gt => Operator::Gt,
+        BinOpType::Add => Operator::Plus,
+        BinOpType::Sub => Operator::Minus,
+        BinOpType::Mul => Operator::Multiply,
+        BinOpType::Div => Operator::Divide,
+        op => bail!("unsupported operator: {}", op),
+    })
+}
+
+pub(crate) fn from_optd_og_join_type(join_type: &JoinType) -> datafusion_expr::JoinType {
+    match join_type {
+        JoinType::Inner => datafusion_expr::JoinType::Inner,
+        JoinType::FullOuter => datafusion_expr::JoinType::Full,
+        JoinType::LeftOuter => datafusion_expr::JoinType::Left,
+        JoinType::RightOuter => datafusion_expr::JoinType::Right,
+        JoinType::LeftSemi => datafusion_expr::JoinType::LeftSemi,
+        JoinType::RightSemi => datafusion_expr::JoinType::RightSemi,
+        JoinType::LeftAnti => datafusion_expr::JoinType::LeftAnti,
+        JoinType::RightAnti => datafusion_expr::JoinType::RightAnti,
+        JoinType::LeftMark => datafusion_expr::JoinType::LeftMark,
+    }
+}
+
+/// The indexes of the columns of a list of column refs, e.g., the keys of a join.
+fn column_indexes(columns: ListPred) -> Result<Vec<usize>> {
+    columns
+        .to_vec()
+        .into_iter()
+        .map(|column| {
+            ColumnRefPred::from_pred_node(column)
+                .map(|column| column.index())
+                .context("expected a column ref")
+        })
+        .collect()
+}
+
 impl OptdPlanContext<'_> {
+    pub(crate) fn table_source(&self, name: &str) -> Result<&Arc<dyn TableSource>> {
+        self.tables
+            .get(name)
+            .with_context(|| format!("table {} is not in the plan", name))
+    }
+
     #[async_recursion]
     async fn conv_from_optd_og_table_scan(
         &mut self,
         node: PhysicalScan,
     ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
-        let source = self.tables.get(node.table().as_ref()).unwrap();
+        let filters = self.conv_from_optd_og_partition_filters(&node)?;
+        let source = self.table_source(node.table().as_ref())?;
         let provider = source_as_provider(source)?;
-        let plan = provider.scan(self.session_state, None, &[], None).await?;
+        let plan = provider
+            .scan(self.session_state, None, &filters, None)
+            .await?;
         Ok(plan)
     }
 
+    /// Pushes a projection which only selects columns into the scan below it, so that columnar
+    /// sources do not read the other columns. Returns `None` if the projection cannot be pushed.
+    async fn conv_from_optd_og_projected_scan(
+        &mut self,
+        node: &PhysicalProjection,
+        meta: &PlanAnnotations,
+    ) -> Result<Option<Arc<dyn ExecutionPlan + 'static>>> {
+        let PlanNodeOrGroup::PlanNode(child) = node.child() else {
+            return Ok(None);
+        };
+        let annotation = meta.get(&child).context("group id not found")?;
+        let Some(scan) = PhysicalScan::from_plan_node(child) else {
+            return Ok(None);
+        };
+        let Some(columns) = node
+            .exprs()
+            .to_vec()
+            .into_iter()
+            .map(|expr| ColumnRefPred::from_pred_node(expr).map(|col| col.index()))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(None);
+        };
+        let filters = self.conv_from_optd_og_partition_filters(&scan)?;
+        let source = self.table_source(scan.table().as_ref())?;
+        let provider = source_as_provider(source)?;
+        let mut projection = columns.clone();
+        projection.sort_unstable();
+        projection.dedup();
+        let scan_exec = provider
+            .scan(self.session_state, Some(&projection), &filters, None)
+            .await?;
+        // The scan is no longer converted on its own, so collect its row count here.
+        let optimizer = self
+            .optimizer
+            .context("no optimizer to collect statistics for")?;
+        let scan_exec = if optimizer.adaptive_enabled() {
+            Arc::new(CollectorExec::new(
+                scan_exec,
+                annotation,
+                optimizer.runtime_statistics.clone(),
+            )) as Arc<dyn ExecutionPlan>
+        } else {
+            scan_exec
+        };
+        let scan_schema = scan_exec.schema();
+        let physical_exprs = columns
+            .iter()
+            .enumerate()
+            .map(|(idx, col)| {
+                let scan_idx = projection.binary_search(col).unwrap();
+                let expr = physical_expr::expressions::Column::new(
+                    scan_schema.field(scan_idx).name(),
+                    scan_idx,
+                );
+                (Arc::new(expr) as PhysicalExprRef, format!("col{}", idx))
+            })
+            .collect::<Vec<_>>();
+        Ok(Some(
+            Arc::new(ProjectionExec::try_new(physical_exprs, scan_exec)?)
+                as Arc<dyn ExecutionPlan + 'static>,
+        ))
+    }
+
     fn conv_from_optd_og_sort_order_expr(
         &mut self,
         sort_expr: SortOrderPred,
@@ -86,7 +344,7 @@ impl OptdPlanContext<'_> {
         let expr = FuncPred::from_pred_node(expr).unwrap();
         let typ = expr.func();
         let FuncType::Agg(func) = typ else {
-            unreachable!()
+            bail!("{} is not an aggregate function", typ)
         };
         let agg_expr = self
             .session_state
@@ -127,36 +385,9 @@ impl OptdPlanContext<'_> {
                     ),
                 ))
             }
-            DfPredType::Constant(typ) => {
+            DfPredType::Constant(_) => {
                 let expr = ConstantPred::from_pred_node(expr).unwrap();
-                let value = expr.value();
-                let value = match typ {
-                    ConstantType::Bool => ScalarValue::Boolean(Some(value.as_bool())),
-                    ConstantType::UInt8 => ScalarValue::UInt8(Some(value.as_u8())),
-                    ConstantType::UInt16 => ScalarValue::UInt16(Some(value.as_u16())),
-                    ConstantType::UInt32 => ScalarValue::UInt32(Some(value.as_u32())),
-                    ConstantType::UInt64 => ScalarValue::UInt64(Some(value.as_u64())),
-                    ConstantType::Int8 => ScalarValue::Int8(Some(value.as_i8())),
-                    ConstantType::Int16 => ScalarValue::Int16(Some(value.as_i16())),
-                    ConstantType::Int32 => ScalarValue::Int32(Some(value.as_i32())),
-                    ConstantType::Int64 => ScalarValue::Int64(Some(value.as_i64())),
-                    ConstantType::Float64 => ScalarValue::Float64(Some(value.as_f64())),
-                    ConstantType::Decimal => {
-                        ScalarValue::Decimal128(Some(value.as_f64() as i128), 20, 0)
-                        // TODO(chi): no hard code decimal
-                    }
-                    ConstantType::Date => ScalarValue::Date32(Some(value.as_i64() as i32)),
-                    ConstantType::IntervalMonthDateNano => {
-                        let value = value.as_i128();
-                        ScalarValue::IntervalMonthDayNano(Some(IntervalMonthDayNano::new(
-                            (value >> 96) as i32,
-                            ((value >> 64) & ((1 << 32) - 1)) as i32,
-                            (value & ((1 << 64) - 1)) as i64,
-                        )))
-                    }
-                    ConstantType::Utf8String => ScalarValue::Utf8(Some(value.as_str().to_string())),
-                    ConstantType::Binary => unimplemented!(),
-                };
+                let value = from_optd_og_constant(&expr)?;
                 Ok(Arc::new(
                     datafusion::physical_plan::expressions::Literal::new(value),
                 ))
@@ -171,13 +402,39 @@ impl OptdPlanContext<'_> {
                     .map(|expr| self.conv_from_optd_og_expr(expr, context))
                     .collect::<Result<Vec<_>>>()?;
                 match func {
-                    FuncType::Scalar(func, ret_typ) => {
+                    FuncType::Scalar(func) => {
                         let scalar_func = self
                             .session_state
                             .scalar_functions()
                             .get(&func)
                             .context("scalar func not found")?
                             .clone();
+                        // optd_og only keeps the name of the function, so resolve the return type
+                        // from the arguments of this call.
+                        let arg_types = args
+                            .iter()
+                            .map(|arg| arg.data_type(context))
+                            .collect::<datafusion::common::Result<Vec<_>>>()?;
+                        let nullables = args
+                            .iter()
+                            .map(|arg| arg.nullable(context))
+                            .collect::<datafusion::common::Result<Vec<_>>>()?;
+                        let scalar_arguments = args
+                            .iter()
+                            .map(|arg| {
+                                arg.as_any()
+                                    .downcast_ref::<physical_expr::expressions::Literal>()
+                                    .map(|literal| literal.value())
+                            })
+                            .collect::<Vec<_>>();
+                        let ret_typ = scalar_func
+                            .return_type_from_args(ReturnTypeArgs {
+                                arg_types: &arg_types,
+                                scalar_arguments: &scalar_arguments,
+                                nullables: &nullables,
+                            })?
+                            .return_type()
+                            .clone();
                         Ok(Arc::new(ScalarFunctionExpr::new(
                             &func,
                             scalar_func.clone(),
@@ -207,7 +464,7 @@ impl OptdPlanContext<'_> {
                         let expr = args[0].clone();
                         Ok(physical_expr::expressions::is_not_null(expr)?)
                     }
-                    _ => unreachable!(),
+                    func => bail!("unsupported function: {}", func),
                 }
             }
             DfPredType::LogOp(typ) => {
@@ -231,19 +488,7 @@ impl OptdPlanContext<'_> {
                 let expr = BinOpPred::from_pred_node(expr).unwrap();
                 let left = self.conv_from_optd_og_expr(expr.left_child(), context)?;
                 let right = self.conv_from_optd_og_expr(expr.right_child(), context)?;
-                let op = match op {
-                    BinOpType::Eq => Operator::Eq,
-                    BinOpType::Neq => Operator::NotEq,
-                    BinOpType::Leq => Operator::LtEq,
-                    BinOpType::Lt => Operator::Lt,
-                    BinOpType::Geq => Operator::GtEq,
-                    BinOpType::Gt => Operator::Gt,
-                    BinOpType::Add => Operator::Plus,
-                    BinOpType::Sub => Operator::Minus,
-                    BinOpType::Mul => Operator::Multiply,
-                    BinOpType::Div => Operator::Divide,
-                    op => unimplemented!("{}", op),
-                };
+                let op = from_optd_og_bin_op(op)?;
                 Ok(
                     Arc::new(datafusion::physical_plan::expressions::BinaryExpr::new(
                         left, op, right,
@@ -307,7 +552,7 @@ impl OptdPlanContext<'_> {
                     ),
                 ))
             }
-            _ => unimplemented!("{:?}", expr), // TODO display?
+            _ => bail!("unsupported expression: {}", expr),
         }
     }
 
@@ -315,8 +560,11 @@ impl OptdPlanContext<'_> {
     async fn conv_from_optd_og_projection(
         &mut self,
         node: PhysicalProjection,
-        meta: &PlanNodeMetaMap,
+        meta: &PlanAnnotations,
     ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
+        if let Some(exec) = self.conv_from_optd_og_projected_scan(&node, meta).await? {
+            return Ok(exec);
+        }
         let input_exec = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
         let physical_exprs = node
             .exprs()
@@ -337,11 +585,52 @@ impl OptdPlanContext<'_> {
         )
     }
 
+    #[async_recursion]
+    async fn conv_from_optd_og_table_function(
+        &mut self,
+        node: PhysicalTableFunction,
+        meta: &PlanAnnotations,
+    ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
+        let name = node.name();
+        if let Some(exec) = self.black_box_execs.get(name.as_ref()) {
+            return Ok(exec.clone());
+        }
+        if name.as_ref() != UNNEST_FUNCTION {
+            // The table function is scanned like a table, and its child only produces the
+            // single row it is evaluated on.
+            let source = self.table_source(name.as_ref())?;
+            let provider = source_as_provider(source)?;
+            let plan = provider.scan(self.session_state, None, &[], None).await?;
+            return Ok(plan);
+        }
+        let input_exec = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
+        let mut args = node.args().to_vec();
+        let preserve_nulls = ConstantPred::from_pred_node(args.pop().unwrap())
+            .unwrap()
+            .value()
+            .as_bool();
+        let list_columns = args
+            .into_iter()
+            .map(|arg| ListUnnest {
+                index_in_input_schema: ColumnRefPred::from_pred_node(arg).unwrap().index(),
+                depth: 1,
+            })
+            .collect();
+        let schema = from_optd_og_schema(node.table_function_schema());
+        Ok(Arc::new(UnnestExec::new(
+            input_exec,
+            list_columns,
+            vec![],
+            Arc::new(schema),
+            UnnestOptions::new().with_preserve_nulls(preserve_nulls),
+        )) as Arc<dyn ExecutionPlan + 'static>)
+    }
+
     #[async_recursion]
     async fn conv_from_optd_og_filter(
         &mut self,
         node: PhysicalFilter,
-        meta: &PlanNodeMetaMap,
+        meta: &PlanAnnotations,
     ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
         let input_exec = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
         let physical_expr = self.conv_from_optd_og_expr(node.cond(), &input_exec.schema())?;
@@ -357,7 +646,7 @@ impl OptdPlanContext<'_> {
     async fn conv_from_optd_og_limit(
         &mut self,
         node: PhysicalLimit,
-        meta: &PlanNodeMetaMap,
+        meta: &PlanAnnotations,
     ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
         let child = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
 
@@ -369,7 +658,7 @@ impl OptdPlanContext<'_> {
             .value()
             .as_i64()
             .try_into()
-            .unwrap();
+            .context("negative limit skip")?;
 
         assert_eq!(node.fetch().typ, DfPredType::Constant(ConstantType::Int64));
         let fetch = ConstantPred::from_pred_node(node.fetch())
@@ -379,7 +668,7 @@ impl OptdPlanContext<'_> {
         let fetch_opt: Option<usize> = if fetch == i64::MAX {
             None
         } else {
-            Some(fetch.try_into().unwrap())
+            Some(fetch.try_into().context("negative limit fetch")?)
         };
 
         Ok(
@@ -393,7 +682,7 @@ impl OptdPlanContext<'_> {
     async fn conv_from_optd_og_sort(
         &mut self,
         node: PhysicalSort,
-        meta: &PlanNodeMetaMap,
+        meta: &PlanAnnotations,
     ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
         let input_exec = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
         let physical_exprs = node
@@ -415,21 +704,19 @@ impl OptdPlanContext<'_> {
         )
     }
 
-    #[async_recursion]
-    async fn conv_from_optd_og_hash_agg(
+    fn conv_from_optd_og_agg(
         &mut self,
-        node: PhysicalAgg,
-        meta: &PlanNodeMetaMap,
-    ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
-        let input_exec = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
-        let agg_exprs = node
-            .aggrs()
+        mode: AggregateMode,
+        input_exec: Arc<dyn ExecutionPlan + 'static>,
+        aggrs: ListPred,
+        groups: ListPred,
+    ) -> Result<AggregateExec> {
+        let agg_exprs = aggrs
             .to_vec()
             .into_iter()
             .map(|expr| self.conv_from_optd_og_agg_expr(expr, &input_exec.schema()))
             .collect::<Result<Vec<_>>>()?;
-        let group_exprs = node
-            .groups()
+        let group_exprs = groups
             .to_vec()
             .into_iter()
             .map(|expr| {
@@ -442,23 +729,119 @@ impl OptdPlanContext<'_> {
         let group_exprs = physical_plan::aggregates::PhysicalGroupBy::new_single(group_exprs);
         let agg_num = agg_exprs.len();
         let schema = input_exec.schema().clone();
-        Ok(Arc::new(
-            datafusion::physical_plan::aggregates::AggregateExec::try_new(
-                AggregateMode::Single,
-                group_exprs,
-                agg_exprs,
-                vec![None; agg_num],
+        Ok(AggregateExec::try_new(
+            mode,
+            group_exprs,
+            agg_exprs,
+            vec![None; agg_num],
+            input_exec,
+            schema,
+        )?)
+    }
+
+    #[async_recursion]
+    async fn conv_from_optd_og_hash_agg(
+        &mut self,
+        node: PhysicalAgg,
+        meta: &PlanAnnotations,
+    ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
+        let input_exec = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
+        let agg = self.conv_from_optd_og_agg(
+            AggregateMode::Single,
+            input_exec,
+            node.aggrs(),
+            node.groups(),
+        )?;
+        Ok(Arc::new(agg) as Arc<dyn ExecutionPlan + 'static>)
+    }
+
+    /// DataFusion picks the streaming (ordered) aggregation mode on its own based on the
+    /// ordering of the input. If the input does not keep the ordering the optimizer expected,
+    /// e.g., because it is read in several partitions, the aggregation falls back to hashing
+    /// the groups, which gives the same result.
+    #[async_recursion]
+    async fn conv_from_optd_og_stream_agg(
+        &mut self,
+        node: PhysicalStreamAgg,
+        meta: &PlanAnnotations,
+    ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
+        let input_exec = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
+        let agg = self.conv_from_optd_og_agg(
+            AggregateMode::Single,
+            input_exec,
+            node.aggrs(),
+            node.groups(),
+        )?;
+        if agg.input_order_mode() != &InputOrderMode::Sorted {
+            tracing::debug!(
+                "input of stream aggregation is not sorted ({:?}), hashing the groups instead",
+                agg.input_order_mode()
+            );
+        }
+        Ok(Arc::new(agg) as Arc<dyn ExecutionPlan + 'static>)
+    }
+
+    /// The partial aggregation is converted together with the final aggregation, as the final
+    /// aggregation is built from the expressions of the partial one. The input is repartitioned
+    /// into the number of partitions the optimizer chose if it is read in another number.
+    #[async_recursion]
+    async fn conv_from_optd_og_two_phase_agg(
+        &mut self,
+        node: PhysicalFinalAgg,
+        meta: &PlanAnnotations,
+    ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
+        let partial = PhysicalPartialAgg::from_plan_node(node.child().unwrap_plan_node())
+            .context("final aggregation must be on top of a partial aggregation")?;
+        let mut input_exec = self
+            .conv_from_optd_og_plan_node(partial.child(), meta)
+            .await?;
+        let partitions = partial
+            .partitions()
+            .value()
+            .as_u64()
+            .try_into()
+            .context("too many partitions")?;
+        if input_exec.properties().output_partitioning().partition_count() != partitions {
+            input_exec = Arc::new(RepartitionExec::try_new(
                 input_exec,
-                schema,
-            )?,
-        ) as Arc<dyn ExecutionPlan + 'static>)
+                Partitioning::RoundRobinBatch(partitions),
+            )?);
+        }
+        let partial_agg = self.conv_from_optd_og_agg(
+            AggregateMode::Partial,
+            input_exec,
+            partial.aggrs(),
+            partial.groups(),
+        )?;
+        let group_exprs = partial_agg.group_expr().as_final();
+        let agg_exprs = partial_agg.aggr_expr().to_vec();
+        let filter_exprs = partial_agg.filter_expr().to_vec();
+        let input_schema = partial_agg.input_schema();
+        let partial_agg = Arc::new(partial_agg) as Arc<dyn ExecutionPlan + 'static>;
+        let partition_cnt = partial_agg
+            .properties()
+            .output_partitioning()
+            .partition_count();
+        let input_exec = if partition_cnt > 1 {
+            Arc::new(CoalescePartitionsExec::new(partial_agg)) as Arc<dyn ExecutionPlan + 'static>
+        } else {
+            partial_agg
+        };
+        Ok(Arc::new(AggregateExec::try_new(
+            AggregateMode::Final,
+            group_exprs,
+            agg_exprs,
+            filter_exprs,
+            input_exec,
+            input_schema,
+        )?) as Arc<dyn ExecutionPlan + 'static>)
     }
 
     #[async_recursion]
     async fn conv_from_optd_og_nested_loop_join(
         &mut self,
         node: PhysicalNestedLoopJoin,
-        meta: &PlanNodeMetaMap,
+        meta: &PlanAnnotations,
     ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
         let left_exec = self.conv_from_optd_og_plan_node(node.left(), meta).await?;
         let right_exec = self.conv_from_optd_og_plan_node(node.right(), meta).await?;
@@ -483,17 +866,7 @@ impl OptdPlanContext<'_> {
                 as Arc<dyn ExecutionPlan + 'static>);
         }
 
-        let join_type = match node.join_type() {
-            JoinType::Inner => datafusion_expr::JoinType::Inner,
-            JoinType::FullOuter => datafusion_expr::JoinType::Full,
-            JoinType::LeftOuter => datafusion_expr::JoinType::Left,
-            JoinType::RightOuter => datafusion_expr::JoinType::Right,
-            JoinType::LeftSemi => datafusion_expr::JoinType::LeftSemi,
-            JoinType::RightSemi => datafusion_expr::JoinType::RightSemi,
-            JoinType::LeftAnti => datafusion_expr::JoinType::LeftAnti,
-            JoinType::RightAnti => datafusion_expr::JoinType::RightAnti,
-            JoinType::LeftMark => datafusion_expr::JoinType::LeftMark,
-        };
+        let join_type = from_optd_og_join_type(node.join_type());
 
         let mut column_idxs = vec![];
         for i in 0..left_exec.schema().fields().len() {
@@ -524,13 +897,31 @@ impl OptdPlanContext<'_> {
     async fn conv_from_optd_og_hash_join(
         &mut self,
         node: PhysicalHashJoin,
-        meta: &PlanNodeMetaMap,
+        meta: &PlanAnnotations,
     ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
-        let left_exec = self.conv_from_optd_og_plan_node(node.left(), meta).await?;
-        let right_exec = self.conv_from_optd_og_plan_node(node.right(), meta).await?;
+        // the filter is registered before the probe side, where it is applied, is converted
+        let runtime_filter = node.runtime_filter().map(|filter_id| {
+            let filter = Arc::new(RuntimeFilter::default());
+            self.runtime_filters.insert(filter_id, filter.clone());
+            filter
+        });
+        let mut left_exec = self.conv_from_optd_og_plan_node(node.left(), meta).await?;
+        let mut right_exec = self.conv_from_optd_og_plan_node(node.right(), meta).await?;
+        if let Some(filter) = runtime_filter {
+            let (build_exec, build_keys) = match node.build_side() {
+                BuildSide::Left => (&mut left_exec, node.left_keys()),
+                BuildSide::Right => (&mut right_exec, node.right_keys()),
+            };
+            let build_keys = column_indexes(build_keys)?;
+            *build_exec = Arc::new(RuntimeFilterBuildExec::new(
+                build_exec.clone(),
+                build_keys,
+                filter,
+            ));
+        }
         let join_type = match node.join_type() {
-            JoinType::Inner => datafusion::logical_expr::JoinType::Inner,
-            _ => unimplemented!(),
+            join_type @ (JoinType::Inner | JoinType::LeftMark) => from_optd_og_join_type(join_type),
+            join_type => bail!("unsupported hash join type: {}", join_type),
         };
         let left_exprs = node.left_keys().to_vec();
         let right_exprs: Vec<Arc<optd_og_core::nodes::PredNode<DfNodeType>>> =
@@ -555,6 +946,36 @@ impl OptdPlanContext<'_> {
                 )) as PhysicalExprRef,
             ));
         }
+        if node.build_side() == BuildSide::Right {
+            // `HashJoinExec` builds on its first input, swap the inputs and reorder the columns
+            // back; only inner joins are built on the right, so the swap keeps the join type.
+            let left_width = left_exec.schema().fields().len();
+            let right_width = right_exec.schema().fields().len();
+            let on = on.into_iter().map(|(left, right)| (right, left)).collect();
+            let join_exec = Arc::new(datafusion::physical_plan::joins::HashJoinExec::try_new(
+                right_exec,
+                left_exec,
+                on,
+                None,
+                &join_type,
+                None,
+                PartitionMode::CollectLeft,
+                false,
+            )?) as Arc<dyn ExecutionPlan + 'static>;
+            let join_schema = join_exec.schema();
+            let physical_exprs = (right_width..right_width + left_width)
+                .chain(0..right_width)
+                .map(|idx| {
+                    let name = join_schema.field(idx).name();
+                    let expr = physical_expr::expressions::Column::new(name, idx);
+                    (Arc::new(expr) as PhysicalExprRef, name.to_string())
+                })
+                .collect::<Vec<_>>();
+            return Ok(
+                Arc::new(ProjectionExec::try_new(physical_exprs, join_exec)?)
+                    as Arc<dyn ExecutionPlan + 'static>,
+            );
+        }
         Ok(
             Arc::new(datafusion::physical_plan::joins::HashJoinExec::try_new(
                 left_exec,
@@ -569,18 +990,63 @@ impl OptdPlanContext<'_> {
         )
     }
 
+    #[async_recursion]
+    async fn conv_from_optd_og_runtime_filter(
+        &mut self,
+        node: PhysicalRuntimeFilter,
+        meta: &PlanAnnotations,
+    ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
+        let input_exec = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
+        let filter = self
+            .runtime_filters
+            .get(&node.id())
+            .context("runtime filter not found above the scan")?
+            .clone();
+        let keys = column_indexes(node.keys())?;
+        Ok(Arc::new(RuntimeFilterExec::new(input_exec, keys, filter))
+            as Arc<dyn ExecutionPlan + 'static>)
+    }
+
+    /// datafusion reads each input of a join once, collecting the build side in memory, so the
+    /// row of a singleton is computed once, and the [`SingletonExec`] only checks there is at
+    /// most one.
+    #[async_recursion]
+    async fn conv_from_optd_og_singleton(
+        &mut self,
+        node: PhysicalSingleton,
+        meta: &PlanAnnotations,
+    ) -> Result<Arc<dyn ExecutionPlan + 'static>> {
+        let input_exec = self.conv_from_optd_og_plan_node(node.child(), meta).await?;
+        Ok(Arc::new(SingletonExec::new(input_exec)) as Arc<dyn ExecutionPlan + 'static>)
+    }
+
+    /// Merges the sorted partitions of a node whose rows the optimizer chose to be ordered, as
+    /// the partitions of an execution plan are only ordered on their own.
+    fn preserve_ordering(
+        exec: Arc<dyn ExecutionPlan>,
+        meta: &PlanAnnotation,
+    ) -> Arc<dyn ExecutionPlan> {
+        let ordered = meta
+            .physical_prop::<OrderingProp>()
+            .is_some_and(|prop| !prop.keys.is_empty());
+        if !ordered || exec.properties().output_partitioning().partition_count() <= 1 {
+            return exec;
+        }
+        match exec.properties().output_ordering() {
+            Some(ordering) => Arc::new(SortPreservingMergeExec::new(ordering.clone(), exec)),
+            None => exec,
+        }
+    }
+
     async fn conv_from_optd_og_plan_node(
         &mut self,
         rel_node: PlanNodeOrGroup<DfNodeType>,
-        meta: &PlanNodeMetaMap,
+        meta: &PlanAnnotations,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         let PlanNodeOrGroup::PlanNode(rel_node) = rel_node else {
-            unreachable!("Tried to convert a non-fully materialized plan")
+            bail!("Tried to convert a non-fully materialized plan")
         };
-        let group_id = meta
-            .get(&(rel_node.as_ref() as *const _ as usize))
-            .expect("group id not found")
-            .group_id;
+        let node_meta = meta.get(&rel_node).context("group id not found")?;
         let rel_node_dbg = rel_node.clone();
         let bare = match &rel_node.typ {
             DfNodeType::PhysicalScan => {
@@ -606,6 +1072,20 @@ impl OptdPlanContext<'_> {
                 self.conv_from_optd_og_hash_agg(PhysicalAgg::from_plan_node(rel_node).unwrap(), meta)
                     .await?
             }
+            DfNodeType::PhysicalStreamAgg => {
+                self.conv_from_optd_og_stream_agg(
+                    PhysicalStreamAgg::from_plan_node(rel_node).unwrap(),
+                    meta,
+                )
+                .await?
+            }
+            DfNodeType::PhysicalFinalAgg => {
+                self.conv_from_optd_og_two_phase_agg(
+                    PhysicalFinalAgg::from_plan_node(rel_node).unwrap(),
+                    meta,
+                )
+                .await?
+            }
             DfNodeType::PhysicalNestedLoopJoin(_) => {
                 self.conv_from_optd_og_nested_loop_join(
                     PhysicalNestedLoopJoin::from_plan_node(rel_node).unwrap(),
@@ -640,17 +1120,40 @@ impl OptdPlanContext<'_> {
                 self.conv_from_optd_og_limit(PhysicalLimit::from_plan_node(rel_node).unwrap(), meta)
                     .await?
             }
-            typ => unimplemented!("{}", typ),
+            DfNodeType::PhysicalTableFunction => {
+                self.conv_from_optd_og_table_function(
+                    PhysicalTableFunction::from_plan_node(rel_node).unwrap(),
+                    meta,
+                )
+                .await?
+            }
+            DfNodeType::PhysicalRuntimeFilter => {
+                self.conv_from_optd_og_runtime_filter(
+                    PhysicalRuntimeFilter::from_plan_node(rel_node).unwrap(),
+                    meta,
+                )
+                .await?
+            }
+            DfNodeType::PhysicalSingleton => {
+                self.conv_from_optd_og_singleton(
+                    PhysicalSingleton::from_plan_node(rel_node).unwrap(),
+                    meta,
+                )
+                .await?
+            }
+            typ => bail!("unsupported plan node: {}", typ),
         };
+        let bare = Self::preserve_ordering(bare, node_meta);
 
-        let optimizer = self.optimizer.as_ref().unwrap();
-        if optimizer.adaptive_enabled() {
-            let bare_with_collector: Result<Arc<dyn ExecutionPlan>> =
-                Ok(Arc::new(CollectorExec::new(
-                    bare,
-                    group_id,
-                    self.optimizer.as_ref().unwrap().runtime_statistics.clone(),
-                )) as Arc<dyn ExecutionPlan>);
+        let optimizer = self
+            .optimizer
+            .context("no optimizer to collect statistics for")?;
+        // a runtime filter shares the group of its scan, whose row count it does not produce
+        if optimizer.adaptive_enabled() && rel_node_dbg.typ != DfNodeType::PhysicalRuntimeFilter {
+            let bare_with_collector: Result<Arc<dyn ExecutionPlan>> = Ok(Arc::new(
+                CollectorExec::new(bare, node_meta, optimizer.runtime_statistics.clone()),
+            )
+                as Arc<dyn ExecutionPlan>);
             bare_with_collector.with_context(|| format!("when processing {}", rel_node_dbg))
         } else {
             Ok(bare)
@@ -660,7 +1163,7 @@ impl OptdPlanContext<'_> {
     pub async fn conv_from_optd_og(
         &mut self,
         root_rel: ArcDfPlanNode,
-        meta: PlanNodeMetaMap,
+        meta: PlanAnnotations,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         self.conv_from_optd_og_plan_node(PlanNodeOrGroup::PlanNode(root_rel), &meta)
             .await
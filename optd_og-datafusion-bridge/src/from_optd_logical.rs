@@ -0,0 +1,484 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Converts the plans optimized by optd_og back into datafusion logical plans, so that optd_og
+//! can be used as a logical rewriter in front of another planner or execution engine. The join
+//! order and the placement of the operators chosen by optd_og are kept, the physical operators
+//! are left to the next planner.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context, Result};
+use datafusion::common::{Column, DFSchema, TableReference, UnnestOptions};
+use datafusion::logical_expr::expr::{
+    AggregateFunction, Between, Case, Cast, InList, Like, ScalarFunction,
+};
+use datafusion::logical_expr::{
+    binary_expr, lit, EmptyRelation, Expr, LogicalPlan, LogicalPlanBuilder, Operator, SortExpr,
+};
+use itertools::Itertools;
+use optd_og_core::nodes::PlanNodeOrGroup;
+use optd_og_datafusion_repr::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, BetweenPred, BinOpPred, CastPred, ColumnRefPred, ConstantPred,
+    DfNodeType, DfPredType, DfReprPlanNode, DfReprPredNode, FuncPred, FuncType, InListPred,
+    JoinType, LikePred, ListPred, LogOpPred, LogOpType, PhysicalAgg, PhysicalEmptyRelation,
+    PhysicalFilter, PhysicalFinalAgg, PhysicalHashJoin, PhysicalLimit, PhysicalNestedLoopJoin,
+    PhysicalPartialAgg, PhysicalProjection, PhysicalRuntimeFilter, PhysicalScan, PhysicalSingleton,
+    PhysicalSort, PhysicalStreamAgg, PhysicalTableFunction, SortOrderPred, SortOrderType,
+    UNNEST_FUNCTION,
+};
+
+use crate::from_optd::{
+    from_optd_og_bin_op, from_optd_og_constant, from_optd_og_join_type, from_optd_og_schema,
+};
+use crate::OptdPlanContext;
+
+/// Aliases the expressions whose output names repeat an earlier one, as datafusion requires the
+/// output columns of a projection or an aggregation to have distinct names.
+fn with_unique_names(exprs: Vec<Expr>) -> Vec<Expr> {
+    let mut names = HashSet::new();
+    exprs
+        .into_iter()
+        .enumerate()
+        .map(|(idx, expr)| {
+            let (qualifier, name) = expr.qualified_name();
+            if names.insert((qualifier, name.clone())) {
+                expr
+            } else {
+                expr.alias(format!("{}#{}", name, idx))
+            }
+        })
+        .collect()
+}
+
+/// Puts all columns of the plan under the qualifier `alias`.
+fn requalify(plan: LogicalPlan, alias: &str) -> Result<LogicalPlan> {
+    let exprs = plan
+        .schema()
+        .iter()
+        .map(|(qualifier, field)| {
+            Expr::Column(Column::from((qualifier, field))).alias(field.name())
+        })
+        .collect_vec();
+    Ok(LogicalPlanBuilder::from(plan)
+        .project(with_unique_names(exprs))?
+        .alias(alias)?
+        .build()?)
+}
+
+/// The columns of both sides of a join must be told apart by name in datafusion, which is not
+/// the case for a self join, so the sides are requalified if they conflict.
+fn join_inputs(left: LogicalPlan, right: LogicalPlan) -> Result<(LogicalPlan, LogicalPlan)> {
+    if left.schema().join(right.schema()).is_ok() {
+        return Ok((left, right));
+    }
+    Ok((
+        requalify(left, "__optd_og_left")?,
+        requalify(right, "__optd_og_right")?,
+    ))
+}
+
+impl OptdPlanContext<'_> {
+    /// Converts the partition filters of a scan into filters on the columns of its table, which
+    /// the table providers prune the files they read with.
+    pub(crate) fn conv_from_optd_og_partition_filters(
+        &self,
+        node: &PhysicalScan,
+    ) -> Result<Vec<Expr>> {
+        let Some(partition_filters) = node.partition_filters() else {
+            return Ok(vec![]);
+        };
+        let table = node.table();
+        let source = self.table_source(table.as_ref())?;
+        let context = DFSchema::try_from_qualified_schema(table.as_ref(), &source.schema())?;
+        self.conv_from_optd_og_logical_exprs(partition_filters, &context)
+    }
+
+    fn conv_from_optd_og_logical_exprs(
+        &self,
+        exprs: ListPred,
+        context: &DFSchema,
+    ) -> Result<Vec<Expr>> {
+        exprs
+            .to_vec()
+            .into_iter()
+            .map(|expr| self.conv_from_optd_og_logical_expr(expr, context))
+            .collect()
+    }
+
+    pub(crate) fn conv_from_optd_og_logical_expr(
+        &self,
+        expr: ArcDfPredNode,
+        context: &DFSchema,
+    ) -> Result<Expr> {
+        let expr = match expr.typ {
+            DfPredType::ColumnRef => {
+                let idx = ColumnRefPred::from_pred_node(expr).unwrap().index();
+                Expr::Column(Column::from(context.qualified_field(idx)))
+            }
+            DfPredType::Constant(_) => {
+                let expr = ConstantPred::from_pred_node(expr).unwrap();
+                lit(from_optd_og_constant(&expr)?)
+            }
+            DfPredType::Func(_) => {
+                let expr = FuncPred::from_pred_node(expr).unwrap();
+                let args = self.conv_from_optd_og_logical_exprs(expr.children(), context)?;
+                match expr.func() {
+                    FuncType::Scalar(func) => {
+                        let scalar_func = self
+                            .session_state
+                            .scalar_functions()
+                            .get(&func)
+                            .context("scalar func not found")?
+                            .clone();
+                        Expr::ScalarFunction(ScalarFunction::new_udf(scalar_func, args))
+                    }
+                    FuncType::Case => Expr::Case(Case::new(
+                        None,
+                        vec![(Box::new(args[0].clone()), Box::new(args[1].clone()))],
+                        Some(Box::new(args[2].clone())),
+                    )),
+                    FuncType::Not => datafusion::logical_expr::not(args[0].clone()),
+                    FuncType::IsNull => args[0].clone().is_null(),
+                    FuncType::IsNotNull => args[0].clone().is_not_null(),
+                    func => bail!("unsupported function: {}", func),
+                }
+            }
+            DfPredType::LogOp(typ) => {
+                let expr = LogOpPred::from_pred_node(expr).unwrap();
+                let op = match typ {
+                    LogOpType::And => Operator::And,
+                    LogOpType::Or => Operator::Or,
+                };
+                let mut children = expr.children().into_iter();
+                let first_expr =
+                    self.conv_from_optd_og_logical_expr(children.next().unwrap(), context)?;
+                children.try_fold(first_expr, |acc, expr| {
+                    let expr = self.conv_from_optd_og_logical_expr(expr, context)?;
+                    Ok::<_, anyhow::Error>(binary_expr(acc, op, expr))
+                })?
+            }
+            DfPredType::BinOp(op) => {
+                let expr = BinOpPred::from_pred_node(expr).unwrap();
+                let left = self.conv_from_optd_og_logical_expr(expr.left_child(), context)?;
+                let right = self.conv_from_optd_og_logical_expr(expr.right_child(), context)?;
+                binary_expr(left, from_optd_og_bin_op(op)?, right)
+            }
+            DfPredType::Between => {
+                let expr = BetweenPred::from_pred_node(expr).unwrap();
+                Expr::Between(Between::new(
+                    Box::new(self.conv_from_optd_og_logical_expr(expr.child(), context)?),
+                    false,
+                    Box::new(self.conv_from_optd_og_logical_expr(expr.lower(), context)?),
+                    Box::new(self.conv_from_optd_og_logical_expr(expr.upper(), context)?),
+                ))
+            }
+            DfPredType::Cast => {
+                let expr = CastPred::from_pred_node(expr).unwrap();
+                let child = self.conv_from_optd_og_logical_expr(expr.child(), context)?;
+                Expr::Cast(Cast::new(Box::new(child), expr.cast_to()))
+            }
+            DfPredType::Like => {
+                let expr = LikePred::from_pred_node(expr).unwrap();
+                let child = self.conv_from_optd_og_logical_expr(expr.child(), context)?;
+                let pattern = self.conv_from_optd_og_logical_expr(expr.pattern(), context)?;
+                Expr::Like(Like::new(
+                    expr.negated(),
+                    Box::new(child),
+                    Box::new(pattern),
+                    None,
+                    expr.case_insensitive(),
+                ))
+            }
+            DfPredType::InList => {
+                let expr = InListPred::from_pred_node(expr).unwrap();
+                let child = self.conv_from_optd_og_logical_expr(expr.child(), context)?;
+                let list = self.conv_from_optd_og_logical_exprs(expr.list(), context)?;
+                Expr::InList(InList::new(Box::new(child), list, expr.negated()))
+            }
+            _ => bail!("unsupported expression: {}", expr),
+        };
+        Ok(expr)
+    }
+
+    fn conv_from_optd_og_logical_agg_expr(
+        &self,
+        expr: ArcDfPredNode,
+        context: &DFSchema,
+    ) -> Result<Expr> {
+        let expr = FuncPred::from_pred_node(expr).unwrap();
+        let typ = expr.func();
+        let FuncType::Agg(func) = typ else {
+            bail!("{} is not an aggregate function", typ)
+        };
+        let agg_func = self
+            .session_state
+            .aggregate_functions()
+            .get(&func)
+            .context("agg func not found")?
+            .clone();
+        let args = self.conv_from_optd_og_logical_exprs(expr.children(), context)?;
+        Ok(Expr::AggregateFunction(AggregateFunction::new_udf(
+            agg_func, args, false, None, None, None,
+        )))
+    }
+
+    /// The group-by columns come first in the output of a datafusion aggregation, followed by
+    /// the aggregates, which is also the order the column references above the aggregation use.
+    fn conv_from_optd_og_logical_agg(
+        &self,
+        child: PlanNodeOrGroup<DfNodeType>,
+        aggrs: ListPred,
+        groups: ListPred,
+    ) -> Result<LogicalPlan> {
+        let input = self.conv_from_optd_og_logical_plan_node(child)?;
+        let group_exprs = self.conv_from_optd_og_logical_exprs(groups, input.schema())?;
+        let agg_exprs = aggrs
+            .to_vec()
+            .into_iter()
+            .map(|expr| self.conv_from_optd_og_logical_agg_expr(expr, input.schema()))
+            .collect::<Result<Vec<_>>>()?;
+        let group_cnt = group_exprs.len();
+        let mut group_exprs = with_unique_names(group_exprs.into_iter().chain(agg_exprs).collect());
+        let agg_exprs = group_exprs.split_off(group_cnt);
+        Ok(LogicalPlanBuilder::from(input)
+            .aggregate(group_exprs, agg_exprs)?
+            .build()?)
+    }
+
+    fn conv_from_optd_og_logical_nested_loop_join(
+        &self,
+        node: PhysicalNestedLoopJoin,
+    ) -> Result<LogicalPlan> {
+        let (left, right) = join_inputs(
+            self.conv_from_optd_og_logical_plan_node(node.left())?,
+            self.conv_from_optd_og_logical_plan_node(node.right())?,
+        )?;
+        if node.join_type() == &JoinType::Inner
+            && node.cond() == ConstantPred::bool(true).into_pred_node()
+        {
+            return Ok(LogicalPlanBuilder::from(left).cross_join(right)?.build()?);
+        }
+        let cond =
+            self.conv_from_optd_og_logical_expr(node.cond(), &left.schema().join(right.schema())?)?;
+        Ok(LogicalPlanBuilder::from(left)
+            .join_on(right, from_optd_og_join_type(node.join_type()), [cond])?
+            .build()?)
+    }
+
+    fn conv_from_optd_og_logical_hash_join(&self, node: PhysicalHashJoin) -> Result<LogicalPlan> {
+        let (left, right) = join_inputs(
+            self.conv_from_optd_og_logical_plan_node(node.left())?,
+            self.conv_from_optd_og_logical_plan_node(node.right())?,
+        )?;
+        let left_keys = self.conv_from_optd_og_logical_exprs(node.left_keys(), left.schema())?;
+        let right_keys = self.conv_from_optd_og_logical_exprs(node.right_keys(), right.schema())?;
+        Ok(LogicalPlanBuilder::from(left)
+            .join_with_expr_keys(
+                right,
+                from_optd_og_join_type(node.join_type()),
+                (left_keys, right_keys),
+                None,
+            )?
+            .build()?)
+    }
+
+    fn conv_from_optd_og_logical_limit(&self, node: PhysicalLimit) -> Result<LogicalPlan> {
+        let input = self.conv_from_optd_og_logical_plan_node(node.child())?;
+        let skip = ConstantPred::from_pred_node(node.skip())
+            .context("limit skip is not a constant")?
+            .value()
+            .as_i64()
+            .try_into()
+            .context("negative limit skip")?;
+        let fetch = ConstantPred::from_pred_node(node.fetch())
+            .context("limit fetch is not a constant")?
+            .value()
+            .as_i64();
+        let fetch = if fetch == i64::MAX {
+            None
+        } else {
+            Some(fetch.try_into().context("negative limit fetch")?)
+        };
+        Ok(LogicalPlanBuilder::from(input)
+            .limit(skip, fetch)?
+            .build()?)
+    }
+
+    fn conv_from_optd_og_logical_table_function(
+        &self,
+        node: PhysicalTableFunction,
+    ) -> Result<LogicalPlan> {
+        let name = node.name();
+        if let Some(plan) = self
+            .black_boxes
+            .as_ref()
+            .and_then(|black_boxes| black_boxes.get(name.as_ref()))
+        {
+            return Ok(plan.clone());
+        }
+        if name.as_ref() != UNNEST_FUNCTION {
+            let source = self.table_source(name.as_ref())?;
+            return Ok(LogicalPlanBuilder::scan(
+                TableReference::bare(name.as_ref()),
+                source.clone(),
+                None,
+            )?
+            .build()?);
+        }
+        let input = self.conv_from_optd_og_logical_plan_node(node.child())?;
+        let mut args = node.args().to_vec();
+        let preserve_nulls = ConstantPred::from_pred_node(args.pop().unwrap())
+            .unwrap()
+            .value()
+            .as_bool();
+        let columns = args
+            .into_iter()
+            .map(|arg| {
+                let idx = ColumnRefPred::from_pred_node(arg).unwrap().index();
+                Column::from(input.schema().qualified_field(idx))
+            })
+            .collect_vec();
+        Ok(LogicalPlanBuilder::from(input)
+            .unnest_columns_with_options(
+                columns,
+                UnnestOptions::new().with_preserve_nulls(preserve_nulls),
+            )?
+            .build()?)
+    }
+
+    fn conv_from_optd_og_logical_plan_node(
+        &self,
+        rel_node: PlanNodeOrGroup<DfNodeType>,
+    ) -> Result<LogicalPlan> {
+        let PlanNodeOrGroup::PlanNode(rel_node) = rel_node else {
+            bail!("Tried to convert a non-fully materialized plan")
+        };
+        let plan = match &rel_node.typ {
+            DfNodeType::PhysicalScan => {
+                let node = PhysicalScan::from_plan_node(rel_node).unwrap();
+                let source = self.table_source(node.table().as_ref())?;
+                let filters = self.conv_from_optd_og_partition_filters(&node)?;
+                LogicalPlanBuilder::scan_with_filters(
+                    node.table().as_ref(),
+                    source.clone(),
+                    None,
+                    filters,
+                )?
+                .build()?
+            }
+            DfNodeType::PhysicalProjection => {
+                let node = PhysicalProjection::from_plan_node(rel_node).unwrap();
+                let input = self.conv_from_optd_og_logical_plan_node(node.child())?;
+                let exprs = self.conv_from_optd_og_logical_exprs(node.exprs(), input.schema())?;
+                LogicalPlanBuilder::from(input)
+                    .project(with_unique_names(exprs))?
+                    .build()?
+            }
+            DfNodeType::PhysicalFilter => {
+                let node = PhysicalFilter::from_plan_node(rel_node).unwrap();
+                let input = self.conv_from_optd_og_logical_plan_node(node.child())?;
+                let cond = self.conv_from_optd_og_logical_expr(node.cond(), input.schema())?;
+                LogicalPlanBuilder::from(input).filter(cond)?.build()?
+            }
+            // the runtime filters only drop rows which do not change the results of the joins
+            DfNodeType::PhysicalRuntimeFilter => self.conv_from_optd_og_logical_plan_node(
+                PhysicalRuntimeFilter::from_plan_node(rel_node)
+                    .unwrap()
+                    .child(),
+            )?,
+            // datafusion evaluates the input of a cross join once
+            DfNodeType::PhysicalSingleton => self.conv_from_optd_og_logical_plan_node(
+                PhysicalSingleton::from_plan_node(rel_node).unwrap().child(),
+            )?,
+            DfNodeType::PhysicalSort => {
+                let node = PhysicalSort::from_plan_node(rel_node).unwrap();
+                let input = self.conv_from_optd_og_logical_plan_node(node.child())?;
+                let sort_exprs = node
+                    .exprs()
+                    .to_vec()
+                    .into_iter()
+                    .map(|expr| {
+                        let expr = SortOrderPred::from_pred_node(expr).unwrap();
+                        let asc = expr.order() == SortOrderType::Asc;
+                        let expr =
+                            self.conv_from_optd_og_logical_expr(expr.child(), input.schema())?;
+                        Ok(SortExpr::new(expr, asc, true))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                LogicalPlanBuilder::from(input).sort(sort_exprs)?.build()?
+            }
+            DfNodeType::PhysicalAgg => {
+                let node = PhysicalAgg::from_plan_node(rel_node).unwrap();
+                self.conv_from_optd_og_logical_agg(node.child(), node.aggrs(), node.groups())?
+            }
+            DfNodeType::PhysicalStreamAgg => {
+                let node = PhysicalStreamAgg::from_plan_node(rel_node).unwrap();
+                self.conv_from_optd_og_logical_agg(node.child(), node.aggrs(), node.groups())?
+            }
+            DfNodeType::PhysicalFinalAgg => {
+                let node = PhysicalFinalAgg::from_plan_node(rel_node).unwrap();
+                let partial =
+                    PhysicalPartialAgg::from_plan_node(node.child().unwrap_plan_node())
+                        .context("final aggregation must be on top of a partial aggregation")?;
+                self.conv_from_optd_og_logical_agg(
+                    partial.child(),
+                    partial.aggrs(),
+                    partial.groups(),
+                )?
+            }
+            DfNodeType::PhysicalNestedLoopJoin(_) => self
+                .conv_from_optd_og_logical_nested_loop_join(
+                    PhysicalNestedLoopJoin::from_plan_node(rel_node).unwrap(),
+                )?,
+            DfNodeType::PhysicalHashJoin(_) => self.conv_from_optd_og_logical_hash_join(
+                PhysicalHashJoin::from_plan_node(rel_node).unwrap(),
+            )?,
+            DfNodeType::PhysicalEmptyRelation => {
+                let node = PhysicalEmptyRelation::from_plan_node(rel_node).unwrap();
+                let schema = from_optd_og_schema(node.empty_relation_schema());
+                LogicalPlan::EmptyRelation(EmptyRelation {
+                    produce_one_row: node.produce_one_row(),
+                    schema: Arc::new(DFSchema::try_from(schema)?),
+                })
+            }
+            DfNodeType::PhysicalLimit => self.conv_from_optd_og_logical_limit(
+                PhysicalLimit::from_plan_node(rel_node).unwrap(),
+            )?,
+            DfNodeType::PhysicalTableFunction => self.conv_from_optd_og_logical_table_function(
+                PhysicalTableFunction::from_plan_node(rel_node).unwrap(),
+            )?,
+            typ => bail!("unsupported plan node: {}", typ),
+        };
+        Ok(plan)
+    }
+
+    /// Converts an optimized plan into a datafusion logical plan. optd_og looks up the columns
+    /// by position, so the output columns are renamed to the ones of `schema`, the schema of the
+    /// logical plan optd_og optimized.
+    pub fn conv_from_optd_og_logical(
+        &self,
+        root_rel: ArcDfPlanNode,
+        schema: &DFSchema,
+    ) -> Result<LogicalPlan> {
+        let plan = self.conv_from_optd_og_logical_plan_node(PlanNodeOrGroup::PlanNode(root_rel))?;
+        ensure!(
+            plan.schema().fields().len() == schema.fields().len(),
+            "the optimized plan has {} columns instead of {}",
+            plan.schema().fields().len(),
+            schema.fields().len()
+        );
+        let exprs = schema
+            .iter()
+            .enumerate()
+            .map(|(idx, (qualifier, field))| {
+                Expr::Column(Column::from(plan.schema().qualified_field(idx)))
+                    .alias_qualified(qualifier.cloned(), field.name())
+            })
+            .collect_vec();
+        Ok(LogicalPlanBuilder::from(plan).project(exprs)?.build()?)
+    }
+}
@@ -5,23 +5,212 @@
 
 use std::sync::Arc;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Context, Result};
+use datafusion::arrow::array::{Array, ArrayRef};
+use datafusion::arrow::datatypes::{DataType, Schema, TimeUnit};
 use datafusion::common::DFSchema;
-use datafusion::logical_expr::{self, logical_plan, LogicalPlan, Operator};
+use datafusion::datasource::source_as_provider;
+use datafusion::functions_table::generate_series::GenerateSeriesTable;
+use datafusion::logical_expr::{self, logical_plan, LogicalPlan, Operator, TableSource};
 use datafusion::scalar::ScalarValue;
-use datafusion_expr::{ExprSchemable, Subquery};
-use itertools::Itertools;
-use optd_og_core::nodes::PredNode;
+use datafusion_expr::Subquery;
+use optd_og_core::nodes::{PredNode, Value};
 use optd_og_datafusion_repr::plan_nodes::{
     ArcDfPlanNode, ArcDfPredNode, BetweenPred, BinOpPred, BinOpType, CastPred, ColumnRefPred,
-    ConstantPred, DfReprPlanNode, DfReprPredNode, ExternColumnRefPred, FuncPred, FuncType,
-    InListPred, JoinType, LikePred, ListPred, LogOpPred, LogOpType, LogicalAgg,
-    LogicalEmptyRelation, LogicalFilter, LogicalJoin, LogicalLimit, LogicalProjection, LogicalScan,
-    LogicalSort, RawDependentJoin, SortOrderPred, SortOrderType, SubqueryType,
+    ConstantPred, ConstantType, DfReprPlanNode, DfReprPredNode, ExternColumnRefPred, FuncPred,
+    FuncType, InListPred, JoinType, LikePred, ListPred, LogOpPred, LogOpType, LogicalAgg,
+    LogicalDistinct, LogicalEmptyRelation, LogicalFilter, LogicalJoin, LogicalLimit,
+    LogicalProjection, LogicalScan, LogicalSort, LogicalTableFunction, RawDependentJoin,
+    SortOrderPred, SortOrderType, SubqueryType, UNNEST_FUNCTION,
 };
-use optd_og_datafusion_repr::properties::schema::Schema as OptdSchema;
+use optd_og_datafusion_repr::properties::schema::{Field as OptdField, Schema as OptdSchema};
 
-use crate::OptdPlanContext;
+use crate::{optd_og_table_name, OptdPlanContext};
+
+/// The time zone of a timestamp only changes how it is displayed, so a zoned timestamp is the
+/// UTC timestamp cast to the zoned type.
+fn timestamp_pred(value: i64, unit: TimeUnit, tz: &Option<Arc<str>>) -> ArcDfPredNode {
+    let constant = ConstantPred::timestamp(value, unit).into_pred_node();
+    match tz {
+        Some(_) => CastPred::new(constant, DataType::Timestamp(unit, tz.clone())).into_pred_node(),
+        None => constant,
+    }
+}
+
+/// Whether the scan reads the rows of a table function like `generate_series(1, 10)`, which
+/// datafusion plans as a scan of the table the function returns rather than of one in the
+/// catalog.
+fn is_table_function(source: &Arc<dyn TableSource>) -> bool {
+    source_as_provider(source).is_ok_and(|provider| provider.as_any().is::<GenerateSeriesTable>())
+}
+
+/// optd_og has no null constants, so the plans with null literals are left to datafusion.
+fn non_null<T>(value: &Option<T>) -> Result<&T> {
+    value.as_ref().context("null literals are not supported")
+}
+
+/// Converts a literal into a constant. The elements of nested literals, e.g., arrays and
+/// structs, are converted as the literals of the same type.
+fn conv_into_optd_og_literal(x: &ScalarValue) -> Result<ArcDfPredNode> {
+    match x {
+        ScalarValue::UInt8(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::uint8(*x).into_pred_node())
+        }
+        ScalarValue::UInt16(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::uint16(*x).into_pred_node())
+        }
+        ScalarValue::UInt32(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::uint32(*x).into_pred_node())
+        }
+        ScalarValue::UInt64(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::uint64(*x).into_pred_node())
+        }
+        ScalarValue::Int8(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::int8(*x).into_pred_node())
+        }
+        ScalarValue::Int16(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::int16(*x).into_pred_node())
+        }
+        ScalarValue::Int32(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::int32(*x).into_pred_node())
+        }
+        ScalarValue::Int64(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::int64(*x).into_pred_node())
+        }
+        ScalarValue::Float64(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::float64(*x).into_pred_node())
+        }
+        ScalarValue::Utf8(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::string(x).into_pred_node())
+        }
+        ScalarValue::Date32(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::date(*x as i64).into_pred_node())
+        }
+        ScalarValue::TimestampSecond(x, tz) => {
+            let x = non_null(x)?;
+            Ok(timestamp_pred(*x, TimeUnit::Second, tz))
+        }
+        ScalarValue::TimestampMillisecond(x, tz) => {
+            let x = non_null(x)?;
+            Ok(timestamp_pred(*x, TimeUnit::Millisecond, tz))
+        }
+        ScalarValue::TimestampMicrosecond(x, tz) => {
+            let x = non_null(x)?;
+            Ok(timestamp_pred(*x, TimeUnit::Microsecond, tz))
+        }
+        ScalarValue::TimestampNanosecond(x, tz) => {
+            let x = non_null(x)?;
+            Ok(timestamp_pred(*x, TimeUnit::Nanosecond, tz))
+        }
+        ScalarValue::Time32Second(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::time(*x as i64, TimeUnit::Second).into_pred_node())
+        }
+        ScalarValue::Time32Millisecond(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::time(*x as i64, TimeUnit::Millisecond).into_pred_node())
+        }
+        ScalarValue::Time64Microsecond(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::time(*x, TimeUnit::Microsecond).into_pred_node())
+        }
+        ScalarValue::Time64Nanosecond(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::time(*x, TimeUnit::Nanosecond).into_pred_node())
+        }
+        ScalarValue::IntervalMonthDayNano(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::interval_month_day_nano(
+                ((((x.months as i128) << 32) + x.days as i128) << 64) + x.nanoseconds as i128,
+            )
+            .into_pred_node())
+        }
+        ScalarValue::Decimal128(x, precision, scale) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::decimal(*x, *precision, *scale).into_pred_node())
+        }
+        ScalarValue::Boolean(x) => {
+            let x = non_null(x)?;
+            Ok(ConstantPred::bool(*x).into_pred_node())
+        }
+        ScalarValue::List(_)
+        | ScalarValue::LargeList(_)
+        | ScalarValue::FixedSizeList(_)
+        | ScalarValue::Struct(_)
+        | ScalarValue::Map(_) => {
+            Ok(ConstantPred::nested(nested_value(x)?, x.data_type()).into_pred_node())
+        }
+        _ => bail!("{:?}", x),
+    }
+}
+
+fn nested_value(x: &ScalarValue) -> Result<Value> {
+    let element = |array: &ArrayRef, idx: usize| -> Result<Value> {
+        let constant = conv_into_optd_og_literal(&ScalarValue::try_from_array(array, idx)?)?;
+        Ok(ConstantPred::from_pred_node(constant)
+            .context("zoned timestamps in nested literals are not supported")?
+            .value())
+    };
+    let elements = |array: ArrayRef| -> Result<Value> {
+        let values = (0..array.len())
+            .map(|idx| element(&array, idx))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Value::List(values.into()))
+    };
+    ensure!(!x.is_null(), "null literals are not supported");
+    match x {
+        ScalarValue::List(array) => elements(array.value(0)),
+        ScalarValue::LargeList(array) => elements(array.value(0)),
+        ScalarValue::FixedSizeList(array) => elements(array.value(0)),
+        ScalarValue::Struct(array) => {
+            let fields = array
+                .fields()
+                .iter()
+                .zip(array.columns())
+                .map(|(field, column)| Ok((field.name().as_str().into(), element(column, 0)?)))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Value::Struct(fields.into()))
+        }
+        ScalarValue::Map(array) => {
+            let entries = array.value(0);
+            let entries = (0..entries.len())
+                .map(|idx| {
+                    Ok((
+                        element(entries.column(0), idx)?,
+                        element(entries.column(1), idx)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Value::Map(entries.into()))
+        }
+        _ => bail!("{:?} is not a nested literal", x),
+    }
+}
+
+fn into_optd_og_schema(schema: &Schema) -> OptdSchema {
+    OptdSchema {
+        fields: schema
+            .fields()
+            .iter()
+            .map(|field| OptdField {
+                name: field.name().to_string(),
+                typ: ConstantType::from_data_type(field.data_type().clone()),
+                nullable: field.is_nullable(),
+            })
+            .collect(),
+    }
+}
 
 impl OptdPlanContext<'_> {
     fn subqueries_to_dependent_joins(
@@ -50,16 +239,15 @@ impl OptdPlanContext<'_> {
                         .filter_map(|col| {
                             if let datafusion_expr::Expr::OuterReferenceColumn(_, col) = col {
                                 Some(
-                                    ExternColumnRefPred::new(
-                                        input_schema.index_of_column(col).unwrap(),
-                                    )
-                                    .into_pred_node(),
+                                    input_schema
+                                        .index_of_column(col)
+                                        .map(|idx| ExternColumnRefPred::new(idx).into_pred_node()),
                                 )
                             } else {
                                 None
                             }
                         })
-                        .collect(),
+                        .collect::<Result<_, _>>()?,
                 ),
                 sq_typ,
             );
@@ -72,7 +260,20 @@ impl OptdPlanContext<'_> {
         &mut self,
         node: &logical_plan::TableScan,
     ) -> Result<ArcDfPlanNode> {
-        let table_name = node.table_name.to_string();
+        let is_table_function = is_table_function(&node.source);
+        let table_name = if is_table_function {
+            node.table_name.to_string()
+        } else {
+            // Resolve the name against the default schema of the session, which the catalog of
+            // the optimizer does not know about.
+            let catalog = &self.session_state.config_options().catalog;
+            optd_og_table_name(
+                &node
+                    .table_name
+                    .clone()
+                    .resolve(&catalog.default_catalog, &catalog.default_schema),
+            )
+        };
         if node.fetch.is_some() {
             bail!("fetch")
         }
@@ -80,16 +281,28 @@ impl OptdPlanContext<'_> {
             bail!("no filters")
         }
         self.tables.insert(table_name.clone(), node.source.clone());
-        let scan = LogicalScan::new(table_name);
+        let scan = if is_table_function {
+            let schema = into_optd_og_schema(&node.source.schema());
+            let one_row = LogicalEmptyRelation::new(true, OptdSchema { fields: vec![] });
+            LogicalTableFunction::new(
+                one_row.into_plan_node(),
+                table_name,
+                ListPred::new(vec![]),
+                schema,
+            )
+            .into_plan_node()
+        } else {
+            LogicalScan::new(table_name).into_plan_node()
+        };
         if let Some(ref projection) = node.projection {
             let mut exprs = Vec::with_capacity(projection.len());
             for &p in projection {
                 exprs.push(ColumnRefPred::new(p).into_pred_node());
             }
-            let projection = LogicalProjection::new(scan.into_plan_node(), ListPred::new(exprs));
+            let projection = LogicalProjection::new(scan, ListPred::new(exprs));
             return Ok(projection.into_plan_node());
         }
-        Ok(scan.into_plan_node())
+        Ok(scan)
     }
 
     fn conv_into_optd_og_expr<'a>(
@@ -135,7 +348,7 @@ impl OptdPlanContext<'_> {
                     Operator::Minus => BinOpType::Sub,
                     Operator::Multiply => BinOpType::Mul,
                     Operator::Divide => BinOpType::Div,
-                    op => unimplemented!("{}", op),
+                    op => bail!("unsupported operator: {}", op),
                 };
                 Ok(BinOpPred::new(left, right, op).into_pred_node())
             }
@@ -144,92 +357,19 @@ impl OptdPlanContext<'_> {
                 Ok(ColumnRefPred::new(idx).into_pred_node())
             }
             Expr::OuterReferenceColumn(_, col) => {
-                let idx = dep_ctx.unwrap().index_of_column(col)?;
+                let idx = dep_ctx
+                    .context("outer reference outside of a subquery")?
+                    .index_of_column(col)?;
                 Ok(ExternColumnRefPred::new(idx).into_pred_node())
             }
-            Expr::Literal(x) => match x {
-                ScalarValue::UInt8(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::uint8(*x).into_pred_node())
-                }
-                ScalarValue::UInt16(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::uint16(*x).into_pred_node())
-                }
-                ScalarValue::UInt32(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::uint32(*x).into_pred_node())
-                }
-                ScalarValue::UInt64(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::uint64(*x).into_pred_node())
-                }
-                ScalarValue::Int8(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::int8(*x).into_pred_node())
-                }
-                ScalarValue::Int16(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::int16(*x).into_pred_node())
-                }
-                ScalarValue::Int32(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::int32(*x).into_pred_node())
-                }
-                ScalarValue::Int64(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::int64(*x).into_pred_node())
-                }
-                ScalarValue::Float64(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::float64(*x).into_pred_node())
-                }
-                ScalarValue::Utf8(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::string(x).into_pred_node())
-                }
-                ScalarValue::Date32(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::date(*x as i64).into_pred_node())
-                }
-                ScalarValue::IntervalMonthDayNano(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::interval_month_day_nano(
-                        ((((x.months as i128) << 32) + x.days as i128) << 64)
-                            + x.nanoseconds as i128,
-                    )
-                    .into_pred_node())
-                }
-                ScalarValue::Decimal128(x, _, _) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::decimal(*x as f64).into_pred_node())
-                }
-                ScalarValue::Boolean(x) => {
-                    let x = x.as_ref().unwrap();
-                    Ok(ConstantPred::bool(*x).into_pred_node())
-                }
-                _ => bail!("{:?}", x),
-            },
+            Expr::Literal(x) => conv_into_optd_og_literal(x),
             Expr::Alias(x) => {
                 self.conv_into_optd_og_expr(x.expr.as_ref(), context, dep_ctx, subqueries)
             }
             Expr::ScalarFunction(x) => {
                 let args = self.conv_into_optd_og_expr_list(&x.args, context, dep_ctx, subqueries)?;
                 Ok(FuncPred::new(
-                    FuncType::new_scalar(
-                        x.func.name().to_string(),
-                        // TODO: remove this infer...
-                        x.func
-                            .return_type_from_exprs(
-                                &x.args,
-                                context,
-                                &x.args
-                                    .iter()
-                                    .map(|x| x.get_type(context).unwrap())
-                                    .collect_vec(),
-                            )
-                            .unwrap(),
-                    ),
+                    FuncType::new_scalar(x.func.name().to_string()),
                     args,
                 )
                 .into_pred_node())
@@ -243,19 +383,24 @@ impl OptdPlanContext<'_> {
             }
             Expr::Case(x) => {
                 let when_then_expr = &x.when_then_expr;
-                assert_eq!(when_then_expr.len(), 1);
+                ensure!(
+                    when_then_expr.len() == 1,
+                    "CASE with more than one WHEN is not supported"
+                );
+                ensure!(x.expr.is_none(), "CASE with an operand is not supported");
                 let (when_expr, then_expr) = &when_then_expr[0];
                 let when_expr =
                     self.conv_into_optd_og_expr(when_expr, context, dep_ctx, subqueries)?;
                 let then_expr =
                     self.conv_into_optd_og_expr(then_expr, context, dep_ctx, subqueries)?;
                 let else_expr = self.conv_into_optd_og_expr(
-                    x.else_expr.as_ref().unwrap(),
+                    x.else_expr
+                        .as_ref()
+                        .context("CASE without ELSE is not supported")?,
                     context,
                     dep_ctx,
                     subqueries,
                 )?;
-                assert!(x.expr.is_none());
                 Ok(FuncPred::new(
                     FuncType::Case,
                     ListPred::new(vec![when_expr, then_expr, else_expr]),
@@ -280,7 +425,7 @@ impl OptdPlanContext<'_> {
                 let low = self.conv_into_optd_og_expr(x.low.as_ref(), context, dep_ctx, subqueries)?;
                 let high =
                     self.conv_into_optd_og_expr(x.high.as_ref(), context, dep_ctx, subqueries)?;
-                assert!(!x.negated, "unimplemented");
+                ensure!(!x.negated, "NOT BETWEEN is not supported");
                 Ok(BetweenPred::new(expr, low, high).into_pred_node())
             }
             Expr::Cast(x) => {
@@ -444,7 +589,7 @@ impl OptdPlanContext<'_> {
             dep_ctx,
             &mut subqueries,
         )?;
-        assert!(
+        ensure!(
             subqueries.is_empty(),
             "Subqueries encountered in conv_into_optd_og_sort---not supported currently"
         );
@@ -470,7 +615,7 @@ impl OptdPlanContext<'_> {
             dep_ctx,
             &mut subqueries,
         )?;
-        assert!(
+        ensure!(
             subqueries.is_empty(),
             "Subqueries encountered in conv_into_optd_og_agg---not supported currently"
         );
@@ -528,16 +673,16 @@ impl OptdPlanContext<'_> {
             let expr = BinOpPred::new(left, right, op).into_pred_node();
             log_ops.push(expr);
         }
-        if node.filter.is_some() {
-            let filter = self.conv_into_optd_og_expr(
-                node.filter.as_ref().unwrap(),
-                node.schema.as_ref(),
-                dep_ctx,
-                &mut subqueries,
-            )?;
+        if let Some(filter) = &node.filter {
+            // The filter sees the columns of both sides, while a semi, anti or mark join only
+            // outputs the columns of one side. The columns are resolved by their qualified names
+            // into their positions, so that the sides of a self join are not mixed up.
+            let filter_schema = node.left.schema().join(node.right.schema())?;
+            let filter =
+                self.conv_into_optd_og_expr(filter, &filter_schema, dep_ctx, &mut subqueries)?;
             log_ops.push(filter);
         }
-        assert!(
+        ensure!(
             subqueries.is_empty(),
             "Subqueries encountered in conv_into_optd_og_join---not supported currently"
         );
@@ -601,10 +746,102 @@ impl OptdPlanContext<'_> {
         Ok(LogicalLimit::new(input, skip, fetch))
     }
 
+    fn conv_into_optd_og_distinct(
+        &mut self,
+        node: &logical_plan::Distinct,
+        dep_ctx: Option<&DFSchema>,
+    ) -> Result<LogicalDistinct> {
+        match node {
+            logical_plan::Distinct::All(input) => {
+                let input = self.conv_into_optd_og_plan_node(input.as_ref(), dep_ctx)?;
+                Ok(LogicalDistinct::new(input))
+            }
+            logical_plan::Distinct::On(_) => bail!("unsupported distinct on"),
+        }
+    }
+
+    fn conv_into_optd_og_unnest(
+        &mut self,
+        node: &logical_plan::Unnest,
+        dep_ctx: Option<&DFSchema>,
+    ) -> Result<LogicalTableFunction> {
+        if !node.struct_type_columns.is_empty() {
+            bail!("unsupported unnest of struct columns")
+        }
+        if !node.options.recursions.is_empty() {
+            bail!("unsupported recursive unnest")
+        }
+        let input = self.conv_into_optd_og_plan_node(node.input.as_ref(), dep_ctx)?;
+        let mut args = Vec::with_capacity(node.list_type_columns.len() + 1);
+        for (idx, list) in &node.list_type_columns {
+            if list.depth != 1 {
+                bail!("unsupported unnest with depth {}", list.depth)
+            }
+            args.push(ColumnRefPred::new(*idx).into_pred_node());
+        }
+        args.push(ConstantPred::bool(node.options.preserve_nulls).into_pred_node());
+        Ok(LogicalTableFunction::new(
+            input,
+            UNNEST_FUNCTION.to_string(),
+            ListPred::new(args),
+            into_optd_og_schema(node.schema.as_arrow()),
+        ))
+    }
+
+    /// Stands in for a subtree optd_og cannot convert with a leaf, which is scanned like a table
+    /// function. The bridge plans the subtree on its own before lowering the optimized plan.
+    fn conv_into_optd_og_black_box(&mut self, node: &LogicalPlan) -> ArcDfPlanNode {
+        let black_boxes = self.black_boxes.as_mut().unwrap();
+        let name = format!("black_box#{}", black_boxes.len());
+        black_boxes.insert(name.clone(), node.clone());
+        let one_row = LogicalEmptyRelation::new(true, OptdSchema { fields: vec![] });
+        LogicalTableFunction::new(
+            one_row.into_plan_node(),
+            name,
+            ListPred::new(vec![]),
+            into_optd_og_schema(node.schema().as_arrow()),
+        )
+        .into_plan_node()
+    }
+
+    /// Drops the black boxes added since there were `black_box_cnt`, i.e., those below a node
+    /// which failed to convert, which are planned as part of the node instead.
+    fn truncate_black_boxes(&mut self, black_box_cnt: usize) {
+        if let Some(black_boxes) = self.black_boxes.as_mut() {
+            for idx in black_box_cnt..black_boxes.len() {
+                black_boxes.remove(&format!("black_box#{idx}"));
+            }
+        }
+    }
+
     fn conv_into_optd_og_plan_node(
         &mut self,
         node: &LogicalPlan,
         dep_ctx: Option<&DFSchema>,
+    ) -> Result<ArcDfPlanNode> {
+        let black_box_cnt = self
+            .black_boxes
+            .as_ref()
+            .map_or(0, |black_boxes| black_boxes.len());
+        match self.conv_into_optd_og_supported_plan_node(node, dep_ctx) {
+            // A subtree referencing the columns of an outer query cannot be planned on its own.
+            Err(err) if self.black_boxes.is_some() && dep_ctx.is_none() => {
+                tracing::debug!("planning {} with datafusion: {}", node.display(), err);
+                self.truncate_black_boxes(black_box_cnt);
+                Ok(self.conv_into_optd_og_black_box(node))
+            }
+            Err(err) => {
+                self.truncate_black_boxes(black_box_cnt);
+                Err(err)
+            }
+            res => res,
+        }
+    }
+
+    fn conv_into_optd_og_supported_plan_node(
+        &mut self,
+        node: &LogicalPlan,
+        dep_ctx: Option<&DFSchema>,
     ) -> Result<ArcDfPlanNode> {
         let node = match node {
             LogicalPlan::TableScan(node) => self.conv_into_optd_og_table_scan(node)?.into_plan_node(),
@@ -626,6 +863,12 @@ impl OptdPlanContext<'_> {
                 self.conv_into_optd_og_empty_relation(node)?.into_plan_node()
             }
             LogicalPlan::Limit(node) => self.conv_into_optd_og_limit(node, dep_ctx)?.into_plan_node(),
+            LogicalPlan::Distinct(node) => self
+                .conv_into_optd_og_distinct(node, dep_ctx)?
+                .into_plan_node(),
+            LogicalPlan::Unnest(node) => self
+                .conv_into_optd_og_unnest(node, dep_ctx)?
+                .into_plan_node(),
             _ => bail!(
                 "unsupported plan node: {}",
                 format!("{:?}", node).split('\n').next().unwrap()
@@ -634,8 +877,10 @@ impl OptdPlanContext<'_> {
         Ok(node)
     }
 
+    /// Converts the plan into optd_og. If black boxes are enabled, only the root must be
+    /// supported.
     pub fn conv_into_optd_og(&mut self, root_rel: &LogicalPlan) -> Result<ArcDfPlanNode> {
-        let res = self.conv_into_optd_og_plan_node(root_rel, None)?;
+        let res = self.conv_into_optd_og_supported_plan_node(root_rel, None)?;
         Ok(res.into_plan_node())
     }
 }
@@ -5,41 +5,86 @@
 
 #![allow(clippy::new_without_default)]
 
+pub mod advisor;
+pub mod audit;
+mod config;
+pub mod explain_options;
 mod from_optd;
+mod from_optd_logical;
 mod into_optd;
+#[cfg(feature = "otel")]
+pub mod otel;
+mod partial;
 mod physical_collector;
+mod runtime_filter;
+mod sampling;
+mod singleton;
+pub mod sql;
+#[cfg(feature = "substrait")]
+pub mod substrait;
+
+pub use config::OptdDFConfig;
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::Context;
 
 use async_trait::async_trait;
-use datafusion::arrow::datatypes::DataType;
 use datafusion::catalog::CatalogProviderList;
 use datafusion::catalog::MemoryCatalogProviderList;
+use datafusion::catalog::TableProvider;
+use datafusion::common::{
+    Constraint, DFSchema, DataFusionError, ResolvedTableReference, TableReference,
+};
+use datafusion::datasource::file_format::arrow::ArrowFormat;
+use datafusion::datasource::file_format::csv::CsvFormat;
+use datafusion::datasource::file_format::json::JsonFormat;
+use datafusion::datasource::file_format::parquet::ParquetFormat;
+use datafusion::datasource::listing::ListingTable;
+use datafusion::datasource::{source_as_provider, MemTable};
 use datafusion::execution::context::{QueryPlanner, SessionState};
 use datafusion::execution::runtime_env::RuntimeConfig;
 use datafusion::execution::SessionStateBuilder;
 use datafusion::logical_expr::{
-    Explain, LogicalPlan, PlanType, StringifiedPlan, TableSource, ToStringifiedPlan,
+    DmlStatement, Explain, LogicalPlan, PlanType, StringifiedPlan, TableSource, ToStringifiedPlan,
+    WriteOp,
 };
+use datafusion::physical_expr::expressions::Column;
 use datafusion::physical_plan::explain::ExplainExec;
-use datafusion::physical_plan::{displayable, ExecutionPlan};
-use datafusion::physical_planner::{DefaultPhysicalPlanner, PhysicalPlanner};
+use datafusion::physical_plan::projection::ProjectionExec;
+use datafusion::physical_plan::{displayable, ExecutionPlan, PhysicalExpr};
+use datafusion::physical_planner::{DefaultPhysicalPlanner, ExtensionPlanner, PhysicalPlanner};
 use datafusion::prelude::{SessionConfig, SessionContext};
 use itertools::Itertools;
+use optd_og_core::rules::Rule;
 use optd_og_datafusion_repr::plan_nodes::{
     dispatch_plan_explain_to_string, ArcDfPlanNode, ConstantType, DfNodeType, DfReprPlanNode,
-    PhysicalHashJoin, PhysicalNestedLoopJoin,
+    LogicalScan, PhysicalHashJoin, PhysicalNestedLoopJoin,
 };
-use optd_og_datafusion_repr::properties::schema::Catalog;
-use optd_og_datafusion_repr::{DatafusionOptimizer, MemoExt};
+use optd_og_datafusion_repr::properties::schema::{
+    AsyncCatalog, Catalog, IndexDef, ResolvedCatalog, ResolvedTable, ScanCapabilities, SchemaCache,
+    SourceFormat,
+};
+use optd_og_datafusion_repr::{DatafusionOptimizer, MemoExt, OptimizationResult, TableId};
 use optd_og_datafusion_repr_adv_cost::adv_stats::stats::DataFusionBaseTableStats;
 use optd_og_datafusion_repr_adv_cost::new_physical_adv_cost;
+use runtime_filter::RuntimeFilter;
+use sampling::SampleSelectivityEstimator;
+use tracing::Instrument;
 
 pub struct OptdPlanContext<'a> {
     tables: HashMap<String, Arc<dyn TableSource>>,
     session_state: &'a SessionState,
     pub optimizer: Option<&'a DatafusionOptimizer>,
+    /// The subtrees which optd_og cannot convert, by the name of the leaf standing in for them,
+    /// if black boxes are enabled.
+    black_boxes: Option<HashMap<String, LogicalPlan>>,
+    /// The plans of the black boxes.
+    black_box_execs: HashMap<String, Arc<dyn ExecutionPlan>>,
+    /// The runtime filters of the hash joins converted, by id.
+    runtime_filters: HashMap<u64, Arc<RuntimeFilter>>,
 }
 
 impl<'a> OptdPlanContext<'a> {
@@ -48,54 +93,207 @@ impl<'a> OptdPlanContext<'a> {
             tables: HashMap::new(),
             session_state,
             optimizer: None,
+            black_boxes: None,
+            black_box_execs: HashMap::new(),
+            runtime_filters: HashMap::new(),
         }
     }
+
+    /// Converts the subtrees below the root which optd_og does not support into opaque leaves
+    /// instead of failing the conversion, so that the rest of the plan is still optimized.
+    pub fn enable_black_boxes(&mut self) {
+        self.black_boxes = Some(HashMap::new());
+    }
+}
+
+/// The catalog and schema which the tables optd_og names without qualifiers belong to.
+const DEFAULT_CATALOG: &str = "datafusion";
+const DEFAULT_SCHEMA: &str = "public";
+
+/// Returns the name optd_og knows a table by, i.e., the name of its scans and the key of its
+/// statistics. The tables in the default schema are named by their bare names, and the others
+/// by their (quoted) qualified names, e.g., `sales.orders` or `other_catalog.sales.orders`, so
+/// that the tables of the same name in different schemas stay apart.
+pub fn optd_og_table_name(table: &ResolvedTableReference) -> String {
+    let reference = if table.catalog.as_ref() != DEFAULT_CATALOG {
+        TableReference::full(
+            table.catalog.clone(),
+            table.schema.clone(),
+            table.table.clone(),
+        )
+    } else if table.schema.as_ref() != DEFAULT_SCHEMA {
+        TableReference::partial(table.schema.clone(), table.table.clone())
+    } else {
+        TableReference::bare(table.table.clone())
+    };
+    reference.to_quoted_string()
 }
 
+/// The catalog of the datafusion session. The tables are looked up asynchronously, and the
+/// optimizer reads the tables [`OptdQueryPlanner`] resolves before optimizing each plan. The
+/// tables are named as by [`optd_og_table_name`].
 pub struct DatafusionCatalog {
     catalog: Arc<dyn CatalogProviderList>,
+    resolved: ResolvedCatalog,
+    /// The indexes of each table. Datafusion tables have no indexes, the indexes are the
+    /// hypothetical ones the index advisor evaluates.
+    hypothetical_indexes: RwLock<HashMap<TableId, Vec<IndexDef>>>,
 }
 
 impl DatafusionCatalog {
     pub fn new(catalog: Arc<dyn CatalogProviderList>) -> Self {
-        Self { catalog }
+        Self {
+            catalog,
+            resolved: ResolvedCatalog::new(),
+            hypothetical_indexes: RwLock::new(HashMap::new()),
+        }
     }
-}
 
-impl Catalog for DatafusionCatalog {
-    fn get(&self, name: &str) -> optd_og_datafusion_repr::properties::schema::Schema {
-        let catalog = self.catalog.catalog("datafusion").unwrap();
-        let schema = catalog.schema("public").unwrap();
-        let table = futures_lite::future::block_on(schema.table(name.as_ref()))
+    pub fn set_hypothetical_indexes(&self, table: &str, indexes: Vec<IndexDef>) {
+        self.hypothetical_indexes
+            .write()
             .unwrap()
-            .unwrap();
+            .insert(TableId::new(table), indexes);
+    }
+
+    pub fn clear_hypothetical_indexes(&self) {
+        self.hypothetical_indexes.write().unwrap().clear();
+    }
+
+    async fn table(&self, name: &str) -> anyhow::Result<Arc<dyn TableProvider>> {
+        let reference = TableReference::parse_str(name).resolve(DEFAULT_CATALOG, DEFAULT_SCHEMA);
+        let catalog = self
+            .catalog
+            .catalog(&reference.catalog)
+            .with_context(|| format!("catalog {} not found", reference.catalog))?;
+        let schema = catalog.schema(&reference.schema).with_context(|| {
+            format!(
+                "schema {}.{} not found",
+                reference.catalog, reference.schema
+            )
+        })?;
+        schema
+            .table(&reference.table)
+            .await?
+            .with_context(|| format!("table {} not found", name))
+    }
+
+    /// Resolves the tables the next plan reads, so that the optimizer can look them up. Returns
+    /// the tables which changed since they were last resolved.
+    pub async fn resolve_tables<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> anyhow::Result<Vec<TableId>> {
+        self.resolved.resolve(self, names).await
+    }
+}
+
+#[async_trait]
+impl AsyncCatalog for DatafusionCatalog {
+    async fn resolve(&self, name: &str) -> anyhow::Result<ResolvedTable> {
+        let table = self.table(name).await?;
         let schema = table.schema();
         let fields = schema.fields();
         let mut optd_og_fields = Vec::with_capacity(fields.len());
         for field in fields {
-            let dt = match field.data_type() {
-                DataType::Date32 => ConstantType::Date,
-                DataType::Int32 => ConstantType::Int32,
-                DataType::Int64 => ConstantType::Int64,
-                DataType::Float64 => ConstantType::Decimal,
-                DataType::Utf8 => ConstantType::Utf8String,
-                DataType::Decimal128(_, _) => ConstantType::Decimal,
-                dt => unimplemented!("{:?}", dt),
-            };
             optd_og_fields.push(optd_og_datafusion_repr::properties::schema::Field {
                 name: field.name().to_string(),
-                typ: dt,
+                typ: ConstantType::from_data_type(field.data_type().clone()),
                 nullable: field.is_nullable(),
             });
         }
-        optd_og_datafusion_repr::properties::schema::Schema {
-            fields: optd_og_fields,
-        }
+        let primary_key = table.constraints().and_then(|constraints| {
+            constraints.iter().find_map(|constraint| match constraint {
+                Constraint::PrimaryKey(indices) => Some(indices.clone()),
+                _ => None,
+            })
+        });
+        let provider = table.as_any();
+        let listing_table = provider.downcast_ref::<ListingTable>();
+        let format = if provider.is::<MemTable>() {
+            SourceFormat::Memory
+        } else if let Some(listing_table) = listing_table {
+            let file_format = listing_table.options().format.as_any();
+            if file_format.is::<ParquetFormat>() {
+                SourceFormat::Parquet
+            } else if file_format.is::<CsvFormat>() {
+                SourceFormat::Csv
+            } else if file_format.is::<JsonFormat>() {
+                SourceFormat::Json
+            } else if file_format.is::<ArrowFormat>() {
+                SourceFormat::Arrow
+            } else {
+                SourceFormat::Unknown
+            }
+        } else {
+            SourceFormat::Unknown
+        };
+        // In-memory batches and Parquet files are columnar, while other file formats are
+        // row-oriented.
+        let projection_pushdown = matches!(format, SourceFormat::Memory | SourceFormat::Parquet);
+        // Parquet files are pruned by the statistics of their row groups and pages.
+        let filter_pushdown = format == SourceFormat::Parquet;
+        // The partition columns of a listing table are the last columns of its schema.
+        let partition_columns = listing_table.map_or(vec![], |table| {
+            table
+                .options()
+                .table_partition_cols
+                .iter()
+                .filter_map(|(name, _)| schema.index_of(name).ok())
+                .collect()
+        });
+        Ok(ResolvedTable {
+            schema: optd_og_datafusion_repr::properties::schema::Schema {
+                fields: optd_og_fields,
+            },
+            primary_key,
+            scan_capabilities: ScanCapabilities {
+                projection_pushdown,
+                filter_pushdown,
+                format,
+            },
+            partition_columns,
+        })
+    }
+}
+
+impl Catalog for DatafusionCatalog {
+    fn get(&self, name: &TableId) -> optd_og_datafusion_repr::properties::schema::Schema {
+        self.resolved.get(name)
+    }
+
+    fn primary_key(&self, name: &TableId) -> Option<Vec<usize>> {
+        self.resolved.primary_key(name)
+    }
+
+    fn scan_capabilities(&self, name: &TableId) -> ScanCapabilities {
+        self.resolved.scan_capabilities(name)
+    }
+
+    fn partition_columns(&self, name: &TableId) -> Vec<usize> {
+        self.resolved.partition_columns(name)
+    }
+
+    fn indexes(&self, name: &TableId) -> Vec<IndexDef> {
+        self.hypothetical_indexes
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .unwrap_or_default()
     }
 }
 
 pub struct OptdQueryPlanner {
     pub optimizer: Arc<Mutex<Option<Box<DatafusionOptimizer>>>>,
+    /// The catalog the optimizer was created with.
+    catalog: Arc<DatafusionCatalog>,
+    /// The table schemas memoized by the optimizer, see [`DatafusionOptimizer::schema_cache`].
+    schema_cache: Arc<SchemaCache>,
+    /// The extension planners of the nodes that optd_og cannot convert, if partial optimization
+    /// is enabled.
+    partial_optimization: Mutex<Option<Vec<Arc<dyn ExtensionPlanner + Send + Sync>>>>,
+    fallback_on_error: AtomicBool,
 }
 
 impl OptdQueryPlanner {
@@ -117,35 +315,174 @@ impl OptdQueryPlanner {
             .enable_adaptive(false);
     }
 
+    /// Marks the statistics of `table` as out of date, to be called after ingesting data into
+    /// it, see [`DatafusionOptimizer::invalidate_stats`]. The explains of the queries scanning
+    /// the table report its statistics as stale until the next analyze is recorded.
+    pub fn invalidate_stats(&self, table: &str) {
+        self.optimizer
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .invalidate_stats(table);
+    }
+
+    /// Optimizes the parts of a plan that optd_og can convert, instead of failing the whole
+    /// statement, e.g., on an extension node. optd_og optimizes each maximal convertible subtree
+    /// on its own, and the datafusion planner plans the nodes above them with the given
+    /// extension planners. The unsupported subtrees below a convertible node are black boxes of
+    /// the optimized plan, planned the same way.
+    pub fn enable_partial_optimization(
+        &self,
+        extension_planners: Vec<Arc<dyn ExtensionPlanner + Send + Sync>>,
+    ) {
+        *self.partial_optimization.lock().unwrap() = Some(extension_planners);
+    }
+
+    pub fn disable_partial_optimization(&self) {
+        *self.partial_optimization.lock().unwrap() = None;
+    }
+
+    /// The `optd.fallback_on_error` knob: plan the query with the datafusion planner if optd_og
+    /// fails to, instead of failing the statement.
+    pub fn set_fallback_on_error(&self, fallback_on_error: bool) {
+        self.fallback_on_error
+            .store(fallback_on_error, Ordering::Relaxed);
+    }
+
+    pub fn fallback_on_error(&self) -> bool {
+        self.fallback_on_error.load(Ordering::Relaxed)
+    }
+
+    /// Resolves the tables of a converted plan, which the optimizer looks up synchronously, and
+    /// invalidates the memoized schemas of the tables which changed, e.g., were re-created.
+    pub async fn resolve_tables(&self, ctx: &OptdPlanContext<'_>) -> anyhow::Result<()> {
+        self.resolve_table_names(ctx.tables.keys().map(String::as_str))
+            .await
+    }
+
+    async fn resolve_table_names<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> anyhow::Result<()> {
+        let changed = self.catalog.resolve_tables(names).await?;
+        for name in changed {
+            self.schema_cache.invalidate(&name);
+        }
+        Ok(())
+    }
+
+    /// Plans the query feeding an `INSERT INTO ... SELECT` with optd_og, and inserts its output
+    /// into the table like the datafusion planner does. Returns `None` if the source query
+    /// cannot be converted, e.g., for `INSERT INTO ... VALUES`.
+    async fn create_insert_plan(
+        &self,
+        dml: &DmlStatement,
+        session_state: &SessionState,
+    ) -> anyhow::Result<Option<Arc<dyn ExecutionPlan>>> {
+        let WriteOp::Insert(insert_op) = &dml.op else {
+            return Ok(None);
+        };
+        let input = dml.input.as_ref();
+        if let Err(err) = OptdPlanContext::new(session_state).conv_into_optd_og(input) {
+            tracing::debug!(
+                "planning the source of {} with datafusion: {}",
+                dml.table_name,
+                err
+            );
+            return Ok(None);
+        }
+        let input_exec = self
+            .create_optd_og_physical_plan(input, session_state)
+            .await?;
+        // optd_og does not preserve the column names, which the table may check on insertion.
+        let input_exec = rename_columns(input_exec, input.schema())?;
+        let provider = source_as_provider(&dml.target)?;
+        let plan = provider
+            .insert_into(session_state, input_exec, *insert_op)
+            .await?;
+        Ok(Some(plan))
+    }
+
     async fn create_physical_plan_inner(
         &self,
         logical_plan: &LogicalPlan,
         session_state: &SessionState,
     ) -> anyhow::Result<Arc<dyn ExecutionPlan>> {
+        if let LogicalPlan::Dml(dml) = logical_plan {
+            if let Some(plan) = self.create_insert_plan(dml, session_state).await? {
+                return Ok(plan);
+            }
+        }
         if let LogicalPlan::Dml(_) | LogicalPlan::Ddl(_) | LogicalPlan::EmptyRelation(_) =
             logical_plan
         {
-            // Fallback to the datafusion planner for DML/DDL operations. optd_og cannot handle this.
+            // Fallback to the datafusion planner for other DML and DDL operations. optd_og cannot
+            // handle this.
             let planner = DefaultPhysicalPlanner::default();
             return Ok(planner
                 .create_physical_plan(logical_plan, session_state)
                 .await?);
         }
+        if !matches!(logical_plan, LogicalPlan::Explain(_)) {
+            let extension_planners = self.partial_optimization.lock().unwrap().clone();
+            if let Some(extension_planners) = extension_planners {
+                let mut ctx = OptdPlanContext::new(session_state);
+                ctx.enable_black_boxes();
+                if let Err(err) = ctx.conv_into_optd_og(logical_plan) {
+                    tracing::debug!("optimizing the convertible parts of the plan: {}", err);
+                    return self
+                        .create_partial_physical_plan(
+                            logical_plan,
+                            session_state,
+                            extension_planners,
+                        )
+                        .await;
+                }
+            }
+        }
+        self.create_optd_og_physical_plan(logical_plan, session_state)
+            .await
+    }
+
+    async fn create_optd_og_physical_plan(
+        &self,
+        logical_plan: &LogicalPlan,
+        session_state: &SessionState,
+    ) -> anyhow::Result<Arc<dyn ExecutionPlan>> {
         let (mut explains, verbose, logical_plan) = match logical_plan {
             LogicalPlan::Explain(Explain { plan, verbose, .. }) => {
                 (Some(Vec::new()), *verbose, plan.as_ref())
             }
             _ => (None, false, logical_plan),
         };
+        let extension_planners = self.partial_optimization.lock().unwrap().clone();
         let mut ctx = OptdPlanContext::new(session_state);
+        if extension_planners.is_some() {
+            ctx.enable_black_boxes();
+        }
         if let Some(explains) = &mut explains {
             explains.push(logical_plan.to_stringified(PlanType::OptimizedLogicalPlan {
                 optimizer_name: "datafusion".to_string(),
             }));
         }
-        let mut optd_og_rel = ctx.conv_into_optd_og(logical_plan)?;
+        let optd_og_rel = tracing::info_span!("optd_og.conversion")
+            .in_scope(|| ctx.conv_into_optd_og(logical_plan))?;
+        if let Some(extension_planners) = extension_planners {
+            self.plan_black_boxes(&mut ctx, session_state, extension_planners)
+                .await?;
+        }
+        self.resolve_tables(&ctx).await?;
 
-        if let Some(explains) = &mut explains {
+        let config = session_state
+            .config()
+            .options()
+            .extensions
+            .get::<OptdDFConfig>()
+            .cloned()
+            .unwrap_or_default();
+
+        if let Some(explains) = explains.as_mut().filter(|_| config.explain_logical) {
             explains.push(StringifiedPlan::new(
                 PlanType::OptimizedLogicalPlan {
                     optimizer_name: "optd_og".to_string(),
@@ -160,27 +497,111 @@ impl OptdQueryPlanner {
                 .unwrap()
                 .explain_to_string(None)));
 
-        let mut optimizer = self.optimizer.lock().unwrap().take().unwrap();
+        let table_row_hints = config.table_row_hints()?;
+        let default_selectivities = config.default_selectivities()?;
+
+        let mut optimizer = self
+            .optimizer
+            .lock()
+            .unwrap()
+            .take()
+            .context("the optimizer is already in use")?;
+        {
+            let mut cardinality_hints = optimizer.cardinality_hints.lock().unwrap();
+            cardinality_hints.replace_table_rows(table_row_hints);
+            cardinality_hints.set_default_selectivities(default_selectivities);
+        }
+        if config.sample_selectivity {
+            // sample the filters the optimizer starts from, i.e., after they are pushed down
+            let plan = if optimizer.is_heuristic_enabled() {
+                optimizer.heuristic_optimize(optd_og_rel.clone())
+            } else {
+                optd_og_rel.clone()
+            };
+            let mut estimator = SampleSelectivityEstimator::new(
+                &self.catalog,
+                session_state,
+                config.sample_rows,
+                config.sample_budget,
+            );
+            match estimator
+                .estimate(&ctx, &optimizer, plan)
+                .instrument(tracing::info_span!("optd_og.sampling"))
+                .await
+            {
+                Ok(sampled) => tracing::debug!("sampled the selectivity of {} filters", sampled),
+                Err(err) => {
+                    tracing::warn!("failed to sample the selectivity of the filters: {}", err)
+                }
+            }
+        }
+        optimizer.set_misestimate_threshold(config.misestimate_threshold);
+        optimizer.set_runtime_filter_selectivity(config.runtime_filter_selectivity);
+        // the budgets and the rules of the session only apply to this query
+        let prop = optimizer.optd_og_cascades_optimizer().prop.clone();
+        let disabled_rules = optimizer.disabled_rules().to_vec();
+        config.apply_exploration_budgets(&mut optimizer.optd_og_optimizer_mut().prop);
+        let result = config.rules_to_disable(&optimizer).and_then(|rules| {
+            optimizer.set_disabled_rules(disabled_rules.iter().cloned().chain(rules).collect())?;
+            optimizer.optimize(optd_og_rel)
+        });
+        optimizer.optd_og_optimizer_mut().prop = prop;
+        optimizer
+            .set_disabled_rules(disabled_rules)
+            .expect("the rules were disabled before");
+
+        let OptimizationResult {
+            group_id,
+            plan: optimized_rel,
+            meta,
+            heuristic_plan,
+            warnings,
+            stale_stats,
+            ..
+        } = match result {
+            Ok(result) => result,
+            Err(err) => {
+                self.optimizer.lock().unwrap().replace(optimizer);
+                return Err(err.context("failed to optimize the plan"));
+            }
+        };
 
-        if optimizer.is_heuristic_enabled() {
-            // TODO: depjoin pushdown might need to run multiple times
-            optd_og_rel = optimizer.heuristic_optimize(optd_og_rel);
-            if let Some(explains) = &mut explains {
+        if let Some(heuristic_plan) = heuristic_plan {
+            if let Some(explains) = explains.as_mut().filter(|_| config.explain_logical) {
                 explains.push(StringifiedPlan::new(
                     PlanType::OptimizedLogicalPlan {
                         optimizer_name: "optd_og-heuristic".to_string(),
                     },
-                    dispatch_plan_explain_to_string(optd_og_rel.clone(), None),
+                    dispatch_plan_explain_to_string(heuristic_plan.clone(), None),
                 ))
             }
             tracing::trace!(
                 optd_og_optimized_plan = %("\n".to_string()
-                + &dispatch_plan_explain_to_string(optd_og_rel.clone(), None)));
+                + &dispatch_plan_explain_to_string(heuristic_plan, None)));
         }
-
-        let (group_id, optimized_rel, meta) = optimizer.cascades_optimize(optd_og_rel)?;
-
         if let Some(explains) = &mut explains {
+            if !warnings.is_empty() {
+                explains.push(StringifiedPlan::new(
+                    PlanType::OptimizedPhysicalPlan {
+                        optimizer_name: "optd_og-warnings".to_string(),
+                    },
+                    warnings.join("\n"),
+                ));
+            }
+            if !stale_stats.is_empty() {
+                explains.push(StringifiedPlan::new(
+                    PlanType::OptimizedPhysicalPlan {
+                        optimizer_name: "optd_og-stale-stats".to_string(),
+                    },
+                    stale_stats.iter().map(|x| x.to_string()).join("\n"),
+                ));
+            }
+        }
+        for warning in warnings {
+            tracing::warn!("{}", warning);
+        }
+
+        if let Some(explains) = explains.as_mut().filter(|_| config.explain_physical) {
             explains.push(StringifiedPlan::new(
                 PlanType::OptimizedPhysicalPlan {
                     optimizer_name: "optd_og".to_string(),
@@ -190,6 +611,8 @@ impl OptdQueryPlanner {
                     if verbose { Some(&meta) } else { None },
                 ),
             ));
+        }
+        if let Some(explains) = explains.as_mut().filter(|_| config.explain_join_orders) {
             tracing::debug!("generating optd_og-join-order");
             let join_orders = optimizer
                 .optd_og_cascades_optimizer()
@@ -213,20 +636,54 @@ impl OptdQueryPlanner {
                 },
             ));
         }
+        if let Some(explains) = explains.as_mut().filter(|_| config.explain_physical) {
+            // the rules which produced the expressions of the chosen plan, and the expressions
+            // they were applied to
+            let cascades_optimizer = optimizer.optd_og_cascades_optimizer();
+            let rules = cascades_optimizer.rules();
+            let rules_fired = cascades_optimizer
+                .step_winner_rules(group_id)
+                .into_iter()
+                .map(|rule_id| {
+                    let rule = &rules[rule_id];
+                    let kind = if rule.is_impl_rule() {
+                        "implementation"
+                    } else {
+                        "transformation"
+                    };
+                    format!("{} ({})", rule.name(), kind)
+                })
+                .sorted()
+                .collect_vec();
+            explains.push(StringifiedPlan::new(
+                PlanType::OptimizedPhysicalPlan {
+                    optimizer_name: "optd_og-rules-fired".to_string(),
+                },
+                if rules_fired.is_empty() {
+                    "None".to_string()
+                } else {
+                    rules_fired.join("\n")
+                },
+            ));
+        }
 
         tracing::trace!(
             optd_og_physical_plan = %("\n".to_string()
             + &dispatch_plan_explain_to_string(optimized_rel.clone(), None)));
 
         ctx.optimizer = Some(&optimizer);
-        let physical_plan = ctx.conv_from_optd_og(optimized_rel, meta).await?;
+        let physical_plan = ctx
+            .conv_from_optd_og(optimized_rel, meta)
+            .instrument(tracing::info_span!("optd_og.lowering"))
+            .await;
+        self.optimizer.lock().unwrap().replace(optimizer);
+        let physical_plan = physical_plan.context("failed to lower the optimized plan")?;
         if let Some(explains) = &mut explains {
             explains.push(
                 displayable(&*physical_plan)
                     .to_stringified(false, datafusion::logical_expr::PlanType::FinalPhysicalPlan),
             );
         }
-        self.optimizer.lock().unwrap().replace(optimizer);
         if let Some(explains) = explains {
             Ok(Arc::new(ExplainExec::new(
                 LogicalPlan::explain_schema(),
@@ -238,13 +695,116 @@ impl OptdQueryPlanner {
         }
     }
 
-    pub fn new(optimizer: DatafusionOptimizer) -> Self {
+    /// Optimizes a logical plan with optd_og and converts the chosen plan back into a datafusion
+    /// logical plan with the same output schema, so that optd_og can be used as a logical
+    /// rewriter in front of another planner or execution engine. The subtrees optd_og cannot
+    /// convert are kept as they are.
+    pub async fn optimize_logical_plan(
+        &self,
+        logical_plan: &LogicalPlan,
+        session_state: &SessionState,
+    ) -> anyhow::Result<LogicalPlan> {
+        let (plan, _) = self
+            .optimize_logical_plan_with_result(logical_plan, session_state)
+            .await?;
+        Ok(plan)
+    }
+
+    /// Like [`Self::optimize_logical_plan`], also returning the result of the optimization with
+    /// the chosen physical plan and its costs.
+    pub async fn optimize_logical_plan_with_result(
+        &self,
+        logical_plan: &LogicalPlan,
+        session_state: &SessionState,
+    ) -> anyhow::Result<(LogicalPlan, OptimizationResult)> {
+        let mut ctx = OptdPlanContext::new(session_state);
+        ctx.enable_black_boxes();
+        let optd_og_rel = ctx.conv_into_optd_og(logical_plan)?;
+        self.resolve_tables(&ctx).await?;
+        let mut optimizer = self
+            .optimizer
+            .lock()
+            .unwrap()
+            .take()
+            .context("the optimizer is already in use")?;
+        let result = optimizer.optimize(optd_og_rel);
+        self.optimizer.lock().unwrap().replace(optimizer);
+        let result = result.context("failed to optimize the plan")?;
+        for warning in &result.warnings {
+            tracing::warn!("{}", warning);
+        }
+        let plan = ctx.conv_from_optd_og_logical(result.plan.clone(), logical_plan.schema())?;
+        Ok((plan, result))
+    }
+
+    /// Optimizes a logical optd_og plan, e.g., one read with
+    /// [`optd_og_datafusion_repr::plan_from_json`]. The tables the plan scans are resolved in the
+    /// catalog of the planner first.
+    pub async fn optimize_optd_og_plan_with_result(
+        &self,
+        plan: ArcDfPlanNode,
+    ) -> anyhow::Result<OptimizationResult> {
+        let mut tables = Vec::new();
+        collect_logical_scans(&plan, &mut tables);
+        self.resolve_table_names(tables.iter().map(|table| table.as_ref()))
+            .await?;
+        let mut optimizer = self
+            .optimizer
+            .lock()
+            .unwrap()
+            .take()
+            .context("the optimizer is already in use")?;
+        let result = optimizer.optimize(plan);
+        self.optimizer.lock().unwrap().replace(optimizer);
+        let result = result.context("failed to optimize the plan")?;
+        for warning in &result.warnings {
+            tracing::warn!("{}", warning);
+        }
+        Ok(result)
+    }
+
+    /// Creates a planner with an optimizer created with `catalog`.
+    pub fn new(optimizer: DatafusionOptimizer, catalog: Arc<DatafusionCatalog>) -> Self {
         Self {
+            schema_cache: optimizer.schema_cache.clone(),
             optimizer: Arc::new(Mutex::new(Some(Box::new(optimizer)))),
+            catalog,
+            partial_optimization: Mutex::new(None),
+            fallback_on_error: AtomicBool::new(false),
         }
     }
 }
 
+/// The tables scanned by a logical plan.
+fn collect_logical_scans(plan: &ArcDfPlanNode, tables: &mut Vec<Arc<str>>) {
+    if let Some(scan) = LogicalScan::from_plan_node(plan.clone()) {
+        tables.push(scan.table());
+    }
+    for child in &plan.children {
+        collect_logical_scans(&child.unwrap_plan_node(), tables);
+    }
+}
+
+/// Renames the output columns of a plan produced by optd_og, which does not preserve the column
+/// names, to the names in the logical schema.
+fn rename_columns(
+    exec: Arc<dyn ExecutionPlan>,
+    schema: &DFSchema,
+) -> anyhow::Result<Arc<dyn ExecutionPlan>> {
+    let exprs = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            (
+                Arc::new(Column::new(field.name(), idx)) as Arc<dyn PhysicalExpr>,
+                field.name().to_string(),
+            )
+        })
+        .collect_vec();
+    Ok(Arc::new(ProjectionExec::try_new(exprs, exec)?))
+}
+
 impl std::fmt::Debug for OptdQueryPlanner {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "OptdQueryPlanner")
@@ -258,15 +818,27 @@ impl QueryPlanner for OptdQueryPlanner {
         logical_plan: &LogicalPlan,
         session_state: &SessionState,
     ) -> datafusion::common::Result<Arc<dyn ExecutionPlan>> {
-        Ok(self
+        match self
             .create_physical_plan_inner(logical_plan, session_state)
             .await
-            .unwrap())
+        {
+            Ok(plan) => Ok(plan),
+            Err(err) if self.fallback_on_error() => {
+                tracing::warn!("planning with datafusion: {:#}", err);
+                DefaultPhysicalPlanner::default()
+                    .create_physical_plan(logical_plan, session_state)
+                    .await
+            }
+            Err(err) => Err(DataFusionError::External(
+                err.context("optd_og failed to plan the query").into(),
+            )),
+        }
     }
 }
 
+/// The joins of a physical plan and the tables they join, ignoring the other operators.
 #[derive(Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
-enum JoinOrder {
+pub enum JoinOrder {
     Table(String),
     HashJoin(Box<Self>, Box<Self>),
     NestedLoopJoin(Box<Self>, Box<Self>),
@@ -286,7 +858,8 @@ impl std::fmt::Display for JoinOrder {
     }
 }
 
-fn get_join_order(rel_node: ArcDfPlanNode) -> Option<JoinOrder> {
+/// Returns `None` if the plan has no scan.
+pub fn get_join_order(rel_node: ArcDfPlanNode) -> Option<JoinOrder> {
     match rel_node.typ {
         DfNodeType::PhysicalHashJoin(_) => {
             let join = PhysicalHashJoin::from_plan_node(rel_node.clone()).unwrap();
@@ -341,6 +914,16 @@ pub async fn create_df_context(
     if !use_df_logical {
         session_config.options_mut().optimizer.max_passes = 0;
     }
+    if session_config
+        .options()
+        .extensions
+        .get::<OptdDFConfig>()
+        .is_none()
+    {
+        session_config = session_config.with_option_extension(OptdDFConfig::default());
+    }
+
+    let target_partitions = session_config.target_partitions();
 
     let rn_config = if let Some(rn_config) = rn_config {
         rn_config
@@ -361,25 +944,30 @@ pub async fn create_df_context(
         .with_catalog_list(catalog.clone())
         .with_default_features();
 
-    let optimizer = if with_advanced_cost {
-        new_physical_adv_cost(
-            Arc::new(DatafusionCatalog::new(catalog.clone())),
-            stats.unwrap_or_default(),
-            enable_adaptive,
-        )
+    let optd_og_catalog = Arc::new(DatafusionCatalog::new(catalog.clone()));
+    let stats = stats.unwrap_or_default();
+    let analyzed_tables = stats
+        .iter()
+        .map(|(table, table_stats)| (table.clone(), table_stats.row_cnt))
+        .collect_vec();
+    let mut optimizer = if with_advanced_cost {
+        new_physical_adv_cost(optd_og_catalog.clone(), stats, enable_adaptive)
     } else {
-        DatafusionOptimizer::new_physical(
-            Arc::new(DatafusionCatalog::new(catalog.clone())),
-            enable_adaptive,
-        )
+        DatafusionOptimizer::new_physical(optd_og_catalog.clone(), enable_adaptive)
     };
+    for (table, row_cnt) in analyzed_tables {
+        optimizer
+            .stats_freshness_mut()
+            .record_analyze(&table, row_cnt);
+    }
+    optimizer.set_target_partitions(target_partitions);
     if !use_df_logical {
         // clean up optimizer rules so that we can plug in our own optimizer
         builder = builder.with_optimizer_rules(vec![]);
     }
     builder = builder.with_physical_optimizer_rules(vec![]);
     // use optd_og-bridge query planner
-    let optimizer = Arc::new(OptdQueryPlanner::new(optimizer));
+    let optimizer = Arc::new(OptdQueryPlanner::new(optimizer, optd_og_catalog));
     builder = builder.with_query_planner(optimizer.clone());
     let state = builder.build();
     let ctx = SessionContext::new_with_state(state).enable_url_table();
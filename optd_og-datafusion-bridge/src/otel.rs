@@ -0,0 +1,109 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! OpenTelemetry export of the planning spans.
+//!
+//! The planner emits `tracing` spans for each planning phase: `optd_og.conversion`,
+//! `optd_og.heuristic`, `optd_og.stage` (once for each stage of the cascades search, with the
+//! stage name in the `stage` field) and `optd_og.lowering`. When tracing
+//! is enabled in the cascades optimizer properties, an `optd_og.rule` span is additionally
+//! emitted for every rule application. This module forwards these spans to an OpenTelemetry
+//! tracer provider supplied by the user, so that planning latency can be analyzed with existing
+//! APM tooling.
+
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider;
+use tracing_opentelemetry::PreSampledTracer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// The instrumentation scope name used for the tracer.
+pub const TRACER_NAME: &str = "optd_og";
+
+/// Create a `tracing` layer exporting the spans to the given tracer provider. Use this if the
+/// application already has its own `tracing` subscriber.
+pub fn otel_layer<S, P>(provider: &P) -> impl Layer<S>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    P: TracerProvider,
+    P::Tracer: PreSampledTracer + Send + Sync + 'static,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(TRACER_NAME))
+}
+
+/// Install a global `tracing` subscriber exporting the planning spans to the given tracer
+/// provider. Fails if a global subscriber has already been set.
+pub fn install_tracer_provider<P>(provider: &P) -> Result<()>
+where
+    P: TracerProvider,
+    P::Tracer: PreSampledTracer + Send + Sync + 'static,
+{
+    let subscriber = tracing_subscriber::registry().with(otel_layer(provider));
+    tracing::subscriber::set_global_default(subscriber)
+        .context("failed to install the opentelemetry subscriber")
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    use super::*;
+    use crate::create_df_context;
+
+    #[tokio::test]
+    async fn export_planning_spans() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+
+        let ctx = create_df_context(None, None, None, false, false, false, None)
+            .await
+            .unwrap()
+            .ctx;
+        ctx.sql("create table t1(v1 int, v2 int)")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let _guard = tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(otel_layer(&provider)),
+        );
+        ctx.sql("select * from t1 where v1 = 1")
+            .await
+            .unwrap()
+            .create_physical_plan()
+            .await
+            .unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        for name in ["optd_og.conversion", "optd_og.stage", "optd_og.lowering"] {
+            assert!(
+                spans.iter().any(|span| span.name == name),
+                "no {} span",
+                name
+            );
+        }
+        assert!(spans
+            .iter()
+            .all(|span| span.instrumentation_scope.name() == TRACER_NAME));
+        // the name of the stage is an attribute of its span
+        let stages = spans
+            .iter()
+            .filter(|span| span.name == "optd_og.stage")
+            .map(|span| {
+                span.attributes
+                    .iter()
+                    .find(|attribute| attribute.key.as_str() == "stage")
+                    .map(|attribute| attribute.value.as_str().to_string())
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(stages, vec!["stage1", "stage2"]);
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Partial optimization: optimizes the parts of a plan which optd_og can convert and leaves the
+//! rest, e.g., the extension nodes of an embedder, to the datafusion planner.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use async_recursion::async_recursion;
+use async_trait::async_trait;
+use datafusion::common::DFSchemaRef;
+use datafusion::execution::context::SessionState;
+use datafusion::logical_expr::{
+    Expr, Extension, LogicalPlan, UserDefinedLogicalNode, UserDefinedLogicalNodeCore,
+};
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::physical_planner::{DefaultPhysicalPlanner, ExtensionPlanner, PhysicalPlanner};
+
+use crate::{rename_columns, OptdPlanContext, OptdQueryPlanner};
+
+/// A subtree of the logical plan which optd_og has already optimized and lowered.
+#[derive(Debug)]
+struct OptdSubplanNode {
+    schema: DFSchemaRef,
+    exec: Arc<dyn ExecutionPlan>,
+}
+
+impl PartialEq for OptdSubplanNode {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.exec, &other.exec)
+    }
+}
+
+impl Eq for OptdSubplanNode {}
+
+impl PartialOrd for OptdSubplanNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Arc::as_ptr(&self.exec)
+            .cast::<()>()
+            .partial_cmp(&Arc::as_ptr(&other.exec).cast::<()>())
+    }
+}
+
+impl Hash for OptdSubplanNode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.exec).cast::<()>().hash(state);
+    }
+}
+
+impl UserDefinedLogicalNodeCore for OptdSubplanNode {
+    fn name(&self) -> &str {
+        "OptdSubplan"
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "OptdSubplan")
+    }
+
+    fn with_exprs_and_inputs(
+        &self,
+        _exprs: Vec<Expr>,
+        _inputs: Vec<LogicalPlan>,
+    ) -> datafusion::common::Result<Self> {
+        Ok(Self {
+            schema: self.schema.clone(),
+            exec: self.exec.clone(),
+        })
+    }
+}
+
+/// Plans [`OptdSubplanNode`]s as the plans optd_og produced for them.
+struct OptdSubplanPlanner;
+
+#[async_trait]
+impl ExtensionPlanner for OptdSubplanPlanner {
+    async fn plan_extension(
+        &self,
+        _planner: &dyn PhysicalPlanner,
+        node: &dyn UserDefinedLogicalNode,
+        _logical_inputs: &[&LogicalPlan],
+        _physical_inputs: &[Arc<dyn ExecutionPlan>],
+        _session_state: &SessionState,
+    ) -> datafusion::common::Result<Option<Arc<dyn ExecutionPlan>>> {
+        Ok(node
+            .as_any()
+            .downcast_ref::<OptdSubplanNode>()
+            .map(|node| node.exec.clone()))
+    }
+}
+
+impl OptdQueryPlanner {
+    /// Optimizes each maximal subtree of the plan that optd_og can convert, and plans the
+    /// remaining nodes with the datafusion planner and the given extension planners.
+    pub(crate) async fn create_partial_physical_plan(
+        &self,
+        logical_plan: &LogicalPlan,
+        session_state: &SessionState,
+        extension_planners: Vec<Arc<dyn ExtensionPlanner + Send + Sync>>,
+    ) -> anyhow::Result<Arc<dyn ExecutionPlan>> {
+        let logical_plan = self
+            .optimize_convertible_subtrees(logical_plan, session_state)
+            .await?;
+        let mut planners: Vec<Arc<dyn ExtensionPlanner + Send + Sync>> =
+            vec![Arc::new(OptdSubplanPlanner)];
+        planners.extend(extension_planners);
+        let planner = DefaultPhysicalPlanner::with_extension_planners(planners);
+        Ok(planner
+            .create_physical_plan(&logical_plan, session_state)
+            .await?)
+    }
+
+    /// Plans the black boxes of a converted plan, optimizing their convertible parts on their
+    /// own.
+    pub(crate) async fn plan_black_boxes(
+        &self,
+        ctx: &mut OptdPlanContext<'_>,
+        session_state: &SessionState,
+        extension_planners: Vec<Arc<dyn ExtensionPlanner + Send + Sync>>,
+    ) -> anyhow::Result<()> {
+        for (name, plan) in ctx.black_boxes.take().unwrap_or_default() {
+            let exec = self
+                .create_partial_physical_plan(&plan, session_state, extension_planners.clone())
+                .await?;
+            ctx.black_box_execs.insert(name, exec);
+        }
+        Ok(())
+    }
+
+    /// Replaces the maximal convertible subtrees of the plan with [`OptdSubplanNode`]s.
+    #[async_recursion]
+    async fn optimize_convertible_subtrees(
+        &self,
+        logical_plan: &LogicalPlan,
+        session_state: &SessionState,
+    ) -> anyhow::Result<LogicalPlan> {
+        let mut ctx = OptdPlanContext::new(session_state);
+        ctx.enable_black_boxes();
+        match ctx.conv_into_optd_og(logical_plan) {
+            Ok(_) => {
+                let exec = self
+                    .create_optd_og_physical_plan(logical_plan, session_state)
+                    .await?;
+                // The parent is planned by datafusion, which looks up the columns by name.
+                let exec = rename_columns(exec, logical_plan.schema())?;
+                return Ok(LogicalPlan::Extension(Extension {
+                    node: Arc::new(OptdSubplanNode {
+                        schema: logical_plan.schema().clone(),
+                        exec,
+                    }),
+                }));
+            }
+            Err(err) => {
+                tracing::debug!(
+                    "planning {} with datafusion: {}",
+                    logical_plan.display(),
+                    err
+                );
+            }
+        }
+        let mut inputs = Vec::new();
+        for input in logical_plan.inputs() {
+            inputs.push(
+                self.optimize_convertible_subtrees(input, session_state)
+                    .await?,
+            );
+        }
+        Ok(logical_plan.with_new_exprs(logical_plan.expressions(), inputs)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+    use datafusion::logical_expr::{col, lit, JoinType, LogicalPlanBuilder};
+    use datafusion::prelude::SessionContext;
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{create_df_context, OptdDfContext, OptdPlanContext};
+
+    /// An extension node optd_og cannot convert, which returns the rows of its input.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Hash)]
+    struct PassThroughNode {
+        input: LogicalPlan,
+    }
+
+    impl UserDefinedLogicalNodeCore for PassThroughNode {
+        fn name(&self) -> &str {
+            "PassThrough"
+        }
+
+        fn inputs(&self) -> Vec<&LogicalPlan> {
+            vec![&self.input]
+        }
+
+        fn schema(&self) -> &DFSchemaRef {
+            self.input.schema()
+        }
+
+        fn expressions(&self) -> Vec<Expr> {
+            vec![]
+        }
+
+        fn fmt_for_explain(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "PassThrough")
+        }
+
+        fn with_exprs_and_inputs(
+            &self,
+            _exprs: Vec<Expr>,
+            mut inputs: Vec<LogicalPlan>,
+        ) -> datafusion::common::Result<Self> {
+            Ok(Self {
+                input: inputs.swap_remove(0),
+            })
+        }
+    }
+
+    struct PassThroughPlanner;
+
+    #[async_trait]
+    impl ExtensionPlanner for PassThroughPlanner {
+        async fn plan_extension(
+            &self,
+            _planner: &dyn PhysicalPlanner,
+            node: &dyn UserDefinedLogicalNode,
+            _logical_inputs: &[&LogicalPlan],
+            physical_inputs: &[Arc<dyn ExecutionPlan>],
+            _session_state: &SessionState,
+        ) -> datafusion::common::Result<Option<Arc<dyn ExecutionPlan>>> {
+            Ok(node
+                .as_any()
+                .downcast_ref::<PassThroughNode>()
+                .map(|_| physical_inputs[0].clone()))
+        }
+    }
+
+    fn pass_through(input: LogicalPlan) -> LogicalPlan {
+        LogicalPlan::Extension(Extension {
+            node: Arc::new(PassThroughNode { input }),
+        })
+    }
+
+    /// The rows of the plan, which are compared by value, as optd_og does not preserve the
+    /// column names.
+    async fn collect_rows(ctx: &SessionContext, plan: LogicalPlan) -> Vec<Vec<String>> {
+        let batches = ctx
+            .execute_logical_plan(plan)
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let options = FormatOptions::default();
+        let mut rows = Vec::new();
+        for batch in batches {
+            let formatters = batch
+                .columns()
+                .iter()
+                .map(|column| ArrayFormatter::try_new(column.as_ref(), &options).unwrap())
+                .collect_vec();
+            for row_idx in 0..batch.num_rows() {
+                rows.push(
+                    formatters
+                        .iter()
+                        .map(|formatter| formatter.value(row_idx).to_string())
+                        .collect(),
+                );
+            }
+        }
+        rows
+    }
+
+    async fn table_plan(ctx: &SessionContext, table: &str) -> LogicalPlan {
+        ctx.table(table).await.unwrap().into_unoptimized_plan()
+    }
+
+    async fn create_tables(optd_og: &OptdDfContext, datafusion: &SessionContext) {
+        for ctx in [&optd_og.ctx, datafusion] {
+            for sql in [
+                "create table t1(v1 int, v2 int)",
+                "create table t2(v3 int, v4 int)",
+                "create table t3(v5 int, v6 int)",
+                "insert into t1 values (1, 100), (2, 200), (3, 300)",
+                "insert into t2 values (2, 20), (3, 30), (3, 35)",
+                "insert into t3 values (1, 7), (3, 8)",
+            ] {
+                ctx.sql(sql).await.unwrap().collect().await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn optimize_below_extension_node() {
+        let optd_og = create_df_context(None, None, None, false, false, false, None)
+            .await
+            .unwrap();
+        let datafusion = SessionContext::new();
+        create_tables(&optd_og, &datafusion).await;
+        optd_og
+            .optimizer
+            .enable_partial_optimization(vec![Arc::new(PassThroughPlanner)]);
+
+        let sql = "select v1, v4 from t1, t2 where v1 = v3 order by v1, v4";
+        let plan = pass_through(optd_og.ctx.sql(sql).await.unwrap().into_unoptimized_plan());
+
+        // the extension node is left to datafusion, and the query below it to optd_og
+        let partial_plan = optd_og
+            .optimizer
+            .optimize_convertible_subtrees(&plan, &optd_og.ctx.state())
+            .await
+            .unwrap();
+        let LogicalPlan::Extension(extension) = &partial_plan else {
+            panic!(
+                "expected the extension node, got {}",
+                partial_plan.display()
+            );
+        };
+        assert_eq!(extension.node.name(), "PassThrough");
+        let LogicalPlan::Extension(subplan) = extension.node.inputs()[0] else {
+            panic!("expected an optimized subplan below the extension node");
+        };
+        assert_eq!(subplan.node.name(), "OptdSubplan");
+
+        let expected = collect_rows(
+            &datafusion,
+            datafusion.sql(sql).await.unwrap().into_unoptimized_plan(),
+        )
+        .await;
+        assert_eq!(collect_rows(&optd_og.ctx, plan).await, expected);
+    }
+
+    #[tokio::test]
+    async fn plan_unsupported_node_below_join() {
+        let optd_og = create_df_context(None, None, None, false, false, false, None)
+            .await
+            .unwrap();
+        let datafusion = SessionContext::new();
+        create_tables(&optd_og, &datafusion).await;
+        optd_og
+            .optimizer
+            .enable_partial_optimization(vec![Arc::new(PassThroughPlanner)]);
+
+        // the filter fails to convert after its input, whose extension node is planned as part
+        // of the filter instead of on its own
+        let t1 = table_plan(&optd_og.ctx, "t1").await;
+        let t2 = table_plan(&optd_og.ctx, "t2").await;
+        let t3 = table_plan(&optd_og.ctx, "t3").await;
+        let filter = LogicalPlanBuilder::from(t1)
+            .join(
+                pass_through(t2),
+                JoinType::Inner,
+                (vec!["v1"], vec!["v3"]),
+                None,
+            )
+            .unwrap()
+            .filter((col("v1") % lit(2)).eq(lit(1)))
+            .unwrap()
+            .build()
+            .unwrap();
+        let plan = LogicalPlanBuilder::from(t3)
+            .join(filter, JoinType::Inner, (vec!["v5"], vec!["v1"]), None)
+            .unwrap()
+            .sort(vec![
+                col("v5").sort(true, false),
+                col("v4").sort(true, false),
+            ])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let state = optd_og.ctx.state();
+        let mut ctx = OptdPlanContext::new(&state);
+        ctx.enable_black_boxes();
+        ctx.conv_into_optd_og(&plan).unwrap();
+        let black_boxes = ctx.black_boxes.unwrap();
+        assert_eq!(black_boxes.len(), 1);
+        assert!(matches!(black_boxes["black_box#0"], LogicalPlan::Filter(_)));
+
+        let sql =
+            "select * from t3, t1, t2 where v5 = v1 and v1 = v3 and v1 % 2 = 1 order by v5, v4";
+        let expected = collect_rows(
+            &datafusion,
+            datafusion.sql(sql).await.unwrap().into_unoptimized_plan(),
+        )
+        .await;
+        assert_eq!(
+            expected,
+            vec![
+                vec!["3", "8", "3", "300", "3", "30"],
+                vec!["3", "8", "3", "300", "3", "35"],
+            ]
+        );
+        assert_eq!(collect_rows(&optd_og.ctx, plan).await, expected);
+    }
+}
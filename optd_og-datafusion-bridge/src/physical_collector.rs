@@ -17,10 +17,13 @@ use datafusion::physical_plan::{
 use futures_lite::Stream;
 use futures_util::stream::StreamExt;
 use optd_og_core::cascades::GroupId;
+use optd_og_core::nodes::PlanAnnotation;
 use optd_og_datafusion_repr::cost::RuntimeAdaptionStorage;
 
 pub struct CollectorExec {
     group_id: GroupId,
+    /// The number of rows the optimizer estimated the input to produce.
+    estimated_row_cnt: Option<f64>,
     input: Arc<dyn ExecutionPlan>,
     collect_into: RuntimeAdaptionStorage,
 }
@@ -33,18 +36,25 @@ impl std::fmt::Debug for CollectorExec {
 
 impl DisplayAs for CollectorExec {
     fn fmt_as(&self, _t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "CollectorExec group_id={}", self.group_id)
+        write!(f, "CollectorExec group_id={}", self.group_id)?;
+        if let Some(row_cnt) = self.estimated_row_cnt {
+            write!(f, " estimated_rows={}", row_cnt)?;
+        }
+        Ok(())
     }
 }
 
 impl CollectorExec {
+    /// Collects the rows produced by the input, the execution of the node annotated with
+    /// `annotation`.
     pub fn new(
         input: Arc<dyn ExecutionPlan>,
-        group_id: GroupId,
+        annotation: &PlanAnnotation,
         collect_into: RuntimeAdaptionStorage,
     ) -> Self {
         Self {
-            group_id,
+            group_id: annotation.group_id,
+            estimated_row_cnt: annotation.row_cnt,
             input,
             collect_into,
         }
@@ -77,11 +87,12 @@ impl ExecutionPlan for CollectorExec {
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
         assert_eq!(children.len(), 1);
-        Ok(Arc::new(Self::new(
-            children[0].clone(),
-            self.group_id,
-            self.collect_into.clone(),
-        )))
+        Ok(Arc::new(Self {
+            group_id: self.group_id,
+            estimated_row_cnt: self.estimated_row_cnt,
+            input: children[0].clone(),
+            collect_into: self.collect_into.clone(),
+        }))
     }
 
     fn statistics(&self) -> Result<datafusion::physical_plan::Statistics> {
@@ -98,11 +109,17 @@ impl ExecutionPlan for CollectorExec {
             return internal_err!("CollectorExec invalid partition {partition}");
         }
 
+        let misestimate_limit = self
+            .collect_into
+            .lock()
+            .unwrap()
+            .misestimate_limit(self.group_id);
         Ok(Box::pin(CollectorReader {
             input: self.input.execute(partition, context)?,
             group_id: self.group_id,
             collect_into: self.collect_into.clone(),
             row_cnt: 0,
+            misestimate_limit,
             done: false,
         }))
     }
@@ -113,6 +130,8 @@ struct CollectorReader {
     group_id: GroupId,
     done: bool,
     row_cnt: usize,
+    /// The row count beyond which the operator is misestimated, `None` once it is recorded.
+    misestimate_limit: Option<usize>,
     collect_into: RuntimeAdaptionStorage,
 }
 
@@ -132,17 +151,27 @@ impl Stream for CollectorReader {
         match poll {
             Poll::Ready(Some(Ok(batch))) => {
                 self.row_cnt += batch.num_rows();
+                if self
+                    .misestimate_limit
+                    .is_some_and(|limit| self.row_cnt > limit)
+                {
+                    // Record the misestimate right away, the operator may never finish, e.g.,
+                    // below a limit.
+                    self.misestimate_limit = None;
+                    self.collect_into.lock().unwrap().record_row_cnt(
+                        self.group_id,
+                        self.row_cnt,
+                        false,
+                    );
+                }
                 Poll::Ready(Some(Ok(batch)))
             }
             Poll::Ready(None) => {
                 self.done = true;
-                {
-                    let mut guard = self.collect_into.lock().unwrap();
-                    let iter_cnt = guard.iter_cnt;
-                    guard
-                        .history
-                        .insert(self.group_id, (self.row_cnt, iter_cnt));
-                }
+                self.collect_into
+                    .lock()
+                    .unwrap()
+                    .record_row_cnt(self.group_id, self.row_cnt, true);
                 Poll::Ready(None)
             }
             other => other,
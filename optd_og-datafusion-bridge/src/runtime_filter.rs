@@ -0,0 +1,403 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! The runtime filters of the hash joins, see `PhysicalRuntimeFilter`. The build side of a join
+//! is read through a [`RuntimeFilterBuildExec`] collecting the range of its keys, which the
+//! [`RuntimeFilterExec`] on the probe side scan applies once the build side is fully read. The
+//! hash joins read their build side before their probe side, until then the rows pass unfiltered.
+
+use std::cmp::Ordering;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use datafusion::arrow::array::BooleanArray;
+use datafusion::arrow::compute::kernels::cmp::{gt_eq, lt_eq};
+use datafusion::arrow::compute::{and, filter_record_batch};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::Result;
+use datafusion::execution::TaskContext;
+use datafusion::functions_aggregate::min_max::{MaxAccumulator, MinAccumulator};
+use datafusion::logical_expr::Accumulator;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, ExecutionPlanProperties, RecordBatchStream,
+    SendableRecordBatchStream,
+};
+use datafusion::scalar::ScalarValue;
+use futures_lite::Stream;
+use futures_util::stream::StreamExt;
+use itertools::Itertools;
+
+#[derive(Default)]
+struct RuntimeFilterState {
+    /// The partitions of the build side not fully read yet.
+    pending_partitions: usize,
+    /// The smallest and the largest value of each key, null if there are none.
+    ranges: Vec<(ScalarValue, ScalarValue)>,
+    published: bool,
+}
+
+/// The range of the build side keys of a hash join, shared with the filter on its probe side.
+#[derive(Default)]
+pub struct RuntimeFilter {
+    state: Mutex<RuntimeFilterState>,
+}
+
+fn merge_bound(bound: ScalarValue, other: ScalarValue, keep: Ordering) -> ScalarValue {
+    if bound.is_null() || (!other.is_null() && other.partial_cmp(&bound) == Some(keep)) {
+        other
+    } else {
+        bound
+    }
+}
+
+impl RuntimeFilter {
+    fn reset(&self, partitions: usize) {
+        *self.state.lock().unwrap() = RuntimeFilterState {
+            pending_partitions: partitions,
+            ..Default::default()
+        };
+    }
+
+    fn merge_partition(&self, ranges: Vec<(ScalarValue, ScalarValue)>) {
+        let mut state = self.state.lock().unwrap();
+        state.ranges = if state.ranges.is_empty() {
+            ranges
+        } else {
+            std::mem::take(&mut state.ranges)
+                .into_iter()
+                .zip(ranges)
+                .map(|((min, max), (other_min, other_max))| {
+                    (
+                        merge_bound(min, other_min, Ordering::Less),
+                        merge_bound(max, other_max, Ordering::Greater),
+                    )
+                })
+                .collect()
+        };
+        state.pending_partitions = state.pending_partitions.saturating_sub(1);
+        state.published = state.pending_partitions == 0;
+    }
+
+    /// The published ranges of the keys, `None` while the build side is being read.
+    fn ranges(&self) -> Option<Vec<(ScalarValue, ScalarValue)>> {
+        let state = self.state.lock().unwrap();
+        state.published.then(|| state.ranges.clone())
+    }
+}
+
+/// Reads the build side of a hash join, and publishes the range of its keys to the runtime
+/// filter once all its partitions are read.
+pub struct RuntimeFilterBuildExec {
+    input: Arc<dyn ExecutionPlan>,
+    keys: Vec<usize>,
+    filter: Arc<RuntimeFilter>,
+}
+
+impl std::fmt::Debug for RuntimeFilterBuildExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RuntimeFilterBuildExec")
+    }
+}
+
+impl DisplayAs for RuntimeFilterBuildExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "RuntimeFilterBuildExec keys=[{}]",
+            self.keys.iter().join(", ")
+        )
+    }
+}
+
+impl RuntimeFilterBuildExec {
+    pub fn new(
+        input: Arc<dyn ExecutionPlan>,
+        keys: Vec<usize>,
+        filter: Arc<RuntimeFilter>,
+    ) -> Self {
+        filter.reset(input.output_partitioning().partition_count());
+        Self {
+            input,
+            keys,
+            filter,
+        }
+    }
+}
+
+impl ExecutionPlan for RuntimeFilterBuildExec {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn name(&self) -> &str {
+        "RuntimeFilterBuildExec"
+    }
+
+    fn properties(&self) -> &datafusion::physical_plan::PlanProperties {
+        self.input.properties()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        assert_eq!(children.len(), 1);
+        Ok(Arc::new(Self::new(
+            children[0].clone(),
+            self.keys.clone(),
+            self.filter.clone(),
+        )))
+    }
+
+    fn statistics(&self) -> Result<datafusion::physical_plan::Statistics> {
+        self.input.statistics()
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let input_schema = self.input.schema();
+        let accumulators = self
+            .keys
+            .iter()
+            .map(|key| {
+                let data_type = input_schema.field(*key).data_type();
+                Ok((
+                    MinAccumulator::try_new(data_type)?,
+                    MaxAccumulator::try_new(data_type)?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Box::pin(RuntimeFilterBuildReader {
+            input: self.input.execute(partition, context)?,
+            keys: self.keys.clone(),
+            accumulators,
+            filter: self.filter.clone(),
+            done: false,
+        }))
+    }
+}
+
+struct RuntimeFilterBuildReader {
+    input: SendableRecordBatchStream,
+    keys: Vec<usize>,
+    accumulators: Vec<(MinAccumulator, MaxAccumulator)>,
+    filter: Arc<RuntimeFilter>,
+    done: bool,
+}
+
+impl RuntimeFilterBuildReader {
+    fn accumulate(&mut self, batch: &RecordBatch) -> Result<()> {
+        for (key, (min, max)) in self.keys.iter().zip(&mut self.accumulators) {
+            let column = [batch.column(*key).clone()];
+            min.update_batch(&column)?;
+            max.update_batch(&column)?;
+        }
+        Ok(())
+    }
+
+    fn publish(&mut self) -> Result<()> {
+        let ranges = self
+            .accumulators
+            .iter_mut()
+            .map(|(min, max)| Ok((min.evaluate()?, max.evaluate()?)))
+            .collect::<Result<Vec<_>>>()?;
+        self.filter.merge_partition(ranges);
+        Ok(())
+    }
+}
+
+impl Stream for RuntimeFilterBuildReader {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        match self.input.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(batch))) => {
+                Poll::Ready(Some(self.accumulate(&batch).map(|_| batch)))
+            }
+            Poll::Ready(None) => {
+                self.done = true;
+                match self.publish() {
+                    Ok(()) => Poll::Ready(None),
+                    Err(err) => Poll::Ready(Some(Err(err))),
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+impl RecordBatchStream for RuntimeFilterBuildReader {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+}
+
+/// Drops the rows of the probe side scan of a hash join whose keys are out of the range of its
+/// build side keys, once published.
+pub struct RuntimeFilterExec {
+    input: Arc<dyn ExecutionPlan>,
+    keys: Vec<usize>,
+    filter: Arc<RuntimeFilter>,
+}
+
+impl std::fmt::Debug for RuntimeFilterExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RuntimeFilterExec")
+    }
+}
+
+impl DisplayAs for RuntimeFilterExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "RuntimeFilterExec keys=[{}]",
+            self.keys.iter().join(", ")
+        )
+    }
+}
+
+impl RuntimeFilterExec {
+    pub fn new(
+        input: Arc<dyn ExecutionPlan>,
+        keys: Vec<usize>,
+        filter: Arc<RuntimeFilter>,
+    ) -> Self {
+        Self {
+            input,
+            keys,
+            filter,
+        }
+    }
+}
+
+impl ExecutionPlan for RuntimeFilterExec {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn name(&self) -> &str {
+        "RuntimeFilterExec"
+    }
+
+    fn properties(&self) -> &datafusion::physical_plan::PlanProperties {
+        self.input.properties()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        assert_eq!(children.len(), 1);
+        Ok(Arc::new(Self::new(
+            children[0].clone(),
+            self.keys.clone(),
+            self.filter.clone(),
+        )))
+    }
+
+    fn statistics(&self) -> Result<datafusion::physical_plan::Statistics> {
+        Ok(self.input.statistics()?.to_inexact())
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        Ok(Box::pin(RuntimeFilterReader {
+            input: self.input.execute(partition, context)?,
+            keys: self.keys.clone(),
+            filter: self.filter.clone(),
+            ranges: None,
+        }))
+    }
+}
+
+struct RuntimeFilterReader {
+    input: SendableRecordBatchStream,
+    keys: Vec<usize>,
+    filter: Arc<RuntimeFilter>,
+    /// The ranges of the keys, once published.
+    ranges: Option<Vec<(ScalarValue, ScalarValue)>>,
+}
+
+impl RuntimeFilterReader {
+    fn filter_batch(&mut self, batch: RecordBatch) -> Result<RecordBatch> {
+        if self.ranges.is_none() {
+            self.ranges = self.filter.ranges();
+        }
+        let Some(ranges) = &self.ranges else {
+            return Ok(batch);
+        };
+        let mut mask: Option<BooleanArray> = None;
+        for (key, (min, max)) in self.keys.iter().zip(ranges) {
+            let column = batch.column(*key);
+            // the keys of a hash join have the same types, but be safe
+            if &min.data_type() != column.data_type() {
+                continue;
+            }
+            // null bounds, i.e., an empty build side, drop all rows, as do null keys
+            let in_range = and(
+                &gt_eq(column, &min.to_scalar()?)?,
+                &lt_eq(column, &max.to_scalar()?)?,
+            )?;
+            mask = Some(match mask {
+                Some(mask) => and(&mask, &in_range)?,
+                None => in_range,
+            });
+        }
+        match mask {
+            Some(mask) => Ok(filter_record_batch(&batch, &mask)?),
+            None => Ok(batch),
+        }
+    }
+}
+
+impl Stream for RuntimeFilterReader {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        match self.input.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(batch))) => Poll::Ready(Some(self.filter_batch(batch))),
+            other => other,
+        }
+    }
+}
+
+impl RecordBatchStream for RuntimeFilterReader {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+}
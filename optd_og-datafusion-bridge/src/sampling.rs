@@ -0,0 +1,185 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Estimates the selectivity of the filters over the tables without statistics by running them
+//! over a sample of the table at plan time, see `optd.sample_selectivity`.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use datafusion::catalog::TableProvider;
+use datafusion::common::cast::as_int64_array;
+use datafusion::common::DFSchema;
+use datafusion::datasource::provider_as_source;
+use datafusion::execution::context::SessionState;
+use datafusion::functions_aggregate::expr_fn::count;
+use datafusion::logical_expr::{lit, when, Expr, LogicalPlanBuilder};
+use datafusion::physical_plan::collect;
+use datafusion::physical_planner::{DefaultPhysicalPlanner, PhysicalPlanner};
+use itertools::Itertools;
+use optd_og_datafusion_repr::cost::cardinality_hints::predicate_fingerprint;
+use optd_og_datafusion_repr::cost::CardinalityHint;
+use optd_og_datafusion_repr::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, DfNodeType, DfReprPlanNode, LogicalFilter, LogicalScan,
+};
+use optd_og_datafusion_repr::properties::column_ref::ColumnRef;
+use optd_og_datafusion_repr::DatafusionOptimizer;
+
+use crate::{DatafusionCatalog, OptdPlanContext};
+
+/// Estimates the selectivity of the filters directly over the scan of a table by counting the
+/// rows of the first rows of the table, read through the datafusion catalog, that the predicate
+/// keeps. The estimates are stored as cardinality hints of the predicates, so that the cost
+/// models use them like any other hint, and the next queries with the same predicates do not
+/// sample the table again.
+pub(crate) struct SampleSelectivityEstimator<'a> {
+    catalog: &'a DatafusionCatalog,
+    session_state: &'a SessionState,
+    /// The number of rows read from the table for each filter.
+    sample_rows: usize,
+    /// The number of rows left to read for the query.
+    budget: usize,
+}
+
+impl<'a> SampleSelectivityEstimator<'a> {
+    pub fn new(
+        catalog: &'a DatafusionCatalog,
+        session_state: &'a SessionState,
+        sample_rows: usize,
+        budget: usize,
+    ) -> Self {
+        Self {
+            catalog,
+            session_state,
+            sample_rows,
+            budget,
+        }
+    }
+
+    /// Samples the filters of `plan` over the tables the optimizer has no statistics of and whose
+    /// predicates have no cardinality hint yet, until the budget runs out. Returns the number of
+    /// filters sampled.
+    pub async fn estimate(
+        &mut self,
+        ctx: &OptdPlanContext<'_>,
+        optimizer: &DatafusionOptimizer,
+        plan: ArcDfPlanNode,
+    ) -> Result<usize> {
+        let mut filters = Vec::new();
+        collect_filters_over_scans(plan, &mut filters);
+        let mut sampled = 0;
+        for (table, pred) in filters {
+            if self.budget == 0 || self.sample_rows == 0 {
+                break;
+            }
+            if optimizer.stats_freshness().is_analyzed(&table) {
+                continue;
+            }
+            let provider = self.catalog.table(&table).await?;
+            let schema = provider.schema();
+            let column_refs = (0..schema.fields().len())
+                .map(|idx| ColumnRef::base_table_column_ref(table.as_ref(), idx))
+                .collect_vec();
+            let Some((hint_table, fingerprint)) = predicate_fingerprint(&pred, &column_refs) else {
+                continue;
+            };
+            let hinted = optimizer
+                .cardinality_hints
+                .lock()
+                .unwrap()
+                .get(&hint_table, &fingerprint)
+                .is_some();
+            if hinted {
+                continue;
+            }
+            let context = DFSchema::try_from_qualified_schema(table.as_ref(), &schema)?;
+            let cond = match ctx.conv_from_optd_og_logical_expr(pred, &context) {
+                Ok(cond) => cond,
+                Err(err) => {
+                    tracing::debug!("not sampling a filter over {}: {}", table, err);
+                    continue;
+                }
+            };
+            let rows = self.sample_rows.min(self.budget);
+            self.budget -= rows;
+            let (sampled_rows, matched_rows) = self.sample(&table, provider, cond, rows).await?;
+            if sampled_rows == 0 {
+                continue;
+            }
+            let hint = CardinalityHint {
+                selectivity: matched_rows as f64 / sampled_rows as f64,
+                provenance: format!("sample of {} rows", sampled_rows),
+            };
+            tracing::debug!(
+                "sampled the selectivity of {} over {}: {}",
+                fingerprint,
+                table,
+                hint.selectivity
+            );
+            optimizer
+                .cardinality_hints
+                .lock()
+                .unwrap()
+                .upsert(hint_table, fingerprint, hint);
+            sampled += 1;
+        }
+        Ok(sampled)
+    }
+
+    /// Reads at most `rows` rows of `table` with the datafusion planner, and returns the number
+    /// of rows read and the number of them `cond` keeps.
+    async fn sample(
+        &self,
+        table: &str,
+        provider: Arc<dyn TableProvider>,
+        cond: Expr,
+        rows: usize,
+    ) -> Result<(usize, usize)> {
+        let plan = LogicalPlanBuilder::scan_with_filters_fetch(
+            table,
+            provider_as_source(provider),
+            None,
+            vec![],
+            Some(rows),
+        )?
+        .limit(0, Some(rows))?
+        .aggregate(
+            Vec::<Expr>::new(),
+            vec![count(lit(1)), count(when(cond, lit(1)).end()?)],
+        )?
+        .build()?;
+        let plan = self.session_state.optimize(&plan)?;
+        let exec = DefaultPhysicalPlanner::default()
+            .create_physical_plan(&plan, self.session_state)
+            .await?;
+        let batches = collect(exec, self.session_state.task_ctx()).await?;
+        let batch = batches
+            .iter()
+            .find(|batch| batch.num_rows() > 0)
+            .with_context(|| format!("the sample of {} produced no rows", table))?;
+        let sampled_rows = as_int64_array(batch.column(0))?.value(0);
+        let matched_rows = as_int64_array(batch.column(1))?.value(0);
+        Ok((sampled_rows as usize, matched_rows as usize))
+    }
+}
+
+/// Collects the tables and predicates of the filters directly over a full scan of a table. The
+/// scans with partition filters or index lookups do not read the whole table, so the sample of
+/// the table says nothing about the rows they produce.
+fn collect_filters_over_scans(plan: ArcDfPlanNode, filters: &mut Vec<(Arc<str>, ArcDfPredNode)>) {
+    if plan.typ == DfNodeType::Filter {
+        let filter = LogicalFilter::from_plan_node(plan.clone()).unwrap();
+        let child = filter.child().unwrap_plan_node();
+        if child.typ == DfNodeType::Scan {
+            let scan = LogicalScan::from_plan_node(child).unwrap();
+            if scan.partition_filters().is_none() && scan.index_lookup().is_none() {
+                filters.push((scan.table(), filter.cond()));
+            }
+        }
+    }
+    for child in &plan.children {
+        collect_filters_over_scans(child.unwrap_plan_node(), filters);
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! The execution of `PhysicalSingleton`, whose child has to return at most one row, e.g., an
+//! uncorrelated scalar subquery. The joins reading the row collect it in memory, so it is only
+//! computed once.
+
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::TaskContext;
+use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, ExecutionPlanProperties, RecordBatchStream,
+    SendableRecordBatchStream,
+};
+use futures_lite::Stream;
+use futures_util::stream::StreamExt;
+
+/// Returns the rows of its input, failing if there are more than one.
+pub struct SingletonExec {
+    input: Arc<dyn ExecutionPlan>,
+}
+
+impl std::fmt::Debug for SingletonExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SingletonExec")
+    }
+}
+
+impl DisplayAs for SingletonExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SingletonExec")
+    }
+}
+
+impl SingletonExec {
+    /// The rows of the partitions of the input are counted together, so they are merged into
+    /// one.
+    pub fn new(input: Arc<dyn ExecutionPlan>) -> Self {
+        let input = if input.output_partitioning().partition_count() > 1 {
+            Arc::new(CoalescePartitionsExec::new(input)) as Arc<dyn ExecutionPlan>
+        } else {
+            input
+        };
+        Self { input }
+    }
+}
+
+impl ExecutionPlan for SingletonExec {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn name(&self) -> &str {
+        "SingletonExec"
+    }
+
+    fn properties(&self) -> &datafusion::physical_plan::PlanProperties {
+        self.input.properties()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        assert_eq!(children.len(), 1);
+        Ok(Arc::new(Self::new(children[0].clone())))
+    }
+
+    fn statistics(&self) -> Result<datafusion::physical_plan::Statistics> {
+        self.input.statistics()
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        Ok(Box::pin(SingletonReader {
+            input: self.input.execute(partition, context)?,
+            row_cnt: 0,
+        }))
+    }
+}
+
+struct SingletonReader {
+    input: SendableRecordBatchStream,
+    /// The number of rows read so far.
+    row_cnt: usize,
+}
+
+impl SingletonReader {
+    fn check_batch(&mut self, batch: RecordBatch) -> Result<RecordBatch> {
+        self.row_cnt += batch.num_rows();
+        if self.row_cnt > 1 {
+            return Err(DataFusionError::Execution(
+                "more than one row returned by a subquery used as an expression".to_string(),
+            ));
+        }
+        Ok(batch)
+    }
+}
+
+impl Stream for SingletonReader {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        match self.input.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(batch))) => Poll::Ready(Some(self.check_batch(batch))),
+            other => other,
+        }
+    }
+}
+
+impl RecordBatchStream for SingletonReader {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+}
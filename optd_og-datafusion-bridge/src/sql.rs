@@ -0,0 +1,95 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Converts SQL into optd_og logical plans without optimizing or executing them, e.g., to try a
+//! rule on a query:
+//!
+//! ```ignore
+//! let catalog = Arc::new(DatafusionCatalog::new(session_state.catalog_list().clone()));
+//! let mut optimizer = DatafusionOptimizer::new_physical(catalog.clone(), false);
+//! let plan = sql_to_optd_og(&catalog, "SELECT * FROM t1 JOIN t2 ON t1.a = t2.c").await?;
+//! let plan = optimizer.optimize(plan)?;
+//! ```
+//!
+//! The statement is parsed and planned by datafusion against the tables of a catalog, and then
+//! converted as any other datafusion plan.
+
+use anyhow::{Context, Result};
+use datafusion::execution::context::SessionState;
+use datafusion::execution::SessionStateBuilder;
+use datafusion::logical_expr::LogicalPlan;
+use optd_og_datafusion_repr::plan_nodes::ArcDfPlanNode;
+
+use crate::{DatafusionCatalog, OptdPlanContext};
+
+impl OptdPlanContext<'_> {
+    /// Converts a SQL statement into optd_og, resolving the tables it reads in the catalog of the
+    /// session. Also returns the datafusion logical plan it was converted through.
+    pub async fn conv_sql_into_optd_og(
+        &mut self,
+        sql: &str,
+    ) -> Result<(ArcDfPlanNode, LogicalPlan)> {
+        let logical_plan = sql_to_logical_plan(sql, self.session_state).await?;
+        let optd_og_rel = self.conv_into_optd_og(&logical_plan)?;
+        Ok((optd_og_rel, logical_plan))
+    }
+}
+
+/// Converts a SQL statement into optd_og, resolving the tables it reads in `catalog`, so that the
+/// plan can be optimized by an optimizer created with `catalog`. The statement is planned in a
+/// new session state with the default datafusion settings, which executes nothing.
+pub async fn sql_to_optd_og(catalog: &DatafusionCatalog, sql: &str) -> Result<ArcDfPlanNode> {
+    let session_state = SessionStateBuilder::new()
+        .with_catalog_list(catalog.catalog.clone())
+        .with_default_features()
+        .build();
+    let mut ctx = OptdPlanContext::new(&session_state);
+    let (plan, _) = ctx.conv_sql_into_optd_og(sql).await?;
+    catalog
+        .resolve_tables(ctx.tables.keys().map(String::as_str))
+        .await?;
+    Ok(plan)
+}
+
+/// Plans a SQL statement with datafusion and applies the datafusion logical optimizer, which the
+/// plans optd_og receives from datafusion have already gone through.
+async fn sql_to_logical_plan(sql: &str, session_state: &SessionState) -> Result<LogicalPlan> {
+    let logical_plan = session_state
+        .create_logical_plan(sql)
+        .await
+        .with_context(|| format!("failed to plan {}", sql))?;
+    Ok(session_state.optimize(&logical_plan)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::prelude::SessionContext;
+    use optd_og_core::nodes::NodeType;
+    use optd_og_datafusion_repr::DatafusionOptimizer;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn optimize_sql() {
+        let ctx = SessionContext::new();
+        for sql in [
+            "create table t1(v1 int, v2 int)",
+            "create table t2(v3 int, v4 int)",
+        ] {
+            ctx.sql(sql).await.unwrap().collect().await.unwrap();
+        }
+        let catalog = Arc::new(DatafusionCatalog::new(ctx.state().catalog_list().clone()));
+        let mut optimizer = DatafusionOptimizer::new_physical(catalog.clone(), false);
+
+        let plan = sql_to_optd_og(&catalog, "select v1, v4 from t1, t2 where v1 = v3")
+            .await
+            .unwrap();
+        assert!(plan.typ.is_logical());
+        let result = optimizer.optimize(plan).unwrap();
+        assert!(!result.plan.typ.is_logical());
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Substrait ingestion and emission, so that optd_og can optimize the plans of engines other than
+//! datafusion.
+//!
+//! A Substrait plan is converted into a datafusion logical plan first, resolving the tables it
+//! reads in the catalog of the session, and then into optd_og as any other datafusion plan. The
+//! optimized plan goes back the same way, through
+//! [`OptdPlanContext::conv_from_optd_og_logical`].
+
+use anyhow::{Context, Result};
+use datafusion::common::DFSchema;
+use datafusion::execution::context::SessionState;
+use datafusion::logical_expr::LogicalPlan;
+use datafusion_substrait::logical_plan::consumer::from_substrait_plan;
+use datafusion_substrait::logical_plan::producer::to_substrait_plan;
+pub use datafusion_substrait::substrait::proto::Plan;
+use optd_og_datafusion_repr::plan_nodes::ArcDfPlanNode;
+use optd_og_datafusion_repr::OptimizationResult;
+
+use crate::{OptdPlanContext, OptdQueryPlanner};
+
+impl OptdPlanContext<'_> {
+    /// Converts a Substrait plan into optd_og. Also returns the datafusion logical plan it was
+    /// converted through, whose schema the optimized plan is emitted with.
+    pub async fn conv_substrait_into_optd_og(
+        &mut self,
+        plan: &Plan,
+    ) -> Result<(ArcDfPlanNode, LogicalPlan)> {
+        let logical_plan = substrait_to_logical_plan(plan, self.session_state).await?;
+        let optd_og_rel = self.conv_into_optd_og(&logical_plan)?;
+        Ok((optd_og_rel, logical_plan))
+    }
+
+    /// Converts an optimized plan into a Substrait plan producing the columns of `schema`.
+    pub fn conv_from_optd_og_substrait(
+        &self,
+        root_rel: ArcDfPlanNode,
+        schema: &DFSchema,
+    ) -> Result<Box<Plan>> {
+        let logical_plan = self.conv_from_optd_og_logical(root_rel, schema)?;
+        to_substrait_plan(&logical_plan, self.session_state)
+            .context("failed to convert the optimized plan into substrait")
+    }
+}
+
+/// Converts a Substrait plan into a datafusion logical plan and applies the datafusion logical
+/// optimizer, which the plans optd_og receives from datafusion have already gone through.
+async fn substrait_to_logical_plan(
+    plan: &Plan,
+    session_state: &SessionState,
+) -> Result<LogicalPlan> {
+    let logical_plan = from_substrait_plan(session_state, plan)
+        .await
+        .context("failed to convert the substrait plan")?;
+    Ok(session_state.optimize(&logical_plan)?)
+}
+
+impl OptdQueryPlanner {
+    /// Optimizes a Substrait plan with optd_og and emits the chosen plan as Substrait. The tables
+    /// the plan reads must be registered in the session.
+    pub async fn optimize_substrait_plan(
+        &self,
+        plan: &Plan,
+        session_state: &SessionState,
+    ) -> Result<Box<Plan>> {
+        let (plan, _) = self
+            .optimize_substrait_plan_with_result(plan, session_state)
+            .await?;
+        Ok(plan)
+    }
+
+    /// Like [`Self::optimize_substrait_plan`], also returning the result of the optimization
+    /// with the chosen physical plan and its costs.
+    pub async fn optimize_substrait_plan_with_result(
+        &self,
+        plan: &Plan,
+        session_state: &SessionState,
+    ) -> Result<(Box<Plan>, OptimizationResult)> {
+        let logical_plan = substrait_to_logical_plan(plan, session_state).await?;
+        let (optimized_plan, result) = self
+            .optimize_logical_plan_with_result(&logical_plan, session_state)
+            .await?;
+        let plan = to_substrait_plan(&optimized_plan, session_state)
+            .context("failed to convert the optimized plan into substrait")?;
+        Ok((plan, result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::util::pretty::pretty_format_batches;
+
+    use super::*;
+    use crate::create_df_context;
+
+    #[tokio::test]
+    async fn substrait_round_trip() {
+        let optd_og_ctx = create_df_context(None, None, None, false, true, false, None)
+            .await
+            .unwrap();
+        let ctx = &optd_og_ctx.ctx;
+        for sql in [
+            "create table t1(v1 int, v2 int)",
+            "create table t2(v3 int, v4 int)",
+            "insert into t1 values (1, 10), (2, 20), (3, 30)",
+            "insert into t2 values (2, 200), (3, 300), (4, 400)",
+        ] {
+            ctx.sql(sql).await.unwrap().collect().await.unwrap();
+        }
+        let df = ctx
+            .sql("select v1, v4 from t1, t2 where v1 = v3 order by v1")
+            .await
+            .unwrap();
+        let logical_plan = df.clone().into_optimized_plan().unwrap();
+        let expected = pretty_format_batches(&df.collect().await.unwrap())
+            .unwrap()
+            .to_string();
+
+        let state = ctx.state();
+        let plan = to_substrait_plan(&logical_plan, &state).unwrap();
+        let optimized = optd_og_ctx
+            .optimizer
+            .optimize_substrait_plan(&plan, &state)
+            .await
+            .unwrap();
+
+        let optimized_plan = from_substrait_plan(&state, &optimized).await.unwrap();
+        assert_eq!(
+            optimized_plan.schema().field_names(),
+            logical_plan.schema().field_names()
+        );
+        let batches = ctx
+            .execute_logical_plan(optimized_plan)
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            pretty_format_batches(&batches).unwrap().to_string(),
+            expected
+        );
+    }
+}
@@ -7,21 +7,56 @@ mod agg;
 mod filter;
 mod join;
 mod limit;
+mod selectivity_cache;
 pub mod stats;
 
+use std::collections::HashMap;
+
+use optd_og_datafusion_repr::cost::CardinalityHintStorage;
 use optd_og_datafusion_repr::properties::column_ref::{BaseTableColumnRef, ColumnRef};
+use optd_og_datafusion_repr::TableId;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use self::selectivity_cache::SelectivityCache;
+pub use self::selectivity_cache::SelectivityCacheStats;
 use super::adv_stats::stats::{
-    BaseTableStats, ColumnCombValueStats, Distribution, MostCommonValues,
+    BaseTableStats, ColumnCombValueStats, Histogram, MostCommonValues, TableStats,
 };
 
 pub struct AdvStats<
     M: MostCommonValues + Clone + Serialize + DeserializeOwned,
-    D: Distribution + Clone + Serialize + DeserializeOwned,
+    D: Histogram + Clone + Serialize + DeserializeOwned,
 > {
-    pub(crate) per_table_stats_map: BaseTableStats<M, D>,
+    /// The statistics of the tables, keyed by their normalized names.
+    pub(crate) per_table_stats_map: HashMap<TableId, TableStats<M, D>>,
+    selectivity_cache: SelectivityCache,
+    default_sel: DefaultSelectivities,
+    /// The overrides of `default_sel` set in the session, see
+    /// `CardinalityHints::default_selectivities`.
+    cardinality_hints: Option<CardinalityHintStorage>,
+}
+
+/// The selectivities estimated for the predicates without statistics. Workloads with skewed or
+/// correlated data may want more pessimistic estimates than the defaults of Postgres.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DefaultSelectivities {
+    /// Of the equalities, e.g., `a = 1`, and `1 - eq` of the inequalities, e.g., `a != 1`.
+    pub eq: f64,
+    /// Of the range comparisons, e.g., `a < 1`.
+    pub ineq: f64,
+    /// Of the predicates nothing is known about, e.g., a column compared to a function call.
+    pub unk: f64,
+}
+
+impl Default for DefaultSelectivities {
+    fn default() -> Self {
+        Self {
+            eq: DEFAULT_EQ_SEL,
+            ineq: DEFAULT_INEQ_SEL,
+            unk: DEFAULT_UNK_SEL,
+        }
+    }
 }
 
 // Default statistics. All are from selfuncs.h in Postgres unless specified otherwise
@@ -32,7 +67,6 @@ const DEFAULT_INEQ_SEL: f64 = 0.3333333333333333;
 // Default n-distinct estimate for derived columns or columns lacking statistics
 const DEFAULT_NUM_DISTINCT: u64 = 200;
 // Default selectivity if we have no information
-#[allow(dead_code)]
 const DEFAULT_UNK_SEL: f64 = 0.005;
 
 // A placeholder for unimplemented!() for codepaths which are accessed by plannertest
@@ -40,15 +74,54 @@ const UNIMPLEMENTED_SEL: f64 = 0.01;
 
 impl<
         M: MostCommonValues + Clone + Serialize + DeserializeOwned,
-        D: Distribution + Clone + Serialize + DeserializeOwned,
+        D: Histogram + Clone + Serialize + DeserializeOwned,
     > AdvStats<M, D>
 {
     pub fn new(per_table_stats_map: BaseTableStats<M, D>) -> Self {
         Self {
-            per_table_stats_map,
+            per_table_stats_map: per_table_stats_map
+                .into_iter()
+                .map(|(table, stats)| (TableId::from(table), stats))
+                .collect(),
+            selectivity_cache: SelectivityCache::default(),
+            default_sel: DefaultSelectivities::default(),
+            cardinality_hints: None,
+        }
+    }
+
+    pub fn with_default_selectivities(mut self, default_sel: DefaultSelectivities) -> Self {
+        self.default_sel = default_sel;
+        self
+    }
+
+    /// Use the overrides of the default selectivities of `cardinality_hints`.
+    pub fn with_cardinality_hints(mut self, cardinality_hints: CardinalityHintStorage) -> Self {
+        self.cardinality_hints = Some(cardinality_hints);
+        self
+    }
+
+    /// The default selectivities, with the overrides set in the session.
+    pub fn default_selectivities(&self) -> DefaultSelectivities {
+        let Some(cardinality_hints) = &self.cardinality_hints else {
+            return self.default_sel;
+        };
+        let overrides = cardinality_hints.lock().unwrap().default_selectivities();
+        DefaultSelectivities {
+            eq: overrides.eq.unwrap_or(self.default_sel.eq),
+            ineq: overrides.ineq.unwrap_or(self.default_sel.ineq),
+            unk: overrides.unk.unwrap_or(self.default_sel.unk),
         }
     }
 
+    /// Drop the cached filter selectivities, e.g., before optimizing the next query.
+    pub fn clear_selectivity_cache(&self) {
+        self.selectivity_cache.clear();
+    }
+
+    pub fn selectivity_cache_stats(&self) -> SelectivityCacheStats {
+        self.selectivity_cache.stats()
+    }
+
     fn get_single_column_stats_from_col_ref(
         &self,
         col_ref: &ColumnRef,
@@ -62,7 +135,7 @@ impl<
 
     fn get_column_comb_stats(
         &self,
-        table: &str,
+        table: &TableId,
         col_comb: &[usize],
     ) -> Option<&ColumnCombValueStats<M, D>> {
         self.per_table_stats_map
@@ -85,12 +158,13 @@ mod tests {
         InListPred, LikePred, ListPred, LogOpPred, LogOpType, UnOpPred, UnOpType,
     };
     use optd_og_datafusion_repr::Value;
+    use optd_og_gungnir::stats::tdigest::IntoFloat;
     use serde::{Deserialize, Serialize};
 
     use super::stats::*;
     use super::*;
-    pub type TestPerColumnStats = ColumnCombValueStats<TestMostCommonValues, TestDistribution>;
-    pub type TestOptCostModel = AdvStats<TestMostCommonValues, TestDistribution>;
+    pub type TestPerColumnStats = ColumnCombValueStats<TestMostCommonValues, TestHistogram>;
+    pub type TestOptCostModel = AdvStats<TestMostCommonValues, TestHistogram>;
 
     #[derive(Serialize, Deserialize)]
     pub struct TestMostCommonValues {
@@ -98,7 +172,7 @@ mod tests {
     }
 
     #[derive(Serialize, Deserialize)]
-    pub struct TestDistribution {
+    pub struct TestHistogram {
         cdfs: HashMap<Value, f64>,
     }
 
@@ -139,7 +213,7 @@ mod tests {
         }
     }
 
-    impl TestDistribution {
+    impl TestHistogram {
         pub fn new(cdfs_vec: Vec<(Value, f64)>) -> Self {
             Self {
                 cdfs: cdfs_vec.into_iter().collect(),
@@ -147,14 +221,26 @@ mod tests {
         }
 
         pub fn empty() -> Self {
-            TestDistribution::new(vec![])
+            TestHistogram::new(vec![])
         }
     }
 
-    impl Distribution for TestDistribution {
+    impl Histogram for TestHistogram {
         fn cdf(&self, value: &Value) -> f64 {
             *self.cdfs.get(value).unwrap_or(&0.0)
         }
+
+        fn quantile(&self, q: f64) -> f64 {
+            self.cdfs
+                .iter()
+                .filter(|(_, cdf)| **cdf >= q)
+                .map(|(value, _)| value.to_float())
+                .fold(f64::INFINITY, f64::min)
+        }
+
+        fn merge(&mut self, other: &Self) {
+            self.cdfs.extend(other.cdfs.clone());
+        }
     }
 
     pub const TABLE1_NAME: &str = "table1";
@@ -338,14 +424,14 @@ mod tests {
     }
 
     /// The reason this isn't an associated function of PerColumnStats is because that would require
-    ///   adding an empty() function to the trait definitions of MostCommonValues and Distribution,
+    ///   adding an empty() function to the trait definitions of MostCommonValues and Histogram,
     ///   which I wanted to avoid
     pub(crate) fn get_empty_per_col_stats() -> TestPerColumnStats {
         TestPerColumnStats::new(
             TestMostCommonValues::empty(),
             0,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         )
     }
 }
@@ -11,12 +11,12 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use super::AdvStats;
-use crate::adv_stats::stats::{Distribution, MostCommonValues};
+use crate::adv_stats::stats::{Histogram, MostCommonValues};
 use crate::adv_stats::DEFAULT_NUM_DISTINCT;
 
 impl<
         M: MostCommonValues + Clone + Serialize + DeserializeOwned,
-        D: Distribution + Clone + Serialize + DeserializeOwned,
+        D: Histogram + Clone + Serialize + DeserializeOwned,
     > AdvStats<M, D>
 {
     pub(crate) fn get_agg_row_cnt(
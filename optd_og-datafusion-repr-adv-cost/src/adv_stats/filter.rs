@@ -5,29 +5,31 @@
 
 use std::ops::Bound;
 
+use arrow_schema::DataType;
 use optd_og_datafusion_repr::plan_nodes::{
     ArcDfPredNode, BinOpType, CastPred, ColumnRefPred, ConstantPred, ConstantType, DfPredType,
-    DfReprPredNode, InListPred, LikePred, LogOpType, UnOpType,
+    DfReprPredNode, InListPred, LikePred, LogOpPred, LogOpType, UnOpType,
 };
 use optd_og_datafusion_repr::properties::column_ref::{
     BaseTableColumnRef, BaseTableColumnRefs, ColumnRef, GroupColumnRefs,
 };
 use optd_og_datafusion_repr::properties::schema::Schema;
-use optd_og_datafusion_repr::Value;
+use optd_og_datafusion_repr::{TableId, Value};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use super::stats::ColumnCombValue;
-use super::{AdvStats, DEFAULT_EQ_SEL, DEFAULT_INEQ_SEL};
-use crate::adv_stats::stats::{ColumnCombValueStats, Distribution, MostCommonValues};
+use super::AdvStats;
+use crate::adv_stats::stats::{ColumnCombValueStats, Histogram, MostCommonValues};
 use crate::adv_stats::UNIMPLEMENTED_SEL;
 
+mod date_interval;
 mod in_list;
 mod like;
 
 impl<
         M: MostCommonValues + Clone + Serialize + DeserializeOwned,
-        D: Distribution + Clone + Serialize + DeserializeOwned,
+        D: Histogram + Clone + Serialize + DeserializeOwned,
     > AdvStats<M, D>
 {
     pub(crate) fn get_filter_row_cnt(
@@ -36,14 +38,61 @@ impl<
         output_schema: Schema,
         output_column_refs: GroupColumnRefs,
         cond: ArcDfPredNode,
+        mark_selectivity: Option<f64>,
     ) -> f64 {
         let selectivity = {
             let column_refs = output_column_refs.base_table_column_refs();
-            self.get_filter_selectivity(cond, &output_schema, column_refs)
+            match mark_selectivity {
+                Some(mark_selectivity) => self.get_mark_filter_selectivity(
+                    cond,
+                    &output_schema,
+                    column_refs,
+                    mark_selectivity,
+                ),
+                None => self.get_filter_selectivity(cond, &output_schema, column_refs),
+            }
         };
         (child_row_cnt * selectivity).max(1.0)
     }
 
+    /// The selectivity of a filter on the output of a mark join, e.g., a filter on the mark of an
+    /// `IN` subquery. The conjuncts testing the mark, which is the last column, have the fraction
+    /// of the rows of the join that have a match as their selectivity, and the other conjuncts
+    /// are estimated as usual.
+    fn get_mark_filter_selectivity(
+        &self,
+        cond: ArcDfPredNode,
+        schema: &Schema,
+        column_refs: &BaseTableColumnRefs,
+        mark_selectivity: f64,
+    ) -> f64 {
+        let mark = ColumnRefPred::new(schema.len() - 1).into_pred_node();
+        let conjuncts = if cond.typ == DfPredType::LogOp(LogOpType::And) {
+            cond.children.clone()
+        } else {
+            vec![cond]
+        };
+        let mut selectivity = 1.0;
+        let mut rest = vec![];
+        for conjunct in conjuncts {
+            if conjunct == mark {
+                selectivity *= mark_selectivity;
+            } else if conjunct.typ == DfPredType::UnOp(UnOpType::Not) && conjunct.child(0) == mark {
+                selectivity *= 1.0 - mark_selectivity;
+            } else {
+                rest.push(conjunct);
+            }
+        }
+        match rest.len() {
+            0 => selectivity,
+            1 => selectivity * self.get_filter_selectivity(rest.remove(0), schema, column_refs),
+            _ => {
+                let rest = LogOpPred::new(LogOpType::And, rest).into_pred_node();
+                selectivity * self.get_filter_selectivity(rest, schema, column_refs)
+            }
+        }
+    }
+
     /// The expr_tree input must be a "mixed expression tree".
     ///
     /// - An "expression node" refers to a RelNode that returns true for is_expression()
@@ -58,11 +107,46 @@ impl<
     ///
     /// A "filter predicate" operates on one input node, unlike a "join predicate" which operates on
     /// two input nodes. This is why the function only takes in a single schema.
+    ///
+    /// The selectivities of compound predicates are cached, see [`Self::clear_selectivity_cache`].
     pub(super) fn get_filter_selectivity(
         &self,
         expr_tree: ArcDfPredNode,
         schema: &Schema,
         column_refs: &BaseTableColumnRefs,
+    ) -> f64 {
+        match &expr_tree.typ {
+            // Cheaper to compute than to look up.
+            DfPredType::Constant(_) | DfPredType::ColumnRef => {
+                self.compute_filter_selectivity(expr_tree, schema, column_refs)
+            }
+            _ => self
+                .selectivity_cache
+                .get_or_compute(&expr_tree, column_refs, || {
+                    self.compute_filter_selectivity(expr_tree.clone(), schema, column_refs)
+                }),
+        }
+    }
+
+    /// Estimate the selectivities of several predicates applied on the same input, e.g., the
+    /// conjuncts of a filter. The estimates of the subexpressions they share are computed once.
+    pub fn get_filter_selectivities(
+        &self,
+        expr_trees: &[ArcDfPredNode],
+        schema: &Schema,
+        column_refs: &BaseTableColumnRefs,
+    ) -> Vec<f64> {
+        expr_trees
+            .iter()
+            .map(|expr_tree| self.get_filter_selectivity(expr_tree.clone(), schema, column_refs))
+            .collect()
+    }
+
+    fn compute_filter_selectivity(
+        &self,
+        expr_tree: ArcDfPredNode,
+        schema: &Schema,
+        column_refs: &BaseTableColumnRefs,
     ) -> f64 {
         match &expr_tree.typ {
             DfPredType::Constant(_) => Self::get_constant_selectivity(expr_tree),
@@ -167,9 +251,9 @@ impl<
         schema: &Schema,
         column_refs: &BaseTableColumnRefs,
     ) -> f64 {
-        let children_sel = children
-            .iter()
-            .map(|expr| self.get_filter_selectivity(expr.clone(), schema, column_refs));
+        let children_sel = self
+            .get_filter_selectivities(children, schema, column_refs)
+            .into_iter();
 
         match log_op_typ {
             LogOpType::And => children_sel.product(),
@@ -178,6 +262,26 @@ impl<
         }
     }
 
+    /// Remove a cast that is applied to both sides of a comparison. A constant cast to a decimal
+    /// is converted instead, as the statistics of a decimal column can only be compared with
+    /// decimal values, whatever their scale.
+    fn unwrap_common_cast(cast_expr: CastPred) -> ArcDfPredNode {
+        let child = cast_expr.child().into_pred_node();
+        let cast_to = cast_expr.cast_to();
+        if !matches!(child.typ, DfPredType::Constant(_))
+            || !matches!(cast_to, DataType::Decimal128(_, _))
+        {
+            return child;
+        }
+        ConstantPred::new(
+            ConstantPred::from_pred_node(child)
+                .expect("we already checked that the type is Constant")
+                .value()
+                .convert_to_type(cast_to),
+        )
+        .into_pred_node()
+    }
+
     /// Convert the left and right child nodes of some operation to what they semantically are.
     /// This is convenient to avoid repeating the same logic just with "left" and "right" swapped.
     /// The last return value is true when the input node (left) is a ColumnRefPred.
@@ -191,6 +295,10 @@ impl<
         let mut non_col_ref_exprs = vec![];
         let is_left_col_ref;
 
+        // Fold the date arithmetic on constants, so that it is estimated like a constant.
+        let left = date_interval::fold_date_interval(left);
+        let right = date_interval::fold_date_interval(right);
+
         // Recursively unwrap casts as much as we can.
         let mut uncasted_left = left;
         let mut uncasted_right = right;
@@ -205,8 +313,8 @@ impl<
                 let right_cast_expr = CastPred::from_pred_node(uncasted_right)
                     .expect("we already checked that the type is Cast");
                 assert!(left_cast_expr.cast_to() == right_cast_expr.cast_to());
-                uncasted_left = left_cast_expr.child().into_pred_node();
-                uncasted_right = right_cast_expr.child().into_pred_node();
+                uncasted_left = Self::unwrap_common_cast(left_cast_expr);
+                uncasted_right = Self::unwrap_common_cast(right_cast_expr);
             } else if uncasted_left.as_ref().typ == DfPredType::Cast
                 || uncasted_right.as_ref().typ == DfPredType::Cast
             {
@@ -386,23 +494,21 @@ impl<
 
                     match non_col_ref_expr.as_ref().typ {
                         DfPredType::BinOp(_) => {
-                            Self::get_default_comparison_op_selectivity(comp_bin_op_typ)
+                            self.get_default_comparison_op_selectivity(comp_bin_op_typ)
                         }
                         DfPredType::Cast => UNIMPLEMENTED_SEL,
                         DfPredType::Constant(_) => unreachable!(
                             "we should have handled this in the values.len() == 1 branch"
                         ),
-                        _ => unimplemented!(
-                            "unhandled case of comparing a column ref node to {}",
-                            non_col_ref_expr.as_ref().typ
-                        ),
+                        // e.g., a function call
+                        _ => self.default_selectivities().unk,
                     }
                 }
             } else {
-                Self::get_default_comparison_op_selectivity(comp_bin_op_typ)
+                self.get_default_comparison_op_selectivity(comp_bin_op_typ)
             }
         } else if col_ref_exprs.len() == 2 {
-            Self::get_default_comparison_op_selectivity(comp_bin_op_typ)
+            self.get_default_comparison_op_selectivity(comp_bin_op_typ)
         } else {
             unreachable!("we could have at most pushed left and right into col_ref_exprs")
         }
@@ -416,7 +522,7 @@ impl<
     /// is_eq means whether it's == or !=
     fn get_column_equality_selectivity(
         &self,
-        table: &str,
+        table: &TableId,
         col_idx: usize,
         value: &Value,
         is_eq: bool,
@@ -428,7 +534,8 @@ impl<
                 let non_mcv_freq = 1.0 - column_stats.mcvs.total_freq();
                 // always safe because usize is at least as large as i32
                 let ndistinct_as_usize = column_stats.ndistinct as usize;
-                let non_mcv_cnt = ndistinct_as_usize - column_stats.mcvs.cnt();
+                // the ndistinct estimated by a sketch may be less than the number of mcvs
+                let non_mcv_cnt = ndistinct_as_usize.saturating_sub(column_stats.mcvs.cnt());
                 if non_mcv_cnt == 0 {
                     return 0.0;
                 }
@@ -444,9 +551,9 @@ impl<
         } else {
             #[allow(clippy::collapsible_else_if)]
             if is_eq {
-                DEFAULT_EQ_SEL
+                self.default_selectivities().eq
             } else {
-                1.0 - DEFAULT_EQ_SEL
+                1.0 - self.default_selectivities().eq
             }
         };
         assert!(
@@ -482,7 +589,7 @@ impl<
     fn get_column_lt_value_freq(
         &self,
         column_stats: &ColumnCombValueStats<M, D>,
-        table: &str,
+        table: &TableId,
         col_idx: usize,
         value: &Value,
     ) -> f64 {
@@ -502,17 +609,18 @@ impl<
     /// Get the selectivity of an expression of the form "column </<=/>=/> value" (or "value
     /// </<=/>=/> column"). Computes selectivity based off of statistics.
     /// Range predicates are handled entirely differently from equality predicates so this is its
-    /// own function. If it is unable to find the statistics, it returns DEFAULT_INEQ_SEL.
+    /// own function. If it is unable to find the statistics, it returns the default inequality
+    /// selectivity.
     /// The selectivity is computed as quantile of the right bound minus quantile of the left bound.
     fn get_column_range_selectivity(
         &self,
-        table: &str,
+        table: &TableId,
         col_idx: usize,
         start: Bound<&Value>,
         end: Bound<&Value>,
     ) -> f64 {
         if let Some(column_stats) = self.get_column_comb_stats(table, &[col_idx]) {
-            // Left and right quantile contain both Distribution and MCVs.
+            // Left and right quantile contain both Histogram and MCVs.
             let left_quantile = match start {
                 Bound::Unbounded => 0.0,
                 Bound::Included(value) => {
@@ -535,19 +643,20 @@ impl<
             );
             right_quantile - left_quantile
         } else {
-            DEFAULT_INEQ_SEL
+            self.default_selectivities().ineq
         }
     }
 
     /// The default selectivity of a comparison expression
     /// Used when one side of the comparison is a column while the other side is something too
     ///   complex/impossible to evaluate (subquery, UDF, another column, we have no stats, etc.)
-    fn get_default_comparison_op_selectivity(comp_bin_op_typ: BinOpType) -> f64 {
+    fn get_default_comparison_op_selectivity(&self, comp_bin_op_typ: BinOpType) -> f64 {
         assert!(comp_bin_op_typ.is_comparison());
+        let default_sel = self.default_selectivities();
         match comp_bin_op_typ {
-            BinOpType::Eq => DEFAULT_EQ_SEL,
-            BinOpType::Neq => 1.0 - DEFAULT_EQ_SEL,
-            BinOpType::Lt | BinOpType::Leq | BinOpType::Gt | BinOpType::Geq => DEFAULT_INEQ_SEL,
+            BinOpType::Eq => default_sel.eq,
+            BinOpType::Neq => 1.0 - default_sel.eq,
+            BinOpType::Lt | BinOpType::Leq | BinOpType::Gt | BinOpType::Geq => default_sel.ineq,
             _ => unreachable!(
                 "all comparison BinOpTypes were enumerated. this should be unreachable"
             ),
@@ -557,14 +666,15 @@ impl<
 
 #[cfg(test)]
 mod tests {
-    use arrow_schema::DataType;
-    use optd_og_core::nodes::Value;
+    use arrow_schema::{DataType, TimeUnit};
+    use optd_og_core::nodes::{Decimal128, SerializableOrderedF64, Value};
+    use optd_og_datafusion_repr::cost::{CardinalityHintStorage, DefaultSelectivityOverrides};
     use optd_og_datafusion_repr::plan_nodes::{BinOpType, ConstantType, LogOpType, UnOpType};
-    use optd_og_datafusion_repr::properties::column_ref::ColumnRef;
+    use optd_og_datafusion_repr::properties::column_ref::{ColumnRef, GroupColumnRefs};
     use optd_og_datafusion_repr::properties::schema::{Field, Schema};
 
     use crate::adv_stats::tests::*;
-    use crate::adv_stats::DEFAULT_EQ_SEL;
+    use crate::adv_stats::{DefaultSelectivities, SelectivityCacheStats, DEFAULT_EQ_SEL};
 
     #[test]
     fn test_const() {
@@ -593,7 +703,7 @@ mod tests {
             TestMostCommonValues::new(vec![(Value::Int32(1), 0.3)]),
             0,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let expr_tree = bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(1)));
         let expr_tree_rev = bin_op(BinOpType::Eq, cnst(Value::Int32(1)), col_ref(0));
@@ -618,7 +728,7 @@ mod tests {
             TestMostCommonValues::new(vec![(Value::Int32(1), 0.2), (Value::Int32(3), 0.44)]),
             5,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let expr_tree = bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(2)));
         let expr_tree_rev = bin_op(BinOpType::Eq, cnst(Value::Int32(2)), col_ref(0));
@@ -644,7 +754,7 @@ mod tests {
             TestMostCommonValues::new(vec![(Value::Int32(1), 0.3)]),
             0,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let expr_tree = bin_op(BinOpType::Neq, col_ref(0), cnst(Value::Int32(1)));
         let expr_tree_rev = bin_op(BinOpType::Neq, cnst(Value::Int32(1)), col_ref(0));
@@ -669,7 +779,7 @@ mod tests {
             TestMostCommonValues::empty(),
             10,
             0.0,
-            Some(TestDistribution::new(vec![(Value::Int32(15), 0.7)])),
+            Some(TestHistogram::new(vec![(Value::Int32(15), 0.7)])),
         ));
         let expr_tree = bin_op(BinOpType::Leq, col_ref(0), cnst(Value::Int32(15)));
         let expr_tree_rev = bin_op(BinOpType::Gt, cnst(Value::Int32(15)), col_ref(0));
@@ -703,7 +813,7 @@ mod tests {
             },
             10,
             0.0,
-            Some(TestDistribution::new(vec![(Value::Int32(15), 0.7)])),
+            Some(TestHistogram::new(vec![(Value::Int32(15), 0.7)])),
         ));
         let expr_tree = bin_op(BinOpType::Leq, col_ref(0), cnst(Value::Int32(15)));
         let expr_tree_rev = bin_op(BinOpType::Gt, cnst(Value::Int32(15)), col_ref(0));
@@ -733,7 +843,7 @@ mod tests {
             ]),
             10,
             0.0,
-            Some(TestDistribution::new(vec![(Value::Int32(15), 0.7)])),
+            Some(TestHistogram::new(vec![(Value::Int32(15), 0.7)])),
         ));
         let expr_tree = bin_op(BinOpType::Leq, col_ref(0), cnst(Value::Int32(15)));
         let expr_tree_rev = bin_op(BinOpType::Gt, cnst(Value::Int32(15)), col_ref(0));
@@ -758,7 +868,7 @@ mod tests {
             TestMostCommonValues::empty(),
             10,
             0.0,
-            Some(TestDistribution::new(vec![(Value::Int32(15), 0.7)])),
+            Some(TestHistogram::new(vec![(Value::Int32(15), 0.7)])),
         ));
         let expr_tree = bin_op(BinOpType::Lt, col_ref(0), cnst(Value::Int32(15)));
         let expr_tree_rev = bin_op(BinOpType::Geq, cnst(Value::Int32(15)), col_ref(0));
@@ -793,7 +903,7 @@ mod tests {
             11, /* there are 4 MCVs which together add up to 0.3. With 11 total ndistinct, each
                  * remaining value has freq 0.1 */
             0.0,
-            Some(TestDistribution::new(vec![(Value::Int32(15), 0.7)])),
+            Some(TestHistogram::new(vec![(Value::Int32(15), 0.7)])),
         ));
         let expr_tree = bin_op(BinOpType::Lt, col_ref(0), cnst(Value::Int32(15)));
         let expr_tree_rev = bin_op(BinOpType::Geq, cnst(Value::Int32(15)), col_ref(0));
@@ -828,7 +938,7 @@ mod tests {
             11, /* there are 4 MCVs which together add up to 0.3. With 11 total ndistinct, each
                  * remaining value has freq 0.1 */
             0.0,
-            Some(TestDistribution::new(vec![(Value::Int32(15), 0.7)])),
+            Some(TestHistogram::new(vec![(Value::Int32(15), 0.7)])),
         ));
         let expr_tree = bin_op(BinOpType::Lt, col_ref(0), cnst(Value::Int32(15)));
         let expr_tree_rev = bin_op(BinOpType::Geq, cnst(Value::Int32(15)), col_ref(0));
@@ -855,7 +965,7 @@ mod tests {
             TestMostCommonValues::empty(),
             10,
             0.0,
-            Some(TestDistribution::new(vec![(Value::Int32(15), 0.7)])),
+            Some(TestHistogram::new(vec![(Value::Int32(15), 0.7)])),
         ));
         let expr_tree = bin_op(BinOpType::Gt, col_ref(0), cnst(Value::Int32(15)));
         let expr_tree_rev = bin_op(BinOpType::Leq, cnst(Value::Int32(15)), col_ref(0));
@@ -880,7 +990,7 @@ mod tests {
             TestMostCommonValues::empty(),
             10,
             0.0,
-            Some(TestDistribution::new(vec![(Value::Int32(15), 0.7)])),
+            Some(TestHistogram::new(vec![(Value::Int32(15), 0.7)])),
         ));
         let expr_tree = bin_op(BinOpType::Geq, col_ref(0), cnst(Value::Int32(15)));
         let expr_tree_rev = bin_op(BinOpType::Lt, cnst(Value::Int32(15)), col_ref(0));
@@ -913,7 +1023,7 @@ mod tests {
             },
             0,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let eq1 = bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(1)));
         let eq5 = bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(5)));
@@ -954,7 +1064,7 @@ mod tests {
             },
             0,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let eq1 = bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(1)));
         let eq5 = bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(5)));
@@ -981,13 +1091,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_selectivity_cache() {
+        let cost_model = create_one_column_cost_model(TestPerColumnStats::new(
+            TestMostCommonValues {
+                mcvs: vec![
+                    (vec![Some(Value::Int32(1))], 0.3),
+                    (vec![Some(Value::Int32(5))], 0.5),
+                ]
+                .into_iter()
+                .collect(),
+            },
+            0,
+            0.0,
+            Some(TestHistogram::empty()),
+        ));
+        let eq1 = bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(1)));
+        let eq5 = bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(5)));
+        let and = log_op(LogOpType::And, vec![eq1.clone(), eq5.clone()]);
+        let or = log_op(LogOpType::Or, vec![eq5.clone(), eq1.clone()]);
+        let schema = Schema::new(vec![]);
+        let column_refs = vec![ColumnRef::base_table_column_ref(
+            String::from(TABLE1_NAME),
+            0,
+        )];
+
+        let sels = cost_model.get_filter_selectivities(&[and.clone(), or], &schema, &column_refs);
+        assert_approx_eq::assert_approx_eq!(sels[0], 0.15);
+        assert_approx_eq::assert_approx_eq!(sels[1], 0.65);
+        // The disjunction reuses the estimates of both equalities.
+        let stats = cost_model.selectivity_cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 4, 4));
+
+        // Another alternative with the same predicate is not estimated again.
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_selectivity(and.clone(), &schema, &column_refs),
+            0.15
+        );
+        assert_eq!(cost_model.selectivity_cache_stats().hits, 3);
+
+        // The same predicate over other columns is a different estimate.
+        let other_column_refs = vec![ColumnRef::Derived];
+        cost_model.get_filter_selectivity(eq1, &schema, &other_column_refs);
+        assert_eq!(cost_model.selectivity_cache_stats().misses, 5);
+
+        cost_model.clear_selectivity_cache();
+        assert_eq!(
+            cost_model.selectivity_cache_stats(),
+            SelectivityCacheStats::default()
+        );
+    }
+
     #[test]
     fn test_not() {
         let cost_model = create_one_column_cost_model(TestPerColumnStats::new(
             TestMostCommonValues::new(vec![(Value::Int32(1), 0.3)]),
             0,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let expr_tree = un_op(
             UnOpType::Not,
@@ -1013,7 +1174,7 @@ mod tests {
             TestMostCommonValues::new(vec![(Value::Int32(1), 0.3)]),
             0,
             0.1,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let expr_tree = bin_op(
             BinOpType::Eq,
@@ -1046,7 +1207,7 @@ mod tests {
             TestMostCommonValues::new(vec![(Value::Int32(1), 0.3)]),
             0,
             0.1,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let expr_tree = bin_op(
             BinOpType::Eq,
@@ -1077,6 +1238,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cast_colref_eq_cast_decimal() {
+        let cost_model = create_one_column_cost_model(TestPerColumnStats::new(
+            TestMostCommonValues::new(vec![(Value::Decimal128(Decimal128::new(5, 2)), 0.3)]),
+            0,
+            0.1,
+            Some(TestHistogram::empty()),
+        ));
+        let expr_tree = bin_op(
+            BinOpType::Eq,
+            cast(col_ref(0), DataType::Decimal128(30, 15)),
+            cast(
+                cnst(Value::Float(SerializableOrderedF64(0.05.into()))),
+                DataType::Decimal128(30, 15),
+            ),
+        );
+        let schema = Schema::new(vec![]);
+        let column_refs = vec![ColumnRef::base_table_column_ref(
+            String::from(TABLE1_NAME),
+            0,
+        )];
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_selectivity(expr_tree, &schema, &column_refs),
+            0.3
+        );
+    }
+
+    #[test]
+    fn test_colref_eq_cast_timestamp() {
+        let cost_model = create_one_column_cost_model(TestPerColumnStats::new(
+            TestMostCommonValues::new(vec![(Value::Timestamp(86_400_000_000_000), 0.3)]),
+            0,
+            0.1,
+            Some(TestHistogram::empty()),
+        ));
+        let expr_tree = bin_op(
+            BinOpType::Eq,
+            col_ref(0),
+            cast(
+                cnst(Value::String("1970-01-02 00:00:00".into())),
+                DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            ),
+        );
+        let schema = Schema::new(vec![]);
+        let column_refs = vec![ColumnRef::base_table_column_ref(
+            String::from(TABLE1_NAME),
+            0,
+        )];
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_selectivity(expr_tree, &schema, &column_refs),
+            0.3
+        );
+    }
+
     /// In this case, we should leave the Cast as is.
     ///
     /// Note that the test only checks the selectivity and thus doesn't explicitly test that the
@@ -1088,7 +1303,7 @@ mod tests {
             TestMostCommonValues::new(vec![]),
             0,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let expr_tree = bin_op(BinOpType::Eq, cast(col_ref(0), DataType::Int64), col_ref(1));
         let expr_tree_rev = bin_op(BinOpType::Eq, col_ref(1), cast(col_ref(0), DataType::Int64));
@@ -1117,4 +1332,103 @@ mod tests {
             DEFAULT_EQ_SEL
         );
     }
+
+    /// The selectivities of the predicates without statistics are the ones the cost model is
+    /// constructed with, unless they are overridden in the session.
+    #[test]
+    fn test_default_selectivities() {
+        let hints = CardinalityHintStorage::default();
+        let cost_model = create_one_column_cost_model(get_empty_per_col_stats())
+            .with_default_selectivities(DefaultSelectivities {
+                eq: 0.1,
+                ineq: 0.2,
+                unk: 0.3,
+            })
+            .with_cardinality_hints(hints.clone());
+        let eq = bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(1)));
+        let lt = bin_op(BinOpType::Lt, col_ref(0), cnst(Value::Int32(1)));
+        let schema = Schema::new(vec![]);
+        // there are no statistics of table2
+        let column_refs = vec![ColumnRef::base_table_column_ref(
+            String::from(TABLE2_NAME),
+            0,
+        )];
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_selectivity(eq.clone(), &schema, &column_refs),
+            0.1
+        );
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_selectivity(lt.clone(), &schema, &column_refs),
+            0.2
+        );
+
+        hints
+            .lock()
+            .unwrap()
+            .set_default_selectivities(DefaultSelectivityOverrides {
+                eq: Some(0.5),
+                ..Default::default()
+            });
+        cost_model.clear_selectivity_cache();
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_selectivity(eq, &schema, &column_refs),
+            0.5
+        );
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_selectivity(lt, &schema, &column_refs),
+            0.2
+        );
+    }
+
+    /// A filter on the mark of a mark join keeps the rows that have a match.
+    #[test]
+    fn test_mark_filter() {
+        let cost_model = create_one_column_cost_model(TestPerColumnStats::new(
+            TestMostCommonValues::new(vec![(Value::Int32(1), 0.3)]),
+            0,
+            0.0,
+            Some(TestHistogram::empty()),
+        ));
+        let schema = Schema::new(vec![
+            Field {
+                name: String::from(""),
+                typ: ConstantType::Int32,
+                nullable: false,
+            },
+            Field {
+                name: String::from(""),
+                typ: ConstantType::Bool,
+                nullable: false,
+            },
+        ]);
+        let column_refs = GroupColumnRefs::new(
+            vec![
+                ColumnRef::base_table_column_ref(String::from(TABLE1_NAME), 0),
+                ColumnRef::Derived,
+            ],
+            None,
+        );
+        let expr_tree = log_op(
+            LogOpType::And,
+            vec![
+                col_ref(1),
+                bin_op(BinOpType::Eq, col_ref(0), cnst(Value::Int32(1))),
+            ],
+        );
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_row_cnt(
+                100.0,
+                schema.clone(),
+                column_refs.clone(),
+                expr_tree,
+                Some(0.4)
+            ),
+            12.0
+        );
+        let expr_tree = un_op(UnOpType::Not, col_ref(1));
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_row_cnt(100.0, schema, column_refs, expr_tree, Some(0.4)),
+            60.0
+        );
+    }
 }
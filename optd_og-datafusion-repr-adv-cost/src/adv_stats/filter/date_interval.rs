@@ -0,0 +1,134 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use datafusion::arrow::datatypes::{Date32Type, IntervalMonthDayNano};
+use optd_og_datafusion_repr::plan_nodes::{
+    ArcDfPredNode, BinOpType, ConstantPred, ConstantType, DfPredType, DfReprPredNode,
+};
+use optd_og_datafusion_repr::Value;
+
+/// Folds the date arithmetic on constants, e.g., `DATE '1995-01-01' + INTERVAL '3' MONTH`, into
+/// a date constant, so that a column compared with it is estimated with the statistics of the
+/// column like a column compared with any other constant. Any other expression is returned as
+/// is.
+pub(super) fn fold_date_interval(expr: ArcDfPredNode) -> ArcDfPredNode {
+    let DfPredType::BinOp(op @ (BinOpType::Add | BinOpType::Sub)) = expr.typ else {
+        return expr;
+    };
+    let left = fold_date_interval(expr.child(0));
+    let right = fold_date_interval(expr.child(1));
+    let days = match (date(&left), interval(&right), interval(&left), date(&right)) {
+        (Some(date), Some(interval), _, _) if op == BinOpType::Add => {
+            Date32Type::add_month_day_nano(date, interval)
+        }
+        (Some(date), Some(interval), _, _) => Date32Type::subtract_month_day_nano(date, interval),
+        (_, _, Some(interval), Some(date)) if op == BinOpType::Add => {
+            Date32Type::add_month_day_nano(date, interval)
+        }
+        _ => return expr,
+    };
+    ConstantPred::date(days.into()).into_pred_node()
+}
+
+/// The days since the UNIX epoch of a date constant.
+fn date(expr: &ArcDfPredNode) -> Option<i32> {
+    let constant = ConstantPred::from_pred_node(expr.clone())?;
+    match (constant.constant_type(), constant.value()) {
+        (ConstantType::Date, Value::Int64(days)) => days.try_into().ok(),
+        (ConstantType::Date, Value::Date32(days)) => Some(days),
+        _ => None,
+    }
+}
+
+/// The interval of an interval constant, which packs the months, the days and the nanoseconds
+/// into an `i128`, see [`ConstantPred::interval_month_day_nano`].
+fn interval(expr: &ArcDfPredNode) -> Option<IntervalMonthDayNano> {
+    let constant = ConstantPred::from_pred_node(expr.clone())?;
+    if constant.constant_type() != ConstantType::IntervalMonthDateNano {
+        return None;
+    }
+    let value = constant.value().as_i128();
+    let nanoseconds = value as i64;
+    let rest = (value - nanoseconds as i128) >> 64;
+    let days = rest as i32;
+    let months = ((rest - days as i128) >> 32) as i32;
+    Some(IntervalMonthDayNano::new(months, days, nanoseconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use optd_og_datafusion_repr::properties::column_ref::ColumnRef;
+    use optd_og_datafusion_repr::properties::schema::Schema;
+
+    use super::*;
+    use crate::adv_stats::tests::{
+        bin_op, col_ref, create_one_column_cost_model, TestHistogram, TestMostCommonValues,
+        TestPerColumnStats, TABLE1_NAME,
+    };
+
+    fn date(days: i64) -> ArcDfPredNode {
+        ConstantPred::date(days).into_pred_node()
+    }
+
+    fn interval(months: i32, days: i32) -> ArcDfPredNode {
+        ConstantPred::interval_month_day_nano(((months as i128) << 96) + ((days as i128) << 64))
+            .into_pred_node()
+    }
+
+    #[test]
+    fn fold_date_plus_interval() {
+        // 1995-01-01 + 3 months = 1995-04-01
+        assert_eq!(
+            fold_date_interval(bin_op(BinOpType::Add, date(9131), interval(3, 0))),
+            date(9221)
+        );
+        assert_eq!(
+            fold_date_interval(bin_op(BinOpType::Add, interval(3, 0), date(9131))),
+            date(9221)
+        );
+        // 1995-04-01 - 1 month - 1 day = 1995-02-28
+        assert_eq!(
+            fold_date_interval(bin_op(BinOpType::Sub, date(9221), interval(1, 1))),
+            date(9189)
+        );
+        // (1995-01-01 + 1 month) + 2 months = 1995-04-01
+        assert_eq!(
+            fold_date_interval(bin_op(
+                BinOpType::Add,
+                bin_op(BinOpType::Add, date(9131), interval(1, 0)),
+                interval(2, 0)
+            )),
+            date(9221)
+        );
+        let not_folded = bin_op(BinOpType::Sub, interval(3, 0), date(9131));
+        assert_eq!(fold_date_interval(not_folded.clone()), not_folded);
+        let not_folded = bin_op(BinOpType::Add, col_ref(0), interval(3, 0));
+        assert_eq!(fold_date_interval(not_folded.clone()), not_folded);
+    }
+
+    #[test]
+    fn range_selectivity_with_date_interval() {
+        let cost_model = create_one_column_cost_model(TestPerColumnStats::new(
+            TestMostCommonValues::empty(),
+            10,
+            0.0,
+            Some(TestHistogram::new(vec![(Value::Int64(9221), 0.7)])),
+        ));
+        let expr_tree = bin_op(
+            BinOpType::Leq,
+            col_ref(0),
+            bin_op(BinOpType::Add, date(9131), interval(3, 0)),
+        );
+        let schema = Schema::new(vec![]);
+        let column_refs = vec![ColumnRef::base_table_column_ref(
+            String::from(TABLE1_NAME),
+            0,
+        )];
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_filter_selectivity(expr_tree, &schema, &column_refs),
+            0.7
+        );
+    }
+}
@@ -12,12 +12,12 @@ use optd_og_datafusion_repr::properties::column_ref::{
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::adv_stats::stats::{Distribution, MostCommonValues};
+use crate::adv_stats::stats::{Histogram, MostCommonValues};
 use crate::adv_stats::{AdvStats, UNIMPLEMENTED_SEL};
 
 impl<
         M: MostCommonValues + Clone + Serialize + DeserializeOwned,
-        D: Distribution + Clone + Serialize + DeserializeOwned,
+        D: Histogram + Clone + Serialize + DeserializeOwned,
     > AdvStats<M, D>
 {
     /// Only support colA in (val1, val2, val3) where colA is a column ref and
@@ -82,7 +82,7 @@ mod tests {
     use optd_og_datafusion_repr::Value;
 
     use crate::adv_stats::tests::{
-        create_one_column_cost_model, in_list, TestDistribution, TestMostCommonValues,
+        create_one_column_cost_model, in_list, TestHistogram, TestMostCommonValues,
         TestPerColumnStats, TABLE1_NAME,
     };
 
@@ -92,7 +92,7 @@ mod tests {
             TestMostCommonValues::new(vec![(Value::Int32(1), 0.8), (Value::Int32(2), 0.2)]),
             2,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let column_refs = vec![ColumnRef::base_table_column_ref(
             String::from(TABLE1_NAME),
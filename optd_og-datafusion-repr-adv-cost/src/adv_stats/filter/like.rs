@@ -14,7 +14,7 @@ use optd_og_datafusion_repr::properties::column_ref::{
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::adv_stats::stats::{ColumnCombValue, Distribution, MostCommonValues};
+use crate::adv_stats::stats::{ColumnCombValue, Histogram, MostCommonValues};
 use crate::adv_stats::{AdvStats, UNIMPLEMENTED_SEL};
 
 // Used for estimating pattern selectivity character-by-character. These numbers
@@ -27,7 +27,7 @@ const FIXED_CHAR_SEL_FACTOR: f64 = 0.2;
 
 impl<
         M: MostCommonValues + Clone + Serialize + DeserializeOwned,
-        D: Distribution + Clone + Serialize + DeserializeOwned,
+        D: Histogram + Clone + Serialize + DeserializeOwned,
     > AdvStats<M, D>
 {
     /// Compute the selectivity of a (NOT) LIKE expression.
@@ -123,7 +123,7 @@ mod tests {
 
     use crate::adv_stats::filter::like::{FIXED_CHAR_SEL_FACTOR, FULL_WILDCARD_SEL_FACTOR};
     use crate::adv_stats::tests::{
-        create_one_column_cost_model, like, TestDistribution, TestMostCommonValues,
+        create_one_column_cost_model, like, TestHistogram, TestMostCommonValues,
         TestPerColumnStats, TABLE1_NAME,
     };
 
@@ -136,7 +136,7 @@ mod tests {
             ]),
             2,
             0.0,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let column_refs = vec![ColumnRef::base_table_column_ref(
             String::from(TABLE1_NAME),
@@ -163,7 +163,7 @@ mod tests {
             TestMostCommonValues::new(vec![(Value::String("abcd".into()), 0.1)]),
             2,
             null_frac,
-            Some(TestDistribution::empty()),
+            Some(TestHistogram::empty()),
         ));
         let column_refs = vec![ColumnRef::base_table_column_ref(
             String::from(TABLE1_NAME),
@@ -19,12 +19,12 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use super::AdvStats;
-use crate::adv_stats::stats::{Distribution, MostCommonValues};
+use crate::adv_stats::stats::{Histogram, MostCommonValues};
 use crate::adv_stats::DEFAULT_NUM_DISTINCT;
 
 impl<
         M: MostCommonValues + Clone + Serialize + DeserializeOwned,
-        D: Distribution + Clone + Serialize + DeserializeOwned,
+        D: Histogram + Clone + Serialize + DeserializeOwned,
     > AdvStats<M, D>
 {
     #[allow(clippy::too_many_arguments)]
@@ -39,6 +39,9 @@ impl<
         left_column_refs: GroupColumnRefs,
         right_column_refs: GroupColumnRefs,
     ) -> f64 {
+        if join_typ == JoinType::LeftMark {
+            return Self::get_mark_join_row_cnt(left_row_cnt);
+        }
         let selectivity = {
             let input_correlation = self.get_input_correlation(left_column_refs, right_column_refs);
             self.get_join_selectivity_from_expr_tree(
@@ -67,6 +70,9 @@ impl<
         left_column_refs: GroupColumnRefs,
         right_column_refs: GroupColumnRefs,
     ) -> f64 {
+        if join_typ == JoinType::LeftMark {
+            return Self::get_mark_join_row_cnt(left_row_cnt);
+        }
         let selectivity = {
             let schema = output_schema;
             let column_refs = output_column_refs;
@@ -90,6 +96,89 @@ impl<
         (left_row_cnt * right_row_cnt * selectivity).max(1.0)
     }
 
+    /// A mark join outputs every row of the left side once, with whether it has a match. The
+    /// output of a mark join only has the columns of the left side and the mark, so the join
+    /// condition cannot be resolved against the output column refs like for the other joins.
+    fn get_mark_join_row_cnt(left_row_cnt: f64) -> f64 {
+        left_row_cnt.max(1.0)
+    }
+
+    /// The fraction of the rows of the left side of a nested loop mark join that have a match,
+    /// i.e., the selectivity of a filter on the mark column. `input_schema` is the schema of the
+    /// left side followed by the right side, which the join condition refers to.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn get_nlj_mark_selectivity(
+        &self,
+        left_row_cnt: f64,
+        right_row_cnt: f64,
+        input_schema: Schema,
+        join_cond: ArcDfPredNode,
+        left_column_refs: GroupColumnRefs,
+        right_column_refs: GroupColumnRefs,
+    ) -> f64 {
+        let column_refs = Self::concat_input_column_refs(&left_column_refs, &right_column_refs);
+        let input_correlation = self.get_input_correlation(left_column_refs, right_column_refs);
+        let selectivity = self.get_join_selectivity_from_expr_tree(
+            JoinType::Inner,
+            join_cond,
+            &input_schema,
+            &column_refs,
+            input_correlation,
+            left_row_cnt,
+            right_row_cnt,
+        );
+        Self::get_mark_selectivity(selectivity, right_row_cnt)
+    }
+
+    /// The fraction of the rows of the left side of a hash mark join that have a match, see
+    /// [`Self::get_nlj_mark_selectivity`].
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn get_hash_join_mark_selectivity(
+        &self,
+        left_row_cnt: f64,
+        right_row_cnt: f64,
+        left_keys: ListPred,
+        right_keys: ListPred,
+        input_schema: Schema,
+        left_column_refs: GroupColumnRefs,
+        right_column_refs: GroupColumnRefs,
+    ) -> f64 {
+        let column_refs = Self::concat_input_column_refs(&left_column_refs, &right_column_refs);
+        let left_col_cnt = left_column_refs.base_table_column_refs().len();
+        let input_correlation = self.get_input_correlation(left_column_refs, right_column_refs);
+        let selectivity = self.get_join_selectivity_from_keys(
+            JoinType::Inner,
+            left_keys,
+            right_keys,
+            &input_schema,
+            &column_refs,
+            input_correlation,
+            left_row_cnt,
+            right_row_cnt,
+            left_col_cnt,
+        );
+        Self::get_mark_selectivity(selectivity, right_row_cnt)
+    }
+
+    /// A row of the left side has a match unless none of the rows of the right side matches it,
+    /// where each of them matches it with the probability of the selectivity of the inner join.
+    fn get_mark_selectivity(join_selectivity: f64, right_row_cnt: f64) -> f64 {
+        let no_match = (1.0 - join_selectivity.clamp(0.0, 1.0)).powf(right_row_cnt.max(0.0));
+        1.0 - no_match
+    }
+
+    fn concat_input_column_refs(
+        left_column_refs: &GroupColumnRefs,
+        right_column_refs: &GroupColumnRefs,
+    ) -> BaseTableColumnRefs {
+        left_column_refs
+            .base_table_column_refs()
+            .iter()
+            .chain(right_column_refs.base_table_column_refs().iter())
+            .cloned()
+            .collect()
+    }
+
     fn get_input_correlation(
         &self,
         left_prop: GroupColumnRefs,
@@ -191,8 +280,6 @@ impl<
             JoinType::Inner => inner_join_selectivity,
             JoinType::LeftOuter => f64::max(inner_join_selectivity, 1.0 / right_row_cnt),
             JoinType::RightOuter => f64::max(inner_join_selectivity, 1.0 / left_row_cnt),
-            // TODO: Does this make sense?
-            JoinType::LeftMark => f64::max(inner_join_selectivity, 1.0 / right_row_cnt),
             _ => unimplemented!("join_typ={} is not implemented", join_typ),
         }
     }
@@ -504,12 +591,15 @@ mod tests {
     use std::collections::HashSet;
 
     use optd_og_core::nodes::Value;
-    use optd_og_datafusion_repr::plan_nodes::{ArcDfPredNode, BinOpType, JoinType, LogOpType};
+    use optd_og_datafusion_repr::plan_nodes::{
+        ArcDfPredNode, BinOpType, JoinType, ListPred, LogOpType,
+    };
     use optd_og_datafusion_repr::properties::column_ref::{
         BaseTableColumnRef, BaseTableColumnRefs, ColumnRef, EqBaseTableColumnSets, EqPredicate,
-        SemanticCorrelation,
+        GroupColumnRefs, SemanticCorrelation,
     };
     use optd_og_datafusion_repr::properties::schema::Schema;
+    use optd_og_datafusion_repr::TableId;
 
     use crate::adv_stats::tests::*;
     use crate::adv_stats::DEFAULT_EQ_SEL;
@@ -525,8 +615,10 @@ mod tests {
         column_refs: &BaseTableColumnRefs,
         input_correlation: Option<SemanticCorrelation>,
     ) -> f64 {
-        let table1_row_cnt = cost_model.per_table_stats_map[TABLE1_NAME].row_cnt as f64;
-        let table2_row_cnt = cost_model.per_table_stats_map[TABLE2_NAME].row_cnt as f64;
+        let table1_row_cnt =
+            cost_model.per_table_stats_map[&TableId::new(TABLE1_NAME)].row_cnt as f64;
+        let table2_row_cnt =
+            cost_model.per_table_stats_map[&TableId::new(TABLE2_NAME)].row_cnt as f64;
         if !reverse_tables {
             cost_model.get_join_selectivity_from_expr_tree(
                 join_typ,
@@ -586,13 +678,13 @@ mod tests {
                 TestMostCommonValues::empty(),
                 5,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
         );
         let expr_tree = bin_op(BinOpType::Eq, col_ref(0), col_ref(1));
@@ -635,13 +727,13 @@ mod tests {
                 TestMostCommonValues::empty(),
                 5,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
         );
         let eq0and1 = bin_op(BinOpType::Eq, col_ref(0), col_ref(1));
@@ -686,13 +778,13 @@ mod tests {
                 TestMostCommonValues::empty(),
                 5,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
         );
         let eq0and1 = bin_op(BinOpType::Eq, col_ref(0), col_ref(1));
@@ -737,13 +829,13 @@ mod tests {
                 TestMostCommonValues::empty(),
                 5,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
         );
         let neq12 = bin_op(BinOpType::Neq, col_ref(0), cnst(Value::Int32(12)));
@@ -788,13 +880,13 @@ mod tests {
                 TestMostCommonValues::empty(),
                 5,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
         );
         let expr_tree = bin_op(BinOpType::Eq, col_ref(0), col_ref(0));
@@ -940,13 +1032,13 @@ mod tests {
                 TestMostCommonValues::empty(),
                 5,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             5,
             4,
@@ -1008,13 +1100,13 @@ mod tests {
                 TestMostCommonValues::empty(),
                 5,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             10,
             8,
@@ -1077,13 +1169,13 @@ mod tests {
                 TestMostCommonValues::empty(),
                 10,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 2,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             20,
             4,
@@ -1146,13 +1238,13 @@ mod tests {
                 TestMostCommonValues::empty(),
                 50,
                 0.0,
-                Some(TestDistribution::new(vec![(Value::Int32(128), 0.4)])),
+                Some(TestHistogram::new(vec![(Value::Int32(128), 0.4)])),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             50,
             4,
@@ -1209,6 +1301,136 @@ mod tests {
         );
     }
 
+    /// A mark join keeps every row of the left table, whatever the selectivity of its condition.
+    #[test]
+    fn test_left_mark_row_cnt() {
+        let cost_model = create_two_table_cost_model_custom_row_cnts(
+            TestPerColumnStats::new(
+                TestMostCommonValues::empty(),
+                5,
+                0.0,
+                Some(TestHistogram::empty()),
+            ),
+            TestPerColumnStats::new(
+                TestMostCommonValues::empty(),
+                4,
+                0.0,
+                Some(TestHistogram::empty()),
+            ),
+            5,
+            4,
+        );
+        let left_column_refs = GroupColumnRefs::new(
+            vec![ColumnRef::base_table_column_ref(
+                String::from(TABLE1_NAME),
+                0,
+            )],
+            None,
+        );
+        let right_column_refs = GroupColumnRefs::new(
+            vec![ColumnRef::base_table_column_ref(
+                String::from(TABLE2_NAME),
+                0,
+            )],
+            None,
+        );
+        // The output has the left column and the mark.
+        let output_column_refs = GroupColumnRefs::new(
+            vec![
+                ColumnRef::base_table_column_ref(String::from(TABLE1_NAME), 0),
+                ColumnRef::Derived,
+            ],
+            None,
+        );
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_nlj_row_cnt(
+                JoinType::LeftMark,
+                5.0,
+                4.0,
+                Schema::new(vec![]),
+                output_column_refs.clone(),
+                bin_op(BinOpType::Eq, col_ref(0), col_ref(1)),
+                left_column_refs.clone(),
+                right_column_refs.clone(),
+            ),
+            5.0
+        );
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_hash_join_row_cnt(
+                JoinType::LeftMark,
+                5.0,
+                4.0,
+                ListPred::new(vec![col_ref(0)]),
+                ListPred::new(vec![col_ref(0)]),
+                Schema::new(vec![]),
+                output_column_refs,
+                left_column_refs,
+                right_column_refs,
+            ),
+            5.0
+        );
+    }
+
+    /// A row of the left table has a match unless none of the rows of the right table matches it.
+    #[test]
+    fn test_left_mark_selectivity() {
+        let cost_model = create_two_table_cost_model_custom_row_cnts(
+            TestPerColumnStats::new(
+                TestMostCommonValues::empty(),
+                5,
+                0.0,
+                Some(TestHistogram::empty()),
+            ),
+            TestPerColumnStats::new(
+                TestMostCommonValues::empty(),
+                4,
+                0.0,
+                Some(TestHistogram::empty()),
+            ),
+            5,
+            4,
+        );
+        let left_column_refs = GroupColumnRefs::new(
+            vec![ColumnRef::base_table_column_ref(
+                String::from(TABLE1_NAME),
+                0,
+            )],
+            None,
+        );
+        let right_column_refs = GroupColumnRefs::new(
+            vec![ColumnRef::base_table_column_ref(
+                String::from(TABLE2_NAME),
+                0,
+            )],
+            None,
+        );
+        // Each row of the right table matches with the probability 1/5.
+        let expected = 1.0 - 0.8_f64.powi(4);
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_nlj_mark_selectivity(
+                5.0,
+                4.0,
+                Schema::new(vec![]),
+                bin_op(BinOpType::Eq, col_ref(0), col_ref(1)),
+                left_column_refs.clone(),
+                right_column_refs.clone(),
+            ),
+            expected
+        );
+        assert_approx_eq::assert_approx_eq!(
+            cost_model.get_hash_join_mark_selectivity(
+                5.0,
+                4.0,
+                ListPred::new(vec![col_ref(0)]),
+                ListPred::new(vec![col_ref(0)]),
+                Schema::new(vec![]),
+                left_column_refs,
+                right_column_refs,
+            ),
+            expected
+        );
+    }
+
     /// Test all possible permutations of three-table joins.
     /// A three-table join consists of at least two joins. `join1_on_cond` is the condition of the
     /// first   join. There can only be one condition because only two tables are involved at
@@ -1235,32 +1457,32 @@ mod tests {
                 TestMostCommonValues::empty(),
                 2,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 3,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
         );
         let col_base_refs = vec![
             BaseTableColumnRef {
-                table: String::from(TABLE1_NAME),
+                table: TABLE1_NAME.into(),
                 col_idx: 0,
             },
             BaseTableColumnRef {
-                table: String::from(TABLE2_NAME),
+                table: TABLE2_NAME.into(),
                 col_idx: 0,
             },
             BaseTableColumnRef {
-                table: String::from(TABLE3_NAME),
+                table: TABLE3_NAME.into(),
                 col_idx: 0,
             },
         ];
@@ -1346,42 +1568,42 @@ mod tests {
                 TestMostCommonValues::empty(),
                 2,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 3,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 4,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
             TestPerColumnStats::new(
                 TestMostCommonValues::empty(),
                 5,
                 0.0,
-                Some(TestDistribution::empty()),
+                Some(TestHistogram::empty()),
             ),
         );
         let col_base_refs = vec![
             BaseTableColumnRef {
-                table: String::from(TABLE1_NAME),
+                table: TABLE1_NAME.into(),
                 col_idx: 0,
             },
             BaseTableColumnRef {
-                table: String::from(TABLE2_NAME),
+                table: TABLE2_NAME.into(),
                 col_idx: 0,
             },
             BaseTableColumnRef {
-                table: String::from(TABLE3_NAME),
+                table: TABLE3_NAME.into(),
                 col_idx: 0,
             },
             BaseTableColumnRef {
-                table: String::from(TABLE4_NAME),
+                table: TABLE4_NAME.into(),
                 col_idx: 0,
             },
         ];
@@ -0,0 +1,66 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use optd_og_datafusion_repr::plan_nodes::ArcDfPredNode;
+use optd_og_datafusion_repr::properties::column_ref::BaseTableColumnRefs;
+
+/// The memo table interns the predicates, so the alternative expressions of a group (and the
+/// groups sharing a conjunct) estimate the same predicate over the same columns again and again.
+/// The cache remembers these estimates until the optimizer moves on to the next query.
+///
+/// The key does not contain the schema: it is only used to find the type of a column, which
+/// is the same for the same base table columns.
+#[derive(Default)]
+pub(crate) struct SelectivityCache {
+    entries: Mutex<HashMap<(ArcDfPredNode, BaseTableColumnRefs), f64>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SelectivityCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub entries: usize,
+}
+
+impl SelectivityCache {
+    /// Returns the cached selectivity, or computes and caches it. The lock is not held while
+    /// computing, since the estimation of a predicate recurses into its children.
+    pub(crate) fn get_or_compute(
+        &self,
+        expr_tree: &ArcDfPredNode,
+        column_refs: &BaseTableColumnRefs,
+        compute: impl FnOnce() -> f64,
+    ) -> f64 {
+        let key = (expr_tree.clone(), column_refs.clone());
+        if let Some(selectivity) = self.entries.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return *selectivity;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let selectivity = compute();
+        self.entries.lock().unwrap().insert(key, selectivity);
+        selectivity
+    }
+
+    pub(crate) fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> SelectivityCacheStats {
+        SelectivityCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
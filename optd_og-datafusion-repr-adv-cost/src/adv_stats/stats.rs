@@ -6,15 +6,18 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use arrow_schema::{DataType, Schema, SchemaRef};
+use arrow_schema::{DataType, Schema, SchemaRef, TimeUnit};
 use datafusion::arrow::array::{
-    Array, BooleanArray, Date32Array, Float32Array, Float64Array, Int16Array, Int32Array,
-    Int8Array, RecordBatch, StringArray, UInt16Array, UInt32Array, UInt8Array,
+    Array, BooleanArray, Date32Array, Decimal128Array, Float32Array, Float64Array, Int16Array,
+    Int32Array, Int8Array, RecordBatch, StringArray, Time64NanosecondArray,
+    TimestampNanosecondArray, UInt16Array, UInt32Array, UInt8Array,
 };
+use datafusion::arrow::compute::cast;
 use datafusion::parquet::arrow::arrow_reader::ParquetRecordBatchReader;
 use itertools::Itertools;
-use optd_og_core::nodes::{SerializableOrderedF64, Value};
+use optd_og_core::nodes::{Decimal128, SerializableOrderedF64, Value};
 use optd_og_gungnir::stats::counter::Counter;
+use optd_og_gungnir::stats::equi_depth::{self, EquiDepthHistogram};
 use optd_og_gungnir::stats::hyperloglog::{self, HyperLogLog};
 use optd_og_gungnir::stats::misragries::{self, MisraGries};
 use optd_og_gungnir::stats::tdigest::{self, TDigest};
@@ -26,26 +29,35 @@ use serde::{Deserialize, Serialize};
 // The "standard" concrete types that optd_og currently uses.
 // All of optd_og (except unit tests) must use the same types.
 pub type DataFusionMostCommonValues = Counter<Vec<Option<Value>>>;
-pub type DataFusionDistribution = TDigest<Value>;
 
-pub type DataFusionBaseTableStats =
-    BaseTableStats<DataFusionMostCommonValues, DataFusionDistribution>;
-pub type DataFusionPerTableStats = TableStats<DataFusionMostCommonValues, DataFusionDistribution>;
-
-/// A more general interface meant to perform the task of a histogram.
-//.
-/// This more general interface is still compatible with histograms but allows
-/// more powerful statistics like TDigest.
-/// Ideally, MostCommonValues would have trait bounds for Serialize and Deserialize. However, I have
-/// not figured
-//    out how to both have Deserialize as a trait bound and utilize the Deserialize macro, because
-// the Deserialize    trait involves lifetimes.
-pub trait Distribution: 'static + Send + Sync {
-    // Give the probability of a random value sampled from the distribution being <= `value`
+pub type DataFusionBaseTableStats = BaseTableStats<DataFusionMostCommonValues, DataFusionHistogram>;
+pub type DataFusionPerTableStats = TableStats<DataFusionMostCommonValues, DataFusionHistogram>;
+
+/// The distribution of the values of a column, to estimate the selectivity of range predicates.
+/// It is implemented by the histograms as well as by more powerful statistics like TDigest.
+/// The values of the most common values are not in the histogram, and the frequencies are
+/// relative to all the rows of the table.
+/// Ideally, Histogram would have trait bounds for Serialize and Deserialize, see
+/// [`MostCommonValues`].
+pub trait Histogram: 'static + Send + Sync {
+    /// The probability of a random value of the column being in the histogram and <= `value`.
     fn cdf(&self, value: &Value) -> f64;
+
+    /// The value (as a float) that a fraction `q` of the values of the histogram are less than or
+    /// equal to.
+    fn quantile(&self, q: f64) -> f64;
+
+    /// The probability of a random value of the column being in the histogram and in
+    /// `(low, high]`.
+    fn range_freq(&self, low: &Value, high: &Value) -> f64 {
+        (self.cdf(high) - self.cdf(low)).max(0.0)
+    }
+
+    /// Merges the histogram of the same column over another partition of the table.
+    fn merge(&mut self, other: &Self);
 }
 
-impl Distribution for TDigest<Value> {
+impl Histogram for TDigest<Value> {
     fn cdf(&self, value: &Value) -> f64 {
         let nb_rows = self.norm_weight;
         if nb_rows == 0 {
@@ -54,6 +66,129 @@ impl Distribution for TDigest<Value> {
             self.centroids.len() as f64 * self.cdf(value) / nb_rows as f64
         }
     }
+
+    fn quantile(&self, q: f64) -> f64 {
+        self.quantile(q)
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.merge(other);
+        self.norm_weight += other.norm_weight;
+    }
+}
+
+impl Histogram for EquiDepthHistogram<Value> {
+    fn cdf(&self, value: &Value) -> f64 {
+        let nb_rows = self.norm_weight;
+        if nb_rows == 0 {
+            self.cdf(value)
+        } else {
+            self.total_weight() * self.cdf(value) / nb_rows as f64
+        }
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        self.quantile(q)
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.merge(other);
+        self.norm_weight += other.norm_weight;
+    }
+}
+
+/// The kinds of histograms the statistics of a table can be collected with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HistogramType {
+    #[default]
+    TDigest,
+    EquiDepth,
+}
+
+impl HistogramType {
+    pub fn new_histogram(&self) -> DataFusionHistogram {
+        match self {
+            Self::TDigest => {
+                DataFusionHistogram::TDigest(TDigest::new(tdigest::DEFAULT_COMPRESSION))
+            }
+            Self::EquiDepth => DataFusionHistogram::EquiDepth(EquiDepthHistogram::new(
+                equi_depth::DEFAULT_NUM_BUCKETS,
+            )),
+        }
+    }
+}
+
+/// The histogram of the statistics of a column, of the kind chosen when collecting them. The
+/// statistics saved before the equi-depth histograms existed are read as TDigests.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum DataFusionHistogram {
+    TDigest(TDigest<Value>),
+    EquiDepth(EquiDepthHistogram<Value>),
+}
+
+impl DataFusionHistogram {
+    pub fn histogram_type(&self) -> HistogramType {
+        match self {
+            Self::TDigest(_) => HistogramType::TDigest,
+            Self::EquiDepth(_) => HistogramType::EquiDepth,
+        }
+    }
+
+    /// Ingests the values of `row_cnt` rows which are not in the most common values.
+    fn merge_values(&mut self, values: &[Value], row_cnt: usize) {
+        match self {
+            Self::TDigest(tdigest) => {
+                tdigest.norm_weight += row_cnt;
+                tdigest.merge_values(values);
+            }
+            Self::EquiDepth(histogram) => {
+                histogram.norm_weight += row_cnt;
+                histogram.merge_values(values);
+            }
+        }
+    }
+}
+
+impl Histogram for DataFusionHistogram {
+    fn cdf(&self, value: &Value) -> f64 {
+        match self {
+            Self::TDigest(tdigest) => Histogram::cdf(tdigest, value),
+            Self::EquiDepth(histogram) => Histogram::cdf(histogram, value),
+        }
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        match self {
+            Self::TDigest(tdigest) => Histogram::quantile(tdigest, q),
+            Self::EquiDepth(histogram) => Histogram::quantile(histogram, q),
+        }
+    }
+
+    /// Panics if the histograms are of different kinds, i.e., the partitions were analyzed
+    /// with different [`HistogramType`]s.
+    fn merge(&mut self, other: &Self) {
+        match (self, other) {
+            (Self::TDigest(tdigest), Self::TDigest(other)) => Histogram::merge(tdigest, other),
+            (Self::EquiDepth(histogram), Self::EquiDepth(other)) => {
+                Histogram::merge(histogram, other)
+            }
+            (histogram, other) => panic!(
+                "cannot merge a {:?} histogram with a {:?} histogram",
+                histogram.histogram_type(),
+                other.histogram_type()
+            ),
+        }
+    }
+}
+
+/// How the statistics of a table are collected.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StatsOptions {
+    /// Keep the sketches needed to merge the statistics with the ones of the other partitions of
+    /// the table, see [`TableStats::partition_from_record_batches`].
+    pub keep_sketches: bool,
+    pub histogram_type: HistogramType,
 }
 
 // Some values in a column combination can be null.
@@ -106,20 +241,67 @@ enum StatType {
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct ColumnCombValueStats<M: MostCommonValues, D: Distribution> {
+pub struct ColumnCombValueStats<M: MostCommonValues, D: Histogram> {
     pub mcvs: M,          // Does NOT contain full nulls.
     pub distr: Option<D>, // Does NOT contain mcvs; optional.
     pub ndistinct: u64,   // Does NOT contain full nulls.
     pub null_frac: f64,   // % of full nulls.
+    /// The sketch `ndistinct` is estimated from, only kept by the stats of a partition of a
+    /// table so that they can be merged with the other partitions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hll: Option<HyperLogLog<ColumnCombValue>>,
 }
 
-impl<M: MostCommonValues, D: Distribution> ColumnCombValueStats<M, D> {
+impl<M: MostCommonValues, D: Histogram> ColumnCombValueStats<M, D> {
     pub fn new(mcvs: M, ndistinct: u64, null_frac: f64, distr: Option<D>) -> Self {
         Self {
             mcvs,
             ndistinct,
             null_frac,
             distr,
+            hll: None,
+        }
+    }
+
+    /// Sets `ndistinct` to the estimate of a sketch over `row_cnt` rows. The estimate is only
+    /// approximate, so it is bounded by what is known exactly: there are at least as many
+    /// distinct values as most common values, and at most as many as non-null rows.
+    pub fn set_approx_ndistinct(&mut self, estimate: u64, row_cnt: usize) {
+        let non_null_cnt = (row_cnt as f64 * (1.0 - self.null_frac)).round() as u64;
+        self.ndistinct = estimate.min(non_null_cnt).max(self.mcvs.cnt() as u64);
+    }
+}
+
+impl ColumnCombValueStats<Counter<ColumnCombValue>, DataFusionHistogram> {
+    /// Merges the stats of the same columns over another partition of the table, where
+    /// `row_cnt` and `other_row_cnt` are the row counts of the two partitions. Without the sketches
+    /// of both partitions, the distinct values of the partitions are assumed to be disjoint,
+    /// e.g., because the table is partitioned on the columns.
+    pub fn merge(&mut self, other: &Self, row_cnt: usize, other_row_cnt: usize) {
+        self.mcvs.merge_weighted(&other.mcvs, 1.0);
+        match (&mut self.distr, &other.distr) {
+            (Some(distr), Some(other_distr)) => distr.merge(other_distr),
+            (None, Some(other_distr)) => self.distr = Some(other_distr.clone()),
+            _ => {}
+        }
+        let total_row_cnt = row_cnt + other_row_cnt;
+        let null_cnt = self.null_frac * row_cnt as f64 + other.null_frac * other_row_cnt as f64;
+        self.null_frac = if total_row_cnt == 0 {
+            0.0
+        } else {
+            null_cnt / total_row_cnt as f64
+        };
+        match (&mut self.hll, &other.hll) {
+            (Some(hll), Some(other_hll)) => {
+                hll.merge(other_hll);
+                let estimate = hll.n_distinct();
+                self.set_approx_ndistinct(estimate, total_row_cnt);
+            }
+            _ => {
+                self.hll = None;
+                let non_null_cnt = (total_row_cnt as f64 - null_cnt).round() as u64;
+                self.ndistinct = (self.ndistinct + other.ndistinct).min(non_null_cnt);
+            }
         }
     }
 }
@@ -128,7 +310,7 @@ impl<M: MostCommonValues, D: Distribution> ColumnCombValueStats<M, D> {
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct TableStats<
     M: MostCommonValues + Clone + Serialize + DeserializeOwned,
-    D: Distribution + Clone + Serialize + DeserializeOwned,
+    D: Histogram + Clone + Serialize + DeserializeOwned,
 > {
     pub row_cnt: usize,
     #[serde_as(as = "HashMap<serde_with::json::JsonString, _>")]
@@ -137,7 +319,7 @@ pub struct TableStats<
 
 impl<
         M: MostCommonValues + Clone + Serialize + DeserializeOwned,
-        D: Distribution + Clone + Serialize + DeserializeOwned,
+        D: Histogram + Clone + Serialize + DeserializeOwned,
     > TableStats<M, D>
 {
     pub fn new(
@@ -151,8 +333,31 @@ impl<
     }
 }
 
+/// The statistics of the tables, by the names of their scans, which are qualified with the schema
+/// for the tables outside the default schema, e.g., `sales.orders`.
 pub type BaseTableStats<M, D> = HashMap<String, TableStats<M, D>>;
 
+/// Merges the statistics collected over the partitions of the tables, e.g., by the workers of a
+/// distributed ANALYZE, into the statistics of the whole tables. The partitions should be
+/// analyzed with [`TableStats::partition_from_record_batches`] to estimate the distinct values
+/// of the tables accurately.
+pub fn merge_base_table_stats(
+    partitions: impl IntoIterator<Item = DataFusionBaseTableStats>,
+) -> DataFusionBaseTableStats {
+    let mut merged = DataFusionBaseTableStats::new();
+    for partition in partitions {
+        for (table, table_stats) in partition {
+            match merged.get_mut(&table) {
+                Some(merged_table_stats) => merged_table_stats.merge(&table_stats),
+                None => {
+                    merged.insert(table, table_stats);
+                }
+            }
+        }
+    }
+    merged
+}
+
 type FirstPassState = (
     Vec<HyperLogLog<ColumnCombValue>>,
     Vec<MisraGries<ColumnCombValue>>,
@@ -160,12 +365,24 @@ type FirstPassState = (
 );
 
 type SecondPassState = (
-    Vec<Option<TDigest<Value>>>,
+    Vec<Option<DataFusionHistogram>>,
     Vec<Counter<ColumnCombValue>>,
     Vec<i32>,
 );
 
-impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
+impl TableStats<Counter<ColumnCombValue>, DataFusionHistogram> {
+    /// Merges the statistics of another partition of the table. Only the column combinations
+    /// with statistics in both partitions are kept.
+    pub fn merge(&mut self, other: &Self) {
+        let row_cnt = self.row_cnt;
+        self.column_comb_stats
+            .retain(|comb, _| other.column_comb_stats.contains_key(comb));
+        for (comb, column_stats) in &mut self.column_comb_stats {
+            column_stats.merge(&other.column_comb_stats[comb], row_cnt, other.row_cnt);
+        }
+        self.row_cnt += other.row_cnt;
+    }
+
     fn is_type_supported(data_type: &DataType) -> bool {
         matches!(
             data_type,
@@ -179,6 +396,10 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
                 | DataType::Float32
                 | DataType::Float64
                 | DataType::Utf8
+                | DataType::Decimal128(_, _)
+                | DataType::Timestamp(_, _)
+                | DataType::Time32(_)
+                | DataType::Time64(_)
         )
     }
 
@@ -194,12 +415,13 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
         comb_stat_types: &[(Vec<usize>, Vec<DataType>, StatType)],
         mgs: &[MisraGries<ColumnCombValue>],
         nb_stats: usize,
+        histogram_type: HistogramType,
     ) -> anyhow::Result<SecondPassState> {
         Ok((
             comb_stat_types
                 .iter()
                 .map(|(_, _, stat_type)| match stat_type {
-                    StatType::Full => Some(TDigest::new(tdigest::DEFAULT_COMPRESSION)),
+                    StatType::Full => Some(histogram_type.new_histogram()),
                     StatType::Partial => None,
                 })
                 .collect(),
@@ -278,6 +500,20 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
             };
         }
 
+        // Timestamps and times are kept in nanoseconds, whatever the unit of the column.
+        macro_rules! nanos_col_cast {
+            ({ $col:expr, $nanos_type:expr, $array_type:path, $value_type:path }) => {
+                cast($col.as_ref(), &$nanos_type)
+                    .unwrap()
+                    .as_any()
+                    .downcast_ref::<$array_type>()
+                    .unwrap()
+                    .iter()
+                    .map(|x| x.map($value_type))
+                    .collect_vec()
+            };
+        }
+
         match col_type {
             DataType::Boolean => simple_col_cast!({col, BooleanArray, Value::Bool}),
             DataType::Int8 => simple_col_cast!({col, Int8Array, Value::Int8}),
@@ -290,6 +526,25 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
             DataType::Float64 => float_col_cast!({ col, Float64Array }),
             DataType::Date32 => simple_col_cast!({col, Date32Array, Value::Date32}),
             DataType::Utf8 => utf8_col_cast!({ col }),
+            DataType::Decimal128(_, scale) => col
+                .as_any()
+                .downcast_ref::<Decimal128Array>()
+                .unwrap()
+                .iter()
+                .map(|x| x.map(|y| Value::Decimal128(Decimal128::new(y, *scale))))
+                .collect_vec(),
+            DataType::Timestamp(_, _) => nanos_col_cast!({
+                col,
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                TimestampNanosecondArray,
+                Value::Timestamp
+            }),
+            DataType::Time32(_) | DataType::Time64(_) => nanos_col_cast!({
+                col,
+                DataType::Time64(TimeUnit::Nanosecond),
+                Time64NanosecondArray,
+                Value::Time
+            }),
             _ => unreachable!(),
         }
     }
@@ -350,7 +605,7 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
     fn generate_full_stats(
         column_combs: &[Vec<ColumnCombValue>],
         cnts: &mut [Counter<ColumnCombValue>],
-        distrs: &mut [Option<TDigest<Value>>],
+        distrs: &mut [Option<DataFusionHistogram>],
         row_counts: &mut [i32],
     ) {
         column_combs
@@ -371,8 +626,7 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
                         .cloned()
                         .collect();
 
-                    d.norm_weight += nb_rows as usize;
-                    d.merge_values(&filtered_values);
+                    d.merge_values(&filtered_values, nb_rows as usize);
                 }
             });
     }
@@ -382,6 +636,45 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
         second_batch_reader: impl FnOnce() -> Vec<ParquetRecordBatchReader>,
         combinations: Vec<ColumnsIdx>,
         schema: Arc<Schema>,
+    ) -> anyhow::Result<Self> {
+        Self::from_record_batches_with_options(
+            first_batch_reader,
+            second_batch_reader,
+            combinations,
+            schema,
+            StatsOptions::default(),
+        )
+    }
+
+    /// Collects the statistics of a partition of a table like
+    /// [`TableStats::from_record_batches`], keeping the sketches needed to merge them with the
+    /// statistics of the other partitions with [`merge_base_table_stats`].
+    pub fn partition_from_record_batches(
+        first_batch_reader: impl FnOnce() -> Vec<ParquetRecordBatchReader>,
+        second_batch_reader: impl FnOnce() -> Vec<ParquetRecordBatchReader>,
+        combinations: Vec<ColumnsIdx>,
+        schema: Arc<Schema>,
+    ) -> anyhow::Result<Self> {
+        Self::from_record_batches_with_options(
+            first_batch_reader,
+            second_batch_reader,
+            combinations,
+            schema,
+            StatsOptions {
+                keep_sketches: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Collects the statistics of a table like [`TableStats::from_record_batches`], e.g., with
+    /// another kind of histogram.
+    pub fn from_record_batches_with_options(
+        first_batch_reader: impl FnOnce() -> Vec<ParquetRecordBatchReader>,
+        second_batch_reader: impl FnOnce() -> Vec<ParquetRecordBatchReader>,
+        combinations: Vec<ColumnsIdx>,
+        schema: Arc<Schema>,
+        options: StatsOptions,
     ) -> anyhow::Result<Self> {
         let comb_stat_types = Self::get_stats_types(&combinations, &schema);
         let nb_stats = comb_stat_types.len();
@@ -430,7 +723,12 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
             .into_par_iter()
             .map(|group| {
                 group.fold(
-                    Self::second_pass_stats_id(&comb_stat_types, &mgs, nb_stats),
+                    Self::second_pass_stats_id(
+                        &comb_stat_types,
+                        &mgs,
+                        nb_stats,
+                        options.histogram_type,
+                    ),
                     |local_stats, batch| {
                         let mut local_stats = local_stats?;
 
@@ -449,7 +747,7 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
             .collect();
 
         let (distrs, cnts, row_cnts) = local_final_stats.into_iter().fold(
-            Self::second_pass_stats_id(&comb_stat_types, &mgs, nb_stats),
+            Self::second_pass_stats_id(&comb_stat_types, &mgs, nb_stats, options.histogram_type),
             |final_stats, local_stats| {
                 let mut final_stats = final_stats?;
                 let local_stats = local_stats?;
@@ -463,7 +761,6 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
                         (&mut final_distrs[i], &local_distrs[i])
                     {
                         final_distr.merge(local_distr);
-                        final_distr.norm_weight += local_distr.norm_weight;
                     }
 
                     final_counts[i] += local_counts[i];
@@ -489,12 +786,12 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
             });
 
         for (comb, cnt, distr, hll, null_cnt) in iter_comb {
-            let column_stats = ColumnCombValueStats::new(
-                cnt,
-                hll.n_distinct(),
-                null_cnt / (row_cnt as f64),
-                distr,
-            );
+            let mut column_stats =
+                ColumnCombValueStats::new(cnt, 0, null_cnt / (row_cnt as f64), distr);
+            column_stats.set_approx_ndistinct(hll.n_distinct(), row_cnt as usize);
+            if options.keep_sketches {
+                column_stats.hll = Some(hll);
+            }
             column_comb_stats.insert(comb, column_stats);
         }
 
@@ -504,3 +801,102 @@ impl TableStats<Counter<ColumnCombValue>, TDigest<Value>> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partition_stats(
+        values: &[i32],
+        null_cnt: usize,
+        keep_sketches: bool,
+    ) -> DataFusionPerTableStats {
+        let values = values
+            .iter()
+            .map(|&value| vec![Some(Value::Int32(value))])
+            .collect_vec();
+        let mut mcvs = Counter::new(&values[..1]);
+        mcvs.aggregate(&values);
+        let mut hll = HyperLogLog::new(hyperloglog::DEFAULT_PRECISION);
+        hll.aggregate(values.iter());
+        let row_cnt = values.len() + null_cnt;
+        let mut column_stats = ColumnCombValueStats::new(
+            mcvs,
+            hll.n_distinct(),
+            null_cnt as f64 / row_cnt as f64,
+            None,
+        );
+        if keep_sketches {
+            column_stats.hll = Some(hll);
+        }
+        TableStats::new(row_cnt, HashMap::from([(vec![0], column_stats)]))
+    }
+
+    #[test]
+    fn merge_partitions_with_sketches() {
+        let merged = merge_base_table_stats([
+            HashMap::from([("t1".to_string(), partition_stats(&[1, 2, 3, 4], 0, true))]),
+            HashMap::from([("t1".to_string(), partition_stats(&[3, 4, 5, 6], 4, true))]),
+        ]);
+        let table_stats = &merged["t1"];
+        assert_eq!(table_stats.row_cnt, 12);
+        let column_stats = &table_stats.column_comb_stats[&vec![0]];
+        assert_eq!(column_stats.ndistinct, 6);
+        assert_eq!(column_stats.null_frac, 4.0 / 12.0);
+        assert_eq!(column_stats.mcvs.cnt(), 2);
+        assert_eq!(
+            column_stats.mcvs.freq(&vec![Some(Value::Int32(3))]),
+            Some(1.0 / 8.0)
+        );
+    }
+
+    #[test]
+    fn merge_partitions_without_sketches() {
+        let mut table_stats = partition_stats(&[1, 2, 3, 4], 0, false);
+        table_stats.merge(&partition_stats(&[5, 6], 2, true));
+        let column_stats = &table_stats.column_comb_stats[&vec![0]];
+        assert_eq!(column_stats.ndistinct, 6);
+        assert!(column_stats.hll.is_none());
+    }
+
+    #[test]
+    fn merge_equi_depth_histograms() {
+        let histogram = |values: std::ops::Range<i32>| {
+            let mut histogram = HistogramType::EquiDepth.new_histogram();
+            let values = values.map(Value::Int32).collect_vec();
+            histogram.merge_values(&values, values.len());
+            histogram
+        };
+        let mut merged = histogram(0..50);
+        merged.merge(&histogram(50..100));
+        assert_eq!(merged.histogram_type(), HistogramType::EquiDepth);
+        assert_approx_eq::assert_approx_eq!(merged.cdf(&Value::Int32(49)), 0.5, 0.01);
+        assert_approx_eq::assert_approx_eq!(
+            merged.range_freq(&Value::Int32(24), &Value::Int32(74)),
+            0.5,
+            0.01
+        );
+        assert_approx_eq::assert_approx_eq!(merged.quantile(0.25), 24.0, 0.01);
+    }
+
+    #[test]
+    #[should_panic]
+    fn merge_histograms_of_different_types() {
+        let mut histogram = HistogramType::TDigest.new_histogram();
+        histogram.merge(&HistogramType::EquiDepth.new_histogram());
+    }
+
+    #[test]
+    fn bound_approx_ndistinct() {
+        let mut column_stats =
+            partition_stats(&[1, 1, 1, 2], 4, false).column_comb_stats[&vec![0]].clone();
+        // at least the most common value
+        column_stats.set_approx_ndistinct(0, 8);
+        assert_eq!(column_stats.ndistinct, 1);
+        // at most the four non-null rows
+        column_stats.set_approx_ndistinct(10, 8);
+        assert_eq!(column_stats.ndistinct, 4);
+        column_stats.set_approx_ndistinct(3, 8);
+        assert_eq!(column_stats.ndistinct, 3);
+    }
+}
@@ -5,34 +5,74 @@
 
 use std::sync::{Arc, Mutex};
 
-use adv_stats::stats::{
-    DataFusionBaseTableStats, DataFusionDistribution, DataFusionMostCommonValues,
-};
-use adv_stats::AdvStats;
+use adv_stats::stats::{DataFusionBaseTableStats, DataFusionHistogram, DataFusionMostCommonValues};
+use adv_stats::{AdvStats, DefaultSelectivities};
+use itertools::Itertools;
 use optd_og_datafusion_repr::cost::adaptive_cost::RuntimeAdaptionStorageInner;
-use optd_og_datafusion_repr::cost::{DfCostModel, RuntimeAdaptionStorage};
-use optd_og_datafusion_repr::plan_nodes::{ArcDfPredNode, DfNodeType, DfReprPredNode, ListPred};
-use optd_og_datafusion_repr::properties::schema::Catalog;
-use optd_og_datafusion_repr::{DatafusionOptimizer, OptimizerExt};
+use optd_og_datafusion_repr::cost::{CardinalityHintStorage, DfCostModel, RuntimeAdaptionStorage};
+use optd_og_datafusion_repr::plan_nodes::{
+    ArcDfPredNode, ConstantPred, DfNodeType, DfReprPredNode, JoinType, ListPred,
+};
+use optd_og_datafusion_repr::properties::schema::{Catalog, Schema};
+use optd_og_datafusion_repr::{DatafusionOptimizer, OptimizerExt, TableId};
 
 pub mod adv_stats;
 
 use std::collections::HashMap;
 
 use optd_og_core::cascades::{CascadesOptimizer, NaiveMemo, RelNodeContext};
-use optd_og_core::cost::{Cost, CostModel, Statistics};
+use optd_og_core::cost::{Cost, CostFormula, CostModel, Statistics};
 
 pub struct AdvancedCostModel {
     base_model: DfCostModel,
-    stats: AdvStats<DataFusionMostCommonValues, DataFusionDistribution>,
+    stats: AdvStats<DataFusionMostCommonValues, DataFusionHistogram>,
 }
 
 impl AdvancedCostModel {
     pub fn new(stats: DataFusionBaseTableStats) -> Self {
-        let stats = AdvStats::new(stats);
         let base_model = DfCostModel::new(HashMap::new());
+        let stats = AdvStats::new(stats).with_cardinality_hints(base_model.get_cardinality_hints());
         Self { base_model, stats }
     }
+
+    /// The selectivities estimated for the predicates without statistics, unless they are
+    /// overridden in the session, see [`DefaultSelectivities`].
+    pub fn with_default_selectivities(mut self, default_sel: DefaultSelectivities) -> Self {
+        self.stats = self.stats.with_default_selectivities(default_sel);
+        self
+    }
+
+    pub fn stats(&self) -> &AdvStats<DataFusionMostCommonValues, DataFusionHistogram> {
+        &self.stats
+    }
+
+    /// See [`DfCostModel::with_catalog`].
+    pub fn with_catalog(mut self, catalog: Arc<dyn Catalog>) -> Self {
+        self.base_model = self.base_model.with_catalog(catalog);
+        self
+    }
+
+    /// See [`DfCostModel::with_cardinality_hints`].
+    pub fn with_cardinality_hints(mut self, cardinality_hints: CardinalityHintStorage) -> Self {
+        self.stats = self.stats.with_cardinality_hints(cardinality_hints.clone());
+        self.base_model = self.base_model.with_cardinality_hints(cardinality_hints);
+        self
+    }
+
+    pub fn get_cardinality_hints(&self) -> CardinalityHintStorage {
+        self.base_model.get_cardinality_hints()
+    }
+
+    /// The columns of the left side of a join followed by the ones of its right side, which the
+    /// condition of a mark join refers to, unlike its output.
+    fn join_input_schema(
+        context: &RelNodeContext,
+        optimizer: &CascadesOptimizer<DfNodeType>,
+    ) -> Schema {
+        let left = optimizer.get_schema_of(context.children_group_ids[0].into());
+        let right = optimizer.get_schema_of(context.children_group_ids[1].into());
+        Schema::new([left.fields, right.fields].concat())
+    }
 }
 
 impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdvancedCostModel {
@@ -44,6 +84,14 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdvancedCostModel {
         self.base_model.explain_statistics(cost)
     }
 
+    fn estimated_row_cnt(&self, stat: &Statistics) -> Option<f64> {
+        self.base_model.estimated_row_cnt(stat)
+    }
+
+    fn cost_components(&self, cost: &Cost) -> Vec<(String, f64)> {
+        self.base_model.cost_components(cost)
+    }
+
     fn accumulate(&self, total_cost: &mut Cost, cost: &Cost) {
         self.base_model.accumulate(total_cost, cost)
     }
@@ -83,12 +131,13 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdvancedCostModel {
         match node {
             DfNodeType::PhysicalScan => {
                 let table = predicates[0].data.as_ref().unwrap().as_str(); // TODO: use df-repr to retrieve it
-                let row_cnt = self
-                    .stats
-                    .per_table_stats_map
-                    .get(table.as_ref())
-                    .map(|per_table_stats| per_table_stats.row_cnt)
-                    .unwrap_or(1) as f64;
+                let row_cnt = match self.stats.per_table_stats_map.get(&TableId::new(&table)) {
+                    Some(per_table_stats) => per_table_stats.row_cnt as f64,
+                    None => self
+                        .base_model
+                        .hinted_table_row_cnt(predicates)
+                        .unwrap_or(1.0),
+                } * DfCostModel::scan_fraction(predicates);
                 DfCostModel::stat(row_cnt)
             }
             DfNodeType::PhysicalLimit => {
@@ -98,6 +147,14 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdvancedCostModel {
                 DfCostModel::stat(row_cnt)
             }
             DfNodeType::PhysicalFilter => {
+                if let Some(stat) = self.base_model.hinted_filter_stat(
+                    row_cnts[0],
+                    &predicates[0],
+                    &context,
+                    optimizer,
+                ) {
+                    return stat;
+                }
                 let output_schema = optimizer.get_schema_of(context.group_id.into());
                 let output_column_ref = optimizer.get_column_ref_of(context.group_id.into());
                 let row_cnt = self.stats.get_filter_row_cnt(
@@ -105,6 +162,7 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdvancedCostModel {
                     output_schema,
                     output_column_ref,
                     predicates[0].clone(),
+                    DfCostModel::mark_selectivity(children_stats[0]),
                 );
                 DfCostModel::stat(row_cnt)
             }
@@ -122,9 +180,20 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdvancedCostModel {
                     output_schema,
                     output_column_ref,
                     predicates[0].clone(),
-                    left_column_ref,
-                    right_column_ref,
+                    left_column_ref.clone(),
+                    right_column_ref.clone(),
                 );
+                if *join_typ == JoinType::LeftMark {
+                    let mark_selectivity = self.stats.get_nlj_mark_selectivity(
+                        row_cnts[0],
+                        row_cnts[1],
+                        Self::join_input_schema(&context, optimizer),
+                        predicates[0].clone(),
+                        left_column_ref,
+                        right_column_ref,
+                    );
+                    return DfCostModel::mark_join_stat(row_cnt, mark_selectivity);
+                }
                 DfCostModel::stat(row_cnt)
             }
             DfNodeType::PhysicalHashJoin(join_typ) => {
@@ -142,18 +211,44 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdvancedCostModel {
                     ListPred::from_pred_node(predicates[1].clone()).unwrap(),
                     output_schema,
                     output_column_ref,
-                    left_column_ref,
-                    right_column_ref,
+                    left_column_ref.clone(),
+                    right_column_ref.clone(),
                 );
+                if *join_typ == JoinType::LeftMark {
+                    let mark_selectivity = self.stats.get_hash_join_mark_selectivity(
+                        row_cnts[0],
+                        row_cnts[1],
+                        ListPred::from_pred_node(predicates[0].clone()).unwrap(),
+                        ListPred::from_pred_node(predicates[1].clone()).unwrap(),
+                        Self::join_input_schema(&context, optimizer),
+                        left_column_ref,
+                        right_column_ref,
+                    );
+                    return DfCostModel::mark_join_stat(row_cnt, mark_selectivity);
+                }
                 DfCostModel::stat(row_cnt)
             }
-            DfNodeType::PhysicalAgg => {
+            DfNodeType::PhysicalAgg
+            | DfNodeType::PhysicalStreamAgg
+            | DfNodeType::PhysicalFinalAgg => {
                 let output_column_ref = optimizer.get_column_ref_of(context.group_id.into());
                 let row_cnt = self
                     .stats
                     .get_agg_row_cnt(predicates[1].clone(), output_column_ref);
                 DfCostModel::stat(row_cnt)
             }
+            DfNodeType::PhysicalPartialAgg => {
+                // Each partition produces at most one row per group.
+                let output_column_ref = optimizer.get_column_ref_of(context.group_id.into());
+                let partitions = ConstantPred::from_pred_node(predicates[2].clone())
+                    .unwrap()
+                    .value()
+                    .as_u64() as f64;
+                let row_cnt = self
+                    .stats
+                    .get_agg_row_cnt(predicates[1].clone(), output_column_ref);
+                DfCostModel::stat((row_cnt * partitions).min(row_cnts[0]))
+            }
             _ => self.base_model.derive_statistics(
                 node,
                 predicates,
@@ -163,6 +258,51 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdvancedCostModel {
             ),
         }
     }
+
+    fn reset_caches(&self) {
+        self.base_model.reset_caches();
+        self.stats.clear_selectivity_cache();
+    }
+
+    fn describe(&self) -> Vec<CostFormula> {
+        let mut formulas = self.base_model.describe();
+        for formula in &mut formulas {
+            match formula.operator.as_str() {
+                "PhysicalScan" => {
+                    formula.row_cnt = "table_rows from the table statistics, or the row hint of the table, or 1, times pruned_fraction if the partitions are pruned, times index_fraction for index lookups".into();
+                    formula.constants = self
+                        .stats
+                        .per_table_stats_map
+                        .iter()
+                        .map(|(table, stats)| (format!("{table}.rows"), stats.row_cnt as f64))
+                        .sorted_by(|a, b| a.0.cmp(&b.0))
+                        .collect();
+                }
+                "PhysicalLimit"
+                | "PhysicalFilter"
+                | "PhysicalNestedLoopJoin"
+                | "PhysicalHashJoin"
+                | "PhysicalAgg"
+                | "PhysicalStreamAgg"
+                | "PhysicalPartialAgg"
+                | "PhysicalFinalAgg" => {
+                    formula.row_cnt = "estimated from the column statistics".into();
+                    formula.constants.clear();
+                    if formula.operator == "PhysicalFilter" {
+                        formula.row_cnt += ", or from the cardinality hint of the predicate, with the fraction of the rows that have a match for the conditions on the mark of a mark join";
+                        let default_sel = self.stats.default_selectivities();
+                        formula.constants = vec![
+                            ("default_eq_sel".to_string(), default_sel.eq),
+                            ("default_ineq_sel".to_string(), default_sel.ineq),
+                            ("default_unk_sel".to_string(), default_sel.unk),
+                        ];
+                    }
+                }
+                _ => {}
+            }
+        }
+        formulas
+    }
 }
 
 pub fn new_physical_adv_cost(
@@ -170,7 +310,8 @@ pub fn new_physical_adv_cost(
     stats: DataFusionBaseTableStats,
     enable_adaptive: bool,
 ) -> DatafusionOptimizer {
-    let cost_model = AdvancedCostModel::new(stats);
+    let cost_model = AdvancedCostModel::new(stats).with_catalog(catalog.clone());
+    let cardinality_hints = cost_model.get_cardinality_hints();
     // This cost model does not accept adaptive (runtime) statistics.
     let runtime_map =
         RuntimeAdaptionStorage::new(Mutex::new(RuntimeAdaptionStorageInner::default()));
@@ -179,5 +320,6 @@ pub fn new_physical_adv_cost(
         enable_adaptive,
         cost_model,
         runtime_map,
+        cardinality_hints,
     )
 }
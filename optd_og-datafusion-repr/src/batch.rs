@@ -0,0 +1,314 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Multi-query optimization: the queries of a batch, e.g., the panels of a dashboard, are
+//! optimized in the same memo table, so that the subplans they have in common are planned once
+//! and can be materialized once for all of them.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use anyhow::Result;
+use itertools::Itertools;
+use optd_og_core::cascades::{GroupHandle, GroupId};
+use optd_og_core::nodes::{PlanAnnotations, PlanNode, PlanNodeOrGroup};
+
+use crate::plan_nodes::{ArcDfPlanNode, DfNodeType};
+use crate::DatafusionOptimizer;
+
+/// A subplan shared by several queries of a batch.
+#[derive(Clone, Debug)]
+pub struct SharedSubplan {
+    pub group_id: GroupId,
+    /// The plan of the group, which may reference the shared subplans listed before it.
+    pub plan: ArcDfPlanNode,
+    /// The positions in the batch of the queries reading the subplan.
+    pub queries: BTreeSet<usize>,
+}
+
+pub struct BatchOptimizationResult {
+    /// The plan of each query, in the order of the batch. A `PlanNodeOrGroup::Group` child
+    /// references the shared subplan of the group, which is to be materialized once and read by
+    /// all of its queries.
+    pub plans: Vec<ArcDfPlanNode>,
+    /// The shared subplans, each one listed after the ones it references.
+    pub shared: Vec<SharedSubplan>,
+    /// The costs and statistics of the nodes of the plans and of the shared subplans.
+    pub meta: PlanAnnotations,
+}
+
+impl BatchOptimizationResult {
+    pub fn get_shared(&self, group_id: GroupId) -> Option<&SharedSubplan> {
+        self.shared
+            .iter()
+            .find(|subplan| subplan.group_id == group_id)
+    }
+}
+
+impl DatafusionOptimizer {
+    /// Optimizes the queries of a batch in the same memo table, and returns plans referencing
+    /// the subplans they share, see [`BatchOptimizationResult`]. The memo table of the previous
+    /// queries is discarded, as in non-adaptive mode.
+    pub fn optimize_batch(
+        &mut self,
+        root_rels: Vec<ArcDfPlanNode>,
+    ) -> Result<BatchOptimizationResult> {
+        let root_rels = if self.enable_heuristic {
+            root_rels
+                .into_iter()
+                .map(|root_rel| self.heuristic_optimize(root_rel))
+                .collect_vec()
+        } else {
+            root_rels
+        };
+        self.cascades_optimizer.step_clear();
+        self.adaptive_plans.clear();
+        {
+            // The group ids are reused by the new memo table.
+            let mut runtime_statistics = self.runtime_statistics.lock().unwrap();
+            runtime_statistics.estimates.clear();
+            runtime_statistics.misestimates.clear();
+        }
+
+        let mut groups: Vec<GroupHandle> = Vec::with_capacity(root_rels.len());
+        for (stage_idx, stage) in self.stages.clone().into_iter().enumerate() {
+            if stage_idx > 0 {
+                self.cascades_optimizer.step_next_stage();
+            }
+            // The groups explored for a query are not explored again for the next ones.
+            for (query, root_rel) in root_rels.iter().enumerate() {
+                let group =
+                    self.run_optimization_stage(&stage, |optimizer| match groups.get(query) {
+                        Some(group) => {
+                            optimizer.fire_optimize_tasks(*group)?;
+                            Ok(*group)
+                        }
+                        None => optimizer.step_optimize(root_rel.clone()),
+                    })?;
+                if stage_idx == 0 {
+                    groups.push(group);
+                }
+            }
+        }
+
+        let mut meta = Some(PlanAnnotations::new());
+        let plans = groups
+            .into_iter()
+            .map(|group| {
+                self.cascades_optimizer
+                    .step_get_optimize_rel(group, &mut meta)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(share_subplans(&plans, &meta.unwrap()))
+    }
+}
+
+fn collect_groups(plan: &ArcDfPlanNode, meta: &PlanAnnotations, groups: &mut BTreeSet<GroupId>) {
+    if let Some(node_meta) = meta.get(plan) {
+        groups.insert(node_meta.group_id);
+    }
+    for child in &plan.children {
+        collect_groups(&child.unwrap_plan_node(), meta, groups);
+    }
+}
+
+struct SubplanSharing<'a> {
+    meta: &'a PlanAnnotations,
+    /// The queries whose plans use each group.
+    queries: HashMap<GroupId, BTreeSet<usize>>,
+    shared: Vec<SharedSubplan>,
+    new_meta: PlanAnnotations,
+}
+
+impl SubplanSharing<'_> {
+    /// Replaces the subplans used by more queries than `readers` with references to the shared
+    /// subplans, e.g., the subplans of a query used by other queries. A subplan used by the same
+    /// queries as the plan it is part of is not shared on its own.
+    fn share(
+        &mut self,
+        plan: &ArcDfPlanNode,
+        readers: &BTreeSet<usize>,
+    ) -> PlanNodeOrGroup<DfNodeType> {
+        let node_meta = self.meta.get(plan);
+        if let Some(node_meta) = node_meta {
+            let queries = &self.queries[&node_meta.group_id];
+            // materializing a scan is no cheaper than scanning the table again
+            if plan.typ != DfNodeType::PhysicalScan && queries.len() > readers.len() {
+                let group_id = node_meta.group_id;
+                if !self
+                    .shared
+                    .iter()
+                    .any(|subplan| subplan.group_id == group_id)
+                {
+                    let queries = queries.clone();
+                    let children = self.share_children(plan, &queries);
+                    let plan = self.rebuild(plan, children);
+                    self.shared.push(SharedSubplan {
+                        group_id,
+                        plan,
+                        queries,
+                    });
+                }
+                return PlanNodeOrGroup::Group(group_id);
+            }
+        }
+        let children = self.share_children(plan, readers);
+        PlanNodeOrGroup::PlanNode(self.rebuild(plan, children))
+    }
+
+    fn share_children(
+        &mut self,
+        plan: &ArcDfPlanNode,
+        readers: &BTreeSet<usize>,
+    ) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+        plan.children
+            .iter()
+            .map(|child| self.share(&child.unwrap_plan_node(), readers))
+            .collect()
+    }
+
+    fn rebuild(
+        &mut self,
+        plan: &ArcDfPlanNode,
+        children: Vec<PlanNodeOrGroup<DfNodeType>>,
+    ) -> ArcDfPlanNode {
+        let node = Arc::new(PlanNode {
+            typ: plan.typ.clone(),
+            children,
+            predicates: plan.predicates.clone(),
+        });
+        if let Some(node_meta) = self.meta.get(plan) {
+            self.new_meta.insert(&node, node_meta.clone());
+        }
+        node
+    }
+}
+
+/// Finds the subplans of the optimized plans of a batch which are used by several queries, and
+/// references them from the plans.
+fn share_subplans(plans: &[ArcDfPlanNode], meta: &PlanAnnotations) -> BatchOptimizationResult {
+    let mut queries: HashMap<GroupId, BTreeSet<usize>> = HashMap::new();
+    for (query, plan) in plans.iter().enumerate() {
+        let mut groups = BTreeSet::new();
+        collect_groups(plan, meta, &mut groups);
+        for group_id in groups {
+            queries.entry(group_id).or_default().insert(query);
+        }
+    }
+    let mut sharing = SubplanSharing {
+        meta,
+        queries,
+        shared: Vec::new(),
+        new_meta: PlanAnnotations::new(),
+    };
+    let plans = plans
+        .iter()
+        .enumerate()
+        .map(|(query, plan)| {
+            // the root of a query is kept even if another query is the same
+            let children = sharing.share_children(plan, &BTreeSet::from([query]));
+            sharing.rebuild(plan, children)
+        })
+        .collect();
+    BatchOptimizationResult {
+        plans,
+        shared: sharing.shared,
+        meta: sharing.new_meta,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use optd_og_core::nodes::{PlanAnnotation, Value};
+
+    use super::*;
+    use crate::cost::DfCostModel;
+    use crate::plan_nodes::{
+        ConstantPred, DfReprPlanNode, DfReprPredNode, ListPred, LogicalScan, PhysicalFilter,
+        PhysicalLimit, PhysicalProjection,
+    };
+
+    fn add_meta(meta: &mut PlanAnnotations, plan: &ArcDfPlanNode, group_id: usize) {
+        meta.insert(
+            plan,
+            PlanAnnotation::new(
+                GroupId(group_id),
+                DfCostModel::cost(1.0, 0.0),
+                Arc::new(DfCostModel::stat(1.0)),
+                &DfCostModel::new(HashMap::new()),
+            ),
+        );
+    }
+
+    /// `Filter(Scan t1)`, with the groups of the scan and of the filter.
+    fn filtered_scan(meta: &mut PlanAnnotations) -> ArcDfPlanNode {
+        let scan = LogicalScan::new("t1".into()).into_plan_node();
+        let scan = Arc::new(PlanNode {
+            typ: DfNodeType::PhysicalScan,
+            children: vec![],
+            predicates: scan.predicates.clone(),
+        });
+        add_meta(meta, &scan, 0);
+        let filter =
+            PhysicalFilter::new(scan, ConstantPred::new(Value::Bool(true)).into_pred_node())
+                .into_plan_node();
+        add_meta(meta, &filter, 1);
+        filter
+    }
+
+    fn project(child: ArcDfPlanNode, meta: &mut PlanAnnotations, group_id: usize) -> ArcDfPlanNode {
+        let projection = PhysicalProjection::new(child, ListPred::new(vec![])).into_plan_node();
+        add_meta(meta, &projection, group_id);
+        projection
+    }
+
+    fn limit(child: ArcDfPlanNode, meta: &mut PlanAnnotations) -> ArcDfPlanNode {
+        let limit = PhysicalLimit::new(
+            child,
+            ConstantPred::new(Value::UInt64(0)).into_pred_node(),
+            ConstantPred::new(Value::UInt64(10)).into_pred_node(),
+        )
+        .into_plan_node();
+        add_meta(meta, &limit, 2);
+        limit
+    }
+
+    #[test]
+    fn share_common_subplans() {
+        let mut meta = PlanAnnotations::new();
+        let first = filtered_scan(&mut meta);
+        let first = project(first, &mut meta, 3);
+        let second = limit(filtered_scan(&mut meta), &mut meta);
+        let second = project(second, &mut meta, 4);
+        let third = limit(filtered_scan(&mut meta), &mut meta);
+        let third = project(third, &mut meta, 5);
+
+        let result = share_subplans(&[first, second, third], &meta);
+        assert_eq!(result.shared.len(), 2);
+        // the filter is read by all the queries
+        let filter = result.get_shared(GroupId(1)).unwrap();
+        assert_eq!(filter.queries, BTreeSet::from([0, 1, 2]));
+        assert_eq!(filter.plan.typ, DfNodeType::PhysicalFilter);
+        assert!(matches!(
+            filter.plan.children[0],
+            PlanNodeOrGroup::PlanNode(_)
+        ));
+        // the limit is read by the last two queries, and reads the filter
+        let limit = result.get_shared(GroupId(2)).unwrap();
+        assert_eq!(limit.queries, BTreeSet::from([1, 2]));
+        assert_eq!(limit.plan.children[0], PlanNodeOrGroup::Group(GroupId(1)));
+
+        assert_eq!(
+            result.plans[0].children[0],
+            PlanNodeOrGroup::Group(GroupId(1))
+        );
+        assert_eq!(
+            result.plans[2].children[0],
+            PlanNodeOrGroup::Group(GroupId(2))
+        );
+        // the 3 projections, the shared filter and its scan, and the shared limit
+        assert_eq!(result.meta.len(), 6);
+    }
+}
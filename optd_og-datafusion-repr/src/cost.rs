@@ -5,6 +5,12 @@
 
 pub mod adaptive_cost;
 pub mod base_cost;
+pub mod cardinality_hints;
+pub mod nlj_threshold;
 
-pub use adaptive_cost::{AdaptiveCostModel, RuntimeAdaptionStorage};
+pub use adaptive_cost::{AdaptiveCostModel, Misestimate, RuntimeAdaptionStorage};
 pub use base_cost::{DfCostModel, COMPUTE_COST, IO_COST};
+pub use cardinality_hints::{
+    CardinalityHint, CardinalityHintStorage, CardinalityHints, DefaultSelectivityOverrides,
+};
+pub use nlj_threshold::NljRowThresholdCostModel;
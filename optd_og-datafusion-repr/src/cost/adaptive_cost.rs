@@ -6,12 +6,15 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
+use itertools::Itertools;
 use optd_og_core::cascades::{CascadesOptimizer, GroupId, NaiveMemo, RelNodeContext};
-use optd_og_core::cost::{Cost, CostModel, Statistics};
+use optd_og_core::cost::{Cost, CostFormula, CostModel, Statistics};
 
 use super::base_cost::DEFAULT_TABLE_ROW_CNT;
+use super::cardinality_hints::CardinalityHintStorage;
 use crate::cost::DfCostModel;
 use crate::plan_nodes::{ArcDfPredNode, DfNodeType};
+use crate::properties::schema::Catalog;
 
 pub type RuntimeAdaptionStorage = Arc<Mutex<RuntimeAdaptionStorageInner>>;
 
@@ -19,6 +22,90 @@ pub type RuntimeAdaptionStorage = Arc<Mutex<RuntimeAdaptionStorageInner>>;
 pub struct RuntimeAdaptionStorageInner {
     pub history: HashMap<GroupId, (usize, usize)>,
     pub iter_cnt: usize,
+    /// The estimated row count of each group of the last plan.
+    pub estimates: HashMap<GroupId, f64>,
+    /// How many times more or fewer rows than estimated an operator has to produce for its
+    /// group to be re-costed with the observed row count, `None` to disable.
+    pub misestimate_threshold: Option<f64>,
+    /// The groups re-costed with their observed row counts. Unlike `history`, they are kept
+    /// until the memo table is cleared, so that the plans of later executions of the query
+    /// are chosen with them.
+    pub misestimates: HashMap<GroupId, Misestimate>,
+}
+
+/// The row count of a group observed at runtime, which deviated from the estimate beyond
+/// [`RuntimeAdaptionStorageInner::misestimate_threshold`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Misestimate {
+    pub estimated_row_cnt: f64,
+    /// A lower bound of the row count if the operator was still running.
+    pub actual_row_cnt: usize,
+    pub finished: bool,
+    /// The `iter_cnt` of the plan it was observed in.
+    pub iter: usize,
+}
+
+impl Misestimate {
+    /// How many times more or fewer rows than estimated were observed.
+    pub fn ratio(&self) -> f64 {
+        let estimated = self.estimated_row_cnt.max(1.0);
+        let actual = (self.actual_row_cnt as f64).max(1.0);
+        (actual / estimated).max(estimated / actual)
+    }
+}
+
+impl RuntimeAdaptionStorageInner {
+    /// The row count beyond which an operator of the group is misestimated, so that the
+    /// misestimate can be recorded before the operator finishes.
+    pub fn misestimate_limit(&self, group_id: GroupId) -> Option<usize> {
+        let estimated = self.estimates.get(&group_id)?;
+        Some((estimated.max(1.0) * self.misestimate_threshold?).ceil() as usize)
+    }
+
+    /// Records the rows an operator of the group produced, `finished` is `false` if the
+    /// operator is still running.
+    pub fn record_row_cnt(&mut self, group_id: GroupId, row_cnt: usize, finished: bool) {
+        if finished {
+            self.history.insert(group_id, (row_cnt, self.iter_cnt));
+        }
+        let (Some(threshold), Some(&estimated_row_cnt)) =
+            (self.misestimate_threshold, self.estimates.get(&group_id))
+        else {
+            return;
+        };
+        let misestimate = Misestimate {
+            estimated_row_cnt,
+            actual_row_cnt: row_cnt,
+            finished,
+            iter: self.iter_cnt,
+        };
+        let misestimated = if finished {
+            misestimate.ratio() > threshold
+        } else {
+            row_cnt as f64 > estimated_row_cnt.max(1.0) * threshold
+        };
+        // A group already re-costed is estimated with the rows observed before, keep its row
+        // count up to date even if it no longer deviates.
+        if misestimated || (finished && self.misestimates.contains_key(&group_id)) {
+            self.misestimates.insert(group_id, misestimate);
+        }
+    }
+
+    /// The misestimates observed in the executions of the last plan.
+    pub fn new_misestimates(&self) -> Vec<(GroupId, Misestimate)> {
+        let threshold = self.misestimate_threshold.unwrap_or(f64::INFINITY);
+        let mut misestimates = self
+            .misestimates
+            .iter()
+            .filter(|(_, misestimate)| {
+                misestimate.iter == self.iter_cnt
+                    && (!misestimate.finished || misestimate.ratio() > threshold)
+            })
+            .map(|(group_id, misestimate)| (*group_id, misestimate.clone()))
+            .collect::<Vec<_>>();
+        misestimates.sort_by_key(|(group_id, _)| *group_id);
+        misestimates
+    }
 }
 
 pub struct AdaptiveCostModel {
@@ -28,14 +115,27 @@ pub struct AdaptiveCostModel {
 }
 
 impl AdaptiveCostModel {
-    fn get_row_cnt(&self, context: &RelNodeContext) -> f64 {
+    fn misestimated_row_cnt(&self, context: &RelNodeContext) -> Option<f64> {
+        let guard = self.runtime_row_cnt.lock().unwrap();
+        let misestimate = guard.misestimates.get(&context.group_id)?;
+        Some(misestimate.actual_row_cnt.max(1) as f64)
+    }
+
+    fn get_row_cnt(&self, predicates: &[ArcDfPredNode], context: &RelNodeContext) -> f64 {
+        if let Some(row_cnt) = self.misestimated_row_cnt(context) {
+            return row_cnt;
+        }
         let guard = self.runtime_row_cnt.lock().unwrap();
         if let Some((runtime_row_cnt, iter)) = guard.history.get(&context.group_id) {
             if *iter + self.decay >= guard.iter_cnt {
                 return (*runtime_row_cnt).max(1) as f64;
             }
         }
-        DEFAULT_TABLE_ROW_CNT as f64
+        let row_cnt = self
+            .base_model
+            .hinted_table_row_cnt(predicates)
+            .unwrap_or(DEFAULT_TABLE_ROW_CNT as f64);
+        row_cnt * DfCostModel::scan_fraction(predicates)
     }
 }
 
@@ -48,6 +148,14 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdaptiveCostModel {
         self.base_model.explain_statistics(cost)
     }
 
+    fn estimated_row_cnt(&self, stat: &Statistics) -> Option<f64> {
+        self.base_model.estimated_row_cnt(stat)
+    }
+
+    fn cost_components(&self, cost: &Cost) -> Vec<(String, f64)> {
+        self.base_model.cost_components(cost)
+    }
+
     fn accumulate(&self, total_cost: &mut Cost, cost: &Cost) {
         self.base_model.accumulate(total_cost, cost)
     }
@@ -69,8 +177,8 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdaptiveCostModel {
         optimizer: &CascadesOptimizer<DfNodeType>,
     ) -> Cost {
         if let DfNodeType::PhysicalScan = node {
-            let row_cnt = self.get_row_cnt(&context);
-            return DfCostModel::cost(0.0, row_cnt);
+            let row_cnt = self.get_row_cnt(predicates, &context);
+            return DfCostModel::cost(0.0, self.base_model.scan_io_cost(row_cnt, predicates));
         }
         self.base_model
             .compute_operation_cost(node, predicates, children, context, optimizer)
@@ -85,12 +193,40 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for AdaptiveCostModel {
         optimizer: &CascadesOptimizer<DfNodeType>,
     ) -> Statistics {
         if let DfNodeType::PhysicalScan = node {
-            let row_cnt = self.get_row_cnt(&context);
+            let row_cnt = self.get_row_cnt(predicates, &context);
+            return DfCostModel::stat(row_cnt);
+        }
+        // The operators above a misestimated one are costed with the rows it produced.
+        if let Some(row_cnt) = self.misestimated_row_cnt(&context) {
             return DfCostModel::stat(row_cnt);
         }
         self.base_model
             .derive_statistics(node, predicates, children, context, optimizer)
     }
+
+    fn describe(&self) -> Vec<CostFormula> {
+        let mut formulas = self.base_model.describe();
+        for formula in &mut formulas {
+            if formula.operator == "PhysicalScan" {
+                // the io cost is the one of the base model
+                let io_factors = formula
+                    .constants
+                    .iter()
+                    .filter(|(name, _)| name.ends_with("_io_factor"))
+                    .cloned()
+                    .collect_vec();
+                *formula = CostFormula::new(
+                    "PhysicalScan",
+                    formula.cost.clone(),
+                    "the observed rows if misestimated, or runtime rows of the last `decay` iterations, or the row hint of the table or default_table_rows times pruned_fraction if the partitions are pruned, times index_fraction for index lookups",
+                )
+                .with_constant("decay", self.decay as f64)
+                .with_constant("default_table_rows", DEFAULT_TABLE_ROW_CNT as f64);
+                formula.constants.extend(io_factors);
+            }
+        }
+        formulas
+    }
 }
 
 impl AdaptiveCostModel {
@@ -102,7 +238,63 @@ impl AdaptiveCostModel {
         }
     }
 
+    /// See [`DfCostModel::with_catalog`].
+    pub fn with_catalog(mut self, catalog: Arc<dyn Catalog>) -> Self {
+        self.base_model = self.base_model.with_catalog(catalog);
+        self
+    }
+
     pub fn get_runtime_map(&self) -> RuntimeAdaptionStorage {
         self.runtime_row_cnt.clone()
     }
+
+    /// See [`DfCostModel::with_cardinality_hints`].
+    pub fn with_cardinality_hints(mut self, cardinality_hints: CardinalityHintStorage) -> Self {
+        self.base_model = self.base_model.with_cardinality_hints(cardinality_hints);
+        self
+    }
+
+    pub fn get_cardinality_hints(&self) -> CardinalityHintStorage {
+        self.base_model.get_cardinality_hints()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_misestimates() {
+        let mut storage = RuntimeAdaptionStorageInner {
+            misestimate_threshold: Some(10.0),
+            ..Default::default()
+        };
+        let (group_1, group_2, group_3) = (GroupId(1), GroupId(2), GroupId(3));
+        storage.estimates = HashMap::from([(group_1, 100.0), (group_2, 100.0), (group_3, 100.0)]);
+        assert_eq!(storage.misestimate_limit(group_1), Some(1000));
+
+        // detected while the operator is running
+        storage.record_row_cnt(group_1, 1001, false);
+        // fewer rows than estimated are only known when the operator finishes
+        storage.record_row_cnt(group_2, 5, false);
+        storage.record_row_cnt(group_2, 5, true);
+        storage.record_row_cnt(group_3, 200, true);
+        let misestimates = storage.new_misestimates();
+        assert_eq!(
+            misestimates
+                .iter()
+                .map(|(group_id, misestimate)| (*group_id, misestimate.finished))
+                .collect::<Vec<_>>(),
+            vec![(group_1, false), (group_2, true)]
+        );
+        assert!(!storage.history.contains_key(&group_1));
+        assert_eq!(storage.history[&group_3], (200, 0));
+
+        // the next plan estimates the group with the observed rows
+        storage.iter_cnt += 1;
+        storage.estimates.insert(group_2, 5.0);
+        storage.record_row_cnt(group_2, 6, true);
+        assert!(storage.new_misestimates().is_empty());
+        assert_eq!(storage.misestimates[&group_2].actual_row_cnt, 6);
+    }
 }
@@ -4,26 +4,61 @@
 // https://opensource.org/licenses/MIT.
 
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use itertools::Itertools;
-use optd_og_core::cascades::{CascadesOptimizer, NaiveMemo, RelNodeContext};
-use optd_og_core::cost::{Cost, CostModel, Statistics};
+use optd_og_core::cascades::{CascadesOptimizer, Memo, NaiveMemo, RelNodeContext};
+use optd_og_core::cost::{Cost, CostFormula, CostModel, Statistics};
 
-use crate::plan_nodes::{ArcDfPredNode, ConstantPred, DfNodeType, DfReprPredNode};
+use super::cardinality_hints::{CardinalityHintStorage, CardinalityHints};
+use crate::plan_nodes::{
+    ArcDfPredNode, BuildSide, ConstantPred, DfNodeType, DfPredType, DfReprPredNode, JoinType,
+    ListPred, PhysicalHashJoin,
+};
+use crate::properties::schema::{Catalog, SourceFormat};
+use crate::{OptimizerExt, TableId};
 
 #[derive(Debug, Clone)]
 pub struct DfStatistics {
     pub row_cnt: f64,
+    /// The provenance of the cardinality hint the row count comes from, if any.
+    pub hint: Option<String>,
+    /// The fraction of the rows whose mark is true, for the output of a mark join, whose last
+    /// column is the mark.
+    pub mark_selectivity: Option<f64>,
 }
 
 pub struct DfCostModel {
-    table_stat: HashMap<String, usize>,
+    /// The row counts of the tables, by the names of their scans.
+    table_stat: HashMap<TableId, usize>,
+    catalog: Option<Arc<dyn Catalog>>,
+    cardinality_hints: CardinalityHintStorage,
 }
 
 pub const COMPUTE_COST: usize = 0;
 pub const IO_COST: usize = 1;
 
 pub(crate) const DEFAULT_TABLE_ROW_CNT: usize = 1000;
+/// The number of rows a table function is assumed to produce for each input row.
+pub(crate) const DEFAULT_TABLE_FUNCTION_ROW_CNT: f64 = 10.0;
+const FILTER_SELECTIVITY: f64 = 0.01;
+/// The fraction of the rows of a table a scan reads when its partitions are pruned.
+const PRUNED_PARTITION_FRACTION: f64 = 0.1;
+/// The fraction of the rows of a table a scan reads when it looks them up in an index.
+const INDEX_LOOKUP_FRACTION: f64 = 0.01;
+/// How much more reading a row looked up in an index costs than reading it in a full scan.
+const INDEX_LOOKUP_IO_FACTOR: f64 = 4.0;
+/// How much more reading a row from a file of the format costs than reading it from memory.
+const ARROW_IO_FACTOR: f64 = 1.5;
+const PARQUET_IO_FACTOR: f64 = 2.0;
+const CSV_IO_FACTOR: f64 = 8.0;
+const JSON_IO_FACTOR: f64 = 10.0;
+/// The number of input rows a hash aggregation can hold in memory.
+const HASH_AGG_MEMORY_ROW_CNT: f64 = 1_000_000.0;
+/// How much more a row spilled by a hash aggregation costs, as it is written out and read back.
+const HASH_AGG_SPILL_IO_FACTOR: f64 = 20.0;
+const JOIN_SELECTIVITY: f64 = 0.01;
+const EMPTY_RELATION_ROW_CNT: f64 = 0.01;
 
 impl DfCostModel {
     pub fn compute_cost(Cost(cost): &Cost) -> f64 {
@@ -42,23 +77,184 @@ impl DfCostModel {
         Cost(vec![compute_cost, io_cost])
     }
 
+    /// The io cost of the rows a hash aggregation over `row_cnt` rows cannot hold in memory, which
+    /// makes a sort followed by a streaming aggregation cheaper for large inputs.
+    pub fn hash_agg_spill_io_cost(row_cnt: f64) -> f64 {
+        (row_cnt - HASH_AGG_MEMORY_ROW_CNT).max(0.0) * HASH_AGG_SPILL_IO_FACTOR
+    }
+
     pub fn stat(row_cnt: f64) -> Statistics {
-        Statistics(Box::new(DfStatistics { row_cnt }))
+        Statistics(Box::new(DfStatistics {
+            row_cnt,
+            hint: None,
+            mark_selectivity: None,
+        }))
+    }
+
+    /// The statistics of a mark join, with the fraction of its rows that have a match.
+    pub fn mark_join_stat(row_cnt: f64, mark_selectivity: f64) -> Statistics {
+        Statistics(Box::new(DfStatistics {
+            row_cnt,
+            hint: None,
+            mark_selectivity: Some(mark_selectivity),
+        }))
+    }
+
+    /// The fraction of the rows whose mark is true, if the statistics are of a mark join.
+    pub fn mark_selectivity(Statistics(stat): &Statistics) -> Option<f64> {
+        stat.downcast_ref::<DfStatistics>()
+            .unwrap()
+            .mark_selectivity
+    }
+
+    /// The statistics of a filter whose row count comes from a cardinality hint.
+    pub fn hinted_stat(row_cnt: f64, provenance: String) -> Statistics {
+        Statistics(Box::new(DfStatistics {
+            row_cnt,
+            hint: Some(provenance),
+            mark_selectivity: None,
+        }))
+    }
+
+    /// The statistics of a filter from the cardinality hint of its predicate, if there is one.
+    pub fn hinted_filter_stat(
+        &self,
+        input_row_cnt: f64,
+        pred: &ArcDfPredNode,
+        context: &RelNodeContext,
+        optimizer: &CascadesOptimizer<DfNodeType>,
+    ) -> Option<Statistics> {
+        let hints = self.cardinality_hints.lock().unwrap();
+        if hints.is_empty() {
+            return None;
+        }
+        let column_refs = optimizer.get_column_ref_of(context.group_id.into());
+        let hint = hints.lookup(pred, column_refs.base_table_column_refs())?;
+        Some(Self::hinted_stat(
+            (input_row_cnt * hint.selectivity).max(1.0),
+            hint.provenance.clone(),
+        ))
     }
 
     pub fn cost_tuple(Cost(cost): &Cost) -> (f64, f64) {
         (cost[COMPUTE_COST], cost[IO_COST])
     }
 
+    /// The fraction of the rows of its table a scan reads, which is less than all rows if the
+    /// scan has partition filters or an index lookup.
+    pub fn scan_fraction(predicates: &[ArcDfPredNode]) -> f64 {
+        let mut fraction = 1.0;
+        if predicates
+            .get(1)
+            .is_some_and(|partition_filters| !partition_filters.children.is_empty())
+        {
+            fraction *= PRUNED_PARTITION_FRACTION;
+        }
+        if predicates.len() > 2 {
+            fraction *= INDEX_LOOKUP_FRACTION;
+        }
+        fraction
+    }
+
+    /// The cost of reading a row of a scan relative to a full scan.
+    pub fn scan_io_factor(predicates: &[ArcDfPredNode]) -> f64 {
+        if predicates.len() > 2 {
+            INDEX_LOOKUP_IO_FACTOR
+        } else {
+            1.0
+        }
+    }
+
+    /// The cost of reading a row of a table stored in the format relative to reading it from
+    /// memory: the files have to be read and decoded, and the text formats parsed.
+    pub fn format_io_factor(format: SourceFormat) -> f64 {
+        match format {
+            SourceFormat::Unknown | SourceFormat::Memory => 1.0,
+            SourceFormat::Arrow => ARROW_IO_FACTOR,
+            SourceFormat::Parquet => PARQUET_IO_FACTOR,
+            SourceFormat::Csv => CSV_IO_FACTOR,
+            SourceFormat::Json => JSON_IO_FACTOR,
+        }
+    }
+
+    /// The io cost of a scan returning `row_cnt` rows, which depends on the format of its table,
+    /// if the catalog is known, and on whether the scan looks the rows up in an index. Storages
+    /// without filter pushdown cannot skip the rows the lookup filters rule out, so they read
+    /// every row of the scanned partitions instead.
+    pub fn scan_io_cost(&self, row_cnt: f64, predicates: &[ArcDfPredNode]) -> f64 {
+        let capabilities = self.catalog.as_ref().map(|catalog| {
+            let table_name = ConstantPred::from_pred_node(predicates[0].clone())
+                .unwrap()
+                .value()
+                .as_str();
+            catalog.scan_capabilities(&TableId::new(&table_name))
+        });
+        let format = capabilities.map_or(SourceFormat::Unknown, |capabilities| capabilities.format);
+        if predicates.len() > 2
+            && capabilities.is_some_and(|capabilities| !capabilities.filter_pushdown)
+        {
+            return row_cnt / INDEX_LOOKUP_FRACTION * Self::format_io_factor(format);
+        }
+        row_cnt * Self::scan_io_factor(predicates) * Self::format_io_factor(format)
+    }
+
+    /// The row count of the table of a scan given by the hints, for tables without statistics.
+    pub fn hinted_table_row_cnt(&self, predicates: &[ArcDfPredNode]) -> Option<f64> {
+        let table_name = ConstantPred::from_pred_node(predicates[0].clone())
+            .unwrap()
+            .value()
+            .as_str();
+        let hints = self.cardinality_hints.lock().unwrap();
+        hints.table_rows(&table_name).map(|rows| rows as f64)
+    }
+
     fn get_row_cnt(&self, predicates: &[ArcDfPredNode]) -> f64 {
         let table_name = ConstantPred::from_pred_node(predicates[0].clone())
             .unwrap()
             .value()
             .as_str();
-        self.table_stat
-            .get(table_name.as_ref())
-            .copied()
-            .unwrap_or(DEFAULT_TABLE_ROW_CNT) as f64
+        let row_cnt = match self.table_stat.get(&TableId::new(&table_name)) {
+            Some(row_cnt) => *row_cnt as f64,
+            None => self
+                .hinted_table_row_cnt(predicates)
+                .unwrap_or(DEFAULT_TABLE_ROW_CNT as f64),
+        };
+        row_cnt * Self::scan_fraction(predicates)
+    }
+
+    /// Whether the projection only selects columns of a scanned table whose storage reads
+    /// just the selected columns, so that the projection is done by the scan itself.
+    fn is_pushed_down_projection(
+        &self,
+        exprs: &ArcDfPredNode,
+        context: &RelNodeContext,
+        optimizer: &CascadesOptimizer<DfNodeType>,
+    ) -> bool {
+        let Some(catalog) = &self.catalog else {
+            return false;
+        };
+        let exprs = ListPred::from_pred_node(exprs.clone()).unwrap();
+        if !exprs
+            .to_vec()
+            .iter()
+            .all(|expr| expr.typ == DfPredType::ColumnRef)
+        {
+            return false;
+        }
+        let memo = optimizer.memo();
+        memo.get_all_exprs_in_group(context.children_group_ids[0])
+            .into_iter()
+            .map(|expr_id| memo.get_expr_memoed(expr_id))
+            .filter(|expr| matches!(expr.typ, DfNodeType::Scan | DfNodeType::PhysicalScan))
+            .any(|expr| {
+                let table_name = ConstantPred::from_pred_node(memo.get_pred(expr.predicates[0]))
+                    .unwrap()
+                    .value()
+                    .as_str();
+                catalog
+                    .scan_capabilities(&TableId::new(&table_name))
+                    .projection_pushdown
+            })
     }
 }
 
@@ -71,8 +267,23 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for DfCostModel {
         )
     }
 
-    fn explain_statistics(&self, stat: &Statistics) -> String {
-        format!("{{row_cnt={}}}", Self::row_cnt(stat))
+    fn explain_statistics(&self, Statistics(stat): &Statistics) -> String {
+        let stat = stat.downcast_ref::<DfStatistics>().unwrap();
+        match &stat.hint {
+            Some(provenance) => format!("{{row_cnt={},hint={}}}", stat.row_cnt, provenance),
+            None => format!("{{row_cnt={}}}", stat.row_cnt),
+        }
+    }
+
+    fn estimated_row_cnt(&self, stat: &Statistics) -> Option<f64> {
+        Some(Self::row_cnt(stat))
+    }
+
+    fn cost_components(&self, cost: &Cost) -> Vec<(String, f64)> {
+        vec![
+            ("compute".to_string(), Self::compute_cost(cost)),
+            ("io".to_string(), Self::io_cost(cost)),
+        ]
     }
 
     fn accumulate(&self, total_cost: &mut Cost, cost: &Cost) {
@@ -89,8 +300,8 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for DfCostModel {
         node: &DfNodeType,
         predicates: &[ArcDfPredNode],
         children: &[&Statistics],
-        _context: RelNodeContext,
-        _optimizer: &CascadesOptimizer<DfNodeType>,
+        context: RelNodeContext,
+        optimizer: &CascadesOptimizer<DfNodeType>,
     ) -> Statistics {
         match node {
             DfNodeType::PhysicalScan => {
@@ -101,24 +312,38 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for DfCostModel {
                 let row_cnt = Self::row_cnt(children[0]);
                 Self::stat(row_cnt.max(1.0))
             }
-            DfNodeType::PhysicalEmptyRelation => Self::stat(0.01),
+            DfNodeType::PhysicalEmptyRelation => Self::stat(EMPTY_RELATION_ROW_CNT),
+            DfNodeType::PhysicalTableFunction => {
+                let row_cnt = Self::row_cnt(children[0]).max(1.0);
+                Self::stat(row_cnt * DEFAULT_TABLE_FUNCTION_ROW_CNT)
+            }
+            DfNodeType::PhysicalSingleton => Self::stat(1.0),
             DfNodeType::PhysicalFilter => {
                 let row_cnt = Self::row_cnt(children[0]);
-                let selectivity = 0.01;
-                Self::stat((row_cnt * selectivity).max(1.0))
+                self.hinted_filter_stat(row_cnt, &predicates[0], &context, optimizer)
+                    .unwrap_or_else(|| Self::stat((row_cnt * FILTER_SELECTIVITY).max(1.0)))
+            }
+            // A mark join outputs every row of the left side once, with whether it has a match.
+            DfNodeType::PhysicalNestedLoopJoin(JoinType::LeftMark)
+            | DfNodeType::PhysicalHashJoin(JoinType::LeftMark) => {
+                Self::stat(Self::row_cnt(children[0]).max(1.0))
             }
             DfNodeType::PhysicalNestedLoopJoin(_) => {
                 let row_cnt_1 = Self::row_cnt(children[0]);
                 let row_cnt_2 = Self::row_cnt(children[1]);
-                let selectivity = 0.01;
-                Self::stat((row_cnt_1 * row_cnt_2 * selectivity).max(1.0))
+                Self::stat((row_cnt_1 * row_cnt_2 * JOIN_SELECTIVITY).max(1.0))
             }
             DfNodeType::PhysicalHashJoin(_) => {
                 let row_cnt_1 = Self::row_cnt(children[0]);
                 let row_cnt_2 = Self::row_cnt(children[1]);
                 Self::stat(row_cnt_1.min(row_cnt_2).max(1.0))
             }
-            DfNodeType::PhysicalSort | DfNodeType::PhysicalAgg | DfNodeType::PhysicalProjection => {
+            DfNodeType::PhysicalSort
+            | DfNodeType::PhysicalAgg
+            | DfNodeType::PhysicalStreamAgg
+            | DfNodeType::PhysicalPartialAgg
+            | DfNodeType::PhysicalFinalAgg
+            | DfNodeType::PhysicalProjection => {
                 let row_cnt = Self::row_cnt(children[0]);
                 Self::stat(row_cnt)
             }
@@ -131,8 +356,8 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for DfCostModel {
         node: &DfNodeType,
         predicates: &[ArcDfPredNode],
         children: &[Option<&Statistics>],
-        _context: RelNodeContext,
-        _optimizer: &CascadesOptimizer<DfNodeType>,
+        context: RelNodeContext,
+        optimizer: &CascadesOptimizer<DfNodeType>,
     ) -> Cost {
         let row_cnts = children
             .iter()
@@ -141,13 +366,19 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for DfCostModel {
         match node {
             DfNodeType::PhysicalScan => {
                 let row_cnt = self.get_row_cnt(predicates);
-                Self::cost(0.0, row_cnt)
+                Self::cost(0.0, self.scan_io_cost(row_cnt, predicates))
             }
             DfNodeType::PhysicalLimit => {
                 let row_cnt = row_cnts[0];
                 Self::cost(row_cnt, 0.0)
             }
-            DfNodeType::PhysicalEmptyRelation => Self::cost(0.01, 0.0),
+            DfNodeType::PhysicalEmptyRelation => Self::cost(EMPTY_RELATION_ROW_CNT, 0.0),
+            DfNodeType::PhysicalTableFunction => {
+                let row_cnt = row_cnts[0].max(1.0);
+                Self::cost(row_cnt * DEFAULT_TABLE_FUNCTION_ROW_CNT, 0.0)
+            }
+            // The row is materialized once, whichever join reads it.
+            DfNodeType::PhysicalSingleton => Self::cost(1.0, 0.0),
             DfNodeType::PhysicalFilter => {
                 let row_cnt = row_cnts[0];
                 let (compute_cost, _) = Self::cost_tuple(&derive_pred_cost(&predicates[0]));
@@ -160,14 +391,22 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for DfCostModel {
                 Self::cost(row_cnt_1 * row_cnt_2 * compute_cost + row_cnt_1, 0.0)
             }
             DfNodeType::PhysicalProjection => {
+                if self.is_pushed_down_projection(&predicates[0], &context, optimizer) {
+                    // The unused columns are never read, which is not the case for row-oriented
+                    // formats where the projection has to be done on the full rows.
+                    return Self::cost(0.0, 0.0);
+                }
                 let row_cnt = row_cnts[0];
                 let (compute_cost, _) = Self::cost_tuple(&derive_pred_cost(&predicates[0]));
                 Self::cost(row_cnt * compute_cost, 0.0)
             }
             DfNodeType::PhysicalHashJoin(_) => {
-                let row_cnt_1 = row_cnts[0];
-                let row_cnt_2 = row_cnts[1];
-                Self::cost(row_cnt_1 * 2.0 + row_cnt_2, 0.0)
+                let (build_row_cnt, probe_row_cnt) =
+                    match PhysicalHashJoin::build_side_of(predicates) {
+                        BuildSide::Left => (row_cnts[0], row_cnts[1]),
+                        BuildSide::Right => (row_cnts[1], row_cnts[0]),
+                    };
+                Self::cost(build_row_cnt * 2.0 + probe_row_cnt, 0.0)
             }
             DfNodeType::PhysicalSort => {
                 let row_cnt = row_cnts[0];
@@ -177,7 +416,42 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for DfCostModel {
                 let row_cnt = row_cnts[0];
                 let (compute_cost_1, _) = Self::cost_tuple(&derive_pred_cost(&predicates[0]));
                 let (compute_cost_2, _) = Self::cost_tuple(&derive_pred_cost(&predicates[1]));
-                Self::cost(row_cnt * (compute_cost_1 + compute_cost_2), 0.0)
+                Self::cost(
+                    row_cnt * (compute_cost_1 + compute_cost_2),
+                    Self::hash_agg_spill_io_cost(row_cnt),
+                )
+            }
+            DfNodeType::PhysicalStreamAgg => {
+                // The input is sorted on the group-by keys, so each row is only compared with
+                // the current group instead of being hashed into a table.
+                let row_cnt = row_cnts[0];
+                let (compute_cost, _) = Self::cost_tuple(&derive_pred_cost(&predicates[0]));
+                Self::cost(row_cnt * (compute_cost + 1.0), 0.0)
+            }
+            DfNodeType::PhysicalPartialAgg => {
+                // Each partition is aggregated in parallel.
+                let row_cnt = row_cnts[0];
+                let partitions = ConstantPred::from_pred_node(predicates[2].clone())
+                    .unwrap()
+                    .value()
+                    .as_u64()
+                    .max(1) as f64;
+                let (compute_cost_1, _) = Self::cost_tuple(&derive_pred_cost(&predicates[0]));
+                let (compute_cost_2, _) = Self::cost_tuple(&derive_pred_cost(&predicates[1]));
+                Self::cost(
+                    row_cnt * (compute_cost_1 + compute_cost_2) / partitions,
+                    0.0,
+                )
+            }
+            DfNodeType::PhysicalFinalAgg => {
+                // The aggregate expressions have already been evaluated by the partial
+                // aggregation, so only their states need to be merged.
+                let row_cnt = row_cnts[0];
+                let agg_cnt = ListPred::from_pred_node(predicates[0].clone())
+                    .unwrap()
+                    .len();
+                let (compute_cost, _) = Self::cost_tuple(&derive_pred_cost(&predicates[1]));
+                Self::cost(row_cnt * (compute_cost + agg_cnt as f64), 0.0)
             }
             x => unimplemented!("cannot compute cost for {}", x),
         }
@@ -186,6 +460,95 @@ impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for DfCostModel {
     fn weighted_cost(&self, cost: &Cost) -> f64 {
         Self::compute_cost(cost) + Self::io_cost(cost)
     }
+
+    fn describe(&self) -> Vec<CostFormula> {
+        let mut scan = CostFormula::new(
+            "PhysicalScan",
+            "io = rows * the io factor of the format of the table, times index_io_factor for index lookups, which read the rows of the scanned partitions unless the storage supports filter pushdown",
+            "table_rows, or the row hint of the table, times pruned_fraction if the partitions are pruned, times index_fraction for index lookups",
+        )
+        .with_constant("default_table_rows", DEFAULT_TABLE_ROW_CNT as f64)
+        .with_constant("pruned_fraction", PRUNED_PARTITION_FRACTION)
+        .with_constant("index_fraction", INDEX_LOOKUP_FRACTION)
+        .with_constant("index_io_factor", INDEX_LOOKUP_IO_FACTOR)
+        .with_constant("arrow_io_factor", ARROW_IO_FACTOR)
+        .with_constant("parquet_io_factor", PARQUET_IO_FACTOR)
+        .with_constant("csv_io_factor", CSV_IO_FACTOR)
+        .with_constant("json_io_factor", JSON_IO_FACTOR);
+        for (table, row_cnt) in self.table_stat.iter().sorted() {
+            scan = scan.with_constant(format!("{table}.rows"), *row_cnt as f64);
+        }
+        vec![
+            scan,
+            CostFormula::new(
+                "PhysicalLimit",
+                "compute = input_rows",
+                "max(input_rows, 1)",
+            ),
+            CostFormula::new(
+                "PhysicalEmptyRelation",
+                "compute = empty_rows",
+                "empty_rows",
+            )
+            .with_constant("empty_rows", EMPTY_RELATION_ROW_CNT),
+            CostFormula::new(
+                "PhysicalFilter",
+                "compute = input_rows * pred_cost",
+                "max(input_rows * selectivity, 1), with the selectivity of the cardinality hint of the predicate if any",
+            )
+            .with_constant("selectivity", FILTER_SELECTIVITY),
+            CostFormula::new(
+                "PhysicalNestedLoopJoin",
+                "compute = left_rows * right_rows * pred_cost + left_rows",
+                "max(left_rows * right_rows * selectivity, 1), or max(left_rows, 1) for mark joins",
+            )
+            .with_constant("selectivity", JOIN_SELECTIVITY),
+            CostFormula::new(
+                "PhysicalProjection",
+                "compute = input_rows * exprs_cost, or 0 for columns pruned by the scan",
+                "input_rows",
+            ),
+            CostFormula::new(
+                "PhysicalHashJoin",
+                "compute = 2 * build_rows + probe_rows, building on the left input unless marked",
+                "max(min(left_rows, right_rows), 1), or max(left_rows, 1) for mark joins",
+            ),
+            CostFormula::new(
+                "PhysicalSort",
+                "compute = input_rows * max(ln(1 + input_rows), 1)",
+                "input_rows",
+            ),
+            CostFormula::new(
+                "PhysicalAgg",
+                "compute = input_rows * (aggrs_cost + groups_cost), io = max(input_rows - memory_rows, 0) * spill_io_factor",
+                "input_rows",
+            )
+            .with_constant("memory_rows", HASH_AGG_MEMORY_ROW_CNT)
+            .with_constant("spill_io_factor", HASH_AGG_SPILL_IO_FACTOR),
+            CostFormula::new(
+                "PhysicalStreamAgg",
+                "compute = input_rows * (aggrs_cost + 1)",
+                "input_rows",
+            ),
+            CostFormula::new(
+                "PhysicalPartialAgg",
+                "compute = input_rows * (aggrs_cost + groups_cost) / partitions",
+                "input_rows",
+            ),
+            CostFormula::new(
+                "PhysicalFinalAgg",
+                "compute = input_rows * (groups_cost + aggr_cnt)",
+                "input_rows",
+            ),
+            CostFormula::new(
+                "PhysicalTableFunction",
+                "compute = max(input_rows, 1) * rows_per_input_row",
+                "max(input_rows, 1) * rows_per_input_row",
+            )
+            .with_constant("rows_per_input_row", DEFAULT_TABLE_FUNCTION_ROW_CNT),
+            CostFormula::new("PhysicalSingleton", "compute = 1", "1"),
+        ]
+    }
 }
 
 fn derive_pred_cost(pred: &ArcDfPredNode) -> Cost {
@@ -202,6 +565,118 @@ fn derive_pred_cost(pred: &ArcDfPredNode) -> Cost {
 
 impl DfCostModel {
     pub fn new(table_stat: HashMap<String, usize>) -> Self {
-        Self { table_stat }
+        Self {
+            table_stat: table_stat
+                .into_iter()
+                .map(|(table, row_cnt)| (TableId::from(table), row_cnt))
+                .collect(),
+            catalog: None,
+            cardinality_hints: Arc::new(Mutex::new(CardinalityHints::default())),
+        }
+    }
+
+    /// Uses the catalog to tell whether operators pushed into a scan reduce the data read, and
+    /// the formats the tables are read from.
+    pub fn with_catalog(mut self, catalog: Arc<dyn Catalog>) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// Consults the given cardinality hints when estimating the row count of a filter.
+    pub fn with_cardinality_hints(mut self, cardinality_hints: CardinalityHintStorage) -> Self {
+        self.cardinality_hints = cardinality_hints;
+        self
+    }
+
+    pub fn get_cardinality_hints(&self) -> CardinalityHintStorage {
+        self.cardinality_hints.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::{DfReprPlanNode, LogicalScan};
+    use crate::properties::schema::{ScanCapabilities, Schema};
+    use crate::testing::TpchCatalog;
+
+    /// The TPC-H catalog where `orders` is stored in CSV files and `customer` in memory.
+    struct FormatCatalog;
+
+    impl Catalog for FormatCatalog {
+        fn get(&self, name: &TableId) -> Schema {
+            TpchCatalog.get(name)
+        }
+
+        fn scan_capabilities(&self, name: &TableId) -> ScanCapabilities {
+            let format = match name.as_str() {
+                "orders" => SourceFormat::Csv,
+                "customer" => SourceFormat::Memory,
+                _ => SourceFormat::Unknown,
+            };
+            ScanCapabilities {
+                projection_pushdown: format == SourceFormat::Memory,
+                filter_pushdown: format == SourceFormat::Memory,
+                format,
+            }
+        }
+    }
+
+    fn scan_predicates(table: &str) -> Vec<ArcDfPredNode> {
+        vec![ConstantPred::string(table).into_pred_node()]
+    }
+
+    #[test]
+    fn scan_io_cost_by_format() {
+        let cost_model = DfCostModel::new(HashMap::new()).with_catalog(Arc::new(FormatCatalog));
+        assert_eq!(
+            cost_model.scan_io_cost(100.0, &scan_predicates("orders")),
+            100.0 * CSV_IO_FACTOR
+        );
+        assert_eq!(
+            cost_model.scan_io_cost(100.0, &scan_predicates("customer")),
+            100.0
+        );
+        assert_eq!(
+            cost_model.scan_io_cost(100.0, &scan_predicates("region")),
+            100.0
+        );
+        // without a catalog, the formats are unknown
+        assert_eq!(
+            DfCostModel::new(HashMap::new()).scan_io_cost(100.0, &scan_predicates("orders")),
+            100.0
+        );
+    }
+
+    #[test]
+    fn index_lookup_io_cost_by_filter_pushdown() {
+        let cost_model = DfCostModel::new(HashMap::new()).with_catalog(Arc::new(FormatCatalog));
+        let lookup_predicates = |table: &str| {
+            let scan = LogicalScan::new(table.to_string())
+                .with_index_lookup("idx".to_string(), ListPred::new(vec![]));
+            scan.into_plan_node().predicates.clone()
+        };
+        assert_eq!(
+            cost_model.scan_io_cost(100.0, &lookup_predicates("customer")),
+            100.0 * INDEX_LOOKUP_IO_FACTOR
+        );
+        // the CSV files are read in full, as the lookup filters cannot be pushed into them
+        assert_eq!(
+            cost_model.scan_io_cost(100.0, &lookup_predicates("orders")),
+            100.0 / INDEX_LOOKUP_FRACTION * CSV_IO_FACTOR
+        );
+    }
+
+    #[test]
+    fn hash_agg_spills_beyond_memory() {
+        assert_eq!(DfCostModel::hash_agg_spill_io_cost(1000.0), 0.0);
+        assert_eq!(
+            DfCostModel::hash_agg_spill_io_cost(HASH_AGG_MEMORY_ROW_CNT),
+            0.0
+        );
+        assert_eq!(
+            DfCostModel::hash_agg_spill_io_cost(HASH_AGG_MEMORY_ROW_CNT + 10.0),
+            10.0 * HASH_AGG_SPILL_IO_FACTOR
+        );
     }
 }
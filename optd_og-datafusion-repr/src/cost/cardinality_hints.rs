@@ -0,0 +1,296 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::plan_nodes::{ArcDfPredNode, ColumnRefPred, DfPredType, DfReprPredNode};
+use crate::properties::column_ref::{BaseTableColumnRef, BaseTableColumnRefs, ColumnRef};
+use crate::TableId;
+
+pub type CardinalityHintStorage = Arc<Mutex<CardinalityHints>>;
+
+/// A correction of the estimated cardinality of a filter, e.g., learned offline from the
+/// workload logs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CardinalityHint {
+    /// The fraction of the input rows of the filter that the predicate keeps.
+    pub selectivity: f64,
+    /// Where the hint comes from, shown in the verbose explain.
+    pub provenance: String,
+}
+
+/// The selectivities set in the session for the predicates without statistics, which override
+/// the defaults of the cost model, e.g., to be more pessimistic for a workload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DefaultSelectivityOverrides {
+    /// Of the equalities, e.g., `a = 1`.
+    pub eq: Option<f64>,
+    /// Of the range comparisons, e.g., `a < 1`.
+    pub ineq: Option<f64>,
+    /// Of the predicates nothing is known about.
+    pub unk: Option<f64>,
+}
+
+/// The cardinality hints of the filters over a single table, keyed by the table and the
+/// fingerprint of the predicate, see [`predicate_fingerprint`]. The cost model consults them
+/// before estimating the selectivity of a filter.
+///
+/// Also holds the row counts of the tables without statistics, which the cost model uses
+/// instead of its default row count, and the overrides of its default selectivities.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CardinalityHints {
+    hints: BTreeMap<TableId, BTreeMap<String, CardinalityHint>>,
+    #[serde(default)]
+    table_rows: BTreeMap<TableId, usize>,
+    #[serde(default)]
+    default_selectivities: DefaultSelectivityOverrides,
+}
+
+impl CardinalityHints {
+    /// Insert or replace the hint of a predicate over `table`, returning the replaced hint.
+    pub fn upsert(
+        &mut self,
+        table: impl Into<String>,
+        fingerprint: impl Into<String>,
+        hint: CardinalityHint,
+    ) -> Option<CardinalityHint> {
+        self.hints
+            .entry(TableId::from(table.into()))
+            .or_default()
+            .insert(fingerprint.into(), hint)
+    }
+
+    pub fn remove(&mut self, table: &str, fingerprint: &str) -> Option<CardinalityHint> {
+        let table = TableId::new(table);
+        let hints = self.hints.get_mut(&table)?;
+        let hint = hints.remove(fingerprint);
+        if hints.is_empty() {
+            self.hints.remove(&table);
+        }
+        hint
+    }
+
+    /// Remove all hints of `table`, e.g., after its data changed.
+    pub fn remove_table(&mut self, table: &str) {
+        self.hints.remove(&TableId::new(table));
+    }
+
+    pub fn get(&self, table: &str, fingerprint: &str) -> Option<&CardinalityHint> {
+        self.hints.get(&TableId::new(table))?.get(fingerprint)
+    }
+
+    /// The hints as `(table, fingerprint, hint)`, ordered by table and fingerprint.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, &CardinalityHint)> {
+        self.hints.iter().flat_map(|(table, hints)| {
+            hints
+                .iter()
+                .map(move |(fingerprint, hint)| (table.as_str(), fingerprint.as_str(), hint))
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.hints.values().map(|hints| hints.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    pub fn table_rows(&self, table: &str) -> Option<usize> {
+        self.table_rows.get(&TableId::new(table)).copied()
+    }
+
+    pub fn set_table_rows(&mut self, table: impl Into<String>, rows: usize) {
+        self.table_rows.insert(TableId::from(table.into()), rows);
+    }
+
+    /// Replace the row counts of all tables, e.g., with the ones of the session config.
+    pub fn replace_table_rows(&mut self, table_rows: impl IntoIterator<Item = (String, usize)>) {
+        self.table_rows = table_rows
+            .into_iter()
+            .map(|(table, rows)| (TableId::from(table), rows))
+            .collect();
+    }
+
+    pub fn default_selectivities(&self) -> DefaultSelectivityOverrides {
+        self.default_selectivities
+    }
+
+    /// Replace the overrides of the default selectivities, e.g., with the ones of the session
+    /// config.
+    pub fn set_default_selectivities(
+        &mut self,
+        default_selectivities: DefaultSelectivityOverrides,
+    ) {
+        self.default_selectivities = default_selectivities;
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The hint of the predicate of a filter whose input has the columns `column_refs`.
+    pub fn lookup(
+        &self,
+        pred: &ArcDfPredNode,
+        column_refs: &BaseTableColumnRefs,
+    ) -> Option<&CardinalityHint> {
+        let (table, fingerprint) = predicate_fingerprint(pred, column_refs)?;
+        self.get(&table, &fingerprint)
+    }
+}
+
+/// The table a predicate is over and its fingerprint, which does not depend on the plan it
+/// appears in: the columns are referred to by their index in the table, and the operands of
+/// `AND` and `OR` are sorted. Returns `None` if the predicate does not refer to the columns of
+/// exactly one table.
+pub fn predicate_fingerprint(
+    pred: &ArcDfPredNode,
+    column_refs: &BaseTableColumnRefs,
+) -> Option<(String, String)> {
+    let mut table = None;
+    let fingerprint = fingerprint(pred, column_refs, &mut table)?;
+    Some((table?, fingerprint))
+}
+
+fn fingerprint(
+    pred: &ArcDfPredNode,
+    column_refs: &BaseTableColumnRefs,
+    table: &mut Option<String>,
+) -> Option<String> {
+    match &pred.typ {
+        DfPredType::ColumnRef => {
+            let idx = ColumnRefPred::from_pred_node(pred.clone()).unwrap().index();
+            let ColumnRef::BaseTableColumnRef(BaseTableColumnRef {
+                table: col_table,
+                col_idx,
+            }) = column_refs.get(idx)?
+            else {
+                return None;
+            };
+            if *table.get_or_insert_with(|| col_table.to_string()) != **col_table {
+                return None;
+            }
+            Some(format!("#{}", col_idx))
+        }
+        DfPredType::ExternColumnRef => None,
+        typ => {
+            let mut children = pred
+                .children
+                .iter()
+                .map(|child| fingerprint(child, column_refs, table))
+                .collect::<Option<Vec<_>>>()?;
+            if let DfPredType::LogOp(_) = typ {
+                children.sort();
+            }
+            let mut fingerprint = format!("({}", typ);
+            for child in children {
+                fingerprint += " ";
+                fingerprint += &child;
+            }
+            if let Some(data) = &pred.data {
+                fingerprint += &format!(" {}", data);
+            }
+            Some(fingerprint + ")")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::{BinOpPred, BinOpType, ConstantPred, LogOpPred, LogOpType};
+
+    fn eq(col_idx: usize, value: i64) -> ArcDfPredNode {
+        BinOpPred::new(
+            ColumnRefPred::new(col_idx).into_pred_node(),
+            ConstantPred::int64(value).into_pred_node(),
+            BinOpType::Eq,
+        )
+        .into_pred_node()
+    }
+
+    #[test]
+    fn fingerprint_is_independent_of_the_plan() {
+        let t1 = vec![
+            ColumnRef::base_table_column_ref("t1".to_string(), 0),
+            ColumnRef::base_table_column_ref("t1".to_string(), 1),
+        ];
+        // The same columns of t1, behind a projection swapping them.
+        let t1_swapped = vec![t1[1].clone(), t1[0].clone()];
+        let pred = LogOpPred::new(LogOpType::And, vec![eq(0, 1), eq(1, 2)]).into_pred_node();
+        let pred_swapped =
+            LogOpPred::new(LogOpType::And, vec![eq(0, 2), eq(1, 1)]).into_pred_node();
+        let (table, fingerprint) = predicate_fingerprint(&pred, &t1).unwrap();
+        assert_eq!(table, "t1");
+        assert_eq!(
+            predicate_fingerprint(&pred_swapped, &t1_swapped),
+            Some((table, fingerprint))
+        );
+
+        let t1_t2 = vec![
+            t1[0].clone(),
+            ColumnRef::base_table_column_ref("t2".to_string(), 0),
+        ];
+        assert_eq!(predicate_fingerprint(&pred, &t1_t2), None);
+        assert_eq!(
+            predicate_fingerprint(&ConstantPred::bool(true).into_pred_node(), &t1),
+            None
+        );
+    }
+
+    #[test]
+    fn upsert_and_remove_hints() {
+        let column_refs = vec![ColumnRef::base_table_column_ref("t1".to_string(), 0)];
+        let pred = eq(0, 1);
+        let (table, fingerprint) = predicate_fingerprint(&pred, &column_refs).unwrap();
+        let hint = |selectivity| CardinalityHint {
+            selectivity,
+            provenance: "workload log".to_string(),
+        };
+
+        let mut hints = CardinalityHints::default();
+        assert_eq!(hints.upsert(&table, &fingerprint, hint(0.5)), None);
+        assert_eq!(
+            hints.upsert(&table, &fingerprint, hint(0.1)),
+            Some(hint(0.5))
+        );
+        assert_eq!(hints.lookup(&pred, &column_refs), Some(&hint(0.1)));
+        assert_eq!(hints.len(), 1);
+
+        let hints_json = serde_json::to_string(&hints).unwrap();
+        let mut hints: CardinalityHints = serde_json::from_str(&hints_json).unwrap();
+        assert_eq!(hints.remove(&table, &fingerprint), Some(hint(0.1)));
+        assert!(hints.is_empty());
+        assert_eq!(hints.lookup(&pred, &column_refs), None);
+    }
+
+    #[test]
+    fn table_row_hints() {
+        let mut hints = CardinalityHints::default();
+        hints.set_table_rows("lineitem", 6000000);
+        assert_eq!(hints.table_rows("lineitem"), Some(6000000));
+        assert_eq!(hints.table_rows("orders"), None);
+
+        hints.replace_table_rows(vec![("orders".to_string(), 1500000)]);
+        assert_eq!(hints.table_rows("lineitem"), None);
+        assert_eq!(hints.table_rows("orders"), Some(1500000));
+        assert_eq!(hints.table_rows("ORDERS"), Some(1500000));
+        assert_eq!(hints.table_rows("\"Orders\""), None);
+        // hints saved before the table row counts existed can still be loaded
+        let hints: CardinalityHints = serde_json::from_str(r#"{"hints":{}}"#).unwrap();
+        assert!(hints.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::Arc;
+
+use optd_og_core::cascades::{CascadesOptimizer, NaiveMemo, RelNodeContext};
+use optd_og_core::cost::{Cost, CostFormula, CostModel, Statistics};
+
+use super::base_cost::COMPUTE_COST;
+use crate::cost::DfCostModel;
+use crate::plan_nodes::{ArcDfPredNode, DfNodeType};
+
+/// Added to the cost of a nested loop join over two inputs above the threshold. It is large
+/// enough to lose against any other join, but finite, so that such a join is still chosen if it
+/// is the only option and the costs of the alternatives can be compared.
+const INFEASIBLE_NLJ_COST: f64 = 1e20;
+
+/// Wraps a cost model to avoid nested loop joins whose inputs both have more rows than the
+/// threshold, see [`crate::DatafusionOptimizer::set_nlj_row_threshold`].
+pub struct NljRowThresholdCostModel {
+    base_model: Arc<dyn CostModel<DfNodeType, NaiveMemo<DfNodeType>>>,
+    threshold: usize,
+}
+
+impl NljRowThresholdCostModel {
+    pub fn new(
+        base_model: Arc<dyn CostModel<DfNodeType, NaiveMemo<DfNodeType>>>,
+        threshold: usize,
+    ) -> Self {
+        Self {
+            base_model,
+            threshold,
+        }
+    }
+
+    /// Whether a nested loop join over inputs with these row counts should be avoided.
+    pub fn exceeds_threshold(threshold: usize, left_rows: f64, right_rows: f64) -> bool {
+        left_rows > threshold as f64 && right_rows > threshold as f64
+    }
+}
+
+impl CostModel<DfNodeType, NaiveMemo<DfNodeType>> for NljRowThresholdCostModel {
+    fn explain_cost(&self, cost: &Cost) -> String {
+        self.base_model.explain_cost(cost)
+    }
+
+    fn explain_statistics(&self, cost: &Statistics) -> String {
+        self.base_model.explain_statistics(cost)
+    }
+
+    fn estimated_row_cnt(&self, stat: &Statistics) -> Option<f64> {
+        self.base_model.estimated_row_cnt(stat)
+    }
+
+    fn cost_components(&self, cost: &Cost) -> Vec<(String, f64)> {
+        self.base_model.cost_components(cost)
+    }
+
+    fn accumulate(&self, total_cost: &mut Cost, cost: &Cost) {
+        self.base_model.accumulate(total_cost, cost)
+    }
+
+    fn sum(&self, operation_cost: &Cost, inputs_cost: &[Cost]) -> Cost {
+        self.base_model.sum(operation_cost, inputs_cost)
+    }
+
+    fn zero(&self) -> Cost {
+        self.base_model.zero()
+    }
+
+    fn weighted_cost(&self, cost: &Cost) -> f64 {
+        self.base_model.weighted_cost(cost)
+    }
+
+    fn compute_operation_cost(
+        &self,
+        node: &DfNodeType,
+        predicates: &[ArcDfPredNode],
+        children: &[Option<&Statistics>],
+        context: RelNodeContext,
+        optimizer: &CascadesOptimizer<DfNodeType>,
+    ) -> Cost {
+        let mut cost = self
+            .base_model
+            .compute_operation_cost(node, predicates, children, context, optimizer);
+        if let (DfNodeType::PhysicalNestedLoopJoin(_), [Some(left), Some(right)]) = (node, children)
+        {
+            let (left_rows, right_rows) = (DfCostModel::row_cnt(left), DfCostModel::row_cnt(right));
+            if Self::exceeds_threshold(self.threshold, left_rows, right_rows) {
+                cost.0[COMPUTE_COST] += INFEASIBLE_NLJ_COST;
+            }
+        }
+        cost
+    }
+
+    fn derive_statistics(
+        &self,
+        node: &DfNodeType,
+        predicates: &[ArcDfPredNode],
+        children: &[&Statistics],
+        context: RelNodeContext,
+        optimizer: &CascadesOptimizer<DfNodeType>,
+    ) -> Statistics {
+        self.base_model
+            .derive_statistics(node, predicates, children, context, optimizer)
+    }
+
+    fn describe(&self) -> Vec<CostFormula> {
+        let mut formulas = self.base_model.describe();
+        for formula in &mut formulas {
+            if formula.operator == "PhysicalNestedLoopJoin" {
+                formula.cost += " + infeasible_cost if both inputs exceed row_threshold";
+                formula
+                    .constants
+                    .push(("row_threshold".to_string(), self.threshold as f64));
+                formula
+                    .constants
+                    .push(("infeasible_cost".to_string(), INFEASIBLE_NLJ_COST));
+            }
+        }
+        formulas
+    }
+
+    fn reset_caches(&self) {
+        self.base_model.reset_caches();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn describe_threshold() {
+        let cost_model =
+            NljRowThresholdCostModel::new(Arc::new(DfCostModel::new(HashMap::new())), 1000);
+        let formula = cost_model
+            .describe()
+            .into_iter()
+            .find(|formula| formula.operator == "PhysicalNestedLoopJoin")
+            .unwrap();
+        assert!(formula
+            .constants
+            .contains(&("row_threshold".to_string(), 1000.0)));
+        assert!(NljRowThresholdCostModel::exceeds_threshold(
+            1000, 1001.0, 5000.0
+        ));
+        assert!(!NljRowThresholdCostModel::exceeds_threshold(
+            1000, 1000.0, 5000.0
+        ));
+    }
+}
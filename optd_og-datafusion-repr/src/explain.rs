@@ -3,26 +3,28 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use optd_og_core::nodes::{PlanNodeMeta, PlanNodeMetaMap};
+use optd_og_core::nodes::{PlanAnnotation, PlanAnnotations};
 use pretty_xmlish::Pretty;
 
 use crate::plan_nodes::{
     ArcDfPlanNode, ArcDfPredNode, BetweenPred, BinOpPred, CastPred, ColumnRefPred, ConstantPred,
     DataTypePred, DependentJoin, DfNodeType, DfPredType, DfReprPlanNode, DfReprPredNode,
     ExternColumnRefPred, FuncPred, InListPred, LikePred, ListPred, LogOpPred, LogicalAgg,
-    LogicalEmptyRelation, LogicalFilter, LogicalJoin, LogicalLimit, LogicalProjection, LogicalScan,
-    LogicalSort, PhysicalAgg, PhysicalEmptyRelation, PhysicalFilter, PhysicalHashJoin,
-    PhysicalLimit, PhysicalNestedLoopJoin, PhysicalProjection, PhysicalScan, PhysicalSort,
-    RawDependentJoin, SortOrderPred, UnOpPred,
+    LogicalDistinct, LogicalEmptyRelation, LogicalFilter, LogicalJoin, LogicalLimit,
+    LogicalProjection, LogicalScan, LogicalSingleton, LogicalSort, LogicalTableFunction,
+    PhysicalAgg, PhysicalEmptyRelation, PhysicalFilter, PhysicalFinalAgg, PhysicalHashJoin,
+    PhysicalLimit, PhysicalNestedLoopJoin, PhysicalPartialAgg, PhysicalProjection,
+    PhysicalRuntimeFilter, PhysicalScan, PhysicalSingleton, PhysicalSort, PhysicalStreamAgg,
+    PhysicalTableFunction, RawDependentJoin, SortOrderPred, UnOpPred,
 };
 
 pub trait Insertable<'a> {
-    fn with_meta(self, meta: &PlanNodeMeta) -> Self;
+    fn with_meta(self, meta: &PlanAnnotation) -> Self;
 }
 
 impl<'a> Insertable<'a> for Vec<(&'a str, Pretty<'a>)> {
     // FIXME: this assumes we are using OptCostModel
-    fn with_meta(mut self, meta: &PlanNodeMeta) -> Self {
+    fn with_meta(mut self, meta: &PlanAnnotation) -> Self {
         self.push(("cost", Pretty::display(&meta.cost_display)));
         self.push(("stat", Pretty::display(&meta.stat_display)));
         self
@@ -31,7 +33,7 @@ impl<'a> Insertable<'a> for Vec<(&'a str, Pretty<'a>)> {
 
 pub fn explain_pred_node(
     node: ArcDfPredNode,
-    meta_map: Option<&PlanNodeMetaMap>,
+    meta_map: Option<&PlanAnnotations>,
 ) -> Pretty<'static> {
     match node.typ {
         DfPredType::ColumnRef => ColumnRefPred::from_pred_node(node)
@@ -68,7 +70,7 @@ pub fn explain_pred_node(
 
 pub fn explain_plan_node(
     node: ArcDfPlanNode,
-    meta_map: Option<&PlanNodeMetaMap>,
+    meta_map: Option<&PlanAnnotations>,
 ) -> Pretty<'static> {
     match node.typ {
         DfNodeType::Join(_) => LogicalJoin::from_plan_node(node).unwrap().explain(meta_map),
@@ -88,6 +90,15 @@ pub fn explain_plan_node(
         DfNodeType::Limit => LogicalLimit::from_plan_node(node)
             .unwrap()
             .explain(meta_map),
+        DfNodeType::Distinct => LogicalDistinct::from_plan_node(node)
+            .unwrap()
+            .explain(meta_map),
+        DfNodeType::TableFunction => LogicalTableFunction::from_plan_node(node)
+            .unwrap()
+            .explain(meta_map),
+        DfNodeType::Singleton => LogicalSingleton::from_plan_node(node)
+            .unwrap()
+            .explain(meta_map),
         DfNodeType::PhysicalFilter => PhysicalFilter::from_plan_node(node)
             .unwrap()
             .explain(meta_map),
@@ -103,6 +114,15 @@ pub fn explain_plan_node(
             .unwrap()
             .explain(meta_map),
         DfNodeType::PhysicalAgg => PhysicalAgg::from_plan_node(node).unwrap().explain(meta_map),
+        DfNodeType::PhysicalStreamAgg => PhysicalStreamAgg::from_plan_node(node)
+            .unwrap()
+            .explain(meta_map),
+        DfNodeType::PhysicalPartialAgg => PhysicalPartialAgg::from_plan_node(node)
+            .unwrap()
+            .explain(meta_map),
+        DfNodeType::PhysicalFinalAgg => PhysicalFinalAgg::from_plan_node(node)
+            .unwrap()
+            .explain(meta_map),
         DfNodeType::PhysicalSort => PhysicalSort::from_plan_node(node)
             .unwrap()
             .explain(meta_map),
@@ -118,5 +138,14 @@ pub fn explain_plan_node(
         DfNodeType::PhysicalNestedLoopJoin(_) => PhysicalNestedLoopJoin::from_plan_node(node)
             .unwrap()
             .explain(meta_map),
+        DfNodeType::PhysicalTableFunction => PhysicalTableFunction::from_plan_node(node)
+            .unwrap()
+            .explain(meta_map),
+        DfNodeType::PhysicalRuntimeFilter => PhysicalRuntimeFilter::from_plan_node(node)
+            .unwrap()
+            .explain(meta_map),
+        DfNodeType::PhysicalSingleton => PhysicalSingleton::from_plan_node(node)
+            .unwrap()
+            .explain(meta_map),
     }
 }
@@ -5,43 +5,111 @@
 
 #![allow(clippy::new_without_default)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
-use cost::{AdaptiveCostModel, RuntimeAdaptionStorage};
+use anyhow::{bail, Result};
+pub use batch::{BatchOptimizationResult, SharedSubplan};
+use cost::{
+    AdaptiveCostModel, CardinalityHintStorage, NljRowThresholdCostModel, RuntimeAdaptionStorage,
+};
+use itertools::Itertools;
 pub use memo_ext::{LogicalJoinOrder, MemoExt};
-use optd_og_core::cascades::{CascadesOptimizer, GroupId, NaiveMemo, OptimizerProperties};
-use optd_og_core::cost::CostModel;
-use optd_og_core::heuristics::{ApplyOrder, HeuristicsOptimizer, HeuristicsOptimizerOptions};
+use optd_og_core::cascades::{
+    CascadesOptimizer, ExplorationStrategy, GroupHandle, GroupId, Memo, MemoCheckpoint, MemoDiff,
+    MemoSnapshot, NaiveMemo, OptimizerProperties,
+};
+use optd_og_core::cost::{render_cost_formulas, CostComparator, CostModel};
+use optd_og_core::heuristics::{
+    ApplyOrder, CascadesCostEstimator, HeuristicsOptimizer, HeuristicsOptimizerOptions,
+    PlanCostEstimator,
+};
 use optd_og_core::logical_property::LogicalPropertyBuilderAny;
-use optd_og_core::nodes::PlanNodeMetaMap;
+use optd_og_core::nodes::PlanAnnotations;
 pub use optd_og_core::nodes::Value;
 use optd_og_core::optimizer::Optimizer;
 use optd_og_core::rules::Rule;
+pub use optimization_result::{
+    OptimizationConfig, OptimizationMetrics, OptimizationResult, OptimizationTiming,
+};
+pub use optimization_stage::{default_optimization_stages, StageConfig};
 pub use optimizer_ext::OptimizerExt;
-use plan_nodes::{ArcDfPlanNode, DfNodeType, DfReprPlanNode};
+pub use plan_baseline::{plan_signature, PlanBaseline, PlanBaselines};
+pub use plan_builder::PlanBuilder;
+pub use plan_invariants::{LimitInvariant, PlanInvariants};
+pub use plan_json::{plan_from_json, plan_to_json, PLAN_JSON_VERSION};
+use plan_nodes::{ArcDfPlanNode, DfNodeType, DfReprPlanNode, PhysicalScan};
 use properties::column_ref::ColumnRefPropertyBuilder;
-use properties::schema::{Catalog, SchemaPropertyBuilder};
+use properties::ordering::OrderingPropertyBuilder;
+use properties::schema::{Catalog, SchemaCache, SchemaPropertyBuilder};
+use properties::uniqueness::UniquenessPropertyBuilder;
+pub use rule_gating::{
+    query_fingerprint, QueryFingerprint, RuleGate, RuleGatingController, RuleOverride,
+};
+use runtime_filter::RuntimeFilterPlanner;
+pub use stats_freshness::{
+    StatsFreshnessTracker, StatsRefreshPolicy, StatsRefreshReason, StatsRefreshRecommendation,
+};
+pub use table_id::TableId;
 
+mod batch;
 pub mod cost;
 mod explain;
 mod memo_ext;
+mod optimization_result;
+mod optimization_stage;
 mod optimizer_ext;
+mod plan_baseline;
+mod plan_builder;
+mod plan_invariants;
+mod plan_json;
 pub mod plan_nodes;
 pub mod properties;
+mod rule_gating;
 pub mod rules;
+mod runtime_filter;
+mod stats_freshness;
+mod table_id;
 mod utils;
 
 #[cfg(test)]
 mod testing;
 
+/// The maximum number of passes of the heuristic rules. The rules run again while a pass leaves
+/// dependent joins behind, as a pass may only decorrelate the outermost of nested subqueries.
+const MAX_HEURISTIC_PASSES: usize = 16;
+
 pub struct DatafusionOptimizer {
     heuristic_optimizer: HeuristicsOptimizer<DfNodeType>,
     pub cascades_optimizer: CascadesOptimizer<DfNodeType>,
     pub runtime_statistics: RuntimeAdaptionStorage,
+    /// The cardinality corrections the cost model consults, which can be updated between
+    /// queries.
+    pub cardinality_hints: CardinalityHintStorage,
+    /// The table schemas memoized by the schema property builders, which has to be invalidated
+    /// when a table changes.
+    pub schema_cache: Arc<SchemaCache>,
     enable_adaptive: bool,
     enable_heuristic: bool,
+    stages: Vec<StageConfig>,
+    /// The cascades rules disabled in all the stages, on top of the rules the stages disable.
+    disabled_rules: Vec<String>,
+    /// The cost model without the wrappers installed by the optimizer settings.
+    base_cost: Arc<dyn CostModel<DfNodeType, NaiveMemo<DfNodeType>>>,
+    nlj_row_threshold: Option<usize>,
+    stats_freshness: StatsFreshnessTracker,
+    /// The plan produced for each root group in adaptive mode, which a re-optimized plan only
+    /// replaces if it preserves its invariants.
+    adaptive_plans: HashMap<GroupId, (ArcDfPlanNode, PlanAnnotations)>,
+    rule_gating: Option<RuleGatingController>,
+    /// The snapshots of the memo table after each stage of the last optimization, if enabled.
+    memo_snapshots: Option<Vec<MemoSnapshot>>,
+    plan_baselines: PlanBaselines,
+    enable_sort_elimination: bool,
+    ordering: OrderingPropertyBuilder,
+    runtime_filters: RuntimeFilterPlanner,
 }
 
 impl DatafusionOptimizer {
@@ -61,6 +129,247 @@ impl DatafusionOptimizer {
         self.enable_heuristic
     }
 
+    /// Reject the heuristic rewrites which increase the estimated cost of the plan, see
+    /// [`HeuristicsOptimizer::set_cost_check`]. The plans are costed with the implementation
+    /// rules and the current cost model of the cascades optimizer.
+    pub fn enable_heuristic_cost_check(&mut self, enable: bool) {
+        let estimator = enable.then(|| {
+            let impl_rules = self
+                .cascades_optimizer
+                .rules()
+                .iter()
+                .filter(|rule| rule.is_impl_rule())
+                .cloned()
+                .collect();
+            Box::new(CascadesCostEstimator::new(
+                impl_rules,
+                self.cascades_optimizer.cost(),
+                self.cascades_optimizer.logical_property_builders(),
+            )) as Box<dyn PlanCostEstimator<DfNodeType>>
+        });
+        self.heuristic_optimizer.set_cost_check(estimator);
+    }
+
+    pub fn optimization_stages(&self) -> &[StageConfig] {
+        &self.stages
+    }
+
+    /// Replace the stages of the cascades search, see [`default_optimization_stages`] for the
+    /// default pipeline. Fails if there is no stage or a stage disables an unknown rule.
+    pub fn set_optimization_stages(&mut self, stages: Vec<StageConfig>) -> Result<()> {
+        if stages.is_empty() {
+            bail!("at least one optimization stage is required");
+        }
+        for stage in &stages {
+            for rule_name in &stage.disabled_rules {
+                if !self
+                    .cascades_optimizer
+                    .rules
+                    .iter()
+                    .any(|rule| rule.name() == rule_name)
+                {
+                    bail!("stage {} disables unknown rule {}", stage.name, rule_name);
+                }
+            }
+        }
+        self.stages = stages;
+        Ok(())
+    }
+
+    pub fn disabled_rules(&self) -> &[String] {
+        &self.disabled_rules
+    }
+
+    /// Disable the cascades rules in all the stages of the next optimizations, e.g., for one
+    /// query. Fails if a rule is unknown. In adaptive mode, the queries are optimized from
+    /// scratch while rules are disabled, as the memo table may hold their rewrites.
+    pub fn set_disabled_rules(&mut self, rules: Vec<String>) -> Result<()> {
+        for rule_name in &rules {
+            if !self
+                .cascades_optimizer
+                .rules
+                .iter()
+                .any(|rule| rule.name() == rule_name)
+            {
+                bail!("unknown rule {}", rule_name);
+            }
+        }
+        self.disabled_rules = rules;
+        Ok(())
+    }
+
+    /// The `optd.nlj_row_threshold` knob: nested loop joins whose inputs both have more rows
+    /// than the threshold are only chosen if there is no other way to do the join, in which case
+    /// the optimization reports a warning. `None` lets the cost model decide.
+    pub fn set_nlj_row_threshold(&mut self, threshold: Option<usize>) {
+        self.nlj_row_threshold = threshold;
+        self.cascades_optimizer.cost = match threshold {
+            Some(threshold) => Arc::new(NljRowThresholdCostModel::new(
+                self.base_cost.clone(),
+                threshold,
+            )),
+            None => self.base_cost.clone(),
+        };
+    }
+
+    pub fn nlj_row_threshold(&self) -> Option<usize> {
+        self.nlj_row_threshold
+    }
+
+    /// The `optd.misestimate_threshold` knob of adaptive mode: when an operator produces this
+    /// many times more or fewer rows than estimated, its group is costed with the observed row
+    /// count from then on, and the operators above it are re-planned in the next optimization
+    /// of the query. `None` only re-costs the scans with their runtime row counts.
+    pub fn set_misestimate_threshold(&mut self, threshold: Option<f64>) {
+        self.runtime_statistics
+            .lock()
+            .unwrap()
+            .misestimate_threshold = threshold;
+    }
+
+    pub fn misestimate_threshold(&self) -> Option<f64> {
+        self.runtime_statistics
+            .lock()
+            .unwrap()
+            .misestimate_threshold
+    }
+
+    /// Gate the cascades rules whose rewrites made the plans of a query slower, judging by the
+    /// executions reported with [`Self::record_execution`]. The gated rules are reported in the
+    /// warnings of the optimization.
+    pub fn enable_rule_gating(&mut self, controller: RuleGatingController) {
+        self.rule_gating = Some(controller);
+    }
+
+    pub fn disable_rule_gating(&mut self) {
+        self.rule_gating = None;
+    }
+
+    /// The rule gating controller, e.g., to override its decisions.
+    pub fn rule_gating_mut(&mut self) -> Option<&mut RuleGatingController> {
+        self.rule_gating.as_mut()
+    }
+
+    /// Reports how long the last plan of the query with the fingerprint took to execute, see
+    /// [`OptimizationResult::fingerprint`].
+    pub fn record_execution(&mut self, fingerprint: QueryFingerprint, elapsed: Duration) {
+        if let Some(controller) = &mut self.rule_gating {
+            controller.record_execution(fingerprint, elapsed);
+        }
+    }
+
+    /// The plans pinned for the queries of a fingerprint, see [`PlanBaselines`].
+    pub fn plan_baselines(&self) -> &PlanBaselines {
+        &self.plan_baselines
+    }
+
+    /// Adds, replaces and removes the plan baselines, e.g., to pin the plan of an
+    /// [`OptimizationResult`] with its fingerprint.
+    pub fn plan_baselines_mut(&mut self) -> &mut PlanBaselines {
+        &mut self.plan_baselines
+    }
+
+    /// Remove the sorts of the optimized plan whose input is already ordered on equivalent
+    /// columns, e.g., through a projection renaming the columns or the keys of a join.
+    pub fn enable_sort_elimination(&mut self, enable: bool) {
+        self.enable_sort_elimination = enable;
+    }
+
+    pub fn is_sort_elimination_enabled(&self) -> bool {
+        self.enable_sort_elimination
+    }
+
+    /// The `optd.runtime_filter_selectivity` knob: the inner hash joins estimated to produce at
+    /// most this fraction of the rows of their probe side filter the scan the probe keys are
+    /// read from with the range of their build side keys, when the plan runs. `None` disables
+    /// the runtime filters.
+    pub fn set_runtime_filter_selectivity(&mut self, max_selectivity: Option<f64>) {
+        self.runtime_filters.set_max_selectivity(max_selectivity);
+    }
+
+    pub fn runtime_filter_selectivity(&self) -> Option<f64> {
+        self.runtime_filters.max_selectivity()
+    }
+
+    /// Take a snapshot of the winners of the memo table after each optimization stage, to
+    /// compare the stages with [`Self::memo_snapshot_diffs`].
+    pub fn enable_memo_snapshots(&mut self, enable: bool) {
+        self.memo_snapshots = if enable { Some(Vec::new()) } else { None };
+    }
+
+    /// The snapshots of the memo table taken after each stage of the last optimization, named
+    /// after the stages.
+    pub fn memo_snapshots(&self) -> &[MemoSnapshot] {
+        self.memo_snapshots.as_deref().unwrap_or_default()
+    }
+
+    /// The differences between the snapshots of consecutive stages of the last optimization.
+    pub fn memo_snapshot_diffs(&self) -> Vec<MemoDiff> {
+        self.memo_snapshots()
+            .iter()
+            .tuple_windows()
+            .map(|(before, after)| before.diff(after))
+            .collect()
+    }
+
+    pub fn stats_freshness(&self) -> &StatsFreshnessTracker {
+        &self.stats_freshness
+    }
+
+    /// The tracker the host reports analyzes and row changes to, and sets the refresh policy and
+    /// callback of.
+    pub fn stats_freshness_mut(&mut self) -> &mut StatsFreshnessTracker {
+        &mut self.stats_freshness
+    }
+
+    /// The tables whose statistics should be refreshed, judging by the row changes reported by
+    /// the host, the runtime row counts of the scans and the age of the statistics.
+    pub fn stats_refresh_recommended(&mut self) -> Vec<StatsRefreshRecommendation> {
+        self.stats_freshness
+            .ingest_runtime_statistics(&self.runtime_statistics.lock().unwrap());
+        self.stats_freshness.recommendations()
+    }
+
+    /// Mark the statistics of `table` as out of date, e.g., after the host ingested data into
+    /// it, and drop the cardinality hints of its filters, which were learned from the old data.
+    /// The refresh callback of the tracker is invoked, so that the host can re-collect the
+    /// statistics, and the plans scanning the table report them as stale until the host reports
+    /// the next analyze.
+    pub fn invalidate_stats(&mut self, table: &str) {
+        self.cardinality_hints.lock().unwrap().remove_table(table);
+        self.stats_freshness.invalidate(table);
+    }
+
+    /// Load the rewrite rules in the JSON file, see [`rules::parse_declarative_rules`], and
+    /// apply them after the built-in heuristic rules.
+    pub fn load_declarative_rules(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let rules = rules::parse_declarative_rules(&std::fs::read_to_string(path)?)?;
+        self.heuristic_optimizer.add_rules(
+            rules
+                .into_iter()
+                .map(|rule| Arc::new(rule) as Arc<dyn Rule<_, _>>),
+        );
+        Ok(())
+    }
+
+    /// Set the number of partitions the input is read in. If there is more than one partition,
+    /// aggregations may be split into a partial and a final phase so that they run in parallel.
+    pub fn set_target_partitions(&mut self, partitions: usize) {
+        let mut rules = self.cascades_optimizer.rules.to_vec();
+        // The rule is always the last one, so that the ids of the other rules are not changed.
+        rules.retain(|rule| rule.name() != "two_phase_agg_rule");
+        if partitions > 1 {
+            rules.push(Arc::new(rules::TwoPhaseAggRule::new(partitions)));
+        }
+        self.cascades_optimizer.rules = rules.into();
+    }
+
+    /// Renders the cost formula of each operator with the constants currently used by the
+    /// cost model as a markdown table.
+    pub fn cost_model_report(&self) -> String {
+        render_cost_formulas(&self.cascades_optimizer.cost().describe())
+    }
+
     pub fn optd_og_cascades_optimizer(&self) -> &CascadesOptimizer<DfNodeType> {
         &self.cascades_optimizer
     }
@@ -74,6 +383,7 @@ impl DatafusionOptimizer {
     }
 
     pub fn default_heuristic_rules(
+        catalog: Arc<dyn Catalog>,
     ) -> Vec<Arc<dyn Rule<DfNodeType, HeuristicsOptimizer<DfNodeType>>>> {
         vec![
             Arc::new(rules::EliminateProjectRule::new()),
@@ -81,21 +391,25 @@ impl DatafusionOptimizer {
             Arc::new(rules::SimplifyJoinCondRule::new()),
             Arc::new(rules::EliminateFilterRule::new()),
             Arc::new(rules::EliminateJoinRule::new()),
+            Arc::new(rules::EliminateSelfJoinRule::new(catalog)),
             Arc::new(rules::EliminateLimitRule::new()),
             Arc::new(rules::EliminateDuplicatedSortExprRule::new()),
             Arc::new(rules::EliminateDuplicatedAggExprRule::new()),
+            Arc::new(rules::EliminateDistinctRule::new()),
             Arc::new(rules::DepJoinEliminate::new()),
             Arc::new(rules::DepInitialDistinct::new()),
             Arc::new(rules::DepJoinPastProj::new()),
             Arc::new(rules::DepJoinPastFilter::new()),
             Arc::new(rules::DepJoinPastAgg::new()),
+            Arc::new(rules::StarSchemaJoinRule::new()),
             Arc::new(rules::ProjectMergeRule::new()),
             Arc::new(rules::FilterMergeRule::new()),
         ]
     }
 
-    pub fn default_cascades_rules() -> Vec<Arc<dyn Rule<DfNodeType, CascadesOptimizer<DfNodeType>>>>
-    {
+    pub fn default_cascades_rules(
+        catalog: Arc<dyn Catalog>,
+    ) -> Vec<Arc<dyn Rule<DfNodeType, CascadesOptimizer<DfNodeType>>>> {
         let rules = rules::PhysicalConversionRule::all_conversions();
         let mut rule_wrappers = vec![];
         for rule in rules {
@@ -106,6 +420,11 @@ impl DatafusionOptimizer {
         rule_wrappers.push(Arc::new(rules::FilterSortTransposeRule::new()));
         rule_wrappers.push(Arc::new(rules::FilterAggTransposeRule::new()));
         rule_wrappers.push(Arc::new(rules::HashJoinRule::new()));
+        rule_wrappers.push(Arc::new(rules::HashJoinBuildRightRule::new()));
+        rule_wrappers.push(Arc::new(rules::HashMarkJoinRule::new()));
+        rule_wrappers.push(Arc::new(rules::StreamAggRule::new()));
+        rule_wrappers.push(Arc::new(rules::SortAggRule::new()));
+        rule_wrappers.push(Arc::new(rules::AggJoinTransposeRule::new()));
         rule_wrappers.push(Arc::new(rules::JoinCommuteRule::new()));
         rule_wrappers.push(Arc::new(rules::JoinAssocRule::new()));
         rule_wrappers.push(Arc::new(rules::ProjectionPullUpJoin::new()));
@@ -115,14 +434,25 @@ impl DatafusionOptimizer {
         rule_wrappers.push(Arc::new(rules::EliminateJoinRule::new()));
         rule_wrappers.push(Arc::new(rules::EliminateFilterRule::new()));
         rule_wrappers.push(Arc::new(rules::ProjectFilterTransposeRule::new()));
+        rule_wrappers.push(Arc::new(rules::EliminateDistinctRule::new()));
+        rule_wrappers.push(Arc::new(rules::DistinctToAggRule::new()));
+        rule_wrappers.push(Arc::new(rules::PartitionPruningRule::new(catalog.clone())));
+        rule_wrappers.push(Arc::new(rules::IndexLookupRule::new(catalog)));
         rule_wrappers
     }
 
     /// Create an optimizer with partial explore (otherwise it's too slow).
     pub fn new_physical(catalog: Arc<dyn Catalog>, enable_adaptive: bool) -> Self {
-        let cost_model = AdaptiveCostModel::new(50);
+        let cost_model = AdaptiveCostModel::new(50).with_catalog(catalog.clone());
         let map = cost_model.get_runtime_map();
-        Self::new_physical_with_cost_model(catalog, enable_adaptive, cost_model, map)
+        let cardinality_hints = cost_model.get_cardinality_hints();
+        Self::new_physical_with_cost_model(
+            catalog,
+            enable_adaptive,
+            cost_model,
+            map,
+            cardinality_hints,
+        )
     }
 
     pub fn new_physical_with_cost_model(
@@ -130,33 +460,53 @@ impl DatafusionOptimizer {
         enable_adaptive: bool,
         cost_model: impl CostModel<DfNodeType, NaiveMemo<DfNodeType>>,
         runtime_map: RuntimeAdaptionStorage,
+        cardinality_hints: CardinalityHintStorage,
     ) -> Self {
-        let cascades_rules = Self::default_cascades_rules();
-        let heuristic_rules = Self::default_heuristic_rules();
+        let cascades_rules = Self::default_cascades_rules(catalog.clone());
+        let heuristic_rules = Self::default_heuristic_rules(catalog.clone());
+        let schema_cache = Arc::new(SchemaCache::new());
         let property_builders: Arc<[Box<dyn LogicalPropertyBuilderAny<DfNodeType>>]> = Arc::new([
-            Box::new(SchemaPropertyBuilder::new(catalog.clone())),
+            Box::new(SchemaPropertyBuilder::new_with_cache(
+                catalog.clone(),
+                schema_cache.clone(),
+            )),
             Box::new(ColumnRefPropertyBuilder::new(catalog.clone())),
+            Box::new(UniquenessPropertyBuilder::new(catalog.clone())),
         ]);
+        let cascades_optimizer = CascadesOptimizer::new_with_options(
+            cascades_rules,
+            Box::new(cost_model),
+            vec![
+                Box::new(SchemaPropertyBuilder::new_with_cache(
+                    catalog.clone(),
+                    schema_cache.clone(),
+                )) as Box<dyn LogicalPropertyBuilderAny<DfNodeType>>,
+                Box::new(ColumnRefPropertyBuilder::new(catalog.clone()))
+                    as Box<dyn LogicalPropertyBuilderAny<DfNodeType>>,
+                Box::new(UniquenessPropertyBuilder::new(catalog.clone()))
+                    as Box<dyn LogicalPropertyBuilderAny<DfNodeType>>,
+            ]
+            .into(),
+            OptimizerProperties {
+                panic_on_budget: false,
+                partial_explore_iter: Some(1 << 18),
+                partial_explore_space: Some(1 << 14),
+                timeout: None,
+                disable_pruning: false,
+                enable_tracing: false,
+                cost_comparator: CostComparator::default(),
+                retain_alternatives: None,
+                cost_floor: None,
+                exploration_strategy: ExplorationStrategy::Exhaustive,
+                verify_memo_integrity: false,
+            },
+        );
         Self {
             runtime_statistics: runtime_map,
-            cascades_optimizer: CascadesOptimizer::new_with_options(
-                cascades_rules,
-                Box::new(cost_model),
-                vec![
-                    Box::new(SchemaPropertyBuilder::new(catalog.clone()))
-                        as Box<dyn LogicalPropertyBuilderAny<DfNodeType>>,
-                    Box::new(ColumnRefPropertyBuilder::new(catalog.clone()))
-                        as Box<dyn LogicalPropertyBuilderAny<DfNodeType>>,
-                ]
-                .into(),
-                OptimizerProperties {
-                    panic_on_budget: false,
-                    partial_explore_iter: Some(1 << 18),
-                    partial_explore_space: Some(1 << 14),
-                    disable_pruning: false,
-                    enable_tracing: false,
-                },
-            ),
+            cardinality_hints,
+            schema_cache,
+            base_cost: cascades_optimizer.cost(),
+            cascades_optimizer,
             heuristic_optimizer: HeuristicsOptimizer::new_with_rules(
                 heuristic_rules,
                 HeuristicsOptimizerOptions {
@@ -168,6 +518,17 @@ impl DatafusionOptimizer {
             ),
             enable_adaptive,
             enable_heuristic: true,
+            stages: default_optimization_stages(),
+            disabled_rules: vec![],
+            nlj_row_threshold: None,
+            stats_freshness: StatsFreshnessTracker::default(),
+            adaptive_plans: HashMap::new(),
+            rule_gating: None,
+            memo_snapshots: None,
+            plan_baselines: PlanBaselines::default(),
+            enable_sort_elimination: true,
+            ordering: OrderingPropertyBuilder::new(catalog.clone()),
+            runtime_filters: RuntimeFilterPlanner::new(catalog),
         }
     }
 
@@ -184,24 +545,44 @@ impl DatafusionOptimizer {
         rule_wrappers.insert(2, Arc::new(rules::ProjectionPullUpJoin::new()));
         rule_wrappers.insert(3, Arc::new(rules::EliminateFilterRule::new()));
 
-        let cost_model = AdaptiveCostModel::new(1000);
+        let cost_model = AdaptiveCostModel::new(1000).with_catalog(catalog.clone());
         let runtime_statistics = cost_model.get_runtime_map();
+        let cardinality_hints = cost_model.get_cardinality_hints();
+        let schema_cache = Arc::new(SchemaCache::new());
         let optimizer = CascadesOptimizer::new(
             rule_wrappers,
             Box::new(cost_model),
             vec![
-                Box::new(SchemaPropertyBuilder::new(catalog.clone()))
-                    as Box<dyn LogicalPropertyBuilderAny<DfNodeType>>,
+                Box::new(SchemaPropertyBuilder::new_with_cache(
+                    catalog.clone(),
+                    schema_cache.clone(),
+                )) as Box<dyn LogicalPropertyBuilderAny<DfNodeType>>,
                 Box::new(ColumnRefPropertyBuilder::new(catalog.clone()))
                     as Box<dyn LogicalPropertyBuilderAny<DfNodeType>>,
+                Box::new(UniquenessPropertyBuilder::new(catalog.clone()))
+                    as Box<dyn LogicalPropertyBuilderAny<DfNodeType>>,
             ]
             .into(),
         );
         Self {
             runtime_statistics,
+            cardinality_hints,
+            schema_cache,
+            base_cost: optimizer.cost(),
             cascades_optimizer: optimizer,
             enable_adaptive: true,
             enable_heuristic: false,
+            stages: default_optimization_stages(),
+            disabled_rules: vec![],
+            nlj_row_threshold: None,
+            stats_freshness: StatsFreshnessTracker::default(),
+            adaptive_plans: HashMap::new(),
+            rule_gating: None,
+            memo_snapshots: None,
+            plan_baselines: PlanBaselines::default(),
+            enable_sort_elimination: true,
+            ordering: OrderingPropertyBuilder::new(catalog.clone()),
+            runtime_filters: RuntimeFilterPlanner::new(catalog),
             heuristic_optimizer: HeuristicsOptimizer::new_with_rules(
                 vec![],
                 HeuristicsOptimizerOptions {
@@ -215,56 +596,473 @@ impl DatafusionOptimizer {
     }
 
     pub fn heuristic_optimize(&mut self, root_rel: ArcDfPlanNode) -> ArcDfPlanNode {
-        self.heuristic_optimizer
-            .optimize(root_rel)
-            .expect("heuristics returns error")
+        let _span = tracing::info_span!("optd_og.heuristic").entered();
+        let mut plan = root_rel;
+        for _ in 0..MAX_HEURISTIC_PASSES {
+            let optimized = self
+                .heuristic_optimizer
+                .optimize(plan.clone())
+                .expect("heuristics returns error");
+            let fixpoint = optimized == plan;
+            plan = optimized;
+            if fixpoint || !has_dependent_join(&plan) {
+                break;
+            }
+        }
+        plan
     }
 
     pub fn cascades_optimize(
         &mut self,
         root_rel: ArcDfPlanNode,
-    ) -> Result<(GroupId, ArcDfPlanNode, PlanNodeMetaMap)> {
+    ) -> Result<(GroupId, ArcDfPlanNode, PlanAnnotations)> {
+        self.cascades_optimize_inner(root_rel, &mut OptimizationTiming::default(), &[])
+    }
+
+    /// Optimize the plan with the heuristic optimizer (if enabled) and the cascades optimizer, and
+    /// collect the metrics, warnings and timing of the optimization.
+    pub fn optimize(&mut self, root_rel: ArcDfPlanNode) -> Result<OptimizationResult> {
+        let start = Instant::now();
+        let mut timing = OptimizationTiming::default();
+        // The runtime statistics of the previous queries may show that the statistics of the
+        // tables are out of date.
+        self.stats_freshness
+            .ingest_runtime_statistics(&self.runtime_statistics.lock().unwrap());
+        self.stats_freshness.notify();
+        let metrics_before = OptimizationMetrics::from_stats(&self.cascades_optimizer.stats);
+        let fingerprint = query_fingerprint(&root_rel);
+        let rule_names = self
+            .cascades_optimizer
+            .rules()
+            .iter()
+            .map(|rule| rule.name().to_string())
+            .collect_vec();
+        let gates = self.rule_gating.as_ref().map_or(vec![], |controller| {
+            controller
+                .gated_rules(fingerprint)
+                .into_iter()
+                .filter(|gate| rule_names.contains(&gate.rule))
+                .collect()
+        });
+        let disabled_rules = gates
+            .iter()
+            .map(|gate| gate.rule.clone())
+            .chain(self.disabled_rules.iter().cloned())
+            .collect_vec();
+        // The misestimates observed since the last plan, which the plan is re-optimized with.
+        let misestimates = if self.enable_adaptive {
+            self.runtime_statistics.lock().unwrap().new_misestimates()
+        } else {
+            vec![]
+        };
+
+        let heuristic_plan = if self.enable_heuristic {
+            let plan = self.heuristic_optimize(root_rel.clone());
+            timing.heuristic = start.elapsed();
+            Some(plan)
+        } else {
+            None
+        };
+        let (group_id, mut plan, mut meta) = self.cascades_optimize_inner(
+            heuristic_plan.clone().unwrap_or(root_rel),
+            &mut timing,
+            &disabled_rules,
+        )?;
+        timing.total = start.elapsed();
+
+        let memo = self.cascades_optimizer.memo();
+        let mut metrics = OptimizationMetrics::from_stats(&self.cascades_optimizer.stats);
+        metrics.group_count = memo.get_all_group_ids().len();
+        metrics.plan_space = memo.estimated_plan_space();
+        let metrics = metrics.since(&metrics_before);
+
+        let winner_rules = self
+            .cascades_optimizer
+            .step_winner_rules(group_id)
+            .into_iter()
+            .map(|rule_id| rule_names[rule_id].clone())
+            .collect::<BTreeSet<_>>();
+        if let Some(controller) = &mut self.rule_gating {
+            controller.record_plan(fingerprint, winner_rules.clone());
+        }
+
+        let mut warnings = gates
+            .into_iter()
+            .map(|gate| format!("rule {} gated for this query: {}", gate.rule, gate.reason))
+            .collect_vec();
+        for (group_id, misestimate) in misestimates {
+            warnings.push(format!(
+                "re-optimized with the observed row count of group {}: estimated {:.0} rows, observed {}{}",
+                group_id,
+                misestimate.estimated_row_cnt,
+                if misestimate.finished { "" } else { "at least " },
+                misestimate.actual_row_cnt
+            ));
+        }
+        let ctx = &self.cascades_optimizer.ctx;
+        if ctx.cancelled {
+            warnings.push(
+                "optimization cancelled, the plan was chosen without full exploration".to_string(),
+            );
+        } else if ctx.timed_out {
+            warnings.push(
+                "optimization timed out, the plan was chosen without full exploration".to_string(),
+            );
+        } else if ctx.all_budget_used {
+            warnings.push(
+                "iteration budget exhausted, the plan was chosen without full exploration"
+                    .to_string(),
+            );
+        } else if ctx.logical_budget_used {
+            warnings.push(
+                "plan space budget exhausted, logical rules were not fully applied".to_string(),
+            );
+        }
+        let baseline = self.plan_baselines.get(fingerprint).cloned();
+        let baseline = match baseline {
+            Some(baseline) => match self.compute_baseline_meta(&baseline, group_id) {
+                Ok(baseline_meta) => {
+                    (plan, meta) = (baseline.plan, baseline_meta);
+                    Some(baseline.signature)
+                }
+                Err(err) => {
+                    warnings.push(format!(
+                        "ignoring the plan baseline of the query: {}, the optimized plan is used",
+                        err
+                    ));
+                    None
+                }
+            },
+            None => None,
+        };
+        // a pinned plan is kept as is
+        if self.enable_sort_elimination && baseline.is_none() {
+            (plan, meta) = self.ordering.eliminate_redundant_sorts(&plan, &meta);
+        }
         if self.enable_adaptive {
+            (plan, meta) = self.substitute_reoptimized_plan(group_id, plan, meta, &mut warnings);
+            let mut runtime_statistics = self.runtime_statistics.lock().unwrap();
+            runtime_statistics.estimates.clear();
+            record_estimates(&mut runtime_statistics.estimates, &plan, &meta);
+        }
+        // the runtime filters are not kept with the plans of adaptive mode, which are compared
+        // with the re-optimized plans
+        (plan, meta) = self.runtime_filters.add_runtime_filters(&plan, &meta);
+        if let Some(threshold) = self.nlj_row_threshold {
+            warnings.extend(nlj_threshold_warnings(&plan, &meta, threshold));
+        }
+        record_scans(&mut self.stats_freshness, &plan, &meta);
+        let mut scanned_tables = BTreeSet::new();
+        collect_scanned_tables(&plan, &mut scanned_tables);
+        let stale_stats = scanned_tables
+            .into_iter()
+            .filter_map(|table| self.stats_freshness.recommendation_for(&table))
+            .collect_vec();
+        self.ordering.annotate_orderings(&plan, &mut meta);
+
+        Ok(OptimizationResult {
+            group_id,
+            plan,
+            meta,
+            heuristic_plan,
+            fingerprint,
+            baseline,
+            winner_rules: winner_rules.into_iter().collect(),
+            metrics,
+            warnings,
+            stale_stats,
+            config: OptimizationConfig {
+                enable_adaptive: self.enable_adaptive,
+                enable_heuristic: self.enable_heuristic,
+                cascades: self.cascades_optimizer.prop.clone(),
+            },
+            timing,
+        })
+    }
+
+    fn cascades_optimize_inner(
+        &mut self,
+        root_rel: ArcDfPlanNode,
+        timing: &mut OptimizationTiming,
+        disabled_rules: &[String],
+    ) -> Result<(GroupId, ArcDfPlanNode, PlanAnnotations)> {
+        // The memo table of the previous runs may hold the rewrites of the disabled rules.
+        if self.enable_adaptive && disabled_rules.is_empty() {
             self.runtime_statistics.lock().unwrap().iter_cnt += 1;
+            // The eviction policy keeps the winners of the previous run, before they are cleared.
+            self.cascades_optimizer.step_evict();
             self.cascades_optimizer.step_clear_winner();
         } else {
             self.cascades_optimizer.step_clear();
+            self.adaptive_plans.clear();
+            // The group ids are reused by the new memo table.
+            let mut runtime_statistics = self.runtime_statistics.lock().unwrap();
+            runtime_statistics.estimates.clear();
+            runtime_statistics.misestimates.clear();
         }
 
         tracing::debug!("before_cascades={}", root_rel.explain_to_string(None));
+        if let Some(snapshots) = &mut self.memo_snapshots {
+            snapshots.clear();
+        }
 
-        self.cascades_optimizer
-            .disable_rule_by_name("join_commute_rule");
-        self.cascades_optimizer
-            .disable_rule_by_name("join_assoc_rule");
+        let mut group = None;
+        for mut stage in self.stages.clone() {
+            stage.disabled_rules.extend(disabled_rules.iter().cloned());
+            let stage_start = Instant::now();
+            let stage_group = self.run_optimization_stage(&stage, |optimizer| match group {
+                Some(group) => {
+                    optimizer.step_next_stage();
+                    optimizer.fire_optimize_tasks(group)?;
+                    Ok(group)
+                }
+                None => optimizer.step_optimize(root_rel.clone()),
+            });
+            timing
+                .stages
+                .push((stage.name.clone(), stage_start.elapsed()));
+            group = Some(stage_group?);
+            if let Some(snapshots) = &mut self.memo_snapshots {
+                snapshots.push(self.cascades_optimizer.step_snapshot_memo(&stage.name));
+            }
+        }
         let group_id = self
             .cascades_optimizer
-            .step_optimize_rel(root_rel.clone())?;
+            .resolve_group(group.expect("at least one optimization stage"));
+
+        let mut meta = Some(PlanAnnotations::new());
+        let optimized_rel = self
+            .cascades_optimizer
+            .step_get_optimize_rel(group_id, &mut meta)?;
+
+        Ok((group_id, optimized_rel, meta.unwrap()))
+    }
+
+    /// Re-optimizing a query with the runtime statistics may pick a plan whose limits keep other
+    /// rows than the plan of the previous run, keep the previous plan in that case.
+    fn substitute_reoptimized_plan(
+        &mut self,
+        group_id: GroupId,
+        plan: ArcDfPlanNode,
+        meta: PlanAnnotations,
+        warnings: &mut Vec<String>,
+    ) -> (ArcDfPlanNode, PlanAnnotations) {
+        if let Some((prev_plan, prev_meta)) = self.adaptive_plans.get(&group_id) {
+            if let Err(err) =
+                PlanInvariants::of(prev_plan).check_substitution(&PlanInvariants::of(&plan))
+            {
+                warnings.push(format!(
+                    "keeping the previous plan of group {}: the re-optimized plan does not preserve its invariants, {}",
+                    group_id, err
+                ));
+                return (prev_plan.clone(), prev_meta.clone());
+            }
+        }
+        self.adaptive_plans
+            .insert(group_id, (plan.clone(), meta.clone()));
+        (plan, meta)
+    }
+
+    /// Run `optimize` with the rules and budgets of the stage.
+    fn run_optimization_stage(
+        &mut self,
+        stage: &StageConfig,
+        optimize: impl FnOnce(&mut CascadesOptimizer<DfNodeType>) -> Result<GroupHandle>,
+    ) -> Result<GroupHandle> {
+        for rule_name in &stage.disabled_rules {
+            self.cascades_optimizer.disable_rule_by_name(rule_name);
+        }
+        let prop = self.cascades_optimizer.prop.clone();
+        if stage.partial_explore_iter.is_some() {
+            self.cascades_optimizer.prop.partial_explore_iter = stage.partial_explore_iter;
+        }
+        if stage.partial_explore_space.is_some() {
+            self.cascades_optimizer.prop.partial_explore_space = stage.partial_explore_space;
+        }
+
+        let res = tracing::info_span!("optd_og.stage", stage = %stage.name)
+            .in_scope(|| optimize(&mut self.cascades_optimizer));
+
+        self.cascades_optimizer.prop = prop;
+        for rule_name in &stage.disabled_rules {
+            self.cascades_optimizer.enable_rule_by_name(rule_name);
+        }
+        let group_id = res?;
 
         tracing::debug!(
-            "stage_1_best_plan={}",
+            "{}_best_plan={}",
+            stage.name,
             self.cascades_optimizer
                 .step_get_optimize_rel(group_id, &mut None)?
                 .explain_to_string(None)
         );
+        Ok(group_id)
+    }
 
+    /// Write a checkpoint of the memo table to `path` every `interval` optimizer tasks, so that
+    /// a long optimization can be continued with [`Self::resume_from_checkpoint`] after a crash.
+    pub fn enable_memo_checkpoints(&mut self, path: impl Into<PathBuf>, interval: usize) {
+        let path = path.into();
         self.cascades_optimizer
-            .enable_rule_by_name("join_commute_rule");
-        self.cascades_optimizer
-            .enable_rule_by_name("join_assoc_rule");
-        self.cascades_optimizer.step_next_stage();
-        self.cascades_optimizer.fire_optimize_tasks(group_id)?;
+            .set_checkpoint_hook(interval, move |checkpoint| {
+                if let Err(err) = write_memo_checkpoint(&path, &checkpoint) {
+                    tracing::warn!(
+                        "failed to write memo checkpoint to {}: {}",
+                        path.display(),
+                        err
+                    );
+                }
+            });
+    }
+
+    pub fn disable_memo_checkpoints(&mut self) {
+        self.cascades_optimizer.clear_checkpoint_hook();
+    }
 
-        let mut meta = Some(HashMap::new());
+    /// Restore the memo table from a checkpoint written by [`Self::enable_memo_checkpoints`] and
+    /// continue the search with the last optimization stage.
+    pub fn resume_from_checkpoint(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<(GroupId, ArcDfPlanNode, PlanAnnotations)> {
+        let data = std::fs::read(path.as_ref())?;
+        let checkpoint: MemoCheckpoint<DfNodeType> = bincode::deserialize(&data)?;
+        self.adaptive_plans.clear();
+        let group = self
+            .cascades_optimizer
+            .step_restore_checkpoint(checkpoint)?;
+        let stage = self.stages.last().unwrap().clone();
+        self.run_optimization_stage(&stage, |optimizer| {
+            optimizer.fire_optimize_tasks(group)?;
+            Ok(group)
+        })?;
+        let group_id = self.cascades_optimizer.resolve_group(group);
+
+        let mut meta = Some(PlanAnnotations::new());
         let optimized_rel = self
             .cascades_optimizer
             .step_get_optimize_rel(group_id, &mut meta)?;
+        Ok((group_id, optimized_rel, meta.unwrap()))
+    }
 
-        tracing::debug!(
-            "stage_2_best_plan={}",
-            optimized_rel.explain_to_string(None)
-        );
+    /// Computes the costs and the cardinalities of a physical plan other than the optimized one,
+    /// e.g., a plan of the memo table the optimizer rejected, to explain it with the same cost
+    /// model as the winner.
+    pub fn compute_plan_meta(&self, plan: ArcDfPlanNode) -> Result<PlanAnnotations> {
+        let mut meta = PlanAnnotations::new();
+        self.cascades_optimizer
+            .step_compute_plan_meta(plan, &mut meta)?;
+        Ok(meta)
+    }
 
-        Ok((group_id, optimized_rel, meta.unwrap()))
+    /// Computes the costs and the cardinalities of a pinned plan, which must be a plan of the
+    /// group of the query, i.e., compute the same result as the optimized plan.
+    fn compute_baseline_meta(
+        &self,
+        baseline: &PlanBaseline,
+        group_id: GroupId,
+    ) -> Result<PlanAnnotations> {
+        let mut meta = PlanAnnotations::new();
+        let baseline_group_id = self
+            .cascades_optimizer
+            .step_compute_plan_meta(baseline.plan.clone(), &mut meta)?;
+        let baseline_group_id = self.cascades_optimizer.resolve_group(baseline_group_id);
+        let group_id = self.cascades_optimizer.resolve_group(group_id);
+        if baseline_group_id != group_id {
+            bail!(
+                "the pinned plan is in group {} of the memo table, not in the group {} of the query",
+                baseline_group_id,
+                group_id
+            );
+        }
+        Ok(meta)
+    }
+}
+
+/// Write the checkpoint to a temporary file first, so that a crash while writing does not
+/// corrupt the previous checkpoint.
+fn write_memo_checkpoint(path: &Path, checkpoint: &MemoCheckpoint<DfNodeType>) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, bincode::serialize(checkpoint)?)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn has_dependent_join(plan: &ArcDfPlanNode) -> bool {
+    matches!(plan.typ, DfNodeType::RawDepJoin(_) | DfNodeType::DepJoin)
+        || plan
+            .children
+            .iter()
+            .any(|child| has_dependent_join(&child.unwrap_plan_node()))
+}
+
+/// Record the scans of the plan, so that their runtime row counts can be compared with the
+/// estimates.
+fn record_scans(tracker: &mut StatsFreshnessTracker, plan: &ArcDfPlanNode, meta: &PlanAnnotations) {
+    if let (Some(scan), Some(meta)) = (PhysicalScan::from_plan_node(plan.clone()), meta.get(plan)) {
+        if let Some(row_cnt) = meta.row_cnt {
+            tracker.record_scan(meta.group_id, scan.table().as_ref(), row_cnt as usize);
+        }
+    }
+    for child in &plan.children {
+        record_scans(tracker, &child.unwrap_plan_node(), meta);
+    }
+}
+
+/// The tables scanned by the plan, e.g., to report the ones with stale statistics.
+fn collect_scanned_tables(plan: &ArcDfPlanNode, tables: &mut BTreeSet<String>) {
+    if let Some(scan) = PhysicalScan::from_plan_node(plan.clone()) {
+        tables.insert(scan.table().to_string());
+    }
+    for child in &plan.children {
+        collect_scanned_tables(&child.unwrap_plan_node(), tables);
+    }
+}
+
+/// Record the estimated row count of each group of the plan, which the runtime row counts of
+/// its operators are compared with.
+fn record_estimates(
+    estimates: &mut HashMap<GroupId, f64>,
+    plan: &ArcDfPlanNode,
+    meta: &PlanAnnotations,
+) {
+    if let Some((group_id, Some(row_cnt))) =
+        meta.get(plan).map(|meta| (meta.group_id, meta.row_cnt))
+    {
+        estimates.insert(group_id, row_cnt);
+    }
+    for child in &plan.children {
+        record_estimates(estimates, &child.unwrap_plan_node(), meta);
+    }
+}
+
+/// Reports the nested loop joins of the plan over inputs above the threshold, which the
+/// optimizer only chooses when there is no other way to do the join.
+fn nlj_threshold_warnings(
+    plan: &ArcDfPlanNode,
+    meta: &PlanAnnotations,
+    threshold: usize,
+) -> Vec<String> {
+    let row_cnt = |node: &ArcDfPlanNode| meta.get(node).and_then(|meta| meta.row_cnt);
+    let mut warnings = vec![];
+    if let DfNodeType::PhysicalNestedLoopJoin(_) = plan.typ {
+        let left = plan.child_rel(0);
+        let right = plan.child_rel(1);
+        if let (Some(left_rows), Some(right_rows)) = (row_cnt(&left), row_cnt(&right)) {
+            if NljRowThresholdCostModel::exceeds_threshold(threshold, left_rows, right_rows) {
+                warnings.push(format!(
+                    "nested loop join over {left_rows} and {right_rows} rows chosen for lack of \
+                     alternatives (optd.nlj_row_threshold = {threshold})"
+                ));
+            }
+        }
+    }
+    for child in &plan.children {
+        warnings.extend(nlj_threshold_warnings(
+            &child.unwrap_plan_node(),
+            meta,
+            threshold,
+        ));
     }
+    warnings
 }
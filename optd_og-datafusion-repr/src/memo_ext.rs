@@ -9,7 +9,7 @@ use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
 use itertools::Itertools;
-use optd_og_core::cascades::{ExprId, GroupId, Memo};
+use optd_og_core::cascades::{ExprId, GroupHandle, GroupId, Memo};
 use optd_og_core::nodes::NodeType;
 
 use crate::plan_nodes::{ConstantPred, DfNodeType, DfReprPredNode};
@@ -32,7 +32,8 @@ impl std::fmt::Display for LogicalJoinOrder {
 }
 
 pub trait MemoExt {
-    fn enumerate_join_order(&self, entry: GroupId) -> Vec<LogicalJoinOrder>;
+    /// The join orders of the group, which may have been merged since `entry` was handed out.
+    fn enumerate_join_order(&self, entry: impl Into<GroupHandle>) -> Vec<LogicalJoinOrder>;
 }
 
 fn enumerate_join_order_expr_inner<M: Memo<DfNodeType> + ?Sized>(
@@ -134,7 +135,8 @@ fn enumerate_join_order_group_inner<M: Memo<DfNodeType> + ?Sized>(
 }
 
 impl<M: Memo<DfNodeType>> MemoExt for M {
-    fn enumerate_join_order(&self, entry: GroupId) -> Vec<LogicalJoinOrder> {
+    fn enumerate_join_order(&self, entry: impl Into<GroupHandle>) -> Vec<LogicalJoinOrder> {
+        let entry = entry.into().resolve(self);
         let mut visited = HashMap::new();
         enumerate_join_order_group_inner(self, entry, &mut visited, &mut false)
             .iter()
@@ -0,0 +1,155 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::time::Duration;
+
+use optd_og_core::cascades::{CascadesStats, GroupId, OptimizerProperties};
+use optd_og_core::nodes::PlanAnnotations;
+
+use crate::plan_nodes::ArcDfPlanNode;
+use crate::{QueryFingerprint, StatsRefreshRecommendation};
+
+/// Everything produced by optimizing a single query with [`crate::DatafusionOptimizer::optimize`].
+pub struct OptimizationResult {
+    /// The root group of the query in the memo table.
+    pub group_id: GroupId,
+    /// The chosen physical plan.
+    pub plan: ArcDfPlanNode,
+    /// The cost and statistics of each node in the chosen plan.
+    pub meta: PlanAnnotations,
+    /// The logical plan produced by the heuristic optimizer, if it is enabled.
+    pub heuristic_plan: Option<ArcDfPlanNode>,
+    /// Identifies the query, e.g., to report the execution of the plan with
+    /// [`crate::DatafusionOptimizer::record_execution`].
+    pub fingerprint: QueryFingerprint,
+    /// The signature of the plan baseline the plan is pinned to, if the query has one and its
+    /// plan could be used, see [`crate::PlanBaselines`].
+    pub baseline: Option<String>,
+    /// The cascades rules whose rewrites the chosen plan is made of, ordered by name.
+    pub winner_rules: Vec<String>,
+    pub metrics: OptimizationMetrics,
+    /// Advisories about the optimization process, e.g., exhausted budgets.
+    pub warnings: Vec<String>,
+    /// The tables scanned by the plan whose statistics should be refreshed, ordered by name, see
+    /// [`crate::StatsFreshnessTracker`].
+    pub stale_stats: Vec<StatsRefreshRecommendation>,
+    pub config: OptimizationConfig,
+    pub timing: OptimizationTiming,
+}
+
+/// Counters of the cascades optimizer for a single query.
+#[derive(Clone, Debug, Default)]
+pub struct OptimizationMetrics {
+    pub explore_group_count: usize,
+    pub optimize_group_count: usize,
+    pub optimize_expr_count: usize,
+    pub apply_rule_count: usize,
+    pub optimize_input_count: usize,
+    /// Number of expressions evicted from the memo table by the eviction policy, see
+    /// [`optd_og_core::cascades::CascadesOptimizer::set_eviction_policy`].
+    pub evicted_expr_count: usize,
+    /// Number of groups in the memo table after optimization.
+    pub group_count: usize,
+    /// Estimated plan space after optimization.
+    pub plan_space: usize,
+}
+
+impl OptimizationMetrics {
+    pub(crate) fn from_stats(stats: &CascadesStats) -> Self {
+        Self {
+            explore_group_count: stats.explore_group_count,
+            optimize_group_count: stats.optimize_group_count,
+            optimize_expr_count: stats.optimize_expr_count,
+            apply_rule_count: stats.apply_rule_count,
+            optimize_input_count: stats.optimize_input_count,
+            evicted_expr_count: stats.evicted_expr_count,
+            group_count: 0,
+            plan_space: 0,
+        }
+    }
+
+    /// The counters accumulated since `before`, as the cascades stats are never reset.
+    pub(crate) fn since(&self, before: &Self) -> Self {
+        Self {
+            explore_group_count: self.explore_group_count - before.explore_group_count,
+            optimize_group_count: self.optimize_group_count - before.optimize_group_count,
+            optimize_expr_count: self.optimize_expr_count - before.optimize_expr_count,
+            apply_rule_count: self.apply_rule_count - before.apply_rule_count,
+            optimize_input_count: self.optimize_input_count - before.optimize_input_count,
+            evicted_expr_count: self.evicted_expr_count - before.evicted_expr_count,
+            group_count: self.group_count,
+            plan_space: self.plan_space,
+        }
+    }
+}
+
+/// The optimizer configuration used for the query.
+#[derive(Clone, Debug)]
+pub struct OptimizationConfig {
+    pub enable_adaptive: bool,
+    pub enable_heuristic: bool,
+    pub cascades: OptimizerProperties,
+}
+
+/// Time spent in each phase of the optimization.
+#[derive(Clone, Debug, Default)]
+pub struct OptimizationTiming {
+    pub heuristic: Duration,
+    /// Time spent in each stage of the cascades optimizer, in the order they run.
+    pub stages: Vec<(String, Duration)>,
+    pub total: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::plan_nodes::{
+        ConstantPred, DfReprPlanNode, DfReprPredNode, JoinType, LogicalJoin, LogicalScan,
+    };
+    use crate::testing::TpchCatalog;
+    use crate::DatafusionOptimizer;
+
+    #[test]
+    fn report_metrics_timing_and_warnings() {
+        let mut optimizer = DatafusionOptimizer::new_physical(Arc::new(TpchCatalog), false);
+        optimizer.set_nlj_row_threshold(Some(100));
+        // a cross product can only be done with a nested loop join
+        let query = LogicalJoin::new(
+            LogicalScan::new("customer".into()).into_plan_node(),
+            LogicalScan::new("orders".into()).into_plan_node(),
+            ConstantPred::bool(true).into_pred_node(),
+            JoinType::Inner,
+        )
+        .into_plan_node();
+        let result = optimizer.optimize(query).unwrap();
+
+        let metrics = &result.metrics;
+        assert!(metrics.explore_group_count > 0);
+        assert!(metrics.optimize_group_count > 0);
+        assert!(metrics.apply_rule_count > 0);
+        assert!(metrics.group_count >= 3);
+        assert!(metrics.plan_space > 0);
+
+        let timing = &result.timing;
+        assert_eq!(
+            timing
+                .stages
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["stage1", "stage2"]
+        );
+        let stages: Duration = timing.stages.iter().map(|(_, time)| *time).sum();
+        assert!(timing.total >= stages);
+        assert!(timing.total >= timing.heuristic);
+
+        assert!(result
+            .warnings
+            .iter()
+            .any(|warning| warning.starts_with("nested loop join over 1000 and 1000 rows")));
+    }
+}
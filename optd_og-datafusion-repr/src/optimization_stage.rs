@@ -0,0 +1,57 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use serde::{Deserialize, Serialize};
+
+/// A phase of the cascades search. The stages of [`crate::DatafusionOptimizer`] run one after
+/// another on the same memo table, so that each stage starts from the plans found by the
+/// previous ones. Only the first stage adds the query to the memo table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageConfig {
+    /// The name of the stage, recorded in the timing and in the `optd_og.stage` span.
+    pub name: String,
+    /// The names of the cascades rules which are not applied in this stage.
+    pub disabled_rules: Vec<String>,
+    /// Overrides the iteration budget of the optimizer for this stage.
+    pub partial_explore_iter: Option<usize>,
+    /// Overrides the plan space budget of the optimizer for this stage.
+    pub partial_explore_space: Option<usize>,
+}
+
+impl StageConfig {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            disabled_rules: vec![],
+            partial_explore_iter: None,
+            partial_explore_space: None,
+        }
+    }
+
+    pub fn disable_rules<S: Into<String>>(mut self, rules: impl IntoIterator<Item = S>) -> Self {
+        self.disabled_rules
+            .extend(rules.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_partial_explore_iter(mut self, iter: usize) -> Self {
+        self.partial_explore_iter = Some(iter);
+        self
+    }
+
+    pub fn with_partial_explore_space(mut self, space: usize) -> Self {
+        self.partial_explore_space = Some(space);
+        self
+    }
+}
+
+/// Find a plan without reordering the joins first, and then explore the join orders starting
+/// from that plan, so that a reasonable plan is available even if the budget is exhausted.
+pub fn default_optimization_stages() -> Vec<StageConfig> {
+    vec![
+        StageConfig::new("stage1").disable_rules(["join_commute_rule", "join_assoc_rule"]),
+        StageConfig::new("stage2"),
+    ]
+}
@@ -9,10 +9,12 @@ use optd_og_core::optimizer::Optimizer;
 use crate::plan_nodes::DfNodeType;
 use crate::properties::column_ref::{ColumnRefPropertyBuilder, GroupColumnRefs};
 use crate::properties::schema::{Schema, SchemaPropertyBuilder};
+use crate::properties::uniqueness::{UniqueKeys, UniquenessPropertyBuilder};
 
 pub trait OptimizerExt: Optimizer<DfNodeType> {
     fn get_schema_of(&self, root_rel: PlanNodeOrGroup<DfNodeType>) -> Schema;
     fn get_column_ref_of(&self, root_rel: PlanNodeOrGroup<DfNodeType>) -> GroupColumnRefs;
+    fn get_unique_keys_of(&self, root_rel: PlanNodeOrGroup<DfNodeType>) -> UniqueKeys;
 }
 
 impl<O: Optimizer<DfNodeType>> OptimizerExt for O {
@@ -23,4 +25,8 @@ impl<O: Optimizer<DfNodeType>> OptimizerExt for O {
     fn get_column_ref_of(&self, root_rel: PlanNodeOrGroup<DfNodeType>) -> GroupColumnRefs {
         self.get_logical_property::<ColumnRefPropertyBuilder>(root_rel, 1)
     }
+
+    fn get_unique_keys_of(&self, root_rel: PlanNodeOrGroup<DfNodeType>) -> UniqueKeys {
+        self.get_logical_property::<UniquenessPropertyBuilder>(root_rel, 2)
+    }
 }
@@ -0,0 +1,143 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Plan baselines, which pin the physical plan of a query so that it does not change when the
+//! cost model or the statistics drift, like the SQL plan baselines of Oracle.
+
+use std::collections::BTreeMap;
+
+use crate::plan_nodes::{dispatch_plan_explain_to_string, ArcDfPlanNode};
+use crate::QueryFingerprint;
+
+/// Identifies a physical plan: the plan explained without costs.
+pub fn plan_signature(plan: &ArcDfPlanNode) -> String {
+    dispatch_plan_explain_to_string(plan.clone(), None)
+}
+
+/// The physical plan pinned for the queries of a fingerprint.
+#[derive(Clone, Debug)]
+pub struct PlanBaseline {
+    pub fingerprint: QueryFingerprint,
+    pub signature: String,
+    pub plan: ArcDfPlanNode,
+}
+
+/// The plan baselines of the optimizer. A query whose fingerprint has a baseline is still
+/// optimized, and the pinned plan is used instead of the winner if the memo table has all of its
+/// expressions, i.e., if the query can still be planned that way.
+#[derive(Default)]
+pub struct PlanBaselines {
+    baselines: BTreeMap<QueryFingerprint, PlanBaseline>,
+}
+
+impl PlanBaselines {
+    /// Pins the plan for the queries of the fingerprint, e.g., the plan of an
+    /// [`crate::OptimizationResult`], replacing the baseline of the fingerprint if there is one.
+    pub fn add(&mut self, fingerprint: QueryFingerprint, plan: ArcDfPlanNode) -> &PlanBaseline {
+        let baseline = PlanBaseline {
+            fingerprint,
+            signature: plan_signature(&plan),
+            plan,
+        };
+        self.baselines.insert(fingerprint, baseline);
+        &self.baselines[&fingerprint]
+    }
+
+    pub fn remove(&mut self, fingerprint: QueryFingerprint) -> Option<PlanBaseline> {
+        self.baselines.remove(&fingerprint)
+    }
+
+    pub fn get(&self, fingerprint: QueryFingerprint) -> Option<&PlanBaseline> {
+        self.baselines.get(&fingerprint)
+    }
+
+    /// The baselines, ordered by fingerprint.
+    pub fn list(&self) -> Vec<&PlanBaseline> {
+        self.baselines.values().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.baselines.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::plan_nodes::{
+        ConstantPred, DfReprPlanNode, DfReprPredNode, JoinType, LogicalJoin, LogicalScan,
+    };
+    use crate::testing::TpchCatalog;
+    use crate::DatafusionOptimizer;
+
+    fn scan(table: &str) -> ArcDfPlanNode {
+        LogicalScan::new(table.into()).into_plan_node()
+    }
+
+    #[test]
+    fn add_and_remove_baselines() {
+        let mut baselines = PlanBaselines::default();
+        let plan = scan("customer");
+        let signature = baselines.add(2, plan.clone()).signature.clone();
+        assert_eq!(signature, plan_signature(&plan));
+        baselines.add(1, scan("orders"));
+        assert_eq!(
+            baselines
+                .list()
+                .iter()
+                .map(|baseline| baseline.fingerprint)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let replaced = scan("nation");
+        baselines.add(2, replaced.clone());
+        assert_eq!(baselines.get(2).unwrap().plan, replaced);
+        assert_eq!(baselines.remove(2).unwrap().plan, replaced);
+        assert!(baselines.get(2).is_none());
+        assert_eq!(baselines.list().len(), 1);
+    }
+
+    #[test]
+    fn pinned_plan_survives_cost_changes() {
+        let mut optimizer = DatafusionOptimizer::new_physical(Arc::new(TpchCatalog), false);
+        let query = LogicalJoin::new(
+            scan("customer"),
+            scan("orders"),
+            ConstantPred::bool(true).into_pred_node(),
+            JoinType::Inner,
+        )
+        .into_plan_node();
+        let result = optimizer.optimize(query.clone()).unwrap();
+        let signature = optimizer
+            .plan_baselines_mut()
+            .add(result.fingerprint, result.plan.clone())
+            .signature
+            .clone();
+
+        // Swap the sizes of the tables, so that the costs of the plans change.
+        {
+            let mut hints = optimizer.cardinality_hints.lock().unwrap();
+            hints.set_table_rows("customer", 1_000_000);
+            hints.set_table_rows("orders", 1);
+        }
+        let pinned = optimizer.optimize(query.clone()).unwrap();
+        assert_eq!(pinned.plan, result.plan);
+        assert_eq!(pinned.baseline, Some(signature));
+
+        // A plan of another group of the memo table does not compute the query.
+        optimizer
+            .plan_baselines_mut()
+            .add(result.fingerprint, result.plan.child_rel(0));
+        let ignored = optimizer.optimize(query).unwrap();
+        assert_eq!(ignored.baseline, None);
+        assert!(ignored
+            .warnings
+            .iter()
+            .any(|warning| warning.starts_with("ignoring the plan baseline of the query")));
+    }
+}
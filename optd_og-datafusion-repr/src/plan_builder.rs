@@ -0,0 +1,135 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use crate::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, ConstantPred, DfReprPlanNode, DfReprPredNode, JoinType, ListPred,
+    LogicalAgg, LogicalDistinct, LogicalFilter, LogicalJoin, LogicalLimit, LogicalProjection,
+    LogicalScan, LogicalSort,
+};
+
+/// Builds a logical plan bottom up without SQL, e.g.,
+/// `PlanBuilder::scan("t1").join(PlanBuilder::scan("t2"), JoinType::Inner, cond).filter(pred)`,
+/// where each step takes the plan built so far as its child. The columns of the predicates are
+/// referred to by their index in the output of the child, and the columns of a join are the
+/// columns of its left child followed by the ones of its right child.
+#[derive(Clone, Debug)]
+pub struct PlanBuilder {
+    plan: ArcDfPlanNode,
+}
+
+impl PlanBuilder {
+    pub fn scan(table: impl Into<String>) -> Self {
+        Self::from_plan(LogicalScan::new(table.into()).into_plan_node())
+    }
+
+    /// Continues building on top of an existing plan.
+    pub fn from_plan(plan: ArcDfPlanNode) -> Self {
+        Self { plan }
+    }
+
+    pub fn filter(self, cond: ArcDfPredNode) -> Self {
+        Self::from_plan(LogicalFilter::new(self.plan, cond).into_plan_node())
+    }
+
+    pub fn project(self, exprs: Vec<ArcDfPredNode>) -> Self {
+        Self::from_plan(LogicalProjection::new(self.plan, ListPred::new(exprs)).into_plan_node())
+    }
+
+    /// Joins the plan built so far, as the left child, with `right`.
+    pub fn join(self, right: PlanBuilder, join_type: JoinType, cond: ArcDfPredNode) -> Self {
+        Self::from_plan(LogicalJoin::new(self.plan, right.plan, cond, join_type).into_plan_node())
+    }
+
+    /// Computes the aggregates `exprs`, e.g., `sum(#1)`, grouped by `groups`. The output has the
+    /// group columns followed by the aggregates.
+    pub fn agg(self, exprs: Vec<ArcDfPredNode>, groups: Vec<ArcDfPredNode>) -> Self {
+        Self::from_plan(
+            LogicalAgg::new(self.plan, ListPred::new(exprs), ListPred::new(groups))
+                .into_plan_node(),
+        )
+    }
+
+    /// Sorts by `exprs`, each a [`SortOrderPred`](crate::plan_nodes::SortOrderPred).
+    pub fn sort(self, exprs: Vec<ArcDfPredNode>) -> Self {
+        Self::from_plan(LogicalSort::new(self.plan, ListPred::new(exprs)).into_plan_node())
+    }
+
+    pub fn distinct(self) -> Self {
+        Self::from_plan(LogicalDistinct::new(self.plan).into_plan_node())
+    }
+
+    pub fn limit(self, skip: u64, fetch: u64) -> Self {
+        Self::from_plan(
+            LogicalLimit::new(
+                self.plan,
+                ConstantPred::uint64(skip).into_pred_node(),
+                ConstantPred::uint64(fetch).into_pred_node(),
+            )
+            .into_plan_node(),
+        )
+    }
+
+    pub fn build(self) -> ArcDfPlanNode {
+        self.plan
+    }
+}
+
+impl From<PlanBuilder> for ArcDfPlanNode {
+    fn from(builder: PlanBuilder) -> Self {
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::{BinOpPred, BinOpType, ColumnRefPred, DfNodeType};
+
+    fn eq(left: usize, right: usize) -> ArcDfPredNode {
+        BinOpPred::new(
+            ColumnRefPred::new(left).into_pred_node(),
+            ColumnRefPred::new(right).into_pred_node(),
+            BinOpType::Eq,
+        )
+        .into_pred_node()
+    }
+
+    #[test]
+    fn build_same_plan_as_nodes() {
+        let plan = PlanBuilder::scan("t1")
+            .join(PlanBuilder::scan("t2"), JoinType::Inner, eq(0, 2))
+            .filter(eq(1, 3))
+            .project(vec![ColumnRefPred::new(0).into_pred_node()])
+            .build();
+
+        let expected = LogicalProjection::new(
+            LogicalFilter::new(
+                LogicalJoin::new(
+                    LogicalScan::new("t1".into()).into_plan_node(),
+                    LogicalScan::new("t2".into()).into_plan_node(),
+                    eq(0, 2),
+                    JoinType::Inner,
+                )
+                .into_plan_node(),
+                eq(1, 3),
+            )
+            .into_plan_node(),
+            ListPred::new(vec![ColumnRefPred::new(0).into_pred_node()]),
+        )
+        .into_plan_node();
+        assert_eq!(plan, expected);
+    }
+
+    #[test]
+    fn build_on_existing_plan() {
+        let plan = PlanBuilder::from_plan(PlanBuilder::scan("t1").build())
+            .distinct()
+            .limit(0, 10)
+            .build();
+        assert_eq!(plan.typ, DfNodeType::Limit);
+        assert_eq!(plan.child_rel(0).typ, DfNodeType::Distinct);
+        assert_eq!(plan.child_rel(0).child_rel(0).typ, DfNodeType::Scan);
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! The semantic properties of a plan that a plan substituted for it must preserve, e.g., when
+//! the query is re-optimized with the runtime statistics or its plan is taken from a cache. The
+//! plans of the same memo group are equivalent by construction, but a limit whose input is not
+//! ordered may return other rows in another plan shape.
+
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use itertools::Itertools;
+
+use crate::plan_nodes::{ArcDfPlanNode, ConstantPred, DfNodeType, DfReprPredNode};
+
+/// A limit of the plan and the ordering of its input, which decides the rows it keeps.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LimitInvariant {
+    pub skip: i64,
+    pub fetch: i64,
+    /// The sort expressions the input of the limit is ordered by, if any.
+    pub ordering: Option<String>,
+}
+
+impl Display for LimitInvariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "skip {} fetch {}", self.skip, self.fetch)?;
+        if let Some(ordering) = &self.ordering {
+            write!(f, " ordered by {}", ordering)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlanInvariants {
+    /// The sort expressions the output of the plan is ordered by, if any.
+    pub ordering: Option<String>,
+    /// The limits of the plan, sorted rather than in plan order as reordering the joins may
+    /// move them to the other side of a join.
+    pub limits: Vec<LimitInvariant>,
+}
+
+impl PlanInvariants {
+    pub fn of(plan: &ArcDfPlanNode) -> Self {
+        let mut limits = vec![];
+        collect_limits(plan, &mut limits);
+        limits.sort();
+        Self {
+            ordering: output_ordering(plan),
+            limits,
+        }
+    }
+
+    /// Fails with the difference if a plan with the invariants `other` cannot be substituted for
+    /// a plan with these invariants.
+    pub fn check_substitution(&self, other: &Self) -> Result<()> {
+        if self.ordering != other.ordering {
+            bail!(
+                "the output is ordered by {} instead of {}",
+                other.ordering.as_deref().unwrap_or("nothing"),
+                self.ordering.as_deref().unwrap_or("nothing")
+            );
+        }
+        if self.limits != other.limits {
+            bail!(
+                "the limits are [{}] instead of [{}]",
+                other.limits.iter().join(", "),
+                self.limits.iter().join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The sort expressions of the sort the output of the node is ordered by, looking through the
+/// nodes which keep the order of their input.
+fn output_ordering(plan: &ArcDfPlanNode) -> Option<String> {
+    match plan.typ {
+        DfNodeType::Sort | DfNodeType::PhysicalSort => Some(plan.predicate(0).to_string()),
+        DfNodeType::Filter
+        | DfNodeType::PhysicalFilter
+        | DfNodeType::Projection
+        | DfNodeType::PhysicalProjection
+        | DfNodeType::Limit
+        | DfNodeType::PhysicalLimit => output_ordering(&plan.child_rel(0)),
+        _ => None,
+    }
+}
+
+fn collect_limits(plan: &ArcDfPlanNode, limits: &mut Vec<LimitInvariant>) {
+    if let DfNodeType::Limit | DfNodeType::PhysicalLimit = plan.typ {
+        let value = |idx| {
+            ConstantPred::from_pred_node(plan.predicate(idx))
+                .unwrap()
+                .value()
+                .as_i64()
+        };
+        let (skip, fetch) = (value(0), value(1));
+        // A limit which keeps all rows is not a semantic property of the plan, and may be
+        // eliminated in one plan but not in another.
+        if skip != 0 || fetch != i64::MAX {
+            limits.push(LimitInvariant {
+                skip,
+                fetch,
+                ordering: output_ordering(&plan.child_rel(0)),
+            });
+        }
+    }
+    for child in &plan.children {
+        collect_limits(&child.unwrap_plan_node(), limits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::{
+        ColumnRefPred, DfReprPlanNode, ListPred, LogicalScan, PhysicalLimit, PhysicalSort,
+        SortOrderPred, SortOrderType,
+    };
+
+    fn limit(child: ArcDfPlanNode, fetch: i64) -> ArcDfPlanNode {
+        PhysicalLimit::new(
+            child,
+            ConstantPred::int64(0).into_pred_node(),
+            ConstantPred::int64(fetch).into_pred_node(),
+        )
+        .into_plan_node()
+    }
+
+    fn sort(child: ArcDfPlanNode, order: SortOrderType) -> ArcDfPlanNode {
+        PhysicalSort::new(
+            child,
+            ListPred::new(vec![SortOrderPred::new(
+                order,
+                ColumnRefPred::new(0).into_pred_node(),
+            )
+            .into_pred_node()]),
+        )
+        .into_plan_node()
+    }
+
+    #[test]
+    fn substitute_plans() {
+        let scan = LogicalScan::new("t1".to_string()).into_plan_node();
+        let top_n = PlanInvariants::of(&limit(sort(scan.clone(), SortOrderType::Asc), 10));
+        assert_eq!(top_n.limits.len(), 1);
+        assert!(top_n.ordering.is_some());
+        assert!(top_n
+            .check_substitution(&PlanInvariants::of(&limit(
+                sort(scan.clone(), SortOrderType::Asc),
+                10
+            )))
+            .is_ok());
+        assert!(top_n
+            .check_substitution(&PlanInvariants::of(&limit(
+                sort(scan.clone(), SortOrderType::Desc),
+                10
+            )))
+            .is_err());
+        assert!(top_n
+            .check_substitution(&PlanInvariants::of(&sort(
+                limit(scan.clone(), 10),
+                SortOrderType::Asc
+            )))
+            .is_err());
+        assert_eq!(
+            PlanInvariants::of(&limit(scan.clone(), i64::MAX)),
+            PlanInvariants::of(&scan)
+        );
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! The JSON format of the plans, to store them, diff them in tests and send them to other
+//! processes. A plan is written with the version of the format, as the format follows the plan
+//! and predicate node types, and a plan of another version is refused instead of misread.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::plan_nodes::ArcDfPlanNode;
+
+/// The version of the JSON format of the plans. Bump it when a change to the node types, the
+/// predicate types or the values changes how the existing plans are written.
+pub const PLAN_JSON_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct VersionedPlanRef<'a> {
+    version: u32,
+    plan: &'a ArcDfPlanNode,
+}
+
+#[derive(Deserialize)]
+struct PlanVersion {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct VersionedPlan {
+    plan: ArcDfPlanNode,
+}
+
+/// Writes a plan as pretty-printed JSON.
+pub fn plan_to_json(plan: &ArcDfPlanNode) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&VersionedPlanRef {
+        version: PLAN_JSON_VERSION,
+        plan,
+    })?)
+}
+
+/// Reads a plan written by [`plan_to_json`] of the same format version.
+pub fn plan_from_json(json: &str) -> Result<ArcDfPlanNode> {
+    let PlanVersion { version } =
+        serde_json::from_str(json).context("failed to read the version of the plan")?;
+    if version != PLAN_JSON_VERSION {
+        bail!(
+            "unsupported plan format version {}, expected {}",
+            version,
+            PLAN_JSON_VERSION
+        );
+    }
+    let VersionedPlan { plan } = serde_json::from_str(json).context("failed to read the plan")?;
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_schema::DataType;
+    use optd_og_core::nodes::{Decimal128, Value};
+    use serde_json::json;
+
+    use super::*;
+    use crate::plan_nodes::{
+        BinOpPred, BinOpType, CastPred, ColumnRefPred, ConstantPred, DfReprPlanNode,
+        DfReprPredNode, FuncPred, FuncType, InListPred, JoinType, LikePred, ListPred, LogOpPred,
+        LogOpType, LogicalAgg, LogicalFilter, LogicalJoin, LogicalLimit, LogicalProjection,
+        LogicalScan, LogicalSort, SortOrderPred, SortOrderType,
+    };
+
+    fn col(idx: usize) -> crate::plan_nodes::ArcDfPredNode {
+        ColumnRefPred::new(idx).into_pred_node()
+    }
+
+    fn filter_plan() -> ArcDfPlanNode {
+        LogicalFilter::new(
+            LogicalScan::new("t1".into()).into_plan_node(),
+            BinOpPred::new(
+                col(0),
+                ConstantPred::int32(1).into_pred_node(),
+                BinOpType::Eq,
+            )
+            .into_pred_node(),
+        )
+        .into_plan_node()
+    }
+
+    /// The format of the plans written before must not change without bumping the version.
+    #[test]
+    fn stable_format() {
+        let written: serde_json::Value =
+            serde_json::from_str(&plan_to_json(&filter_plan()).unwrap()).unwrap();
+        let expected = json!({
+            "version": 1,
+            "plan": {
+                "typ": "Filter",
+                "children": [{
+                    "PlanNode": {
+                        "typ": "Scan",
+                        "children": [],
+                        "predicates": [{
+                            "typ": { "Constant": "Utf8String" },
+                            "children": [],
+                            "data": { "String": "t1" }
+                        }]
+                    }
+                }],
+                "predicates": [{
+                    "typ": { "BinOp": "Eq" },
+                    "children": [
+                        {
+                            "typ": "ColumnRef",
+                            "children": [],
+                            "data": { "UInt64": 0 }
+                        },
+                        {
+                            "typ": { "Constant": "Int32" },
+                            "children": [],
+                            "data": { "Int32": 1 }
+                        }
+                    ],
+                    "data": null
+                }]
+            }
+        });
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn round_trip() {
+        let scan = LogicalScan::new("t1".into()).into_plan_node();
+        let join = LogicalJoin::new(
+            scan.clone(),
+            LogicalScan::new("\"T2\"".into()).into_plan_node(),
+            LogOpPred::new(
+                LogOpType::And,
+                vec![
+                    BinOpPred::new(col(0), col(2), BinOpType::Eq).into_pred_node(),
+                    LikePred::new(
+                        false,
+                        true,
+                        col(3),
+                        ConstantPred::string("a%").into_pred_node(),
+                    )
+                    .into_pred_node(),
+                ],
+            )
+            .into_pred_node(),
+            JoinType::LeftOuter,
+        )
+        .into_plan_node();
+        let agg = LogicalAgg::new(
+            LogicalFilter::new(
+                join,
+                InListPred::new(
+                    CastPred::new(col(1), DataType::Int64).into_pred_node(),
+                    ListPred::new(vec![
+                        ConstantPred::int64(1).into_pred_node(),
+                        ConstantPred::new(Value::Decimal128(Decimal128::new(12345, 2)))
+                            .into_pred_node(),
+                    ]),
+                    true,
+                )
+                .into_pred_node(),
+            )
+            .into_plan_node(),
+            ListPred::new(vec![FuncPred::new(
+                FuncType::new_agg("sum".to_string()),
+                ListPred::new(vec![col(1)]),
+            )
+            .into_pred_node()]),
+            ListPred::new(vec![col(0)]),
+        )
+        .into_plan_node();
+        let plan = LogicalLimit::new(
+            LogicalSort::new(
+                LogicalProjection::new(
+                    agg,
+                    ListPred::new(vec![
+                        col(1),
+                        ConstantPred::float64(0.5).into_pred_node(),
+                        ConstantPred::bool(true).into_pred_node(),
+                    ]),
+                )
+                .into_plan_node(),
+                ListPred::new(vec![
+                    SortOrderPred::new(SortOrderType::Desc, col(0)).into_pred_node()
+                ]),
+            )
+            .into_plan_node(),
+            ConstantPred::uint64(0).into_pred_node(),
+            ConstantPred::uint64(10).into_pred_node(),
+        )
+        .into_plan_node();
+
+        let json = plan_to_json(&plan).unwrap();
+        assert_eq!(plan_from_json(&json).unwrap(), plan);
+    }
+
+    #[test]
+    fn reject_other_version() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(&plan_to_json(&filter_plan()).unwrap()).unwrap();
+        json["version"] = json!(PLAN_JSON_VERSION + 1);
+        let err = plan_from_json(&json.to_string()).unwrap_err();
+        assert!(err.to_string().contains("unsupported plan format version"));
+    }
+}
@@ -6,6 +6,7 @@
 //! Typed interface of plan nodes.
 
 mod agg;
+mod distinct;
 mod empty_relation;
 mod filter;
 mod join;
@@ -13,22 +14,26 @@ mod limit;
 pub(super) mod macros;
 mod predicates;
 mod projection;
+mod runtime_filter;
 mod scan;
+mod singleton;
 mod sort;
 mod subquery;
+mod table_function;
 
 use std::fmt::Debug;
 
-pub use agg::{LogicalAgg, PhysicalAgg};
+pub use agg::{LogicalAgg, PhysicalAgg, PhysicalFinalAgg, PhysicalPartialAgg, PhysicalStreamAgg};
 use arrow_schema::DataType;
+pub use distinct::LogicalDistinct;
 pub use empty_relation::{
     decode_empty_relation_schema, LogicalEmptyRelation, PhysicalEmptyRelation,
 };
 pub use filter::{LogicalFilter, PhysicalFilter};
-pub use join::{JoinType, LogicalJoin, PhysicalHashJoin, PhysicalNestedLoopJoin};
+pub use join::{BuildSide, JoinType, LogicalJoin, PhysicalHashJoin, PhysicalNestedLoopJoin};
 pub use limit::{LogicalLimit, PhysicalLimit};
 use optd_og_core::nodes::{
-    ArcPlanNode, ArcPredNode, NodeType, PlanNode, PlanNodeMeta, PlanNodeMetaMap, PredNode,
+    ArcPlanNode, ArcPredNode, NodeType, PlanAnnotation, PlanAnnotations, PlanNode, PredNode,
 };
 pub use predicates::{
     BetweenPred, BinOpPred, BinOpType, CastPred, ColumnRefPred, ConstantPred, ConstantType,
@@ -37,13 +42,17 @@ pub use predicates::{
 };
 use pretty_xmlish::{Pretty, PrettyConfig};
 pub use projection::{LogicalProjection, PhysicalProjection};
+pub use runtime_filter::PhysicalRuntimeFilter;
 pub use scan::{LogicalScan, PhysicalScan};
+use serde::{Deserialize, Serialize};
+pub use singleton::{LogicalSingleton, PhysicalSingleton};
 pub use sort::{LogicalSort, PhysicalSort};
 pub use subquery::{DependentJoin, RawDependentJoin, SubqueryType};
+pub use table_function::{LogicalTableFunction, PhysicalTableFunction, UNNEST_FUNCTION};
 
 use crate::explain::{explain_plan_node, explain_pred_node};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum DfPredType {
     List,
     Constant(ConstantType),
@@ -69,7 +78,7 @@ impl std::fmt::Display for DfPredType {
 
 /// DfNodeType FAQ:
 ///   - The define_plan_node!() macro defines what the children of each join node are
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum DfNodeType {
     // Developers: update `is_logical` function after adding new plan nodes
     // Plan nodes
@@ -83,16 +92,25 @@ pub enum DfNodeType {
     Agg,
     EmptyRelation,
     Limit,
+    Distinct,
+    TableFunction,
+    Singleton,
     // Physical plan nodes
     PhysicalProjection,
     PhysicalFilter,
     PhysicalScan,
     PhysicalSort,
     PhysicalAgg,
+    PhysicalStreamAgg,
+    PhysicalPartialAgg,
+    PhysicalFinalAgg,
     PhysicalHashJoin(JoinType),
     PhysicalNestedLoopJoin(JoinType),
     PhysicalEmptyRelation,
     PhysicalLimit,
+    PhysicalTableFunction,
+    PhysicalRuntimeFilter,
+    PhysicalSingleton,
 }
 
 impl std::fmt::Display for DfNodeType {
@@ -114,6 +132,9 @@ impl NodeType for DfNodeType {
                 | Self::Agg
                 | Self::EmptyRelation
                 | Self::Limit
+                | Self::Distinct
+                | Self::TableFunction
+                | Self::Singleton
         )
     }
 }
@@ -126,9 +147,9 @@ pub trait DfReprPlanNode: 'static + Clone {
 
     fn from_plan_node(plan_node: ArcDfPlanNode) -> Option<Self>;
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static>;
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static>;
 
-    fn explain_to_string(&self, meta_map: Option<&PlanNodeMetaMap>) -> String {
+    fn explain_to_string(&self, meta_map: Option<&PlanAnnotations>) -> String {
         let mut config = PrettyConfig {
             need_boundaries: false,
             reduced_spaces: false,
@@ -140,10 +161,8 @@ pub trait DfReprPlanNode: 'static + Clone {
         out
     }
 
-    fn get_meta<'a>(&self, meta_map: &'a PlanNodeMetaMap) -> &'a PlanNodeMeta {
-        meta_map
-            .get(&(self.clone().into_plan_node().as_ref() as *const _ as usize))
-            .unwrap()
+    fn get_meta<'a>(&self, meta_map: &'a PlanAnnotations) -> &'a PlanAnnotation {
+        meta_map.get(&self.clone().into_plan_node()).unwrap()
     }
 }
 
@@ -156,7 +175,7 @@ impl DfReprPlanNode for ArcDfPlanNode {
         Some(pred_node)
     }
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         explain_plan_node(self.clone(), meta_map)
     }
 }
@@ -169,9 +188,9 @@ pub trait DfReprPredNode: 'static + Clone {
 
     fn from_pred_node(pred_node: ArcDfPredNode) -> Option<Self>;
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static>;
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static>;
 
-    fn explain_to_string(&self, meta_map: Option<&PlanNodeMetaMap>) -> String {
+    fn explain_to_string(&self, meta_map: Option<&PlanAnnotations>) -> String {
         let mut config = PrettyConfig {
             need_boundaries: false,
             reduced_spaces: false,
@@ -193,14 +212,14 @@ impl DfReprPredNode for ArcDfPredNode {
         Some(pred_node)
     }
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         explain_pred_node(self.clone(), meta_map)
     }
 }
 
 pub fn dispatch_plan_explain_to_string(
     plan_node: ArcDfPlanNode,
-    meta_map: Option<&PlanNodeMetaMap>,
+    meta_map: Option<&PlanAnnotations>,
 ) -> String {
     let mut config = PrettyConfig {
         need_boundaries: false,
@@ -4,7 +4,7 @@
 // https://opensource.org/licenses/MIT.
 
 use super::macros::define_plan_node;
-use super::predicates::ListPred;
+use super::predicates::{ConstantPred, ListPred};
 use super::{ArcDfPlanNode, DfNodeType, DfPlanNode, DfReprPlanNode};
 
 #[derive(Clone, Debug)]
@@ -32,3 +32,50 @@ define_plan_node!(
         { 1, groups: ListPred }
     ]
 );
+
+/// An aggregation over an input which is sorted on the group-by keys, so that each group
+/// can be finalized as soon as its keys change without building a hash table.
+#[derive(Clone, Debug)]
+pub struct PhysicalStreamAgg(pub ArcDfPlanNode);
+
+define_plan_node!(
+    PhysicalStreamAgg : DfPlanNode,
+    PhysicalStreamAgg, [
+        { 0, child: ArcDfPlanNode }
+    ], [
+        { 0, aggrs: ListPred },
+        { 1, groups: ListPred }
+    ]
+);
+
+/// The first phase of a two-phase aggregation, which aggregates each of the `partitions` input
+/// partitions independently into partial aggregate states.
+#[derive(Clone, Debug)]
+pub struct PhysicalPartialAgg(pub ArcDfPlanNode);
+
+define_plan_node!(
+    PhysicalPartialAgg : DfPlanNode,
+    PhysicalPartialAgg, [
+        { 0, child: ArcDfPlanNode }
+    ], [
+        { 0, aggrs: ListPred },
+        { 1, groups: ListPred },
+        { 2, partitions: ConstantPred }
+    ]
+);
+
+/// The second phase of a two-phase aggregation, which merges the partial aggregate states
+/// produced by its [`PhysicalPartialAgg`] child. The aggregate expressions and groups are the
+/// same as the ones of the partial aggregation.
+#[derive(Clone, Debug)]
+pub struct PhysicalFinalAgg(pub ArcDfPlanNode);
+
+define_plan_node!(
+    PhysicalFinalAgg : DfPlanNode,
+    PhysicalFinalAgg, [
+        { 0, child: ArcDfPlanNode }
+    ], [
+        { 0, aggrs: ListPred },
+        { 1, groups: ListPred }
+    ]
+);
@@ -0,0 +1,62 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use optd_og_core::nodes::{PlanAnnotations, PlanNodeOrGroup};
+use pretty_xmlish::Pretty;
+
+use super::{ArcDfPlanNode, DfNodeType, DfPlanNode, DfReprPlanNode};
+use crate::explain::Insertable;
+
+/// Removes duplicated rows of the child. There is no physical counterpart of this node: it is
+/// always converted into an aggregation grouping by all columns, or eliminated if the child is
+/// already unique.
+#[derive(Clone, Debug)]
+pub struct LogicalDistinct(pub ArcDfPlanNode);
+
+impl DfReprPlanNode for LogicalDistinct {
+    fn into_plan_node(self) -> ArcDfPlanNode {
+        self.0
+    }
+
+    fn from_plan_node(plan_node: ArcDfPlanNode) -> Option<Self> {
+        if plan_node.typ != DfNodeType::Distinct {
+            return None;
+        }
+        Some(Self(plan_node))
+    }
+
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
+        let mut fields = vec![];
+        if let Some(meta_map) = meta_map {
+            fields = fields.with_meta(self.0.get_meta(meta_map));
+        }
+        Pretty::simple_record(
+            "LogicalDistinct",
+            fields,
+            vec![self.child().unwrap_plan_node().explain(meta_map)],
+        )
+    }
+}
+
+impl LogicalDistinct {
+    pub fn new(child: ArcDfPlanNode) -> LogicalDistinct {
+        Self::new_unchecked(child)
+    }
+
+    pub fn new_unchecked(child: impl Into<PlanNodeOrGroup<DfNodeType>>) -> LogicalDistinct {
+        LogicalDistinct(
+            DfPlanNode {
+                typ: DfNodeType::Distinct,
+                children: vec![child.into()],
+                predicates: vec![],
+            }
+            .into(),
+        )
+    }
+
+    pub fn child(&self) -> PlanNodeOrGroup<DfNodeType> {
+        self.0.child(0)
+    }
+}
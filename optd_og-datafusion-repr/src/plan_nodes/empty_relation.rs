@@ -6,7 +6,7 @@
 use std::sync::Arc;
 
 use bincode;
-use optd_og_core::nodes::PlanNodeMetaMap;
+use optd_og_core::nodes::PlanAnnotations;
 use pretty_xmlish::Pretty;
 
 use super::{
@@ -31,7 +31,7 @@ impl DfReprPlanNode for LogicalEmptyRelation {
         Some(Self(plan_node))
     }
 
-    fn explain(&self, _meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, _meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         Pretty::childless_record(
             "LogicalEmptyRelation",
             vec![("produce_one_row", self.produce_one_row().to_string().into())],
@@ -82,7 +82,7 @@ impl DfReprPlanNode for PhysicalEmptyRelation {
         Some(Self(plan_node))
     }
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         let mut fields = vec![("produce_one_row", self.produce_one_row().to_string().into())];
         if let Some(meta_map) = meta_map {
             fields = fields.with_meta(self.0.get_meta(meta_map));
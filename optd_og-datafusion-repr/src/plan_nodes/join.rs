@@ -6,10 +6,18 @@
 use core::fmt;
 use std::fmt::Display;
 
+use optd_og_core::nodes::{PlanAnnotations, PlanNodeOrGroup};
+use pretty_xmlish::Pretty;
+use serde::{Deserialize, Serialize};
+
 use super::macros::define_plan_node;
-use super::{ArcDfPlanNode, ArcDfPredNode, DfNodeType, DfPlanNode, DfReprPlanNode, ListPred};
+use super::{
+    ArcDfPlanNode, ArcDfPredNode, ConstantPred, DfNodeType, DfPlanNode, DfReprPlanNode,
+    DfReprPredNode, ListPred,
+};
+use crate::explain::Insertable;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum JoinType {
     Inner = 1,
     FullOuter,
@@ -54,19 +62,187 @@ define_plan_node!(
     ], { join_type: JoinType }
 );
 
+/// The input a hash join builds its hash table on, the other one being streamed through it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BuildSide {
+    Left,
+    Right,
+}
+
+impl Display for BuildSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildSide::Left => write!(f, "left"),
+            BuildSide::Right => write!(f, "right"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PhysicalHashJoin(pub ArcDfPlanNode);
 
-define_plan_node!(
-    PhysicalHashJoin : DfPlanNode,
-    PhysicalHashJoin, [
-        { 0, left: ArcDfPlanNode },
-        { 1, right: ArcDfPlanNode }
-    ], [
-        { 0, left_keys: ListPred },
-        { 1, right_keys: ListPred }
-    ], { join_type: JoinType }
-);
+impl DfReprPlanNode for PhysicalHashJoin {
+    fn into_plan_node(self) -> ArcDfPlanNode {
+        self.0
+    }
+
+    fn from_plan_node(plan_node: ArcDfPlanNode) -> Option<Self> {
+        if let DfNodeType::PhysicalHashJoin(_) = &plan_node.typ {
+            Some(Self(plan_node))
+        } else {
+            None
+        }
+    }
+
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
+        let mut fields = vec![
+            ("join_type", self.join_type().to_string().into()),
+            ("left_keys", self.left_keys().explain(meta_map)),
+            ("right_keys", self.right_keys().explain(meta_map)),
+        ];
+        if self.build_side() == BuildSide::Right {
+            fields.push(("build_side", self.build_side().to_string().into()));
+        }
+        if let Some(filter_id) = self.runtime_filter() {
+            fields.push(("runtime_filter", filter_id.to_string().into()));
+        }
+        if let Some(meta_map) = meta_map {
+            fields = fields.with_meta(self.0.get_meta(meta_map));
+        }
+        Pretty::simple_record(
+            "PhysicalHashJoin",
+            fields,
+            vec![
+                self.left().unwrap_plan_node().explain(meta_map),
+                self.right().unwrap_plan_node().explain(meta_map),
+            ],
+        )
+    }
+}
+
+impl PhysicalHashJoin {
+    /// A hash join building its hash table on the left input.
+    pub fn new(
+        left: ArcDfPlanNode,
+        right: ArcDfPlanNode,
+        left_keys: ListPred,
+        right_keys: ListPred,
+        join_type: JoinType,
+    ) -> PhysicalHashJoin {
+        Self::new_unchecked(left, right, left_keys, right_keys, join_type)
+    }
+
+    pub fn new_unchecked(
+        left: impl Into<PlanNodeOrGroup<DfNodeType>>,
+        right: impl Into<PlanNodeOrGroup<DfNodeType>>,
+        left_keys: ListPred,
+        right_keys: ListPred,
+        join_type: JoinType,
+    ) -> PhysicalHashJoin {
+        PhysicalHashJoin(
+            DfPlanNode {
+                typ: DfNodeType::PhysicalHashJoin(join_type),
+                children: vec![left.into(), right.into()],
+                predicates: vec![left_keys.into_pred_node(), right_keys.into_pred_node()],
+            }
+            .into(),
+        )
+    }
+
+    /// The same join building its hash table on the given input. Only the inner joins can build
+    /// on the right input, as the other joins keep the rows of the left one. The build side is
+    /// chosen by the cost model, from the alternatives produced by the hash join rule.
+    pub fn with_build_side(&self, build_side: BuildSide) -> PhysicalHashJoin {
+        assert!(
+            build_side == BuildSide::Left || *self.join_type() == JoinType::Inner,
+            "only inner joins can build on the right input"
+        );
+        self.with_annotations(build_side, self.runtime_filter())
+    }
+
+    /// The same join publishing the range of its build side keys to the
+    /// [`super::PhysicalRuntimeFilter`] with the given id, below its probe side.
+    pub fn with_runtime_filter(&self, filter_id: u64) -> PhysicalHashJoin {
+        self.with_annotations(self.build_side(), Some(filter_id))
+    }
+
+    /// The build side and the runtime filter follow the join keys, the build side is only
+    /// omitted if it is the left input and there is no runtime filter.
+    fn with_annotations(
+        &self,
+        build_side: BuildSide,
+        runtime_filter: Option<u64>,
+    ) -> PhysicalHashJoin {
+        let mut predicates = self.0.predicates[..2].to_vec();
+        if build_side == BuildSide::Right || runtime_filter.is_some() {
+            predicates.push(ConstantPred::bool(build_side == BuildSide::Right).into_pred_node());
+        }
+        if let Some(filter_id) = runtime_filter {
+            predicates.push(ConstantPred::uint64(filter_id).into_pred_node());
+        }
+        PhysicalHashJoin(
+            DfPlanNode {
+                typ: self.0.typ.clone(),
+                children: self.0.children.clone(),
+                predicates,
+            }
+            .into(),
+        )
+    }
+
+    pub fn left(&self) -> PlanNodeOrGroup<DfNodeType> {
+        self.0.child(0)
+    }
+
+    pub fn right(&self) -> PlanNodeOrGroup<DfNodeType> {
+        self.0.child(1)
+    }
+
+    pub fn left_keys(&self) -> ListPred {
+        ListPred::from_pred_node(self.0.predicate(0)).unwrap()
+    }
+
+    pub fn right_keys(&self) -> ListPred {
+        ListPred::from_pred_node(self.0.predicate(1)).unwrap()
+    }
+
+    pub fn join_type(&self) -> &JoinType {
+        let DfNodeType::PhysicalHashJoin(join_type) = &self.0.typ else {
+            unreachable!()
+        };
+        join_type
+    }
+
+    pub fn build_side(&self) -> BuildSide {
+        Self::build_side_of(&self.0.predicates)
+    }
+
+    /// The id of the runtime filter the join publishes the range of its build side keys to.
+    pub fn runtime_filter(&self) -> Option<u64> {
+        self.0.predicates.get(3).map(|filter_id| {
+            ConstantPred::from_pred_node(filter_id.clone())
+                .unwrap()
+                .value()
+                .as_u64()
+        })
+    }
+
+    /// The build side of a hash join with the given predicates, which is the left input unless
+    /// it is marked after the join keys.
+    pub fn build_side_of(predicates: &[ArcDfPredNode]) -> BuildSide {
+        match predicates.get(2) {
+            Some(build_right)
+                if ConstantPred::from_pred_node(build_right.clone())
+                    .unwrap()
+                    .value()
+                    .as_bool() =>
+            {
+                BuildSide::Right
+            }
+            _ => BuildSide::Left,
+        }
+    }
+}
 
 impl LogicalJoin {
     /// Takes in left/right schema sizes, and maps a column index to be as if it
@@ -25,7 +25,7 @@ macro_rules! define_plan_node {
                 }
             }
 
-            fn explain(&self, meta_map: Option<&crate::PlanNodeMetaMap>) -> pretty_xmlish::Pretty<'static> {
+            fn explain(&self, meta_map: Option<&crate::PlanAnnotations>) -> pretty_xmlish::Pretty<'static> {
                 use crate::plan_nodes::{DfReprPredNode};
                 use crate::explain::Insertable;
 
@@ -3,8 +3,9 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use optd_og_core::nodes::PlanNodeMetaMap;
+use optd_og_core::nodes::PlanAnnotations;
 use pretty_xmlish::Pretty;
+use serde::{Deserialize, Serialize};
 
 use crate::plan_nodes::{ArcDfPredNode, DfPredNode, DfPredType, DfReprPredNode};
 
@@ -12,7 +13,7 @@ use crate::plan_nodes::{ArcDfPredNode, DfPredNode, DfPredType, DfReprPredNode};
 /// functions     to distinguish between them matches how datafusion::logical_expr::Operator does
 /// things I initially thought about splitting BinOpType into three "subenums". However, having two
 /// nested levels of     types leads to some really confusing code
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum BinOpType {
     // numerical
     Add,
@@ -50,6 +51,19 @@ impl BinOpType {
             Self::Eq | Self::Neq | Self::Gt | Self::Lt | Self::Geq | Self::Leq
         )
     }
+
+    /// The comparison operator which is true exactly when this one is false, ignoring NULLs.
+    pub fn negated_comparison(&self) -> Option<Self> {
+        match self {
+            Self::Eq => Some(Self::Neq),
+            Self::Neq => Some(Self::Eq),
+            Self::Gt => Some(Self::Leq),
+            Self::Lt => Some(Self::Geq),
+            Self::Geq => Some(Self::Lt),
+            Self::Leq => Some(Self::Gt),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -96,7 +110,7 @@ impl DfReprPredNode for BinOpPred {
         Some(Self(pred_node))
     }
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         Pretty::simple_record(
             self.op_type().to_string(),
             vec![],
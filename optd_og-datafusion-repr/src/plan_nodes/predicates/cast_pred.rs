@@ -4,7 +4,7 @@
 // https://opensource.org/licenses/MIT.
 
 use arrow_schema::DataType;
-use optd_og_core::nodes::PlanNodeMetaMap;
+use optd_og_core::nodes::PlanAnnotations;
 use pretty_xmlish::Pretty;
 
 use super::data_type_pred::DataTypePred;
@@ -48,7 +48,7 @@ impl DfReprPredNode for CastPred {
         Some(Self(pred_node))
     }
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         Pretty::simple_record(
             "Cast",
             vec![
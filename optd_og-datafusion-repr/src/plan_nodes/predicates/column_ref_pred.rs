@@ -3,7 +3,7 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use optd_og_core::nodes::{PlanNodeMetaMap, Value};
+use optd_og_core::nodes::{PlanAnnotations, Value};
 use pretty_xmlish::Pretty;
 
 use crate::plan_nodes::{ArcDfPredNode, DfPredNode, DfPredType, DfReprPredNode};
@@ -48,7 +48,7 @@ impl DfReprPredNode for ColumnRefPred {
         Some(Self(pred_node))
     }
 
-    fn explain(&self, _meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, _meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         Pretty::display(&format!("#{}", self.index()))
     }
 }
@@ -5,12 +5,12 @@
 
 use std::sync::Arc;
 
-use arrow_schema::{DataType, IntervalUnit};
-use optd_og_core::nodes::{PlanNodeMetaMap, SerializableOrderedF64, Value};
+use arrow_schema::{DataType, Field, Fields, IntervalUnit, TimeUnit, DECIMAL128_MAX_PRECISION};
+use optd_og_core::nodes::{Decimal128, PlanAnnotations, SerializableOrderedF64, Value};
 use pretty_xmlish::Pretty;
 use serde::{Deserialize, Serialize};
 
-use crate::plan_nodes::{ArcDfPredNode, DfPredNode, DfPredType, DfReprPredNode};
+use crate::plan_nodes::{ArcDfPredNode, DataTypePred, DfPredNode, DfPredType, DfReprPredNode};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum ConstantType {
@@ -24,11 +24,23 @@ pub enum ConstantType {
     Int16,
     Int32,
     Int64,
+    Float32,
     Float64,
     Date,
+    Date64,
+    /// The time zone is not kept, as it does not change the stored UTC value. A constant with a
+    /// time zone is a cast of the constant to the zoned type, see [`ConstantPred::timestamp`].
+    Timestamp(TimeUnit),
+    Time(TimeUnit),
     IntervalMonthDateNano,
-    Decimal,
+    /// The precision and the scale of the decimal.
+    Decimal128(u8, i8),
     Binary,
+    /// The nested types. The types of their elements and fields are not part of the constant
+    /// type, see [`ConstantPred::data_type`].
+    List,
+    Struct,
+    Map,
 }
 
 impl ConstantType {
@@ -46,14 +58,21 @@ impl ConstantType {
             Value::Int64(_) => ConstantType::Int64,
             Value::Float(_) => ConstantType::Float64,
             Value::Date32(_) => ConstantType::Date,
-            Value::Decimal128(_) => ConstantType::Decimal,
+            Value::Decimal128(decimal) => {
+                ConstantType::Decimal128(DECIMAL128_MAX_PRECISION, decimal.scale)
+            }
+            Value::Timestamp(_) => ConstantType::Timestamp(TimeUnit::Nanosecond),
+            Value::Time(_) => ConstantType::Time(TimeUnit::Nanosecond),
+            Value::List(_) => ConstantType::List,
+            Value::Struct(_) => ConstantType::Struct,
+            Value::Map(_) => ConstantType::Map,
             _ => unimplemented!("get_data_type_from_value() not implemented for value {value}"),
         }
     }
 
     // TODO: current DataType and ConstantType are not 1 to 1 mapping
     // optd_og schema stores constantType from data type in catalog.get
-    // for decimal128, the precision is lost
+    // for decimal256, the precision is capped to the one of decimal128
     pub fn from_data_type(data_type: DataType) -> Self {
         match data_type {
             DataType::Binary => ConstantType::Binary,
@@ -66,15 +85,29 @@ impl ConstantType {
             DataType::Int16 => ConstantType::Int16,
             DataType::Int32 => ConstantType::Int32,
             DataType::Int64 => ConstantType::Int64,
+            DataType::Float32 => ConstantType::Float32,
             DataType::Float64 => ConstantType::Float64,
             DataType::Date32 => ConstantType::Date,
+            DataType::Date64 => ConstantType::Date64,
+            DataType::Timestamp(unit, _) => ConstantType::Timestamp(unit),
+            DataType::Time32(unit) | DataType::Time64(unit) => ConstantType::Time(unit),
             DataType::Interval(IntervalUnit::MonthDayNano) => ConstantType::IntervalMonthDateNano,
             DataType::Utf8 => ConstantType::Utf8String,
-            DataType::Decimal128(_, _) => ConstantType::Decimal,
+            DataType::Decimal128(precision, scale) => ConstantType::Decimal128(precision, scale),
+            DataType::Decimal256(precision, scale) => {
+                ConstantType::Decimal128(precision.min(DECIMAL128_MAX_PRECISION), scale)
+            }
+            DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _) => {
+                ConstantType::List
+            }
+            DataType::Struct(_) => ConstantType::Struct,
+            DataType::Map(_, _) => ConstantType::Map,
             _ => unimplemented!("no conversion to ConstantType for DataType {data_type}"),
         }
     }
 
+    /// The datafusion type of the constant type. The nested types lose the types of their
+    /// elements and fields, which are `Null`.
     pub fn into_data_type(&self) -> DataType {
         match self {
             ConstantType::Binary => DataType::Binary,
@@ -87,15 +120,44 @@ impl ConstantType {
             ConstantType::Int16 => DataType::Int16,
             ConstantType::Int32 => DataType::Int32,
             ConstantType::Int64 => DataType::Int64,
+            ConstantType::Float32 => DataType::Float32,
             ConstantType::Float64 => DataType::Float64,
             ConstantType::Date => DataType::Date32,
+            ConstantType::Date64 => DataType::Date64,
+            ConstantType::Timestamp(unit) => DataType::Timestamp(*unit, None),
+            ConstantType::Time(unit @ (TimeUnit::Second | TimeUnit::Millisecond)) => {
+                DataType::Time32(*unit)
+            }
+            ConstantType::Time(unit) => DataType::Time64(*unit),
             ConstantType::IntervalMonthDateNano => DataType::Interval(IntervalUnit::MonthDayNano),
-            ConstantType::Decimal => DataType::Float64,
+            ConstantType::Decimal128(precision, scale) => DataType::Decimal128(*precision, *scale),
             ConstantType::Utf8String => DataType::Utf8,
+            ConstantType::List => DataType::new_list(DataType::Null, true),
+            ConstantType::Struct => DataType::Struct(Fields::empty()),
+            ConstantType::Map => DataType::Map(
+                Arc::new(Field::new(
+                    "entries",
+                    DataType::Struct(Fields::from(vec![
+                        Field::new("key", DataType::Null, false),
+                        Field::new("value", DataType::Null, true),
+                    ])),
+                    false,
+                )),
+                false,
+            ),
         }
     }
 }
 
+fn nanos_per_unit(unit: TimeUnit) -> i64 {
+    match unit {
+        TimeUnit::Second => 1_000_000_000,
+        TimeUnit::Millisecond => 1_000_000,
+        TimeUnit::Microsecond => 1_000,
+        TimeUnit::Nanosecond => 1,
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ConstantPred(pub ArcDfPredNode);
 
@@ -174,10 +236,28 @@ impl ConstantPred {
         Self::new_with_type(Value::Int64(value), ConstantType::Date)
     }
 
-    pub fn decimal(value: f64) -> Self {
+    /// A timestamp of `value` units since the UNIX epoch. A timestamp with a time zone is built
+    /// by casting it to the zoned type, which only changes how it is displayed.
+    pub fn timestamp(value: i64, unit: TimeUnit) -> Self {
         Self::new_with_type(
-            Value::Float(SerializableOrderedF64(value.into())),
-            ConstantType::Decimal,
+            Value::Timestamp(value.saturating_mul(nanos_per_unit(unit))),
+            ConstantType::Timestamp(unit),
+        )
+    }
+
+    /// A time of `value` units since midnight.
+    pub fn time(value: i64, unit: TimeUnit) -> Self {
+        Self::new_with_type(
+            Value::Time(value * nanos_per_unit(unit)),
+            ConstantType::Time(unit),
+        )
+    }
+
+    /// A decimal of `value * 10^-scale`.
+    pub fn decimal(value: i128, precision: u8, scale: i8) -> Self {
+        Self::new_with_type(
+            Value::Decimal128(Decimal128::new(value, scale)),
+            ConstantType::Decimal128(precision, scale),
         )
     }
 
@@ -185,11 +265,46 @@ impl ConstantPred {
         Self::new_with_type(Value::Serialized(value), ConstantType::Binary)
     }
 
+    /// A nested constant, e.g., an array or a struct, of `data_type`. The constant keeps its
+    /// datafusion type as its child, as the constant type does not have the types of the
+    /// elements and the fields.
+    pub fn nested(value: Value, data_type: DataType) -> Self {
+        ConstantPred(
+            DfPredNode {
+                typ: DfPredType::Constant(ConstantType::from_data_type(data_type.clone())),
+                children: vec![DataTypePred::new(data_type).into_pred_node()],
+                data: Some(value),
+            }
+            .into(),
+        )
+    }
+
+    /// Gets the datafusion type of the constant, with the types of the elements and the fields
+    /// of a nested constant.
+    pub fn data_type(&self) -> DataType {
+        match self.0.children.first() {
+            Some(data_type) => DataTypePred::from_pred_node(data_type.clone())
+                .unwrap()
+                .data_type(),
+            None => self.constant_type().into_data_type(),
+        }
+    }
+
     /// Gets the constant value.
     pub fn value(&self) -> Value {
         self.0.data.clone().unwrap()
     }
 
+    /// Gets a timestamp or time constant in the unit of its type, e.g., for a
+    /// `Timestamp(Millisecond)` constant, the milliseconds since the UNIX epoch.
+    pub fn value_in_unit(&self) -> i64 {
+        match (self.constant_type(), self.value()) {
+            (ConstantType::Timestamp(unit), Value::Timestamp(nanos))
+            | (ConstantType::Time(unit), Value::Time(nanos)) => nanos / nanos_per_unit(unit),
+            (typ, value) => panic!("{value} of type {typ:?} is not a timestamp or time"),
+        }
+    }
+
     pub fn constant_type(&self) -> ConstantType {
         if let DfPredType::Constant(typ) = self.0.typ {
             typ
@@ -212,7 +327,7 @@ impl DfReprPredNode for ConstantPred {
         }
     }
 
-    fn explain(&self, _meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, _meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         if self.constant_type() == ConstantType::IntervalMonthDateNano {
             let value = self.value().as_i128();
             let month = (value >> 96) as u32;
@@ -227,3 +342,68 @@ impl DfReprPredNode for ConstantPred {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_type_round_trip() {
+        for data_type in [
+            DataType::Boolean,
+            DataType::Int8,
+            DataType::Int16,
+            DataType::UInt8,
+            DataType::UInt16,
+            DataType::UInt32,
+            DataType::UInt64,
+            DataType::Float32,
+            DataType::Date64,
+            DataType::Timestamp(TimeUnit::Millisecond, None),
+            DataType::Time32(TimeUnit::Second),
+            DataType::Time64(TimeUnit::Microsecond),
+            DataType::Decimal128(15, 2),
+            DataType::Binary,
+        ] {
+            assert_eq!(
+                ConstantType::from_data_type(data_type.clone()).into_data_type(),
+                data_type
+            );
+        }
+        assert_eq!(
+            ConstantType::from_data_type(DataType::Timestamp(
+                TimeUnit::Nanosecond,
+                Some("UTC".into())
+            )),
+            ConstantType::Timestamp(TimeUnit::Nanosecond)
+        );
+        assert_eq!(
+            ConstantType::from_data_type(DataType::Decimal256(40, 2)),
+            ConstantType::Decimal128(38, 2)
+        );
+    }
+
+    #[test]
+    fn timestamp_in_unit() {
+        let constant = ConstantPred::timestamp(1_700_000_000_123, TimeUnit::Millisecond);
+        assert_eq!(
+            constant.value(),
+            Value::Timestamp(1_700_000_000_123_000_000)
+        );
+        assert_eq!(constant.value_in_unit(), 1_700_000_000_123);
+        assert_eq!(ConstantPred::time(61, TimeUnit::Second).value_in_unit(), 61);
+    }
+
+    #[test]
+    fn nested_constant_keeps_data_type() {
+        let data_type = DataType::new_list(DataType::Int32, false);
+        let constant = ConstantPred::nested(
+            Value::List(vec![Value::Int32(1), Value::Int32(2)].into()),
+            data_type.clone(),
+        );
+        assert_eq!(constant.constant_type(), ConstantType::List);
+        assert_eq!(constant.data_type(), data_type);
+        assert_eq!(constant.value().to_string(), "[1(i32), 2(i32)]");
+        assert_eq!(ConstantPred::int32(1).data_type(), DataType::Int32);
+    }
+}
@@ -4,7 +4,7 @@
 // https://opensource.org/licenses/MIT.
 
 use arrow_schema::DataType;
-use optd_og_core::nodes::PlanNodeMetaMap;
+use optd_og_core::nodes::PlanAnnotations;
 use pretty_xmlish::Pretty;
 
 use crate::plan_nodes::{ArcDfPredNode, DfPredNode, DfPredType, DfReprPredNode};
@@ -45,7 +45,7 @@ impl DfReprPredNode for DataTypePred {
         Some(Self(pred_node))
     }
 
-    fn explain(&self, _meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, _meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         Pretty::display(&self.data_type().to_string())
     }
 }
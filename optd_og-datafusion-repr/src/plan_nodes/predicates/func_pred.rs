@@ -3,16 +3,20 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use arrow_schema::DataType;
-use optd_og_core::nodes::PlanNodeMetaMap;
+use optd_og_core::nodes::PlanAnnotations;
 use pretty_xmlish::Pretty;
+use serde::{Deserialize, Serialize};
 
 use super::ListPred;
 use crate::plan_nodes::{ArcDfPredNode, DfPredNode, DfPredType, DfReprPredNode};
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+/// The identity of a function. A scalar function does not carry its return type: it depends on
+/// the types of the arguments, which datafusion may infer differently for the same call in
+/// different parts of a query. Keeping it out of the predicate lets the memo table dedup the
+/// repeated calls, and the bridge resolves the return type when lowering the function.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum FuncType {
-    Scalar(String, DataType),
+    Scalar(String),
     Agg(String),
     Case,
     Not,
@@ -23,7 +27,7 @@ pub enum FuncType {
 impl std::fmt::Display for FuncType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            FuncType::Scalar(func_id, _) => {
+            FuncType::Scalar(func_id) => {
                 write!(f, "Scalar({})", heck::AsUpperCamelCase(func_id))
             }
             FuncType::Agg(func_id) => write!(f, "Agg({})", heck::AsUpperCamelCase(func_id)),
@@ -33,8 +37,8 @@ impl std::fmt::Display for FuncType {
 }
 
 impl FuncType {
-    pub fn new_scalar(func_id: String, return_type: DataType) -> Self {
-        FuncType::Scalar(func_id, return_type) // TODO: infer ret type in optd_og
+    pub fn new_scalar(func_id: String) -> Self {
+        FuncType::Scalar(func_id)
     }
 
     pub fn new_agg(func_id: String) -> Self {
@@ -89,7 +93,7 @@ impl DfReprPredNode for FuncPred {
         Some(Self(pred_node))
     }
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         Pretty::simple_record(
             self.func().to_string(),
             vec![],
@@ -97,3 +101,43 @@ impl DfReprPredNode for FuncPred {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use optd_og_core::cascades::{Memo, NaiveMemo};
+
+    use super::*;
+    use crate::plan_nodes::{
+        ColumnRefPred, DfNodeType, DfReprPlanNode, LogicalFilter, LogicalScan,
+    };
+
+    fn udf_call(column: usize) -> ArcDfPredNode {
+        FuncPred::new(
+            FuncType::new_scalar("my_udf".to_string()),
+            ListPred::new(vec![ColumnRefPred::new(column).into_pred_node()]),
+        )
+        .into_pred_node()
+    }
+
+    #[test]
+    fn dedup_repeated_udf_calls() {
+        let mut memo = NaiveMemo::<DfNodeType>::new(Arc::new([]));
+        assert_eq!(
+            memo.add_new_pred(udf_call(0)),
+            memo.add_new_pred(udf_call(0))
+        );
+        assert_ne!(
+            memo.add_new_pred(udf_call(0)),
+            memo.add_new_pred(udf_call(1))
+        );
+
+        let filter = |cond| {
+            LogicalFilter::new(LogicalScan::new("t1".to_string()).into_plan_node(), cond)
+                .into_plan_node()
+        };
+        let (group, expr) = memo.add_new_expr(filter(udf_call(0)));
+        assert_eq!(memo.add_new_expr(filter(udf_call(0))), (group, expr));
+    }
+}
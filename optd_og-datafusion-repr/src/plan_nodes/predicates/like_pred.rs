@@ -5,7 +5,7 @@
 
 use std::sync::Arc;
 
-use optd_og_core::nodes::{PlanNodeMetaMap, Value};
+use optd_og_core::nodes::{PlanAnnotations, Value};
 use pretty_xmlish::Pretty;
 
 use crate::plan_nodes::{ArcDfPredNode, DfPredNode, DfPredType, DfReprPredNode};
@@ -69,7 +69,7 @@ impl DfReprPredNode for LikePred {
         Some(Self(pred_node))
     }
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         Pretty::simple_record(
             "Like",
             vec![
@@ -4,7 +4,7 @@
 // https://opensource.org/licenses/MIT.
 
 use itertools::Itertools;
-use optd_og_core::nodes::PlanNodeMetaMap;
+use optd_og_core::nodes::PlanAnnotations;
 use pretty_xmlish::Pretty;
 
 use crate::plan_nodes::{ArcDfPredNode, DfPredNode, DfPredType, DfReprPredNode};
@@ -54,7 +54,7 @@ impl DfReprPredNode for ListPred {
         Some(Self(pred_node))
     }
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         Pretty::Array(
             (0..self.len())
                 .map(|x| self.child(x).explain(meta_map))
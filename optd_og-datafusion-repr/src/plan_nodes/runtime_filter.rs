@@ -0,0 +1,34 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use super::macros::define_plan_node;
+use super::{
+    ArcDfPlanNode, ArcDfPredNode, ConstantPred, DfNodeType, DfPlanNode, DfReprPlanNode, ListPred,
+};
+
+/// Drops the rows of a scan whose keys are out of the range of the join keys seen on the build
+/// side of the hash join with the same filter id, which is only known when the plan runs. The
+/// rows dropped would not have matched, so the filter never changes the result of the join.
+#[derive(Clone, Debug)]
+pub struct PhysicalRuntimeFilter(pub ArcDfPlanNode);
+
+define_plan_node!(
+    PhysicalRuntimeFilter : DfPlanNode,
+    PhysicalRuntimeFilter, [
+        { 0, child: ArcDfPlanNode }
+    ], [
+        { 0, keys: ListPred },
+        { 1, filter_id: ArcDfPredNode }
+    ]
+);
+
+impl PhysicalRuntimeFilter {
+    pub fn id(&self) -> u64 {
+        ConstantPred::from_pred_node(self.filter_id())
+            .unwrap()
+            .value()
+            .as_u64()
+    }
+}
@@ -5,11 +5,14 @@
 
 use std::sync::Arc;
 
-use optd_og_core::nodes::PlanNodeMetaMap;
+use optd_og_core::nodes::PlanAnnotations;
 use pretty_xmlish::Pretty;
 
-use super::{ArcDfPlanNode, ConstantPred, DfNodeType, DfPlanNode, DfReprPlanNode, DfReprPredNode};
+use super::{
+    ArcDfPlanNode, ConstantPred, DfNodeType, DfPlanNode, DfReprPlanNode, DfReprPredNode, ListPred,
+};
 use crate::explain::Insertable;
+use crate::TableId;
 
 #[derive(Clone, Debug)]
 pub struct LogicalScan(pub ArcDfPlanNode);
@@ -26,14 +29,42 @@ impl DfReprPlanNode for LogicalScan {
         Some(Self(plan_node))
     }
 
-    fn explain(&self, _meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
-        Pretty::childless_record(
-            "LogicalScan",
-            vec![("table", self.table().to_string().into())],
-        )
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
+        let mut fields = vec![("table", self.table().to_string().into())];
+        if let Some(partition_filters) = self.partition_filters() {
+            fields.push(("partition_filters", partition_filters.explain(meta_map)));
+        }
+        if let Some((index, lookup_filters)) = self.index_lookup() {
+            fields.push(("index", index.to_string().into()));
+            fields.push(("index_lookup", lookup_filters.explain(meta_map)));
+        }
+        Pretty::childless_record("LogicalScan", fields)
     }
 }
 
+/// The predicates pruning the partitions of a scan, which follow the table name if there are any.
+fn partition_filters(plan_node: &ArcDfPlanNode) -> Option<ListPred> {
+    plan_node
+        .predicates
+        .get(1)
+        .map(|pred| ListPred::from_pred_node(pred.clone()).unwrap())
+        .filter(|filters| !filters.is_empty())
+}
+
+/// The index a scan looks its rows up with and the predicates of the lookup, which follow the
+/// (possibly empty) partition filters.
+fn index_lookup(plan_node: &ArcDfPlanNode) -> Option<(Arc<str>, ListPred)> {
+    let index = plan_node.predicates.get(2)?;
+    let lookup_filters = plan_node.predicates.get(3)?;
+    Some((
+        ConstantPred::from_pred_node(index.clone())
+            .unwrap()
+            .value()
+            .as_str(),
+        ListPred::from_pred_node(lookup_filters.clone()).unwrap(),
+    ))
+}
+
 impl LogicalScan {
     pub fn new(table: String) -> LogicalScan {
         LogicalScan(
@@ -46,12 +77,65 @@ impl LogicalScan {
         )
     }
 
+    /// A scan which only reads the partitions of the table where `partition_filters` may hold.
+    /// The filters only refer to the partition columns of the table, and the rows they do not
+    /// hold for may still be returned.
+    pub fn new_with_partition_filters(table: String, partition_filters: ListPred) -> LogicalScan {
+        LogicalScan(
+            DfPlanNode {
+                typ: DfNodeType::Scan,
+                children: vec![],
+                predicates: vec![
+                    ConstantPred::string(table).into_pred_node(),
+                    partition_filters.into_pred_node(),
+                ],
+            }
+            .into(),
+        )
+    }
+
     pub fn table(&self) -> Arc<str> {
         ConstantPred::from_pred_node(self.0.predicates.first().unwrap().clone())
             .unwrap()
             .value()
             .as_str()
     }
+
+    /// The normalized name of the table, which the catalog and the statistics are keyed by.
+    pub fn table_id(&self) -> TableId {
+        self.table().into()
+    }
+
+    pub fn partition_filters(&self) -> Option<ListPred> {
+        partition_filters(&self.0)
+    }
+
+    /// The scan looking the rows of the table up in `index` with `lookup_filters`, which only
+    /// refer to the leading column of the index, instead of reading all rows. The partition
+    /// filters of the scan are kept. Like the partition filters, the lookup filters may not be
+    /// exact, and storages without the index read all rows instead.
+    pub fn with_index_lookup(&self, index: String, lookup_filters: ListPred) -> LogicalScan {
+        let partition_filters = self
+            .partition_filters()
+            .unwrap_or_else(|| ListPred::new(vec![]));
+        LogicalScan(
+            DfPlanNode {
+                typ: DfNodeType::Scan,
+                children: vec![],
+                predicates: vec![
+                    self.0.predicates[0].clone(),
+                    partition_filters.into_pred_node(),
+                    ConstantPred::string(index).into_pred_node(),
+                    lookup_filters.into_pred_node(),
+                ],
+            }
+            .into(),
+        )
+    }
+
+    pub fn index_lookup(&self) -> Option<(Arc<str>, ListPred)> {
+        index_lookup(&self.0)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -69,8 +153,15 @@ impl DfReprPlanNode for PhysicalScan {
         Some(Self(plan_node))
     }
 
-    fn explain(&self, meta_map: Option<&PlanNodeMetaMap>) -> Pretty<'static> {
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
         let mut fields = vec![("table", self.table().to_string().into())];
+        if let Some(partition_filters) = self.partition_filters() {
+            fields.push(("partition_filters", partition_filters.explain(meta_map)));
+        }
+        if let Some((index, lookup_filters)) = self.index_lookup() {
+            fields.push(("index", index.to_string().into()));
+            fields.push(("index_lookup", lookup_filters.explain(meta_map)));
+        }
         if let Some(meta_map) = meta_map {
             fields = fields.with_meta(self.0.get_meta(meta_map));
         }
@@ -85,4 +176,17 @@ impl PhysicalScan {
             .value()
             .as_str()
     }
+
+    /// The normalized name of the table, which the catalog and the statistics are keyed by.
+    pub fn table_id(&self) -> TableId {
+        self.table().into()
+    }
+
+    pub fn partition_filters(&self) -> Option<ListPred> {
+        partition_filters(&self.0)
+    }
+
+    pub fn index_lookup(&self) -> Option<(Arc<str>, ListPred)> {
+        index_lookup(&self.0)
+    }
 }
@@ -0,0 +1,32 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use super::macros::define_plan_node;
+use super::{ArcDfPlanNode, DfNodeType, DfPlanNode, DfReprPlanNode};
+
+/// Materializes the single row of its child once, e.g., the value of an uncorrelated scalar
+/// subquery, so that the join it is an input of reads the row instead of evaluating the child
+/// again for each of its rows.
+#[derive(Clone, Debug)]
+pub struct LogicalSingleton(pub ArcDfPlanNode);
+
+define_plan_node!(
+    LogicalSingleton : DfPlanNode,
+    Singleton, [
+        { 0, child: ArcDfPlanNode }
+    ], [
+    ]
+);
+
+#[derive(Clone, Debug)]
+pub struct PhysicalSingleton(pub ArcDfPlanNode);
+
+define_plan_node!(
+    PhysicalSingleton : DfPlanNode,
+    PhysicalSingleton, [
+        { 0, child: ArcDfPlanNode }
+    ], [
+    ]
+);
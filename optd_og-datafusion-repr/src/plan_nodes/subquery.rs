@@ -6,20 +6,29 @@
 use core::fmt;
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
 use super::macros::define_plan_node;
 use super::{
     ArcDfPlanNode, ArcDfPredNode, BinOpType, DfNodeType, DfPlanNode, DfPredNode, DfReprPlanNode,
     ListPred,
 };
 
-/// These are the only three fundamental types of subqueries.
+/// These are the only three fundamental types of subqueries, plus `All` which is the negation of
+/// `Any` with the negated comparison operator.
 /// Refer to the Unnesting Arbitrary Queries talk by Mark Raasveldt for
 /// info on how to translate other subquery types to these three.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SubqueryType {
     Scalar,
     Exists,
+    /// Only produced with `op = Eq` from SQL, for `IN (subquery)`: datafusion does not plan the
+    /// quantified comparisons `op ANY (subquery)` from SQL. The other operators can be built by
+    /// hand, e.g., by a host constructing optd_og plans directly.
     Any { pred: DfPredNode, op: BinOpType },
+    /// Not produced from SQL, as datafusion does not plan `op ALL (subquery)`. Only reachable by
+    /// building the plan by hand.
+    All { pred: DfPredNode, op: BinOpType },
 }
 
 impl Display for SubqueryType {
@@ -0,0 +1,151 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::Arc;
+
+use optd_og_core::nodes::{PlanAnnotations, PlanNodeOrGroup};
+use pretty_xmlish::Pretty;
+
+use super::{
+    decode_empty_relation_schema, ArcDfPlanNode, ConstantPred, DfNodeType, DfPlanNode,
+    DfReprPlanNode, DfReprPredNode, ListPred,
+};
+use crate::explain::Insertable;
+use crate::properties::schema::Schema;
+
+/// The name of the table function which expands each element of the list columns in `args`
+/// into a row. The last argument is a boolean constant telling whether a null list produces a
+/// row of nulls.
+pub const UNNEST_FUNCTION: &str = "unnest";
+
+/// A table function evaluated on each row of its child, e.g., `unnest`. Table functions which
+/// are scanned like a table, e.g., `generate_series`, have a child producing a single row.
+///
+/// The output schema is stored with the node as it depends on the function signature, which
+/// is not known to the optimizer.
+#[derive(Clone, Debug)]
+pub struct LogicalTableFunction(pub ArcDfPlanNode);
+
+impl DfReprPlanNode for LogicalTableFunction {
+    fn into_plan_node(self) -> ArcDfPlanNode {
+        self.0
+    }
+
+    fn from_plan_node(plan_node: ArcDfPlanNode) -> Option<Self> {
+        if plan_node.typ != DfNodeType::TableFunction {
+            return None;
+        }
+        Some(Self(plan_node))
+    }
+
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
+        Pretty::simple_record(
+            "LogicalTableFunction",
+            vec![
+                ("name", self.name().to_string().into()),
+                ("args", self.args().explain(meta_map)),
+            ],
+            vec![self.child().unwrap_plan_node().explain(meta_map)],
+        )
+    }
+}
+
+impl LogicalTableFunction {
+    pub fn new(child: ArcDfPlanNode, name: String, args: ListPred, schema: Schema) -> Self {
+        Self::new_unchecked(child, name, args, schema)
+    }
+
+    pub fn new_unchecked(
+        child: impl Into<PlanNodeOrGroup<DfNodeType>>,
+        name: String,
+        args: ListPred,
+        schema: Schema,
+    ) -> Self {
+        let serialized_data: Arc<[u8]> = bincode::serialize(&schema).unwrap().into_iter().collect();
+        LogicalTableFunction(
+            DfPlanNode {
+                typ: DfNodeType::TableFunction,
+                children: vec![child.into()],
+                predicates: vec![
+                    ConstantPred::string(name).into_pred_node(),
+                    args.into_pred_node(),
+                    ConstantPred::serialized(serialized_data).into_pred_node(),
+                ],
+            }
+            .into(),
+        )
+    }
+
+    pub fn child(&self) -> PlanNodeOrGroup<DfNodeType> {
+        self.0.child(0)
+    }
+
+    pub fn name(&self) -> Arc<str> {
+        ConstantPred::from_pred_node(self.0.predicate(0))
+            .unwrap()
+            .value()
+            .as_str()
+    }
+
+    pub fn args(&self) -> ListPred {
+        ListPred::from_pred_node(self.0.predicate(1)).unwrap()
+    }
+
+    pub fn table_function_schema(&self) -> Schema {
+        decode_empty_relation_schema(&self.0.predicates[2])
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PhysicalTableFunction(pub ArcDfPlanNode);
+
+impl DfReprPlanNode for PhysicalTableFunction {
+    fn into_plan_node(self) -> ArcDfPlanNode {
+        self.0
+    }
+
+    fn from_plan_node(plan_node: ArcDfPlanNode) -> Option<Self> {
+        if plan_node.typ != DfNodeType::PhysicalTableFunction {
+            return None;
+        }
+        Some(Self(plan_node))
+    }
+
+    fn explain(&self, meta_map: Option<&PlanAnnotations>) -> Pretty<'static> {
+        let mut fields = vec![
+            ("name", self.name().to_string().into()),
+            ("args", self.args().explain(meta_map)),
+        ];
+        if let Some(meta_map) = meta_map {
+            fields = fields.with_meta(self.0.get_meta(meta_map));
+        }
+        Pretty::simple_record(
+            "PhysicalTableFunction",
+            fields,
+            vec![self.child().unwrap_plan_node().explain(meta_map)],
+        )
+    }
+}
+
+impl PhysicalTableFunction {
+    pub fn child(&self) -> PlanNodeOrGroup<DfNodeType> {
+        self.0.child(0)
+    }
+
+    pub fn name(&self) -> Arc<str> {
+        ConstantPred::from_pred_node(self.0.predicate(0))
+            .unwrap()
+            .value()
+            .as_str()
+    }
+
+    pub fn args(&self) -> ListPred {
+        ListPred::from_pred_node(self.0.predicate(1)).unwrap()
+    }
+
+    pub fn table_function_schema(&self) -> Schema {
+        decode_empty_relation_schema(&self.0.predicates[2])
+    }
+}
@@ -4,6 +4,8 @@
 // https://opensource.org/licenses/MIT.
 
 pub mod column_ref;
+pub mod ordering;
 pub mod schema;
+pub mod uniqueness;
 
 const DEFAULT_NAME: &str = "unnamed";
@@ -5,7 +5,7 @@
 
 use std::collections::HashSet;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use anyhow::anyhow;
 use itertools::Itertools;
@@ -19,17 +19,20 @@ use crate::{
         DfPredType, DfReprPredNode, JoinType, LogOpType, SubqueryType,
     },
     utils::DisjointSets,
+    TableId,
 };
 
 pub type BaseTableColumnRefs = Vec<ColumnRef>;
 
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct BaseTableColumnRef {
-    pub table: String,
+    /// The table, interned by the [`ColumnRefPropertyBuilder`] so that the column refs of a
+    /// table share its name.
+    pub table: TableId,
     pub col_idx: usize,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum ColumnRef {
     BaseTableColumnRef(BaseTableColumnRef),
     /// This variant is only used when building the property. It should NEVER
@@ -41,8 +44,11 @@ pub enum ColumnRef {
 }
 
 impl ColumnRef {
-    pub fn base_table_column_ref(table: String, col_idx: usize) -> Self {
-        ColumnRef::BaseTableColumnRef(BaseTableColumnRef { table, col_idx })
+    pub fn base_table_column_ref(table: impl Into<TableId>, col_idx: usize) -> Self {
+        ColumnRef::BaseTableColumnRef(BaseTableColumnRef {
+            table: table.into(),
+            col_idx,
+        })
     }
 
     pub fn child_column_ref(col_idx: usize) -> Self {
@@ -239,17 +245,27 @@ impl EqBaseTableColumnSets {
     }
 }
 
+/// The column refs of a group. Both parts are shared with the groups whose column refs are the
+/// same, e.g., a filter and its child, so that cloning them does not copy the column refs of
+/// wide tables.
 #[derive(Clone, Debug)]
 pub struct GroupColumnRefs {
-    column_refs: BaseTableColumnRefs,
+    column_refs: Arc<BaseTableColumnRefs>,
     /// Correlation of the output columns of the group.
-    output_correlation: Option<SemanticCorrelation>,
+    output_correlation: Option<Arc<SemanticCorrelation>>,
 }
 
 impl GroupColumnRefs {
     pub fn new(
         column_refs: BaseTableColumnRefs,
         output_correlation: Option<SemanticCorrelation>,
+    ) -> Self {
+        Self::new_shared(column_refs.into(), output_correlation.map(Arc::new))
+    }
+
+    fn new_shared(
+        column_refs: Arc<BaseTableColumnRefs>,
+        output_correlation: Option<Arc<SemanticCorrelation>>,
     ) -> Self {
         Self {
             column_refs,
@@ -262,17 +278,32 @@ impl GroupColumnRefs {
     }
 
     pub fn output_correlation(&self) -> Option<&SemanticCorrelation> {
-        self.output_correlation.as_ref()
+        self.output_correlation.as_deref()
     }
 }
 
 pub struct ColumnRefPropertyBuilder {
     catalog: Arc<dyn Catalog>,
+    /// The tables seen so far, whose names are shared by their column refs.
+    table_names: Mutex<HashSet<TableId>>,
 }
 
 impl ColumnRefPropertyBuilder {
     pub fn new(catalog: Arc<dyn Catalog>) -> Self {
-        Self { catalog }
+        Self {
+            catalog,
+            table_names: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn intern_table_name(&self, name: &str) -> TableId {
+        let name = TableId::new(name);
+        let mut table_names = self.table_names.lock().unwrap();
+        if let Some(name) = table_names.get(&name) {
+            return name.clone();
+        }
+        table_names.insert(name.clone());
+        name
     }
 
     fn concat_children_col_refs(children: &[&GroupColumnRefs]) -> BaseTableColumnRefs {
@@ -317,7 +348,7 @@ impl ColumnRefPropertyBuilder {
                             for child in children {
                                 if let Some(SemanticCorrelation {
                                     eq_columns: EqColumns::EqColumnIdxPairs(pairs),
-                                }) = &child.output_correlation
+                                }) = child.output_correlation()
                                 {
                                     eq_column_idx_pairs.extend(pairs.iter());
                                 }
@@ -417,21 +448,28 @@ impl LogicalPropertyBuilder<DfNodeType> for ColumnRefPropertyBuilder {
                     .unwrap()
                     .value()
                     .as_str();
+                let table_name = self.intern_table_name(&table_name);
                 let schema = self.catalog.get(&table_name);
                 let column_cnt = schema.fields.len();
                 let column_refs = (0..column_cnt)
-                    .map(|i| ColumnRef::base_table_column_ref(table_name.to_string(), i))
+                    .map(|i| ColumnRef::base_table_column_ref(table_name.clone(), i))
                     .collect();
                 GroupColumnRefs::new(column_refs, None)
             }
             DfNodeType::EmptyRelation => {
                 let schema = decode_empty_relation_schema(&predicates[1]);
                 let column_cnt = schema.fields.len();
+                let table_name = self.intern_table_name(DEFAULT_NAME);
                 let column_refs = (0..column_cnt)
-                    .map(|i| ColumnRef::base_table_column_ref(DEFAULT_NAME.to_string(), i))
+                    .map(|i| ColumnRef::base_table_column_ref(table_name.clone(), i))
                     .collect();
                 GroupColumnRefs::new(column_refs, None)
             }
+            DfNodeType::TableFunction => {
+                let schema = decode_empty_relation_schema(&predicates[2]);
+                let column_refs = vec![ColumnRef::Derived; schema.len()];
+                GroupColumnRefs::new(column_refs, None)
+            }
             DfNodeType::Projection => {
                 let child = children[0];
                 let exprs = &predicates[0];
@@ -446,9 +484,22 @@ impl LogicalPropertyBuilder<DfNodeType> for ColumnRefPropertyBuilder {
                         ColumnRef::Derived => ColumnRef::Derived,
                         _ => panic!("projection expr must be Derived or ChildColumnRef"),
                     })
-                    .collect();
+                    .collect::<BaseTableColumnRefs>();
                 // Projection keeps the semantic correlations of the children.
-                GroupColumnRefs::new(column_refs, child.output_correlation.clone())
+                if column_refs == *child.column_refs {
+                    return child.clone();
+                }
+                GroupColumnRefs::new_shared(Arc::new(column_refs), child.output_correlation.clone())
+            }
+            // Semi and anti joins only filter the rows of one side, which keeps its correlations.
+            DfNodeType::Join(JoinType::LeftSemi | JoinType::LeftAnti) => children[0].clone(),
+            DfNodeType::Join(JoinType::RightSemi | JoinType::RightAnti) => children[1].clone(),
+            DfNodeType::Join(JoinType::LeftMark) => {
+                let left = children[0];
+                let mut column_refs = left.column_refs.to_vec();
+                // The mark column is computed from the join condition.
+                column_refs.push(ColumnRef::Derived);
+                GroupColumnRefs::new_shared(Arc::new(column_refs), left.output_correlation.clone())
             }
             // Should account for all physical join types.
             DfNodeType::Join(join_type) => {
@@ -456,8 +507,8 @@ impl LogicalPropertyBuilder<DfNodeType> for ColumnRefPropertyBuilder {
                 let column_refs = Self::concat_children_col_refs(&children[0..2]);
                 // Merge the equal columns of two children as input correlation.
                 let children_correlation = SemanticCorrelation::merge(
-                    children[0].output_correlation.clone(),
-                    children[1].output_correlation.clone(),
+                    children[0].output_correlation().cloned(),
+                    children[1].output_correlation().cloned(),
                 );
                 let mut children_eq_columns =
                     if let Some(children_correlation) = children_correlation {
@@ -477,7 +528,7 @@ impl LogicalPropertyBuilder<DfNodeType> for ColumnRefPropertyBuilder {
                         if let Some(SemanticCorrelation {
                             eq_columns: EqColumns::EqColumnIdxPairs(pairs),
                         }) =
-                            &Self::derive_for_predicate(predicates[0].clone()).output_correlation
+                            Self::derive_for_predicate(predicates[0].clone()).output_correlation()
                         {
                             for (l_col_idx, r_col_idx) in pairs {
                                 let l_col_ref = &column_refs[*l_col_idx];
@@ -504,14 +555,16 @@ impl LogicalPropertyBuilder<DfNodeType> for ColumnRefPropertyBuilder {
                 SubqueryType::Scalar => {
                     self.derive(DfNodeType::Join(JoinType::Inner), predicates, children)
                 }
-                SubqueryType::Exists | SubqueryType::Any { pred: _, op: _ } => {
+                SubqueryType::Exists
+                | SubqueryType::Any { pred: _, op: _ }
+                | SubqueryType::All { pred: _, op: _ } => {
                     self.derive(DfNodeType::Join(JoinType::LeftMark), predicates, children)
                 }
             },
             DfNodeType::DepJoin => {
                 self.derive(DfNodeType::Join(JoinType::Inner), predicates, children)
             }
-            DfNodeType::Agg => {
+            DfNodeType::Agg | DfNodeType::PhysicalPartialAgg => {
                 let child = children[0];
                 // Group by columns first.
                 let mut group_by_col_refs: Vec<_> =
@@ -535,7 +588,11 @@ impl LogicalPropertyBuilder<DfNodeType> for ColumnRefPropertyBuilder {
                 // Aggregation clears all semantic correlations.
                 GroupColumnRefs::new(group_by_col_refs, None)
             }
-            DfNodeType::Filter | DfNodeType::Sort | DfNodeType::Limit => children[0].clone(),
+            DfNodeType::Filter
+            | DfNodeType::Sort
+            | DfNodeType::Limit
+            | DfNodeType::Distinct
+            | DfNodeType::Singleton => children[0].clone(),
             _ => unimplemented!("Unsupported rel node type {:?}", typ),
         }
     }
@@ -548,23 +605,24 @@ impl LogicalPropertyBuilder<DfNodeType> for ColumnRefPropertyBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::TpchCatalog;
 
     #[test]
     fn test_eq_base_table_column_sets() {
         let col1 = BaseTableColumnRef {
-            table: "t1".to_string(),
+            table: "t1".into(),
             col_idx: 1,
         };
         let col2 = BaseTableColumnRef {
-            table: "t2".to_string(),
+            table: "t2".into(),
             col_idx: 2,
         };
         let col3 = BaseTableColumnRef {
-            table: "t3".to_string(),
+            table: "t3".into(),
             col_idx: 3,
         };
         let col4 = BaseTableColumnRef {
-            table: "t4".to_string(),
+            table: "t4".into(),
             col_idx: 4,
         };
         let pred1 = EqPredicate::new(col1.clone(), col2.clone());
@@ -602,4 +660,89 @@ mod tests {
         assert!(predicates.contains(&pred2));
         assert!(predicates.contains(&pred3));
     }
+
+    fn join_column_refs(join_type: JoinType) -> Vec<Option<(String, usize)>> {
+        let builder = ColumnRefPropertyBuilder::new(Arc::new(TpchCatalog));
+        let scan = |table: &str| {
+            builder.derive(
+                DfNodeType::Scan,
+                &[ConstantPred::string(table).into_pred_node()],
+                &[],
+            )
+        };
+        let left = scan("region");
+        let right = scan("customer");
+        builder
+            .derive(
+                DfNodeType::Join(join_type),
+                &[ConstantPred::bool(true).into_pred_node()],
+                &[&left, &right],
+            )
+            .base_table_column_refs()
+            .iter()
+            .map(|col| match col {
+                ColumnRef::BaseTableColumnRef(col) => Some((col.table.to_string(), col.col_idx)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn join_column_refs_per_join_type() {
+        let region = (0..3).map(|idx| Some(("region".to_string(), idx)));
+        let customer = (0..8).map(|idx| Some(("customer".to_string(), idx)));
+        for join_type in [
+            JoinType::Inner,
+            JoinType::LeftOuter,
+            JoinType::RightOuter,
+            JoinType::FullOuter,
+        ] {
+            assert_eq!(
+                join_column_refs(join_type),
+                region.clone().chain(customer.clone()).collect_vec()
+            );
+        }
+        for join_type in [JoinType::LeftSemi, JoinType::LeftAnti] {
+            assert_eq!(join_column_refs(join_type), region.clone().collect_vec());
+        }
+        for join_type in [JoinType::RightSemi, JoinType::RightAnti] {
+            assert_eq!(join_column_refs(join_type), customer.clone().collect_vec());
+        }
+        assert_eq!(
+            join_column_refs(JoinType::LeftMark),
+            region.chain([None]).collect_vec()
+        );
+    }
+
+    #[test]
+    fn share_column_refs() {
+        let builder = ColumnRefPropertyBuilder::new(Arc::new(TpchCatalog));
+        let scan = builder.derive(
+            DfNodeType::Scan,
+            &[ConstantPred::string("customer").into_pred_node()],
+            &[],
+        );
+        let filter = builder.derive(
+            DfNodeType::Filter,
+            &[ConstantPred::bool(true).into_pred_node()],
+            &[&scan],
+        );
+        assert!(std::ptr::eq(
+            scan.base_table_column_refs(),
+            filter.base_table_column_refs()
+        ));
+
+        // the column refs of another scan of the table share its name
+        let other_scan = builder.derive(
+            DfNodeType::Scan,
+            &[ConstantPred::string("customer").into_pred_node()],
+            &[],
+        );
+        let table_name = |col_refs: &GroupColumnRefs| match &col_refs.base_table_column_refs()[0] {
+            ColumnRef::BaseTableColumnRef(col) => col.table.clone(),
+            _ => unreachable!(),
+        };
+        let (table, other_table) = (table_name(&scan), table_name(&other_scan));
+        assert!(std::ptr::eq(table.as_str(), other_table.as_str()));
+    }
 }
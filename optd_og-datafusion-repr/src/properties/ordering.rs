@@ -0,0 +1,665 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! The order of the rows produced by a physical plan, together with the columns known to hold
+//! the same value in every row (e.g., the keys of an inner join), so that a sort on columns
+//! equivalent to the ones the rows are already sorted on can be eliminated.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use optd_og_core::nodes::{PlanAnnotations, PlanNode, PlanNodeOrGroup};
+use optd_og_core::physical_property::{PhysicalProperty, PhysicalPropertyBuilder};
+
+use super::schema::Catalog;
+use crate::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, BinOpType, BuildSide, ColumnRefPred, ConstantPred, DfNodeType,
+    DfPredType, DfReprPredNode, JoinType, ListPred, LogOpType, PhysicalHashJoin, SortOrderPred,
+    SortOrderType,
+};
+use crate::TableId;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OrderingProp {
+    /// The columns the rows are sorted on, most significant first.
+    pub keys: Vec<(usize, SortOrderType)>,
+    /// Sets of columns which hold the same value in every row.
+    pub equivalences: Vec<BTreeSet<usize>>,
+    /// The number of columns of the rows, if known. The columns of the right side of a join are
+    /// shifted by the width of its left side.
+    pub width: Option<usize>,
+}
+
+impl OrderingProp {
+    /// Whether the two columns hold the same value in every row.
+    pub fn equivalent(&self, a: usize, b: usize) -> bool {
+        a == b
+            || self
+                .equivalences
+                .iter()
+                .any(|class| class.contains(&a) && class.contains(&b))
+    }
+
+    /// Whether the rows with the same values of the required keys are adjacent, i.e., the leading
+    /// keys are the required ones up to equivalent columns, in any order and direction. This is
+    /// all a stream aggregation needs of its input.
+    pub fn groups_adjacent(&self, required: &OrderingProp) -> bool {
+        let Some(leading) = self.keys.get(..required.keys.len()) else {
+            return false;
+        };
+        leading.iter().all(|(col, _)| {
+            required
+                .keys
+                .iter()
+                .any(|(required_col, _)| self.equivalent(*col, *required_col))
+        }) && required.keys.iter().all(|(required_col, _)| {
+            leading
+                .iter()
+                .any(|(col, _)| self.equivalent(*col, *required_col))
+        })
+    }
+
+    fn add_equivalence(&mut self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+        let (merged, mut rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.equivalences)
+            .into_iter()
+            .partition(|class| class.contains(&a) || class.contains(&b));
+        let mut class = merged.into_iter().flatten().collect::<BTreeSet<_>>();
+        class.insert(a);
+        class.insert(b);
+        rest.push(class);
+        self.equivalences = rest;
+    }
+}
+
+impl Display for OrderingProp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keys = self
+            .keys
+            .iter()
+            .map(|(col, order)| format!("#{} {}", col, order))
+            .join(", ");
+        write!(f, "[{}]", keys)?;
+        for class in &self.equivalences {
+            write!(
+                f,
+                " {{{}}}",
+                class.iter().map(|col| format!("#{}", col)).join("=")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl PhysicalProperty for OrderingProp {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn to_boxed(&self) -> Box<dyn PhysicalProperty> {
+        Box::new(self.clone())
+    }
+}
+
+/// Derives the [`OrderingProp`] of physical plans. Sorts are assumed not to be stable, so a sort
+/// only orders its output on its own keys.
+pub struct OrderingPropertyBuilder {
+    catalog: Arc<dyn Catalog>,
+}
+
+fn column_index(pred: &ArcDfPredNode) -> Option<usize> {
+    ColumnRefPred::from_pred_node(pred.clone()).map(|col| col.index())
+}
+
+/// The column pairs compared for equality by the conjuncts of a filter condition.
+fn column_equalities(cond: &ArcDfPredNode) -> Vec<(usize, usize)> {
+    match &cond.typ {
+        DfPredType::LogOp(LogOpType::And) => {
+            cond.children.iter().flat_map(column_equalities).collect()
+        }
+        DfPredType::BinOp(BinOpType::Eq) => {
+            match (
+                column_index(&cond.children[0]),
+                column_index(&cond.children[1]),
+            ) {
+                (Some(a), Some(b)) => vec![(a, b)],
+                _ => vec![],
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// The number of columns produced by a join of the two widths.
+fn join_width(join_type: &JoinType, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+    match join_type {
+        JoinType::LeftSemi | JoinType::LeftAnti => left,
+        JoinType::RightSemi | JoinType::RightAnti => right,
+        JoinType::LeftMark => left.map(|left| left + 1),
+        _ => Some(left? + right?),
+    }
+}
+
+impl OrderingPropertyBuilder {
+    pub fn new(catalog: Arc<dyn Catalog>) -> Self {
+        Self { catalog }
+    }
+
+    /// The order of the rows produced by a sort, up to its first key which is not a column.
+    pub fn sort_ordering(sort_keys: &ListPred) -> OrderingProp {
+        OrderingProp {
+            keys: sort_keys
+                .to_vec()
+                .into_iter()
+                .map_while(|key| {
+                    let key = SortOrderPred::from_pred_node(key)?;
+                    Some((column_index(&key.child())?, key.order()))
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// The ordering a stream aggregation requires of its input, i.e., sorted on the group-by
+    /// columns, or `None` if a group-by expression is not a column.
+    pub fn stream_agg_requirement(groups: &[ArcDfPredNode]) -> Option<OrderingProp> {
+        Some(OrderingProp {
+            keys: groups
+                .iter()
+                .map(|group| Some((column_index(group)?, SortOrderType::Asc)))
+                .collect::<Option<_>>()?,
+            ..Default::default()
+        })
+    }
+
+    fn derive_projection(child: &OrderingProp, exprs: &[ArcDfPredNode]) -> OrderingProp {
+        // The output columns of each child column, for the projections which only rename or
+        // reorder columns.
+        let mut outputs: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (idx, expr) in exprs.iter().enumerate() {
+            if let Some(col) = column_index(expr) {
+                outputs.entry(col).or_default().push(idx);
+            }
+        }
+        let output_of = |col: usize| {
+            outputs
+                .get(&col)
+                .and_then(|out| out.first())
+                .copied()
+                .or_else(|| {
+                    // the column is projected away, but an equivalent one may be kept
+                    child
+                        .equivalences
+                        .iter()
+                        .find(|class| class.contains(&col))
+                        .and_then(|class| class.iter().find_map(|col| outputs.get(col)))
+                        .map(|out| out[0])
+                })
+        };
+        let mut prop = OrderingProp {
+            keys: child
+                .keys
+                .iter()
+                .map_while(|(col, order)| Some((output_of(*col)?, *order)))
+                .collect(),
+            equivalences: vec![],
+            width: Some(exprs.len()),
+        };
+        for class in &child.equivalences {
+            let out = class
+                .iter()
+                .filter_map(|col| outputs.get(col))
+                .flatten()
+                .copied()
+                .collect_vec();
+            for pair in out.windows(2) {
+                prop.add_equivalence(pair[0], pair[1]);
+            }
+        }
+        for out in outputs.values() {
+            for pair in out.windows(2) {
+                prop.add_equivalence(pair[0], pair[1]);
+            }
+        }
+        prop
+    }
+
+    fn derive_stream_agg(
+        child: &OrderingProp,
+        aggrs: usize,
+        groups: &[ArcDfPredNode],
+    ) -> OrderingProp {
+        let groups = groups.iter().map(column_index).collect_vec();
+        let position_of = |col: usize| {
+            groups
+                .iter()
+                .position(|group| group.is_some_and(|group| child.equivalent(group, col)))
+        };
+        let mut prop = OrderingProp {
+            keys: child
+                .keys
+                .iter()
+                .map_while(|(col, order)| Some((position_of(*col)?, *order)))
+                .collect(),
+            equivalences: vec![],
+            width: Some(groups.len() + aggrs),
+        };
+        for (i, a) in groups.iter().enumerate() {
+            for (j, b) in groups.iter().enumerate().skip(i + 1) {
+                if let (Some(a), Some(b)) = (a, b) {
+                    if child.equivalent(*a, *b) {
+                        prop.add_equivalence(i, j);
+                    }
+                }
+            }
+        }
+        prop
+    }
+
+    fn derive_hash_join(
+        join_type: &JoinType,
+        predicates: &[ArcDfPredNode],
+        left: &OrderingProp,
+        right: &OrderingProp,
+    ) -> OrderingProp {
+        let width = join_width(join_type, left.width, right.width);
+        let (JoinType::Inner, Some(left_width)) = (join_type, left.width) else {
+            return OrderingProp {
+                width,
+                ..Default::default()
+            };
+        };
+        // The probe side is streamed through the hash table built on the other side, so the rows
+        // keep the order of the probe side.
+        let keys = match PhysicalHashJoin::build_side_of(predicates) {
+            BuildSide::Left => right
+                .keys
+                .iter()
+                .map(|(col, order)| (col + left_width, *order))
+                .collect(),
+            BuildSide::Right => left.keys.clone(),
+        };
+        let mut prop = OrderingProp {
+            keys,
+            equivalences: left.equivalences.clone(),
+            width,
+        };
+        for class in &right.equivalences {
+            let class = class.iter().map(|col| col + left_width).collect_vec();
+            for pair in class.windows(2) {
+                prop.add_equivalence(pair[0], pair[1]);
+            }
+        }
+        let left_keys = ListPred::from_pred_node(predicates[0].clone()).unwrap();
+        let right_keys = ListPred::from_pred_node(predicates[1].clone()).unwrap();
+        for (left_key, right_key) in left_keys.to_vec().iter().zip(right_keys.to_vec().iter()) {
+            if let (Some(a), Some(b)) = (column_index(left_key), column_index(right_key)) {
+                prop.add_equivalence(a, b + left_width);
+            }
+        }
+        prop
+    }
+
+    /// Removes the sorts whose input is already ordered on equivalent columns, e.g., a sort
+    /// above a projection renaming the sorted columns, or above an inner join whose probe side
+    /// is sorted on a join key. The nodes kept retain their costs and statistics in the
+    /// returned meta map.
+    pub fn eliminate_redundant_sorts(
+        &self,
+        plan: &ArcDfPlanNode,
+        meta: &PlanAnnotations,
+    ) -> (ArcDfPlanNode, PlanAnnotations) {
+        let mut new_meta = PlanAnnotations::new();
+        let (plan, _) = self.eliminate_sorts_inner(plan, meta, &mut new_meta);
+        (plan, new_meta)
+    }
+
+    fn eliminate_sorts_inner(
+        &self,
+        plan: &ArcDfPlanNode,
+        meta: &PlanAnnotations,
+        new_meta: &mut PlanAnnotations,
+    ) -> (ArcDfPlanNode, OrderingProp) {
+        let (mut children, mut props): (Vec<_>, Vec<_>) = plan
+            .children
+            .iter()
+            .map(|child| self.eliminate_sorts_inner(&child.unwrap_plan_node(), meta, new_meta))
+            .unzip();
+        let prop = self.derive(
+            plan.typ.clone(),
+            &plan.predicates,
+            &props.iter().collect_vec(),
+        );
+        if plan.typ == DfNodeType::PhysicalSort {
+            let sort_keys = ListPred::from_pred_node(plan.predicates[0].clone()).unwrap();
+            // only the sorts on columns alone are fully described by the property
+            if prop.keys.len() == sort_keys.len() && self.satisfies(&props[0], &prop) {
+                return (children.pop().unwrap(), props.pop().unwrap());
+            }
+        }
+        let changed = children
+            .iter()
+            .zip(&plan.children)
+            .any(|(new, old)| !Arc::ptr_eq(new, &old.unwrap_plan_node()));
+        let node = if changed {
+            Arc::new(PlanNode {
+                typ: plan.typ.clone(),
+                children: children
+                    .into_iter()
+                    .map(PlanNodeOrGroup::PlanNode)
+                    .collect(),
+                predicates: plan.predicates.clone(),
+            })
+        } else {
+            plan.clone()
+        };
+        if let Some(node_meta) = meta.get(plan) {
+            new_meta.insert(&node, node_meta.clone());
+        }
+        (node, prop)
+    }
+
+    /// Records the order of the rows of each node in its meta, so that the conversion into an
+    /// execution plan keeps the rows of the ordered nodes in order.
+    pub fn annotate_orderings(&self, plan: &ArcDfPlanNode, meta: &mut PlanAnnotations) {
+        self.annotate_orderings_inner(plan, meta);
+    }
+
+    fn annotate_orderings_inner(
+        &self,
+        plan: &ArcDfPlanNode,
+        meta: &mut PlanAnnotations,
+    ) -> OrderingProp {
+        let props = plan
+            .children
+            .iter()
+            .map(|child| self.annotate_orderings_inner(&child.unwrap_plan_node(), meta))
+            .collect_vec();
+        let prop = self.derive(
+            plan.typ.clone(),
+            &plan.predicates,
+            &props.iter().collect_vec(),
+        );
+        if let Some(node_meta) = meta.get_mut(plan) {
+            node_meta.set_physical_prop(prop.clone());
+        }
+        prop
+    }
+}
+
+impl PhysicalPropertyBuilder<DfNodeType> for OrderingPropertyBuilder {
+    type Prop = OrderingProp;
+
+    fn derive(
+        &self,
+        typ: DfNodeType,
+        predicates: &[ArcDfPredNode],
+        children: &[&Self::Prop],
+    ) -> Self::Prop {
+        match typ {
+            DfNodeType::PhysicalScan => {
+                let table = ConstantPred::from_pred_node(predicates[0].clone())
+                    .unwrap()
+                    .value()
+                    .as_str();
+                OrderingProp {
+                    width: Some(self.catalog.get(&TableId::new(&table)).len()),
+                    ..Default::default()
+                }
+            }
+            DfNodeType::PhysicalSort => {
+                let sort_keys = ListPred::from_pred_node(predicates[0].clone()).unwrap();
+                OrderingProp {
+                    keys: Self::sort_ordering(&sort_keys).keys,
+                    ..children[0].clone()
+                }
+            }
+            DfNodeType::PhysicalFilter => {
+                let mut prop = children[0].clone();
+                for (a, b) in column_equalities(&predicates[0]) {
+                    prop.add_equivalence(a, b);
+                }
+                prop
+            }
+            DfNodeType::PhysicalLimit | DfNodeType::PhysicalRuntimeFilter => children[0].clone(),
+            DfNodeType::PhysicalProjection => {
+                let exprs = ListPred::from_pred_node(predicates[0].clone()).unwrap();
+                Self::derive_projection(children[0], &exprs.to_vec())
+            }
+            DfNodeType::PhysicalStreamAgg => {
+                let aggrs = ListPred::from_pred_node(predicates[0].clone()).unwrap();
+                let groups = ListPred::from_pred_node(predicates[1].clone()).unwrap();
+                Self::derive_stream_agg(children[0], aggrs.len(), &groups.to_vec())
+            }
+            DfNodeType::PhysicalAgg
+            | DfNodeType::PhysicalPartialAgg
+            | DfNodeType::PhysicalFinalAgg => {
+                let aggrs = ListPred::from_pred_node(predicates[0].clone()).unwrap();
+                let groups = ListPred::from_pred_node(predicates[1].clone()).unwrap();
+                OrderingProp {
+                    width: Some(aggrs.len() + groups.len()),
+                    ..Default::default()
+                }
+            }
+            DfNodeType::PhysicalHashJoin(join_type) => {
+                Self::derive_hash_join(&join_type, predicates, children[0], children[1])
+            }
+            DfNodeType::PhysicalNestedLoopJoin(join_type) => OrderingProp {
+                width: join_width(&join_type, children[0].width, children[1].width),
+                ..Default::default()
+            },
+            _ => OrderingProp::default(),
+        }
+    }
+
+    fn passthrough(
+        &self,
+        typ: DfNodeType,
+        predicates: &[ArcDfPredNode],
+        required: &Self::Prop,
+    ) -> Vec<Self::Prop> {
+        match typ {
+            DfNodeType::PhysicalScan | DfNodeType::PhysicalEmptyRelation => vec![],
+            // the columns are the same as the ones of the child
+            DfNodeType::PhysicalFilter | DfNodeType::PhysicalLimit => vec![required.clone()],
+            DfNodeType::PhysicalStreamAgg => {
+                let groups = ListPred::from_pred_node(predicates[1].clone()).unwrap();
+                vec![Self::stream_agg_requirement(&groups.to_vec()).unwrap_or_default()]
+            }
+            DfNodeType::PhysicalHashJoin(_) | DfNodeType::PhysicalNestedLoopJoin(_) => {
+                vec![self.default(), self.default()]
+            }
+            _ => vec![self.default()],
+        }
+    }
+
+    fn satisfies(&self, prop: &OrderingProp, required: &OrderingProp) -> bool {
+        // the required keys should be a prefix of the current keys, up to equivalent columns
+        required.keys.len() <= prop.keys.len()
+            && required.keys.iter().zip(&prop.keys).all(
+                |((required_col, required_order), (col, order))| {
+                    required_order == order && prop.equivalent(*col, *required_col)
+                },
+            )
+    }
+
+    fn enforce(&self, prop: &OrderingProp) -> (DfNodeType, Vec<ArcDfPredNode>) {
+        let sort_keys = prop
+            .keys
+            .iter()
+            .map(|(col, order)| {
+                SortOrderPred::new(*order, ColumnRefPred::new(*col).into_pred_node())
+                    .into_pred_node()
+            })
+            .collect_vec();
+        (
+            DfNodeType::PhysicalSort,
+            vec![ListPred::new(sort_keys).into_pred_node()],
+        )
+    }
+
+    fn default(&self) -> Self::Prop {
+        OrderingProp::default()
+    }
+
+    fn property_name(&self) -> &'static str {
+        "ordering"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::{
+        DfReprPlanNode, LogicalScan, PhysicalHashJoin, PhysicalProjection, PhysicalSort,
+        PhysicalStreamAgg,
+    };
+    use crate::testing::TpchCatalog;
+
+    fn scan(table: &str) -> ArcDfPlanNode {
+        let scan = LogicalScan::new(table.into()).into_plan_node();
+        Arc::new(PlanNode {
+            typ: DfNodeType::PhysicalScan,
+            children: vec![],
+            predicates: scan.predicates.clone(),
+        })
+    }
+
+    fn columns(cols: &[usize]) -> ListPred {
+        ListPred::new(
+            cols.iter()
+                .map(|col| ColumnRefPred::new(*col).into_pred_node())
+                .collect(),
+        )
+    }
+
+    fn sort(child: ArcDfPlanNode, keys: &[(usize, SortOrderType)]) -> ArcDfPlanNode {
+        let keys = keys
+            .iter()
+            .map(|(col, order)| {
+                SortOrderPred::new(*order, ColumnRefPred::new(*col).into_pred_node())
+                    .into_pred_node()
+            })
+            .collect();
+        PhysicalSort::new(child, ListPred::new(keys)).into_plan_node()
+    }
+
+    fn count_sorts(plan: &ArcDfPlanNode) -> usize {
+        (plan.typ == DfNodeType::PhysicalSort) as usize
+            + plan
+                .children
+                .iter()
+                .map(|child| count_sorts(&child.unwrap_plan_node()))
+                .sum::<usize>()
+    }
+
+    #[test]
+    fn eliminate_sort_above_renaming_projection() {
+        let builder = OrderingPropertyBuilder::new(Arc::new(TpchCatalog));
+        // As in TPC-H Q1, the aggregation streams over orders sorted on (o_orderstatus,
+        // o_orderpriority), and the projection above swaps the group columns.
+        let agg = PhysicalStreamAgg::new(
+            sort(
+                scan("orders"),
+                &[(2, SortOrderType::Asc), (5, SortOrderType::Asc)],
+            ),
+            columns(&[3]),
+            columns(&[2, 5]),
+        )
+        .into_plan_node();
+        let projection = PhysicalProjection::new(agg, columns(&[1, 0, 2])).into_plan_node();
+        let plan = sort(
+            projection.clone(),
+            &[(1, SortOrderType::Asc), (0, SortOrderType::Asc)],
+        );
+        let (optimized, _) = builder.eliminate_redundant_sorts(&plan, &PlanAnnotations::new());
+        assert_eq!(count_sorts(&optimized), 1);
+        assert!(Arc::ptr_eq(&optimized, &projection));
+
+        // not sorted on the first group column
+        let plan = sort(projection.clone(), &[(0, SortOrderType::Asc)]);
+        let (optimized, _) = builder.eliminate_redundant_sorts(&plan, &PlanAnnotations::new());
+        assert_eq!(count_sorts(&optimized), 2);
+        let plan = sort(projection, &[(1, SortOrderType::Desc)]);
+        let (optimized, _) = builder.eliminate_redundant_sorts(&plan, &PlanAnnotations::new());
+        assert_eq!(count_sorts(&optimized), 2);
+    }
+
+    #[test]
+    fn eliminate_sort_on_equivalent_join_key() {
+        let builder = OrderingPropertyBuilder::new(Arc::new(TpchCatalog));
+        // customer (8 columns) joined with orders sorted on o_custkey
+        let join = PhysicalHashJoin::new(
+            scan("customer"),
+            sort(scan("orders"), &[(1, SortOrderType::Asc)]),
+            columns(&[0]),
+            columns(&[1]),
+            JoinType::Inner,
+        )
+        .into_plan_node();
+        let prop = builder
+            .eliminate_sorts_inner(&join, &PlanAnnotations::new(), &mut PlanAnnotations::new())
+            .1;
+        assert_eq!(prop.keys, vec![(9, SortOrderType::Asc)]);
+        assert!(prop.equivalent(0, 9));
+
+        // sorting on c_custkey is sorting on o_custkey
+        let plan = sort(join.clone(), &[(0, SortOrderType::Asc)]);
+        let (optimized, _) = builder.eliminate_redundant_sorts(&plan, &PlanAnnotations::new());
+        assert!(Arc::ptr_eq(&optimized, &join));
+
+        // the build side is not ordered
+        let join = PhysicalHashJoin::new(
+            sort(scan("customer"), &[(0, SortOrderType::Asc)]),
+            scan("orders"),
+            columns(&[0]),
+            columns(&[1]),
+            JoinType::Inner,
+        );
+        let plan = sort(join.clone().into_plan_node(), &[(0, SortOrderType::Asc)]);
+        let (optimized, _) = builder.eliminate_redundant_sorts(&plan, &PlanAnnotations::new());
+        assert_eq!(count_sorts(&optimized), 2);
+
+        // unless the hash table is built on the right side
+        let join = join.with_build_side(BuildSide::Right).into_plan_node();
+        let plan = sort(join.clone(), &[(0, SortOrderType::Asc)]);
+        let (optimized, _) = builder.eliminate_redundant_sorts(&plan, &PlanAnnotations::new());
+        assert!(Arc::ptr_eq(&optimized, &join));
+    }
+
+    #[test]
+    fn stream_agg_requirement() {
+        let required =
+            OrderingPropertyBuilder::stream_agg_requirement(&columns(&[1, 0]).to_vec()).unwrap();
+        let sorted_on = |keys: &[(usize, SortOrderType)]| OrderingProp {
+            keys: keys.to_vec(),
+            ..Default::default()
+        };
+        assert!(sorted_on(&[
+            (0, SortOrderType::Desc),
+            (1, SortOrderType::Asc),
+            (2, SortOrderType::Asc)
+        ])
+        .groups_adjacent(&required));
+        assert!(!sorted_on(&[
+            (0, SortOrderType::Asc),
+            (2, SortOrderType::Asc),
+            (1, SortOrderType::Asc)
+        ])
+        .groups_adjacent(&required));
+        assert!(!sorted_on(&[(0, SortOrderType::Asc)]).groups_adjacent(&required));
+
+        let prop = OrderingProp {
+            keys: vec![(0, SortOrderType::Asc), (3, SortOrderType::Asc)],
+            equivalences: vec![BTreeSet::from([1, 3])],
+            width: None,
+        };
+        assert!(prop.groups_adjacent(&required));
+    }
+}
@@ -3,8 +3,11 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
+use anyhow::{Context, Result};
+use async_trait::async_trait;
 use itertools::Itertools;
 use optd_og_core::logical_property::{LogicalProperty, LogicalPropertyBuilder};
 use serde::{Deserialize, Serialize};
@@ -14,24 +17,33 @@ use crate::plan_nodes::{
     decode_empty_relation_schema, ArcDfPredNode, ConstantPred, ConstantType, DfNodeType,
     DfPredType, DfReprPredNode, FuncType, JoinType, SubqueryType,
 };
+use crate::TableId;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Field {
+    /// The name of the column for display. A column is identified by its position in the
+    /// schema, and the names are not unique, e.g., after a self join.
     pub name: String,
     pub typ: ConstantType,
     pub nullable: bool,
 }
 
-impl std::fmt::Display for Field {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Field {
+    fn fmt_with_name(&self, name: &str, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.nullable {
-            write!(f, "{}:{:?}", self.name, self.typ)
+            write!(f, "{}:{:?}", name, self.typ)
         } else {
-            write!(f, "{}:{:?}(non-null)", self.name, self.typ)
+            write!(f, "{}:{:?}(non-null)", name, self.typ)
         }
     }
 }
 
+impl std::fmt::Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_name(&self.name, f)
+    }
+}
+
 impl Field {
     /// Generate a field that is only a place holder whose members are never used.
     fn placeholder() -> Self {
@@ -43,18 +55,21 @@ impl Field {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Schema {
     pub fields: Vec<Field>,
 }
 
 impl std::fmt::Display for Schema {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "[{}]",
-            self.fields.iter().map(|x| x.to_string()).join(", ")
-        )
+        write!(f, "[")?;
+        for (idx, (field, name)) in self.fields.iter().zip(self.unique_names()).enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            field.fmt_with_name(&name, f)?;
+        }
+        write!(f, "]")
     }
 }
 
@@ -70,6 +85,23 @@ impl Schema {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// The names of the columns, where a name shared by several columns is suffixed with the
+    /// position of the column, e.g., `[a#0, b, a#2]`.
+    pub fn unique_names(&self) -> Vec<String> {
+        let counts = self.fields.iter().counts_by(|field| field.name.as_str());
+        self.fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| {
+                if counts[field.name.as_str()] > 1 {
+                    format!("{}#{}", field.name, idx)
+                } else {
+                    field.name.clone()
+                }
+            })
+            .collect()
+    }
 }
 
 impl LogicalProperty for Schema {
@@ -78,17 +110,216 @@ impl LogicalProperty for Schema {
     }
 }
 
+/// The format a table is stored in, which the cost of reading its rows depends on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SourceFormat {
+    /// Costed like a table held in memory.
+    #[default]
+    Unknown,
+    /// Record batches held in memory.
+    Memory,
+    /// Arrow IPC files.
+    Arrow,
+    Parquet,
+    Csv,
+    /// Newline-delimited JSON files.
+    Json,
+}
+
+/// What the storage of a table can do when operators are pushed into its scan.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanCapabilities {
+    /// Whether only the projected columns are read, e.g., for columnar formats like Parquet.
+    /// Row-oriented formats like CSV have to read and parse every column anyway.
+    pub projection_pushdown: bool,
+    /// Whether the filters pushed into the scan reduce the rows read, e.g., Parquet skips the
+    /// row groups and pages their statistics rule out, while CSV has to read every row.
+    pub filter_pushdown: bool,
+    /// The format the rows are read from, see [`crate::cost::DfCostModel::format_io_factor`].
+    pub format: SourceFormat,
+}
+
+/// The tables of the catalog are looked up by the names of their scans, which the frontend
+/// qualifies with the catalog and schema where they are needed to tell apart the tables of the
+/// same name, e.g., `sales.orders`, normalized into a [`TableId`].
 pub trait Catalog: Send + Sync + 'static {
-    fn get(&self, name: &str) -> Schema;
+    fn get(&self, name: &TableId) -> Schema;
+
+    /// Returns the column indices of the primary key of the table, if the table has one.
+    fn primary_key(&self, _name: &TableId) -> Option<Vec<usize>> {
+        None
+    }
+
+    /// Returns what the storage of the table can do, assuming nothing by default.
+    fn scan_capabilities(&self, _name: &TableId) -> ScanCapabilities {
+        ScanCapabilities::default()
+    }
+
+    /// Returns the column indices of the columns the table is partitioned by, e.g., the
+    /// directories of a Hive-style partitioned Parquet table. The predicates on these columns
+    /// prune the partitions a scan reads, see [`crate::rules::PartitionPruningRule`].
+    fn partition_columns(&self, _name: &TableId) -> Vec<usize> {
+        vec![]
+    }
+
+    /// Returns the indexes of the table, which the scans filtered on their leading column can
+    /// look their rows up with, see [`crate::rules::IndexLookupRule`]. The indexes may be
+    /// hypothetical, e.g., to find out which indexes would make a workload cheaper.
+    fn indexes(&self, _name: &TableId) -> Vec<IndexDef> {
+        vec![]
+    }
+}
+
+/// An index of a table on the given columns.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IndexDef {
+    pub name: String,
+    pub columns: Vec<usize>,
+}
+
+/// Everything the optimizer looks up in the catalog for a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedTable {
+    pub schema: Schema,
+    pub primary_key: Option<Vec<usize>>,
+    pub scan_capabilities: ScanCapabilities,
+    pub partition_columns: Vec<usize>,
+}
+
+/// A catalog whose lookups are async, e.g., because the tables are stored remotely. The property
+/// builders cannot await, so the tables of a plan are resolved into a [`ResolvedCatalog`] before
+/// the plan is optimized. The tables are resolved by the names of their scans as written, e.g.,
+/// with the case of quoted identifiers.
+#[async_trait]
+pub trait AsyncCatalog: Send + Sync + 'static {
+    async fn resolve(&self, name: &str) -> Result<ResolvedTable>;
+}
+
+/// A [`Catalog`] serving the tables resolved before the optimization.
+#[derive(Default)]
+pub struct ResolvedCatalog {
+    tables: RwLock<HashMap<TableId, ResolvedTable>>,
+}
+
+impl ResolvedCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether the table is new or differs from the one resolved before.
+    pub fn insert(&self, name: impl Into<TableId>, table: ResolvedTable) -> bool {
+        let previous = self
+            .tables
+            .write()
+            .unwrap()
+            .insert(name.into(), table.clone());
+        previous.as_ref() != Some(&table)
+    }
+
+    /// Looks up the tables in `catalog` again, as they may have changed since the last query.
+    /// Returns the names of the tables which changed, whose cached schemas are stale, see
+    /// [`SchemaCache::invalidate`].
+    pub async fn resolve<'a>(
+        &self,
+        catalog: &dyn AsyncCatalog,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Vec<TableId>> {
+        let mut changed = vec![];
+        for name in names {
+            let table = catalog
+                .resolve(name)
+                .await
+                .with_context(|| format!("failed to resolve table {}", name))?;
+            let id = TableId::new(name);
+            if self.insert(id.clone(), table) {
+                changed.push(id);
+            }
+        }
+        Ok(changed)
+    }
+
+    fn with_table<R>(&self, name: &TableId, f: impl FnOnce(&ResolvedTable) -> R) -> R {
+        let tables = self.tables.read().unwrap();
+        let table = tables
+            .get(name)
+            .unwrap_or_else(|| panic!("table {} was not resolved before the optimization", name));
+        f(table)
+    }
+}
+
+impl Catalog for ResolvedCatalog {
+    fn get(&self, name: &TableId) -> Schema {
+        self.with_table(name, |table| table.schema.clone())
+    }
+
+    fn primary_key(&self, name: &TableId) -> Option<Vec<usize>> {
+        self.with_table(name, |table| table.primary_key.clone())
+    }
+
+    fn scan_capabilities(&self, name: &TableId) -> ScanCapabilities {
+        self.with_table(name, |table| table.scan_capabilities)
+    }
+
+    fn partition_columns(&self, name: &TableId) -> Vec<usize> {
+        self.with_table(name, |table| table.partition_columns.clone())
+    }
+}
+
+/// The schemas of the tables memoized by table name, so that deriving the schema of every scan
+/// does not look the table up in the catalog again. A cache can be shared by several
+/// [`SchemaPropertyBuilder`]s, and has to be invalidated when a table changes.
+#[derive(Default)]
+pub struct SchemaCache {
+    schemas: RwLock<HashMap<TableId, Schema>>,
+}
+
+impl SchemaCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get_or_insert_with(&self, name: &TableId, f: impl FnOnce() -> Schema) -> Schema {
+        if let Some(schema) = self.schemas.read().unwrap().get(name) {
+            return schema.clone();
+        }
+        let schema = f();
+        self.schemas
+            .write()
+            .unwrap()
+            .insert(name.clone(), schema.clone());
+        schema
+    }
+
+    /// Forget the schema of a table, e.g., after it was altered or re-created.
+    pub fn invalidate(&self, name: &str) {
+        self.schemas.write().unwrap().remove(&TableId::new(name));
+    }
+
+    pub fn invalidate_all(&self) {
+        self.schemas.write().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.schemas.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub struct SchemaPropertyBuilder {
     catalog: Arc<dyn Catalog>,
+    cache: Arc<SchemaCache>,
 }
 
 impl SchemaPropertyBuilder {
     pub fn new(catalog: Arc<dyn Catalog>) -> Self {
-        Self { catalog }
+        Self::new_with_cache(catalog, Arc::new(SchemaCache::new()))
+    }
+
+    pub fn new_with_cache(catalog: Arc<dyn Catalog>, cache: Arc<SchemaCache>) -> Self {
+        Self { catalog, cache }
     }
 
     fn derive_for_predicate(predicate: ArcDfPredNode) -> Schema {
@@ -167,24 +398,44 @@ impl LogicalPropertyBuilder<DfNodeType> for SchemaPropertyBuilder {
                     .unwrap()
                     .value()
                     .as_str();
-                self.catalog.get(&table_name)
+                let table = TableId::new(&table_name);
+                self.cache
+                    .get_or_insert_with(&table, || self.catalog.get(&table))
             }
-            DfNodeType::Agg => {
+            // A partial aggregation lives in a group of its own, and is treated as producing the
+            // same columns as the aggregation it is split from.
+            DfNodeType::Agg | DfNodeType::PhysicalPartialAgg => {
                 let mut group_by_schema = Self::derive_for_predicate(predicates[0].clone());
                 let agg_schema = Self::derive_for_predicate(predicates[1].clone());
                 group_by_schema.fields.extend(agg_schema.fields);
                 group_by_schema
             }
             DfNodeType::Projection => Self::derive_for_predicate(predicates[0].clone()),
-            DfNodeType::Filter | DfNodeType::Limit | DfNodeType::Sort => children[0].clone(),
+            DfNodeType::Filter
+            | DfNodeType::Limit
+            | DfNodeType::Sort
+            | DfNodeType::Distinct
+            | DfNodeType::Singleton => children[0].clone(),
             DfNodeType::Join(join_type) => {
                 use crate::plan_nodes::JoinType::*;
                 match join_type {
                     Inner | LeftOuter | RightOuter | FullOuter => {
-                        let mut schema = children[0].clone();
-                        let schema2 = children[1].clone();
-                        schema.fields.extend(schema2.fields);
-                        schema
+                        let mut left = children[0].clone();
+                        let mut right = children[1].clone();
+                        // The side which may not have a matching row is padded with nulls.
+                        if matches!(join_type, RightOuter | FullOuter) {
+                            left.fields
+                                .iter_mut()
+                                .for_each(|field| field.nullable = true);
+                        }
+                        if matches!(join_type, LeftOuter | FullOuter) {
+                            right
+                                .fields
+                                .iter_mut()
+                                .for_each(|field| field.nullable = true);
+                        }
+                        left.fields.extend(right.fields);
+                        left
                     }
                     LeftSemi | LeftAnti => children[0].clone(),
                     RightSemi | RightAnti => children[1].clone(),
@@ -203,7 +454,9 @@ impl LogicalPropertyBuilder<DfNodeType> for SchemaPropertyBuilder {
                 SubqueryType::Scalar => {
                     self.derive(DfNodeType::Join(JoinType::Inner), predicates, children)
                 }
-                SubqueryType::Exists | SubqueryType::Any { pred: _, op: _ } => {
+                SubqueryType::Exists
+                | SubqueryType::Any { pred: _, op: _ }
+                | SubqueryType::All { pred: _, op: _ } => {
                     self.derive(DfNodeType::Join(JoinType::LeftMark), predicates, children)
                 }
             },
@@ -211,6 +464,7 @@ impl LogicalPropertyBuilder<DfNodeType> for SchemaPropertyBuilder {
                 self.derive(DfNodeType::Join(JoinType::Inner), predicates, children)
             }
             DfNodeType::EmptyRelation => decode_empty_relation_schema(&predicates[1]),
+            DfNodeType::TableFunction => decode_empty_relation_schema(&predicates[2]),
             x => unimplemented!("cannot derive schema property for {}", x),
         }
     }
@@ -219,3 +473,141 @@ impl LogicalPropertyBuilder<DfNodeType> for SchemaPropertyBuilder {
         "schema"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TpchCatalog;
+
+    fn schema(fields: &[(&str, bool)]) -> Schema {
+        Schema {
+            fields: fields
+                .iter()
+                .map(|(name, nullable)| Field {
+                    name: name.to_string(),
+                    typ: ConstantType::Int32,
+                    nullable: *nullable,
+                })
+                .collect(),
+        }
+    }
+
+    fn join_schema(join_type: JoinType) -> Schema {
+        let builder = SchemaPropertyBuilder::new(Arc::new(TpchCatalog));
+        let left = schema(&[("a", false), ("b", false)]);
+        let right = schema(&[("c", false)]);
+        builder.derive(
+            DfNodeType::Join(join_type),
+            &[ConstantPred::bool(true).into_pred_node()],
+            &[&left, &right],
+        )
+    }
+
+    fn fields(schema: &Schema) -> Vec<(&str, bool)> {
+        schema
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.nullable))
+            .collect()
+    }
+
+    #[test]
+    fn join_schema_per_join_type() {
+        assert_eq!(
+            fields(&join_schema(JoinType::Inner)),
+            vec![("a", false), ("b", false), ("c", false)]
+        );
+        assert_eq!(
+            fields(&join_schema(JoinType::LeftOuter)),
+            vec![("a", false), ("b", false), ("c", true)]
+        );
+        assert_eq!(
+            fields(&join_schema(JoinType::RightOuter)),
+            vec![("a", true), ("b", true), ("c", false)]
+        );
+        assert_eq!(
+            fields(&join_schema(JoinType::FullOuter)),
+            vec![("a", true), ("b", true), ("c", true)]
+        );
+        for join_type in [JoinType::LeftSemi, JoinType::LeftAnti] {
+            assert_eq!(
+                fields(&join_schema(join_type)),
+                vec![("a", false), ("b", false)]
+            );
+        }
+        for join_type in [JoinType::RightSemi, JoinType::RightAnti] {
+            assert_eq!(fields(&join_schema(join_type)), vec![("c", false)]);
+        }
+        let mark = join_schema(JoinType::LeftMark);
+        assert_eq!(
+            fields(&mark),
+            vec![("a", false), ("b", false), ("exists", false)]
+        );
+        assert_eq!(mark.fields[2].typ, ConstantType::Bool);
+    }
+
+    #[derive(Default)]
+    struct CountingCatalog {
+        lookups: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Catalog for CountingCatalog {
+        fn get(&self, _name: &TableId) -> Schema {
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            schema(&[("a", false)])
+        }
+    }
+
+    #[test]
+    fn scan_schemas_are_cached() {
+        let catalog = Arc::new(CountingCatalog::default());
+        let cache = Arc::new(SchemaCache::new());
+        let builder = SchemaPropertyBuilder::new_with_cache(catalog.clone(), cache.clone());
+        // Another builder of the same optimizer, e.g., of the heuristic optimizer.
+        let other_builder = SchemaPropertyBuilder::new_with_cache(catalog.clone(), cache.clone());
+        let scan = |builder: &SchemaPropertyBuilder, table: &str| {
+            builder.derive(
+                DfNodeType::Scan,
+                &[ConstantPred::string(table).into_pred_node()],
+                &[],
+            )
+        };
+        let lookups = || catalog.lookups.load(std::sync::atomic::Ordering::Relaxed);
+
+        assert_eq!(fields(&scan(&builder, "t1")), vec![("a", false)]);
+        scan(&builder, "t1");
+        scan(&other_builder, "t1");
+        // The same table, quoted or in another case.
+        scan(&builder, "\"t1\"");
+        scan(&builder, "T1");
+        assert_eq!(lookups(), 1);
+        scan(&builder, "t2");
+        assert_eq!(lookups(), 2);
+        assert_eq!(cache.len(), 2);
+
+        cache.invalidate("t1");
+        scan(&other_builder, "t1");
+        scan(&builder, "t2");
+        assert_eq!(lookups(), 3);
+        cache.invalidate_all();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn unique_names_of_self_join() {
+        let builder = SchemaPropertyBuilder::new(Arc::new(TpchCatalog));
+        let table = schema(&[("a", false), ("b", true)]);
+        let self_join = builder.derive(
+            DfNodeType::Join(JoinType::Inner),
+            &[ConstantPred::bool(true).into_pred_node()],
+            &[&table, &table],
+        );
+        assert_eq!(self_join.unique_names(), vec!["a#0", "b#1", "a#2", "b#3"]);
+        assert_eq!(
+            self_join.to_string(),
+            "[a#0:Int32(non-null), b#1:Int32, a#2:Int32(non-null), b#3:Int32]"
+        );
+        assert_eq!(table.unique_names(), vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::Arc;
+
+use itertools::Itertools;
+use optd_og_core::logical_property::{LogicalProperty, LogicalPropertyBuilder};
+
+use super::schema::Catalog;
+use crate::plan_nodes::{
+    decode_empty_relation_schema, ArcDfPredNode, ColumnRefPred, ConstantPred, DfNodeType,
+    DfReprPredNode, JoinType, ListPred, SubqueryType,
+};
+use crate::TableId;
+
+/// The sets of output columns which are known to uniquely identify a row.
+///
+/// An empty key means the relation produces at most one row. If `keys` is empty, nothing is
+/// known about the uniqueness of the rows.
+#[derive(Clone, Debug, Default)]
+pub struct UniqueKeys {
+    pub keys: Vec<Vec<usize>>,
+    /// Number of output columns, used to derive keys for parent nodes.
+    pub column_cnt: usize,
+}
+
+impl std::fmt::Display for UniqueKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}]",
+            self.keys
+                .iter()
+                .map(|key| format!("({})", key.iter().join(", ")))
+                .join(", ")
+        )
+    }
+}
+
+impl UniqueKeys {
+    pub fn new(keys: Vec<Vec<usize>>, column_cnt: usize) -> Self {
+        Self { keys, column_cnt }
+    }
+
+    /// Nothing is known about the uniqueness of the rows.
+    pub fn unknown(column_cnt: usize) -> Self {
+        Self::new(vec![], column_cnt)
+    }
+
+    /// Whether the relation is known to contain no duplicated rows.
+    pub fn is_unique(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Whether the given columns are known to uniquely identify a row.
+    pub fn is_key(&self, columns: &[usize]) -> bool {
+        self.keys
+            .iter()
+            .any(|key| key.iter().all(|col| columns.contains(col)))
+    }
+}
+
+impl LogicalProperty for UniqueKeys {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+pub struct UniquenessPropertyBuilder {
+    catalog: Arc<dyn Catalog>,
+}
+
+impl UniquenessPropertyBuilder {
+    pub fn new(catalog: Arc<dyn Catalog>) -> Self {
+        Self { catalog }
+    }
+}
+
+impl LogicalPropertyBuilder<DfNodeType> for UniquenessPropertyBuilder {
+    type Prop = UniqueKeys;
+
+    fn derive(
+        &self,
+        typ: DfNodeType,
+        predicates: &[ArcDfPredNode],
+        children: &[&Self::Prop],
+    ) -> Self::Prop {
+        match typ {
+            DfNodeType::Scan => {
+                let table_name = ConstantPred::from_pred_node(predicates[0].clone())
+                    .unwrap()
+                    .value()
+                    .as_str();
+                let table = TableId::new(&table_name);
+                let column_cnt = self.catalog.get(&table).len();
+                let keys = self.catalog.primary_key(&table).into_iter().collect();
+                UniqueKeys::new(keys, column_cnt)
+            }
+            DfNodeType::EmptyRelation => {
+                // Produces either zero or one row.
+                let column_cnt = decode_empty_relation_schema(&predicates[1]).len();
+                UniqueKeys::new(vec![vec![]], column_cnt)
+            }
+            DfNodeType::TableFunction => {
+                let column_cnt = decode_empty_relation_schema(&predicates[2]).len();
+                UniqueKeys::unknown(column_cnt)
+            }
+            DfNodeType::Projection => {
+                let exprs = ListPred::from_pred_node(predicates[0].clone()).unwrap();
+                // Map each child column to the first output column which directly refers to it.
+                let mut child_to_output = vec![None; children[0].column_cnt];
+                for (output_idx, expr) in exprs.to_vec().into_iter().enumerate() {
+                    if let Some(col) = ColumnRefPred::from_pred_node(expr) {
+                        if let Some(slot) = child_to_output.get_mut(col.index()) {
+                            slot.get_or_insert(output_idx);
+                        }
+                    }
+                }
+                let keys = children[0]
+                    .keys
+                    .iter()
+                    .filter_map(|key| key.iter().map(|col| child_to_output[*col]).collect())
+                    .collect();
+                UniqueKeys::new(keys, exprs.len())
+            }
+            // A singleton does not check that its child produces a single row, the empty key of
+            // an aggregation without groups is kept.
+            DfNodeType::Filter | DfNodeType::Sort | DfNodeType::Limit | DfNodeType::Singleton => {
+                children[0].clone()
+            }
+            DfNodeType::Distinct => {
+                if children[0].is_unique() {
+                    children[0].clone()
+                } else {
+                    let column_cnt = children[0].column_cnt;
+                    UniqueKeys::new(vec![(0..column_cnt).collect()], column_cnt)
+                }
+            }
+            DfNodeType::Agg => {
+                // Group by columns come first in the output and are unique by definition.
+                let agg_cnt = ListPred::from_pred_node(predicates[0].clone())
+                    .unwrap()
+                    .len();
+                let group_cnt = ListPred::from_pred_node(predicates[1].clone())
+                    .unwrap()
+                    .len();
+                UniqueKeys::new(vec![(0..group_cnt).collect()], group_cnt + agg_cnt)
+            }
+            DfNodeType::PhysicalPartialAgg => {
+                // The same group may be produced by each of the partitions.
+                let agg_cnt = ListPred::from_pred_node(predicates[0].clone())
+                    .unwrap()
+                    .len();
+                let group_cnt = ListPred::from_pred_node(predicates[1].clone())
+                    .unwrap()
+                    .len();
+                UniqueKeys::unknown(group_cnt + agg_cnt)
+            }
+            DfNodeType::Join(join_type) => {
+                let left = children[0];
+                let right = children[1];
+                match join_type {
+                    JoinType::Inner => {
+                        // Every output row is a distinct pair of a left and a right row.
+                        let keys = left
+                            .keys
+                            .iter()
+                            .cartesian_product(right.keys.iter())
+                            .map(|(l, r)| {
+                                l.iter()
+                                    .copied()
+                                    .chain(r.iter().map(|col| col + left.column_cnt))
+                                    .collect()
+                            })
+                            .collect();
+                        UniqueKeys::new(keys, left.column_cnt + right.column_cnt)
+                    }
+                    JoinType::LeftOuter | JoinType::RightOuter | JoinType::FullOuter => {
+                        UniqueKeys::unknown(left.column_cnt + right.column_cnt)
+                    }
+                    JoinType::LeftSemi | JoinType::LeftAnti => left.clone(),
+                    JoinType::RightSemi | JoinType::RightAnti => right.clone(),
+                    JoinType::LeftMark => UniqueKeys::new(left.keys.clone(), left.column_cnt + 1),
+                }
+            }
+            DfNodeType::RawDepJoin(sq_type) => match sq_type {
+                SubqueryType::Scalar => {
+                    self.derive(DfNodeType::Join(JoinType::Inner), predicates, children)
+                }
+                SubqueryType::Exists
+                | SubqueryType::Any { pred: _, op: _ }
+                | SubqueryType::All { pred: _, op: _ } => {
+                    self.derive(DfNodeType::Join(JoinType::LeftMark), predicates, children)
+                }
+            },
+            DfNodeType::DepJoin => {
+                self.derive(DfNodeType::Join(JoinType::Inner), predicates, children)
+            }
+            x => unimplemented!("cannot derive uniqueness property for {}", x),
+        }
+    }
+
+    fn property_name(&self) -> &'static str {
+        "uniqueness"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::{DfReprPlanNode, LogicalScan};
+    use crate::testing::TpchCatalog;
+
+    fn columns(columns: &[usize]) -> ArcDfPredNode {
+        ListPred::new(
+            columns
+                .iter()
+                .map(|idx| ColumnRefPred::new(*idx).into_pred_node())
+                .collect(),
+        )
+        .into_pred_node()
+    }
+
+    #[test]
+    fn is_key() {
+        let keys = UniqueKeys::new(vec![vec![0, 2], vec![3]], 4);
+        assert!(keys.is_unique());
+        assert!(keys.is_key(&[0, 1, 2]));
+        assert!(keys.is_key(&[3]));
+        assert!(!keys.is_key(&[0, 1]));
+        assert!(!UniqueKeys::unknown(4).is_unique());
+    }
+
+    #[test]
+    fn singleton_keeps_child_keys() {
+        let builder = UniquenessPropertyBuilder::new(Arc::new(TpchCatalog));
+        let child = UniqueKeys::unknown(2);
+        assert!(!builder
+            .derive(DfNodeType::Singleton, &[], &[&child])
+            .is_unique());
+        let agg = UniqueKeys::new(vec![vec![]], 1);
+        assert!(builder
+            .derive(DfNodeType::Singleton, &[], &[&agg])
+            .is_key(&[]));
+    }
+
+    #[test]
+    fn scan_and_projection_keys() {
+        let builder = UniquenessPropertyBuilder::new(Arc::new(TpchCatalog));
+        // customer has the primary key custkey (#0)
+        let scan = LogicalScan::new("customer".into()).into_plan_node();
+        let scan_keys = builder.derive(DfNodeType::Scan, &scan.predicates, &[]);
+        assert!(scan_keys.is_key(&[0]));
+        assert!(!scan_keys.is_key(&[1, 2]));
+
+        let proj = builder.derive(DfNodeType::Projection, &[columns(&[1, 0])], &[&scan_keys]);
+        assert_eq!(proj.column_cnt, 2);
+        assert!(proj.is_key(&[1]));
+        assert!(!proj.is_key(&[0]));
+
+        let proj = builder.derive(DfNodeType::Projection, &[columns(&[1, 2])], &[&scan_keys]);
+        assert!(!proj.is_unique());
+    }
+
+    #[test]
+    fn distinct_and_agg_keys() {
+        let builder = UniquenessPropertyBuilder::new(Arc::new(TpchCatalog));
+        let child = UniqueKeys::unknown(3);
+        let distinct = builder.derive(DfNodeType::Distinct, &[], &[&child]);
+        assert!(distinct.is_key(&[0, 1, 2]));
+        assert!(!distinct.is_key(&[0, 1]));
+
+        let keyed = UniqueKeys::new(vec![vec![1]], 3);
+        let distinct = builder.derive(DfNodeType::Distinct, &[], &[&keyed]);
+        assert!(distinct.is_key(&[1]));
+
+        // one aggregate grouped by two columns
+        let agg = builder.derive(
+            DfNodeType::Agg,
+            &[columns(&[2]), columns(&[0, 1])],
+            &[&child],
+        );
+        assert_eq!(agg.column_cnt, 3);
+        assert!(agg.is_key(&[0, 1]));
+        assert!(!agg.is_key(&[0, 2]));
+
+        // no groups, a single row
+        let agg = builder.derive(DfNodeType::Agg, &[columns(&[0]), columns(&[])], &[&child]);
+        assert!(agg.is_key(&[]));
+    }
+
+    #[test]
+    fn join_keys() {
+        let builder = UniquenessPropertyBuilder::new(Arc::new(TpchCatalog));
+        let left = UniqueKeys::new(vec![vec![0]], 2);
+        let right = UniqueKeys::new(vec![vec![1]], 3);
+        let inner = builder.derive(DfNodeType::Join(JoinType::Inner), &[], &[&left, &right]);
+        assert_eq!(inner.column_cnt, 5);
+        assert!(inner.is_key(&[0, 3]));
+        assert!(!inner.is_key(&[0]));
+
+        let outer = builder.derive(DfNodeType::Join(JoinType::LeftOuter), &[], &[&left, &right]);
+        assert!(!outer.is_unique());
+
+        let semi = builder.derive(DfNodeType::Join(JoinType::LeftSemi), &[], &[&left, &right]);
+        assert!(semi.is_key(&[0]));
+
+        let mark = builder.derive(DfNodeType::Join(JoinType::LeftMark), &[], &[&left, &right]);
+        assert_eq!(mark.column_cnt, 3);
+        assert!(mark.is_key(&[0]));
+    }
+}
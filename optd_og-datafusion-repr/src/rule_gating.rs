@@ -0,0 +1,220 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Gates the rules whose rewrites made the plans of a query slower, as observed when the plans
+//! were executed.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use crate::plan_nodes::ArcDfPlanNode;
+
+/// Identifies the queries which are optimized from the same plan. Fingerprints are only
+/// comparable within a process.
+pub type QueryFingerprint = u64;
+
+pub fn query_fingerprint(plan: &ArcDfPlanNode) -> QueryFingerprint {
+    let mut hasher = DefaultHasher::new();
+    plan.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Overrides the decision of the [`RuleGatingController`] for a rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleOverride {
+    /// Never gate the rule.
+    Enabled,
+    /// Always gate the rule.
+    Disabled,
+}
+
+/// A rule which is not applied when optimizing a query, and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleGate {
+    pub rule: String,
+    pub reason: String,
+}
+
+struct Execution {
+    rules: BTreeSet<String>,
+    elapsed: Duration,
+}
+
+/// Compares the execution times of the plans of a query with and without the rewrites of each
+/// rule, and gates the rules whose rewrites correlate with slower executions. Only the plans
+/// whose rules differ between executions tell the rules apart, e.g., when the plan changes with
+/// the runtime statistics in adaptive mode.
+pub struct RuleGatingController {
+    /// The number of executions with and without the rewrites of a rule needed to judge it.
+    pub min_executions: usize,
+    /// How many times slower the plans with the rewrites of a rule must be on average for the
+    /// rule to be gated.
+    pub regression_ratio: f64,
+    /// The rules of the last plan of each query, which the next execution is attributed to.
+    pending: HashMap<QueryFingerprint, BTreeSet<String>>,
+    executions: HashMap<QueryFingerprint, Vec<Execution>>,
+    /// The overrides of a single query, or of all queries if the fingerprint is `None`.
+    overrides: HashMap<(Option<QueryFingerprint>, String), RuleOverride>,
+}
+
+impl Default for RuleGatingController {
+    fn default() -> Self {
+        Self::new(3, 1.5)
+    }
+}
+
+impl RuleGatingController {
+    pub fn new(min_executions: usize, regression_ratio: f64) -> Self {
+        Self {
+            min_executions,
+            regression_ratio,
+            pending: HashMap::new(),
+            executions: HashMap::new(),
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Records the rules whose rewrites the plan chosen for the query is made of.
+    pub fn record_plan(&mut self, fingerprint: QueryFingerprint, rules: BTreeSet<String>) {
+        self.pending.insert(fingerprint, rules);
+    }
+
+    /// Records how long the last plan of the query took to execute. Returns `false` if no plan
+    /// of the query was recorded.
+    pub fn record_execution(&mut self, fingerprint: QueryFingerprint, elapsed: Duration) -> bool {
+        let Some(rules) = self.pending.get(&fingerprint) else {
+            return false;
+        };
+        self.executions
+            .entry(fingerprint)
+            .or_default()
+            .push(Execution {
+                rules: rules.clone(),
+                elapsed,
+            });
+        true
+    }
+
+    /// Overrides the decision for `rule`, for the query of `fingerprint` or for all queries.
+    pub fn set_override(
+        &mut self,
+        fingerprint: Option<QueryFingerprint>,
+        rule: impl Into<String>,
+        rule_override: RuleOverride,
+    ) {
+        self.overrides
+            .insert((fingerprint, rule.into()), rule_override);
+    }
+
+    pub fn clear_override(&mut self, fingerprint: Option<QueryFingerprint>, rule: &str) {
+        self.overrides.remove(&(fingerprint, rule.to_string()));
+    }
+
+    fn get_override(&self, fingerprint: QueryFingerprint, rule: &str) -> Option<RuleOverride> {
+        self.overrides
+            .get(&(Some(fingerprint), rule.to_string()))
+            .or_else(|| self.overrides.get(&(None, rule.to_string())))
+            .copied()
+    }
+
+    /// The rules not to apply when optimizing the query, ordered by name.
+    pub fn gated_rules(&self, fingerprint: QueryFingerprint) -> Vec<RuleGate> {
+        let mut gates = BTreeSet::new();
+        for ((override_fingerprint, rule), rule_override) in &self.overrides {
+            if *rule_override == RuleOverride::Disabled
+                && override_fingerprint.is_none_or(|f| f == fingerprint)
+                && self.get_override(fingerprint, rule) == Some(RuleOverride::Disabled)
+            {
+                gates.insert((rule.clone(), "disabled by an override".to_string()));
+            }
+        }
+        let executions = self
+            .executions
+            .get(&fingerprint)
+            .map_or(&[][..], Vec::as_slice);
+        let rules: BTreeSet<&String> = executions.iter().flat_map(|e| &e.rules).collect();
+        for rule in rules {
+            if self.get_override(fingerprint, rule).is_some() {
+                continue;
+            }
+            let (with, without): (Vec<_>, Vec<_>) =
+                executions.iter().partition(|e| e.rules.contains(rule));
+            if with.len() < self.min_executions || without.len() < self.min_executions {
+                continue;
+            }
+            let mean = |executions: &[&Execution]| {
+                executions
+                    .iter()
+                    .map(|e| e.elapsed.as_secs_f64())
+                    .sum::<f64>()
+                    / executions.len() as f64
+            };
+            let (mean_with, mean_without) = (mean(&with), mean(&without));
+            if mean_with > mean_without * self.regression_ratio {
+                gates.insert((
+                    rule.clone(),
+                    format!(
+                        "the plans with its rewrites ran {:.1}x slower ({} executions with, {} without)",
+                        mean_with / mean_without,
+                        with.len(),
+                        without.len()
+                    ),
+                ));
+            }
+        }
+        gates
+            .into_iter()
+            .map(|(rule, reason)| RuleGate { rule, reason })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn gate_rules_correlated_with_regressions() {
+        let mut controller = RuleGatingController::new(2, 1.5);
+        let fingerprint = 42;
+        let mut run = |plan_rules: &[&str], millis| {
+            controller.record_plan(fingerprint, rules(plan_rules));
+            assert!(controller.record_execution(fingerprint, Duration::from_millis(millis)));
+        };
+        run(&["hash_join_rule"], 100);
+        run(&["hash_join_rule", "project_filter_transpose_rule"], 300);
+        run(&["hash_join_rule"], 110);
+        run(&["hash_join_rule", "project_filter_transpose_rule"], 280);
+
+        let gates = controller.gated_rules(fingerprint);
+        assert_eq!(gates.len(), 1);
+        assert_eq!(gates[0].rule, "project_filter_transpose_rule");
+        assert!(controller.gated_rules(fingerprint + 1).is_empty());
+        assert!(!controller.record_execution(fingerprint + 1, Duration::from_millis(1)));
+
+        controller.set_override(
+            Some(fingerprint),
+            "project_filter_transpose_rule",
+            RuleOverride::Enabled,
+        );
+        assert!(controller.gated_rules(fingerprint).is_empty());
+        controller.set_override(None, "join_assoc_rule", RuleOverride::Disabled);
+        assert_eq!(
+            controller.gated_rules(fingerprint),
+            vec![RuleGate {
+                rule: "join_assoc_rule".to_string(),
+                reason: "disabled by an override".to_string(),
+            }]
+        );
+        controller.clear_override(Some(fingerprint), "project_filter_transpose_rule");
+        assert_eq!(controller.gated_rules(fingerprint).len(), 2);
+    }
+}
@@ -3,23 +3,37 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+mod agg;
+mod declarative;
+mod distinct;
 mod eliminate_duplicated_expr;
 mod eliminate_limit;
+mod eliminate_self_join;
 mod filter;
 mod filter_pushdown;
+mod index_lookup;
 mod joins;
 mod macros;
+mod partition_pruning;
 mod physical;
 mod project_transpose;
+mod star_schema;
 mod subquery;
 
+pub use agg::*;
+pub use declarative::{parse_declarative_rules, DeclarativeRule, DeclarativeRuleSpec};
+pub use distinct::*;
 pub use eliminate_duplicated_expr::*;
 pub use eliminate_limit::*;
+pub use eliminate_self_join::*;
 pub use filter::*;
 pub use filter_pushdown::*;
+pub use index_lookup::IndexLookupRule;
 pub use joins::*;
+pub use partition_pruning::PartitionPruningRule;
 pub use physical::PhysicalConversionRule;
 pub use project_transpose::*;
+pub use star_schema::StarSchemaJoinRule;
 pub use subquery::{
     DepInitialDistinct, DepJoinEliminate, DepJoinPastAgg, DepJoinPastFilter, DepJoinPastProj,
 };
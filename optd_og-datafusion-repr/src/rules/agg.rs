@@ -0,0 +1,487 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use optd_og_core::nodes::PlanNodeOrGroup;
+use optd_og_core::optimizer::Optimizer;
+use optd_og_core::rules::{Rule, RuleMatcher};
+
+use super::macros::{define_impl_rule, define_rule};
+use crate::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, BinOpPred, BinOpType, ColumnRefPred, ConstantPred, DfNodeType,
+    DfPredType, DfReprPlanNode, DfReprPredNode, JoinType, ListPred, LogOpType, LogicalAgg,
+    LogicalJoin, LogicalProjection, LogicalSort, PhysicalFinalAgg, PhysicalPartialAgg,
+    PhysicalStreamAgg, PredExt, SortOrderPred, SortOrderType,
+};
+use crate::properties::ordering::OrderingPropertyBuilder;
+use crate::OptimizerExt;
+
+define_impl_rule!(
+    StreamAggRule,
+    apply_stream_agg,
+    StreamAggPicks,
+    (Agg => agg: LogicalAgg, (Sort => sort: LogicalSort, child))
+);
+
+/// Implements an aggregation over a sort on the group-by keys as a streaming aggregation,
+/// which does not need to build a hash table. The sort must satisfy the ordering the stream
+/// aggregation requires of its input, i.e., its leading keys must be the group-by columns, in
+/// any order and direction. The aggregations over other inputs are implemented by
+/// [`SortAggRule`], whose sort is eliminated if the input turns out to be ordered already.
+/// For example:
+///     select custkey, count(*)
+///     from (select * from orders order by custkey)
+///     group by custkey
+fn apply_stream_agg(
+    _optimizer: &impl Optimizer<DfNodeType>,
+    StreamAggPicks { agg, sort, .. }: StreamAggPicks,
+) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+    let groups = agg.groups();
+    if groups.is_empty() {
+        return vec![];
+    }
+    let Some(required) = OrderingPropertyBuilder::stream_agg_requirement(&groups.to_vec()) else {
+        return vec![];
+    };
+    if !OrderingPropertyBuilder::sort_ordering(&sort.exprs()).groups_adjacent(&required) {
+        return vec![];
+    }
+    let node = PhysicalStreamAgg::new_unchecked(sort.into_plan_node(), agg.exprs(), groups);
+    vec![node.into_plan_node().into()]
+}
+
+define_impl_rule!(
+    SortAggRule,
+    apply_sort_agg,
+    SortAggPicks,
+    (Agg => agg: LogicalAgg, child)
+);
+
+/// Implements an aggregation as a sort on the group-by keys followed by a streaming aggregation,
+/// as opposed to the hash aggregation, so that the cost model can choose between the two by the
+/// size of the input. The sort is a logical node, so that it can be eliminated when the input is
+/// already sorted. A distinct is implemented the same way once converted into an aggregation.
+/// For example:
+///     select custkey, count(*)
+///     from orders
+///     group by custkey
+/// becomes
+///     select custkey, count(*)
+///     from (select * from orders order by custkey)
+///     group by custkey
+fn apply_sort_agg(
+    _optimizer: &impl Optimizer<DfNodeType>,
+    SortAggPicks { agg, child }: SortAggPicks,
+) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+    let groups = agg.groups();
+    if groups.is_empty() {
+        return vec![];
+    }
+    let mut sort_exprs = Vec::with_capacity(groups.len());
+    for group in groups.to_vec() {
+        let Some(col) = ColumnRefPred::from_pred_node(group) else {
+            return vec![];
+        };
+        sort_exprs
+            .push(SortOrderPred::new(SortOrderType::Asc, col.into_pred_node()).into_pred_node());
+    }
+    let sort = LogicalSort::new_unchecked(child, ListPred::new(sort_exprs));
+    let node = PhysicalStreamAgg::new_unchecked(sort.into_plan_node(), agg.exprs(), groups);
+    vec![node.into_plan_node().into()]
+}
+
+/// Implements an aggregation as a partial aggregation on each input partition followed by a
+/// final aggregation which merges the partial results, so that the bulk of the aggregation
+/// runs in parallel. Only registered when the input is read in more than one partition, see
+/// `DatafusionOptimizer::set_target_partitions`.
+pub struct TwoPhaseAggRule {
+    matcher: RuleMatcher<DfNodeType>,
+    partitions: usize,
+}
+
+impl TwoPhaseAggRule {
+    pub fn new(partitions: usize) -> Self {
+        Self {
+            matcher: RuleMatcher::MatchNode {
+                typ: DfNodeType::Agg,
+                children: vec![RuleMatcher::Any],
+            },
+            partitions,
+        }
+    }
+}
+
+impl<O: Optimizer<DfNodeType>> Rule<DfNodeType, O> for TwoPhaseAggRule {
+    fn matcher(&self) -> &RuleMatcher<DfNodeType> {
+        &self.matcher
+    }
+
+    fn apply(&self, _optimizer: &O, binding: ArcDfPlanNode) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+        let agg = LogicalAgg::from_plan_node(binding).unwrap();
+        let partial = PhysicalPartialAgg::new_unchecked(
+            agg.child(),
+            agg.exprs(),
+            agg.groups(),
+            ConstantPred::uint64(self.partitions as u64),
+        );
+        let node =
+            PhysicalFinalAgg::new_unchecked(partial.into_plan_node(), agg.exprs(), agg.groups());
+        vec![node.into_plan_node().into()]
+    }
+
+    fn name(&self) -> &'static str {
+        "two_phase_agg_rule"
+    }
+
+    fn is_impl_rule(&self) -> bool {
+        true
+    }
+}
+
+define_rule!(
+    AggJoinTransposeRule,
+    apply_agg_join_transpose,
+    AggJoinTransposePicks,
+    (Agg => agg: LogicalAgg, (Join(JoinType::Inner) => join: LogicalJoin, left, right))
+);
+
+/// Collects the column pairs of a conjunction of `left_col = right_col` predicates, in the
+/// columns of each side. Returns `None` if any part of the condition is not of this form.
+fn equi_join_columns(cond: &ArcDfPredNode, left_len: usize) -> Option<Vec<(usize, usize)>> {
+    let conds = match cond.typ {
+        DfPredType::BinOp(BinOpType::Eq) => vec![cond.clone()],
+        DfPredType::LogOp(LogOpType::And) => cond.children.clone(),
+        _ => return None,
+    };
+    let mut pairs = vec![];
+    for cond in conds {
+        let bin_op = BinOpPred::from_pred_node(cond)?;
+        if bin_op.op_type() != BinOpType::Eq {
+            return None;
+        }
+        let left = ColumnRefPred::from_pred_node(bin_op.left_child())?.index();
+        let right = ColumnRefPred::from_pred_node(bin_op.right_child())?.index();
+        let (left, right) = if left < right {
+            (left, right)
+        } else {
+            (right, left)
+        };
+        if left >= left_len || right < left_len {
+            return None;
+        }
+        pairs.push((left, right - left_len));
+    }
+    Some(pairs)
+}
+
+/// Pushes an aggregation below an inner join into the side its aggregate expressions refer to,
+/// grouping that side by its group-by and join columns:
+///     select c.custkey, c.name, sum(o.totalprice)
+///     from customer c join orders o on c.custkey = o.custkey
+///     group by c.custkey, c.name
+/// becomes
+///     select c.custkey, c.name, o.total
+///     from customer c
+///     join (select custkey, sum(totalprice) as total from orders group by custkey) o
+///     on c.custkey = o.custkey
+///
+/// Each group of the pushed side must join with a single group of the original aggregation,
+/// which holds if the join columns of the other side are a unique key of it, so that no row is
+/// duplicated by the join, and if the join columns of the pushed side are determined by the
+/// group-by columns, so that the groups are not split. Whether the pushed aggregation is
+/// cheaper, e.g., because it reduces the input of the join, is left to the cost model.
+fn apply_agg_join_transpose(
+    optimizer: &impl Optimizer<DfNodeType>,
+    AggJoinTransposePicks {
+        agg,
+        join,
+        left,
+        right,
+    }: AggJoinTransposePicks,
+) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+    // An aggregation without groups produces a row even if the join produces none.
+    if agg.groups().is_empty() {
+        return vec![];
+    }
+    let mut groups = vec![];
+    for group in agg.groups().to_vec() {
+        let Some(col) = ColumnRefPred::from_pred_node(group) else {
+            return vec![];
+        };
+        groups.push(col.index());
+    }
+    let left_len = optimizer.get_schema_of(left.clone()).len();
+    let right_len = optimizer.get_schema_of(right.clone()).len();
+    let Some(pairs) = equi_join_columns(&join.cond(), left_len) else {
+        return vec![];
+    };
+    let exprs = agg.exprs().to_vec();
+    let expr_cols = exprs
+        .iter()
+        .flat_map(|expr| expr.get_column_refs())
+        .map(|col| col.index())
+        .collect::<Vec<_>>();
+    let push_left = expr_cols.iter().all(|col| *col < left_len);
+    let push_right = expr_cols.iter().all(|col| *col >= left_len);
+    if !push_left && !push_right {
+        return vec![];
+    }
+    // The offset of the pushed side among the join columns, and the pairs of join columns of the
+    // pushed and the other side.
+    let (pushed, other, offset, pairs) = if push_left {
+        (left.clone(), right.clone(), 0, pairs)
+    } else {
+        let pairs = pairs.into_iter().map(|(l, r)| (r, l)).collect();
+        (right.clone(), left.clone(), left_len, pairs)
+    };
+    let pushed_len = if push_left { left_len } else { right_len };
+    let other_offset = if push_left { left_len } else { 0 };
+    let is_pushed = |col: usize| col >= offset && col < offset + pushed_len;
+
+    let other_keys = pairs.iter().map(|(_, o)| *o).collect::<Vec<_>>();
+    if !optimizer.get_unique_keys_of(other).is_key(&other_keys) {
+        return vec![];
+    }
+    let pushed_groups = groups
+        .iter()
+        .filter(|col| is_pushed(**col))
+        .map(|col| col - offset)
+        .collect::<Vec<_>>();
+    let groups_determine_key = optimizer
+        .get_unique_keys_of(pushed.clone())
+        .is_key(&pushed_groups);
+    let determined = pairs.iter().all(|(p, o)| {
+        groups_determine_key
+            || groups.contains(&(p + offset))
+            || groups.contains(&(o + other_offset))
+    });
+    if !determined {
+        return vec![];
+    }
+
+    // Group the pushed side by its group-by columns and its join columns.
+    let mut new_groups = pushed_groups;
+    for (p, _) in &pairs {
+        if !new_groups.contains(p) {
+            new_groups.push(*p);
+        }
+    }
+    let new_exprs = exprs
+        .iter()
+        .map(|expr| expr.rewrite_column_refs(|col| Some(col - offset)).unwrap())
+        .collect();
+    let new_agg = LogicalAgg::new_unchecked(
+        pushed,
+        ListPred::new(new_exprs),
+        ListPred::new(
+            new_groups
+                .iter()
+                .map(|col| ColumnRefPred::new(*col).into_pred_node())
+                .collect(),
+        ),
+    );
+    let new_agg_len = new_groups.len() + exprs.len();
+    // Maps the columns of the join to the columns of the new join.
+    let new_other_offset = if push_left { new_agg_len } else { 0 };
+    let new_offset = if push_left { 0 } else { left_len };
+    let map_col = |col: usize| {
+        if is_pushed(col) {
+            new_groups
+                .iter()
+                .position(|group| *group == col - offset)
+                .map(|idx| idx + new_offset)
+        } else {
+            Some(col - other_offset + new_other_offset)
+        }
+    };
+    let Some(cond) = join.cond().rewrite_column_refs(map_col) else {
+        return vec![];
+    };
+    let new_join = if push_left {
+        LogicalJoin::new_unchecked(new_agg.into_plan_node(), right, cond, JoinType::Inner)
+    } else {
+        LogicalJoin::new_unchecked(left, new_agg.into_plan_node(), cond, JoinType::Inner)
+    };
+
+    let mut proj_exprs = Vec::with_capacity(groups.len() + exprs.len());
+    for col in &groups {
+        proj_exprs.push(ColumnRefPred::new(map_col(*col).unwrap()).into_pred_node());
+    }
+    for idx in 0..exprs.len() {
+        proj_exprs.push(ColumnRefPred::new(new_offset + new_groups.len() + idx).into_pred_node());
+    }
+    let node =
+        LogicalProjection::new_unchecked(new_join.into_plan_node(), ListPred::new(proj_exprs));
+    vec![node.into_plan_node().into()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::plan_nodes::{FuncPred, FuncType, LogicalScan};
+    use crate::testing::{new_test_optimizer, new_test_optimizer_with_keys};
+
+    fn agg_over_sort(groups: Vec<usize>, sort_keys: Vec<usize>) -> ArcDfPlanNode {
+        let sort = LogicalSort::new(
+            LogicalScan::new("customer".into()).into_plan_node(),
+            ListPred::new(
+                sort_keys
+                    .into_iter()
+                    .map(|idx| {
+                        SortOrderPred::new(
+                            SortOrderType::Asc,
+                            ColumnRefPred::new(idx).into_pred_node(),
+                        )
+                        .into_pred_node()
+                    })
+                    .collect(),
+            ),
+        );
+        LogicalAgg::new(
+            sort.into_plan_node(),
+            ListPred::new(vec![]),
+            ListPred::new(
+                groups
+                    .into_iter()
+                    .map(|idx| ColumnRefPred::new(idx).into_pred_node())
+                    .collect(),
+            ),
+        )
+        .into_plan_node()
+    }
+
+    #[test]
+    fn stream_agg_on_sorted_groups() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(StreamAggRule::new()));
+
+        let plan = test_optimizer
+            .optimize(agg_over_sort(vec![1, 0], vec![0, 1, 2]))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::PhysicalStreamAgg);
+        assert_eq!(plan.child_rel(0).typ, DfNodeType::Sort);
+
+        let plan = test_optimizer
+            .optimize(agg_over_sort(vec![0, 2], vec![0, 1, 2]))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Agg);
+    }
+
+    #[test]
+    fn sort_agg_sorts_on_groups() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(SortAggRule::new()));
+
+        let plan = test_optimizer
+            .optimize(agg_over_sort(vec![1, 0], vec![2]))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::PhysicalStreamAgg);
+        let sort = LogicalSort::from_plan_node(plan.child_rel(0)).unwrap();
+        let sort_cols = sort
+            .exprs()
+            .to_vec()
+            .into_iter()
+            .map(|expr| {
+                let order = SortOrderPred::from_pred_node(expr).unwrap();
+                assert_eq!(order.order(), SortOrderType::Asc);
+                ColumnRefPred::from_pred_node(order.child())
+                    .unwrap()
+                    .index()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sort_cols, vec![1, 0]);
+
+        // an aggregation without groups has nothing to sort on
+        let plan = test_optimizer
+            .optimize(agg_over_sort(vec![], vec![0]))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Agg);
+    }
+
+    #[test]
+    fn two_phase_agg() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(TwoPhaseAggRule::new(4)));
+
+        let plan = test_optimizer
+            .optimize(agg_over_sort(vec![0], vec![0]))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::PhysicalFinalAgg);
+        let partial = PhysicalPartialAgg::from_plan_node(plan.child_rel(0)).unwrap();
+        assert_eq!(partial.partitions().value().as_u64(), 4);
+        assert_eq!(partial.groups().len(), 1);
+        assert_eq!(partial.child().unwrap_plan_node().typ, DfNodeType::Sort);
+    }
+
+    /// `customer` (8 columns) joined with `orders` on `custkey`, aggregating the total price of
+    /// the orders by the given columns.
+    fn sum_by_customer(groups: Vec<usize>) -> ArcDfPlanNode {
+        let join = LogicalJoin::new(
+            LogicalScan::new("customer".into()).into_plan_node(),
+            LogicalScan::new("orders".into()).into_plan_node(),
+            BinOpPred::new(
+                ColumnRefPred::new(0).into_pred_node(),
+                ColumnRefPred::new(9).into_pred_node(),
+                BinOpType::Eq,
+            )
+            .into_pred_node(),
+            JoinType::Inner,
+        );
+        let sum = FuncPred::new(
+            FuncType::new_agg("sum".to_string()),
+            ListPred::new(vec![ColumnRefPred::new(11).into_pred_node()]),
+        );
+        LogicalAgg::new(
+            join.into_plan_node(),
+            ListPred::new(vec![sum.into_pred_node()]),
+            ListPred::new(
+                groups
+                    .into_iter()
+                    .map(|idx| ColumnRefPred::new(idx).into_pred_node())
+                    .collect(),
+            ),
+        )
+        .into_plan_node()
+    }
+
+    #[test]
+    fn push_agg_below_join_on_key() {
+        let mut test_optimizer =
+            new_test_optimizer_with_keys(Arc::new(AggJoinTransposeRule::new()));
+
+        let plan = test_optimizer
+            .optimize(sum_by_customer(vec![0, 1]))
+            .unwrap();
+        let proj = LogicalProjection::from_plan_node(plan).unwrap();
+        let exprs = proj
+            .exprs()
+            .to_vec()
+            .into_iter()
+            .map(|expr| ColumnRefPred::from_pred_node(expr).unwrap().index())
+            .collect::<Vec<_>>();
+        assert_eq!(exprs, vec![0, 1, 9]);
+        let join = LogicalJoin::from_plan_node(proj.child().unwrap_plan_node()).unwrap();
+        assert_eq!(join.left().unwrap_plan_node().typ, DfNodeType::Scan);
+        let agg = LogicalAgg::from_plan_node(join.right().unwrap_plan_node()).unwrap();
+        assert_eq!(
+            agg.groups().to_vec(),
+            vec![ColumnRefPred::new(1).into_pred_node()]
+        );
+        assert_eq!(
+            agg.exprs().child(0).child(0).child(0),
+            ColumnRefPred::new(3).into_pred_node()
+        );
+        let cond = BinOpPred::from_pred_node(join.cond()).unwrap();
+        assert_eq!(cond.right_child(), ColumnRefPred::new(8).into_pred_node());
+    }
+
+    #[test]
+    fn keep_agg_splitting_groups() {
+        let mut test_optimizer =
+            new_test_optimizer_with_keys(Arc::new(AggJoinTransposeRule::new()));
+
+        // grouping by the name only, orders of customers with the same name are in one group
+        let plan = test_optimizer.optimize(sum_by_customer(vec![1])).unwrap();
+        assert_eq!(plan.typ, DfNodeType::Agg);
+    }
+}
@@ -0,0 +1,405 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Rewrite rules specified in JSON and loaded at runtime, so that simple rewrites can be tried
+//! without recompiling the optimizer. For example, the following rule removes filters whose
+//! condition is `true`:
+//!
+//! ```json
+//! [{
+//!     "name": "eliminate_true_filter",
+//!     "pattern": { "node": "Filter", "children": ["input"], "preds": ["cond"] },
+//!     "guards": [{ "kind": "bool_constant", "pred": "cond", "value": true }],
+//!     "rewrite": "input"
+//! }]
+//! ```
+//!
+//! A pattern binds the children and the predicates of the matched nodes to names, and the
+//! rewrite template builds the new plan from the bound names. A name bound more than once must
+//! refer to equal children or predicates for the pattern to match. The children of a pattern
+//! node must either be all listed or omitted. Node types are written as
+//! they are serialized, e.g., `"Filter"` or `{ "Join": "Inner" }`.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use optd_og_core::nodes::{PlanNode, PlanNodeOrGroup};
+use optd_og_core::optimizer::Optimizer;
+use optd_og_core::rules::{Rule, RuleMatcher};
+use serde::Deserialize;
+
+use crate::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, ConstantPred, ConstantType, DfNodeType, DfPredType,
+    DfReprPredNode, ListPred,
+};
+
+/// Name which matches anything without binding it.
+const WILDCARD: &str = "_";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeclarativeRuleSpec {
+    pub name: String,
+    pub pattern: PatternSpec,
+    #[serde(default)]
+    pub guards: Vec<GuardSpec>,
+    pub rewrite: RewriteSpec,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PatternSpec {
+    /// Bind the child to a name.
+    Bind(String),
+    Node {
+        node: DfNodeType,
+        #[serde(default)]
+        preds: Vec<String>,
+        #[serde(default)]
+        children: Vec<PatternSpec>,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GuardSpec {
+    /// The predicate is a boolean constant with the given value.
+    BoolConstant { pred: String, value: bool },
+    /// The predicate is a list of the given length.
+    ListLen { pred: String, len: usize },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RewriteSpec {
+    /// A bound child.
+    Ref(String),
+    Node {
+        node: DfNodeType,
+        #[serde(default)]
+        preds: Vec<PredTemplate>,
+        #[serde(default)]
+        children: Vec<RewriteSpec>,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PredTemplate {
+    /// A bound predicate.
+    Ref(String),
+    Bool {
+        bool: bool,
+    },
+}
+
+#[derive(Default)]
+struct Bindings {
+    children: HashMap<String, PlanNodeOrGroup<DfNodeType>>,
+    preds: HashMap<String, ArcDfPredNode>,
+}
+
+pub struct DeclarativeRule {
+    spec: DeclarativeRuleSpec,
+    matcher: RuleMatcher<DfNodeType>,
+    name: &'static str,
+}
+
+impl DeclarativeRule {
+    pub fn new(spec: DeclarativeRuleSpec) -> Result<Self> {
+        let PatternSpec::Node { .. } = &spec.pattern else {
+            bail!("rule {}: the pattern must match a node", spec.name);
+        };
+        let mut children = HashSet::new();
+        let mut preds = HashSet::new();
+        collect_names(&spec.pattern, &mut children, &mut preds);
+        for guard in &spec.guards {
+            let (GuardSpec::BoolConstant { pred, .. } | GuardSpec::ListLen { pred, .. }) = guard;
+            if !preds.contains(pred.as_str()) {
+                bail!(
+                    "rule {}: guard refers to unbound predicate {}",
+                    spec.name,
+                    pred
+                );
+            }
+        }
+        check_rewrite(&spec.name, &spec.rewrite, &children, &preds)?;
+        let matcher = to_matcher(&spec.pattern);
+        // Rules are loaded once and live as long as the optimizer.
+        let name = Box::leak(spec.name.clone().into_boxed_str());
+        Ok(Self {
+            spec,
+            matcher,
+            name,
+        })
+    }
+
+    fn check_guards(&self, bindings: &Bindings) -> bool {
+        self.spec.guards.iter().all(|guard| match guard {
+            GuardSpec::BoolConstant { pred, value } => {
+                let pred = bindings.preds[pred].clone();
+                pred.typ == DfPredType::Constant(ConstantType::Bool)
+                    && ConstantPred::from_pred_node(pred)
+                        .unwrap()
+                        .value()
+                        .as_bool()
+                        == *value
+            }
+            GuardSpec::ListLen { pred, len } => {
+                ListPred::from_pred_node(bindings.preds[pred].clone())
+                    .is_some_and(|list| list.len() == *len)
+            }
+        })
+    }
+}
+
+/// Parse a JSON array of rule specifications.
+pub fn parse_declarative_rules(json: &str) -> Result<Vec<DeclarativeRule>> {
+    let specs: Vec<DeclarativeRuleSpec> = serde_json::from_str(json)?;
+    specs.into_iter().map(DeclarativeRule::new).collect()
+}
+
+fn collect_names<'a>(
+    pattern: &'a PatternSpec,
+    children: &mut HashSet<&'a str>,
+    preds: &mut HashSet<&'a str>,
+) {
+    match pattern {
+        PatternSpec::Bind(name) => {
+            children.insert(name);
+        }
+        PatternSpec::Node {
+            preds: pred_names,
+            children: child_patterns,
+            ..
+        } => {
+            preds.extend(pred_names.iter().map(String::as_str));
+            for child in child_patterns {
+                collect_names(child, children, preds);
+            }
+        }
+    }
+}
+
+fn check_rewrite(
+    rule_name: &str,
+    rewrite: &RewriteSpec,
+    children: &HashSet<&str>,
+    preds: &HashSet<&str>,
+) -> Result<()> {
+    match rewrite {
+        RewriteSpec::Ref(name) => {
+            if name == WILDCARD || !children.contains(name.as_str()) {
+                bail!(
+                    "rule {}: rewrite refers to unbound child {}",
+                    rule_name,
+                    name
+                );
+            }
+        }
+        RewriteSpec::Node {
+            preds: pred_templates,
+            children: child_templates,
+            ..
+        } => {
+            for pred in pred_templates {
+                if let PredTemplate::Ref(name) = pred {
+                    if name == WILDCARD || !preds.contains(name.as_str()) {
+                        bail!(
+                            "rule {}: rewrite refers to unbound predicate {}",
+                            rule_name,
+                            name
+                        );
+                    }
+                }
+            }
+            for child in child_templates {
+                check_rewrite(rule_name, child, children, preds)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn to_matcher(pattern: &PatternSpec) -> RuleMatcher<DfNodeType> {
+    match pattern {
+        PatternSpec::Bind(_) => RuleMatcher::Any,
+        PatternSpec::Node { node, children, .. } => RuleMatcher::MatchNode {
+            typ: node.clone(),
+            // Omitted children match any number of children.
+            children: if children.is_empty() {
+                vec![RuleMatcher::AnyMany]
+            } else {
+                children.iter().map(to_matcher).collect()
+            },
+        },
+    }
+}
+
+fn bind<T: Clone + PartialEq>(map: &mut HashMap<String, T>, name: &str, value: T) -> bool {
+    if name == WILDCARD {
+        return true;
+    }
+    match map.get(name) {
+        Some(bound) => *bound == value,
+        None => {
+            map.insert(name.to_string(), value);
+            true
+        }
+    }
+}
+
+fn match_pattern(
+    pattern: &PatternSpec,
+    node: PlanNodeOrGroup<DfNodeType>,
+    bindings: &mut Bindings,
+) -> bool {
+    match pattern {
+        PatternSpec::Bind(name) => bind(&mut bindings.children, name, node),
+        PatternSpec::Node {
+            node: typ,
+            preds,
+            children,
+        } => {
+            let node = node.unwrap_plan_node();
+            if node.typ != *typ
+                || node.predicates.len() < preds.len()
+                || (!children.is_empty() && node.children.len() != children.len())
+            {
+                return false;
+            }
+            preds
+                .iter()
+                .zip(node.predicates.iter())
+                .all(|(name, pred)| bind(&mut bindings.preds, name, pred.clone()))
+                && children
+                    .iter()
+                    .zip(node.children.iter())
+                    .all(|(child, node)| match_pattern(child, node.clone(), bindings))
+        }
+    }
+}
+
+fn instantiate(rewrite: &RewriteSpec, bindings: &Bindings) -> PlanNodeOrGroup<DfNodeType> {
+    match rewrite {
+        RewriteSpec::Ref(name) => bindings.children[name].clone(),
+        RewriteSpec::Node {
+            node,
+            preds,
+            children,
+        } => PlanNode {
+            typ: node.clone(),
+            children: children
+                .iter()
+                .map(|child| instantiate(child, bindings))
+                .collect(),
+            predicates: preds
+                .iter()
+                .map(|pred| match pred {
+                    PredTemplate::Ref(name) => bindings.preds[name].clone(),
+                    PredTemplate::Bool { bool } => ConstantPred::bool(*bool).into_pred_node(),
+                })
+                .collect(),
+        }
+        .into(),
+    }
+}
+
+impl<O: Optimizer<DfNodeType>> Rule<DfNodeType, O> for DeclarativeRule {
+    fn matcher(&self) -> &RuleMatcher<DfNodeType> {
+        &self.matcher
+    }
+
+    fn apply(&self, _optimizer: &O, binding: ArcDfPlanNode) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+        let mut bindings = Bindings::default();
+        if !match_pattern(&self.spec.pattern, binding.into(), &mut bindings)
+            || !self.check_guards(&bindings)
+        {
+            return vec![];
+        }
+        vec![instantiate(&self.spec.rewrite, &bindings)]
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::{ColumnRefPred, DfReprPlanNode, LogicalFilter, LogicalScan};
+    use crate::testing::new_test_optimizer;
+
+    const RULES: &str = r#"[
+        {
+            "name": "eliminate_true_filter",
+            "pattern": { "node": "Filter", "children": ["input"], "preds": ["cond"] },
+            "guards": [{ "kind": "bool_constant", "pred": "cond", "value": true }],
+            "rewrite": "input"
+        },
+        {
+            "name": "eliminate_duplicated_filter",
+            "pattern": {
+                "node": "Filter",
+                "children": [{ "node": "Filter", "children": ["input"], "preds": ["cond"] }],
+                "preds": ["cond"]
+            },
+            "rewrite": { "node": "Filter", "children": ["input"], "preds": ["cond"] }
+        }
+    ]"#;
+
+    fn filter(child: ArcDfPlanNode, cond: ArcDfPredNode) -> ArcDfPlanNode {
+        LogicalFilter::new(child, cond).into_plan_node()
+    }
+
+    #[test]
+    fn declarative_rules() {
+        let mut rules = parse_declarative_rules(RULES).unwrap();
+        let scan = LogicalScan::new("customer".into()).into_plan_node();
+
+        let mut test_optimizer = new_test_optimizer(Arc::new(rules.remove(0)));
+        let plan = test_optimizer
+            .optimize(filter(
+                scan.clone(),
+                ConstantPred::bool(true).into_pred_node(),
+            ))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Scan);
+        let plan = test_optimizer
+            .optimize(filter(
+                scan.clone(),
+                ConstantPred::bool(false).into_pred_node(),
+            ))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Filter);
+
+        let mut test_optimizer = new_test_optimizer(Arc::new(rules.remove(0)));
+        let cond = ColumnRefPred::new(0).into_pred_node();
+        let plan = test_optimizer
+            .optimize(filter(filter(scan.clone(), cond.clone()), cond))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Filter);
+        assert_eq!(plan.child_rel(0).typ, DfNodeType::Scan);
+        let plan = test_optimizer
+            .optimize(filter(
+                filter(scan, ColumnRefPred::new(0).into_pred_node()),
+                ColumnRefPred::new(1).into_pred_node(),
+            ))
+            .unwrap();
+        assert_eq!(plan.child_rel(0).typ, DfNodeType::Filter);
+    }
+
+    #[test]
+    fn unbound_names() {
+        let err = parse_declarative_rules(
+            r#"[{ "name": "bad", "pattern": { "node": "Filter", "children": ["input"] },
+                  "rewrite": "other" }]"#,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("unbound child other"));
+    }
+}
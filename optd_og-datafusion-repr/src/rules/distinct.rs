@@ -0,0 +1,149 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use optd_og_core::nodes::PlanNodeOrGroup;
+use optd_og_core::optimizer::Optimizer;
+use optd_og_core::rules::{Rule, RuleMatcher};
+
+use super::macros::define_rule;
+use crate::plan_nodes::{
+    ColumnRefPred, DfNodeType, DfReprPlanNode, DfReprPredNode, ListPred, LogicalAgg,
+};
+use crate::OptimizerExt;
+
+define_rule!(
+    EliminateDistinctRule,
+    apply_eliminate_distinct,
+    EliminateDistinctPicks,
+    (Distinct, child)
+);
+
+/// Eliminates the distinct if the child is already known to contain no duplicated rows.
+/// For example:
+///     select distinct custkey, name
+///     from customer
+/// becomes
+///     select custkey, name
+///     from customer
+/// if custkey is the primary key of customer.
+fn apply_eliminate_distinct(
+    optimizer: &impl Optimizer<DfNodeType>,
+    EliminateDistinctPicks { child }: EliminateDistinctPicks,
+) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+    if optimizer.get_unique_keys_of(child.clone()).is_unique() {
+        return vec![child];
+    }
+    vec![]
+}
+
+define_rule!(
+    DistinctToAggRule,
+    apply_distinct_to_agg,
+    DistinctToAggPicks,
+    (Distinct, child)
+);
+
+/// Converts a distinct into an aggregation without aggregate expressions which groups by all
+/// columns of the child, so that it can be implemented and costed like any other aggregation.
+/// For example:
+///     select distinct name, nationkey
+///     from customer
+/// becomes
+///     select name, nationkey
+///     from customer
+///     group by name, nationkey
+fn apply_distinct_to_agg(
+    optimizer: &impl Optimizer<DfNodeType>,
+    DistinctToAggPicks { child }: DistinctToAggPicks,
+) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+    let schema = optimizer.get_schema_of(child.clone());
+    let groups = (0..schema.len())
+        .map(|idx| ColumnRefPred::new(idx).into_pred_node())
+        .collect();
+    let node = LogicalAgg::new_unchecked(child, ListPred::new(vec![]), ListPred::new(groups));
+    vec![node.into_plan_node().into()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::plan_nodes::{ArcDfPlanNode, LogicalDistinct, LogicalProjection, LogicalScan};
+    use crate::properties::schema::Catalog;
+    use crate::testing::{new_test_optimizer, new_test_optimizer_with_keys, TpchCatalog};
+    use crate::TableId;
+
+    /// `select distinct <columns> from customer`, whose primary key is custkey (#0).
+    fn distinct_customer(columns: Vec<usize>) -> ArcDfPlanNode {
+        let proj = LogicalProjection::new(
+            LogicalScan::new("customer".into()).into_plan_node(),
+            ListPred::new(
+                columns
+                    .into_iter()
+                    .map(|idx| ColumnRefPred::new(idx).into_pred_node())
+                    .collect(),
+            ),
+        );
+        LogicalDistinct::new(proj.into_plan_node()).into_plan_node()
+    }
+
+    #[test]
+    fn eliminate_distinct_over_key() {
+        let mut test_optimizer =
+            new_test_optimizer_with_keys(Arc::new(EliminateDistinctRule::new()));
+
+        let plan = test_optimizer
+            .optimize(distinct_customer(vec![1, 0]))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Projection);
+
+        let plan = test_optimizer
+            .optimize(LogicalDistinct::new(distinct_customer(vec![1])).into_plan_node())
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Distinct);
+        assert_eq!(plan.child_rel(0).typ, DfNodeType::Projection);
+    }
+
+    #[test]
+    fn keep_distinct_over_non_key() {
+        let mut test_optimizer =
+            new_test_optimizer_with_keys(Arc::new(EliminateDistinctRule::new()));
+
+        let plan = test_optimizer
+            .optimize(distinct_customer(vec![1, 2]))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Distinct);
+    }
+
+    #[test]
+    fn distinct_to_agg_groups_by_all_columns() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(DistinctToAggRule::new()));
+
+        let plan = test_optimizer
+            .optimize(distinct_customer(vec![1, 2]))
+            .unwrap();
+        let agg = LogicalAgg::from_plan_node(plan).unwrap();
+        assert!(agg.exprs().is_empty());
+        assert_eq!(
+            agg.groups().to_vec(),
+            vec![
+                ColumnRefPred::new(0).into_pred_node(),
+                ColumnRefPred::new(1).into_pred_node()
+            ]
+        );
+        assert_eq!(agg.child().unwrap_plan_node().typ, DfNodeType::Projection);
+
+        let column_cnt = TpchCatalog.get(&TableId::new("customer")).len();
+        let plan = test_optimizer
+            .optimize(
+                LogicalDistinct::new(LogicalScan::new("customer".into()).into_plan_node())
+                    .into_plan_node(),
+            )
+            .unwrap();
+        let agg = LogicalAgg::from_plan_node(plan).unwrap();
+        assert_eq!(agg.groups().len(), column_cnt);
+    }
+}
@@ -9,24 +9,27 @@ use optd_og_core::rules::{Rule, RuleMatcher};
 
 use super::macros::define_rule;
 use crate::plan_nodes::{
-    ArcDfPlanNode, ConstantPred, ConstantType, DfNodeType, DfPredType, DfReprPlanNode,
-    DfReprPredNode, LogicalEmptyRelation, LogicalLimit,
+    ConstantPred, ConstantType, DfNodeType, DfPredType, DfReprPlanNode, DfReprPredNode,
+    LogicalEmptyRelation, LogicalLimit,
 };
 use crate::OptimizerExt;
 
-define_rule!(EliminateLimitRule, apply_eliminate_limit, (Limit, child));
+define_rule!(
+    EliminateLimitRule,
+    apply_eliminate_limit,
+    EliminateLimitPicks,
+    (Limit => limit: LogicalLimit, child)
+);
 
 /// Transformations:
 ///     - Limit with skip 0 and no fetch -> Eliminate from the tree
 ///     - Limit with limit 0 -> EmptyRelation
 fn apply_eliminate_limit(
     optimizer: &impl Optimizer<DfNodeType>,
-    binding: ArcDfPlanNode,
+    EliminateLimitPicks { limit, child }: EliminateLimitPicks,
 ) -> Vec<PlanNodeOrGroup<DfNodeType>> {
-    let limit = LogicalLimit::from_plan_node(binding).unwrap();
     let skip = limit.skip();
     let fetch = limit.fetch();
-    let child = limit.child();
     if let DfPredType::Constant(ConstantType::Int64) = skip.typ {
         if let DfPredType::Constant(ConstantType::Int64) = fetch.typ {
             let skip_val = ConstantPred::from_pred_node(skip).unwrap().value().as_i64();
@@ -0,0 +1,205 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use optd_og_core::nodes::PlanNodeOrGroup;
+use optd_og_core::optimizer::Optimizer;
+use optd_og_core::rules::{Rule, RuleMatcher};
+
+use crate::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, BinOpPred, BinOpType, ColumnRefPred, DfNodeType, DfPredType,
+    DfReprPlanNode, DfReprPredNode, JoinType, ListPred, LogOpType, LogicalJoin, LogicalProjection,
+    LogicalScan,
+};
+use crate::properties::schema::Catalog;
+
+/// Eliminates an inner join of a table with itself on its full primary key.
+/// Since every row can only match itself, the join is replaced by a single scan whose
+/// columns are projected twice, so that the output schema stays the same. Projections
+/// on top which only use one side will be collapsed by the projection rules afterwards.
+/// For example:
+///     select b.name
+///     from customer a, customer b
+///     where a.custkey = b.custkey
+/// becomes
+///     select name
+///     from customer
+///
+/// This pattern is common in SQL generated by ORMs. The primary key is fetched from
+/// `Catalog::primary_key`; tables without a known primary key are never rewritten.
+pub struct EliminateSelfJoinRule {
+    matcher: RuleMatcher<DfNodeType>,
+    catalog: Arc<dyn Catalog>,
+}
+
+impl EliminateSelfJoinRule {
+    pub fn new(catalog: Arc<dyn Catalog>) -> Self {
+        Self {
+            matcher: RuleMatcher::MatchNode {
+                typ: DfNodeType::Join(JoinType::Inner),
+                children: vec![
+                    RuleMatcher::MatchNode {
+                        typ: DfNodeType::Scan,
+                        children: vec![],
+                    },
+                    RuleMatcher::MatchNode {
+                        typ: DfNodeType::Scan,
+                        children: vec![],
+                    },
+                ],
+            },
+            catalog,
+        }
+    }
+}
+
+/// Collects the column pairs of a conjunction of `left_col = right_col` predicates where both
+/// sides refer to the same column index of the (identical) join children. Returns `None` if
+/// any part of the condition is not of this form.
+fn same_column_eq_pairs(cond: &ArcDfPredNode, num_cols: usize) -> Option<HashSet<usize>> {
+    let conds = match cond.typ {
+        DfPredType::BinOp(BinOpType::Eq) => vec![cond.clone()],
+        DfPredType::LogOp(LogOpType::And) => cond.children.clone(),
+        _ => return None,
+    };
+    let mut cols = HashSet::new();
+    for cond in conds {
+        let bin_op = BinOpPred::from_pred_node(cond)?;
+        if bin_op.op_type() != BinOpType::Eq {
+            return None;
+        }
+        let left = ColumnRefPred::from_pred_node(bin_op.left_child())?.index();
+        let right = ColumnRefPred::from_pred_node(bin_op.right_child())?.index();
+        let (left, right) = if left < right {
+            (left, right)
+        } else {
+            (right, left)
+        };
+        if left >= num_cols || right != left + num_cols {
+            return None;
+        }
+        cols.insert(left);
+    }
+    Some(cols)
+}
+
+impl<O: Optimizer<DfNodeType>> Rule<DfNodeType, O> for EliminateSelfJoinRule {
+    fn matcher(&self) -> &RuleMatcher<DfNodeType> {
+        &self.matcher
+    }
+
+    fn apply(&self, _optimizer: &O, binding: ArcDfPlanNode) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+        let join = LogicalJoin::from_plan_node(binding).unwrap();
+        let left = LogicalScan::from_plan_node(join.left().unwrap_plan_node()).unwrap();
+        let right = LogicalScan::from_plan_node(join.right().unwrap_plan_node()).unwrap();
+        let table = left.table_id();
+        if table != right.table_id() {
+            return vec![];
+        }
+        let Some(primary_key) = self.catalog.primary_key(&table) else {
+            return vec![];
+        };
+        if primary_key.is_empty() {
+            return vec![];
+        }
+        let num_cols = self.catalog.get(&table).len();
+        let Some(cols) = same_column_eq_pairs(&join.cond(), num_cols) else {
+            return vec![];
+        };
+        if !primary_key.iter().all(|col| cols.contains(col)) {
+            return vec![];
+        }
+
+        let exprs = (0..num_cols)
+            .chain(0..num_cols)
+            .map(|idx| ColumnRefPred::new(idx).into_pred_node())
+            .collect();
+        let node = LogicalProjection::new_unchecked(left.into_plan_node(), ListPred::new(exprs));
+        vec![node.into_plan_node().into()]
+    }
+
+    fn name(&self) -> &'static str {
+        "eliminate_self_join_rule"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::LogOpPred;
+    use crate::testing::{new_test_optimizer, TpchCatalog};
+
+    fn self_join(table: &str, cond: ArcDfPredNode) -> ArcDfPlanNode {
+        LogicalJoin::new(
+            LogicalScan::new(table.into()).into_plan_node(),
+            LogicalScan::new(table.into()).into_plan_node(),
+            cond,
+            JoinType::Inner,
+        )
+        .into_plan_node()
+    }
+
+    fn col_eq(left: usize, right: usize) -> ArcDfPredNode {
+        BinOpPred::new(
+            ColumnRefPred::new(left).into_pred_node(),
+            ColumnRefPred::new(right).into_pred_node(),
+            BinOpType::Eq,
+        )
+        .into_pred_node()
+    }
+
+    #[test]
+    fn eliminate_self_join_on_primary_key() {
+        let mut test_optimizer =
+            new_test_optimizer(Arc::new(EliminateSelfJoinRule::new(Arc::new(TpchCatalog))));
+
+        // region has 3 columns and primary key (regionkey)
+        let plan = test_optimizer
+            .optimize(self_join("region", col_eq(3, 0)))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Projection);
+        assert_eq!(plan.child_rel(0).typ, DfNodeType::Scan);
+        let proj = LogicalProjection::from_plan_node(plan).unwrap();
+        let exprs = proj.exprs();
+        assert_eq!(exprs.len(), 6);
+        assert_eq!(
+            ColumnRefPred::from_pred_node(exprs.child(4))
+                .unwrap()
+                .index(),
+            1
+        );
+    }
+
+    #[test]
+    fn keep_self_join_on_non_key() {
+        let mut test_optimizer =
+            new_test_optimizer(Arc::new(EliminateSelfJoinRule::new(Arc::new(TpchCatalog))));
+
+        // join on region.name only
+        let plan = test_optimizer
+            .optimize(self_join("region", col_eq(1, 4)))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Join(JoinType::Inner));
+
+        // extra non-equality predicate
+        let cond = LogOpPred::new(
+            LogOpType::And,
+            vec![
+                col_eq(0, 3),
+                BinOpPred::new(
+                    ColumnRefPred::new(1).into_pred_node(),
+                    ColumnRefPred::new(5).into_pred_node(),
+                    BinOpType::Lt,
+                )
+                .into_pred_node(),
+            ],
+        )
+        .into_pred_node();
+        let plan = test_optimizer.optimize(self_join("region", cond)).unwrap();
+        assert_eq!(plan.typ, DfNodeType::Join(JoinType::Inner));
+    }
+}
@@ -15,7 +15,7 @@ use crate::plan_nodes::{
     DfReprPredNode, JoinType, LogOpPred, LogOpType, LogicalEmptyRelation, LogicalFilter,
     LogicalJoin,
 };
-use crate::{ArcDfPlanNode, OptimizerExt};
+use crate::OptimizerExt;
 
 // simplify_log_expr simplifies the Filters operator in several possible
 //  ways:
@@ -84,7 +84,12 @@ pub(crate) fn simplify_log_expr(log_expr: ArcDfPredNode, changed: &mut bool) ->
     LogOpPred::new(op, new_children).into_pred_node()
 }
 
-define_rule!(SimplifyFilterRule, apply_simplify_filter, (Filter, child));
+define_rule!(
+    SimplifyFilterRule,
+    apply_simplify_filter,
+    SimplifyFilterPicks,
+    (Filter => filter: LogicalFilter, child)
+);
 
 // SimplifySelectFilters simplifies the Filters operator in several possible
 //  ways:
@@ -93,16 +98,15 @@ define_rule!(SimplifyFilterRule, apply_simplify_filter, (Filter, child));
 //    - Removes Duplicates
 fn apply_simplify_filter(
     _optimizer: &impl Optimizer<DfNodeType>,
-    binding: ArcDfPlanNode,
+    SimplifyFilterPicks { filter, child }: SimplifyFilterPicks,
 ) -> Vec<PlanNodeOrGroup<DfNodeType>> {
-    let filter = LogicalFilter::from_plan_node(binding).unwrap();
     let cond = filter.cond();
     match cond.typ {
         DfPredType::LogOp(_) => {
             let mut changed = false;
             let new_log_expr = simplify_log_expr(cond, &mut changed);
             if changed {
-                let filter_node = LogicalFilter::new_unchecked(filter.child(), new_log_expr);
+                let filter_node = LogicalFilter::new_unchecked(child, new_log_expr);
                 return vec![filter_node.into_plan_node().into()];
             }
             vec![]
@@ -117,17 +121,15 @@ fn apply_simplify_filter(
 define_rule!(
     SimplifyJoinCondRule,
     apply_simplify_join_cond,
-    (Join(JoinType::Inner), left, right)
+    SimplifyJoinCondPicks,
+    (Join(JoinType::Inner) => join: LogicalJoin, left, right)
 );
 
 fn apply_simplify_join_cond(
     _optimizer: &impl Optimizer<DfNodeType>,
-    binding: ArcDfPlanNode,
+    SimplifyJoinCondPicks { join, left, right }: SimplifyJoinCondPicks,
 ) -> Vec<PlanNodeOrGroup<DfNodeType>> {
-    let join = LogicalJoin::from_plan_node(binding).unwrap();
     let cond = join.cond();
-    let left = join.left();
-    let right = join.right();
 
     match cond.typ {
         DfPredType::LogOp(_) => {
@@ -146,27 +148,31 @@ fn apply_simplify_join_cond(
     }
 }
 
-define_rule!(EliminateFilterRule, apply_eliminate_filter, (Filter, child));
+define_rule!(
+    EliminateFilterRule,
+    apply_eliminate_filter,
+    EliminateFilterPicks,
+    (Filter => filter: LogicalFilter, child)
+);
 
 /// Transformations:
 ///     - Filter node w/ false pred -> EmptyRelation
 ///     - Filter node w/ true pred  -> Eliminate from the tree
 fn apply_eliminate_filter(
     optimizer: &impl Optimizer<DfNodeType>,
-    binding: ArcDfPlanNode,
+    EliminateFilterPicks { filter, child }: EliminateFilterPicks,
 ) -> Vec<PlanNodeOrGroup<DfNodeType>> {
-    let filter = LogicalFilter::from_plan_node(binding).unwrap();
     let cond = filter.cond();
     if let DfPredType::Constant(ConstantType::Bool) = cond.typ {
         if let Some(ref data) = cond.data {
             if data.as_bool() {
                 // If the condition is true, eliminate the filter node, as it
                 // will yield everything from below it.
-                return vec![filter.child()];
+                return vec![child];
             } else {
                 // If the condition is false, replace this node with the empty relation,
                 // since it will never yield tuples.
-                let schema = optimizer.get_schema_of(filter.child());
+                let schema = optimizer.get_schema_of(child);
                 let node = LogicalEmptyRelation::new(false, schema);
                 return vec![node.into_plan_node().into()];
             }
@@ -0,0 +1,192 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::Arc;
+
+use optd_og_core::nodes::PlanNodeOrGroup;
+use optd_og_core::optimizer::Optimizer;
+use optd_og_core::rules::{Rule, RuleMatcher};
+
+use crate::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, BinOpPred, BinOpType, ColumnRefPred, DfNodeType, DfPredType,
+    DfReprPlanNode, DfReprPredNode, ListPred, LogOpType, LogicalFilter, LogicalScan,
+};
+use crate::properties::schema::Catalog;
+
+/// Looks the rows of a scanned table up in an index with the conjuncts of the filter above
+/// which compare the leading column of the index with a constant. For example, with an index on
+/// `orderkey`:
+///     select * from lineitem where orderkey = 42 and quantity > 10
+/// the scan only reads the rows where `orderkey = 42`. The filter is kept, and each index of the
+/// table yields an alternative the cost model decides between.
+///
+/// The indexes are fetched from `Catalog::indexes`; tables without indexes are never rewritten.
+pub struct IndexLookupRule {
+    matcher: RuleMatcher<DfNodeType>,
+    catalog: Arc<dyn Catalog>,
+}
+
+impl IndexLookupRule {
+    pub fn new(catalog: Arc<dyn Catalog>) -> Self {
+        Self {
+            matcher: RuleMatcher::MatchNode {
+                typ: DfNodeType::Filter,
+                children: vec![RuleMatcher::MatchNode {
+                    typ: DfNodeType::Scan,
+                    children: vec![],
+                }],
+            },
+            catalog,
+        }
+    }
+}
+
+/// Whether the predicate compares the column with a constant.
+fn is_lookup_pred(pred: &ArcDfPredNode, column: usize) -> bool {
+    let Some(bin_op) = BinOpPred::from_pred_node(pred.clone()) else {
+        return false;
+    };
+    if !matches!(
+        bin_op.op_type(),
+        BinOpType::Eq | BinOpType::Lt | BinOpType::Leq | BinOpType::Gt | BinOpType::Geq
+    ) {
+        return false;
+    }
+    let is_column = |pred: &ArcDfPredNode| {
+        ColumnRefPred::from_pred_node(pred.clone()).is_some_and(|col| col.index() == column)
+    };
+    let is_constant = |pred: &ArcDfPredNode| matches!(pred.typ, DfPredType::Constant(_));
+    let (left, right) = (bin_op.left_child(), bin_op.right_child());
+    (is_column(&left) && is_constant(&right)) || (is_constant(&left) && is_column(&right))
+}
+
+impl<O: Optimizer<DfNodeType>> Rule<DfNodeType, O> for IndexLookupRule {
+    fn matcher(&self) -> &RuleMatcher<DfNodeType> {
+        &self.matcher
+    }
+
+    fn apply(&self, _optimizer: &O, binding: ArcDfPlanNode) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+        let filter = LogicalFilter::from_plan_node(binding).unwrap();
+        let scan = LogicalScan::from_plan_node(filter.child().unwrap_plan_node()).unwrap();
+        if scan.index_lookup().is_some() {
+            return vec![];
+        }
+        let cond = filter.cond();
+        let conjuncts = match cond.typ {
+            DfPredType::LogOp(LogOpType::And) => cond.children.clone(),
+            _ => vec![cond.clone()],
+        };
+        let mut alternatives = vec![];
+        for index in self.catalog.indexes(&scan.table_id()) {
+            let Some(&leading_column) = index.columns.first() else {
+                continue;
+            };
+            let lookup_filters = conjuncts
+                .iter()
+                .filter(|pred| is_lookup_pred(pred, leading_column))
+                .cloned()
+                .collect::<Vec<_>>();
+            if lookup_filters.is_empty() {
+                continue;
+            }
+            let scan = scan.with_index_lookup(index.name, ListPred::new(lookup_filters));
+            let node = LogicalFilter::new_unchecked(scan.into_plan_node(), cond.clone());
+            alternatives.push(node.into_plan_node().into());
+        }
+        alternatives
+    }
+
+    fn name(&self) -> &'static str {
+        "index_lookup_rule"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::{ConstantPred, LogOpPred};
+    use crate::properties::schema::{IndexDef, Schema};
+    use crate::testing::{new_test_optimizer, TpchCatalog};
+    use crate::TableId;
+
+    /// The TPC-H catalog with an index on the `nationkey` of `customer`.
+    struct IndexedCatalog;
+
+    impl Catalog for IndexedCatalog {
+        fn get(&self, name: &TableId) -> Schema {
+            TpchCatalog.get(name)
+        }
+
+        fn indexes(&self, name: &TableId) -> Vec<IndexDef> {
+            match name.as_str() {
+                "customer" => vec![IndexDef {
+                    name: "customer_nationkey".to_string(),
+                    columns: vec![3, 0],
+                }],
+                _ => vec![],
+            }
+        }
+    }
+
+    fn col_op_int(col: usize, value: i32, op: BinOpType) -> ArcDfPredNode {
+        BinOpPred::new(
+            ColumnRefPred::new(col).into_pred_node(),
+            ConstantPred::int32(value).into_pred_node(),
+            op,
+        )
+        .into_pred_node()
+    }
+
+    fn filtered_scan(table: &str, cond: ArcDfPredNode) -> ArcDfPlanNode {
+        LogicalFilter::new(LogicalScan::new(table.into()).into_plan_node(), cond).into_plan_node()
+    }
+
+    #[test]
+    fn look_up_leading_index_column() {
+        let mut test_optimizer =
+            new_test_optimizer(Arc::new(IndexLookupRule::new(Arc::new(IndexedCatalog))));
+
+        let cond = LogOpPred::new(
+            LogOpType::And,
+            vec![
+                col_op_int(3, 1, BinOpType::Geq),
+                col_op_int(0, 2, BinOpType::Eq),
+            ],
+        )
+        .into_pred_node();
+        let plan = test_optimizer
+            .optimize(filtered_scan("customer", cond.clone()))
+            .unwrap();
+        let filter = LogicalFilter::from_plan_node(plan).unwrap();
+        assert_eq!(filter.cond(), cond);
+        let scan = LogicalScan::from_plan_node(filter.child().unwrap_plan_node()).unwrap();
+        let (index, lookup_filters) = scan.index_lookup().unwrap();
+        assert_eq!(index.as_ref(), "customer_nationkey");
+        assert_eq!(
+            lookup_filters.to_vec(),
+            vec![col_op_int(3, 1, BinOpType::Geq)]
+        );
+        assert!(scan.partition_filters().is_none());
+    }
+
+    #[test]
+    fn keep_scan_without_lookup_conjuncts() {
+        let mut test_optimizer =
+            new_test_optimizer(Arc::new(IndexLookupRule::new(Arc::new(IndexedCatalog))));
+
+        // only the leading column of the index can be looked up
+        let plan = test_optimizer
+            .optimize(filtered_scan("customer", col_op_int(0, 2, BinOpType::Eq)))
+            .unwrap();
+        let scan = LogicalScan::from_plan_node(plan.child_rel(0)).unwrap();
+        assert!(scan.index_lookup().is_none());
+
+        let plan = test_optimizer
+            .optimize(filtered_scan("customer", col_op_int(3, 2, BinOpType::Neq)))
+            .unwrap();
+        let scan = LogicalScan::from_plan_node(plan.child_rel(0)).unwrap();
+        assert!(scan.index_lookup().is_none());
+    }
+}
@@ -11,7 +11,7 @@ use optd_og_core::rules::{Rule, RuleMatcher};
 
 use super::macros::{define_impl_rule, define_rule};
 use crate::plan_nodes::{
-    ArcDfPlanNode, BinOpPred, BinOpType, ColumnRefPred, ConstantPred, ConstantType, DfNodeType,
+    BinOpPred, BinOpType, BuildSide, ColumnRefPred, ConstantPred, ConstantType, DfNodeType,
     DfPredType, DfReprPlanNode, DfReprPredNode, JoinType, ListPred, LogOpType,
     LogicalEmptyRelation, LogicalJoin, LogicalProjection, PhysicalHashJoin, PredExt,
 };
@@ -22,16 +22,14 @@ use crate::OptimizerExt;
 define_rule!(
     JoinCommuteRule,
     apply_join_commute,
-    (DfNodeType::Join(JoinType::Inner), left, right)
+    JoinCommutePicks,
+    (DfNodeType::Join(JoinType::Inner) => join: LogicalJoin, left, right)
 );
 
 fn apply_join_commute(
     optimizer: &impl Optimizer<DfNodeType>,
-    binding: ArcDfPlanNode,
+    JoinCommutePicks { join, left, right }: JoinCommutePicks,
 ) -> Vec<PlanNodeOrGroup<DfNodeType>> {
-    let join = LogicalJoin::from_plan_node(binding).unwrap();
-    let left = join.left();
-    let right = join.right();
     let left_schema = optimizer.get_schema_of(left.clone());
     let right_schema = optimizer.get_schema_of(right.clone());
     let cond = join
@@ -59,18 +57,16 @@ fn apply_join_commute(
 define_rule!(
     EliminateJoinRule,
     apply_eliminate_join,
-    (Join(JoinType::Inner), left, right)
+    EliminateJoinPicks,
+    (Join(JoinType::Inner) => join: LogicalJoin, left, right)
 );
 
 /// Eliminate logical join with constant predicates
 /// True predicates becomes CrossJoin (not yet implemented)
 fn apply_eliminate_join(
     optimizer: &impl Optimizer<DfNodeType>,
-    binding: ArcDfPlanNode,
+    EliminateJoinPicks { join, left, right }: EliminateJoinPicks,
 ) -> Vec<PlanNodeOrGroup<DfNodeType>> {
-    let join = LogicalJoin::from_plan_node(binding).unwrap();
-    let left = join.left();
-    let right = join.right();
     let cond = join.cond();
 
     if let DfPredType::Constant(const_type) = cond.typ {
@@ -106,18 +102,24 @@ fn apply_eliminate_join(
 define_rule!(
     JoinAssocRule,
     apply_join_assoc,
-    (Join(JoinType::Inner), (Join(JoinType::Inner), a, b), c)
+    JoinAssocPicks,
+    (
+        Join(JoinType::Inner) => join1: LogicalJoin,
+        (Join(JoinType::Inner) => join2: LogicalJoin, a, b),
+        c
+    )
 );
 
 fn apply_join_assoc(
     optimizer: &impl Optimizer<DfNodeType>,
-    binding: ArcDfPlanNode,
+    JoinAssocPicks {
+        join1,
+        join2,
+        a,
+        b,
+        c,
+    }: JoinAssocPicks,
 ) -> Vec<PlanNodeOrGroup<DfNodeType>> {
-    let join1: LogicalJoin = LogicalJoin::from_plan_node(binding).unwrap();
-    let c = join1.right();
-    let join2 = LogicalJoin::from_plan_node(join1.left().unwrap_plan_node()).unwrap();
-    let a = join2.left();
-    let b = join2.right();
     let cond1 = join2.cond();
     let a_schema = optimizer.get_schema_of(a.clone());
     let cond2 = join1.cond();
@@ -140,20 +142,83 @@ fn apply_join_assoc(
     vec![node.into_plan_node().into()]
 }
 
+/// Whether the pair of columns can be used as keys of a hash join, which requires both sides
+/// to have the same type so that equal values have equal hashes.
+fn is_hash_join_key(
+    left_schema: &Schema,
+    right_schema: &Schema,
+    left_expr: &ColumnRefPred,
+    right_expr: &ColumnRefPred,
+) -> bool {
+    let left_typ = left_schema.fields[left_expr.index()].typ;
+    let right_typ = right_schema.fields[right_expr.index()].typ;
+    left_typ == right_typ
+}
+
 define_impl_rule!(
     HashJoinRule,
     apply_hash_join,
-    (Join(JoinType::Inner), left, right)
+    HashJoinPicks,
+    (Join(JoinType::Inner) => join: LogicalJoin, left, right)
 );
 
 fn apply_hash_join(
     optimizer: &impl Optimizer<DfNodeType>,
-    binding: ArcDfPlanNode,
+    HashJoinPicks { join, left, right }: HashJoinPicks,
 ) -> Vec<PlanNodeOrGroup<DfNodeType>> {
-    let join = LogicalJoin::from_plan_node(binding).unwrap();
+    hash_join(optimizer, join, left, right)
+}
+
+// The hash join of the hash join rule, building its hash table on the right input instead, so
+// that the cost model picks the build side.
+define_impl_rule!(
+    HashJoinBuildRightRule,
+    apply_hash_join_build_right,
+    HashJoinBuildRightPicks,
+    (Join(JoinType::Inner) => join: LogicalJoin, left, right)
+);
+
+fn apply_hash_join_build_right(
+    optimizer: &impl Optimizer<DfNodeType>,
+    HashJoinBuildRightPicks { join, left, right }: HashJoinBuildRightPicks,
+) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+    hash_join(optimizer, join, left, right)
+        .into_iter()
+        .map(|node| {
+            PhysicalHashJoin::from_plan_node(node.unwrap_plan_node())
+                .unwrap()
+                .with_build_side(BuildSide::Right)
+                .into_plan_node()
+                .into()
+        })
+        .collect()
+}
+
+// The mark column of a mark join is whether the hash table has a matching row.
+define_impl_rule!(
+    HashMarkJoinRule,
+    apply_hash_mark_join,
+    HashMarkJoinPicks,
+    (Join(JoinType::LeftMark) => join: LogicalJoin, left, right)
+);
+
+fn apply_hash_mark_join(
+    optimizer: &impl Optimizer<DfNodeType>,
+    HashMarkJoinPicks { join, left, right }: HashMarkJoinPicks,
+) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+    hash_join(optimizer, join, left, right)
+}
+
+/// Converts a join whose condition is a conjunction of equalities between the columns of both
+/// sides into a hash join of the same type.
+fn hash_join(
+    optimizer: &impl Optimizer<DfNodeType>,
+    join: LogicalJoin,
+    left: PlanNodeOrGroup<DfNodeType>,
+    right: PlanNodeOrGroup<DfNodeType>,
+) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+    let join_type = *join.join_type();
     let cond = join.cond();
-    let left = join.left();
-    let right = join.right();
     match cond.typ {
         DfPredType::BinOp(BinOpType::Eq) => {
             let left_schema = optimizer.get_schema_of(left.clone());
@@ -181,12 +246,16 @@ fn apply_hash_join(
 
             if can_convert {
                 let right_expr = ColumnRefPred::new(right_expr.index() - left_schema.len());
+                let right_schema = optimizer.get_schema_of(right.clone());
+                if !is_hash_join_key(&left_schema, &right_schema, &left_expr, &right_expr) {
+                    return vec![];
+                }
                 let node = PhysicalHashJoin::new_unchecked(
                     left,
                     right,
                     ListPred::new(vec![left_expr.into_pred_node()]),
                     ListPred::new(vec![right_expr.into_pred_node()]),
-                    JoinType::Inner,
+                    join_type,
                 );
                 return vec![node.into_plan_node().into()];
             }
@@ -207,6 +276,7 @@ fn apply_hash_join(
             }
 
             let left_schema = optimizer.get_schema_of(left.clone());
+            let right_schema = optimizer.get_schema_of(right.clone());
             let mut left_exprs = vec![];
             let mut right_exprs = vec![];
             for child in &cond.children {
@@ -235,6 +305,9 @@ fn apply_hash_join(
                     return vec![];
                 }
                 let right_expr = ColumnRefPred::new(right_expr.index() - left_schema.len());
+                if !is_hash_join_key(&left_schema, &right_schema, &left_expr, &right_expr) {
+                    return vec![];
+                }
                 right_exprs.push(right_expr.into_pred_node());
                 left_exprs.push(left_expr.into_pred_node());
             }
@@ -244,7 +317,7 @@ fn apply_hash_join(
                 right,
                 ListPred::new(left_exprs),
                 ListPred::new(right_exprs),
-                JoinType::Inner,
+                join_type,
             );
             return vec![node.into_plan_node().into()];
         }
@@ -252,3 +325,120 @@ fn apply_hash_join(
     }
     vec![]
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::plan_nodes::{ArcDfPlanNode, LogicalScan};
+    use crate::testing::new_test_optimizer;
+
+    fn join_on(left: &str, right: &str, left_col: usize, right_col: usize) -> ArcDfPlanNode {
+        join_on_with_type(left, right, left_col, right_col, JoinType::Inner)
+    }
+
+    fn join_on_with_type(
+        left: &str,
+        right: &str,
+        left_col: usize,
+        right_col: usize,
+        join_type: JoinType,
+    ) -> ArcDfPlanNode {
+        LogicalJoin::new(
+            LogicalScan::new(left.into()).into_plan_node(),
+            LogicalScan::new(right.into()).into_plan_node(),
+            BinOpPred::new(
+                ColumnRefPred::new(left_col).into_pred_node(),
+                ColumnRefPred::new(right_col).into_pred_node(),
+                BinOpType::Eq,
+            )
+            .into_pred_node(),
+            join_type,
+        )
+        .into_plan_node()
+    }
+
+    #[test]
+    fn hash_join_on_matching_types() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(HashJoinRule::new()));
+
+        // region.regionkey = customer.custkey, both int32
+        let plan = test_optimizer
+            .optimize(join_on("region", "customer", 0, 3))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::PhysicalHashJoin(JoinType::Inner));
+        let join = PhysicalHashJoin::from_plan_node(plan).unwrap();
+        assert_eq!(join.build_side(), BuildSide::Left);
+    }
+
+    #[test]
+    fn hash_join_build_right() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(HashJoinBuildRightRule::new()));
+
+        let plan = test_optimizer
+            .optimize(join_on("region", "customer", 0, 3))
+            .unwrap();
+        let join = PhysicalHashJoin::from_plan_node(plan).unwrap();
+        assert_eq!(join.build_side(), BuildSide::Right);
+        assert_eq!(
+            join.left_keys().to_vec(),
+            vec![ColumnRefPred::new(0).into_pred_node()]
+        );
+    }
+
+    #[test]
+    fn no_hash_join_on_infeasible_keys() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(HashJoinRule::new()));
+
+        // region.name = customer.custkey, utf8 and int32
+        let plan = test_optimizer
+            .optimize(join_on("region", "customer", 1, 3))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Join(JoinType::Inner));
+
+        // customer.acctbal = orders.custkey, float64 and int32
+        let plan = test_optimizer
+            .optimize(join_on("customer", "orders", 5, 9))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Join(JoinType::Inner));
+    }
+
+    #[test]
+    fn hash_join_on_float_keys() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(HashJoinRule::new()));
+
+        // customer.acctbal = orders.totalprice, both float64
+        let plan = test_optimizer
+            .optimize(join_on("customer", "orders", 5, 11))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::PhysicalHashJoin(JoinType::Inner));
+    }
+    #[test]
+    fn hash_mark_join() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(HashMarkJoinRule::new()));
+
+        // region.regionkey in (select custkey from customer)
+        let plan = test_optimizer
+            .optimize(join_on_with_type(
+                "region",
+                "customer",
+                0,
+                3,
+                JoinType::LeftMark,
+            ))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::PhysicalHashJoin(JoinType::LeftMark));
+        let join = PhysicalHashJoin::from_plan_node(plan).unwrap();
+        assert_eq!(
+            join.right_keys().to_vec(),
+            vec![ColumnRefPred::new(0).into_pred_node()]
+        );
+
+        // inner joins are left to the hash join rule
+        let plan = test_optimizer
+            .optimize(join_on("region", "customer", 0, 3))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Join(JoinType::Inner));
+    }
+}
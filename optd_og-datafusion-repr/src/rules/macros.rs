@@ -3,7 +3,27 @@
 // Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use optd_og_core::nodes::ArcPlanNode;
+
+use crate::plan_nodes::DfNodeType;
+
+/// What the apply function of a rule receives: either the plain binding, or a picks struct
+/// generated by [`define_picks`] from a typed matcher.
+pub(crate) trait RulePicks: Sized {
+    /// Returns `None` if the binding does not have the shape of the matcher.
+    fn from_binding(binding: ArcPlanNode<DfNodeType>) -> Option<Self>;
+}
+
+impl RulePicks for ArcPlanNode<DfNodeType> {
+    fn from_binding(binding: ArcPlanNode<DfNodeType>) -> Option<Self> {
+        Some(binding)
+    }
+}
+
 macro_rules! define_matcher {
+    ( $discriminant:expr, ( $typ:expr => $node:ident : $wrapper:ty $(, $children:tt )* ) ) => {
+        crate::rules::macros::define_matcher!($discriminant, ( $typ $(, $children )* ))
+    };
     ( $discriminant:expr, ( $typ:expr $(, $children:tt )* ) ) => {
         if $discriminant {
             RuleMatcher::MatchDiscriminant {
@@ -26,8 +46,84 @@ macro_rules! define_matcher {
     };
 }
 
+/// Generates a struct with a field for each pick of a typed matcher, e.g.,
+/// `(Join(JoinType::Inner) => join: LogicalJoin, (Sort => sort: LogicalSort, a), b)` has the
+/// fields `join: LogicalJoin`, `sort: LogicalSort`, `a` and `b`, where the nodes the matcher
+/// does not constrain are `PlanNodeOrGroup`s.
+macro_rules! define_picks {
+    ($picks:ident, $matcher:tt) => {
+        crate::rules::macros::define_picks! { @collect $picks, $matcher, [], [$matcher] }
+    };
+    (@collect $picks:ident, $matcher:tt, [$($fields:tt)*], []) => {
+        // A rule may not need all its picks.
+        #[allow(dead_code)]
+        pub struct $picks {
+            $($fields)*
+        }
+
+        impl crate::rules::macros::RulePicks for $picks {
+            fn from_binding(
+                binding: optd_og_core::nodes::ArcPlanNode<crate::plan_nodes::DfNodeType>,
+            ) -> Option<Self> {
+                crate::rules::macros::bind_picks!(@node binding, $matcher);
+                crate::rules::macros::define_picks!(@construct [$($fields)*])
+            }
+        }
+    };
+    (@collect $picks:ident, $matcher:tt, [$($fields:tt)*],
+        [( $typ:expr => $node:ident : $wrapper:ty $(, $children:tt )* ) $($rest:tt)*]) => {
+        crate::rules::macros::define_picks! {
+            @collect $picks, $matcher, [$($fields)* pub $node: $wrapper,], [$($children)* $($rest)*]
+        }
+    };
+    (@collect $picks:ident, $matcher:tt, [$($fields:tt)*],
+        [( $typ:expr $(, $children:tt )* ) $($rest:tt)*]) => {
+        crate::rules::macros::define_picks! {
+            @collect $picks, $matcher, [$($fields)*], [$($children)* $($rest)*]
+        }
+    };
+    (@collect $picks:ident, $matcher:tt, [$($fields:tt)*], [$pick:ident $($rest:tt)*]) => {
+        crate::rules::macros::define_picks! {
+            @collect $picks, $matcher,
+            [
+                $($fields)*
+                pub $pick: optd_og_core::nodes::PlanNodeOrGroup<crate::plan_nodes::DfNodeType>,
+            ],
+            [$($rest)*]
+        }
+    };
+    (@construct [$(pub $field:ident : $typ:ty,)*]) => {
+        Some(Self { $($field),* })
+    };
+}
+
+/// Binds the picks of a typed matcher to local variables, returning `None` from the enclosing
+/// function if the binding does not have the shape of the matcher.
+macro_rules! bind_picks {
+    (@node $node:expr, ( $typ:expr => $name:ident : $wrapper:ty $(, $children:tt )* )) => {
+        let $name = <$wrapper as crate::plan_nodes::DfReprPlanNode>::from_plan_node($node)?;
+        #[allow(unused_mut, unused_variables)]
+        let mut children = crate::plan_nodes::DfReprPlanNode::into_plan_node($name.clone())
+            .children
+            .clone()
+            .into_iter();
+        $( crate::rules::macros::bind_picks!(@child children.next()?, $children); )*
+    };
+    (@node $node:expr, ( $typ:expr $(, $children:tt )* )) => {
+        #[allow(unused_mut, unused_variables)]
+        let mut children = $node.children.clone().into_iter();
+        $( crate::rules::macros::bind_picks!(@child children.next()?, $children); )*
+    };
+    (@child $child:expr, $pick:ident) => {
+        let $pick = $child;
+    };
+    (@child $child:expr, $matcher:tt) => {
+        crate::rules::macros::bind_picks!(@node $child.as_plan_node()?, $matcher);
+    };
+}
+
 macro_rules! define_rule_inner {
-    ($rule_type:expr, $discriminant:expr, $name:ident, $apply:ident, $($matcher:tt)+) => {
+    ($rule_type:expr, $discriminant:expr, $name:ident, $apply:ident, $picks:ty, $($matcher:tt)+) => {
         pub struct $name {
             matcher: RuleMatcher<DfNodeType>,
         }
@@ -51,7 +147,10 @@ macro_rules! define_rule_inner {
                 optimizer: &O,
                 binding: optd_og_core::nodes::ArcPlanNode<DfNodeType>,
             ) -> Vec<optd_og_core::nodes::PlanNodeOrGroup<DfNodeType>> {
-                $apply(optimizer, binding)
+                match <$picks as crate::rules::macros::RulePicks>::from_binding(binding) {
+                    Some(picks) => $apply(optimizer, picks),
+                    None => vec![],
+                }
             }
 
             camelpaste::paste! {
@@ -67,24 +166,45 @@ macro_rules! define_rule_inner {
     };
 }
 
+/// Defines a rule whose apply function receives the binding, or, if a picks struct is named, the
+/// picks of the typed matcher, see [`define_picks`].
 macro_rules! define_rule {
+    ($name:ident, $apply:ident, $picks:ident, $matcher:tt) => {
+        crate::rules::macros::define_picks! { $picks, $matcher }
+        crate::rules::macros::define_rule_inner! { false, false, $name, $apply, $picks, $matcher }
+    };
     ($name:ident, $apply:ident, $($matcher:tt)+) => {
-        crate::rules::macros::define_rule_inner! { false, false, $name, $apply, $($matcher)+ }
+        crate::rules::macros::define_rule_inner! {
+            false, false, $name, $apply, optd_og_core::nodes::ArcPlanNode<DfNodeType>, $($matcher)+
+        }
     };
 }
 
 macro_rules! define_rule_discriminant {
+    ($name:ident, $apply:ident, $picks:ident, $matcher:tt) => {
+        crate::rules::macros::define_picks! { $picks, $matcher }
+        crate::rules::macros::define_rule_inner! { false, true, $name, $apply, $picks, $matcher }
+    };
     ($name:ident, $apply:ident, $($matcher:tt)+) => {
-        crate::rules::macros::define_rule_inner! { false, true, $name, $apply, $($matcher)+ }
+        crate::rules::macros::define_rule_inner! {
+            false, true, $name, $apply, optd_og_core::nodes::ArcPlanNode<DfNodeType>, $($matcher)+
+        }
     };
 }
 
 macro_rules! define_impl_rule {
+    ($name:ident, $apply:ident, $picks:ident, $matcher:tt) => {
+        crate::rules::macros::define_picks! { $picks, $matcher }
+        crate::rules::macros::define_rule_inner! { true, false, $name, $apply, $picks, $matcher }
+    };
     ($name:ident, $apply:ident, $($matcher:tt)+) => {
-        crate::rules::macros::define_rule_inner! { true, false, $name, $apply, $($matcher)+ }
+        crate::rules::macros::define_rule_inner! {
+            true, false, $name, $apply, optd_og_core::nodes::ArcPlanNode<DfNodeType>, $($matcher)+
+        }
     };
 }
 
 pub(crate) use {
-    define_impl_rule, define_matcher, define_rule, define_rule_discriminant, define_rule_inner,
+    bind_picks, define_impl_rule, define_matcher, define_picks, define_rule,
+    define_rule_discriminant, define_rule_inner,
 };
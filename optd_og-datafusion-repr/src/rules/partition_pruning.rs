@@ -0,0 +1,187 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::Arc;
+
+use optd_og_core::nodes::PlanNodeOrGroup;
+use optd_og_core::optimizer::Optimizer;
+use optd_og_core::rules::{Rule, RuleMatcher};
+
+use crate::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, ColumnRefPred, DfNodeType, DfPredType, DfReprPlanNode,
+    DfReprPredNode, FuncType, ListPred, LogOpType, LogicalFilter, LogicalScan,
+};
+use crate::properties::schema::Catalog;
+
+/// Prunes the partitions of a scanned table with the conjuncts of the filter above which only
+/// refer to the partition columns of the table. For example, on a table partitioned by `year`:
+///     select * from sales where year = 2024 and amount > 10
+/// the scan only reads the partitions where `year = 2024`. The filter is kept, as the storage
+/// may return rows of other partitions, and the pruned scan is cheaper than the full one.
+///
+/// The partition columns are fetched from `Catalog::partition_columns`; tables without
+/// partition columns are never rewritten.
+pub struct PartitionPruningRule {
+    matcher: RuleMatcher<DfNodeType>,
+    catalog: Arc<dyn Catalog>,
+}
+
+impl PartitionPruningRule {
+    pub fn new(catalog: Arc<dyn Catalog>) -> Self {
+        Self {
+            matcher: RuleMatcher::MatchNode {
+                typ: DfNodeType::Filter,
+                children: vec![RuleMatcher::MatchNode {
+                    typ: DfNodeType::Scan,
+                    children: vec![],
+                }],
+            },
+            catalog,
+        }
+    }
+}
+
+/// Whether the predicate can be evaluated with the values of the partition columns alone.
+/// Functions other than the boolean ones are not, as the storage may not be able to run them.
+fn is_partition_pred(pred: &ArcDfPredNode, partition_columns: &[usize]) -> bool {
+    match &pred.typ {
+        DfPredType::ColumnRef => partition_columns
+            .contains(&ColumnRefPred::from_pred_node(pred.clone()).unwrap().index()),
+        DfPredType::Constant(_) => true,
+        DfPredType::Func(FuncType::Not | FuncType::IsNull | FuncType::IsNotNull)
+        | DfPredType::BinOp(_)
+        | DfPredType::LogOp(_)
+        | DfPredType::Between
+        | DfPredType::Cast
+        | DfPredType::Like
+        | DfPredType::InList
+        | DfPredType::List => pred
+            .children
+            .iter()
+            .all(|child| is_partition_pred(child, partition_columns)),
+        _ => false,
+    }
+}
+
+fn has_column_ref(pred: &ArcDfPredNode) -> bool {
+    pred.typ == DfPredType::ColumnRef || pred.children.iter().any(has_column_ref)
+}
+
+impl<O: Optimizer<DfNodeType>> Rule<DfNodeType, O> for PartitionPruningRule {
+    fn matcher(&self) -> &RuleMatcher<DfNodeType> {
+        &self.matcher
+    }
+
+    fn apply(&self, _optimizer: &O, binding: ArcDfPlanNode) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+        let filter = LogicalFilter::from_plan_node(binding).unwrap();
+        let scan = LogicalScan::from_plan_node(filter.child().unwrap_plan_node()).unwrap();
+        if scan.partition_filters().is_some() || scan.index_lookup().is_some() {
+            return vec![];
+        }
+        let partition_columns = self.catalog.partition_columns(&scan.table_id());
+        if partition_columns.is_empty() {
+            return vec![];
+        }
+        let cond = filter.cond();
+        let conjuncts = match cond.typ {
+            DfPredType::LogOp(LogOpType::And) => cond.children.clone(),
+            _ => vec![cond.clone()],
+        };
+        let partition_filters = conjuncts
+            .into_iter()
+            .filter(|pred| has_column_ref(pred) && is_partition_pred(pred, &partition_columns))
+            .collect::<Vec<_>>();
+        if partition_filters.is_empty() {
+            return vec![];
+        }
+
+        let scan = LogicalScan::new_with_partition_filters(
+            scan.table().to_string(),
+            ListPred::new(partition_filters),
+        );
+        let node = LogicalFilter::new_unchecked(scan.into_plan_node(), cond);
+        vec![node.into_plan_node().into()]
+    }
+
+    fn name(&self) -> &'static str {
+        "partition_pruning_rule"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_nodes::{BinOpPred, BinOpType, ConstantPred, LogOpPred};
+    use crate::properties::schema::Schema;
+    use crate::testing::{new_test_optimizer, TpchCatalog};
+    use crate::TableId;
+
+    /// The TPC-H catalog where `customer` is partitioned by `nationkey`.
+    struct PartitionedCatalog;
+
+    impl Catalog for PartitionedCatalog {
+        fn get(&self, name: &TableId) -> Schema {
+            TpchCatalog.get(name)
+        }
+
+        fn partition_columns(&self, name: &TableId) -> Vec<usize> {
+            match name.as_str() {
+                "customer" => vec![3],
+                _ => vec![],
+            }
+        }
+    }
+
+    fn col_eq_int(col: usize, value: i32) -> ArcDfPredNode {
+        BinOpPred::new(
+            ColumnRefPred::new(col).into_pred_node(),
+            ConstantPred::int32(value).into_pred_node(),
+            BinOpType::Eq,
+        )
+        .into_pred_node()
+    }
+
+    fn filtered_scan(table: &str, cond: ArcDfPredNode) -> ArcDfPlanNode {
+        LogicalFilter::new(LogicalScan::new(table.into()).into_plan_node(), cond).into_plan_node()
+    }
+
+    #[test]
+    fn prune_partitions_with_partition_conjuncts() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(PartitionPruningRule::new(Arc::new(
+            PartitionedCatalog,
+        ))));
+
+        let cond = LogOpPred::new(LogOpType::And, vec![col_eq_int(3, 1), col_eq_int(0, 2)])
+            .into_pred_node();
+        let plan = test_optimizer
+            .optimize(filtered_scan("customer", cond.clone()))
+            .unwrap();
+        let filter = LogicalFilter::from_plan_node(plan).unwrap();
+        assert_eq!(filter.cond(), cond);
+        let scan = LogicalScan::from_plan_node(filter.child().unwrap_plan_node()).unwrap();
+        let partition_filters = scan.partition_filters().unwrap();
+        assert_eq!(partition_filters.to_vec(), vec![col_eq_int(3, 1)]);
+    }
+
+    #[test]
+    fn keep_scan_without_partition_conjuncts() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(PartitionPruningRule::new(Arc::new(
+            PartitionedCatalog,
+        ))));
+
+        let plan = test_optimizer
+            .optimize(filtered_scan("customer", col_eq_int(0, 2)))
+            .unwrap();
+        let scan = LogicalScan::from_plan_node(plan.child_rel(0)).unwrap();
+        assert!(scan.partition_filters().is_none());
+
+        // region has no partition columns
+        let plan = test_optimizer
+            .optimize(filtered_scan("region", col_eq_int(0, 2)))
+            .unwrap();
+        let scan = LogicalScan::from_plan_node(plan.child_rel(0)).unwrap();
+        assert!(scan.partition_filters().is_none());
+    }
+}
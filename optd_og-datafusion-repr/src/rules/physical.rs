@@ -42,6 +42,8 @@ impl PhysicalConversionRule {
             Arc::new(PhysicalConversionRule::new(DfNodeType::Agg)),
             Arc::new(PhysicalConversionRule::new(DfNodeType::EmptyRelation)),
             Arc::new(PhysicalConversionRule::new(DfNodeType::Limit)),
+            Arc::new(PhysicalConversionRule::new(DfNodeType::TableFunction)),
+            Arc::new(PhysicalConversionRule::new(DfNodeType::Singleton)),
         ];
 
         rules
@@ -125,6 +127,22 @@ impl<O: Optimizer<DfNodeType>> Rule<DfNodeType, O> for PhysicalConversionRule {
                 };
                 vec![node.into()]
             }
+            DfNodeType::TableFunction => {
+                let node = PlanNode {
+                    typ: DfNodeType::PhysicalTableFunction,
+                    children,
+                    predicates,
+                };
+                vec![node.into()]
+            }
+            DfNodeType::Singleton => {
+                let node = PlanNode {
+                    typ: DfNodeType::PhysicalSingleton,
+                    children,
+                    predicates,
+                };
+                vec![node.into()]
+            }
             _ => vec![],
         }
     }
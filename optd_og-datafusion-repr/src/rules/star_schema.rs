@@ -0,0 +1,414 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use optd_og_core::nodes::PlanNodeOrGroup;
+use optd_og_core::optimizer::Optimizer;
+use optd_og_core::rules::{Rule, RuleMatcher};
+
+use super::macros::define_rule;
+use crate::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, BinOpPred, BinOpType, ColumnRefPred, ConstantPred, ConstantType,
+    DfNodeType, DfPredType, DfReprPlanNode, DfReprPredNode, JoinType, ListPred, LogOpPred,
+    LogOpType, LogicalJoin, LogicalProjection, PredExt,
+};
+use crate::properties::uniqueness::UniqueKeys;
+use crate::OptimizerExt;
+
+// Reorders a tree of inner joins forming a star (or snowflake) schema into a left-deep tree with
+// the fact table at the bottom left, so that the fact table is the probe side of every join and
+// the (usually much smaller) dimensions are the build sides. The order is only a seed for the
+// cascades optimizer, which is still free to explore others.
+//
+// The fact table is the relation referencing the most dimensions, where a relation references a
+// dimension if it is joined with it on a unique key of the dimension but not on one of its own.
+// The relations only reachable through a dimension, i.e., the dimensions of a snowflake, are
+// joined with it first, so that each dimension is joined with the fact table as one subtree.
+define_rule!(
+    StarSchemaJoinRule,
+    apply_star_schema_join,
+    (Join(JoinType::Inner), left, right)
+);
+
+/// A relation of a flattened join tree.
+struct Relation {
+    node: ArcDfPlanNode,
+    /// The position of the first column of the relation among the columns of all relations.
+    offset: usize,
+    width: usize,
+}
+
+/// A tree of inner joins flattened into its relations and the conjuncts of its join conditions,
+/// which refer to the columns of all relations.
+#[derive(Default)]
+struct JoinGraph {
+    relations: Vec<Relation>,
+    conds: Vec<ArcDfPredNode>,
+    width: usize,
+}
+
+impl JoinGraph {
+    /// Adds the relations of the tree, looking through the projections which only reorder the
+    /// columns of an inner join, e.g., the ones added by this rule. Returns the column each
+    /// output column of the tree refers to.
+    fn flatten(
+        &mut self,
+        optimizer: &impl Optimizer<DfNodeType>,
+        node: ArcDfPlanNode,
+    ) -> Vec<usize> {
+        match node.typ {
+            DfNodeType::Join(JoinType::Inner) => {
+                let join = LogicalJoin::from_plan_node(node).unwrap();
+                let mut columns = self.flatten(optimizer, join.left().unwrap_plan_node());
+                columns.extend(self.flatten(optimizer, join.right().unwrap_plan_node()));
+                let cond = join
+                    .cond()
+                    .rewrite_column_refs(|idx| Some(columns[idx]))
+                    .unwrap();
+                self.add_cond(cond);
+                columns
+            }
+            DfNodeType::Projection if is_join_reorder(&node) => {
+                let proj = LogicalProjection::from_plan_node(node).unwrap();
+                let columns = self.flatten(optimizer, proj.child().unwrap_plan_node());
+                proj.exprs()
+                    .to_vec()
+                    .into_iter()
+                    .map(|expr| columns[ColumnRefPred::from_pred_node(expr).unwrap().index()])
+                    .collect()
+            }
+            _ => {
+                let width = optimizer.get_schema_of(node.clone().into()).len();
+                let offset = self.width;
+                self.relations.push(Relation {
+                    node,
+                    offset,
+                    width,
+                });
+                self.width += width;
+                (offset..offset + width).collect()
+            }
+        }
+    }
+
+    fn add_cond(&mut self, cond: ArcDfPredNode) {
+        match cond.typ {
+            DfPredType::LogOp(LogOpType::And) => {
+                for child in cond.children.iter() {
+                    self.add_cond(child.clone());
+                }
+            }
+            DfPredType::Constant(ConstantType::Bool)
+                if cond.data.as_ref().is_some_and(|data| data.as_bool()) => {}
+            _ => self.conds.push(cond),
+        }
+    }
+
+    fn relation_of(&self, column: usize) -> usize {
+        self.relations
+            .iter()
+            .position(|rel| column < rel.offset + rel.width)
+            .unwrap()
+    }
+
+    /// The relations the conjunct refers to.
+    fn relations_of(&self, cond: &ArcDfPredNode) -> Vec<usize> {
+        let mut relations = cond
+            .get_column_refs()
+            .iter()
+            .map(|col| self.relation_of(col.index()))
+            .collect::<Vec<_>>();
+        relations.sort_unstable();
+        relations.dedup();
+        relations
+    }
+
+    /// The columns of each relation equal to columns of each other relation, in the columns of
+    /// the relation.
+    fn equi_columns(&self) -> Vec<Vec<Vec<usize>>> {
+        let n = self.relations.len();
+        let mut equi_columns = vec![vec![vec![]; n]; n];
+        for cond in &self.conds {
+            let Some(bin_op) = BinOpPred::from_pred_node(cond.clone()) else {
+                continue;
+            };
+            if bin_op.op_type() != BinOpType::Eq {
+                continue;
+            }
+            let (Some(left), Some(right)) = (
+                ColumnRefPred::from_pred_node(bin_op.left_child()),
+                ColumnRefPred::from_pred_node(bin_op.right_child()),
+            ) else {
+                continue;
+            };
+            let (left, right) = (left.index(), right.index());
+            let (left_rel, right_rel) = (self.relation_of(left), self.relation_of(right));
+            if left_rel == right_rel {
+                continue;
+            }
+            equi_columns[left_rel][right_rel].push(left - self.relations[left_rel].offset);
+            equi_columns[right_rel][left_rel].push(right - self.relations[right_rel].offset);
+        }
+        equi_columns
+    }
+}
+
+/// Whether the node is a projection which only reorders the columns of an inner join.
+fn is_join_reorder(node: &ArcDfPlanNode) -> bool {
+    let proj = LogicalProjection::from_plan_node(node.clone()).unwrap();
+    proj.child().unwrap_plan_node().typ == DfNodeType::Join(JoinType::Inner)
+        && proj
+            .exprs()
+            .to_vec()
+            .into_iter()
+            .all(|expr| ColumnRefPred::from_pred_node(expr).is_some())
+}
+
+/// Orders the relations of the join graph with the fact table first, followed by the subtrees of
+/// the dimensions. Returns `None` if the graph is not a star.
+fn star_order(graph: &JoinGraph, unique_keys: &[UniqueKeys]) -> Option<Vec<Vec<usize>>> {
+    let n = graph.relations.len();
+    let equi_columns = graph.equi_columns();
+    let references = |from: usize, to: usize| {
+        !equi_columns[to][from].is_empty()
+            && unique_keys[to].is_key(&equi_columns[to][from])
+            && !unique_keys[from].is_key(&equi_columns[from][to])
+    };
+    let dimensions_of = |fact: usize| (0..n).filter(move |dim| references(fact, *dim));
+    // the first relation referencing the most dimensions
+    let fact = (0..n)
+        .rev()
+        .max_by_key(|fact| dimensions_of(*fact).count())?;
+    let dimensions = dimensions_of(fact).collect::<Vec<_>>();
+    if dimensions.len() < 2 {
+        return None;
+    }
+
+    let mut assigned = vec![false; n];
+    assigned[fact] = true;
+    for dim in &dimensions {
+        assigned[*dim] = true;
+    }
+    let mut order = vec![vec![fact]];
+    for dim in dimensions {
+        let mut subtree = vec![dim];
+        let mut idx = 0;
+        while idx < subtree.len() {
+            let neighbors = (0..n)
+                .filter(|rel| !assigned[*rel] && !equi_columns[subtree[idx]][*rel].is_empty())
+                .collect::<Vec<_>>();
+            for rel in neighbors {
+                assigned[rel] = true;
+                subtree.push(rel);
+            }
+            idx += 1;
+        }
+        order.push(subtree);
+    }
+    // The other relations joined with the fact table, e.g., on a key of both.
+    while let Some(rel) = (0..n).find(|rel| {
+        !assigned[*rel]
+            && (0..n).any(|other| assigned[other] && !equi_columns[*rel][other].is_empty())
+    }) {
+        assigned[rel] = true;
+        order.push(vec![rel]);
+    }
+    // Never introduce cross joins.
+    if assigned.contains(&false) {
+        return None;
+    }
+    Some(order)
+}
+
+/// Builds the join trees of a star, placing each conjunct at the lowest join which has all the
+/// relations it refers to.
+struct StarBuilder<'a> {
+    graph: &'a JoinGraph,
+    /// The position of each column of the relations in the output of the star.
+    positions: Vec<usize>,
+    placed: Vec<bool>,
+}
+
+impl StarBuilder<'_> {
+    fn join(
+        &mut self,
+        (left, mut relations): (ArcDfPlanNode, Vec<usize>),
+        (right, right_relations): (ArcDfPlanNode, Vec<usize>),
+    ) -> (ArcDfPlanNode, Vec<usize>) {
+        relations.extend(right_relations);
+        let base = self.positions[self.graph.relations[relations[0]].offset];
+        let mut conds = vec![];
+        for (idx, cond) in self.graph.conds.iter().enumerate() {
+            if self.placed[idx]
+                || !self
+                    .graph
+                    .relations_of(cond)
+                    .iter()
+                    .all(|rel| relations.contains(rel))
+            {
+                continue;
+            }
+            self.placed[idx] = true;
+            conds.push(
+                cond.rewrite_column_refs(|col| Some(self.positions[col] - base))
+                    .unwrap(),
+            );
+        }
+        let cond = match conds.len() {
+            0 => ConstantPred::bool(true).into_pred_node(),
+            1 => conds.remove(0),
+            _ => LogOpPred::new(LogOpType::And, conds).into_pred_node(),
+        };
+        let node = LogicalJoin::new_unchecked(left, right, cond, JoinType::Inner);
+        (node.into_plan_node(), relations)
+    }
+
+    fn left_deep(&mut self, relations: &[usize]) -> (ArcDfPlanNode, Vec<usize>) {
+        let graph = self.graph;
+        let relation = |rel: usize| (graph.relations[rel].node.clone(), vec![rel]);
+        let mut tree = relation(relations[0]);
+        for rel in &relations[1..] {
+            tree = self.join(tree, relation(*rel));
+        }
+        tree
+    }
+}
+
+fn apply_star_schema_join(
+    optimizer: &impl Optimizer<DfNodeType>,
+    binding: ArcDfPlanNode,
+) -> Vec<PlanNodeOrGroup<DfNodeType>> {
+    let mut graph = JoinGraph::default();
+    let columns = graph.flatten(optimizer, binding.clone());
+    if graph.relations.len() < 3 {
+        return vec![];
+    }
+    let unique_keys = graph
+        .relations
+        .iter()
+        .map(|rel| optimizer.get_unique_keys_of(rel.node.clone().into()))
+        .collect::<Vec<_>>();
+    let Some(order) = star_order(&graph, &unique_keys) else {
+        return vec![];
+    };
+
+    let mut positions = vec![0; graph.width];
+    let mut next_position = 0;
+    for rel in order.iter().flatten() {
+        let rel = &graph.relations[*rel];
+        for position in &mut positions[rel.offset..rel.offset + rel.width] {
+            *position = next_position;
+            next_position += 1;
+        }
+    }
+    let mut builder = StarBuilder {
+        graph: &graph,
+        positions,
+        placed: vec![false; graph.conds.len()],
+    };
+    let mut tree = builder.left_deep(&order[0]);
+    for subtree in &order[1..] {
+        let subtree = builder.left_deep(subtree);
+        tree = builder.join(tree, subtree);
+    }
+    let (node, _) = tree;
+
+    let columns = columns
+        .into_iter()
+        .map(|col| builder.positions[col])
+        .collect::<Vec<_>>();
+    let is_identity =
+        columns.len() == graph.width && columns.iter().enumerate().all(|(i, c)| i == *c);
+    if is_identity {
+        if node == binding {
+            // already in the order of the star
+            return vec![];
+        }
+        return vec![node.into()];
+    }
+    let exprs = columns
+        .into_iter()
+        .map(|col| ColumnRefPred::new(col).into_pred_node())
+        .collect();
+    let node = LogicalProjection::new_unchecked(node, ListPred::new(exprs));
+    vec![node.into_plan_node().into()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::plan_nodes::LogicalScan;
+    use crate::testing::new_test_optimizer_with_keys;
+
+    fn scan(table: &str) -> ArcDfPlanNode {
+        LogicalScan::new(table.into()).into_plan_node()
+    }
+
+    fn join(left: ArcDfPlanNode, right: ArcDfPlanNode, cond: ArcDfPredNode) -> ArcDfPlanNode {
+        LogicalJoin::new(left, right, cond, JoinType::Inner).into_plan_node()
+    }
+
+    fn col_eq(left: usize, right: usize) -> ArcDfPredNode {
+        BinOpPred::new(
+            ColumnRefPred::new(left).into_pred_node(),
+            ColumnRefPred::new(right).into_pred_node(),
+            BinOpType::Eq,
+        )
+        .into_pred_node()
+    }
+
+    #[test]
+    fn reorder_snowflake_around_fact() {
+        let mut test_optimizer = new_test_optimizer_with_keys(Arc::new(StarSchemaJoinRule::new()));
+
+        // customer (8 columns) -> region (3), orders (9) -> customer, orders -> region
+        let plan = join(
+            join(
+                join(scan("customer"), scan("region"), col_eq(3, 8)),
+                scan("orders"),
+                col_eq(12, 0),
+            ),
+            scan("region"),
+            col_eq(18, 20),
+        );
+        let plan = test_optimizer.optimize(plan).unwrap();
+
+        // orders join (customer join region) join region, in the original column order
+        let proj = LogicalProjection::from_plan_node(plan.clone()).unwrap();
+        let exprs = proj
+            .exprs()
+            .to_vec()
+            .into_iter()
+            .map(|expr| ColumnRefPred::from_pred_node(expr).unwrap().index())
+            .collect::<Vec<_>>();
+        assert_eq!(exprs, (9..20).chain(0..9).chain(20..23).collect::<Vec<_>>());
+        let top = LogicalJoin::from_plan_node(proj.child().unwrap_plan_node()).unwrap();
+        assert_eq!(top.cond(), col_eq(7, 20));
+        assert_eq!(top.right().unwrap_plan_node(), scan("region"));
+        let fact = LogicalJoin::from_plan_node(top.left().unwrap_plan_node()).unwrap();
+        assert_eq!(fact.cond(), col_eq(1, 9));
+        assert_eq!(fact.left().unwrap_plan_node(), scan("orders"));
+        let dimension = LogicalJoin::from_plan_node(fact.right().unwrap_plan_node()).unwrap();
+        assert_eq!(dimension.cond(), col_eq(3, 8));
+        assert_eq!(dimension.left().unwrap_plan_node(), scan("customer"));
+
+        // already in the order of the star
+        assert_eq!(test_optimizer.optimize(plan.clone()).unwrap(), plan);
+    }
+
+    #[test]
+    fn keep_join_with_single_dimension() {
+        let mut test_optimizer = new_test_optimizer_with_keys(Arc::new(StarSchemaJoinRule::new()));
+
+        // customer -> region, orders -> customer
+        let plan = join(
+            join(scan("customer"), scan("region"), col_eq(3, 8)),
+            scan("orders"),
+            col_eq(12, 0),
+        );
+        assert_eq!(test_optimizer.optimize(plan.clone()).unwrap(), plan);
+    }
+}
@@ -9,9 +9,10 @@ use optd_og_core::rules::{Rule, RuleMatcher};
 
 use crate::plan_nodes::{
     ArcDfPlanNode, ArcDfPredNode, BinOpPred, BinOpType, ColumnRefPred, ConstantPred, DependentJoin,
-    DfNodeType, DfPredType, DfReprPlanNode, DfReprPredNode, ExternColumnRefPred, FuncPred,
-    FuncType, JoinType, ListPred, LogOpPred, LogOpType, LogicalAgg, LogicalFilter, LogicalJoin,
-    LogicalLimit, LogicalProjection, PredExt, RawDependentJoin, SubqueryType,
+    DfNodeType, DfPredNode, DfPredType, DfReprPlanNode, DfReprPredNode, ExternColumnRefPred,
+    FuncPred, FuncType, JoinType, ListPred, LogOpPred, LogOpType, LogicalAgg, LogicalFilter,
+    LogicalJoin, LogicalLimit, LogicalProjection, LogicalSingleton, PredExt, RawDependentJoin,
+    SubqueryType,
 };
 use crate::rules::macros::{define_rule, define_rule_discriminant};
 use crate::OptimizerExt;
@@ -82,9 +83,10 @@ fn apply_dep_initial_distinct(
     // If we have no correlated columns, we can skip the whole dependent join step
     if correlated_col_indices.is_empty() {
         let res = match join.sq_type() {
+            // The value of the subquery is the same for all rows, so it is computed once.
             SubqueryType::Scalar => LogicalJoin::new_unchecked(
                 left,
-                right,
+                LogicalSingleton::new_unchecked(right).into_plan_node(),
                 ConstantPred::bool(true).into_pred_node(),
                 JoinType::Inner,
             )
@@ -126,18 +128,41 @@ fn apply_dep_initial_distinct(
                 )
                 .into_plan_node()
             }
-            SubqueryType::Any { pred, op } => LogicalJoin::new_unchecked(
-                left,
-                right,
-                BinOpPred::new(
-                    pred.clone().into(),
-                    ColumnRefPred::new(left_schema_size).into_pred_node(),
-                    *op,
-                )
-                .into_pred_node(),
-                JoinType::LeftMark,
-            )
-            .into_plan_node(),
+            SubqueryType::Any { pred, op } => {
+                quantified_comparison_to_agg(&left, &right, left_schema_size, pred, *op, false)
+                    .unwrap_or_else(|| {
+                        LogicalJoin::new_unchecked(
+                            left,
+                            right,
+                            BinOpPred::new(
+                                pred.clone().into(),
+                                ColumnRefPred::new(left_schema_size).into_pred_node(),
+                                *op,
+                            )
+                            .into_pred_node(),
+                            JoinType::LeftMark,
+                        )
+                        .into_plan_node()
+                    })
+            }
+            SubqueryType::All { pred, op } => {
+                quantified_comparison_to_agg(&left, &right, left_schema_size, pred, *op, true)
+                    .unwrap_or_else(|| {
+                        // x op ALL (...) is NOT (x negated_op ANY (...))
+                        let mark_join = LogicalJoin::new_unchecked(
+                            left,
+                            right,
+                            BinOpPred::new(
+                                pred.clone().into(),
+                                ColumnRefPred::new(left_schema_size).into_pred_node(),
+                                op.negated_comparison().unwrap(),
+                            )
+                            .into_pred_node(),
+                            JoinType::LeftMark,
+                        );
+                        negate_mark_column(mark_join.into_plan_node(), left_schema_size)
+                    })
+            }
         };
 
         return vec![res.into()];
@@ -196,27 +221,37 @@ fn apply_dep_initial_distinct(
                 })
                 .collect(),
         ),
-        SubqueryType::Any { pred, op } => LogOpPred::new(
-            LogOpType::And,
-            correlated_col_indices
-                .iter()
-                .enumerate()
-                .map(|(i, _)| {
-                    assert!(i + left_schema_size < left_schema_size + new_dep_join_schema_size);
-                    BinOpPred::new(
-                        pred.clone().into(),
-                        ColumnRefPred::new(i + left_schema_size).into_pred_node(),
-                        *op,
-                    )
-                    .into_pred_node()
-                })
-                .collect(),
-        ),
+        SubqueryType::Any { pred, op } | SubqueryType::All { pred, op } => {
+            // x op ALL (...) is NOT (x negated_op ANY (...)), the mark column is negated below.
+            let op = if matches!(join.sq_type(), SubqueryType::All { pred: _, op: _ }) {
+                op.negated_comparison().unwrap()
+            } else {
+                *op
+            };
+            LogOpPred::new(
+                LogOpType::And,
+                correlated_col_indices
+                    .iter()
+                    .enumerate()
+                    .map(|(i, _)| {
+                        assert!(i + left_schema_size < left_schema_size + new_dep_join_schema_size);
+                        BinOpPred::new(
+                            pred.clone().into(),
+                            ColumnRefPred::new(i + left_schema_size).into_pred_node(),
+                            op,
+                        )
+                        .into_pred_node()
+                    })
+                    .collect(),
+            )
+        }
     };
 
     let join_type = match join.sq_type() {
         SubqueryType::Scalar => JoinType::Inner,
-        SubqueryType::Exists | SubqueryType::Any { pred: _, op: _ } => JoinType::LeftMark,
+        SubqueryType::Exists
+        | SubqueryType::Any { pred: _, op: _ }
+        | SubqueryType::All { pred: _, op: _ } => JoinType::LeftMark,
     };
 
     let new_join = LogicalJoin::new_unchecked(
@@ -243,6 +278,8 @@ fn apply_dep_initial_distinct(
             ),
         )
         .into_plan_node()
+    } else if matches!(join.sq_type(), SubqueryType::All { pred: _, op: _ }) {
+        negate_mark_column(new_join.into_plan_node(), left_schema_size)
     } else {
         new_join.into_plan_node()
     };
@@ -250,6 +287,136 @@ fn apply_dep_initial_distinct(
     vec![node.into()]
 }
 
+/// Projects the columns of the left side of a mark join, and the negation of the mark column.
+fn negate_mark_column(mark_join: ArcDfPlanNode, left_schema_size: usize) -> ArcDfPlanNode {
+    LogicalProjection::new(
+        mark_join,
+        ListPred::new(
+            (0..left_schema_size)
+                .map(|x| ColumnRefPred::new(x).into_pred_node())
+                .chain(std::iter::once(
+                    FuncPred::new(
+                        FuncType::Not,
+                        ListPred::new(vec![ColumnRefPred::new(left_schema_size).into_pred_node()]),
+                    )
+                    .into_pred_node(),
+                ))
+                .collect(),
+        ),
+    )
+    .into_plan_node()
+}
+
+/// Rewrites an uncorrelated `pred op ANY (subquery)` or `pred op ALL (subquery)` with an ordering
+/// comparison into a comparison with the minimum or maximum of the subquery, which avoids a
+/// mark join on an inequality. For example:
+///     x < ANY (select y from t)
+/// becomes
+///     case when count(*) = 0 then false
+///          when x < max(y) then true
+///          when count(y) < count(*) then null
+///          else x < max(y) end
+/// where the aggregates are computed by a single-row aggregation of the subquery, joined with
+/// the left side. An empty subquery makes ANY false and ALL true. Like a mark join, the result is
+/// null if the comparison with the maximum or minimum does not decide it and the subquery has a
+/// null, e.g., `5 > ALL (1, NULL)` is null. Returns `None` for `=` and `<>`, which are kept as
+/// mark joins. Only reachable for plans built by hand, as datafusion does not plan quantified
+/// comparisons from SQL, see [`SubqueryType::Any`].
+fn quantified_comparison_to_agg(
+    left: &PlanNodeOrGroup<DfNodeType>,
+    right: &PlanNodeOrGroup<DfNodeType>,
+    left_schema_size: usize,
+    pred: &DfPredNode,
+    op: BinOpType,
+    all: bool,
+) -> Option<ArcDfPlanNode> {
+    let use_max = match op {
+        BinOpType::Lt | BinOpType::Leq => !all,
+        BinOpType::Gt | BinOpType::Geq => all,
+        _ => return None,
+    };
+    let agg_func = if use_max { "max" } else { "min" };
+    let right_agg = LogicalAgg::new_unchecked(
+        right.clone(),
+        ListPred::new(vec![
+            FuncPred::new(
+                FuncType::Agg(agg_func.to_string()),
+                ListPred::new(vec![ColumnRefPred::new(0).into_pred_node()]),
+            )
+            .into_pred_node(),
+            FuncPred::new(
+                FuncType::Agg("count".to_string()),
+                ListPred::new(vec![ConstantPred::int64(1).into_pred_node()]),
+            )
+            .into_pred_node(),
+            FuncPred::new(
+                FuncType::Agg("count".to_string()),
+                ListPred::new(vec![ColumnRefPred::new(0).into_pred_node()]),
+            )
+            .into_pred_node(),
+        ]),
+        ListPred::new(vec![]),
+    );
+    let join = LogicalJoin::new_unchecked(
+        left.clone(),
+        right_agg.into_plan_node(),
+        ConstantPred::bool(true).into_pred_node(),
+        JoinType::Inner,
+    );
+    let is_empty = BinOpPred::new(
+        ColumnRefPred::new(left_schema_size + 1).into_pred_node(),
+        ConstantPred::int64(0).into_pred_node(),
+        BinOpType::Eq,
+    );
+    let has_null = BinOpPred::new(
+        ColumnRefPred::new(left_schema_size + 2).into_pred_node(),
+        ColumnRefPred::new(left_schema_size + 1).into_pred_node(),
+        BinOpType::Lt,
+    );
+    let cmp = BinOpPred::new(
+        pred.clone().into(),
+        ColumnRefPred::new(left_schema_size).into_pred_node(),
+        op,
+    )
+    .into_pred_node();
+    // ANY is decided by a value the comparison holds for, ALL by one it does not hold for
+    let decided = if all {
+        FuncPred::new(FuncType::Not, ListPred::new(vec![cmp.clone()])).into_pred_node()
+    } else {
+        cmp.clone()
+    };
+    // there are no null literals, nullif(true, true) is a null boolean
+    let null = FuncPred::new(
+        FuncType::new_scalar("nullif".to_string()),
+        ListPred::new(vec![
+            ConstantPred::bool(true).into_pred_node(),
+            ConstantPred::bool(true).into_pred_node(),
+        ]),
+    );
+    let case = |when: ArcDfPredNode, then: ArcDfPredNode, otherwise: ArcDfPredNode| {
+        FuncPred::new(FuncType::Case, ListPred::new(vec![when, then, otherwise])).into_pred_node()
+    };
+    let mark = case(
+        is_empty.into_pred_node(),
+        ConstantPred::bool(all).into_pred_node(),
+        case(
+            decided,
+            ConstantPred::bool(!all).into_pred_node(),
+            case(has_null.into_pred_node(), null.into_pred_node(), cmp),
+        ),
+    );
+    let proj = LogicalProjection::new(
+        join.into_plan_node(),
+        ListPred::new(
+            (0..left_schema_size)
+                .map(|x| ColumnRefPred::new(x).into_pred_node())
+                .chain(std::iter::once(mark))
+                .collect(),
+        ),
+    );
+    Some(proj.into_plan_node())
+}
+
 define_rule!(
     DepJoinPastProj,
     apply_dep_join_past_proj,
@@ -488,12 +655,9 @@ fn apply_dep_join_past_agg(
                         if expr.typ == DfPredType::Func(FuncType::Agg("count".to_string())) {
                             let expr_child = expr.child(0).child(0);
                             // Any count(constant)should be treated as `count(*)`
-                            if let DfPredType::Constant(constant_typ) = expr_child.typ {
+                            if let DfPredType::Constant(_) = expr_child.typ {
                                 return FuncPred::new(
-                                    FuncType::Scalar(
-                                        "coalesce".to_string(),
-                                        constant_typ.into_data_type(),
-                                    ),
+                                    FuncType::new_scalar("coalesce".to_string()),
                                     ListPred::new(vec![
                                         ColumnRefPred::new(x).into_pred_node(),
                                         ConstantPred::int64(0).into_pred_node(),
@@ -573,3 +737,190 @@ fn apply_dep_join_eliminate_at_scan(
         vec![]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::plan_nodes::LogicalScan;
+    use crate::testing::new_test_optimizer;
+
+    fn uncorrelated_subquery(sq_type: SubqueryType) -> ArcDfPlanNode {
+        let right = LogicalProjection::new(
+            LogicalScan::new("customer".into()).into_plan_node(),
+            ListPred::new(vec![ColumnRefPred::new(0).into_pred_node()]),
+        );
+        RawDependentJoin::new(
+            LogicalScan::new("region".into()).into_plan_node(),
+            right.into_plan_node(),
+            ConstantPred::bool(true).into_pred_node(),
+            ListPred::new(vec![]),
+            sq_type,
+        )
+        .into_plan_node()
+    }
+
+    fn region_key() -> DfPredNode {
+        Arc::unwrap_or_clone(ColumnRefPred::new(0).into_pred_node())
+    }
+
+    #[test]
+    fn scalar_to_join_with_singleton() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(DepInitialDistinct::new()));
+
+        let plan = test_optimizer
+            .optimize(uncorrelated_subquery(SubqueryType::Scalar))
+            .unwrap();
+        let join = LogicalJoin::from_plan_node(plan).unwrap();
+        assert_eq!(*join.join_type(), JoinType::Inner);
+        let singleton = LogicalSingleton::from_plan_node(join.right().unwrap_plan_node()).unwrap();
+        assert_eq!(
+            singleton.child().unwrap_plan_node().typ,
+            DfNodeType::Projection
+        );
+    }
+
+    #[test]
+    fn any_with_inequality_to_max() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(DepInitialDistinct::new()));
+
+        let plan = test_optimizer
+            .optimize(uncorrelated_subquery(SubqueryType::Any {
+                pred: region_key(),
+                op: BinOpType::Lt,
+            }))
+            .unwrap();
+        let proj = LogicalProjection::from_plan_node(plan).unwrap();
+        // region has 3 columns, plus the result of the comparison
+        assert_eq!(proj.exprs().len(), 4);
+        assert_eq!(proj.exprs().child(3).typ, DfPredType::Func(FuncType::Case));
+        let join = LogicalJoin::from_plan_node(proj.child().unwrap_plan_node()).unwrap();
+        assert_eq!(*join.join_type(), JoinType::Inner);
+        let agg = LogicalAgg::from_plan_node(join.right().unwrap_plan_node()).unwrap();
+        assert_eq!(
+            agg.exprs().child(0).typ,
+            DfPredType::Func(FuncType::Agg("max".to_string()))
+        );
+        // count(*) and count of the non-null values, to tell whether the subquery has nulls
+        assert_eq!(agg.exprs().len(), 3);
+    }
+
+    #[test]
+    fn all_with_inequality_is_null_with_nulls() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(DepInitialDistinct::new()));
+
+        let plan = test_optimizer
+            .optimize(uncorrelated_subquery(SubqueryType::All {
+                pred: region_key(),
+                op: BinOpType::Gt,
+            }))
+            .unwrap();
+        let proj = LogicalProjection::from_plan_node(plan).unwrap();
+        // case when count(*) = 0 then true
+        //      when not (#0 > max) then false
+        //      when count(col) < count(*) then null else #0 > max end
+        let mark = FuncPred::from_pred_node(proj.exprs().child(3)).unwrap();
+        assert_eq!(
+            mark.children().child(1),
+            ConstantPred::bool(true).into_pred_node()
+        );
+        let decided = FuncPred::from_pred_node(mark.children().child(2)).unwrap();
+        assert_eq!(
+            decided.children().child(0).typ,
+            DfPredType::Func(FuncType::Not)
+        );
+        assert_eq!(
+            decided.children().child(1),
+            ConstantPred::bool(false).into_pred_node()
+        );
+        let has_null = FuncPred::from_pred_node(decided.children().child(2)).unwrap();
+        assert_eq!(
+            has_null.children().child(1).typ,
+            DfPredType::Func(FuncType::new_scalar("nullif".to_string()))
+        );
+        let join = LogicalJoin::from_plan_node(proj.child().unwrap_plan_node()).unwrap();
+        let agg = LogicalAgg::from_plan_node(join.right().unwrap_plan_node()).unwrap();
+        assert_eq!(
+            agg.exprs().child(0).typ,
+            DfPredType::Func(FuncType::Agg("max".to_string()))
+        );
+    }
+
+    #[test]
+    fn quantified_comparison_with_each_operator() {
+        for (all, op, agg_func) in [
+            (false, BinOpType::Lt, Some("max")),
+            (false, BinOpType::Leq, Some("max")),
+            (false, BinOpType::Gt, Some("min")),
+            (false, BinOpType::Geq, Some("min")),
+            (false, BinOpType::Eq, None),
+            (false, BinOpType::Neq, None),
+            (true, BinOpType::Lt, Some("min")),
+            (true, BinOpType::Leq, Some("min")),
+            (true, BinOpType::Gt, Some("max")),
+            (true, BinOpType::Geq, Some("max")),
+            (true, BinOpType::Eq, None),
+            (true, BinOpType::Neq, None),
+        ] {
+            let mut test_optimizer = new_test_optimizer(Arc::new(DepInitialDistinct::new()));
+            let pred = region_key();
+            let sq_type = if all {
+                SubqueryType::All { pred, op }
+            } else {
+                SubqueryType::Any { pred, op }
+            };
+            let plan = test_optimizer
+                .optimize(uncorrelated_subquery(sq_type))
+                .unwrap();
+            let proj = LogicalProjection::from_plan_node(plan.clone());
+            let join = match &proj {
+                Some(proj) => LogicalJoin::from_plan_node(proj.child().unwrap_plan_node()),
+                None => LogicalJoin::from_plan_node(plan),
+            }
+            .unwrap();
+            match agg_func {
+                Some(agg_func) => {
+                    let agg = LogicalAgg::from_plan_node(join.right().unwrap_plan_node()).unwrap();
+                    assert_eq!(
+                        agg.exprs().child(0).typ,
+                        DfPredType::Func(FuncType::Agg(agg_func.to_string())),
+                        "{} {}",
+                        if all { "ALL" } else { "ANY" },
+                        op
+                    );
+                    // the mark is true, false or null the same way as the one of a mark join
+                    let mark = FuncPred::from_pred_node(proj.unwrap().exprs().child(3)).unwrap();
+                    assert_eq!(
+                        mark.children().child(1),
+                        ConstantPred::bool(all).into_pred_node()
+                    );
+                    let decided = FuncPred::from_pred_node(mark.children().child(2)).unwrap();
+                    assert_eq!(
+                        decided.children().child(1),
+                        ConstantPred::bool(!all).into_pred_node()
+                    );
+                }
+                None => assert_eq!(*join.join_type(), JoinType::LeftMark),
+            }
+        }
+    }
+
+    #[test]
+    fn all_with_equality_to_negated_mark_join() {
+        let mut test_optimizer = new_test_optimizer(Arc::new(DepInitialDistinct::new()));
+
+        let plan = test_optimizer
+            .optimize(uncorrelated_subquery(SubqueryType::All {
+                pred: region_key(),
+                op: BinOpType::Eq,
+            }))
+            .unwrap();
+        let proj = LogicalProjection::from_plan_node(plan).unwrap();
+        assert_eq!(proj.exprs().child(3).typ, DfPredType::Func(FuncType::Not));
+        let join = LogicalJoin::from_plan_node(proj.child().unwrap_plan_node()).unwrap();
+        assert_eq!(*join.join_type(), JoinType::LeftMark);
+        let cond = BinOpPred::from_pred_node(join.cond()).unwrap();
+        assert_eq!(cond.op_type(), BinOpType::Neq);
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Sideways information passing between the joins: an inner hash join whose build side only
+//! matches a small part of its probe side publishes the range of its build side keys, once it
+//! has read them, to a [`PhysicalRuntimeFilter`] on the scan the probe keys are read from, so
+//! that the rows which cannot match are dropped before reaching the joins above the scan.
+
+use std::sync::Arc;
+
+use optd_og_core::nodes::{PlanAnnotations, PlanNode, PlanNodeOrGroup};
+
+use crate::plan_nodes::{
+    ArcDfPlanNode, BuildSide, ColumnRefPred, ConstantPred, DfNodeType, DfReprPlanNode,
+    DfReprPredNode, JoinType, ListPred, PhysicalHashJoin, PhysicalProjection,
+    PhysicalRuntimeFilter,
+};
+use crate::properties::schema::Catalog;
+use crate::TableId;
+
+pub struct RuntimeFilterPlanner {
+    catalog: Arc<dyn Catalog>,
+    /// The runtime filters are added to the joins producing at most this fraction of the rows
+    /// of their probe side, `None` disables them.
+    max_selectivity: Option<f64>,
+}
+
+impl RuntimeFilterPlanner {
+    pub fn new(catalog: Arc<dyn Catalog>) -> Self {
+        Self {
+            catalog,
+            max_selectivity: None,
+        }
+    }
+
+    pub fn max_selectivity(&self) -> Option<f64> {
+        self.max_selectivity
+    }
+
+    pub fn set_max_selectivity(&mut self, max_selectivity: Option<f64>) {
+        self.max_selectivity = max_selectivity;
+    }
+
+    /// Adds the runtime filters to the selective hash joins of the plan, if enabled. The nodes
+    /// kept retain their costs and statistics in the returned meta map, and the filters get the
+    /// ones of the scans they filter.
+    pub fn add_runtime_filters(
+        &self,
+        plan: &ArcDfPlanNode,
+        meta: &PlanAnnotations,
+    ) -> (ArcDfPlanNode, PlanAnnotations) {
+        let Some(max_selectivity) = self.max_selectivity else {
+            return (plan.clone(), meta.clone());
+        };
+        let mut pass = RuntimeFilterPass {
+            catalog: self.catalog.as_ref(),
+            max_selectivity,
+            meta: PlanAnnotations::new(),
+            next_filter_id: 0,
+        };
+        let plan = pass.add_filters(plan, meta);
+        // the nodes replaced while pushing the filters down are no longer in the plan
+        let mut new_meta = PlanAnnotations::new();
+        retain_meta(&plan, &pass.meta, &mut new_meta);
+        (plan, new_meta)
+    }
+}
+
+fn retain_meta(plan: &ArcDfPlanNode, meta: &PlanAnnotations, new_meta: &mut PlanAnnotations) {
+    if let Some(node_meta) = meta.get(plan) {
+        new_meta.insert(plan, node_meta.clone());
+    }
+    for child in &plan.children {
+        retain_meta(&child.unwrap_plan_node(), meta, new_meta);
+    }
+}
+
+fn with_child(plan: &ArcDfPlanNode, idx: usize, child: ArcDfPlanNode) -> ArcDfPlanNode {
+    let mut children = plan.children.clone();
+    children[idx] = PlanNodeOrGroup::PlanNode(child);
+    Arc::new(PlanNode {
+        typ: plan.typ.clone(),
+        children,
+        predicates: plan.predicates.clone(),
+    })
+}
+
+struct RuntimeFilterPass<'a> {
+    catalog: &'a dyn Catalog,
+    max_selectivity: f64,
+    meta: PlanAnnotations,
+    next_filter_id: u64,
+}
+
+impl RuntimeFilterPass<'_> {
+    fn copy_meta(&mut self, from: &ArcDfPlanNode, to: &ArcDfPlanNode) {
+        if let Some(node_meta) = self.meta.get(from) {
+            self.meta.insert(to, node_meta.clone());
+        }
+    }
+
+    fn row_cnt(&self, node: &ArcDfPlanNode) -> Option<f64> {
+        self.meta.get(node).and_then(|meta| meta.row_cnt)
+    }
+
+    /// The number of columns of the nodes the filters are pushed through.
+    fn width(&self, node: &ArcDfPlanNode) -> Option<usize> {
+        match &node.typ {
+            DfNodeType::PhysicalScan => {
+                let table = ConstantPred::from_pred_node(node.predicates[0].clone())
+                    .unwrap()
+                    .value()
+                    .as_str();
+                Some(self.catalog.get(&TableId::new(&table)).len())
+            }
+            DfNodeType::PhysicalFilter
+            | DfNodeType::PhysicalSort
+            | DfNodeType::PhysicalRuntimeFilter => self.width(&node.child_rel(0)),
+            DfNodeType::PhysicalProjection => Some(
+                PhysicalProjection::from_plan_node(node.clone())
+                    .unwrap()
+                    .exprs()
+                    .len(),
+            ),
+            DfNodeType::PhysicalHashJoin(JoinType::Inner)
+            | DfNodeType::PhysicalNestedLoopJoin(JoinType::Inner) => {
+                Some(self.width(&node.child_rel(0))? + self.width(&node.child_rel(1))?)
+            }
+            _ => None,
+        }
+    }
+
+    fn add_filters(&mut self, plan: &ArcDfPlanNode, meta: &PlanAnnotations) -> ArcDfPlanNode {
+        let children = plan
+            .children
+            .iter()
+            .map(|child| self.add_filters(&child.unwrap_plan_node(), meta))
+            .collect::<Vec<_>>();
+        let changed = children
+            .iter()
+            .zip(&plan.children)
+            .any(|(new, old)| !Arc::ptr_eq(new, &old.unwrap_plan_node()));
+        let node = if changed {
+            Arc::new(PlanNode {
+                typ: plan.typ.clone(),
+                children: children
+                    .into_iter()
+                    .map(PlanNodeOrGroup::PlanNode)
+                    .collect(),
+                predicates: plan.predicates.clone(),
+            })
+        } else {
+            plan.clone()
+        };
+        if let Some(node_meta) = meta.get(plan) {
+            self.meta.insert(&node, node_meta.clone());
+        }
+        if let DfNodeType::PhysicalHashJoin(JoinType::Inner) = node.typ {
+            if let Some(node) = self.add_join_filter(PhysicalHashJoin(node.clone())) {
+                return node;
+            }
+        }
+        node
+    }
+
+    /// Filters the probe side of an inner hash join if it is selective enough.
+    fn add_join_filter(&mut self, join: PhysicalHashJoin) -> Option<ArcDfPlanNode> {
+        if join.runtime_filter().is_some() {
+            return None;
+        }
+        let (probe_idx, probe_keys) = match join.build_side() {
+            BuildSide::Left => (1, join.right_keys()),
+            BuildSide::Right => (0, join.left_keys()),
+        };
+        let probe = join.0.child_rel(probe_idx);
+        if self.row_cnt(&join.0)? > self.row_cnt(&probe)? * self.max_selectivity {
+            return None;
+        }
+        let keys = probe_keys
+            .to_vec()
+            .into_iter()
+            .map(|key| ColumnRefPred::from_pred_node(key).map(|col| col.index()))
+            .collect::<Option<Vec<_>>>()?;
+        let filter_id = self.next_filter_id;
+        let probe = self.push_filter(&probe, &keys, filter_id)?;
+        self.next_filter_id += 1;
+        let node = with_child(&join.with_runtime_filter(filter_id).0, probe_idx, probe);
+        self.copy_meta(&join.0, &node);
+        Some(node)
+    }
+
+    /// Places the filter on the scan the columns are read from, through the nodes which keep
+    /// the rows they do not drop unchanged, and the inner joins.
+    fn push_filter(
+        &mut self,
+        node: &ArcDfPlanNode,
+        keys: &[usize],
+        filter_id: u64,
+    ) -> Option<ArcDfPlanNode> {
+        let (child_idx, child) = match &node.typ {
+            DfNodeType::PhysicalScan => {
+                let keys = keys
+                    .iter()
+                    .map(|key| ColumnRefPred::new(*key).into_pred_node())
+                    .collect();
+                let filter = PhysicalRuntimeFilter::new(
+                    node.clone(),
+                    ListPred::new(keys),
+                    ConstantPred::uint64(filter_id).into_pred_node(),
+                )
+                .into_plan_node();
+                self.copy_meta(node, &filter);
+                return Some(filter);
+            }
+            DfNodeType::PhysicalFilter
+            | DfNodeType::PhysicalSort
+            | DfNodeType::PhysicalRuntimeFilter => {
+                (0, self.push_filter(&node.child_rel(0), keys, filter_id)?)
+            }
+            DfNodeType::PhysicalProjection => {
+                let exprs = PhysicalProjection::from_plan_node(node.clone())
+                    .unwrap()
+                    .exprs();
+                let keys = keys
+                    .iter()
+                    .map(|key| {
+                        ColumnRefPred::from_pred_node(exprs.child(*key)).map(|col| col.index())
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                (0, self.push_filter(&node.child_rel(0), &keys, filter_id)?)
+            }
+            DfNodeType::PhysicalHashJoin(JoinType::Inner)
+            | DfNodeType::PhysicalNestedLoopJoin(JoinType::Inner) => {
+                let left_width = self.width(&node.child_rel(0))?;
+                if keys.iter().all(|key| *key < left_width) {
+                    (0, self.push_filter(&node.child_rel(0), keys, filter_id)?)
+                } else if keys.iter().all(|key| *key >= left_width) {
+                    let keys = keys.iter().map(|key| key - left_width).collect::<Vec<_>>();
+                    (1, self.push_filter(&node.child_rel(1), &keys, filter_id)?)
+                } else {
+                    return None;
+                }
+            }
+            _ => return None,
+        };
+        let new_node = with_child(node, child_idx, child);
+        self.copy_meta(node, &new_node);
+        Some(new_node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use optd_og_core::cascades::GroupId;
+    use optd_og_core::nodes::PlanAnnotation;
+
+    use super::*;
+    use crate::cost::DfCostModel;
+    use crate::plan_nodes::LogicalScan;
+    use crate::testing::TpchCatalog;
+
+    fn scan(table: &str) -> ArcDfPlanNode {
+        let scan = LogicalScan::new(table.into()).into_plan_node();
+        Arc::new(PlanNode {
+            typ: DfNodeType::PhysicalScan,
+            children: vec![],
+            predicates: scan.predicates.clone(),
+        })
+    }
+
+    fn add_meta(meta: &mut PlanAnnotations, plan: &ArcDfPlanNode, group_id: usize, rows: f64) {
+        meta.insert(
+            plan,
+            PlanAnnotation::new(
+                GroupId(group_id),
+                DfCostModel::cost(1.0, 0.0),
+                Arc::new(DfCostModel::stat(rows)),
+                &DfCostModel::new(HashMap::new()),
+            ),
+        );
+    }
+
+    #[test]
+    fn filter_probe_scan_of_selective_join() {
+        let mut meta = PlanAnnotations::new();
+        let customer = scan("customer");
+        add_meta(&mut meta, &customer, 0, 10.0);
+        let orders = scan("orders");
+        add_meta(&mut meta, &orders, 1, 1000.0);
+        // c_custkey = o_custkey, building on customer
+        let join = PhysicalHashJoin::new(
+            customer,
+            orders,
+            ListPred::new(vec![ColumnRefPred::new(0).into_pred_node()]),
+            ListPred::new(vec![ColumnRefPred::new(1).into_pred_node()]),
+            JoinType::Inner,
+        )
+        .into_plan_node();
+        add_meta(&mut meta, &join, 2, 10.0);
+
+        let mut planner = RuntimeFilterPlanner::new(Arc::new(TpchCatalog));
+        let (plan, _) = planner.add_runtime_filters(&join, &meta);
+        assert!(Arc::ptr_eq(&plan, &join));
+
+        planner.set_max_selectivity(Some(0.1));
+        let (plan, new_meta) = planner.add_runtime_filters(&join, &meta);
+        let plan = PhysicalHashJoin::from_plan_node(plan).unwrap();
+        assert_eq!(plan.runtime_filter(), Some(0));
+        let filter =
+            PhysicalRuntimeFilter::from_plan_node(plan.right().unwrap_plan_node()).unwrap();
+        assert_eq!(filter.id(), 0);
+        assert_eq!(
+            filter.keys().to_vec(),
+            vec![ColumnRefPred::new(1).into_pred_node()]
+        );
+        assert_eq!(
+            filter.child().unwrap_plan_node().typ,
+            DfNodeType::PhysicalScan
+        );
+        assert_eq!(new_meta.len(), 4);
+        assert_eq!(new_meta.get(&filter.0).unwrap().group_id, GroupId(1));
+
+        planner.set_max_selectivity(Some(0.001));
+        let (plan, _) = planner.add_runtime_filters(&join, &meta);
+        assert!(Arc::ptr_eq(&plan, &join));
+    }
+}
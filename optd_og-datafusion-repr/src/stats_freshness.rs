@@ -0,0 +1,390 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use optd_og_core::cascades::GroupId;
+
+use crate::cost::adaptive_cost::RuntimeAdaptionStorageInner;
+use crate::TableId;
+
+/// When the statistics of a table should be refreshed. Like the autoanalyze of postgres, a table
+/// is due once more than `min_changed_rows + changed_row_fraction * rows` of its rows changed.
+#[derive(Clone, Debug)]
+pub struct StatsRefreshPolicy {
+    pub min_changed_rows: usize,
+    pub changed_row_fraction: f64,
+    /// Refresh the statistics of a table analyzed longer ago than this, whether or not it
+    /// changed.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for StatsRefreshPolicy {
+    fn default() -> Self {
+        Self {
+            min_changed_rows: 50,
+            changed_row_fraction: 0.1,
+            max_age: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StatsRefreshReason {
+    /// The number of rows changed since the statistics were collected, counting both the changes
+    /// reported by the host and the difference between the estimated and the scanned row count.
+    RowsChanged {
+        stats_rows: usize,
+        changed_rows: usize,
+    },
+    /// The statistics are older than [`StatsRefreshPolicy::max_age`].
+    Stale { age: Duration },
+    /// The host invalidated the statistics, e.g., after ingesting data into the table.
+    Invalidated,
+}
+
+impl Display for StatsRefreshReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RowsChanged {
+                stats_rows,
+                changed_rows,
+            } => write!(f, "{changed_rows} of {stats_rows} rows changed"),
+            Self::Stale { age } => write!(f, "analyzed {}s ago", age.as_secs()),
+            Self::Invalidated => write!(f, "invalidated"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsRefreshRecommendation {
+    pub table: TableId,
+    pub reason: StatsRefreshReason,
+}
+
+impl Display for StatsRefreshRecommendation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "refresh statistics of {}: {}", self.table, self.reason)
+    }
+}
+
+type RefreshCallback = Box<dyn FnMut(&StatsRefreshRecommendation) + Send + Sync>;
+
+#[derive(Default)]
+struct TableFreshness {
+    analyzed_at: Option<Instant>,
+    /// Incremented on each analyze of the table, so that the statistics of a table can be told
+    /// apart from the ones collected before.
+    version: u64,
+    /// Whether the host invalidated the statistics since the last analyze.
+    invalidated: bool,
+    /// The row count of the statistics, or the estimated row count of the last scan if the
+    /// host never reported an analyze.
+    stats_rows: usize,
+    /// Rows inserted, updated or deleted since the last analyze, as reported by the host.
+    changed_rows: usize,
+    /// The row count of the last scan of the table, from the runtime statistics.
+    runtime_rows: Option<usize>,
+    /// Whether the refresh callback was invoked since the last analyze.
+    notified: bool,
+}
+
+/// Tracks how fresh the statistics of each table are. The host reports when it analyzes a table
+/// and how many rows it changes, the optimizer records the scans of the plans it produces and
+/// compares their estimated row counts with the runtime statistics. The tables are named as in
+/// the catalog, and normalized like [`TableId`] is.
+#[derive(Default)]
+pub struct StatsFreshnessTracker {
+    policy: StatsRefreshPolicy,
+    tables: HashMap<TableId, TableFreshness>,
+    /// The table scanned by each scan group of the optimized plans.
+    scan_groups: HashMap<GroupId, TableId>,
+    callback: Option<RefreshCallback>,
+}
+
+impl StatsFreshnessTracker {
+    pub fn new(policy: StatsRefreshPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn policy(&self) -> &StatsRefreshPolicy {
+        &self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: StatsRefreshPolicy) {
+        self.policy = policy;
+    }
+
+    /// Invoke `callback` once for each table that becomes due for a refresh, so that the host
+    /// can schedule an ANALYZE. It is invoked again for the table after the next analyze.
+    pub fn set_refresh_callback(
+        &mut self,
+        callback: impl FnMut(&StatsRefreshRecommendation) + Send + Sync + 'static,
+    ) {
+        self.callback = Some(Box::new(callback));
+    }
+
+    pub fn clear_refresh_callback(&mut self) {
+        self.callback = None;
+    }
+
+    /// The statistics of `table` were collected now, over `row_cnt` rows.
+    pub fn record_analyze(&mut self, table: &str, row_cnt: usize) {
+        let version = self.stats_version(table) + 1;
+        self.tables.insert(
+            TableId::new(table),
+            TableFreshness {
+                analyzed_at: Some(Instant::now()),
+                version,
+                stats_rows: row_cnt,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// The number of analyzes of `table` reported by the host, 0 if it was never analyzed.
+    pub fn stats_version(&self, table: &str) -> u64 {
+        self.tables
+            .get(&TableId::new(table))
+            .map_or(0, |freshness| freshness.version)
+    }
+
+    /// How long ago the statistics of `table` were collected.
+    pub fn stats_age(&self, table: &str) -> Option<Duration> {
+        Some(
+            self.tables
+                .get(&TableId::new(table))?
+                .analyzed_at?
+                .elapsed(),
+        )
+    }
+
+    /// Mark the statistics of `table` as out of date, e.g., after the host ingested data into
+    /// it, until the next analyze. The refresh callback is invoked right away, even if it was
+    /// already invoked for the table, so that the host can re-collect the statistics.
+    pub fn invalidate(&mut self, table: &str) {
+        let freshness = self.tables.entry(TableId::new(table)).or_default();
+        freshness.invalidated = true;
+        freshness.notified = false;
+        self.notify();
+    }
+
+    /// Whether the host reported an analyze of `table`, i.e., the cost model has its statistics.
+    pub fn is_analyzed(&self, table: &str) -> bool {
+        self.tables
+            .get(&TableId::new(table))
+            .is_some_and(|freshness| freshness.analyzed_at.is_some())
+    }
+
+    /// `row_cnt` rows of `table` were inserted, updated or deleted.
+    pub fn record_row_changes(&mut self, table: &str, row_cnt: usize) {
+        let freshness = self.tables.entry(TableId::new(table)).or_default();
+        freshness.changed_rows = freshness.changed_rows.saturating_add(row_cnt);
+    }
+
+    /// A plan scans `table` in group `group_id`, estimating `estimated_rows` rows.
+    pub(crate) fn record_scan(&mut self, group_id: GroupId, table: &str, estimated_rows: usize) {
+        let table = TableId::new(table);
+        self.scan_groups.insert(group_id, table.clone());
+        let freshness = self.tables.entry(table).or_default();
+        if freshness.analyzed_at.is_none() {
+            freshness.stats_rows = estimated_rows;
+        }
+    }
+
+    /// Take the row counts of the scans from the runtime statistics.
+    pub(crate) fn ingest_runtime_statistics(&mut self, runtime: &RuntimeAdaptionStorageInner) {
+        for (group_id, table) in &self.scan_groups {
+            if let Some((row_cnt, _)) = runtime.history.get(group_id) {
+                if let Some(freshness) = self.tables.get_mut(table) {
+                    freshness.runtime_rows = Some(*row_cnt);
+                }
+            }
+        }
+    }
+
+    fn recommendation(&self, freshness: &TableFreshness) -> Option<StatsRefreshReason> {
+        if freshness.invalidated {
+            return Some(StatsRefreshReason::Invalidated);
+        }
+        let drift = freshness
+            .runtime_rows
+            .map_or(0, |rows| rows.abs_diff(freshness.stats_rows));
+        let changed_rows = freshness.changed_rows.max(drift);
+        let threshold = self.policy.min_changed_rows as f64
+            + self.policy.changed_row_fraction * freshness.stats_rows as f64;
+        if changed_rows as f64 > threshold {
+            return Some(StatsRefreshReason::RowsChanged {
+                stats_rows: freshness.stats_rows,
+                changed_rows,
+            });
+        }
+        let age = freshness.analyzed_at?.elapsed();
+        match self.policy.max_age {
+            Some(max_age) if age > max_age => Some(StatsRefreshReason::Stale { age }),
+            _ => None,
+        }
+    }
+
+    /// The tables whose statistics should be refreshed, ordered by name.
+    pub fn recommendations(&self) -> Vec<StatsRefreshRecommendation> {
+        let mut recommendations = self
+            .tables
+            .iter()
+            .filter_map(|(table, freshness)| {
+                self.recommendation(freshness)
+                    .map(|reason| StatsRefreshRecommendation {
+                        table: table.clone(),
+                        reason,
+                    })
+            })
+            .collect::<Vec<_>>();
+        recommendations.sort_by(|a, b| a.table.cmp(&b.table));
+        recommendations
+    }
+
+    /// Why the statistics of `table` should be refreshed, if they should.
+    pub fn recommendation_for(&self, table: &str) -> Option<StatsRefreshRecommendation> {
+        let table = TableId::new(table);
+        let reason = self.recommendation(self.tables.get(&table)?)?;
+        Some(StatsRefreshRecommendation { table, reason })
+    }
+
+    /// Invoke the callback for the tables which became due since the last call.
+    pub(crate) fn notify(&mut self) {
+        if self.callback.is_none() {
+            return;
+        }
+        for recommendation in self.recommendations() {
+            let freshness = self.tables.get_mut(&recommendation.table).unwrap();
+            if !freshness.notified {
+                freshness.notified = true;
+                (self.callback.as_mut().unwrap())(&recommendation);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn refresh_after_row_changes() {
+        let mut tracker = StatsFreshnessTracker::default();
+        let notified = Arc::new(Mutex::new(vec![]));
+        let notified_clone = notified.clone();
+        tracker.set_refresh_callback(move |recommendation| {
+            notified_clone
+                .lock()
+                .unwrap()
+                .push(recommendation.table.to_string())
+        });
+
+        assert!(!tracker.is_analyzed("t1"));
+        tracker.record_analyze("t1", 1000);
+        assert!(tracker.is_analyzed("t1"));
+        assert!(tracker.is_analyzed("T1"));
+        assert_eq!(tracker.stats_version("t1"), 1);
+        tracker.record_row_changes("t1", 150);
+        assert!(tracker.recommendations().is_empty());
+        tracker.record_row_changes("t1", 1);
+        tracker.notify();
+        tracker.notify();
+        assert_eq!(
+            tracker.recommendations(),
+            vec![StatsRefreshRecommendation {
+                table: TableId::new("t1"),
+                reason: StatsRefreshReason::RowsChanged {
+                    stats_rows: 1000,
+                    changed_rows: 151
+                }
+            }]
+        );
+        assert_eq!(*notified.lock().unwrap(), vec!["t1".to_string()]);
+
+        tracker.record_analyze("t1", 1151);
+        assert!(tracker.recommendations().is_empty());
+        assert_eq!(tracker.stats_version("t1"), 2);
+    }
+
+    #[test]
+    fn refresh_after_invalidation() {
+        let mut tracker = StatsFreshnessTracker::default();
+        let notified = Arc::new(Mutex::new(vec![]));
+        let notified_clone = notified.clone();
+        tracker.set_refresh_callback(move |recommendation| {
+            notified_clone
+                .lock()
+                .unwrap()
+                .push(recommendation.reason.clone())
+        });
+
+        tracker.record_analyze("t1", 1000);
+        tracker.record_row_changes("t1", 500);
+        tracker.notify();
+        tracker.invalidate("t1");
+        assert_eq!(
+            *notified.lock().unwrap(),
+            vec![
+                StatsRefreshReason::RowsChanged {
+                    stats_rows: 1000,
+                    changed_rows: 500
+                },
+                StatsRefreshReason::Invalidated
+            ]
+        );
+        assert_eq!(
+            tracker.recommendation_for("t1").unwrap().reason,
+            StatsRefreshReason::Invalidated
+        );
+        assert!(tracker.recommendation_for("t2").is_none());
+
+        tracker.record_analyze("t1", 1500);
+        assert!(tracker.recommendation_for("t1").is_none());
+        assert_eq!(tracker.stats_version("t1"), 2);
+    }
+
+    #[test]
+    fn refresh_after_runtime_drift() {
+        let mut tracker = StatsFreshnessTracker::default();
+        let group_id = GroupId(1);
+        tracker.record_scan(group_id, "t1", 100);
+        let mut runtime = RuntimeAdaptionStorageInner::default();
+        runtime.history.insert(group_id, (120, 1));
+        tracker.ingest_runtime_statistics(&runtime);
+        assert!(tracker.recommendations().is_empty());
+        runtime.history.insert(group_id, (1000, 2));
+        tracker.ingest_runtime_statistics(&runtime);
+        assert_eq!(
+            tracker.recommendations()[0].reason,
+            StatsRefreshReason::RowsChanged {
+                stats_rows: 100,
+                changed_rows: 900
+            }
+        );
+    }
+
+    #[test]
+    fn refresh_stale_stats() {
+        let mut tracker = StatsFreshnessTracker::new(StatsRefreshPolicy {
+            max_age: Some(Duration::ZERO),
+            ..Default::default()
+        });
+        tracker.record_analyze("t1", 10);
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(matches!(
+            tracker.recommendations()[0].reason,
+            StatsRefreshReason::Stale { .. }
+        ));
+    }
+}
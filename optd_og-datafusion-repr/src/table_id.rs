@@ -0,0 +1,102 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::fmt::Display;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// The normalized name of a table, which the catalog, the column refs of the memo and the
+/// statistics of the cost model are keyed by. The unquoted parts of the name are folded to lower
+/// case and the quoted ones are kept as they are, without their quotes, so that `LINEITEM`,
+/// `lineitem` and `"lineitem"` are the same table, but `"Orders"` and `orders` are not.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct TableId(Arc<str>);
+
+impl TableId {
+    pub fn new(name: &str) -> Self {
+        let mut normalized = String::with_capacity(name.len());
+        let mut chars = name.chars().peekable();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            match c {
+                // A doubled quote within a quoted identifier is an escaped quote.
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    normalized.push('"');
+                }
+                '"' => quoted = !quoted,
+                c if quoted => normalized.push(c),
+                c => normalized.extend(c.to_lowercase()),
+            }
+        }
+        Self(normalized.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for TableId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for TableId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<&str> for TableId {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for TableId {
+    fn from(name: String) -> Self {
+        Self::new(&name)
+    }
+}
+
+impl From<Arc<str>> for TableId {
+    fn from(name: Arc<str>) -> Self {
+        Self::new(&name)
+    }
+}
+
+impl From<TableId> for String {
+    fn from(id: TableId) -> Self {
+        id.0.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_case_and_quotes() {
+        assert_eq!(TableId::new("lineitem").as_str(), "lineitem");
+        assert_eq!(TableId::new("LineItem"), TableId::new("lineitem"));
+        assert_eq!(TableId::new("\"lineitem\""), TableId::new("LINEITEM"));
+        assert_eq!(TableId::new("\"LineItem\"").as_str(), "LineItem");
+        assert_ne!(TableId::new("\"LineItem\""), TableId::new("lineitem"));
+        assert_eq!(TableId::new("SALES.\"Orders\"").as_str(), "sales.Orders");
+        assert_ne!(
+            TableId::new("sales.\"Orders\""),
+            TableId::new("sales.orders")
+        );
+        assert_eq!(TableId::new("\"a\"\"b\"").as_str(), "a\"b");
+        assert_ne!(TableId::new("sales.orders"), TableId::new("orders"));
+    }
+}
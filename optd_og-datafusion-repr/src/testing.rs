@@ -9,11 +9,14 @@ mod tpch_catalog;
 use std::sync::Arc;
 
 use optd_og_core::heuristics::{ApplyOrder, HeuristicsOptimizer, HeuristicsOptimizerOptions};
+use optd_og_core::logical_property::LogicalPropertyBuilderAny;
 use optd_og_core::rules::Rule;
 
-use self::tpch_catalog::TpchCatalog;
+pub use self::tpch_catalog::TpchCatalog;
 use crate::plan_nodes::DfNodeType;
+use crate::properties::column_ref::ColumnRefPropertyBuilder;
 use crate::properties::schema::SchemaPropertyBuilder;
+use crate::properties::uniqueness::UniquenessPropertyBuilder;
 
 /// Create a "dummy" optimizer preloaded with the TPC-H catalog for testing
 /// Note: Only provides the schema property currently
@@ -32,3 +35,26 @@ pub fn new_test_optimizer(
         Arc::new([]),
     )
 }
+
+/// Create a "dummy" optimizer like [`new_test_optimizer`], which also provides the column ref and
+/// uniqueness properties, for the rules relying on the keys of the relations.
+pub fn new_test_optimizer_with_keys(
+    rule: Arc<dyn Rule<DfNodeType, HeuristicsOptimizer<DfNodeType>>>,
+) -> HeuristicsOptimizer<DfNodeType> {
+    let dummy_catalog = Arc::new(TpchCatalog);
+    let property_builders: Arc<[Box<dyn LogicalPropertyBuilderAny<DfNodeType>>]> = Arc::new([
+        Box::new(SchemaPropertyBuilder::new(dummy_catalog.clone())),
+        Box::new(ColumnRefPropertyBuilder::new(dummy_catalog.clone())),
+        Box::new(UniquenessPropertyBuilder::new(dummy_catalog)),
+    ]);
+
+    HeuristicsOptimizer::new_with_rules(
+        vec![rule],
+        HeuristicsOptimizerOptions {
+            apply_order: ApplyOrder::TopDown,
+            enable_physical_prop_passthrough: true,
+        },
+        property_builders,
+        Arc::new([]),
+    )
+}
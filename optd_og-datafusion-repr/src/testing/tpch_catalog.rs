@@ -7,12 +7,13 @@ use core::panic;
 
 use crate::plan_nodes::ConstantType;
 use crate::properties::schema::{Catalog, Field, Schema};
+use crate::TableId;
 
 pub struct TpchCatalog;
 
 impl Catalog for TpchCatalog {
-    fn get(&self, name: &str) -> Schema {
-        match name {
+    fn get(&self, name: &TableId) -> Schema {
+        match name.as_str() {
             "region" => Schema {
                 fields: vec![
                     Field {
@@ -139,4 +140,11 @@ impl Catalog for TpchCatalog {
             }
         }
     }
+
+    fn primary_key(&self, name: &TableId) -> Option<Vec<usize>> {
+        match name.as_str() {
+            "region" | "customer" | "orders" => Some(vec![0]),
+            _ => None,
+        }
+    }
 }
@@ -4,6 +4,7 @@
 // https://opensource.org/licenses/MIT.
 
 pub mod counter;
+pub mod equi_depth;
 pub mod hyperloglog;
 pub mod misragries;
 pub mod murmur2;
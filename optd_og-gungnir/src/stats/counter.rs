@@ -64,6 +64,18 @@ where
         self.total_count += other.total_count;
     }
 
+    /// Merges the Counter of another partition of the data into the current one, scaling its
+    /// counts by `weight`, e.g., the inverse of the sampling rate of the partition. Unlike
+    /// [`Counter::merge`], the elements only tracked by `other` are tracked from now on, without
+    /// their occurrences in the partitions that did not track them.
+    pub fn merge_weighted(&mut self, other: &Counter<T>, weight: f64) {
+        let scale = |occ: i32| (occ as f64 * weight).round() as i32;
+        for (key, &occ) in &other.counts {
+            *self.counts.entry(key.clone()).or_insert(0) += scale(occ);
+        }
+        self.total_count += scale(other.total_count);
+    }
+
     /// Returns the frequencies of the most common values.
     pub fn frequencies(&self) -> HashMap<T, f64> {
         self.counts
@@ -202,4 +214,20 @@ mod tests {
             );
         });
     }
+    #[test]
+    fn merge_weighted() {
+        let mut mcv = Counter::<i32>::new(&[0, 1]);
+        mcv.aggregate(&[0, 0, 1, 2]);
+        let mut other = Counter::<i32>::new(&[1, 3]);
+        other.aggregate(&[1, 3, 3, 4]);
+
+        mcv.merge_weighted(&other, 2.0);
+
+        let mcv_freq = mcv.frequencies();
+        assert_eq!(mcv.total_count, 12);
+        assert_eq!(mcv_freq.len(), 3);
+        assert_eq!(mcv_freq[&0], 2.0 / 12.0);
+        assert_eq!(mcv_freq[&1], 3.0 / 12.0);
+        assert_eq!(mcv_freq[&3], 4.0 / 12.0);
+    }
 }
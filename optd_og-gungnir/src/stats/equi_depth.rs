@@ -0,0 +1,283 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Implementation of an equi-depth histogram, whose buckets hold the same number of values, so
+//! that the ranges with many values are split into narrow buckets.
+//! The histogram is built incrementally from batches of values, and the histograms of the
+//! partitions of a table are merged, by re-bucketing the buckets of both assuming the values are
+//! spread uniformly within each bucket.
+
+use std::marker::PhantomData;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::stats::tdigest::IntoFloat;
+
+pub const DEFAULT_NUM_BUCKETS: usize = 100;
+
+/// The number of bisection steps to find a quantile, which is precise enough for any range of
+/// f64 values.
+const QUANTILE_SEARCH_STEPS: usize = 64;
+
+/// A bucket of the histogram: the values between `lower` and `upper`, both included.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bucket {
+    pub lower: f64,
+    pub upper: f64,
+    /// The number of values in the bucket, which is fractional after a merge.
+    pub weight: f64,
+}
+
+impl Bucket {
+    // The weight of the values of the bucket <= v.
+    fn weight_leq(&self, v: f64) -> f64 {
+        if v >= self.upper {
+            self.weight
+        } else if v < self.lower {
+            0.0
+        } else {
+            self.weight * (v - self.lower) / (self.upper - self.lower)
+        }
+    }
+}
+
+/// The equi-depth histogram structure to query quantiles and CDFs.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct EquiDepthHistogram<T: IntoFloat> {
+    /// The buckets, sorted by their bounds.
+    buckets: Vec<Bucket>,
+    /// The number of buckets the values are split into.
+    num_buckets: usize,
+    /// Number of values in the histogram (sum of all buckets).
+    total_weight: f64,
+
+    /// The number of rows the values come from, including the rows whose values are not in the
+    /// histogram, e.g., the most common values, to normalize the CDF against.
+    pub norm_weight: usize,
+
+    data_type: PhantomData<T>, // For type checker.
+}
+
+// Self-contained implementation of the equi-depth histogram.
+impl<T> EquiDepthHistogram<T>
+where
+    T: IntoFloat,
+{
+    /// Creates and initializes a new empty histogram.
+    pub fn new(num_buckets: usize) -> Self {
+        assert!(num_buckets > 0);
+
+        EquiDepthHistogram {
+            buckets: Vec::new(),
+            num_buckets,
+            total_weight: 0.0,
+
+            norm_weight: 0,
+            data_type: PhantomData,
+        }
+    }
+
+    pub fn buckets(&self) -> &[Bucket] {
+        &self.buckets
+    }
+
+    pub fn total_weight(&self) -> f64 {
+        self.total_weight
+    }
+
+    /// Ingests an array of non-NaN values into the histogram.
+    pub fn merge_values(&mut self, values: &[T]) {
+        let values = values
+            .iter()
+            .map(|val| val.to_float())
+            .sorted_by(|a, b| a.partial_cmp(b).unwrap())
+            .collect_vec();
+        let bucket_size = values.len().div_ceil(self.num_buckets).max(1);
+        let buckets = values
+            .chunks(bucket_size)
+            .map(|chunk| Bucket {
+                lower: chunk[0],
+                upper: chunk[chunk.len() - 1],
+                weight: chunk.len() as f64,
+            })
+            .collect_vec();
+        self.merge_buckets(buckets, values.len() as f64);
+    }
+
+    /// Merges two histograms together.
+    /// Particularly useful for parallel execution.
+    pub fn merge(&mut self, other: &EquiDepthHistogram<T>) {
+        self.merge_buckets(other.buckets.clone(), other.total_weight);
+    }
+
+    fn merge_buckets(&mut self, buckets: Vec<Bucket>, weight: f64) {
+        if buckets.is_empty() {
+            return;
+        }
+        if self.buckets.is_empty() {
+            self.buckets = buckets;
+            self.total_weight = weight;
+            return;
+        }
+        let mut merged = std::mem::take(&mut self.buckets);
+        merged.extend(buckets);
+        self.total_weight += weight;
+        self.buckets = self.rebucket(&merged);
+    }
+
+    // Splits the values of the (possibly overlapping) buckets into num_buckets buckets of the
+    // same weight.
+    fn rebucket(&self, buckets: &[Bucket]) -> Vec<Bucket> {
+        let lower = buckets
+            .iter()
+            .map(|b| b.lower)
+            .fold(f64::INFINITY, f64::min);
+        let upper = buckets
+            .iter()
+            .map(|b| b.upper)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let mut bounds = vec![lower];
+        for k in 1..self.num_buckets {
+            let target = self.total_weight * k as f64 / self.num_buckets as f64;
+            bounds.push(search_weight(buckets, target, lower, upper));
+        }
+        bounds.push(upper);
+
+        let mut weight_below = 0.0;
+        let mut rebucketed = Vec::with_capacity(self.num_buckets);
+        for (bucket_lower, bucket_upper) in bounds.into_iter().tuple_windows() {
+            let weight_leq = weight_leq(buckets, bucket_upper);
+            let weight = weight_leq - weight_below;
+            weight_below = weight_leq;
+            if weight > 0.0 {
+                rebucketed.push(Bucket {
+                    lower: bucket_lower,
+                    upper: bucket_upper,
+                    weight,
+                });
+            }
+        }
+        rebucketed
+    }
+
+    /// Obtains a given quantile from the histogram.
+    /// Returns 0.0 if the histogram is empty.
+    /// Note: This is *not* normalized with norm_weight.
+    pub fn quantile(&self, q: f64) -> f64 {
+        let (Some(first), Some(last)) = (self.buckets.first(), self.buckets.last()) else {
+            return 0.0;
+        };
+        search_weight(
+            &self.buckets,
+            q * self.total_weight,
+            first.lower,
+            last.upper,
+        )
+    }
+
+    /// Obtains the CDF corresponding to a given value.
+    /// Returns 0.0 if the histogram is empty.
+    /// Note: This is *not* normalized with norm_weight.
+    pub fn cdf(&self, v: &T) -> f64 {
+        if self.total_weight == 0.0 {
+            return 0.0;
+        }
+        weight_leq(&self.buckets, v.to_float()) / self.total_weight
+    }
+}
+
+// The weight of the values of the buckets <= v.
+fn weight_leq(buckets: &[Bucket], v: f64) -> f64 {
+    buckets.iter().map(|b| b.weight_leq(v)).sum()
+}
+
+// Finds the smallest value in [lower, upper] such that the weight of the values of the buckets
+// <= it is at least target.
+fn search_weight(buckets: &[Bucket], target: f64, mut lower: f64, mut upper: f64) -> f64 {
+    if weight_leq(buckets, lower) >= target {
+        return lower;
+    }
+    for _ in 0..QUANTILE_SEARCH_STEPS {
+        let mid = lower + (upper - lower) / 2.0;
+        if weight_leq(buckets, mid) >= target {
+            upper = mid;
+        } else {
+            lower = mid;
+        }
+    }
+    upper
+}
+
+// Start of unit testing section.
+#[cfg(test)]
+mod tests {
+    use ordered_float::OrderedFloat;
+
+    use super::EquiDepthHistogram;
+
+    fn values(range: std::ops::Range<i32>) -> Vec<OrderedFloat<f64>> {
+        range.map(|v| OrderedFloat(v as f64)).collect()
+    }
+
+    fn assert_close(obtained: f64, expected: f64, error: f64) {
+        assert!(
+            (obtained - expected).abs() <= error,
+            "{} is not {} +/- {}",
+            obtained,
+            expected,
+            error
+        );
+    }
+
+    #[test]
+    fn equi_depth_uniform() {
+        let mut histogram = EquiDepthHistogram::new(10);
+        histogram.merge_values(&values(0..1000));
+        assert_eq!(histogram.buckets().len(), 10);
+        assert_eq!(histogram.total_weight(), 1000.0);
+        assert!(histogram.buckets().iter().all(|b| b.weight == 100.0));
+
+        assert_eq!(histogram.cdf(&OrderedFloat(-1.0)), 0.0);
+        assert_close(histogram.cdf(&OrderedFloat(499.0)), 0.5, 0.01);
+        assert_eq!(histogram.cdf(&OrderedFloat(999.0)), 1.0);
+        assert_close(histogram.quantile(0.25), 250.0, 5.0);
+        assert_close(histogram.quantile(0.9), 900.0, 5.0);
+    }
+
+    #[test]
+    fn equi_depth_merge() {
+        let mut histogram = EquiDepthHistogram::new(10);
+        histogram.merge_values(&values(0..500));
+        let mut other = EquiDepthHistogram::new(10);
+        other.merge_values(&values(500..1000));
+        histogram.merge(&other);
+        assert_eq!(histogram.buckets().len(), 10);
+        assert_eq!(histogram.total_weight(), 1000.0);
+        assert_close(histogram.cdf(&OrderedFloat(249.0)), 0.25, 0.01);
+        assert_close(histogram.cdf(&OrderedFloat(749.0)), 0.75, 0.01);
+        assert_close(histogram.quantile(0.5), 500.0, 5.0);
+    }
+
+    #[test]
+    fn equi_depth_skewed() {
+        // half of the values are 0, so the histogram has narrow buckets around 0
+        let mut skewed = vec![OrderedFloat(0.0); 500];
+        skewed.extend(values(1..501));
+        let mut histogram = EquiDepthHistogram::new(10);
+        histogram.merge_values(&skewed);
+        assert_close(histogram.cdf(&OrderedFloat(0.0)), 0.5, 0.01);
+        assert_eq!(histogram.quantile(0.3), 0.0);
+        assert_close(histogram.quantile(0.75), 250.0, 5.0);
+    }
+
+    #[test]
+    fn equi_depth_empty() {
+        let histogram = EquiDepthHistogram::<OrderedFloat<f64>>::new(10);
+        assert_eq!(histogram.cdf(&OrderedFloat(1.0)), 0.0);
+        assert_eq!(histogram.quantile(0.5), 0.0);
+    }
+}
@@ -14,6 +14,7 @@ use std::cmp::max;
 use std::marker::PhantomData;
 
 use optd_og_core::nodes::Value;
+use serde::{Deserialize, Serialize};
 
 use crate::stats::murmur2::murmur_hash;
 
@@ -26,7 +27,10 @@ pub trait ByteSerializable {
 
 /// The HyperLogLog (HLL) structure to provide a statistical estimate of NDistinct.
 /// For safety reasons, HLLs can only count elements of the same ByteSerializable type.
-#[derive(Clone)]
+/// HLLs are serializable so that the HLLs of the partitions of a table can be built by different
+/// workers and merged.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
 pub struct HyperLogLog<T: ByteSerializable> {
     registers: Vec<u8>, // The buckets to estimate HLL on (i.e. upper p bits).
     precision: u8,      // The precision (p) of our HLL; 4 <= p <= 16.
@@ -135,7 +139,8 @@ where
         data.for_each(|e| self.process(e));
     }
 
-    /// Merges two HLLs together and returns a new one.
+    /// Merges two HLLs together, so that the HLL estimates the n_distinct of the union of
+    /// the elements seen by both.
     /// Particularly useful for parallel execution.
     pub fn merge(&mut self, other: &HyperLogLog<T>) {
         assert!(self.precision == other.precision);
@@ -79,6 +79,8 @@ impl IntoFloat for Value {
             Value::Bool(v) => *v as i64 as f64,
             Value::String(v) => arith_encoder::encode(v),
             Value::Date32(v) => *v as f64,
+            Value::Decimal128(v) => v.to_f64(),
+            Value::Timestamp(v) | Value::Time(v) => *v as f64,
             _ => unreachable!(),
         }
     }
@@ -118,7 +118,10 @@ impl DatafusionDBMS {
         Ok(ctx)
     }
 
-    async fn execute(ctx: &SessionContext, sql: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    pub(crate) async fn execute(
+        ctx: &SessionContext,
+        sql: &str,
+    ) -> anyhow::Result<Vec<Vec<String>>> {
         let sql = unescape_input(sql)?;
         let dialect = Box::new(GenericDialect);
         let statements = DFParser::parse_sql_with_dialect(&sql, dialect.as_ref())?;
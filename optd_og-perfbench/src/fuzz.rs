@@ -0,0 +1,205 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Differential testing of the optimizer. Random queries over a seeded schema are run both with
+//! optd_og and with the native DataFusion planner, and the results must be the same.
+
+use anyhow::{bail, Context};
+use datafusion::execution::context::SessionContext;
+use optd_og_datafusion_bridge::create_df_context;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::datafusion_dbms::DatafusionDBMS;
+
+const NUM_COLUMNS: usize = 3;
+const MAX_VALUE: i32 = 10;
+
+#[derive(Clone, Debug)]
+pub struct FuzzConfig {
+    pub seed: u64,
+    pub num_queries: usize,
+    pub num_tables: usize,
+    pub num_rows: usize,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            seed: 15721,
+            num_queries: 100,
+            num_tables: 3,
+            num_rows: 20,
+        }
+    }
+}
+
+/// Run `config.num_queries` random queries and fail on the first one whose result differs from
+/// the result of DataFusion. The error contains the seed and the query to reproduce it.
+pub async fn run_fuzz(config: &FuzzConfig) -> anyhow::Result<()> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let optd_og_ctx = create_df_context(None, None, None, false, false, false, None)
+        .await?
+        .ctx;
+    let df_ctx = SessionContext::new();
+    for stmt in gen_schema(&mut rng, config) {
+        DatafusionDBMS::execute(&optd_og_ctx, &stmt).await?;
+        DatafusionDBMS::execute(&df_ctx, &stmt).await?;
+    }
+
+    for query_idx in 0..config.num_queries {
+        let sql = gen_query(&mut rng, config.num_tables);
+        let mut expected = DatafusionDBMS::execute(&df_ctx, &sql)
+            .await
+            .with_context(|| format!("datafusion failed on query {}: {}", query_idx, sql))?;
+        let mut actual = DatafusionDBMS::execute(&optd_og_ctx, &sql)
+            .await
+            .with_context(|| {
+                format!(
+                    "optd_og failed on query {} (seed {}): {}",
+                    query_idx, config.seed, sql
+                )
+            })?;
+        // The queries without an ORDER BY may return the rows in any order.
+        expected.sort();
+        actual.sort();
+        if expected != actual {
+            bail!(
+                "result mismatch on query {} (seed {}): {}\nexpected {} rows: {:?}\ngot {} rows: {:?}",
+                query_idx,
+                config.seed,
+                sql,
+                expected.len(),
+                expected,
+                actual.len(),
+                actual
+            );
+        }
+        log::debug!("fuzz query {} passed: {}", query_idx, sql);
+    }
+    Ok(())
+}
+
+/// Create the tables `t0`, `t1`, ... with integer columns `c0`, `c1`, ... containing small
+/// values, so that joins and filters have matches, and some nulls.
+fn gen_schema(rng: &mut StdRng, config: &FuzzConfig) -> Vec<String> {
+    let mut stmts = vec![];
+    for table_idx in 0..config.num_tables {
+        let columns = (0..NUM_COLUMNS)
+            .map(|col| format!("c{} INT", col))
+            .collect::<Vec<_>>()
+            .join(", ");
+        stmts.push(format!("CREATE TABLE t{} ({})", table_idx, columns));
+        let rows = (0..config.num_rows)
+            .map(|_| {
+                let values = (0..NUM_COLUMNS)
+                    .map(|_| gen_value(rng))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("({})", values)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        stmts.push(format!("INSERT INTO t{} VALUES {}", table_idx, rows));
+    }
+    stmts
+}
+
+fn gen_value(rng: &mut StdRng) -> String {
+    if rng.gen_bool(0.1) {
+        "NULL".to_string()
+    } else {
+        rng.gen_range(0..MAX_VALUE).to_string()
+    }
+}
+
+fn gen_column(rng: &mut StdRng, num_relations: usize) -> String {
+    format!(
+        "a{}.c{}",
+        rng.gen_range(0..num_relations),
+        rng.gen_range(0..NUM_COLUMNS)
+    )
+}
+
+fn gen_filter(rng: &mut StdRng, num_relations: usize) -> String {
+    let column = gen_column(rng, num_relations);
+    match rng.gen_range(0..4) {
+        0 => format!("{} IS NULL", column),
+        1 => format!("{} IS NOT NULL", column),
+        _ => {
+            let op = ["=", "<>", "<", "<=", ">", ">="].choose(rng).unwrap();
+            format!("{} {} {}", column, op, rng.gen_range(0..MAX_VALUE))
+        }
+    }
+}
+
+/// Generate a select-project-join query, optionally with an aggregation, a `DISTINCT` or an
+/// `ORDER BY ... LIMIT` over all the output columns, so that the result is deterministic.
+fn gen_query(rng: &mut StdRng, num_tables: usize) -> String {
+    let num_relations = rng.gen_range(1..=num_tables.min(3));
+    let from = (0..num_relations)
+        .map(|idx| format!("t{} AS a{}", rng.gen_range(0..num_tables), idx))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut conds = (1..num_relations)
+        .map(|idx| {
+            format!(
+                "a{}.c{} = a{}.c{}",
+                rng.gen_range(0..idx),
+                rng.gen_range(0..NUM_COLUMNS),
+                idx,
+                rng.gen_range(0..NUM_COLUMNS)
+            )
+        })
+        .collect::<Vec<_>>();
+    for _ in 0..rng.gen_range(0..=2) {
+        conds.push(gen_filter(rng, num_relations));
+    }
+    let where_clause = if conds.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conds.join(" AND "))
+    };
+
+    let (select, num_outputs, group_by) = if rng.gen_bool(0.3) {
+        let group = gen_column(rng, num_relations);
+        let agg = gen_column(rng, num_relations);
+        (
+            format!("{}, COUNT(*), SUM({}), MIN({})", group, agg, agg),
+            4,
+            format!(" GROUP BY {}", group),
+        )
+    } else {
+        let num_outputs = rng.gen_range(1..=3);
+        let columns = (0..num_outputs)
+            .map(|_| gen_column(rng, num_relations))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let distinct = if rng.gen_bool(0.2) { "DISTINCT " } else { "" };
+        (
+            format!("{}{}", distinct, columns),
+            num_outputs,
+            String::new(),
+        )
+    };
+
+    let order_by = if rng.gen_bool(0.3) {
+        format!(
+            " ORDER BY {} LIMIT {}",
+            (1..=num_outputs)
+                .map(|idx| idx.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            rng.gen_range(0..MAX_VALUE)
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        "SELECT {} FROM {}{}{}{}",
+        select, from, where_clause, group_by, order_by
+    )
+}
@@ -6,6 +6,8 @@
 pub mod benchmark;
 pub mod cardbench;
 mod datafusion_dbms;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
 pub mod job;
 mod postgres_dbms;
 pub mod shell;
@@ -0,0 +1,24 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+#![cfg(feature = "fuzz")]
+
+#[cfg(test)]
+mod tests {
+    use optd_og_perfbench::fuzz::{run_fuzz, FuzzConfig};
+
+    #[test_case::test_case(0)]
+    #[test_case::test_case(15721)]
+    #[tokio::test]
+    async fn optd_og_matches_datafusion(seed: u64) {
+        run_fuzz(&FuzzConfig {
+            seed,
+            num_queries: 50,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    }
+}
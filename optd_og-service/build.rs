@@ -0,0 +1,12 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the vendored protoc, so that building the service does not need one installed.
+    // SAFETY: the build script is single-threaded.
+    unsafe { std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?) };
+    tonic_build::compile_protos("proto/optimizer.proto")?;
+    Ok(())
+}
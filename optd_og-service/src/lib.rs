@@ -0,0 +1,220 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! A gRPC service optimizing the plans of other processes with optd_og, so that engines other
+//! than datafusion can use it out of process. The tables the plans read are resolved in the
+//! catalog of the service, see the `optd_og-service` binary for how it is configured.
+
+use optd_og_datafusion_bridge::substrait::Plan;
+use optd_og_datafusion_bridge::OptdDfContext;
+use optd_og_datafusion_repr::plan_nodes::dispatch_plan_explain_to_string;
+use optd_og_datafusion_repr::{plan_from_json, OptimizationResult};
+use prost::Message;
+use tokio::sync::Mutex;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("optd_og");
+}
+
+use proto::optimize_request::Plan as RequestPlan;
+use proto::optimizer_server::Optimizer;
+pub use proto::optimizer_server::OptimizerServer;
+use proto::{OptimizationMetrics, OptimizeRequest, OptimizeResponse};
+
+pub struct OptimizerService {
+    /// The optimizer optimizes one plan at a time, the requests wait for their turn.
+    ctx: Mutex<OptdDfContext>,
+}
+
+impl OptimizerService {
+    pub fn new(ctx: OptdDfContext) -> Self {
+        Self {
+            ctx: Mutex::new(ctx),
+        }
+    }
+
+    async fn optimize_substrait(
+        &self,
+        plan: &[u8],
+        verbose: bool,
+    ) -> anyhow::Result<OptimizeResponse> {
+        let plan = Plan::decode(plan)?;
+        let ctx = self.ctx.lock().await;
+        let (plan, result) = ctx
+            .optimizer
+            .optimize_substrait_plan_with_result(&plan, &ctx.ctx.state())
+            .await?;
+        let mut response = optimize_response(result, verbose);
+        response.substrait = plan.encode_to_vec();
+        Ok(response)
+    }
+
+    async fn optimize_json(&self, plan: &str, verbose: bool) -> anyhow::Result<OptimizeResponse> {
+        let plan = plan_from_json(plan)?;
+        let ctx = self.ctx.lock().await;
+        let result = ctx
+            .optimizer
+            .optimize_optd_og_plan_with_result(plan)
+            .await?;
+        Ok(optimize_response(result, verbose))
+    }
+}
+
+fn optimize_response(result: OptimizationResult, verbose: bool) -> OptimizeResponse {
+    let root_meta = result.meta.get(&result.plan).cloned();
+    OptimizeResponse {
+        physical_plan: dispatch_plan_explain_to_string(
+            result.plan,
+            if verbose { Some(&result.meta) } else { None },
+        ),
+        substrait: vec![],
+        cost: root_meta.as_ref().map_or(0.0, |meta| meta.weighted_cost),
+        cost_display: root_meta
+            .as_ref()
+            .map(|meta| meta.cost_display.clone())
+            .unwrap_or_default(),
+        stat_display: root_meta.map(|meta| meta.stat_display).unwrap_or_default(),
+        warnings: result.warnings,
+        metrics: Some(OptimizationMetrics {
+            group_count: result.metrics.group_count as u64,
+            plan_space: result.metrics.plan_space as u64,
+            apply_rule_count: result.metrics.apply_rule_count as u64,
+            optimize_expr_count: result.metrics.optimize_expr_count as u64,
+            optimization_time_ms: result.timing.total.as_secs_f64() * 1000.0,
+        }),
+    }
+}
+
+#[tonic::async_trait]
+impl Optimizer for OptimizerService {
+    async fn optimize(
+        &self,
+        request: Request<OptimizeRequest>,
+    ) -> Result<Response<OptimizeResponse>, Status> {
+        let request = request.into_inner();
+        let response = match request.plan {
+            Some(RequestPlan::Substrait(plan)) => {
+                self.optimize_substrait(&plan, request.verbose).await
+            }
+            Some(RequestPlan::Json(plan)) => self.optimize_json(&plan, request.verbose).await,
+            None => return Err(Status::invalid_argument("the request has no plan")),
+        };
+        response
+            .map(Response::new)
+            .map_err(|err| Status::invalid_argument(format!("{:#}", err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion_substrait::logical_plan::producer::to_substrait_plan;
+    use optd_og_datafusion_bridge::create_df_context;
+    use optd_og_datafusion_repr::plan_nodes::{
+        BinOpPred, BinOpType, ColumnRefPred, ConstantPred, DfReprPlanNode, DfReprPredNode,
+        LogicalFilter, LogicalScan,
+    };
+    use optd_og_datafusion_repr::plan_to_json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn optimize_substrait_plan() {
+        let ctx = create_df_context(None, None, None, false, true, false, None)
+            .await
+            .unwrap();
+        ctx.ctx
+            .sql("create table t1(v1 int, v2 int)")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        ctx.ctx
+            .sql("create table t2(v3 int, v4 int)")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let logical_plan = ctx
+            .ctx
+            .sql("select v1, v4 from t1, t2 where v1 = v3")
+            .await
+            .unwrap()
+            .into_optimized_plan()
+            .unwrap();
+        let plan = to_substrait_plan(&logical_plan, &ctx.ctx.state()).unwrap();
+
+        let service = OptimizerService::new(ctx);
+        let response = service
+            .optimize(Request::new(OptimizeRequest {
+                plan: Some(RequestPlan::Substrait(plan.encode_to_vec())),
+                verbose: true,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.physical_plan.contains("PhysicalHashJoin"));
+        assert!(response.cost > 0.0);
+        Plan::decode(response.substrait.as_slice()).unwrap();
+
+        let status = service
+            .optimize(Request::new(OptimizeRequest {
+                plan: Some(RequestPlan::Substrait(b"not a plan".to_vec())),
+                verbose: false,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn optimize_json_plan() {
+        let ctx = create_df_context(None, None, None, false, true, false, None)
+            .await
+            .unwrap();
+        ctx.ctx
+            .sql("create table t1(v1 int, v2 int)")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let plan = LogicalFilter::new(
+            LogicalScan::new("t1".into()).into_plan_node(),
+            BinOpPred::new(
+                ColumnRefPred::new(0).into_pred_node(),
+                ConstantPred::int32(1).into_pred_node(),
+                BinOpType::Eq,
+            )
+            .into_pred_node(),
+        )
+        .into_plan_node();
+
+        let service = OptimizerService::new(ctx);
+        let response = service
+            .optimize(Request::new(OptimizeRequest {
+                plan: Some(RequestPlan::Json(plan_to_json(&plan).unwrap())),
+                verbose: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.physical_plan.contains("PhysicalScan"));
+        assert!(response.substrait.is_empty());
+
+        let status = service
+            .optimize(Request::new(OptimizeRequest {
+                plan: Some(RequestPlan::Json(
+                    r#"{"version":0,"plan":null}"#.to_string(),
+                )),
+                verbose: false,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use datafusion::prelude::{CsvReadOptions, ParquetReadOptions};
+use optd_og_datafusion_bridge::create_df_context;
+use optd_og_datafusion_repr_adv_cost::adv_stats::stats::DataFusionBaseTableStats;
+use optd_og_service::{OptimizerServer, OptimizerService};
+use tonic::transport::Server;
+
+#[derive(Parser)]
+struct Cli {
+    #[clap(long)]
+    #[clap(default_value = "127.0.0.1:50051")]
+    addr: SocketAddr,
+
+    #[clap(long = "table")]
+    #[clap(help = "A table of the catalog as `name=path`, where path is a parquet or csv file")]
+    tables: Vec<String>,
+
+    #[clap(long)]
+    #[clap(
+        help = "The table statistics in JSON, as collected by `DataFusionBaseTableStats`. Enables the advanced cost model."
+    )]
+    stats: Option<PathBuf>,
+
+    #[clap(long)]
+    #[clap(action)]
+    enable_adaptive: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+    let cli = Cli::parse();
+
+    let stats = match &cli.stats {
+        Some(path) => {
+            let stats: DataFusionBaseTableStats = serde_json::from_slice(
+                &std::fs::read(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?,
+            )?;
+            Some(stats)
+        }
+        None => None,
+    };
+    let ctx = create_df_context(
+        None,
+        None,
+        None,
+        cli.enable_adaptive,
+        true,
+        stats.is_some(),
+        stats,
+    )
+    .await?;
+    for table in &cli.tables {
+        let Some((name, path)) = table.split_once('=') else {
+            bail!("expected name=path, got {}", table);
+        };
+        if path.ends_with(".csv") {
+            ctx.ctx
+                .register_csv(name, path, CsvReadOptions::new())
+                .await?;
+        } else {
+            ctx.ctx
+                .register_parquet(name, path, ParquetReadOptions::default())
+                .await?;
+        }
+    }
+
+    tracing::info!("optd_og-service listening on {}", cli.addr);
+    Server::builder()
+        .add_service(OptimizerServer::new(OptimizerService::new(ctx)))
+        .serve(cli.addr)
+        .await?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 pub mod execution;
 pub mod planning;
+pub mod report;
 
 use std::future::Future;
 
@@ -9,6 +10,7 @@ use tokio::runtime::Runtime;
 
 pub use execution::ExecutionBenchRunner;
 pub use planning::PlanningBenchRunner;
+pub use report::{report_optimization, QueryOptimizationReport};
 
 pub trait PlannerBenchRunner {
     /// Describes what the benchmark is evaluating.
@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use crate::{extract_flags, DatafusionDBMS, TestFlags};
+use anyhow::{Context, Result};
+use optd_og_datafusion_bridge::get_join_order;
+use serde::Serialize;
+use sqlplannertest::{parse_test_cases, TestCase};
+
+/// How the optimizer did on a single query, without executing it.
+#[derive(Debug, Serialize)]
+pub struct QueryOptimizationReport {
+    /// The test file the query is from, e.g., `tpch/q1`.
+    pub name: String,
+    pub planning_time_ms: f64,
+    pub heuristic_time_ms: f64,
+    /// The time spent in each stage of the cascades optimizer.
+    pub stage_time_ms: Vec<(String, f64)>,
+    /// The number of groups in the memo table.
+    pub group_count: usize,
+    pub plan_space: usize,
+    pub apply_rule_count: usize,
+    /// The join order of the chosen plan, or `None` if the query has no scan.
+    pub join_order: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Optimizes the queries of a sqlplannertest yml file, each test case in a new session. If
+/// `populate_sql` is set, it is executed after the `before` statements so that the cost model
+/// sees the statistics of the populated tables.
+pub async fn report_optimization(
+    path: &Path,
+    name: &str,
+    populate_sql: Option<&str>,
+    with_advanced_cost: bool,
+) -> Result<Vec<QueryOptimizationReport>> {
+    let testcases = std::fs::read(path)?;
+    let testcases: Vec<TestCase> = serde_yaml::from_slice(&testcases)?;
+    let base_path = path.parent().context("no parent directory")?.to_path_buf();
+    let testcases = parse_test_cases(base_path, testcases)?;
+
+    let mut reports = Vec::new();
+    for testcase in &testcases {
+        let dbms = if with_advanced_cost {
+            DatafusionDBMS::new_advanced_cost().await?
+        } else {
+            DatafusionDBMS::new().await?
+        };
+        for sql in &testcase.before_sql {
+            dbms.execute(sql, &TestFlags::default()).await?;
+        }
+        if let Some(populate_sql) = populate_sql {
+            for sql in populate_sql.split(";\n") {
+                dbms.execute(sql, &TestFlags::default()).await?;
+            }
+        }
+        // Use the flags of the benchmark, if any.
+        let flags = match testcase.tasks.iter().find(|x| x.starts_with("bench")) {
+            Some(bench_task) => extract_flags(bench_task)?,
+            None => TestFlags::default(),
+        };
+        dbms.setup(&flags).await?;
+
+        let statements = dbms.parse_sql(&testcase.sql).await?;
+        let num_statements = statements.len();
+        for statement in statements {
+            let result = dbms
+                .optimize_statement(statement)
+                .await
+                .with_context(|| format!("failed to optimize {}", name))?;
+            reports.push(QueryOptimizationReport {
+                name: if testcases.len() == 1 && num_statements == 1 {
+                    name.to_string()
+                } else {
+                    format!("{}#{}", name, reports.len())
+                },
+                planning_time_ms: result.timing.total.as_secs_f64() * 1000.0,
+                heuristic_time_ms: result.timing.heuristic.as_secs_f64() * 1000.0,
+                stage_time_ms: result
+                    .timing
+                    .stages
+                    .iter()
+                    .map(|(stage, time)| (stage.clone(), time.as_secs_f64() * 1000.0))
+                    .collect(),
+                group_count: result.metrics.group_count,
+                plan_space: result.metrics.plan_space,
+                apply_rule_count: result.metrics.apply_rule_count,
+                join_order: get_join_order(result.plan).map(|x| x.to_string()),
+                warnings: result.warnings,
+            });
+        }
+    }
+    Ok(reports)
+}
@@ -0,0 +1,73 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use optd_og_sqlplannertest::bench_helper::report_optimization;
+use sqlplannertest::discover_tests_with_selections;
+
+/// Optimizes the queries without executing them and prints the planning time, the memo table
+/// size and the join order of each query as JSON.
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    /// Test modules or test files to report on
+    #[clap(default_values_t = vec!["tpch".to_string()])]
+    selections: Vec<String>,
+    /// SQL executed before optimizing the queries to populate the tables, e.g.,
+    /// `tests/tpch/bench_populate.sql`
+    #[clap(long)]
+    populate: Option<PathBuf>,
+    /// Write the report to this file instead of stdout
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// Use the advanced cost model
+    #[clap(long)]
+    enable_advanced_cost_model: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let populate_sql = cli
+        .populate
+        .as_ref()
+        .map(|path| {
+            std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))
+        })
+        .transpose()?;
+
+    let tests_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let mut reports = Vec::new();
+    for path in discover_tests_with_selections(&tests_dir, &cli.selections)? {
+        let path = path?;
+        let name = path
+            .strip_prefix(&tests_dir)?
+            .with_extension("")
+            .to_string_lossy()
+            .to_string();
+        reports.extend(
+            report_optimization(
+                &path,
+                &name,
+                populate_sql.as_deref(),
+                cli.enable_advanced_cost_model,
+            )
+            .await?,
+        );
+    }
+
+    let report = serde_json::to_string_pretty(&reports)?;
+    match cli.output {
+        Some(output) => std::fs::write(&output, report)
+            .with_context(|| format!("failed to write {}", output.display()))?,
+        None => println!("{}", report),
+    }
+    Ok(())
+}
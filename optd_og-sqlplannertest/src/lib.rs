@@ -4,6 +4,7 @@
 // https://opensource.org/licenses/MIT.
 
 pub mod bench_helper;
+mod plan_diff;
 
 use std::collections::{HashSet, VecDeque};
 use std::sync::Arc;
@@ -13,20 +14,26 @@ use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
 use datafusion::catalog::CatalogProviderList;
 use datafusion::execution::TaskContext;
 use datafusion::physical_plan::ExecutionPlan;
-use datafusion::prelude::SessionContext;
+use datafusion::physical_planner::{DefaultPhysicalPlanner, PhysicalPlanner};
+use datafusion::prelude::{SessionConfig, SessionContext};
 use datafusion::sql::parser::{DFParser, Statement};
 use datafusion::sql::sqlparser::dialect::GenericDialect;
 use datafusion_optd_og_cli::helper::unescape_input;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use mimalloc::MiMalloc;
-use optd_og_datafusion_bridge::{create_df_context, OptdDfContext, OptdQueryPlanner};
+use optd_og_core::cascades::{CostBreakdown, GroupId, MemoDumpOptions};
+use optd_og_datafusion_bridge::{
+    create_df_context, OptdDfContext, OptdPlanContext, OptdQueryPlanner,
+};
+use optd_og_datafusion_repr::OptimizationResult;
+use plan_diff::diff_plans;
 use regex::Regex;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 
 #[derive(Default)]
@@ -68,8 +75,13 @@ impl DatafusionDBMS {
         catalog: Option<Arc<dyn CatalogProviderList>>,
         with_advanced_cost: bool,
     ) -> Result<(SessionContext, Arc<OptdQueryPlanner>)> {
+        // The planner tests are read in a single partition whatever the number of cores of the
+        // machine, so that their plans do not depend on it, see the `target_partitions` flag.
+        let session_config = SessionConfig::from_env()?
+            .with_information_schema(true)
+            .with_target_partitions(1);
         let OptdDfContext { ctx, optimizer, .. } = create_df_context(
-            None,
+            Some(session_config),
             None,
             catalog,
             false,
@@ -90,11 +102,17 @@ impl DatafusionDBMS {
             .optimizer
             .lock()
             .unwrap();
+        // before the rules are enabled, as it adds or removes the two-phase aggregation rule
+        guard
+            .as_mut()
+            .unwrap()
+            .set_target_partitions(flags.target_partitions.unwrap_or(1));
         let optimizer = guard.as_mut().unwrap().optd_og_optimizer_mut();
 
         optimizer.prop.panic_on_budget = flags.panic_on_budget;
         optimizer.prop.enable_tracing = flags.enable_tracing;
         optimizer.prop.disable_pruning = flags.disable_pruning;
+        optimizer.prop.verify_memo_integrity = flags.verify_memo_integrity;
         let rules = optimizer.rules();
         if flags.enable_logical_rules.is_empty() {
             for r in 0..rules.len() {
@@ -124,6 +142,32 @@ impl DatafusionDBMS {
             }
             guard.as_mut().unwrap().enable_heuristic(false);
         }
+        let optimizer = guard.as_mut().unwrap().optd_og_optimizer_mut();
+        let mut rules_to_disable = flags
+            .disable_rules
+            .iter()
+            .map(|x| x.as_str())
+            .collect::<HashSet<_>>();
+        for (rule_id, rule) in rules.as_ref().iter().enumerate() {
+            if rules_to_disable.remove(rule.name()) {
+                optimizer.disable_rule(rule_id);
+            }
+        }
+        if !rules_to_disable.is_empty() {
+            bail!("Unknown rule: {:?}", rules_to_disable);
+        }
+        guard
+            .as_mut()
+            .unwrap()
+            .set_nlj_row_threshold(flags.nlj_row_threshold);
+        guard
+            .as_mut()
+            .unwrap()
+            .enable_memo_snapshots(flags.memo_snapshots);
+        guard
+            .as_mut()
+            .unwrap()
+            .enable_heuristic_cost_check(flags.heuristic_cost_check);
 
         Ok(())
     }
@@ -142,6 +186,22 @@ impl DatafusionDBMS {
         stmt: Statement,
         flags: &TestFlags,
     ) -> Result<(Arc<dyn ExecutionPlan>, Arc<TaskContext>)> {
+        if flags.optd_og_logical {
+            // Rewrite the plan with optd_og and let the datafusion planner plan the result.
+            let state = self.ctx.state();
+            let plan = state.statement_to_plan(stmt).await?;
+            let plan = state.optimize(&plan)?;
+            let plan = self
+                .optd_og_optimizer
+                .as_ref()
+                .unwrap()
+                .optimize_logical_plan(&plan, &state)
+                .await?;
+            let plan = DefaultPhysicalPlanner::default()
+                .create_physical_plan(&plan, &state)
+                .await?;
+            return Ok((plan, self.ctx.task_ctx()));
+        }
         let df = if flags.enable_df_logical {
             let plan = self
                 .use_df_logical_ctx
@@ -159,6 +219,40 @@ impl DatafusionDBMS {
         Ok((plan, task_ctx))
     }
 
+    /// Optimizes a SQL statement with optd_og, without creating the execution plan.
+    pub(crate) async fn optimize_statement(&self, stmt: Statement) -> Result<OptimizationResult> {
+        let state = self.ctx.state();
+        let plan = state.statement_to_plan(stmt).await?;
+        let plan = state.optimize(&plan)?;
+        let mut ctx = OptdPlanContext::new(&state);
+        let optd_og_rel = ctx.conv_into_optd_og(&plan)?;
+        let planner = self.optd_og_optimizer.as_ref().unwrap();
+        planner.resolve_tables(&ctx).await?;
+        let mut guard = planner.optimizer.lock().unwrap();
+        guard.as_mut().unwrap().optimize(optd_og_rel)
+    }
+
+    /// Optimizes the last statement of `sql` with optd_og, and breaks down the cost of the
+    /// chosen plan.
+    pub(crate) async fn cost_breakdown(&self, sql: &str) -> Result<CostBreakdown> {
+        let Some(statement) = self.parse_sql(sql).await?.pop_back() else {
+            bail!("No statement to optimize");
+        };
+        let result = self.optimize_statement(statement).await?;
+        let guard = self
+            .optd_og_optimizer
+            .as_ref()
+            .unwrap()
+            .optimizer
+            .lock()
+            .unwrap();
+        guard
+            .as_ref()
+            .unwrap()
+            .optd_og_cascades_optimizer()
+            .step_cost_breakdown(result.group_id)
+    }
+
     /// Executes the physical [`ExecutionPlan`] and collect the results in memory.
     pub(crate) async fn execute_physical(
         &self,
@@ -220,6 +314,17 @@ impl DatafusionDBMS {
     ) -> Result<()> {
         use std::fmt::Write;
 
+        // the explain sections are session options, so they are set for every explain to not
+        // leak into the following tasks
+        for section in ["logical", "join_orders", "physical"] {
+            let enabled = !flags.disable_explain.iter().any(|x| x == section);
+            self.execute(
+                &format!("set optd.explain_{} = {}", section, enabled),
+                &TestFlags::default(),
+            )
+            .await?;
+        }
+
         let verbose = flags.verbose;
         let explain_sql = if verbose {
             format!("explain verbose {}", &sql)
@@ -281,6 +386,40 @@ impl DatafusionDBMS {
                         .unwrap()
                 )?;
                 writeln!(r)?;
+            } else if subtask == "warnings" {
+                writeln!(
+                    r,
+                    "{}",
+                    result
+                        .iter()
+                        .find(|x| x[0] == "physical_plan after optd_og-warnings")
+                        .map(|x| x[1].as_str())
+                        .unwrap_or("None")
+                )?;
+            } else if subtask == "stale_stats" {
+                writeln!(
+                    r,
+                    "{}",
+                    result
+                        .iter()
+                        .find(|x| x[0] == "physical_plan after optd_og-stale-stats")
+                        .map(|x| x[1].as_str())
+                        .unwrap_or("None")
+                )?;
+            } else if subtask == "rules_fired" {
+                writeln!(
+                    r,
+                    "{}",
+                    result
+                        .iter()
+                        .find(|x| x[0] == "physical_plan after optd_og-rules-fired")
+                        .map(|x| &x[1])
+                        .unwrap()
+                )?;
+            } else if subtask == "physical_optd_cost" {
+                write!(r, "{}", self.cost_breakdown(sql).await?)?;
+            } else if subtask == "sections" {
+                writeln!(r, "{}", result.iter().map(|x| &x[0]).join("\n"))?;
             } else if subtask == "physical_datafusion" {
                 writeln!(
                     r,
@@ -298,6 +437,38 @@ impl DatafusionDBMS {
 
         Ok(())
     }
+
+    /// Executes the `plan_diff` task, which diffs the physical plans of the query optimized with
+    /// two sets of flags, e.g., `plan_diff[][disable_rules:join_commute_rule]`.
+    async fn task_plan_diff(&mut self, r: &mut String, sql: &str, task: &str) -> Result<()> {
+        use std::fmt::Write;
+
+        let Some(captures) = PLAN_DIFF_REGEX.captures(task) else {
+            bail!("Failed to parse plan_diff task: {}", task);
+        };
+        let mut plans = Vec::with_capacity(2);
+        for idx in 1..=2 {
+            let flags = parse_flags(&captures[idx])?;
+            let explain_sql = if flags.verbose {
+                format!("explain verbose {}", &sql)
+            } else {
+                format!("explain {}", &sql)
+            };
+            let result = self.execute(&explain_sql, &flags).await?;
+            let Some(plan) = result
+                .into_iter()
+                .find(|x| x[0] == "physical_plan after optd_og")
+                .map(|mut x| x.swap_remove(1))
+            else {
+                bail!("No optd_og physical plan for [{}]", &captures[idx]);
+            };
+            plans.push(plan);
+        }
+        writeln!(r, "--- [{}]", &captures[1])?;
+        writeln!(r, "+++ [{}]", &captures[2])?;
+        writeln!(r, "{}", diff_plans(&plans[0], &plans[1]))?;
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -310,6 +481,10 @@ impl sqlplannertest::PlannerTestRunner for DatafusionDBMS {
             self.execute(sql, &TestFlags::default()).await?;
         }
         for task in &test_case.tasks {
+            if task.starts_with("plan_diff") {
+                self.task_plan_diff(r, &test_case.sql, task).await?;
+                continue;
+            }
             let flags = extract_flags(task)?;
             if task.starts_with("execute") {
                 self.task_execute(r, &test_case.sql, &flags).await?;
@@ -326,9 +501,23 @@ impl sqlplannertest::PlannerTestRunner for DatafusionDBMS {
                     .unwrap();
                 let optimizer = guard.as_mut().unwrap().optd_og_optimizer_mut();
                 let mut buf = String::new();
-                optimizer.dump(&mut buf).unwrap();
+                optimizer
+                    .dump_with_options(&mut buf, &flags.memo_dump_options)
+                    .unwrap();
                 r.push_str(&buf);
             }
+            if flags.memo_snapshots {
+                let guard = self
+                    .optd_og_optimizer
+                    .as_ref()
+                    .unwrap()
+                    .optimizer
+                    .lock()
+                    .unwrap();
+                for diff in guard.as_ref().unwrap().memo_snapshot_diffs() {
+                    r.push_str(&diff.to_string());
+                }
+            }
         }
         Ok(result)
     }
@@ -336,6 +525,7 @@ impl sqlplannertest::PlannerTestRunner for DatafusionDBMS {
 
 lazy_static! {
     static ref FLAGS_REGEX: Regex = Regex::new(r"\[(.*)\]").unwrap();
+    static ref PLAN_DIFF_REGEX: Regex = Regex::new(r"^plan_diff\[([^\]]*)\]\[([^\]]*)\]$").unwrap();
 }
 
 #[derive(Default, Debug)]
@@ -343,10 +533,25 @@ pub struct TestFlags {
     verbose: bool,
     enable_df_logical: bool,
     enable_logical_rules: Vec<String>,
+    disable_rules: Vec<String>,
     panic_on_budget: bool,
     enable_tracing: bool,
     dump_memo_table: bool,
+    /// The parts of the memo table printed by `dump_memo_table`.
+    memo_dump_options: MemoDumpOptions,
+    /// Print the differences of the memo table between the optimization stages.
+    memo_snapshots: bool,
     disable_pruning: bool,
+    /// Check the integrity of the memo table after every rule application.
+    verify_memo_integrity: bool,
+    /// Reject the heuristic rewrites which increase the estimated cost of the plan.
+    heuristic_cost_check: bool,
+    nlj_row_threshold: Option<usize>,
+    /// The number of partitions the optimizer assumes the input is read in, 1 by default.
+    target_partitions: Option<usize>,
+    optd_og_logical: bool,
+    /// The optd_og explain sections not shown, i.e., `logical`, `join_orders` or `physical`.
+    disable_explain: Vec<String>,
 }
 
 /// Extract the flags from a task. The flags are specified in square brackets.
@@ -354,39 +559,88 @@ pub struct TestFlags {
 /// "verbose"]`.
 pub fn extract_flags(task: &str) -> Result<TestFlags> {
     if let Some(captures) = FLAGS_REGEX.captures(task) {
-        let flags = captures
-            .get(1)
-            .unwrap()
-            .as_str()
-            .split(',')
-            .map(|x| x.trim().to_string())
-            .collect_vec();
-        let mut options = TestFlags::default();
-        for flag in flags {
-            if flag == "verbose" {
-                options.verbose = true;
-            } else if flag == "use_df_logical" {
-                options.enable_df_logical = true;
-            } else if flag.starts_with("logical_rules") {
-                if let Some((_, flag)) = flag.split_once(':') {
-                    options.enable_logical_rules = flag.split('+').map(|x| x.to_string()).collect();
-                } else {
-                    bail!("Failed to parse logical_rules flag: {}", flag);
-                }
-            } else if flag == "panic_on_budget" {
-                options.panic_on_budget = true;
-            } else if flag == "dump_memo_table" {
-                options.dump_memo_table = true;
-            } else if flag == "disable_pruning" {
-                options.disable_pruning = true;
-            } else if flag == "enable_tracing" {
-                options.enable_tracing = true;
+        parse_flags(captures.get(1).unwrap().as_str())
+    } else {
+        Ok(TestFlags::default())
+    }
+}
+
+/// Parse a comma-separated list of flags.
+fn parse_flags(flags: &str) -> Result<TestFlags> {
+    let flags = flags
+        .split(',')
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect_vec();
+    let mut options = TestFlags::default();
+    for flag in flags {
+        if flag == "verbose" {
+            options.verbose = true;
+        } else if flag == "use_df_logical" {
+            options.enable_df_logical = true;
+        } else if flag.starts_with("logical_rules") {
+            if let Some((_, flag)) = flag.split_once(':') {
+                options.enable_logical_rules = flag.split('+').map(|x| x.to_string()).collect();
+            } else {
+                bail!("Failed to parse logical_rules flag: {}", flag);
+            }
+        } else if flag.starts_with("disable_explain") {
+            if let Some((_, flag)) = flag.split_once(':') {
+                options.disable_explain = flag.split('+').map(|x| x.to_string()).collect();
             } else {
-                bail!("Unknown flag: {}", flag);
+                bail!("Failed to parse disable_explain flag: {}", flag);
             }
+        } else if flag.starts_with("disable_rules") {
+            if let Some((_, flag)) = flag.split_once(':') {
+                options.disable_rules = flag.split('+').map(|x| x.to_string()).collect();
+            } else {
+                bail!("Failed to parse disable_rules flag: {}", flag);
+            }
+        } else if let Some(threshold) = flag.strip_prefix("nlj_row_threshold:") {
+            options.nlj_row_threshold =
+                Some(threshold.parse().with_context(|| {
+                    format!("Failed to parse nlj_row_threshold flag: {}", flag)
+                })?);
+        } else if let Some(partitions) = flag.strip_prefix("target_partitions:") {
+            options.target_partitions =
+                Some(partitions.parse().with_context(|| {
+                    format!("Failed to parse target_partitions flag: {}", flag)
+                })?);
+        } else if flag == "panic_on_budget" {
+            options.panic_on_budget = true;
+        } else if flag == "dump_memo_table" {
+            options.dump_memo_table = true;
+        } else if let Some(group_id) = flag.strip_prefix("dump_memo_group:") {
+            let group_id = group_id
+                .trim_start_matches('!')
+                .parse()
+                .with_context(|| format!("Failed to parse dump_memo_group flag: {}", flag))?;
+            options.memo_dump_options.root = Some(GroupId(group_id).into());
+        } else if let Some(depth) = flag.strip_prefix("dump_memo_depth:") {
+            options.memo_dump_options.max_depth = Some(
+                depth
+                    .parse()
+                    .with_context(|| format!("Failed to parse dump_memo_depth flag: {}", flag))?,
+            );
+        } else if flag == "dump_memo_winners" {
+            options.memo_dump_options.winners_only = true;
+        } else if flag == "dump_memo_last_stage" {
+            options.memo_dump_options.last_stage_only = true;
+        } else if flag == "memo_snapshots" {
+            options.memo_snapshots = true;
+        } else if flag == "disable_pruning" {
+            options.disable_pruning = true;
+        } else if flag == "verify_memo_integrity" {
+            options.verify_memo_integrity = true;
+        } else if flag == "heuristic_cost_check" {
+            options.heuristic_cost_check = true;
+        } else if flag == "enable_tracing" {
+            options.enable_tracing = true;
+        } else if flag == "optd_og_logical" {
+            options.optd_og_logical = true;
+        } else {
+            bail!("Unknown flag: {}", flag);
         }
-        Ok(options)
-    } else {
-        Ok(TestFlags::default())
     }
+    Ok(options)
 }
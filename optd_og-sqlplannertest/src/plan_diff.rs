@@ -0,0 +1,67 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+/// Diffs two explained plans line by line. Each line of the explain output is a plan node at its
+/// depth in the tree, so the diff shows which nodes are added, removed or changed. The lines
+/// are prefixed with `-` if they are only in `before`, with `+` if they are only in `after`, and
+/// with a space otherwise.
+pub fn diff_plans(before: &str, after: &str) -> String {
+    let before = before.lines().collect::<Vec<_>>();
+    let after = after.lines().collect::<Vec<_>>();
+
+    // lcs[i][j] is the length of the longest common subsequence of before[i..] and after[j..].
+    let mut lcs = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            lcs[i][j] = if before[i] == after[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < before.len() || j < after.len() {
+        // Removals are printed before additions so that a changed node reads as `-` then `+`.
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            diff += &format!(" {}\n", before[i]);
+            i += 1;
+            j += 1;
+        } else if i < before.len() && (j == after.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff += &format!("-{}\n", before[i]);
+            i += 1;
+        } else {
+            diff += &format!("+{}\n", after[j]);
+            j += 1;
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_changed_node() {
+        let before = "PhysicalProjection\n└── PhysicalNestedLoopJoin\n    ├── PhysicalScan { t1 }\n    └── PhysicalScan { t2 }";
+        let after = "PhysicalProjection\n└── PhysicalHashJoin\n    ├── PhysicalScan { t2 }\n    └── PhysicalScan { t1 }";
+        assert_eq!(
+            diff_plans(before, after),
+            " PhysicalProjection\n-└── PhysicalNestedLoopJoin\n-    ├── PhysicalScan { t1 }\n-    └── PhysicalScan { t2 }\n+└── PhysicalHashJoin\n+    ├── PhysicalScan { t2 }\n+    └── PhysicalScan { t1 }\n"
+        );
+    }
+
+    #[test]
+    fn diff_same_plan() {
+        let plan = "PhysicalFilter\n└── PhysicalScan { t1 }";
+        assert_eq!(
+            diff_plans(plan, plan),
+            " PhysicalFilter\n └── PhysicalScan { t1 }\n"
+        );
+    }
+}
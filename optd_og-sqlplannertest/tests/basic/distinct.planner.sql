@@ -0,0 +1,36 @@
+-- (no id or description)
+create table t1(v1 int primary key, v2 int);
+insert into t1 values (0, 0), (1, 0), (2, 1);
+
+/*
+3
+*/
+
+-- Test whether the distinct over the primary key is eliminated.
+select distinct v1, v2 from t1;
+
+/*
+PhysicalScan { table: t1 }
+*/
+
+-- Test whether the distinct over other columns is converted into an aggregation.
+select distinct v2 from t1;
+
+/*
+PhysicalAgg { aggrs: [], groups: [ #0 ] }
+└── PhysicalProjection { exprs: [ #1 ] }
+    └── PhysicalScan { table: t1 }
+*/
+
+-- Test the results of the distincts.
+select distinct v1, v2 from t1 order by v1;
+select distinct v2 from t1 order by v2;
+
+/*
+0 0
+1 0
+2 1
+0
+1
+*/
+
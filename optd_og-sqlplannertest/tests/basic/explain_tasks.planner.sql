@@ -0,0 +1,32 @@
+-- (no id or description)
+create table t1(v1 int);
+insert into t1 values (0), (1), (2);
+
+/*
+3
+*/
+
+-- Test the rules fired when optimizing a scan
+select * from t1;
+
+/*
+physical_conversion (implementation)
+*/
+
+-- Test diffing the plans with and without the verbose flag
+select * from t1;
+
+/*
+--- []
++++ [verbose]
+-PhysicalScan { table: t1 }
++PhysicalScan { table: t1, cost: {compute=0,io=1000}, stat: {row_cnt=1000} }
+*/
+
+-- Test the cost breakdown of a scan
+select * from t1;
+
+/*
+PhysicalScan group_id=!2 operation_cost={compute=0,io=1000} total_cost={compute=0,io=1000} stat={row_cnt=1000}
+*/
+
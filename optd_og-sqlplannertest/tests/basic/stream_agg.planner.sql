@@ -0,0 +1,56 @@
+-- (no id or description)
+create table t1(v1 int, v2 int);
+insert into t1 values (1, 1), (1, 2), (2, 1), (3, 3), (3, 1);
+
+/*
+5
+*/
+
+-- Test aggregating the input sorted on the group-by keys
+select v1, count(*) from (select * from t1 order by v1) group by v1 order by v1;
+
+/*
+PhysicalStreamAgg
+├── aggrs:Agg(Count)
+│   └── [ 1(i64) ]
+├── groups: [ #0 ]
+└── PhysicalSort
+    ├── exprs:SortOrder { order: Asc }
+    │   └── #0
+    └── PhysicalScan { table: t1 }
+1 2
+2 1
+3 2
+*/
+
+-- Test aggregating the input sorted on the group-by keys in another order and direction
+select v1, v2, count(*) from (select * from t1 order by v2 desc, v1) group by v1, v2 order by v1, v2;
+
+/*
+1 1 1
+1 2 1
+2 1 1
+3 1 1
+3 3 1
+*/
+
+-- Test aggregating the input not sorted on the group-by keys
+select v2, count(*) from (select * from t1 order by v1) group by v2 order by v2;
+
+/*
+PhysicalSort
+├── exprs:SortOrder { order: Asc }
+│   └── #0
+└── PhysicalAgg
+    ├── aggrs:Agg(Count)
+    │   └── [ 1(i64) ]
+    ├── groups: [ #1 ]
+    └── PhysicalSort
+        ├── exprs:SortOrder { order: Asc }
+        │   └── #0
+        └── PhysicalScan { table: t1 }
+1 3
+2 1
+3 1
+*/
+
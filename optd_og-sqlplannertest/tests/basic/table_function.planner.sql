@@ -0,0 +1,43 @@
+-- (no id or description)
+create table t1(v1 int, v2 int[]);
+insert into t1 values (1, [1, 2]), (2, []), (3, [3]);
+
+/*
+3
+*/
+
+-- Test scanning a table function
+select * from generate_series(1, 3);
+
+/*
+LogicalProjection { exprs: [ #0 ] }
+└── LogicalTableFunction { name: generate_series(), args: [] }
+    └── LogicalEmptyRelation { produce_one_row: true }
+PhysicalTableFunction { name: generate_series(), args: [] }
+└── PhysicalEmptyRelation { produce_one_row: true }
+1
+2
+3
+*/
+
+-- Test unnesting a list column
+select v1, unnest(v2) from t1;
+
+/*
+LogicalProjection { exprs: [ #0, #1 ] }
+└── LogicalTableFunction { name: unnest, args: [ #1, false ] }
+    └── LogicalProjection { exprs: [ #0, #1 ] }
+        └── LogicalScan { table: t1 }
+PhysicalTableFunction { name: unnest, args: [ #1, false ] }
+└── PhysicalScan { table: t1 }
+*/
+
+-- Test unnesting a list column, skipping the empty lists
+select v1, unnest(v2) from t1 order by v1, 2;
+
+/*
+1 1
+1 2
+3 3
+*/
+
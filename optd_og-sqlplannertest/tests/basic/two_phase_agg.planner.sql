@@ -0,0 +1,33 @@
+-- (no id or description)
+create table t1(v1 int, v2 int);
+insert into t1 values (0, 1), (1, 2), (0, 3), (2, 4), (1, 5);
+
+/*
+5
+*/
+
+-- Test whether the aggregation is split into a partial and a final phase when the input is read in more than one partition.
+select v1, sum(v2) from t1 group by v1;
+
+/*
+PhysicalFinalAgg
+├── aggrs:Agg(Sum)
+│   └── [ #1 ]
+├── groups: [ #0 ]
+└── PhysicalPartialAgg
+    ├── aggrs:Agg(Sum)
+    │   └── [ #1 ]
+    ├── groups: [ #0 ]
+    ├── partitions: 4(u64)
+    └── PhysicalScan { table: t1 }
+*/
+
+-- Test whether the two-phase aggregation merges the partial results of the groups.
+select v1, sum(v2) from t1 group by v1 order by v1;
+
+/*
+0 4
+1 7
+2 4
+*/
+
@@ -0,0 +1,46 @@
+-- (no id or description)
+create table t1(t1v1 int, t1v2 int);
+create table t2(t2v1 int, t2v3 int);
+create table t3(t3v2 int, t3v4 int);
+create table t4(t4v1 int, t4v2 int);
+set optd.table_row_hint = 't1=2,t3=2';
+
+/*
+
+*/
+
+-- Test whether the threshold replaces the cross product of the small tables with hash joins.
+select * from t1, t3, t2 where t1v1 = t2v1 and t3v4 = t2v3;
+
+/*
+PhysicalHashJoin { join_type: Inner, left_keys: [ #0, #3 ], right_keys: [ #0, #1 ] }
+├── PhysicalNestedLoopJoin { join_type: Inner, cond: true }
+│   ├── PhysicalScan { table: t1 }
+│   └── PhysicalScan { table: t3 }
+└── PhysicalScan { table: t2 }
+PhysicalHashJoin { join_type: Inner, left_keys: [ #0 ], right_keys: [ #2 ] }
+├── PhysicalScan { table: t1 }
+└── PhysicalHashJoin { join_type: Inner, left_keys: [ #1 ], right_keys: [ #1 ] }
+    ├── PhysicalScan { table: t3 }
+    └── PhysicalScan { table: t2 }
+None
+*/
+
+-- Test whether the nested loop join is kept with a warning if there is no other way to do the join.
+select * from t2 inner join t4 on t2v1 = t4v1 or t2v3 = t4v2;
+
+/*
+PhysicalNestedLoopJoin
+├── join_type: Inner
+├── cond:Or
+│   ├── Eq
+│   │   ├── #0
+│   │   └── #2
+│   └── Eq
+│       ├── #1
+│       └── #3
+├── PhysicalScan { table: t2 }
+└── PhysicalScan { table: t4 }
+nested loop join over 1000 and 1000 rows chosen for lack of alternatives (optd.nlj_row_threshold = 100)
+*/
+
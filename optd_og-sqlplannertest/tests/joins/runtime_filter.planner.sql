@@ -0,0 +1,25 @@
+-- (no id or description)
+create table t1(t1v1 int, t1v2 int);
+insert into t1 values (0, 0), (1, 1), (2, 2);
+set optd.runtime_filter_selectivity = 1.0;
+
+/*
+3
+*/
+
+-- Test whether the hash join filters the scan of its probe side with the range of its build side keys.
+select * from t1 as a, t1 as b where a.t1v1 = b.t1v1 order by a.t1v1;
+
+/*
+PhysicalSort
+├── exprs:SortOrder { order: Asc }
+│   └── #0
+└── PhysicalHashJoin { join_type: Inner, left_keys: [ #0 ], right_keys: [ #0 ], runtime_filter: 0 }
+    ├── PhysicalScan { table: t1 }
+    └── PhysicalRuntimeFilter { keys: [ #0 ], filter_id: 0(u64) }
+        └── PhysicalScan { table: t1 }
+0 0 0 0
+1 1 1 1
+2 2 2 2
+*/
+
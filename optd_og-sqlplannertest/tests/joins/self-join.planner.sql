@@ -37,3 +37,31 @@ PhysicalSort
 2 2 2 2
 */
 
+-- test self join with columns of the same name from both sides
+select a.t1v1, b.t1v1, a.t1v2 + b.t1v2 from t1 as a, t1 as b where a.t1v1 = b.t1v1 + 1 order by a.t1v1;
+
+/*
+1 0 1
+2 1 3
+*/
+
+-- test three-way self join
+select a.t1v1, b.t1v2, c.t1v1 from t1 as a, t1 as b, t1 as c where a.t1v1 = b.t1v1 and b.t1v2 = c.t1v2 and c.t1v1 > 0 order by a.t1v1;
+
+/*
+1 1 1
+2 2 2
+*/
+
+-- test self join rewritten by optd_og and planned by datafusion
+select a.t1v1, b.t1v1, a.t1v2 + b.t1v2 from t1 as a, t1 as b where a.t1v1 = b.t1v1 + 1 order by a.t1v1;
+select b.t1v2, count(*) from t1 as a, t1 as b where a.t1v1 = b.t1v1 group by b.t1v2 order by b.t1v2;
+
+/*
+1 0 1
+2 1 3
+0 1
+1 1
+2 1
+*/
+
@@ -0,0 +1,21 @@
+-- (no id or description)
+create table t1(t1v1 int, t1v2 int);
+create table t2(t2v1 int, t2v3 int);
+create table t3(t3v2 int, t3v4 int);
+insert into t1 values (1, 10), (2, 20), (3, 30);
+insert into t2 values (1, 100), (1, 50), (2, 5), (3, 200);
+insert into t3 values (1, 10), (1, 20), (3, 500);
+
+/*
+3
+4
+3
+*/
+
+-- Test whether the optimizer can unnest two levels of correlated subqueries, which fails to execute if a dependent join is left
+select t1v1 from t1 where (select sum(t2v3) from t2 where t2v1 = t1v1 and t2v3 > (select sum(t3v4) from t3 where t3v2 = t2v1)) > 100;
+
+/*
+1
+*/
+
@@ -113,28 +113,29 @@ PhysicalSort
             │           │   │   └── PhysicalScan { table: nation }
             │           │   └── PhysicalScan { table: supplier }
             │           └── PhysicalScan { table: partsupp }
-            └── PhysicalProjection
-                ├── exprs:Cast
-                │   ├── cast_to: Decimal128(38, 15)
-                │   ├── child:Mul
-                │   │   ├── Cast { cast_to: Float64, child: #0 }
-                │   │   └── 0.0001(float)
+            └── PhysicalSingleton
+                └── PhysicalProjection
+                    ├── exprs:Cast
+                    │   ├── cast_to: Decimal128(38, 15)
+                    │   ├── child:Mul
+                    │   │   ├── Cast { cast_to: Float64, child: #0 }
+                    │   │   └── 0.0001(float)
 
-                └── PhysicalAgg
-                    ├── aggrs:Agg(Sum)
-                    │   └── Mul
-                    │       ├── #3
-                    │       └── Cast { cast_to: Decimal128(10, 0), child: #2 }
-                    ├── groups: []
-                    └── PhysicalProjection { exprs: [ #11, #12, #13, #14, #15, #4, #5, #6, #7, #8, #9, #10, #0, #1, #2, #3 ] }
-                        └── PhysicalHashJoin { join_type: Inner, left_keys: [ #4 ], right_keys: [ #1 ] }
-                            ├── PhysicalHashJoin { join_type: Inner, left_keys: [ #0 ], right_keys: [ #3 ] }
-                            │   ├── PhysicalFilter
-                            │   │   ├── cond:Eq
-                            │   │   │   ├── #1
-                            │   │   │   └── "CHINA"
-                            │   │   └── PhysicalScan { table: nation }
-                            │   └── PhysicalScan { table: supplier }
-                            └── PhysicalScan { table: partsupp }
+                    └── PhysicalAgg
+                        ├── aggrs:Agg(Sum)
+                        │   └── Mul
+                        │       ├── #3
+                        │       └── Cast { cast_to: Decimal128(10, 0), child: #2 }
+                        ├── groups: []
+                        └── PhysicalProjection { exprs: [ #11, #12, #13, #14, #15, #4, #5, #6, #7, #8, #9, #10, #0, #1, #2, #3 ] }
+                            └── PhysicalHashJoin { join_type: Inner, left_keys: [ #4 ], right_keys: [ #1 ] }
+                                ├── PhysicalHashJoin { join_type: Inner, left_keys: [ #0 ], right_keys: [ #3 ] }
+                                │   ├── PhysicalFilter
+                                │   │   ├── cond:Eq
+                                │   │   │   ├── #1
+                                │   │   │   └── "CHINA"
+                                │   │   └── PhysicalScan { table: nation }
+                                │   └── PhysicalScan { table: supplier }
+                                └── PhysicalScan { table: partsupp }
 */
 
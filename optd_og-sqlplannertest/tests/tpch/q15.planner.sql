@@ -123,28 +123,29 @@ PhysicalSort
         │       │       │           └── INTERVAL_MONTH_DAY_NANO (3, 0, 0)
         │       │       └── PhysicalScan { table: lineitem }
         │       └── PhysicalScan { table: supplier }
-        └── PhysicalAgg
-            ├── aggrs:Agg(Max)
-            │   └── [ #1 ]
-            ├── groups: []
+        └── PhysicalSingleton
             └── PhysicalAgg
-                ├── aggrs:Agg(Sum)
-                │   └── Mul
-                │       ├── #5
-                │       └── Sub
-                │           ├── Cast { cast_to: Decimal128(20, 0), child: 1(i64) }
-                │           └── #6
-                ├── groups: [ #2 ]
-                └── PhysicalFilter
-                    ├── cond:And
-                    │   ├── Geq
-                    │   │   ├── #10
-                    │   │   └── Cast { cast_to: Date32, child: "1993-01-01" }
-                    │   └── Lt
-                    │       ├── #10
-                    │       └── Add
-                    │           ├── Cast { cast_to: Date32, child: "1993-01-01" }
-                    │           └── INTERVAL_MONTH_DAY_NANO (3, 0, 0)
-                    └── PhysicalScan { table: lineitem }
+                ├── aggrs:Agg(Max)
+                │   └── [ #1 ]
+                ├── groups: []
+                └── PhysicalAgg
+                    ├── aggrs:Agg(Sum)
+                    │   └── Mul
+                    │       ├── #5
+                    │       └── Sub
+                    │           ├── Cast { cast_to: Decimal128(20, 0), child: 1(i64) }
+                    │           └── #6
+                    ├── groups: [ #2 ]
+                    └── PhysicalFilter
+                        ├── cond:And
+                        │   ├── Geq
+                        │   │   ├── #10
+                        │   │   └── Cast { cast_to: Date32, child: "1993-01-01" }
+                        │   └── Lt
+                        │       ├── #10
+                        │       └── Add
+                        │           ├── Cast { cast_to: Date32, child: "1993-01-01" }
+                        │           └── INTERVAL_MONTH_DAY_NANO (3, 0, 0)
+                        └── PhysicalScan { table: lineitem }
 */
 
@@ -0,0 +1,69 @@
+-- (no id or description)
+create table t1(v1 int, v2 int);
+create table t2(v1 int, v2 int);
+insert into t1 values (0, 0), (1, 1), (2, 2);
+insert into t2 values (0, 200), (1, 201), (2, 202);
+
+/*
+3
+3
+*/
+
+-- Test all explain sections are shown by default
+select * from t1, t2 where t1.v1 = t2.v1;
+
+/*
+logical_plan after datafusion
+logical_plan after optd_og
+logical_plan after optd_og-heuristic
+physical_plan after optd_og
+physical_plan after optd_og-all-logical-join-orders
+physical_plan after optd_og-join-order
+physical_plan after optd_og-rules-fired
+physical_plan
+*/
+
+-- Test explain without the optd_og logical plans
+select * from t1, t2 where t1.v1 = t2.v1;
+
+/*
+logical_plan after datafusion
+physical_plan after optd_og
+physical_plan after optd_og-all-logical-join-orders
+physical_plan after optd_og-join-order
+physical_plan after optd_og-rules-fired
+physical_plan
+*/
+
+-- Test explain without the join orders
+select * from t1, t2 where t1.v1 = t2.v1;
+
+/*
+logical_plan after datafusion
+logical_plan after optd_og
+logical_plan after optd_og-heuristic
+physical_plan after optd_og
+physical_plan after optd_og-rules-fired
+physical_plan
+*/
+
+-- Test explain without the optd_og physical plan
+select * from t1, t2 where t1.v1 = t2.v1;
+
+/*
+logical_plan after datafusion
+logical_plan after optd_og
+logical_plan after optd_og-heuristic
+physical_plan after optd_og-all-logical-join-orders
+physical_plan after optd_og-join-order
+physical_plan
+*/
+
+-- Test explain without any optd_og section
+select * from t1, t2 where t1.v1 = t2.v1;
+
+/*
+logical_plan after datafusion
+physical_plan
+*/
+
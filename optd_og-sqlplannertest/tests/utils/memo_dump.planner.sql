@@ -29,7 +29,7 @@ group_id=!2 winner=23 weighted_cost=1000 cost={compute=0,io=1000} stat={row_cnt=
   step=1/7 decide_winner group_id=!2 proposed_winner_expr=23 children_winner_exprs=[] total_weighted_cost=1000
   step=2/1 decide_winner group_id=!2 proposed_winner_expr=23 children_winner_exprs=[] total_weighted_cost=1000
 group_id=!6 winner=21 weighted_cost=1003000 cost={compute=1001000,io=2000} stat={row_cnt=10000} | (PhysicalNestedLoopJoin(Inner) !2 !2 P4)
-  schema=[t1v1:Int32, t1v2:Int32, t1v1:Int32, t1v2:Int32]
+  schema=[t1v1#0:Int32, t1v2#1:Int32, t1v1#2:Int32, t1v2#3:Int32]
   column_ref=[t1.0, t1.1, t1.0, t1.1]
   expr_id=5 | (Join(Inner) !2 !2 P4)
   expr_id=21 | (PhysicalNestedLoopJoin(Inner) !2 !2 P4)
@@ -46,7 +46,7 @@ group_id=!6 winner=21 weighted_cost=1003000 cost={compute=1001000,io=2000} stat=
   step=2/11 apply_rule group_id=!6 applied_expr_id=49 produced_expr_id=42 rule_id=17
   step=2/12 apply_rule group_id=!6 applied_expr_id=49 produced_expr_id=49 rule_id=17
 group_id=!12 winner=17 weighted_cost=11908.75477931522 cost={compute=9908.75477931522,io=2000} stat={row_cnt=1000} | (PhysicalSort !31 P10)
-  schema=[t1v1:Int32, t1v2:Int32, t1v1:Int32, t1v2:Int32]
+  schema=[t1v1#0:Int32, t1v2#1:Int32, t1v1#2:Int32, t1v2#3:Int32]
   column_ref=[t1.0, t1.1, t1.0, t1.1]
   expr_id=11 | (Sort !31 P10)
   expr_id=17 | (PhysicalSort !31 P10)
@@ -55,7 +55,7 @@ group_id=!12 winner=17 weighted_cost=11908.75477931522 cost={compute=9908.754779
   step=1/13 decide_winner group_id=!12 proposed_winner_expr=17 children_winner_exprs=[28] total_weighted_cost=11908.75477931522
   step=2/28 decide_winner group_id=!12 proposed_winner_expr=17 children_winner_exprs=[28] total_weighted_cost=11908.75477931522
 group_id=!31 winner=28 weighted_cost=5000 cost={compute=3000,io=2000} stat={row_cnt=1000} | (PhysicalHashJoin(Inner) !2 !2 P26 P26)
-  schema=[t1v1:Int32, t1v2:Int32, t1v1:Int32, t1v2:Int32]
+  schema=[t1v1#0:Int32, t1v2#1:Int32, t1v1#2:Int32, t1v2#3:Int32]
   column_ref=[t1.0, t1.1, t1.0, t1.1]
   expr_id=8 | (Filter !6 P7)
   expr_id=15 | (Join(Inner) !2 !2 P7)
@@ -0,0 +1,117 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+
+use optd_og_datafusion_repr::plan_nodes::ConstantType;
+use optd_og_datafusion_repr::properties::schema::{
+    Catalog, Field, IndexDef, ScanCapabilities, Schema,
+};
+use optd_og_datafusion_repr::TableId;
+
+/// A table of the [`MockCatalog`] with everything the optimizer looks up for it.
+#[derive(Clone, Debug)]
+pub struct MockTable {
+    pub schema: Schema,
+    pub primary_key: Option<Vec<usize>>,
+    pub scan_capabilities: ScanCapabilities,
+    pub partition_columns: Vec<usize>,
+    pub indexes: Vec<IndexDef>,
+}
+
+impl MockTable {
+    pub fn new(schema: Schema) -> Self {
+        Self {
+            schema,
+            primary_key: None,
+            scan_capabilities: ScanCapabilities::default(),
+            partition_columns: vec![],
+            indexes: vec![],
+        }
+    }
+
+    /// A table of non-nullable `Int32` columns with the given names.
+    pub fn int32(columns: &[&str]) -> Self {
+        Self::new(Schema::new(
+            columns
+                .iter()
+                .map(|name| Field {
+                    name: name.to_string(),
+                    typ: ConstantType::Int32,
+                    nullable: false,
+                })
+                .collect(),
+        ))
+    }
+
+    pub fn with_primary_key(mut self, columns: Vec<usize>) -> Self {
+        self.primary_key = Some(columns);
+        self
+    }
+
+    pub fn with_scan_capabilities(mut self, scan_capabilities: ScanCapabilities) -> Self {
+        self.scan_capabilities = scan_capabilities;
+        self
+    }
+
+    pub fn with_partition_columns(mut self, columns: Vec<usize>) -> Self {
+        self.partition_columns = columns;
+        self
+    }
+
+    pub fn with_index(mut self, name: &str, columns: Vec<usize>) -> Self {
+        self.indexes.push(IndexDef {
+            name: name.to_string(),
+            columns,
+        });
+        self
+    }
+}
+
+/// A [`Catalog`] of the tables given to it. Looking up any other table panics, as it is a bug of
+/// the test.
+#[derive(Clone, Debug, Default)]
+pub struct MockCatalog {
+    tables: HashMap<TableId, MockTable>,
+}
+
+impl MockCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_table(mut self, name: &str, table: MockTable) -> Self {
+        self.tables.insert(TableId::new(name), table);
+        self
+    }
+
+    fn table(&self, name: &TableId) -> &MockTable {
+        self.tables
+            .get(name)
+            .unwrap_or_else(|| panic!("table {} is not in the mock catalog", name))
+    }
+}
+
+impl Catalog for MockCatalog {
+    fn get(&self, name: &TableId) -> Schema {
+        self.table(name).schema.clone()
+    }
+
+    fn primary_key(&self, name: &TableId) -> Option<Vec<usize>> {
+        self.table(name).primary_key.clone()
+    }
+
+    fn scan_capabilities(&self, name: &TableId) -> ScanCapabilities {
+        self.table(name).scan_capabilities
+    }
+
+    fn partition_columns(&self, name: &TableId) -> Vec<usize> {
+        self.table(name).partition_columns.clone()
+    }
+
+    fn indexes(&self, name: &TableId) -> Vec<IndexDef> {
+        self.table(name).indexes.clone()
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Utilities to unit test the rules and cost models written against `optd_og-datafusion-repr`
+//! without a datafusion session: a [`MockCatalog`] of the tables, [`MockStats`] with their row
+//! counts, and the [`plan`] builders to write the plans under test, e.g.,
+//! `filter(join(scan("t1"), scan("t2"), eq(col(0), col(2))), eq(col(1), int32(1)))`.
+
+mod catalog;
+pub mod plan;
+mod stats;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+pub use catalog::{MockCatalog, MockTable};
+use optd_og_core::cascades::NaiveMemo;
+use optd_og_core::cost::CostModel;
+use optd_og_core::heuristics::{ApplyOrder, HeuristicsOptimizer, HeuristicsOptimizerOptions};
+use optd_og_core::logical_property::LogicalPropertyBuilderAny;
+use optd_og_core::nodes::PlanAnnotations;
+use optd_og_core::rules::Rule;
+use optd_og_datafusion_repr::cost::{CardinalityHintStorage, RuntimeAdaptionStorage};
+use optd_og_datafusion_repr::plan_nodes::{ArcDfPlanNode, DfNodeType};
+use optd_og_datafusion_repr::properties::column_ref::ColumnRefPropertyBuilder;
+use optd_og_datafusion_repr::properties::schema::{Catalog, SchemaPropertyBuilder};
+use optd_og_datafusion_repr::properties::uniqueness::UniquenessPropertyBuilder;
+use optd_og_datafusion_repr::DatafusionOptimizer;
+pub use stats::MockStats;
+
+/// Create a heuristic optimizer applying `rules` top down, with the schema, column ref and
+/// uniqueness properties of the tables of `catalog`, to test the rewrites of the rules.
+pub fn new_test_optimizer(
+    catalog: Arc<dyn Catalog>,
+    rules: Vec<Arc<dyn Rule<DfNodeType, HeuristicsOptimizer<DfNodeType>>>>,
+) -> HeuristicsOptimizer<DfNodeType> {
+    let property_builders: Arc<[Box<dyn LogicalPropertyBuilderAny<DfNodeType>>]> = Arc::new([
+        Box::new(SchemaPropertyBuilder::new(catalog.clone())),
+        Box::new(ColumnRefPropertyBuilder::new(catalog.clone())),
+        Box::new(UniquenessPropertyBuilder::new(catalog)),
+    ]);
+    HeuristicsOptimizer::new_with_rules(
+        rules,
+        HeuristicsOptimizerOptions {
+            apply_order: ApplyOrder::TopDown,
+            enable_physical_prop_passthrough: true,
+        },
+        property_builders,
+        Arc::new([]),
+    )
+}
+
+/// Optimize a logical plan with the default cascades rules and `cost_model`, to test the plans a
+/// cost model picks. Returns the physical plan with the costs and row counts of its nodes.
+pub fn optimize_with_cost_model(
+    catalog: Arc<dyn Catalog>,
+    cost_model: impl CostModel<DfNodeType, NaiveMemo<DfNodeType>>,
+    plan: ArcDfPlanNode,
+) -> Result<(ArcDfPlanNode, PlanAnnotations)> {
+    let mut optimizer = DatafusionOptimizer::new_physical_with_cost_model(
+        catalog,
+        false,
+        cost_model,
+        RuntimeAdaptionStorage::default(),
+        CardinalityHintStorage::default(),
+    );
+    let (_, plan, meta) = optimizer.cascades_optimize(plan)?;
+    Ok((plan, meta))
+}
+
+#[cfg(test)]
+mod tests {
+    use optd_og_core::optimizer::Optimizer;
+    use optd_og_datafusion_repr::rules::EliminateFilterRule;
+
+    use super::plan::*;
+    use super::*;
+
+    fn catalog() -> Arc<MockCatalog> {
+        Arc::new(
+            MockCatalog::new()
+                .with_table("t1", MockTable::int32(&["a", "b"]))
+                .with_table("t2", MockTable::int32(&["c", "d"])),
+        )
+    }
+
+    #[test]
+    fn rewrite_with_rule() {
+        let mut optimizer =
+            new_test_optimizer(catalog(), vec![Arc::new(EliminateFilterRule::new())]);
+        let plan = optimizer
+            .optimize(filter(scan("t1"), boolean(true)))
+            .unwrap();
+        assert_eq!(plan.typ, DfNodeType::Scan);
+    }
+
+    #[test]
+    fn cost_with_mock_stats() {
+        let catalog = catalog();
+        let stats = MockStats::new()
+            .with_row_cnt("t1", 1000)
+            .with_row_cnt("t2", 10);
+        let plan = join(scan("t1"), scan("t2"), eq(col(0), col(2)));
+        let (plan, meta) =
+            optimize_with_cost_model(catalog.clone(), stats.cost_model(catalog), plan).unwrap();
+        let annotation = meta.get(&plan).unwrap();
+        assert!(annotation.row_cnt.is_some());
+        assert!(annotation.weighted_cost > 0.0);
+    }
+}
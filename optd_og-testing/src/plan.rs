@@ -0,0 +1,158 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Builders of the logical plans and predicates under test. The columns are referred to by their
+//! index in the output of the child, and the columns of a join are the columns of its left child
+//! followed by the ones of its right child.
+
+pub use optd_og_datafusion_repr::plan_nodes::JoinType;
+use optd_og_datafusion_repr::plan_nodes::{
+    ArcDfPlanNode, ArcDfPredNode, BinOpPred, BinOpType, ColumnRefPred, ConstantPred,
+    DfReprPlanNode, DfReprPredNode, FuncPred, FuncType, ListPred, LogOpPred, LogOpType, LogicalAgg,
+    LogicalFilter, LogicalJoin, LogicalLimit, LogicalProjection, LogicalScan, LogicalSort,
+    SortOrderPred, SortOrderType,
+};
+
+pub fn scan(table: &str) -> ArcDfPlanNode {
+    LogicalScan::new(table.to_string()).into_plan_node()
+}
+
+pub fn filter(child: ArcDfPlanNode, cond: ArcDfPredNode) -> ArcDfPlanNode {
+    LogicalFilter::new(child, cond).into_plan_node()
+}
+
+/// An inner join.
+pub fn join(left: ArcDfPlanNode, right: ArcDfPlanNode, cond: ArcDfPredNode) -> ArcDfPlanNode {
+    join_with_type(JoinType::Inner, left, right, cond)
+}
+
+pub fn join_with_type(
+    join_type: JoinType,
+    left: ArcDfPlanNode,
+    right: ArcDfPlanNode,
+    cond: ArcDfPredNode,
+) -> ArcDfPlanNode {
+    LogicalJoin::new(left, right, cond, join_type).into_plan_node()
+}
+
+pub fn project(child: ArcDfPlanNode, exprs: Vec<ArcDfPredNode>) -> ArcDfPlanNode {
+    LogicalProjection::new(child, ListPred::new(exprs)).into_plan_node()
+}
+
+/// An aggregation computing `exprs`, e.g., `agg_func("sum", col(1))`, grouped by `groups`.
+pub fn agg(
+    child: ArcDfPlanNode,
+    exprs: Vec<ArcDfPredNode>,
+    groups: Vec<ArcDfPredNode>,
+) -> ArcDfPlanNode {
+    LogicalAgg::new(child, ListPred::new(exprs), ListPred::new(groups)).into_plan_node()
+}
+
+/// A sort by `exprs`, each wrapped in [`asc`] or [`desc`].
+pub fn sort(child: ArcDfPlanNode, exprs: Vec<ArcDfPredNode>) -> ArcDfPlanNode {
+    LogicalSort::new(child, ListPred::new(exprs)).into_plan_node()
+}
+
+pub fn limit(child: ArcDfPlanNode, skip: u64, fetch: u64) -> ArcDfPlanNode {
+    LogicalLimit::new(
+        child,
+        ConstantPred::uint64(skip).into_pred_node(),
+        ConstantPred::uint64(fetch).into_pred_node(),
+    )
+    .into_plan_node()
+}
+
+pub fn col(idx: usize) -> ArcDfPredNode {
+    ColumnRefPred::new(idx).into_pred_node()
+}
+
+pub fn int32(value: i32) -> ArcDfPredNode {
+    ConstantPred::int32(value).into_pred_node()
+}
+
+pub fn int64(value: i64) -> ArcDfPredNode {
+    ConstantPred::int64(value).into_pred_node()
+}
+
+pub fn string(value: &str) -> ArcDfPredNode {
+    ConstantPred::string(value).into_pred_node()
+}
+
+pub fn boolean(value: bool) -> ArcDfPredNode {
+    ConstantPred::bool(value).into_pred_node()
+}
+
+pub fn bin_op(op: BinOpType, left: ArcDfPredNode, right: ArcDfPredNode) -> ArcDfPredNode {
+    BinOpPred::new(left, right, op).into_pred_node()
+}
+
+pub fn eq(left: ArcDfPredNode, right: ArcDfPredNode) -> ArcDfPredNode {
+    bin_op(BinOpType::Eq, left, right)
+}
+
+pub fn lt(left: ArcDfPredNode, right: ArcDfPredNode) -> ArcDfPredNode {
+    bin_op(BinOpType::Lt, left, right)
+}
+
+pub fn gt(left: ArcDfPredNode, right: ArcDfPredNode) -> ArcDfPredNode {
+    bin_op(BinOpType::Gt, left, right)
+}
+
+pub fn and(preds: Vec<ArcDfPredNode>) -> ArcDfPredNode {
+    LogOpPred::new(LogOpType::And, preds).into_pred_node()
+}
+
+pub fn or(preds: Vec<ArcDfPredNode>) -> ArcDfPredNode {
+    LogOpPred::new(LogOpType::Or, preds).into_pred_node()
+}
+
+/// An aggregate function like `sum` or `count` of `arg`.
+pub fn agg_func(name: &str, arg: ArcDfPredNode) -> ArcDfPredNode {
+    FuncPred::new(
+        FuncType::new_agg(name.to_string()),
+        ListPred::new(vec![arg]),
+    )
+    .into_pred_node()
+}
+
+pub fn asc(expr: ArcDfPredNode) -> ArcDfPredNode {
+    SortOrderPred::new(SortOrderType::Asc, expr).into_pred_node()
+}
+
+pub fn desc(expr: ArcDfPredNode) -> ArcDfPredNode {
+    SortOrderPred::new(SortOrderType::Desc, expr).into_pred_node()
+}
+
+#[cfg(test)]
+mod tests {
+    use optd_og_datafusion_repr::plan_nodes::DfNodeType;
+
+    use super::*;
+
+    #[test]
+    fn build_plan() {
+        let plan = limit(
+            sort(
+                filter(
+                    join(scan("t1"), scan("t2"), eq(col(0), col(2))),
+                    and(vec![gt(col(1), int32(1)), lt(col(3), int64(10))]),
+                ),
+                vec![desc(col(1))],
+            ),
+            0,
+            10,
+        );
+        assert_eq!(plan.typ, DfNodeType::Limit);
+        let join = plan.child_rel(0).child_rel(0).child_rel(0);
+        assert_eq!(join.typ, DfNodeType::Join(JoinType::Inner));
+        assert_eq!(
+            LogicalScan::from_plan_node(join.child_rel(1))
+                .unwrap()
+                .table()
+                .as_ref(),
+            "t2"
+        );
+    }
+}
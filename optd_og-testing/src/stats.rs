@@ -0,0 +1,36 @@
+// Copyright (c) 2023-2024 CMU Database Group
+//
+// Use of this source code is governed by an MIT-style license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use optd_og_datafusion_repr::cost::DfCostModel;
+use optd_og_datafusion_repr::properties::schema::Catalog;
+
+/// The row counts of the tables under test, which the base cost model estimates the rest from.
+#[derive(Clone, Debug, Default)]
+pub struct MockStats {
+    row_cnts: HashMap<String, usize>,
+}
+
+impl MockStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_row_cnt(mut self, table: &str, row_cnt: usize) -> Self {
+        self.row_cnts.insert(table.to_string(), row_cnt);
+        self
+    }
+
+    pub fn row_cnts(&self) -> &HashMap<String, usize> {
+        &self.row_cnts
+    }
+
+    /// The base cost model with these row counts and the scan capabilities of `catalog`.
+    pub fn cost_model(&self, catalog: Arc<dyn Catalog>) -> DfCostModel {
+        DfCostModel::new(self.row_cnts.clone()).with_catalog(catalog)
+    }
+}